@@ -10,15 +10,17 @@
 
 //! C bindings to Pathfinder.
 
-use font_kit::handle::Handle;
 use foreign_types::ForeignTypeRef;
 use gl;
-use pathfinder_canvas::{CanvasFontContext, CanvasRenderingContext2D, FillStyle, LineJoin, Path2D};
-use pathfinder_canvas::{TextAlign, TextMetrics};
+use pathfinder_canvas::{CanvasFontContext, CanvasRenderingContext2D, CompositeOperation};
+use pathfinder_canvas::{FillStyle, ImageSmoothingQuality, LineJoin, Path2D};
 use pathfinder_color::{ColorF, ColorU};
 use pathfinder_content::fill::FillRule;
+use pathfinder_content::gradient::{ColorStop, Gradient};
 use pathfinder_content::outline::ArcDirection;
+use pathfinder_content::pattern::{Image, Pattern, PatternFlags, PatternSource};
 use pathfinder_content::stroke::LineCap;
+use pathfinder_geometry::line_segment::LineSegment2F;
 use pathfinder_geometry::rect::{RectF, RectI};
 use pathfinder_geometry::transform2d::{Matrix2x2F, Transform2F};
 use pathfinder_geometry::transform3d::{Perspective, Transform4F};
@@ -36,7 +38,17 @@ use pathfinder_simd::default::F32x4;
 use std::ffi::CString;
 use std::os::raw::{c_char, c_void};
 use std::slice;
+
+#[cfg(feature = "pf-text")]
+use font_kit::handle::Handle;
+#[cfg(feature = "pf-text")]
+use pathfinder_canvas::{TextAlign, TextMetrics};
+#[cfg(feature = "pf-text")]
 use std::str;
+#[cfg(feature = "pf-export")]
+use pathfinder_export::{Export, FileFormat, RasterOptions};
+#[cfg(feature = "pf-export")]
+use std::ptr;
 
 #[cfg(all(target_os = "macos", not(feature = "pf-gl")))]
 use metal::{CAMetalLayer, CoreAnimationLayerRef};
@@ -55,15 +67,66 @@ pub const PF_LINE_JOIN_MITER:   u8 = 0;
 pub const PF_LINE_JOIN_BEVEL:   u8 = 1;
 pub const PF_LINE_JOIN_ROUND:   u8 = 2;
 
+#[cfg(feature = "pf-text")]
 pub const PF_TEXT_ALIGN_LEFT:   u8 = 0;
+#[cfg(feature = "pf-text")]
 pub const PF_TEXT_ALIGN_CENTER: u8 = 1;
+#[cfg(feature = "pf-text")]
 pub const PF_TEXT_ALIGN_RIGHT:  u8 = 2;
 
+pub const PF_COMPOSITE_OPERATION_SOURCE_OVER:      u8 = 0;
+pub const PF_COMPOSITE_OPERATION_SOURCE_IN:        u8 = 1;
+pub const PF_COMPOSITE_OPERATION_SOURCE_OUT:       u8 = 2;
+pub const PF_COMPOSITE_OPERATION_SOURCE_ATOP:      u8 = 3;
+pub const PF_COMPOSITE_OPERATION_DESTINATION_OVER: u8 = 4;
+pub const PF_COMPOSITE_OPERATION_DESTINATION_IN:   u8 = 5;
+pub const PF_COMPOSITE_OPERATION_DESTINATION_OUT:  u8 = 6;
+pub const PF_COMPOSITE_OPERATION_DESTINATION_ATOP: u8 = 7;
+pub const PF_COMPOSITE_OPERATION_LIGHTER:          u8 = 8;
+pub const PF_COMPOSITE_OPERATION_COPY:             u8 = 9;
+pub const PF_COMPOSITE_OPERATION_XOR:              u8 = 10;
+pub const PF_COMPOSITE_OPERATION_MULTIPLY:         u8 = 11;
+pub const PF_COMPOSITE_OPERATION_SCREEN:           u8 = 12;
+pub const PF_COMPOSITE_OPERATION_OVERLAY:          u8 = 13;
+pub const PF_COMPOSITE_OPERATION_DARKEN:           u8 = 14;
+pub const PF_COMPOSITE_OPERATION_LIGHTEN:          u8 = 15;
+pub const PF_COMPOSITE_OPERATION_COLOR_DODGE:      u8 = 16;
+pub const PF_COMPOSITE_OPERATION_COLOR_BURN:       u8 = 17;
+pub const PF_COMPOSITE_OPERATION_HARD_LIGHT:       u8 = 18;
+pub const PF_COMPOSITE_OPERATION_SOFT_LIGHT:       u8 = 19;
+pub const PF_COMPOSITE_OPERATION_DIFFERENCE:       u8 = 20;
+pub const PF_COMPOSITE_OPERATION_EXCLUSION:        u8 = 21;
+pub const PF_COMPOSITE_OPERATION_HUE:              u8 = 22;
+pub const PF_COMPOSITE_OPERATION_SATURATION:       u8 = 23;
+pub const PF_COMPOSITE_OPERATION_COLOR:            u8 = 24;
+pub const PF_COMPOSITE_OPERATION_LUMINOSITY:       u8 = 25;
+
+pub const PF_IMAGE_SMOOTHING_QUALITY_LOW:    u8 = 0;
+pub const PF_IMAGE_SMOOTHING_QUALITY_MEDIUM: u8 = 1;
+pub const PF_IMAGE_SMOOTHING_QUALITY_HIGH:   u8 = 2;
+
 // `content`
 
 pub const PF_ARC_DIRECTION_CW:  u8 = 0;
 pub const PF_ARC_DIRECTION_CCW: u8 = 1;
 
+pub const PF_FILL_RULE_WINDING:  u8 = 0;
+pub const PF_FILL_RULE_EVEN_ODD: u8 = 1;
+
+pub const PF_PATTERN_FLAGS_REPEAT_X:     u8 = 0x1;
+pub const PF_PATTERN_FLAGS_REPEAT_Y:     u8 = 0x2;
+pub const PF_PATTERN_FLAGS_NO_SMOOTHING: u8 = 0x4;
+pub const PF_PATTERN_FLAGS_ANISOTROPIC:  u8 = 0x8;
+
+// `export`
+
+#[cfg(feature = "pf-export")]
+pub const PF_FILE_FORMAT_SVG: u8 = 0;
+#[cfg(feature = "pf-export")]
+pub const PF_FILE_FORMAT_PDF: u8 = 1;
+#[cfg(feature = "pf-export")]
+pub const PF_FILE_FORMAT_PS:  u8 = 2;
+
 // `gl`
 
 pub const PF_GL_VERSION_GL3:    u8 = 0;
@@ -72,10 +135,12 @@ pub const PF_GL_VERSION_GLES3:  u8 = 1;
 // `renderer`
 
 pub const PF_RENDERER_OPTIONS_FLAGS_HAS_BACKGROUND_COLOR: u8 = 0x1;
+pub const PF_RENDERER_OPTIONS_FLAGS_SHOW_TILE_OVERLAY:    u8 = 0x2;
 
 // Types
 
 // External: `font-kit`
+#[cfg(feature = "pf-text")]
 pub type FKHandleRef = *mut Handle;
 
 // `canvas`
@@ -86,13 +151,22 @@ pub type PFFillStyleRef = *mut FillStyle;
 pub type PFLineCap = u8;
 pub type PFLineJoin = u8;
 pub type PFArcDirection = u8;
+#[cfg(feature = "pf-text")]
 pub type PFTextAlign = u8;
+pub type PFCompositeOperation = u8;
+pub type PFImageSmoothingQuality = u8;
+#[cfg(feature = "pf-text")]
 #[repr(C)]
 pub struct PFTextMetrics {
     pub width: f32,
 }
 
 // `content`
+pub type PFFillRule = u8;
+pub type PFGradientRef = *mut Gradient;
+pub type PFImageRef = *mut Image;
+pub type PFPatternRef = *mut Pattern;
+pub type PFPatternFlags = u8;
 #[repr(C)]
 pub struct PFColorF {
     pub r: f32,
@@ -120,6 +194,11 @@ pub struct PFVector2I {
     pub y: i32,
 }
 #[repr(C)]
+pub struct PFLineSegment2F {
+    pub from: PFVector2F,
+    pub to: PFVector2F,
+}
+#[repr(C)]
 pub struct PFRectF {
     pub origin: PFVector2F,
     pub lower_right: PFVector2F,
@@ -155,6 +234,13 @@ pub struct PFPerspective {
     pub window_size: PFVector2I,
 }
 
+// `export`
+#[cfg(feature = "pf-export")]
+pub type PFFileFormat = u8;
+/// A byte buffer returned by the export functions.
+#[cfg(feature = "pf-export")]
+pub type PFDataRef = *mut Vec<u8>;
+
 // `gl`
 pub type PFGLDeviceRef = *mut GLDevice;
 pub type PFGLVersion = u8;
@@ -210,6 +296,7 @@ pub unsafe extern "C" fn PFCanvasFontContextCreateWithSystemSource() -> PFCanvas
     Box::into_raw(Box::new(CanvasFontContext::from_system_source()))
 }
 
+#[cfg(feature = "pf-text")]
 #[no_mangle]
 pub unsafe extern "C" fn PFCanvasFontContextCreateWithFonts(fonts: *const FKHandleRef,
                                                             font_count: usize)
@@ -250,8 +337,14 @@ pub unsafe extern "C" fn PFCanvasStrokeRect(canvas: PFCanvasRef, rect: *const PF
     (*canvas).stroke_rect((*rect).to_rust())
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasClearRect(canvas: PFCanvasRef, rect: *const PFRectF) {
+    (*canvas).clear_rect((*rect).to_rust())
+}
+
 // Drawing text
 
+#[cfg(feature = "pf-text")]
 #[no_mangle]
 pub unsafe extern "C" fn PFCanvasFillText(canvas: PFCanvasRef,
                                           string: *const c_char,
@@ -260,6 +353,7 @@ pub unsafe extern "C" fn PFCanvasFillText(canvas: PFCanvasRef,
     (*canvas).fill_text(to_rust_string(&string, string_len), (*position).to_rust())
 }
 
+#[cfg(feature = "pf-text")]
 #[no_mangle]
 pub unsafe extern "C" fn PFCanvasStrokeText(canvas: PFCanvasRef,
                                             string: *const c_char,
@@ -268,6 +362,7 @@ pub unsafe extern "C" fn PFCanvasStrokeText(canvas: PFCanvasRef,
     (*canvas).stroke_text(to_rust_string(&string, string_len), (*position).to_rust())
 }
 
+#[cfg(feature = "pf-text")]
 #[no_mangle]
 pub unsafe extern "C" fn PFCanvasMeasureText(canvas: PFCanvasRef,
                                              string: *const c_char,
@@ -317,11 +412,38 @@ pub unsafe extern "C" fn PFCanvasSetCurrentTransform(canvas: PFCanvasRef, transf
     (*canvas).set_current_transform(&(*transform).to_rust());
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasGetCurrentTransform(canvas: PFCanvasRef,
+                                                     out_transform: *mut PFTransform2F) {
+    debug_assert!(!out_transform.is_null());
+    *out_transform = (*canvas).current_transform().to_c()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn PFCanvasResetTransform(canvas: PFCanvasRef) {
     (*canvas).reset_transform();
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasTransform(canvas: PFCanvasRef, transform: *const PFTransform2F) {
+    (*canvas).transform(&(*transform).to_rust());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasTranslate(canvas: PFCanvasRef, offset: *const PFVector2F) {
+    (*canvas).translate((*offset).to_rust());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasScale(canvas: PFCanvasRef, scale: *const PFVector2F) {
+    (*canvas).scale((*scale).to_rust());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasRotate(canvas: PFCanvasRef, angle: f32) {
+    (*canvas).rotate(angle);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn PFCanvasSave(canvas: PFCanvasRef) {
     (*canvas).save();
@@ -337,6 +459,7 @@ pub unsafe extern "C" fn PFCanvasSetLineDashOffset(canvas: PFCanvasRef, new_offs
     (*canvas).set_line_dash_offset(new_offset)
 }
 
+#[cfg(feature = "pf-text")]
 #[no_mangle]
 pub unsafe extern "C" fn PFCanvasSetFontByPostScriptName(canvas: PFCanvasRef,
                                                          postscript_name: *const c_char,
@@ -344,11 +467,13 @@ pub unsafe extern "C" fn PFCanvasSetFontByPostScriptName(canvas: PFCanvasRef,
     (*canvas).set_font_by_postscript_name(to_rust_string(&postscript_name, postscript_name_len))
 }
 
+#[cfg(feature = "pf-text")]
 #[no_mangle]
 pub unsafe extern "C" fn PFCanvasSetFontSize(canvas: PFCanvasRef, new_font_size: f32) {
     (*canvas).set_font_size(new_font_size)
 }
 
+#[cfg(feature = "pf-text")]
 #[no_mangle]
 pub unsafe extern "C" fn PFCanvasSetTextAlign(canvas: PFCanvasRef, new_text_align: PFTextAlign) {
     (*canvas).set_text_align(match new_text_align {
@@ -371,14 +496,76 @@ pub unsafe extern "C" fn PFCanvasSetStrokeStyle(canvas: PFCanvasRef,
     (*canvas).set_stroke_style((*stroke_style).clone())
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetGlobalAlpha(canvas: PFCanvasRef, new_global_alpha: f32) {
+    (*canvas).set_global_alpha(new_global_alpha)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetGlobalCompositeOperation(
+        canvas: PFCanvasRef,
+        new_composite_operation: PFCompositeOperation) {
+    (*canvas).set_global_composite_operation(to_rust_composite_operation(new_composite_operation))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetShadowBlur(canvas: PFCanvasRef, new_shadow_blur: f32) {
+    (*canvas).set_shadow_blur(new_shadow_blur)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetShadowColor(canvas: PFCanvasRef,
+                                                new_shadow_color: *const PFColorU) {
+    (*canvas).set_shadow_color((*new_shadow_color).to_rust())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetShadowOffset(canvas: PFCanvasRef,
+                                                 new_shadow_offset: *const PFVector2F) {
+    (*canvas).set_shadow_offset((*new_shadow_offset).to_rust())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetImageSmoothingEnabled(canvas: PFCanvasRef, enabled: bool) {
+    (*canvas).set_image_smoothing_enabled(enabled)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetImageSmoothingQuality(
+        canvas: PFCanvasRef,
+        new_quality: PFImageSmoothingQuality) {
+    (*canvas).set_image_smoothing_quality(match new_quality {
+        PF_IMAGE_SMOOTHING_QUALITY_MEDIUM => ImageSmoothingQuality::Medium,
+        PF_IMAGE_SMOOTHING_QUALITY_HIGH   => ImageSmoothingQuality::High,
+        _                                 => ImageSmoothingQuality::Low,
+    });
+}
+
+/// Records the strokes and text that paths come from, so that SVG export can write them as such.
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetRecordPathSources(canvas: PFCanvasRef,
+                                                      record_path_sources: bool) {
+    (*canvas).set_record_path_sources(record_path_sources)
+}
+
+/// Fills with the nonzero winding rule.
+///
 /// This function automatically destroys the path. If you wish to use the path again, clone it
 /// first.
 #[no_mangle]
 pub unsafe extern "C" fn PFCanvasFillPath(canvas: PFCanvasRef, path: PFPathRef) {
-    // TODO(pcwalton): Expose fill rules to the C API.
     (*canvas).fill_path(*Box::from_raw(path), FillRule::Winding)
 }
 
+/// This function automatically destroys the path. If you wish to use the path again, clone it
+/// first.
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasFillPathWithFillRule(canvas: PFCanvasRef,
+                                                      path: PFPathRef,
+                                                      fill_rule: PFFillRule) {
+    (*canvas).fill_path(*Box::from_raw(path), to_rust_fill_rule(fill_rule))
+}
+
 /// This function automatically destroys the path. If you wish to use the path again, clone it
 /// first.
 #[no_mangle]
@@ -386,6 +573,15 @@ pub unsafe extern "C" fn PFCanvasStrokePath(canvas: PFCanvasRef, path: PFPathRef
     (*canvas).stroke_path(*Box::from_raw(path))
 }
 
+/// This function automatically destroys the path. If you wish to use the path again, clone it
+/// first.
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasClipPath(canvas: PFCanvasRef,
+                                          path: PFPathRef,
+                                          fill_rule: PFFillRule) {
+    (*canvas).clip_path(*Box::from_raw(path), to_rust_fill_rule(fill_rule))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn PFPathCreate() -> PFPathRef {
     Box::into_raw(Box::new(Path2D::new()))
@@ -470,11 +666,138 @@ pub unsafe extern "C" fn PFFillStyleCreateColor(color: *const PFColorU) -> PFFil
     Box::into_raw(Box::new(FillStyle::Color((*color).to_rust())))
 }
 
+/// This function takes ownership of the gradient.
+#[no_mangle]
+pub unsafe extern "C" fn PFFillStyleCreateGradient(gradient: PFGradientRef) -> PFFillStyleRef {
+    Box::into_raw(Box::new(FillStyle::Gradient(*Box::from_raw(gradient))))
+}
+
+/// This function takes ownership of the pattern.
+#[no_mangle]
+pub unsafe extern "C" fn PFFillStyleCreatePattern(pattern: PFPatternRef) -> PFFillStyleRef {
+    Box::into_raw(Box::new(FillStyle::Pattern(*Box::from_raw(pattern))))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn PFFillStyleDestroy(fill_style: PFFillStyleRef) {
     drop(Box::from_raw(fill_style))
 }
 
+// `content`
+
+#[no_mangle]
+pub unsafe extern "C" fn PFGradientCreateLinear(line: *const PFLineSegment2F) -> PFGradientRef {
+    Box::into_raw(Box::new(Gradient::linear((*line).to_rust())))
+}
+
+/// Creates a gradient between the circle of radius `start_radius` centered at the start of `line`
+/// and the circle of radius `end_radius` centered at its end.
+#[no_mangle]
+pub unsafe extern "C" fn PFGradientCreateRadial(line: *const PFLineSegment2F,
+                                                start_radius: f32,
+                                                end_radius: f32)
+                                                -> PFGradientRef {
+    Box::into_raw(Box::new(Gradient::radial((*line).to_rust(), start_radius, end_radius)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFGradientAddColorStop(gradient: PFGradientRef,
+                                                color: *const PFColorU,
+                                                offset: f32) {
+    (*gradient).add_color_stop(ColorStop::new((*color).to_rust(), offset))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFGradientDestroy(gradient: PFGradientRef) {
+    drop(Box::from_raw(gradient))
+}
+
+/// Copies `size.x * size.y` non-premultiplied RGBA pixels, in rows from the top down.
+#[no_mangle]
+pub unsafe extern "C" fn PFImageCreateFromPixels(size: *const PFVector2I,
+                                                 pixels: *const PFColorU)
+                                                 -> PFImageRef {
+    let size = (*size).to_rust();
+    let pixels = slice::from_raw_parts(pixels, size.x() as usize * size.y() as usize);
+    let pixels = pixels.iter().map(|pixel| pixel.to_rust()).collect();
+    Box::into_raw(Box::new(Image::new(size, pixels)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFImageDestroy(image: PFImageRef) {
+    drop(Box::from_raw(image))
+}
+
+/// This function takes ownership of the image.
+#[no_mangle]
+pub unsafe extern "C" fn PFPatternCreateFromImage(image: PFImageRef,
+                                                  transform: *const PFTransform2F,
+                                                  flags: PFPatternFlags)
+                                                  -> PFPatternRef {
+    Box::into_raw(Box::new(Pattern::new(PatternSource::Image(*Box::from_raw(image)),
+                                        (*transform).to_rust(),
+                                        PatternFlags::from_bits_truncate(flags))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFPatternDestroy(pattern: PFPatternRef) {
+    drop(Box::from_raw(pattern))
+}
+
+// `export`
+
+/// Serializes the scene to SVG, PDF, or PostScript. Returns null on failure.
+#[cfg(feature = "pf-export")]
+#[no_mangle]
+pub unsafe extern "C" fn PFSceneExport(scene: PFSceneRef, format: PFFileFormat) -> PFDataRef {
+    let format = match format {
+        PF_FILE_FORMAT_PDF => FileFormat::PDF,
+        PF_FILE_FORMAT_PS  => FileFormat::PS,
+        _                  => FileFormat::SVG,
+    };
+    let mut data = vec![];
+    match (*scene).export(&mut data, format) {
+        Ok(()) => Box::into_raw(Box::new(data)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Renders the scene's view box, stretched to `size`, in software and encodes it as a PNG file.
+///
+/// If `background_color` is null, the background is transparent. Returns null if the scene
+/// can't be rasterized.
+#[cfg(feature = "pf-export")]
+#[no_mangle]
+pub unsafe extern "C" fn PFSceneRasterizeToPNG(scene: PFSceneRef,
+                                               size: *const PFVector2I,
+                                               background_color: *const PFColorF)
+                                               -> PFDataRef {
+    let options = RasterOptions {
+        background_color: background_color.as_ref().map(|color| color.to_rust()),
+        ..RasterOptions::default()
+    };
+    match pathfinder_export::rasterize_to_png(&*scene, (*size).to_rust(), &options) {
+        Ok(png) => Box::into_raw(Box::new(png)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Returns a pointer to the bytes, which remain valid until the data is destroyed, and writes
+/// their count to `out_len`.
+#[cfg(feature = "pf-export")]
+#[no_mangle]
+pub unsafe extern "C" fn PFDataGetBytes(data: PFDataRef, out_len: *mut usize) -> *const u8 {
+    debug_assert!(!out_len.is_null());
+    *out_len = (*data).len();
+    (*data).as_ptr()
+}
+
+#[cfg(feature = "pf-export")]
+#[no_mangle]
+pub unsafe extern "C" fn PFDataDestroy(data: PFDataRef) {
+    drop(Box::from_raw(data))
+}
+
 // `gl`
 
 #[no_mangle]
@@ -516,6 +839,16 @@ pub unsafe extern "C" fn PFGLDestFramebufferCreateFullWindow(window_size: *const
     Box::into_raw(Box::new(DestFramebuffer::full_window((*window_size).to_rust())))
 }
 
+/// Renders into the device's default framebuffer without clearing it, saving and restoring the
+/// GL state around each command so that the caller's own rendering is undisturbed.
+#[no_mangle]
+pub unsafe extern "C" fn PFGLDestFramebufferCreateForeignTarget(viewport: *const PFRectI,
+                                                                window_size: *const PFVector2I)
+                                                                -> PFGLDestFramebufferRef {
+    Box::into_raw(Box::new(DestFramebuffer::foreign_target((*viewport).to_rust(),
+                                                           (*window_size).to_rust())))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn PFGLDestFramebufferDestroy(dest_framebuffer: PFGLDestFramebufferRef) {
     drop(Box::from_raw(dest_framebuffer))
@@ -594,21 +927,27 @@ pub unsafe extern "C" fn PFMetalRendererGetDevice(renderer: PFMetalRendererRef)
 
 /// This function does not take ownership of `renderer` or `build_options`. Therefore, if you
 /// created the renderer and/or options, you must destroy them yourself to avoid a leak.
+///
+/// Returns false if the scene couldn't be built or some of its commands couldn't be rendered.
 #[no_mangle]
 pub unsafe extern "C" fn PFSceneProxyBuildAndRenderGL(scene_proxy: PFSceneProxyRef,
                                                       renderer: PFGLRendererRef,
-                                                      build_options: PFBuildOptionsRef) {
-    (*scene_proxy).build_and_render(&mut *renderer, (*build_options).clone())
+                                                      build_options: PFBuildOptionsRef)
+                                                      -> bool {
+    (*scene_proxy).build_and_render(&mut *renderer, (*build_options).clone()).is_ok()
 }
 
 /// This function does not take ownership of `renderer` or `build_options`. Therefore, if you
 /// created the renderer and/or options, you must destroy them yourself to avoid a leak.
+///
+/// Returns false if the scene couldn't be built or some of its commands couldn't be rendered.
 #[cfg(all(target_os = "macos", not(feature = "pf-gl")))]
 #[no_mangle]
 pub unsafe extern "C" fn PFSceneProxyBuildAndRenderMetal(scene_proxy: PFSceneProxyRef,
                                                          renderer: PFMetalRendererRef,
-                                                         build_options: PFBuildOptionsRef) {
-    (*scene_proxy).build_and_render(&mut *renderer, (*build_options).clone())
+                                                         build_options: PFBuildOptionsRef)
+                                                         -> bool {
+    (*scene_proxy).build_and_render(&mut *renderer, (*build_options).clone()).is_ok()
 }
 
 // `metal`
@@ -680,6 +1019,18 @@ pub unsafe extern "C" fn PFBuildOptionsSetSubpixelAAEnabled(options: PFBuildOpti
     (*options).subpixel_aa_enabled = subpixel_aa_enabled
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn PFBuildOptionsSetMaxFillsPerCommand(options: PFBuildOptionsRef,
+                                                             max_fills_per_command: usize) {
+    (*options).max_fills_per_command = max_fills_per_command
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFBuildOptionsSetMergeBatches(options: PFBuildOptionsRef,
+                                                       merge_batches: bool) {
+    (*options).merge_batches = merge_batches
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn PFSceneDestroy(scene: PFSceneRef) {
     drop(Box::from_raw(scene))
@@ -698,6 +1049,7 @@ pub unsafe extern "C" fn PFSceneProxyDestroy(scene_proxy: PFSceneProxyRef) {
 
 // Helpers for `canvas`
 
+#[cfg(feature = "pf-text")]
 unsafe fn to_rust_string(ptr: &*const c_char, mut len: usize) -> &str {
     if len == 0 {
         len = libc::strlen(*ptr);
@@ -705,16 +1057,49 @@ unsafe fn to_rust_string(ptr: &*const c_char, mut len: usize) -> &str {
     str::from_utf8(slice::from_raw_parts(*ptr as *const u8, len)).unwrap()
 }
 
+#[cfg(feature = "pf-text")]
 trait TextMetricsExt {
     fn to_c(&self) -> PFTextMetrics;
 }
 
+#[cfg(feature = "pf-text")]
 impl TextMetricsExt for TextMetrics {
     fn to_c(&self) -> PFTextMetrics {
         PFTextMetrics { width: self.width }
     }
 }
 
+fn to_rust_composite_operation(composite_operation: PFCompositeOperation) -> CompositeOperation {
+    match composite_operation {
+        PF_COMPOSITE_OPERATION_SOURCE_IN        => CompositeOperation::SourceIn,
+        PF_COMPOSITE_OPERATION_SOURCE_OUT       => CompositeOperation::SourceOut,
+        PF_COMPOSITE_OPERATION_SOURCE_ATOP      => CompositeOperation::SourceAtop,
+        PF_COMPOSITE_OPERATION_DESTINATION_OVER => CompositeOperation::DestinationOver,
+        PF_COMPOSITE_OPERATION_DESTINATION_IN   => CompositeOperation::DestinationIn,
+        PF_COMPOSITE_OPERATION_DESTINATION_OUT  => CompositeOperation::DestinationOut,
+        PF_COMPOSITE_OPERATION_DESTINATION_ATOP => CompositeOperation::DestinationAtop,
+        PF_COMPOSITE_OPERATION_LIGHTER          => CompositeOperation::Lighter,
+        PF_COMPOSITE_OPERATION_COPY             => CompositeOperation::Copy,
+        PF_COMPOSITE_OPERATION_XOR              => CompositeOperation::Xor,
+        PF_COMPOSITE_OPERATION_MULTIPLY         => CompositeOperation::Multiply,
+        PF_COMPOSITE_OPERATION_SCREEN           => CompositeOperation::Screen,
+        PF_COMPOSITE_OPERATION_OVERLAY          => CompositeOperation::Overlay,
+        PF_COMPOSITE_OPERATION_DARKEN           => CompositeOperation::Darken,
+        PF_COMPOSITE_OPERATION_LIGHTEN          => CompositeOperation::Lighten,
+        PF_COMPOSITE_OPERATION_COLOR_DODGE      => CompositeOperation::ColorDodge,
+        PF_COMPOSITE_OPERATION_COLOR_BURN       => CompositeOperation::ColorBurn,
+        PF_COMPOSITE_OPERATION_HARD_LIGHT       => CompositeOperation::HardLight,
+        PF_COMPOSITE_OPERATION_SOFT_LIGHT       => CompositeOperation::SoftLight,
+        PF_COMPOSITE_OPERATION_DIFFERENCE       => CompositeOperation::Difference,
+        PF_COMPOSITE_OPERATION_EXCLUSION        => CompositeOperation::Exclusion,
+        PF_COMPOSITE_OPERATION_HUE              => CompositeOperation::Hue,
+        PF_COMPOSITE_OPERATION_SATURATION       => CompositeOperation::Saturation,
+        PF_COMPOSITE_OPERATION_COLOR            => CompositeOperation::Color,
+        PF_COMPOSITE_OPERATION_LUMINOSITY       => CompositeOperation::Luminosity,
+        _                                       => CompositeOperation::SourceOver,
+    }
+}
+
 // Helpers for `content`
 
 impl PFColorF {
@@ -731,8 +1116,22 @@ impl PFColorU {
     }
 }
 
+fn to_rust_fill_rule(fill_rule: PFFillRule) -> FillRule {
+    match fill_rule {
+        PF_FILL_RULE_EVEN_ODD => FillRule::EvenOdd,
+        _                     => FillRule::Winding,
+    }
+}
+
 // Helpers for `geometry`
 
+impl PFLineSegment2F {
+    #[inline]
+    pub fn to_rust(&self) -> LineSegment2F {
+        LineSegment2F::new(self.from.to_rust(), self.to.to_rust())
+    }
+}
+
 impl PFRectF {
     #[inline]
     pub fn to_rust(&self) -> RectF {
@@ -775,6 +1174,22 @@ impl PFTransform2F {
     }
 }
 
+trait Transform2FExt {
+    fn to_c(&self) -> PFTransform2F;
+}
+
+impl Transform2FExt for Transform2F {
+    fn to_c(&self) -> PFTransform2F {
+        PFTransform2F {
+            matrix: PFMatrix2x2F {
+                m00: self.m11(), m01: self.m12(),
+                m10: self.m21(), m11: self.m22(),
+            },
+            vector: PFVector2F { x: self.vector.x(), y: self.vector.y() },
+        }
+    }
+}
+
 impl PFTransform4F {
     #[inline]
     pub fn to_rust(&self) -> Transform4F {
@@ -806,6 +1221,200 @@ impl PFRendererOptions {
             } else {
                 None
             },
+            show_tile_overlay: self.flags & PF_RENDERER_OPTIONS_FLAGS_SHOW_TILE_OVERLAY != 0,
+            ..RendererOptions::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pathfinder_content::gradient::GradientGeometry;
+
+    fn vector(x: f32, y: f32) -> PFVector2F {
+        PFVector2F { x, y }
+    }
+
+    unsafe fn create_canvas() -> PFCanvasRef {
+        let font_context = PFCanvasFontContextCreateWithSystemSource();
+        let canvas = PFCanvasCreate(font_context, &vector(100.0, 100.0));
+        PFCanvasFontContextRelease(font_context);
+        canvas
+    }
+
+    unsafe fn create_rect_path(origin: PFVector2F, lower_right: PFVector2F) -> PFPathRef {
+        let path = PFPathCreate();
+        PFPathRect(path, &PFRectF { origin, lower_right });
+        path
+    }
+
+    #[test]
+    fn test_current_transform_round_trips() {
+        unsafe {
+            let canvas = create_canvas();
+            let transform = PFTransform2F {
+                matrix: PFMatrix2x2F { m00: 2.0, m01: 0.5, m10: -1.0, m11: 3.0 },
+                vector: vector(4.0, 5.0),
+            };
+            PFCanvasSetCurrentTransform(canvas, &transform);
+
+            let mut out_transform = PFTransform2F {
+                matrix: PFMatrix2x2F { m00: 0.0, m01: 0.0, m10: 0.0, m11: 0.0 },
+                vector: vector(0.0, 0.0),
+            };
+            PFCanvasGetCurrentTransform(canvas, &mut out_transform);
+            assert_eq!(out_transform.to_rust(), transform.to_rust());
+
+            PFCanvasResetTransform(canvas);
+            PFCanvasGetCurrentTransform(canvas, &mut out_transform);
+            assert_eq!(out_transform.to_rust(), Transform2F::default());
+            PFCanvasDestroy(canvas);
+        }
+    }
+
+    #[test]
+    fn test_gradients_keep_their_geometry_and_stops() {
+        unsafe {
+            let line = PFLineSegment2F { from: vector(0.0, 0.0), to: vector(10.0, 0.0) };
+            let gradient = PFGradientCreateRadial(&line, 1.0, 5.0);
+            PFGradientAddColorStop(gradient, &PFColorU { r: 255, g: 0, b: 0, a: 255 }, 0.0);
+            PFGradientAddColorStop(gradient, &PFColorU { r: 0, g: 0, b: 255, a: 128 }, 1.0);
+
+            match *(*gradient).geometry() {
+                GradientGeometry::Radial { line: radial_line, start_radius, end_radius } => {
+                    assert_eq!(radial_line, line.to_rust());
+                    assert_eq!((start_radius, end_radius), (1.0, 5.0));
+                }
+                GradientGeometry::Linear(_) => panic!("expected a radial gradient"),
+            }
+            let stops: Vec<_> = (*gradient).stops().iter().map(|stop| {
+                (stop.color, stop.offset)
+            }).collect();
+            assert_eq!(stops, vec![(ColorU::new(255, 0, 0, 255), 0.0),
+                                   (ColorU::new(0, 0, 255, 128), 1.0)]);
+
+            let expected = (*gradient).clone();
+            let fill_style = PFFillStyleCreateGradient(gradient);
+            match *fill_style {
+                FillStyle::Gradient(ref gradient) => assert_eq!(*gradient, expected),
+                _ => panic!("expected a gradient fill style"),
+            }
+            PFFillStyleDestroy(fill_style);
+
+            let linear = PFGradientCreateLinear(&line);
+            assert_eq!(*(*linear).geometry(), GradientGeometry::Linear(line.to_rust()));
+            PFGradientDestroy(linear);
+        }
+    }
+
+    #[test]
+    fn test_patterns_keep_their_image_transform_and_flags() {
+        unsafe {
+            let pixels = [PFColorU { r: 1, g: 2, b: 3, a: 4 }, PFColorU { r: 5, g: 6, b: 7, a: 8 }];
+            let image = PFImageCreateFromPixels(&PFVector2I { x: 2, y: 1 }, pixels.as_ptr());
+            assert_eq!((*image).size(), Vector2I::new(2, 1));
+            assert_eq!((*image).pixels(), &[ColorU::new(1, 2, 3, 4), ColorU::new(5, 6, 7, 8)]);
+
+            let transform = PFTransform2F {
+                matrix: PFMatrix2x2F { m00: 2.0, m01: 0.0, m10: 0.0, m11: 2.0 },
+                vector: vector(1.0, 1.0),
+            };
+            let pattern = PFPatternCreateFromImage(image,
+                                                   &transform,
+                                                   PF_PATTERN_FLAGS_REPEAT_X |
+                                                   PF_PATTERN_FLAGS_NO_SMOOTHING);
+            assert_eq!((*pattern).transform, transform.to_rust());
+            assert_eq!((*pattern).flags, PatternFlags::REPEAT_X | PatternFlags::NO_SMOOTHING);
+            assert!(!(*pattern).smoothing_enabled());
+            match (*pattern).source {
+                PatternSource::Image(ref image) => assert_eq!(image.size(), Vector2I::new(2, 1)),
+                _ => panic!("expected an image pattern"),
+            }
+
+            let fill_style = PFFillStyleCreatePattern(pattern);
+            assert!(matches!(*fill_style, FillStyle::Pattern(_)));
+            PFFillStyleDestroy(fill_style);
+        }
+    }
+
+    #[test]
+    fn test_enum_constants_map_to_their_rust_values() {
+        assert_eq!(to_rust_fill_rule(PF_FILL_RULE_WINDING), FillRule::Winding);
+        assert_eq!(to_rust_fill_rule(PF_FILL_RULE_EVEN_ODD), FillRule::EvenOdd);
+        assert_eq!(to_rust_fill_rule(2), FillRule::Winding);
+
+        assert_eq!(to_rust_composite_operation(PF_COMPOSITE_OPERATION_SOURCE_OVER),
+                   CompositeOperation::SourceOver);
+        assert_eq!(to_rust_composite_operation(PF_COMPOSITE_OPERATION_DESTINATION_ATOP),
+                   CompositeOperation::DestinationAtop);
+        assert_eq!(to_rust_composite_operation(PF_COMPOSITE_OPERATION_XOR),
+                   CompositeOperation::Xor);
+        assert_eq!(to_rust_composite_operation(PF_COMPOSITE_OPERATION_LUMINOSITY),
+                   CompositeOperation::Luminosity);
+        assert_eq!(to_rust_composite_operation(PF_COMPOSITE_OPERATION_LUMINOSITY + 1),
+                   CompositeOperation::SourceOver);
+        // Every constant names a different operation.
+        let operations: Vec<_> = (PF_COMPOSITE_OPERATION_SOURCE_OVER..=
+                                  PF_COMPOSITE_OPERATION_LUMINOSITY).map(|operation| {
+            to_rust_composite_operation(operation)
+        }).collect();
+        for (index, operation) in operations.iter().enumerate() {
+            assert!(!operations[(index + 1)..].contains(operation), "{:?}", operation);
+        }
+    }
+
+    #[test]
+    fn test_fill_rules_reach_the_scene() {
+        unsafe {
+            let canvas = create_canvas();
+            PFCanvasClipPath(canvas,
+                             create_rect_path(vector(0.0, 0.0), vector(50.0, 50.0)),
+                             PF_FILL_RULE_EVEN_ODD);
+            PFCanvasFillPathWithFillRule(canvas,
+                                         create_rect_path(vector(10.0, 10.0), vector(90.0, 90.0)),
+                                         PF_FILL_RULE_EVEN_ODD);
+            PFCanvasFillPath(canvas, create_rect_path(vector(20.0, 20.0), vector(30.0, 30.0)));
+            let scene = PFCanvasCreateScene(canvas);
+
+            assert_eq!((*scene).path_count(), 2);
+            let even_odd_path = (*scene).path(0);
+            assert_eq!(even_odd_path.fill_rule(), FillRule::EvenOdd);
+            let clip_path = even_odd_path.clip_path().expect("the clip wasn't applied");
+            assert_eq!((*scene).clip_path(clip_path).fill_rule(), FillRule::EvenOdd);
+            assert_eq!((*scene).path(1).fill_rule(), FillRule::Winding);
+            PFSceneDestroy(scene);
+        }
+    }
+
+    #[cfg(feature = "pf-export")]
+    #[test]
+    fn test_scenes_export_to_bytes() {
+        unsafe fn bytes(data: PFDataRef) -> Vec<u8> {
+            assert!(!data.is_null());
+            let mut len = 0;
+            let bytes = slice::from_raw_parts(PFDataGetBytes(data, &mut len), len).to_vec();
+            PFDataDestroy(data);
+            bytes
+        }
+
+        unsafe {
+            let canvas = create_canvas();
+            PFCanvasFillRect(canvas, &PFRectF {
+                origin: vector(10.0, 10.0),
+                lower_right: vector(20.0, 20.0),
+            });
+            let scene = PFCanvasCreateScene(canvas);
+
+            assert!(bytes(PFSceneExport(scene, PF_FILE_FORMAT_SVG)).starts_with(b"<svg"));
+            assert!(bytes(PFSceneExport(scene, PF_FILE_FORMAT_PDF)).starts_with(b"%PDF"));
+            assert!(bytes(PFSceneExport(scene, PF_FILE_FORMAT_PS)).starts_with(b"%!PS"));
+            // Unknown formats fall back to SVG.
+            assert!(bytes(PFSceneExport(scene, 3)).starts_with(b"<svg"));
+
+            let png = bytes(PFSceneRasterizeToPNG(scene, &PFVector2I { x: 4, y: 4 }, ptr::null()));
+            assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+            PFSceneDestroy(scene);
         }
     }
 }
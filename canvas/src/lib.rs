@@ -16,7 +16,7 @@ use pathfinder_content::effects::{BlendMode, BlurDirection, CompositeOp, Effects
 use pathfinder_content::fill::FillRule;
 use pathfinder_content::gradient::Gradient;
 use pathfinder_content::outline::{ArcDirection, Contour, Outline};
-use pathfinder_content::pattern::{Pattern, PatternFlags};
+use pathfinder_content::pattern::Pattern;
 use pathfinder_content::render_target::RenderTargetId;
 use pathfinder_content::stroke::{LineCap, LineJoin as StrokeLineJoin};
 use pathfinder_content::stroke::{OutlineStrokeToFill, StrokeStyle};
@@ -25,7 +25,9 @@ use pathfinder_geometry::vector::Vector2F;
 use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_renderer::paint::{Paint, PaintId};
-use pathfinder_renderer::scene::{ClipPath, ClipPathId, DrawPath, RenderTarget, Scene};
+use pathfinder_renderer::picture::Picture;
+use pathfinder_renderer::scene::{ClipPath, ClipPathId, DrawPath, PathSource, RenderTarget};
+use pathfinder_renderer::scene::Scene;
 use std::borrow::Cow;
 use std::default::Default;
 use std::f32::consts::PI;
@@ -33,6 +35,9 @@ use std::mem;
 use std::sync::Arc;
 use text::FontCollection;
 
+#[cfg(feature = "pf-text")]
+use pathfinder_text::GlyphQuantization;
+
 #[cfg(feature = "pf-text")]
 pub use text::TextMetrics;
 pub use text::CanvasFontContext;
@@ -47,6 +52,7 @@ pub struct CanvasRenderingContext2D {
     scene: Scene,
     current_state: State,
     saved_states: Vec<State>,
+    record_path_sources: bool,
     #[allow(dead_code)]
     font_context: CanvasFontContext,
 }
@@ -68,6 +74,7 @@ impl CanvasRenderingContext2D {
             scene,
             current_state: State::default(default_font_collection),
             saved_states: vec![],
+            record_path_sources: false,
             font_context,
         }
     }
@@ -77,6 +84,22 @@ impl CanvasRenderingContext2D {
         self.scene
     }
 
+    /// Finishes recording, returning what was drawn as a picture that can be played into other
+    /// scenes and canvases again and again. See `Picture`.
+    #[inline]
+    pub fn into_picture(self) -> Picture {
+        Picture::new(self.scene)
+    }
+
+    /// Sets whether paths in the scene remember the strokes and text they were drawn from, so
+    /// that exporters can write them in editable form. See `PathSource`.
+    ///
+    /// This is off by default, since it keeps a copy of every stroked path.
+    #[inline]
+    pub fn set_record_path_sources(&mut self, record_path_sources: bool) {
+        self.record_path_sources = record_path_sources;
+    }
+
     // Drawing rectangles
 
     #[inline]
@@ -160,6 +183,26 @@ impl CanvasRenderingContext2D {
         self.current_state.stroke_paint = new_stroke_style.into_paint();
     }
 
+    /// Creates a gradient that interpolates between the circle of radius `from_radius` centered
+    /// at `from` and the circle of radius `to_radius` centered at `to`, like
+    /// `createRadialGradient()` in HTML canvas.
+    ///
+    /// The circles may have different centers, and the gradient extends past both of them. If
+    /// they're identical, the gradient paints nothing.
+    ///
+    /// Panics if either radius is negative.
+    #[inline]
+    pub fn create_radial_gradient(&self,
+                                  from: Vector2F,
+                                  from_radius: f32,
+                                  to: Vector2F,
+                                  to_radius: f32)
+                                  -> Gradient {
+        assert!(from_radius >= 0.0 && to_radius >= 0.0,
+                "Radial gradient radii must not be negative!");
+        Gradient::radial(LineSegment2F::new(from, to), from_radius, to_radius)
+    }
+
     // Shadows
 
     #[inline]
@@ -201,7 +244,7 @@ impl CanvasRenderingContext2D {
     pub fn fill_path(&mut self, path: Path2D, fill_rule: FillRule) {
         let paint = self.current_state.resolve_paint(&self.current_state.fill_paint);
         let paint_id = self.scene.push_paint(&paint);
-        self.push_path(path.into_outline(), paint_id, fill_rule);
+        self.push_path(path.into_outline(), paint_id, fill_rule, None);
     }
 
     #[inline]
@@ -221,6 +264,18 @@ impl CanvasRenderingContext2D {
         }
 
         let mut outline = path.into_outline();
+        let source = if self.record_path_sources {
+            Some(PathSource::Stroke {
+                outline: outline.clone(),
+                transform: self.current_state.transform,
+                style: stroke_style,
+                line_dash: self.current_state.line_dash.clone(),
+                line_dash_offset: self.current_state.line_dash_offset,
+            })
+        } else {
+            None
+        };
+
         if !self.current_state.line_dash.is_empty() {
             let mut dash = OutlineDash::new(&outline,
                                             &self.current_state.line_dash,
@@ -233,7 +288,7 @@ impl CanvasRenderingContext2D {
         stroke_to_fill.offset();
         outline = stroke_to_fill.into_outline();
 
-        self.push_path(outline, paint_id, FillRule::Winding);
+        self.push_path(outline, paint_id, FillRule::Winding, source);
     }
 
     pub fn clip_path(&mut self, path: Path2D, fill_rule: FillRule) {
@@ -247,7 +302,11 @@ impl CanvasRenderingContext2D {
         self.current_state.clip_path = Some(clip_path_id);
     }
 
-    fn push_path(&mut self, mut outline: Outline, paint_id: PaintId, fill_rule: FillRule) {
+    fn push_path(&mut self,
+                 mut outline: Outline,
+                 paint_id: PaintId,
+                 fill_rule: FillRule,
+                 source: Option<PathSource>) {
         let transform = self.current_state.transform;
         let clip_path = self.current_state.clip_path;
         let blend_mode = self.current_state.global_composite_operation.to_blend_mode();
@@ -284,6 +343,7 @@ impl CanvasRenderingContext2D {
         path.set_fill_rule(fill_rule);
         path.set_blend_mode(blend_mode);
         path.set_opacity(opacity);
+        path.set_source(source);
         self.scene.push_path(path);
 
         self.composite_render_target_if_needed(composite_op, render_target_id);
@@ -346,6 +406,17 @@ impl CanvasRenderingContext2D {
         }));
     }
 
+    // Pictures
+
+    /// Plays a picture into the canvas with the current transform.
+    ///
+    /// The picture is drawn as it was recorded: the current clip, global alpha, compositing
+    /// operation, and shadow don't apply to it.
+    #[inline]
+    pub fn draw_picture(&mut self, picture: &Picture) {
+        self.scene.draw_picture(picture, &self.current_state.transform);
+    }
+
     // Transformations
 
     #[inline]
@@ -353,9 +424,14 @@ impl CanvasRenderingContext2D {
         self.current_state.transform
     }
 
+    /// Replaces the current transform, like `setTransform()` in HTML canvas.
+    ///
+    /// As in HTML canvas, transforms with infinite or NaN components are ignored.
     #[inline]
     pub fn set_current_transform(&mut self, new_transform: &Transform2F) {
-        self.current_state.transform = *new_transform;
+        if is_finite(new_transform) {
+            self.current_state.transform = *new_transform;
+        }
     }
 
     #[inline]
@@ -363,6 +439,30 @@ impl CanvasRenderingContext2D {
         self.current_state.transform = Transform2F::default();
     }
 
+    /// Applies `transform` to coordinates before the current transform, like `transform()` in
+    /// HTML canvas.
+    #[inline]
+    pub fn transform(&mut self, transform: &Transform2F) {
+        let new_transform = self.current_state.transform * *transform;
+        self.set_current_transform(&new_transform);
+    }
+
+    #[inline]
+    pub fn translate(&mut self, offset: Vector2F) {
+        self.transform(&Transform2F::from_translation(offset));
+    }
+
+    #[inline]
+    pub fn scale(&mut self, scale: Vector2F) {
+        self.transform(&Transform2F::from_scale(scale));
+    }
+
+    /// Rotates clockwise by `angle` radians, like `rotate()` in HTML canvas.
+    #[inline]
+    pub fn rotate(&mut self, angle: f32) {
+        self.transform(&Transform2F::from_rotation(angle));
+    }
+
     // Compositing
 
     #[inline]
@@ -439,6 +539,8 @@ struct State {
     shadow_blur: f32,
     shadow_offset: Vector2F,
     text_align: TextAlign,
+    #[cfg(feature = "pf-text")]
+    glyph_quantization: GlyphQuantization,
     image_smoothing_enabled: bool,
     image_smoothing_quality: ImageSmoothingQuality,
     global_alpha: f32,
@@ -464,6 +566,8 @@ impl State {
             shadow_blur: 0.0,
             shadow_offset: Vector2F::default(),
             text_align: TextAlign::Left,
+            #[cfg(feature = "pf-text")]
+            glyph_quantization: GlyphQuantization::None,
             image_smoothing_enabled: true,
             image_smoothing_quality: ImageSmoothingQuality::Low,
             global_alpha: 1.0,
@@ -473,20 +577,18 @@ impl State {
     }
 
     fn resolve_paint<'a>(&self, paint: &'a Paint) -> Cow<'a, Paint> {
-        if self.transform.is_identity() {
-            return Cow::Borrowed(paint);
-        }
+        let mut must_copy = !self.transform.is_identity();
         if let Paint::Pattern(ref pattern) = *paint {
-            if !self.image_smoothing_enabled ==
-                    pattern.flags.contains(PatternFlags::NO_SMOOTHING) {
-                return Cow::Borrowed(paint)
-            }
+            must_copy |= pattern.smoothing_enabled() != self.image_smoothing_enabled;
+        }
+        if !must_copy {
+            return Cow::Borrowed(paint);
         }
 
         let mut paint = (*paint).clone();
         paint.apply_transform(&self.transform);
         if let Paint::Pattern(ref mut pattern) = paint {
-            pattern.flags.set(PatternFlags::NO_SMOOTHING, !self.image_smoothing_enabled);
+            pattern.set_smoothing_enabled(self.image_smoothing_enabled);
         }
         Cow::Owned(paint)
     }
@@ -610,6 +712,17 @@ impl Path2D {
     }
 }
 
+fn is_finite(transform: &Transform2F) -> bool {
+    [
+        transform.m11(),
+        transform.m12(),
+        transform.m21(),
+        transform.m22(),
+        transform.vector.x(),
+        transform.vector.y(),
+    ].iter().all(|component| component.is_finite())
+}
+
 #[derive(Clone)]
 pub enum FillStyle {
     Color(ColorU),
@@ -746,3 +859,93 @@ pub enum ImageSmoothingQuality {
     Medium,
     High,
 }
+
+#[cfg(test)]
+mod test {
+    use super::{CanvasFontContext, CanvasRenderingContext2D, FillStyle};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::pattern::{Image, Pattern, PatternFlags, PatternSource};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+    use pathfinder_renderer::paint::Paint;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn new_canvas() -> CanvasRenderingContext2D {
+        CanvasRenderingContext2D::new(CanvasFontContext::from_system_source(),
+                                      Vector2F::splat(64.0))
+    }
+
+    // Fills a rect with a pixel art pattern and returns the pattern that the scene paints it with.
+    fn fill_with_pattern(mut canvas: CanvasRenderingContext2D, flags: PatternFlags) -> Pattern {
+        let pixels = vec![ColorU::black(), ColorU::white(), ColorU::white(), ColorU::black()];
+        let source = PatternSource::Image(Image::new(Vector2I::splat(2), pixels));
+        canvas.set_fill_style(FillStyle::Pattern(Pattern::new(source,
+                                                              Transform2F::default(),
+                                                              flags)));
+        canvas.fill_rect(RectF::new(Vector2F::default(), Vector2F::splat(32.0)));
+
+        let scene = canvas.into_scene();
+        match *scene.paint(scene.path(0).paint()) {
+            Paint::Pattern(ref pattern) => pattern.clone(),
+            _ => panic!("The rect wasn't filled with a pattern!"),
+        }
+    }
+
+    #[test]
+    fn test_image_smoothing_applies_without_a_transform() {
+        assert!(fill_with_pattern(new_canvas(), PatternFlags::empty()).smoothing_enabled());
+        assert!(fill_with_pattern(new_canvas(), PatternFlags::NO_SMOOTHING).smoothing_enabled());
+
+        let mut canvas = new_canvas();
+        canvas.set_image_smoothing_enabled(false);
+        assert!(!fill_with_pattern(canvas, PatternFlags::empty()).smoothing_enabled());
+    }
+
+    #[test]
+    fn test_transformed_patterns_keep_matching_smoothing() {
+        // The pattern already has smoothing off, so only the transform needs applying.
+        let transform = Transform2F::from_scale(Vector2F::splat(4.0));
+        let mut canvas = new_canvas();
+        canvas.set_current_transform(&transform);
+        canvas.set_image_smoothing_enabled(false);
+        let pattern = fill_with_pattern(canvas, PatternFlags::NO_SMOOTHING);
+        assert!(!pattern.smoothing_enabled());
+        assert_eq!(pattern.transform, transform);
+    }
+
+    #[test]
+    fn test_transforms_compose_like_html_canvas() {
+        let mut canvas = new_canvas();
+        canvas.translate(Vector2F::new(10.0, 20.0));
+        canvas.scale(Vector2F::new(2.0, 3.0));
+        let transform = canvas.current_transform();
+        assert_eq!(transform * Vector2F::new(1.0, 1.0), Vector2F::new(12.0, 23.0));
+
+        // Rotations are clockwise, since the Y axis points down.
+        canvas.reset_transform();
+        canvas.rotate(FRAC_PI_2);
+        let point = canvas.current_transform() * Vector2F::new(1.0, 0.0);
+        assert!((point - Vector2F::new(0.0, 1.0)).length() < 0.0001);
+
+        canvas.reset_transform();
+        assert_eq!(canvas.current_transform(), Transform2F::default());
+    }
+
+    #[test]
+    fn test_non_finite_transforms_are_ignored() {
+        let transform = Transform2F::from_translation(Vector2F::new(5.0, 6.0));
+        let mut canvas = new_canvas();
+        canvas.set_current_transform(&transform);
+
+        canvas.set_current_transform(&Transform2F::from_scale(Vector2F::splat(f32::NAN)));
+        canvas.scale(Vector2F::new(f32::INFINITY, 1.0));
+        canvas.translate(Vector2F::new(0.0, f32::NEG_INFINITY));
+        assert_eq!(canvas.current_transform(), transform);
+
+        canvas.save();
+        canvas.scale(Vector2F::splat(2.0));
+        canvas.restore();
+        assert_eq!(canvas.current_transform(), transform);
+    }
+}
@@ -11,6 +11,7 @@
 use pathfinder_geometry::vector::Vector2F;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_renderer::paint::PaintId;
+use pathfinder_renderer::scene::{PathSource, TextRun};
 use std::sync::Arc;
 use std::iter;
 use font_kit::family_name::FamilyName;
@@ -21,6 +22,9 @@ use font_kit::source::{Source, SystemSource};
 use font_kit::sources::mem::MemSource;
 pub use skribo::{FontCollection, FontFamily, Layout, TextStyle};
 use pathfinder_text::{SceneExt, TextRenderMode};
+pub use pathfinder_text::GlyphQuantization;
+use pathfinder_text::woff;
+pub use pathfinder_text::woff::WoffError;
 pub use font_kit::loaders::default::Font;
 pub use crate::{CanvasRenderingContext2D, TextAlign};
 
@@ -47,6 +51,7 @@ impl CanvasRenderingContext2D {
                                     &(transform * self.current_state.transform),
                                     TextRenderMode::Fill,
                                     HintingOptions::None,
+                                    self.current_state.glyph_quantization,
                                     paint_id));
     }
 
@@ -65,13 +70,35 @@ impl CanvasRenderingContext2D {
 
         let transform = self.current_state.transform * Transform2F::from_translation(position);
 
+        let first_path_index = self.scene.path_count();
         // TODO(pcwalton): Report errors.
         drop(self.scene.push_layout(&layout,
                                     &TextStyle { size: self.current_state.font_size },
                                     &transform,
                                     render_mode,
                                     HintingOptions::None,
+                                    self.current_state.glyph_quantization,
                                     paint_id));
+
+        if self.record_path_sources {
+            let font_family = match layout.glyphs.first() {
+                Some(glyph) => glyph.font.font.family_name(),
+                None => return,
+            };
+            let text_run = Arc::new(TextRun {
+                text: string.to_owned(),
+                font_family,
+                font_size: self.current_state.font_size,
+                transform,
+                stroke: match render_mode {
+                    TextRenderMode::Fill => None,
+                    TextRenderMode::Stroke(stroke_style) => Some(stroke_style),
+                },
+            });
+            for path_index in first_path_index..self.scene.path_count() {
+                self.scene.set_path_source(path_index, Some(PathSource::Text(text_run.clone())));
+            }
+        }
     }
 
     fn layout_text(&self, string: &str) -> Layout {
@@ -125,6 +152,18 @@ impl CanvasRenderingContext2D {
     pub fn set_text_align(&mut self, new_text_align: TextAlign) {
         self.current_state.text_align = new_text_align;
     }
+
+    #[inline]
+    pub fn glyph_quantization(&self) -> GlyphQuantization {
+        self.current_state.glyph_quantization
+    }
+
+    /// Sets how glyph origins are rounded, in canvas coordinates after the current transform. The
+    /// default is `GlyphQuantization::None`.
+    #[inline]
+    pub fn set_glyph_quantization(&mut self, new_glyph_quantization: GlyphQuantization) {
+        self.current_state.glyph_quantization = new_glyph_quantization;
+    }
 }
 
 // TODO(pcwalton): Support other fields.
@@ -167,6 +206,17 @@ impl CanvasFontContext {
     pub fn from_fonts<I>(fonts: I) -> CanvasFontContext where I: Iterator<Item = Handle> {
         CanvasFontContext::new(Arc::new(MemSource::from_fonts(fonts).unwrap()))
     }
+
+    /// A convenience method to create a font context with a set of in-memory fonts, each given as
+    /// TrueType, OpenType, WOFF, or WOFF2 data.
+    pub fn from_font_data<I>(fonts: I) -> Result<CanvasFontContext, WoffError>
+                             where I: Iterator<Item = Vec<u8>> {
+        let mut handles = vec![];
+        for font_data in fonts {
+            handles.push(Handle::from_memory(Arc::new(woff::decode_font_data(font_data)?), 0));
+        }
+        Ok(CanvasFontContext::from_fonts(handles.into_iter()))
+    }
 }
 
 // Text layout utilities
@@ -8,13 +8,31 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+//! Colors and color spaces.
+//!
+//! Without the default `pf-std` feature, this crate is `no_std` and only needs `alloc`.
+
+#![cfg_attr(not(feature = "pf-std"), no_std)]
+
+extern crate alloc;
+
 use pathfinder_simd::default::F32x4;
-use std::f32::consts::PI;
-use std::fmt::{self, Debug, Formatter};
-use std::slice;
+use pathfinder_simd::math;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use core::fmt::{self, Debug, Formatter};
+use core::slice;
+
+#[cfg(feature = "pf-serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub use crate::space::ColorSpace;
+
+mod space;
 
 // TODO(pcwalton): Maybe this should be a u32? Need to be aware of endianness issues if we do that.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "pf-serde", derive(Serialize, Deserialize))]
 #[repr(C)]
 pub struct ColorU {
     pub r: u8,
@@ -119,7 +137,7 @@ impl ColorF {
         // Calculate chroma.
         let c = (1.0 - f32::abs(2.0 * l - 1.0)) * s;
         let xc = F32x4::new(c * (1.0 - f32::abs(h % 2.0 - 1.0)), c, 0.0, a);
-        let rgba = match f32::ceil(h) as i32 {
+        let rgba = match math::ceil(h) as i32 {
             1     => xc.yxzw(),
             2     => xc.xyzw(),
             3     => xc.zyxw(),
@@ -197,6 +215,32 @@ impl Debug for ColorF {
     }
 }
 
+// `ColorF` is a SIMD vector underneath, so it goes through a struct shaped like `ColorU`.
+#[cfg(feature = "pf-serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "ColorF")]
+struct ColorFRepr {
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+}
+
+#[cfg(feature = "pf-serde")]
+impl Serialize for ColorF {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        ColorFRepr { r: self.r(), g: self.g(), b: self.b(), a: self.a() }.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "pf-serde")]
+impl<'de> Deserialize<'de> for ColorF {
+    fn deserialize<D>(deserializer: D) -> Result<ColorF, D::Error> where D: Deserializer<'de> {
+        let repr = ColorFRepr::deserialize(deserializer)?;
+        Ok(ColorF::new(repr.r, repr.g, repr.b, repr.a))
+    }
+}
+
 #[inline]
 pub fn color_slice_to_u8_slice(slice: &[ColorU]) -> &[u8] {
     unsafe {
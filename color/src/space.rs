@@ -0,0 +1,310 @@
+// pathfinder/color/src/space.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversions between sRGB and other color spaces.
+
+use crate::ColorF;
+use pathfinder_simd::default::F32x4;
+use pathfinder_simd::math;
+use core::f32::consts::PI;
+
+#[cfg(feature = "pf-serde")]
+use serde::{Deserialize, Serialize};
+
+// Below this saturation or chroma, a color is treated as having no hue when interpolating.
+const ACHROMATIC_EPSILON: f32 = 1.0e-5;
+
+/// A color space that colors can be converted to and interpolated in.
+///
+/// Colors are converted to four components, with alpha last and left alone. Hues are in radians,
+/// from 0 to 2π, as in `ColorF::from_hsla()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "pf-serde", derive(Serialize, Deserialize))]
+pub enum ColorSpace {
+    /// The gamma-encoded sRGB that `ColorF` stores: red, green, and blue.
+    Srgb,
+    /// sRGB without gamma encoding, in which light adds up physically: red, green, and blue.
+    LinearSrgb,
+    /// Hue, saturation, and lightness.
+    Hsl,
+    /// Hue, saturation, and value.
+    Hsv,
+    /// Björn Ottosson's perceptual color space: lightness, green-red, and blue-yellow.
+    Oklab,
+    /// OKLab in polar form: lightness, chroma, and hue.
+    Oklch,
+}
+
+impl ColorSpace {
+    // The index of the hue component, for spaces in polar form.
+    fn hue_index(self) -> Option<usize> {
+        match self {
+            ColorSpace::Hsl | ColorSpace::Hsv => Some(0),
+            ColorSpace::Oklch => Some(2),
+            ColorSpace::Srgb | ColorSpace::LinearSrgb | ColorSpace::Oklab => None,
+        }
+    }
+
+    // The index of the component that's zero when a color has no hue.
+    fn chroma_index(self) -> usize {
+        match self {
+            ColorSpace::Hsl | ColorSpace::Hsv | ColorSpace::Oklch => 1,
+            ColorSpace::Srgb | ColorSpace::LinearSrgb | ColorSpace::Oklab => unreachable!(),
+        }
+    }
+}
+
+impl ColorF {
+    /// Returns the color's components in `space`.
+    #[allow(clippy::excessive_precision)]
+    pub fn to_space(&self, space: ColorSpace) -> F32x4 {
+        let (r, g, b, a) = (self.r(), self.g(), self.b(), self.a());
+        match space {
+            ColorSpace::Srgb => self.0,
+            ColorSpace::LinearSrgb => {
+                F32x4::new(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+            }
+            ColorSpace::Hsl => {
+                let (hue, max, min) = hue_max_min(r, g, b);
+                let lightness = 0.5 * (max + min);
+                let saturation = if max == min {
+                    0.0
+                } else {
+                    (max - min) / (1.0 - f32::abs(2.0 * lightness - 1.0))
+                };
+                F32x4::new(hue, saturation, lightness, a)
+            }
+            ColorSpace::Hsv => {
+                let (hue, max, min) = hue_max_min(r, g, b);
+                let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+                F32x4::new(hue, saturation, max, a)
+            }
+            ColorSpace::Oklab => {
+                let linear = self.to_space(ColorSpace::LinearSrgb);
+                let (r, g, b) = (linear[0], linear[1], linear[2]);
+                let l = math::cbrt(0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b);
+                let m = math::cbrt(0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b);
+                let s = math::cbrt(0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b);
+                F32x4::new(0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+                           1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+                           0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+                           a)
+            }
+            ColorSpace::Oklch => {
+                let lab = self.to_space(ColorSpace::Oklab);
+                let chroma = math::hypot(lab[1], lab[2]);
+                let hue = if chroma < ACHROMATIC_EPSILON {
+                    0.0
+                } else {
+                    normalize_hue(math::atan2(lab[2], lab[1]))
+                };
+                F32x4::new(lab[0], chroma, hue, a)
+            }
+        }
+    }
+
+    /// Makes a color from its components in `space`.
+    ///
+    /// Colors outside the sRGB gamut come back with components outside the range 0 to 1.
+    #[allow(clippy::excessive_precision)]
+    pub fn from_space(components: F32x4, space: ColorSpace) -> ColorF {
+        let a = components[3];
+        match space {
+            ColorSpace::Srgb => ColorF(components),
+            ColorSpace::LinearSrgb => {
+                ColorF::new(linear_to_srgb(components[0]),
+                            linear_to_srgb(components[1]),
+                            linear_to_srgb(components[2]),
+                            a)
+            }
+            ColorSpace::Hsl => {
+                let hue = normalize_hue(components[0]);
+                ColorF::from_hsla(hue, components[1], components[2], a)
+            }
+            ColorSpace::Hsv => {
+                // https://en.wikipedia.org/wiki/HSL_and_HSV#HSV_to_HSL
+                let (hue, saturation, value) = (components[0], components[1], components[2]);
+                let lightness = value * (1.0 - 0.5 * saturation);
+                let saturation = if lightness == 0.0 || lightness == 1.0 {
+                    0.0
+                } else {
+                    (value - lightness) / f32::min(lightness, 1.0 - lightness)
+                };
+                ColorF::from_hsla(normalize_hue(hue), saturation, lightness, a)
+            }
+            ColorSpace::Oklab => {
+                let (lightness, green_red, blue_yellow) =
+                    (components[0], components[1], components[2]);
+                let l = lightness + 0.3963377774 * green_red + 0.2158037573 * blue_yellow;
+                let m = lightness - 0.1055613458 * green_red - 0.0638541728 * blue_yellow;
+                let s = lightness - 0.0894841775 * green_red - 1.2914855480 * blue_yellow;
+                let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+                let linear = F32x4::new(4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+                                        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+                                        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+                                        a);
+                ColorF::from_space(linear, ColorSpace::LinearSrgb)
+            }
+            ColorSpace::Oklch => {
+                let (lightness, chroma, hue) = (components[0], components[1], components[2]);
+                let lab = F32x4::new(lightness, chroma * math::cos(hue), chroma * math::sin(hue), a);
+                ColorF::from_space(lab, ColorSpace::Oklab)
+            }
+        }
+    }
+
+    /// Interpolates between two colors in `space`, like `lerp()`, which interpolates in sRGB.
+    ///
+    /// Hues go the shorter way around the color wheel. A color with no hue, such as gray, takes
+    /// the other color's, so that it fades to and from it without passing through other hues.
+    pub fn lerp_in_space(&self, other: ColorF, t: f32, space: ColorSpace) -> ColorF {
+        let mut from = self.to_space(space);
+        let mut to = other.to_space(space);
+
+        if let Some(hue_index) = space.hue_index() {
+            let chroma_index = space.chroma_index();
+            if from[chroma_index] < ACHROMATIC_EPSILON {
+                from[hue_index] = to[hue_index];
+            } else if to[chroma_index] < ACHROMATIC_EPSILON {
+                to[hue_index] = from[hue_index];
+            }
+
+            let mut hue_delta = to[hue_index] - from[hue_index];
+            if hue_delta > PI {
+                hue_delta -= 2.0 * PI;
+            } else if hue_delta < -PI {
+                hue_delta += 2.0 * PI;
+            }
+            to[hue_index] = from[hue_index] + hue_delta;
+        }
+
+        ColorF::from_space(from + (to - from) * F32x4::splat(t), space)
+    }
+}
+
+// Returns the hue, shared by HSL and HSV, and the largest and smallest components.
+fn hue_max_min(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    // https://en.wikipedia.org/wiki/HSL_and_HSV#From_RGB
+    let max = f32::max(r, f32::max(g, b));
+    let min = f32::min(r, f32::min(g, b));
+    let chroma = max - min;
+    let sector = if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        math::rem_euclid((g - b) / chroma, 6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    (sector * (PI / 3.0), max, min)
+}
+
+fn normalize_hue(hue: f32) -> f32 {
+    math::rem_euclid(hue, 2.0 * PI)
+}
+
+// Components outside the range 0 to 1 are extended symmetrically, so that out-of-gamut colors
+// survive a round trip.
+fn srgb_to_linear(component: f32) -> f32 {
+    let magnitude = f32::abs(component);
+    let linear = if magnitude <= 0.04045 {
+        magnitude / 12.92
+    } else {
+        math::powf((magnitude + 0.055) / 1.055, 2.4)
+    };
+    f32::copysign(linear, component)
+}
+
+fn linear_to_srgb(component: f32) -> f32 {
+    let magnitude = f32::abs(component);
+    let srgb = if magnitude <= 0.0031308 {
+        magnitude * 12.92
+    } else {
+        1.055 * math::powf(magnitude, 1.0 / 2.4) - 0.055
+    };
+    f32::copysign(srgb, component)
+}
+
+#[cfg(test)]
+mod test {
+    use super::ColorSpace;
+    use crate::ColorF;
+    use core::f32::consts::PI;
+
+    const SPACES: [ColorSpace; 6] = [
+        ColorSpace::Srgb,
+        ColorSpace::LinearSrgb,
+        ColorSpace::Hsl,
+        ColorSpace::Hsv,
+        ColorSpace::Oklab,
+        ColorSpace::Oklch,
+    ];
+
+    fn assert_near(actual: [f32; 4], expected: [f32; 4]) {
+        for (&actual_component, &expected_component) in actual.iter().zip(expected.iter()) {
+            assert!(f32::abs(actual_component - expected_component) < 1.0e-3,
+                    "expected {:?}, got {:?}",
+                    expected,
+                    actual);
+        }
+    }
+
+    fn components(color: ColorF) -> [f32; 4] {
+        [color.r(), color.g(), color.b(), color.a()]
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let colors = [
+            ColorF::new(1.0, 0.0, 0.0, 1.0),
+            ColorF::new(0.2, 0.6, 0.9, 0.5),
+            ColorF::new(0.75, 0.75, 0.75, 1.0),
+            ColorF::new(0.9, 0.85, 0.1, 0.25),
+            ColorF::black(),
+            ColorF::white(),
+        ];
+        for &space in &SPACES {
+            for &color in &colors {
+                let round_tripped = ColorF::from_space(color.to_space(space), space);
+                assert_near(components(round_tripped), components(color));
+            }
+        }
+    }
+
+    #[test]
+    fn test_known_values() {
+        let red = ColorF::new(1.0, 0.0, 0.0, 1.0);
+        let oklab = red.to_space(ColorSpace::Oklab);
+        assert_near([oklab[0], oklab[1], oklab[2], oklab[3]], [0.62796, 0.22486, 0.12585, 1.0]);
+
+        let blue = ColorF::new(0.0, 0.0, 1.0, 1.0);
+        let hsl = blue.to_space(ColorSpace::Hsl);
+        assert_near([hsl[0], hsl[1], hsl[2], hsl[3]], [4.0 * PI / 3.0, 1.0, 0.5, 1.0]);
+
+        let white = ColorF::white().to_space(ColorSpace::Oklch);
+        assert_near([white[0], white[1], 0.0, white[3]], [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_hues_interpolate_the_short_way_around() {
+        // Halfway from magenta to yellow in HSL is red, not green.
+        let magenta = ColorF::new(1.0, 0.0, 1.0, 1.0);
+        let yellow = ColorF::new(1.0, 1.0, 0.0, 1.0);
+        let midpoint = magenta.lerp_in_space(yellow, 0.5, ColorSpace::Hsl);
+        assert_near(components(midpoint), [1.0, 0.0, 0.0, 1.0]);
+
+        // Gray takes blue's hue, so the fade stays blue.
+        let gray = ColorF::new(0.5, 0.5, 0.5, 1.0);
+        let blue = ColorF::new(0.0, 0.0, 1.0, 1.0);
+        let midpoint = gray.lerp_in_space(blue, 0.5, ColorSpace::Hsv);
+        assert_near(components(midpoint), [0.375, 0.375, 0.75, 1.0]);
+    }
+}
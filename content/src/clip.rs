@@ -16,8 +16,10 @@ use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::util::lerp;
 use pathfinder_geometry::vector::{Vector2F, Vector4F};
 use smallvec::SmallVec;
-use std::fmt::Debug;
-use std::mem;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::mem;
 
 #[derive(Clone, Copy, Debug)]
 struct Edge(LineSegment2F);
@@ -10,8 +10,9 @@
 
 //! Line dashing support.
 
-use crate::outline::{Contour, Outline, PushSegmentFlags};
-use std::mem;
+use crate::outline::{Contour, Outline};
+use crate::segment::Segment;
+use core::mem;
 
 const EPSILON: f32 = 0.0001;
 
@@ -28,15 +29,19 @@ impl<'a> OutlineDash<'a> {
     }
 
     pub fn dash(&mut self) {
+        // As in SVG and canvas, a pattern that never advances leaves the outline solid.
+        if !self.state.advances() {
+            self.output = self.input.clone();
+            return;
+        }
+
         for contour in &self.input.contours {
             ContourDash::new(contour, &mut self.output, &mut self.state).dash()
         }
     }
 
-    pub fn into_outline(mut self) -> Outline {
-        if self.state.is_on() {
-            self.output.push_contour(self.state.output);
-        }
+    #[inline]
+    pub fn into_outline(self) -> Outline {
         self.output
     }
 }
@@ -54,6 +59,9 @@ impl<'a, 'b, 'c> ContourDash<'a, 'b, 'c> {
     }
 
     fn dash(&mut self) {
+        // Each contour starts the pattern afresh.
+        self.state.restart();
+
         let (mut iterator, mut queued_segment) = (self.input.iter(), None);
         loop {
             if queued_segment.is_none() {
@@ -76,7 +84,7 @@ impl<'a, 'b, 'c> ContourDash<'a, 'b, 'c> {
             }
 
             if self.state.is_on() {
-                self.state.output.push_segment(&current_segment, PushSegmentFlags::empty());
+                push_segment_to_dash(&mut self.state.output, &current_segment);
             }
 
             self.state.distance_left -= distance;
@@ -93,41 +101,195 @@ impl<'a, 'b, 'c> ContourDash<'a, 'b, 'c> {
                 self.state.distance_left = self.state.dashes[self.state.current_dash_index];
             }
         }
+
+        // Don't let a dash that runs off the end of this contour join up with the next one.
+        if self.state.is_on() {
+            self.output.push_contour(mem::replace(&mut self.state.output, Contour::new()));
+        }
+    }
+}
+
+// Appends `segment` to the dash being built. Only the first segment of a dash brings its start
+// point, since later ones share theirs with the segment before. Repeating it, or appending a
+// zero-length piece, would leave a degenerate segment that the stroker can't find a normal for.
+fn push_segment_to_dash(dash: &mut Contour, segment: &Segment) {
+    if dash.is_empty() {
+        dash.push_endpoint(segment.baseline.from());
+    } else if segment.arc_length() < EPSILON {
+        return;
+    }
+
+    if segment.is_line() {
+        dash.push_endpoint(segment.baseline.to());
+    } else if segment.is_quadratic() {
+        dash.push_quadratic(segment.ctrl.from(), segment.baseline.to());
+    } else {
+        dash.push_cubic(segment.ctrl.from(), segment.ctrl.to(), segment.baseline.to());
     }
 }
 
 struct DashState<'a> {
     output: Contour,
     dashes: &'a [f32],
+    first_dash_index: usize,
+    first_distance_left: f32,
     current_dash_index: usize,
     distance_left: f32,
 }
 
 impl<'a> DashState<'a> {
-    fn new(dashes: &'a [f32], mut offset: f32) -> DashState<'a> {
+    fn new(dashes: &'a [f32], offset: f32) -> DashState<'a> {
         let total: f32 = dashes.iter().cloned().sum();
-        offset %= total;
+        // A negative offset shifts the pattern forward rather than back.
+        let mut offset = offset % total;
+        if offset < 0.0 {
+            offset += total;
+        }
 
-        let mut current_dash_index = 0;
-        while current_dash_index < dashes.len() {
-            let dash = dashes[current_dash_index];
-            if offset < dash {
-                break;
-            }
-            offset -= dash;
-            current_dash_index += 1;
+        let mut first_dash_index = 0;
+        while first_dash_index + 1 < dashes.len() && offset >= dashes[first_dash_index] {
+            offset -= dashes[first_dash_index];
+            first_dash_index += 1;
         }
+        let first_distance_left = match dashes.get(first_dash_index) {
+            Some(dash) => dash - offset,
+            None => 0.0,
+        };
 
         DashState {
             output: Contour::new(),
             dashes,
-            current_dash_index,
-            distance_left: offset,
+            first_dash_index,
+            first_distance_left,
+            current_dash_index: first_dash_index,
+            distance_left: first_distance_left,
         }
     }
 
+    // Returns false if the pattern is empty, negative, or all zero, in which case dashing would
+    // never get anywhere.
+    fn advances(&self) -> bool {
+        let total: f32 = self.dashes.iter().cloned().sum();
+        total > 0.0 && total.is_finite() && self.dashes.iter().all(|&dash| dash >= 0.0)
+    }
+
+    #[inline]
+    fn restart(&mut self) {
+        self.current_dash_index = self.first_dash_index;
+        self.distance_left = self.first_distance_left;
+    }
+
     #[inline]
     fn is_on(&self) -> bool {
         self.current_dash_index % 2 == 0
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::OutlineDash;
+    use crate::outline::{Contour, Outline};
+    use crate::stroke::{OutlineStrokeToFill, StrokeStyle};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::Vector2F;
+
+    // A polyline through `points`, one contour per slice.
+    fn polylines(contours: &[&[(f32, f32)]]) -> Outline {
+        let mut outline = Outline::new();
+        for points in contours {
+            let mut contour = Contour::new();
+            for &(x, y) in points.iter() {
+                contour.push_endpoint(Vector2F::new(x, y));
+            }
+            outline.push_contour(contour);
+        }
+        outline
+    }
+
+    // Returns the starting and ending x coordinates of each dash.
+    fn dash(outline: &Outline, dashes: &[f32], offset: f32) -> Vec<(f32, f32)> {
+        let mut dash = OutlineDash::new(outline, dashes, offset);
+        dash.dash();
+        dash.into_outline().contours().iter().map(|contour| {
+            (contour.position_of(0).x(), contour.last_position().unwrap().x())
+        }).map(|(from, to)| (from.round(), to.round())).collect()
+    }
+
+    #[test]
+    fn test_dashes_start_on() {
+        let line = polylines(&[&[(0.0, 0.0), (100.0, 0.0)]]);
+        assert_eq!(dash(&line, &[10.0, 10.0], 0.0),
+                   vec![(0.0, 10.0), (20.0, 30.0), (40.0, 50.0), (60.0, 70.0), (80.0, 90.0)]);
+        assert_eq!(dash(&line, &[30.0, 10.0, 5.0, 10.0], 0.0),
+                   vec![(0.0, 30.0), (40.0, 45.0), (55.0, 85.0), (95.0, 100.0)]);
+    }
+
+    #[test]
+    fn test_offsets_shift_the_pattern() {
+        let line = polylines(&[&[(0.0, 0.0), (50.0, 0.0)]]);
+        assert_eq!(dash(&line, &[10.0, 10.0], 5.0),
+                   vec![(0.0, 5.0), (15.0, 25.0), (35.0, 45.0)]);
+        assert_eq!(dash(&line, &[10.0, 10.0], 15.0),
+                   vec![(5.0, 15.0), (25.0, 35.0), (45.0, 50.0)]);
+        // Negative offsets move the pattern along the path, and whole periods don't matter.
+        assert_eq!(dash(&line, &[10.0, 10.0], -5.0),
+                   vec![(5.0, 15.0), (25.0, 35.0), (45.0, 50.0)]);
+        assert_eq!(dash(&line, &[10.0, 10.0], 45.0), dash(&line, &[10.0, 10.0], 5.0));
+        assert_eq!(dash(&line, &[10.0, 10.0], -35.0), dash(&line, &[10.0, 10.0], 5.0));
+    }
+
+    #[test]
+    fn test_patterns_that_never_advance_are_solid() {
+        let line = polylines(&[&[(0.0, 0.0), (50.0, 0.0)]]);
+        assert_eq!(dash(&line, &[], 0.0), vec![(0.0, 50.0)]);
+        assert_eq!(dash(&line, &[0.0, 0.0], 3.0), vec![(0.0, 50.0)]);
+        assert_eq!(dash(&line, &[10.0, -5.0], 0.0), vec![(0.0, 50.0)]);
+    }
+
+    #[test]
+    fn test_each_contour_restarts_the_pattern() {
+        // The first contour ends partway through a dash, which must not continue into the second.
+        let lines = polylines(&[&[(0.0, 0.0), (15.0, 0.0)], &[(100.0, 10.0), (125.0, 10.0)]]);
+        assert_eq!(dash(&lines, &[10.0, 10.0], 0.0),
+                   vec![(0.0, 10.0), (100.0, 110.0), (120.0, 125.0)]);
+    }
+
+    #[test]
+    fn test_dashes_around_corners_can_be_stroked() {
+        let mut square = Contour::new();
+        for &(x, y) in &[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)] {
+            square.push_endpoint(Vector2F::new(x, y));
+        }
+        square.close();
+        let mut outline = Outline::new();
+        outline.push_contour(square);
+
+        let mut dash = OutlineDash::new(&outline, &[15.0, 5.0], 0.0);
+        dash.dash();
+        let dashed = dash.into_outline();
+        assert_eq!(dashed.contours().len(), 2);
+        // Each dash turns one corner, which appears once.
+        assert!(dashed.contours().iter().all(|contour| contour.len() == 3));
+
+        let style = StrokeStyle { line_width: 2.0, ..StrokeStyle::default() };
+        let mut stroke_to_fill = OutlineStrokeToFill::new(&dashed, style);
+        stroke_to_fill.offset();
+        let stroked = stroke_to_fill.into_outline();
+        assert_eq!(stroked.contours().len(), 2);
+        assert_eq!(stroked.bounds(),
+                   RectF::from_points(Vector2F::new(-1.0, -1.0), Vector2F::new(11.0, 11.0)));
+    }
+
+    #[test]
+    fn test_dashes_follow_corners() {
+        let corner = polylines(&[&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]]);
+        let mut dash = OutlineDash::new(&corner, &[15.0, 5.0], 0.0);
+        dash.dash();
+        let outline = dash.into_outline();
+        assert_eq!(outline.contours().len(), 1);
+        let contour = &outline.contours()[0];
+        assert_eq!(contour.position_of(0), Vector2F::new(0.0, 0.0));
+        assert_eq!(contour.last_position(), Some(Vector2F::new(10.0, 5.0)));
+        assert_eq!(outline.bounds().lower_right(), Vector2F::new(10.0, 5.0));
+    }
+}
@@ -66,6 +66,18 @@ pub enum Filter {
         direction: BlurDirection,
         sigma: f32,
     },
+
+    /// A fragment shader supplied by the application, such as a CRT, bloom, or halftone effect.
+    ///
+    /// The shader is registered with the GPU renderer, which describes what it's given. The
+    /// result is composited with source-over. The CPU renderer can't run custom shaders, so it
+    /// composites the layer unchanged.
+    Custom {
+        /// The shader, as returned when it was registered.
+        shader: CustomFilterId,
+        /// Passed to the shader as the `uParams` uniform.
+        params: [f32; 4],
+    },
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -88,8 +100,10 @@ pub enum CompositeOp {
 
 /// Blend modes that can be applied to individual paths.
 ///
-/// All blend modes preserve parts of the destination that are not overlapped by the source path.
-/// Other Porter-Duff compositing operations are `CompositeOp`s.
+/// All blend modes preserve parts of the destination that are not overlapped by the source path,
+/// and mix the blended color with the destination where the path only partly covers a pixel.
+/// Drawing a render target with a `CompositeOp` applies an operator to the whole destination
+/// instead.
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum BlendMode {
     // Supported by GPU blender
@@ -103,6 +117,13 @@ pub enum BlendMode {
     Lighten,
     Darken,
 
+    // Porter-Duff operators, which need dual-source blending or a readable framebuffer
+    Copy,
+    SrcIn,
+    DestIn,
+    SrcOut,
+    DestAtop,
+
     // Overlay
     Multiply,
     Screen,
@@ -132,6 +153,10 @@ pub enum BlendMode {
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct DefringingKernel(pub [f32; 4]);
 
+/// A custom filter shader registered with a renderer.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CustomFilterId(pub u32);
+
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum BlurDirection {
     X,
@@ -165,7 +190,7 @@ impl BlendMode {
     #[inline]
     pub fn occludes_backdrop(self) -> bool {
         match self {
-            BlendMode::SrcOver | BlendMode::Clear => true,
+            BlendMode::SrcOver | BlendMode::Clear | BlendMode::Copy => true,
             BlendMode::DestOver |
             BlendMode::DestOut |
             BlendMode::SrcAtop |
@@ -173,6 +198,10 @@ impl BlendMode {
             BlendMode::Lighter |
             BlendMode::Lighten |
             BlendMode::Darken |
+            BlendMode::SrcIn |
+            BlendMode::DestIn |
+            BlendMode::SrcOut |
+            BlendMode::DestAtop |
             BlendMode::Multiply |
             BlendMode::Screen |
             BlendMode::HardLight |
@@ -188,4 +217,18 @@ impl BlendMode {
             BlendMode::Luminosity => false,
         }
     }
+
+    /// For the blend modes that are only done in hardware with dual-source blending, returns the
+    /// Porter-Duff operator that they apply where the path covers the destination.
+    #[inline]
+    pub fn porter_duff_op(self) -> Option<CompositeOp> {
+        match self {
+            BlendMode::Copy => Some(CompositeOp::Copy),
+            BlendMode::SrcIn => Some(CompositeOp::SrcIn),
+            BlendMode::DestIn => Some(CompositeOp::DestIn),
+            BlendMode::SrcOut => Some(CompositeOp::SrcOut),
+            BlendMode::DestAtop => Some(CompositeOp::DestAtop),
+            _ => None,
+        }
+    }
 }
@@ -13,10 +13,9 @@ use crate::util;
 use pathfinder_color::ColorU;
 use pathfinder_geometry::line_segment::LineSegment2F;
 use pathfinder_geometry::util as geometry_util;
-use std::cmp::{Ordering, PartialOrd};
-use std::convert;
-use std::hash::{Hash, Hasher};
-use std::mem;
+use core::cmp::{Ordering, PartialOrd};
+use core::convert;
+use core::hash::{Hash, Hasher};
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Gradient {
@@ -65,11 +64,8 @@ impl Eq for ColorStop {}
 
 impl Hash for ColorStop {
     fn hash<H>(&self, state: &mut H) where H: Hasher {
-        unsafe {
-            self.color.hash(state);
-            let offset = mem::transmute::<f32, u32>(self.offset);
-            offset.hash(state);
-        }
+        self.color.hash(state);
+        self.offset.to_bits().hash(state);
     }
 }
 
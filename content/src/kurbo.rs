@@ -0,0 +1,132 @@
+// pathfinder/content/src/kurbo.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversions between outlines and `kurbo` Bézier paths.
+
+use crate::outline::{Contour, Outline};
+use crate::segment::SegmentKind;
+use kurbo::{BezPath, PathEl, Point};
+use pathfinder_geometry::vector::Vector2F;
+use core::mem;
+
+impl<'a> From<&'a BezPath> for Outline {
+    fn from(path: &'a BezPath) -> Outline {
+        let mut outline = Outline::new();
+        let mut contour = Contour::new();
+        let mut start = Vector2F::default();
+        for element in path.elements() {
+            // Drawing after a `ClosePath` without a `MoveTo` starts from the closed subpath's
+            // start point.
+            let is_move_to = match *element {
+                PathEl::MoveTo(_) => true,
+                _ => false,
+            };
+            if contour.is_empty() && !is_move_to {
+                contour.push_endpoint(start);
+            }
+
+            match *element {
+                PathEl::MoveTo(point) => {
+                    if !contour.is_empty() {
+                        outline.push_contour(mem::replace(&mut contour, Contour::new()));
+                    }
+                    start = Vector2F::from(point);
+                    contour.push_endpoint(start);
+                }
+                PathEl::LineTo(point) => contour.push_endpoint(Vector2F::from(point)),
+                PathEl::QuadTo(ctrl, point) => {
+                    contour.push_quadratic(Vector2F::from(ctrl), Vector2F::from(point))
+                }
+                PathEl::CurveTo(ctrl0, ctrl1, point) => {
+                    contour.push_cubic(Vector2F::from(ctrl0),
+                                       Vector2F::from(ctrl1),
+                                       Vector2F::from(point))
+                }
+                PathEl::ClosePath => {
+                    contour.close();
+                    outline.push_contour(mem::replace(&mut contour, Contour::new()));
+                }
+            }
+        }
+
+        if !contour.is_empty() {
+            outline.push_contour(contour);
+        }
+        outline
+    }
+}
+
+impl From<BezPath> for Outline {
+    #[inline]
+    fn from(path: BezPath) -> Outline {
+        Outline::from(&path)
+    }
+}
+
+impl<'a> From<&'a Outline> for BezPath {
+    fn from(outline: &'a Outline) -> BezPath {
+        let mut path = BezPath::new();
+        for contour in outline.contours() {
+            if contour.is_empty() {
+                continue;
+            }
+            path.move_to(Point::from(contour.position_of(0)));
+
+            let mut segments = contour.iter().peekable();
+            while let Some(segment) = segments.next() {
+                // Closed contours end with an implicit line back to the start, which
+                // `close_path()` stands for.
+                if contour.is_closed() && segments.peek().is_none() {
+                    break;
+                }
+
+                match segment.kind {
+                    SegmentKind::None => {}
+                    SegmentKind::Line => path.line_to(Point::from(segment.baseline.to())),
+                    SegmentKind::Quadratic => {
+                        path.quad_to(Point::from(segment.ctrl.from()),
+                                     Point::from(segment.baseline.to()))
+                    }
+                    SegmentKind::Cubic => {
+                        path.curve_to(Point::from(segment.ctrl.from()),
+                                      Point::from(segment.ctrl.to()),
+                                      Point::from(segment.baseline.to()))
+                    }
+                }
+            }
+
+            if contour.is_closed() {
+                path.close_path();
+            }
+        }
+        path
+    }
+}
+
+impl From<Outline> for BezPath {
+    #[inline]
+    fn from(outline: Outline) -> BezPath {
+        BezPath::from(&outline)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::outline::Outline;
+    use kurbo::BezPath;
+
+    #[test]
+    fn test_round_trip() {
+        let path = BezPath::from_svg("M0 0Q5 -5 10 0L10 10ZM20 0C25 5 30 -5 35 0").unwrap();
+        let outline = Outline::from(&path);
+        assert_eq!(outline.contours().len(), 2);
+        assert_eq!(BezPath::from(outline), path);
+    }
+}
@@ -11,7 +11,13 @@
 //! Pathfinder's representation of a vector scene.
 //!
 //! This module also contains various path utilities.
+//!
+//! Without the default `pf-std` feature, this crate is `no_std` and only needs `alloc`, but it has
+//! no patterns or tessellation.
+
+#![cfg_attr(not(feature = "pf-std"), no_std)]
 
+extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 #[macro_use]
@@ -24,12 +30,20 @@ pub mod fill;
 pub mod gradient;
 pub mod orientation;
 pub mod outline;
+#[cfg(feature = "pf-std")]
 pub mod pattern;
 pub mod render_target;
+pub mod sdf;
 pub mod segment;
 pub mod sorted_vector;
 pub mod stroke;
+#[cfg(feature = "pf-std")]
+pub mod tessellation;
 pub mod transform;
 
 mod dilation;
+#[cfg(feature = "pf-kurbo")]
+mod kurbo;
+#[cfg(feature = "pf-lyon")]
+mod lyon;
 mod util;
@@ -0,0 +1,138 @@
+// pathfinder/content/src/lyon.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversions between outlines and `lyon_path` paths.
+
+use crate::outline::{Contour, Outline};
+use crate::segment::SegmentKind;
+use lyon_path::builder::{FlatPathBuilder, PathBuilder};
+use lyon_path::math::{self, Point};
+use lyon_path::{Path, PathEvent};
+use pathfinder_geometry::vector::Vector2F;
+use core::mem;
+
+impl<'a> From<&'a Path> for Outline {
+    fn from(path: &'a Path) -> Outline {
+        let mut outline = Outline::new();
+        let mut contour = Contour::new();
+        for event in path.iter() {
+            match event {
+                PathEvent::Begin { at } => contour.push_endpoint(to_vector(at)),
+                PathEvent::Line { to, .. } => contour.push_endpoint(to_vector(to)),
+                PathEvent::Quadratic { ctrl, to, .. } => {
+                    contour.push_quadratic(to_vector(ctrl), to_vector(to))
+                }
+                PathEvent::Cubic { ctrl1, ctrl2, to, .. } => {
+                    contour.push_cubic(to_vector(ctrl1), to_vector(ctrl2), to_vector(to))
+                }
+                PathEvent::End { close, .. } => {
+                    if close {
+                        contour.close();
+                    }
+                    outline.push_contour(mem::replace(&mut contour, Contour::new()));
+                }
+            }
+        }
+        outline
+    }
+}
+
+impl From<Path> for Outline {
+    #[inline]
+    fn from(path: Path) -> Outline {
+        Outline::from(&path)
+    }
+}
+
+impl<'a> From<&'a Outline> for Path {
+    fn from(outline: &'a Outline) -> Path {
+        let mut builder = Path::builder();
+        for contour in outline.contours() {
+            if contour.is_empty() {
+                continue;
+            }
+            builder.move_to(to_point(contour.position_of(0)));
+
+            let mut segments = contour.iter().peekable();
+            while let Some(segment) = segments.next() {
+                // Closed contours end with an implicit line back to the start, which `close()`
+                // stands for.
+                if contour.is_closed() && segments.peek().is_none() {
+                    break;
+                }
+
+                match segment.kind {
+                    SegmentKind::None => {}
+                    SegmentKind::Line => builder.line_to(to_point(segment.baseline.to())),
+                    SegmentKind::Quadratic => {
+                        builder.quadratic_bezier_to(to_point(segment.ctrl.from()),
+                                                    to_point(segment.baseline.to()))
+                    }
+                    SegmentKind::Cubic => {
+                        builder.cubic_bezier_to(to_point(segment.ctrl.from()),
+                                                to_point(segment.ctrl.to()),
+                                                to_point(segment.baseline.to()))
+                    }
+                }
+            }
+
+            if contour.is_closed() {
+                builder.close();
+            }
+        }
+        builder.build()
+    }
+}
+
+impl From<Outline> for Path {
+    #[inline]
+    fn from(outline: Outline) -> Path {
+        Path::from(&outline)
+    }
+}
+
+#[inline]
+fn to_vector(point: Point) -> Vector2F {
+    Vector2F::new(point.x, point.y)
+}
+
+#[inline]
+fn to_point(vector: Vector2F) -> Point {
+    math::point(vector.x(), vector.y())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::outline::{Contour, Outline};
+    use lyon_path::Path;
+    use pathfinder_geometry::vector::Vector2F;
+
+    #[test]
+    fn test_round_trip() {
+        let mut closed = Contour::new();
+        closed.push_endpoint(Vector2F::new(0.0, 0.0));
+        closed.push_quadratic(Vector2F::new(5.0, -5.0), Vector2F::new(10.0, 0.0));
+        closed.push_endpoint(Vector2F::new(10.0, 10.0));
+        closed.close();
+        let mut open = Contour::new();
+        open.push_endpoint(Vector2F::new(20.0, 0.0));
+        open.push_cubic(Vector2F::new(25.0, 5.0),
+                        Vector2F::new(30.0, -5.0),
+                        Vector2F::new(35.0, 0.0));
+        let mut outline = Outline::new();
+        outline.push_contour(closed);
+        outline.push_contour(open);
+
+        let path = Path::from(&outline);
+        assert_eq!(path.iter().count(), 7);
+        let round_tripped = Outline::from(path);
+        assert_eq!(format!("{:?}", round_tripped), format!("{:?}", outline));
+    }
+}
@@ -20,9 +20,17 @@ use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::transform3d::Perspective;
 use pathfinder_geometry::unit_vector::UnitVector;
 use pathfinder_geometry::vector::Vector2F;
-use std::f32::consts::PI;
-use std::fmt::{self, Debug, Formatter};
-use std::mem;
+use pathfinder_simd::math;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use core::fmt::{self, Debug, Formatter};
+use core::mem;
+
+// How far, in device pixels, a projected curve may stray from the true projection before it's
+// subdivided.
+const PERSPECTIVE_TOLERANCE: f32 = 0.25;
+const MAX_PERSPECTIVE_SUBDIVISION_DEPTH: u32 = 8;
 
 #[derive(Clone)]
 pub struct Outline {
@@ -202,6 +210,32 @@ impl Outline {
             self.push_contour(ContourRectClipper::new(clip_rect, contour).clip());
         }
     }
+
+    /// Removes points with NaN or infinite coordinates, which clipping and tiling can't handle.
+    /// Returns true if any points were removed.
+    ///
+    /// A non-finite endpoint is dropped along with the curve leading to it, so the contour goes
+    /// straight on to the next finite endpoint. A curve with a non-finite control point becomes a
+    /// line.
+    pub fn remove_non_finite_points(&mut self) -> bool {
+        let all_finite = self.contours.iter().all(|contour| {
+            contour.points.iter().all(|&point| is_finite(point))
+        });
+        if all_finite {
+            return false;
+        }
+
+        let contours: Vec<_> = self.contours
+                                   .iter()
+                                   .map(|contour| contour.without_non_finite_points())
+                                   .collect();
+        self.contours.clear();
+        self.bounds = RectF::default();
+        for contour in contours {
+            self.push_contour(contour);
+        }
+        true
+    }
 }
 
 impl Debug for Outline {
@@ -243,7 +277,7 @@ impl Contour {
     }
 
     #[inline]
-    pub fn iter(&self) -> ContourIter {
+    pub fn iter(&self) -> ContourIter<'_> {
         ContourIter {
             contour: self,
             index: 1,
@@ -313,8 +347,6 @@ impl Contour {
                              point: Vector2F,
                              flags: PointFlags,
                              update_bounds: bool) {
-        debug_assert!(!point.x().is_nan() && !point.y().is_nan());
-
         if update_bounds {
             let first = self.is_empty();
             union_rect(&mut self.bounds, point, first);
@@ -359,8 +391,8 @@ impl Contour {
         if end_angle - start_angle >= PI * 2.0 {
             self.push_ellipse(transform);
         } else {
-            let start = Vector2F::new(f32::cos(start_angle), f32::sin(start_angle));
-            let end = Vector2F::new(f32::cos(end_angle), f32::sin(end_angle));
+            let start = Vector2F::new(math::cos(start_angle), math::sin(start_angle));
+            let end = Vector2F::new(math::cos(end_angle), math::sin(end_angle));
             self.push_arc_from_unit_chord(transform, LineSegment2F::new(start, end), direction);
         }
     }
@@ -537,10 +569,29 @@ impl Contour {
         }
     }
 
+    /// Projects this contour.
+    ///
+    /// Lines stay lines under a projective transform, but curves don't stay polynomial curves, so
+    /// curves are subdivided until projecting their control points is accurate enough.
     pub fn apply_perspective(&mut self, perspective: &Perspective) {
-        for (point_index, point) in self.points.iter_mut().enumerate() {
-            *point = *perspective * *point;
-            union_rect(&mut self.bounds, *point, point_index == 0);
+        let contour = self.take();
+        self.closed = contour.closed;
+        if contour.is_empty() {
+            return;
+        }
+
+        self.push_point(*perspective * contour.position_of(0), PointFlags::empty(), true);
+
+        let mut point_index = 0;
+        while point_index + 1 < contour.len() {
+            let segment = contour.segment_after(point_index);
+            if segment.is_line() {
+                self.push_point(*perspective * segment.baseline.to(), PointFlags::empty(), true);
+                point_index += 1;
+            } else {
+                push_perspective_cubic(self, &segment.to_cubic(), perspective, 0);
+                point_index += if segment.is_quadratic() { 2 } else { 3 };
+            }
         }
     }
 
@@ -694,6 +745,33 @@ impl Contour {
 
     // Use this function to keep bounds up to date when mutating paths. See `Outline::transform()`
     // for an example of use.
+    fn without_non_finite_points(&self) -> Contour {
+        let mut contour = Contour::new();
+        contour.closed = self.closed;
+
+        let mut control_points_start = 0;
+        for point_index in 0..self.points.len() {
+            if !self.flags[point_index].is_empty() {
+                continue;
+            }
+
+            let point = self.points[point_index];
+            if is_finite(point) {
+                let control_points = &self.points[control_points_start..point_index];
+                if !contour.is_empty() && control_points.iter().all(|point| is_finite(*point)) {
+                    for control_point_index in control_points_start..point_index {
+                        contour.push_point(self.points[control_point_index],
+                                           self.flags[control_point_index],
+                                           true);
+                    }
+                }
+                contour.push_point(point, PointFlags::empty(), true);
+            }
+            control_points_start = point_index + 1;
+        }
+        contour
+    }
+
     pub(crate) fn update_bounds(&self, bounds: &mut Option<RectF>) {
         *bounds = Some(match *bounds {
             None => self.bounds,
@@ -834,6 +912,40 @@ pub enum ArcDirection {
     CCW,
 }
 
+// Pushes the projection of a cubic curve, minus its start point.
+fn push_perspective_cubic(contour: &mut Contour,
+                          segment: &Segment,
+                          perspective: &Perspective,
+                          depth: u32) {
+    let ctrl0 = *perspective * segment.ctrl.from();
+    let ctrl1 = *perspective * segment.ctrl.to();
+    let to = *perspective * segment.baseline.to();
+
+    if depth < MAX_PERSPECTIVE_SUBDIVISION_DEPTH {
+        let from = *perspective * segment.baseline.from();
+        let projected = Segment::cubic(LineSegment2F::new(from, to),
+                                       LineSegment2F::new(ctrl0, ctrl1));
+        let exact_midpoint = *perspective * segment.as_cubic_segment().sample(0.5);
+        let approx_midpoint = projected.as_cubic_segment().sample(0.5);
+        let error = (exact_midpoint - approx_midpoint).square_length();
+        if error > PERSPECTIVE_TOLERANCE * PERSPECTIVE_TOLERANCE {
+            let (prev, next) = segment.as_cubic_segment().split(0.5);
+            push_perspective_cubic(contour, &prev, perspective, depth + 1);
+            push_perspective_cubic(contour, &next, perspective, depth + 1);
+            return;
+        }
+    }
+
+    contour.push_point(ctrl0, PointFlags::CONTROL_POINT_0, true);
+    contour.push_point(ctrl1, PointFlags::CONTROL_POINT_1, true);
+    contour.push_point(to, PointFlags::empty(), true);
+}
+
+#[inline]
+fn is_finite(point: Vector2F) -> bool {
+    point.x().is_finite() && point.y().is_finite()
+}
+
 #[inline]
 pub(crate) fn union_rect(bounds: &mut RectF, new_point: Vector2F, first: bool) {
     if first {
@@ -842,3 +954,78 @@ pub(crate) fn union_rect(bounds: &mut RectF, new_point: Vector2F, first: bool) {
         *bounds = bounds.union_point(new_point)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Contour;
+    use pathfinder_geometry::transform3d::{Perspective, Transform4F};
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+
+    // Shrinks the scene as x grows, as though it were tilted away from the viewer.
+    fn tilted_perspective() -> Perspective {
+        let transform = Transform4F::row_major(1.0, 0.0, 0.0, 0.0,
+                                               0.0, 1.0, 0.0, 0.0,
+                                               0.0, 0.0, 1.0, 0.0,
+                                               0.5, 0.0, 0.0, 1.0);
+        Perspective::new(&transform, Vector2I::splat(1000))
+    }
+
+    // The distance from `point` to the nearest of many points sampled along `contour`.
+    fn distance_to_contour(contour: &Contour, point: Vector2F) -> f32 {
+        contour.iter().flat_map(|segment| {
+            (0..=256).map(move |step| (segment.sample(step as f32 / 256.0) - point).length())
+        }).fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn test_projected_lines_are_not_subdivided() {
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(-1.0, -1.0));
+        contour.push_endpoint(Vector2F::new(1.0, -1.0));
+        contour.push_endpoint(Vector2F::new(1.0, 1.0));
+        contour.close();
+
+        let perspective = tilted_perspective();
+        let mut projected = contour.clone();
+        projected.apply_perspective(&perspective);
+        assert!(projected.is_closed());
+        assert_eq!(projected.len(), 3);
+        for point_index in 0..3 {
+            assert_eq!(projected.position_of(point_index),
+                       perspective * contour.position_of(point_index));
+        }
+    }
+
+    #[test]
+    fn test_projected_curves_are_subdivided() {
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(-1.0, 0.0));
+        contour.push_cubic(Vector2F::new(-1.0, 1.0),
+                           Vector2F::new(1.0, 1.0),
+                           Vector2F::new(1.0, 0.0));
+        let curve = contour.segment_after(0);
+
+        let perspective = tilted_perspective();
+        let mut projected = contour.clone();
+        projected.apply_perspective(&perspective);
+        assert!(projected.len() > 4);
+        assert_eq!(projected.position_of(0), perspective * curve.baseline.from());
+        assert_eq!(projected.position_of(projected.len() - 1), perspective * curve.baseline.to());
+
+        // Projecting only the control points would be visibly wrong here.
+        let mut naive = contour.clone();
+        for point_index in 0..naive.len() {
+            let point = perspective * contour.position_of(point_index);
+            naive.points[point_index as usize] = point;
+        }
+
+        let max_error = |contour: &Contour| {
+            (0..=64).map(|step| {
+                let exact_point = perspective * curve.sample(step as f32 / 64.0);
+                distance_to_contour(contour, exact_point)
+            }).fold(0.0, f32::max)
+        };
+        assert!(max_error(&naive) > 2.0);
+        assert!(max_error(&projected) < 0.5);
+    }
+}
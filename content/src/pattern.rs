@@ -17,6 +17,7 @@ use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::Vector2I;
 use std::fmt::{self, Debug, Formatter};
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 #[cfg(feature = "pf-image")]
 use image::RgbaImage;
@@ -32,7 +33,9 @@ pub struct Pattern {
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum PatternSource {
     Image(Image),
+    CompressedImage(CompressedImage),
     RenderTarget(RenderTargetId),
+    External(ExternalTexture),
 }
 
 /// RGBA, non-premultiplied.
@@ -45,11 +48,53 @@ pub struct Image {
     is_opaque: bool,
 }
 
+/// An image that has already been block-compressed for the GPU.
+///
+/// The renderer uploads the data as is, so it must be in a format the device supports; see
+/// `Device::supports_texture_format()` in `pathfinder_gpu`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct CompressedImage {
+    size: Vector2I,
+    format: CompressedImageFormat,
+    data: Arc<Vec<u8>>,
+}
+
+/// A texture that something other than Pathfinder produces, such as a video decoder or a camera.
+///
+/// The renderer samples the texture directly, without copying it. The application supplies the
+/// texture for each ID to the renderer before rendering the scene.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ExternalTexture {
+    pub id: ExternalTextureId,
+    pub size: Vector2I,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ExternalTextureId(pub u32);
+
+/// Block compression formats. All of these use 4×4 blocks.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CompressedImageFormat {
+    /// S3TC DXT1, 8 bytes per block.
+    BC1,
+    /// S3TC DXT5, 16 bytes per block.
+    BC3,
+    /// BPTC, 16 bytes per block.
+    BC7,
+    /// ETC2 RGBA with EAC alpha, 16 bytes per block.
+    ETC2RGBA8,
+    /// ASTC LDR 4×4, 16 bytes per block.
+    ASTC4x4,
+}
+
 bitflags! {
     pub struct PatternFlags: u8 {
         const REPEAT_X      = 0x01;
         const REPEAT_Y      = 0x02;
         const NO_SMOOTHING  = 0x04;
+        /// Filters anisotropically, so that the pattern stays sharp when it's squashed much more
+        /// along one axis than the other, as in perspective. Has no effect with `NO_SMOOTHING`.
+        const ANISOTROPIC   = 0x08;
     }
 }
 
@@ -58,6 +103,18 @@ impl Pattern {
     pub fn new(source: PatternSource, transform: Transform2F, flags: PatternFlags) -> Pattern {
         Pattern { source, transform, flags }
     }
+
+    /// Returns false if the pattern is sampled with nearest-neighbor filtering, which keeps
+    /// pixel art crisp when scaled up.
+    #[inline]
+    pub fn smoothing_enabled(&self) -> bool {
+        !self.flags.contains(PatternFlags::NO_SMOOTHING)
+    }
+
+    #[inline]
+    pub fn set_smoothing_enabled(&mut self, enable: bool) {
+        self.flags.set(PatternFlags::NO_SMOOTHING, !enable)
+    }
 }
 
 impl Image {
@@ -91,11 +148,66 @@ impl Image {
     }
 }
 
+impl ExternalTexture {
+    #[inline]
+    pub fn new(id: ExternalTextureId, size: Vector2I) -> ExternalTexture {
+        ExternalTexture { id, size }
+    }
+}
+
+impl CompressedImage {
+    /// Wraps compressed image data. Panics if `data` is too small for an image of `size`.
+    #[inline]
+    pub fn new(size: Vector2I, format: CompressedImageFormat, data: Arc<Vec<u8>>)
+               -> CompressedImage {
+        assert!(data.len() >= format.byte_size(size));
+        CompressedImage { size, format, data }
+    }
+
+    #[inline]
+    pub fn size(&self) -> Vector2I {
+        self.size
+    }
+
+    #[inline]
+    pub fn format(&self) -> CompressedImageFormat {
+        self.format
+    }
+
+    #[inline]
+    pub fn data(&self) -> &Arc<Vec<u8>> {
+        &self.data
+    }
+}
+
+impl CompressedImageFormat {
+    #[inline]
+    pub fn bytes_per_block(self) -> usize {
+        match self {
+            CompressedImageFormat::BC1 => 8,
+            CompressedImageFormat::BC3 |
+            CompressedImageFormat::BC7 |
+            CompressedImageFormat::ETC2RGBA8 |
+            CompressedImageFormat::ASTC4x4 => 16,
+        }
+    }
+
+    /// The number of bytes in an image of the given size, rounded up to whole 4×4 blocks.
+    #[inline]
+    pub fn byte_size(self, size: Vector2I) -> usize {
+        let blocks_x = (size.x() as usize).div_ceil(4);
+        let blocks_y = (size.y() as usize).div_ceil(4);
+        blocks_x * blocks_y * self.bytes_per_block()
+    }
+}
+
 impl PatternSource {
     #[inline]
     pub fn is_opaque(&self) -> bool {
         match *self {
             PatternSource::Image(ref image) => image.is_opaque(),
+            // We can't tell without decoding, so assume the worst.
+            PatternSource::CompressedImage(_) | PatternSource::External(_) => false,
             PatternSource::RenderTarget(_) => {
                 // TODO(pcwalton): Maybe do something smarter here?
                 false
@@ -111,6 +223,17 @@ impl Debug for Image {
     }
 }
 
+impl Debug for CompressedImage {
+    #[inline]
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter,
+               "(compressed image {}×{} px, {:?})",
+               self.size.x(),
+               self.size.y(),
+               self.format)
+    }
+}
+
 impl Eq for Pattern {}
 
 impl Hash for Pattern {
@@ -120,3 +243,52 @@ impl Hash for Pattern {
         self.flags.hash(state);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{CompressedImage, CompressedImageFormat, Image, Pattern, PatternFlags};
+    use super::PatternSource;
+    use pathfinder_color::ColorU;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::Vector2I;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_compressed_byte_size_rounds_up_to_blocks() {
+        let bc1 = CompressedImageFormat::BC1;
+        let bc7 = CompressedImageFormat::BC7;
+        assert_eq!(bc1.byte_size(Vector2I::new(4, 4)), 8);
+        assert_eq!(bc1.byte_size(Vector2I::new(5, 5)), 2 * 2 * 8);
+        assert_eq!(bc7.byte_size(Vector2I::new(1, 1)), 16);
+        assert_eq!(bc7.byte_size(Vector2I::new(8, 3)), 2 * 16);
+        assert_eq!(bc7.byte_size(Vector2I::new(0, 0)), 0);
+    }
+
+    #[test]
+    fn test_compressed_image_is_never_opaque() {
+        let format = CompressedImageFormat::BC1;
+        let size = Vector2I::new(4, 4);
+        let image = CompressedImage::new(size, format, Arc::new(vec![0; format.byte_size(size)]));
+        assert!(!PatternSource::CompressedImage(image).is_opaque());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_compressed_image_rejects_short_data() {
+        let format = CompressedImageFormat::BC3;
+        let size = Vector2I::new(8, 8);
+        CompressedImage::new(size, format, Arc::new(vec![0; format.byte_size(size) - 1]));
+    }
+
+    #[test]
+    fn test_smoothing_toggles_only_its_flag() {
+        let source = PatternSource::Image(Image::new(Vector2I::splat(1), vec![ColorU::white()]));
+        let mut pattern = Pattern::new(source, Transform2F::default(), PatternFlags::REPEAT_X);
+        assert!(pattern.smoothing_enabled());
+        pattern.set_smoothing_enabled(false);
+        assert!(!pattern.smoothing_enabled());
+        assert_eq!(pattern.flags, PatternFlags::REPEAT_X | PatternFlags::NO_SMOOTHING);
+        pattern.set_smoothing_enabled(true);
+        assert_eq!(pattern.flags, PatternFlags::REPEAT_X);
+    }
+}
@@ -0,0 +1,492 @@
+// pathfinder/content/src/sdf.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Signed distance fields generated from outlines, for baking glyphs and icons that are drawn
+//! with distance field shaders.
+//!
+//! Multi-channel fields use the technique described in Viktor Chlumský, "Shape Decomposition for
+//! Multi-Channel Distance Fields", 2015.
+
+use crate::fill::FillRule;
+use crate::outline::Outline;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use pathfinder_simd::math;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::f32;
+
+// The length, in texels, of the line segments that curves are flattened into.
+const FLATTENING_STEP: f32 = 1.0;
+const MAX_PIECES_PER_CURVE: u32 = 256;
+
+// Edges meeting at an angle sharper than this many radians form a corner, which a multi-channel
+// field keeps sharp.
+const CORNER_ANGLE_THRESHOLD: f32 = 3.0;
+
+const EPSILON: f32 = 0.0001;
+
+// The color channels that an edge contributes to, one bit per channel.
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const YELLOW: u8 = RED | GREEN;
+const MAGENTA: u8 = RED | BLUE;
+const CYAN: u8 = GREEN | BLUE;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+/// How many distances each texel of a signed distance field stores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SDFChannels {
+    /// One byte per texel, holding the distance to the outline, for an `R8` texture.
+    Single,
+    /// Four bytes per texel, for an `RGBA8` texture. The median of the red, green, and blue
+    /// channels is a distance that keeps corners sharp when the field is magnified. Alpha holds
+    /// the same distance as a single-channel field does, for effects like outlines and shadows
+    /// that need the true distance farther from the edge.
+    Multi,
+}
+
+/// How to generate a signed distance field.
+#[derive(Clone, Copy, Debug)]
+pub struct SDFOptions {
+    /// The size of the field, in texels.
+    pub size: Vector2I,
+    /// Maps the outline to texel coordinates, top row first. The center of texel (x, y) is at
+    /// (x + 0.5, y + 0.5).
+    pub transform: Transform2F,
+    /// How far, in texels, the field extends on either side of the outline. Distances of `spread`
+    /// or more outside the outline map to 0, distances on the outline to 128, and distances of
+    /// `spread` or more inside it to 255.
+    pub spread: f32,
+    pub channels: SDFChannels,
+    /// Decides which points are inside the outline.
+    pub fill_rule: FillRule,
+}
+
+/// A signed distance field, ready to be uploaded as a texture.
+#[derive(Clone, Debug)]
+pub struct SignedDistanceField {
+    pub size: Vector2I,
+    pub channels: SDFChannels,
+    /// Texel data, top row first, with no padding between rows.
+    pub texels: Vec<u8>,
+}
+
+impl SDFChannels {
+    #[inline]
+    pub fn bytes_per_texel(self) -> usize {
+        match self {
+            SDFChannels::Single => 1,
+            SDFChannels::Multi => 4,
+        }
+    }
+}
+
+impl SignedDistanceField {
+    /// Generates a signed distance field for the filled outline. Open contours are closed first.
+    ///
+    /// This runs on the CPU, in time proportional to the number of texels times the length of the
+    /// outline, so it's meant for baking fields ahead of time, not for every frame.
+    pub fn from_outline(outline: &Outline, options: &SDFOptions) -> SignedDistanceField {
+        let contours = build_contours(outline, &options.transform);
+        let (width, height) = (options.size.x().max(0), options.size.y().max(0));
+        let bytes_per_texel = options.channels.bytes_per_texel();
+        let spread = options.spread.max(EPSILON);
+        let mut texels = vec![0; width as usize * height as usize * bytes_per_texel];
+
+        let mut crossings = vec![];
+        for y in 0..height {
+            let center_y = y as f32 + 0.5;
+            find_crossings(&contours, center_y, &mut crossings);
+
+            let (mut next_crossing, mut winding) = (0, 0);
+            for x in 0..width {
+                let point = Vector2F::new(x as f32 + 0.5, center_y);
+                while next_crossing < crossings.len() && crossings[next_crossing].0 < point.x() {
+                    winding += crossings[next_crossing].1;
+                    next_crossing += 1;
+                }
+                let inside = match options.fill_rule {
+                    FillRule::Winding => winding != 0,
+                    FillRule::EvenOdd => winding % 2 != 0,
+                };
+
+                let texel_offset = (y as usize * width as usize + x as usize) * bytes_per_texel;
+                let texel = &mut texels[texel_offset..(texel_offset + bytes_per_texel)];
+                let true_distance = true_distance(&contours, point, inside);
+                match options.channels {
+                    SDFChannels::Single => texel[0] = encode_distance(true_distance, spread),
+                    SDFChannels::Multi => {
+                        let distances = channel_distances(&contours, point, inside);
+                        for (channel, &distance) in distances.iter().enumerate() {
+                            texel[channel] = encode_distance(distance, spread);
+                        }
+                        texel[3] = encode_distance(true_distance, spread);
+                    }
+                }
+            }
+        }
+
+        SignedDistanceField {
+            size: Vector2I::new(width, height),
+            channels: options.channels,
+            texels,
+        }
+    }
+}
+
+// A segment of the outline, flattened into a polyline, with the color channels it contributes to.
+struct Edge {
+    points: Vec<Vector2F>,
+    color: u8,
+}
+
+// The distance from a point to an edge, positive to the left of the edge.
+#[derive(Clone, Copy)]
+struct EdgeDistance {
+    distance: f32,
+    // Breaks ties between edges that are the same distance away, as they are near a shared
+    // endpoint. The edge whose direction is the most perpendicular to the point wins.
+    dot: f32,
+    // Negative if the closest point is the start of the edge, and the point is behind it; greater
+    // than 1 if the closest point is the end, and the point is past it; otherwise in between.
+    t: f32,
+}
+
+impl Edge {
+    fn start_direction(&self) -> Vector2F {
+        (self.points[1] - self.points[0]).normalize()
+    }
+
+    fn end_direction(&self) -> Vector2F {
+        let count = self.points.len();
+        (self.points[count - 1] - self.points[count - 2]).normalize()
+    }
+
+    fn distance_to(&self, point: Vector2F) -> EdgeDistance {
+        let last_piece_index = self.points.len() - 2;
+        let mut closest = EdgeDistance { distance: f32::INFINITY, dot: 0.0, t: 0.5 };
+        for (piece_index, piece) in self.points.windows(2).enumerate() {
+            let (from, vector) = (piece[0], piece[1] - piece[0]);
+            let from_to_point = point - from;
+            let t = from_to_point.dot(vector) / vector.square_length();
+            let closest_point = from + vector.scale(t.clamp(0.0, 1.0));
+            let distance = (point - closest_point).length();
+            if distance >= closest.distance.abs() {
+                continue;
+            }
+
+            let sign = if from_to_point.det(vector) < 0.0 { 1.0 } else { -1.0 };
+            let dot = if (t > 0.0 && t < 1.0) || distance < EPSILON {
+                0.0
+            } else {
+                vector.normalize().dot((point - closest_point).normalize()).abs()
+            };
+            let past_end = (piece_index == 0 && t < 0.0) ||
+                (piece_index == last_piece_index && t > 1.0);
+            let t = if past_end { t } else { 0.5 };
+            closest = EdgeDistance { distance: distance * sign, dot, t };
+        }
+        closest
+    }
+
+    // Replaces the distance to an endpoint with the distance to the line extending the edge past
+    // it, if that's closer. Channels that disagree about which edge is closest then meet at a sharp
+    // corner.
+    fn pseudo_distance(&self, point: Vector2F, edge_distance: EdgeDistance) -> f32 {
+        let (endpoint, direction) = if edge_distance.t < 0.0 {
+            (self.points[0], self.start_direction())
+        } else if edge_distance.t > 1.0 {
+            (self.points[self.points.len() - 1], self.end_direction())
+        } else {
+            return edge_distance.distance;
+        };
+
+        let endpoint_to_point = point - endpoint;
+        let along = endpoint_to_point.dot(direction);
+        if (edge_distance.t < 0.0 && along >= 0.0) || (edge_distance.t > 1.0 && along <= 0.0) {
+            return edge_distance.distance;
+        }
+        let pseudo_distance = -endpoint_to_point.det(direction);
+        if pseudo_distance.abs() <= edge_distance.distance.abs() {
+            pseudo_distance
+        } else {
+            edge_distance.distance
+        }
+    }
+}
+
+impl EdgeDistance {
+    fn is_closer_than(&self, other: &EdgeDistance) -> bool {
+        match self.distance.abs().partial_cmp(&other.distance.abs()) {
+            Some(Ordering::Less) => true,
+            Some(Ordering::Equal) => self.dot < other.dot,
+            _ => false,
+        }
+    }
+}
+
+// Transforms the outline, closes its contours, flattens them into edges, and colors the edges.
+fn build_contours(outline: &Outline, transform: &Transform2F) -> Vec<Vec<Edge>> {
+    let mut contours = vec![];
+    for contour in outline.contours() {
+        let mut edges = vec![];
+        for segment in contour.iter() {
+            let segment = segment.transform(transform);
+            let piece_count = if segment.is_line() {
+                1
+            } else {
+                let cubic = segment.to_cubic();
+                let hull_length = (cubic.ctrl.from() - cubic.baseline.from()).length() +
+                    cubic.ctrl.vector().length() +
+                    (cubic.baseline.to() - cubic.ctrl.to()).length();
+                (math::ceil(hull_length / FLATTENING_STEP) as u32).clamp(1, MAX_PIECES_PER_CURVE)
+            };
+
+            let mut points = vec![segment.baseline.from()];
+            for piece_index in 1..=piece_count {
+                let point = segment.sample(piece_index as f32 / piece_count as f32);
+                if (point - *points.last().unwrap()).square_length() > EPSILON * EPSILON {
+                    points.push(point);
+                }
+            }
+            if points.len() > 1 {
+                edges.push(Edge { points, color: WHITE });
+            }
+        }
+
+        if edges.is_empty() {
+            continue;
+        }
+        let first_point = edges[0].points[0];
+        let last_point = *edges.last().unwrap().points.last().unwrap();
+        if (last_point - first_point).square_length() > EPSILON * EPSILON {
+            edges.push(Edge { points: vec![last_point, first_point], color: WHITE });
+        }
+
+        color_edges(&mut edges);
+        contours.push(edges);
+    }
+    contours
+}
+
+// Colors the edges of a closed contour so that the edges on either side of each corner share only
+// one channel. Follows `edgeColoringSimple()` from `msdfgen`.
+fn color_edges(edges: &mut [Edge]) {
+    let edge_count = edges.len();
+    let cross_threshold = math::sin(CORNER_ANGLE_THRESHOLD);
+    let corners: Vec<usize> = (0..edge_count).filter(|&index| {
+        let prev_direction = edges[(index + edge_count - 1) % edge_count].end_direction();
+        let next_direction = edges[index].start_direction();
+        prev_direction.dot(next_direction) <= 0.0 ||
+            prev_direction.det(next_direction).abs() > cross_threshold
+    }).collect();
+
+    match corners.len() {
+        // Smooth contours don't need more than one channel.
+        0 => {}
+        1 => {
+            // A teardrop. Split the edges into thirds around the corner. With fewer than three
+            // edges, the corner isn't kept sharp.
+            if edge_count < 3 {
+                return;
+            }
+            let first_color = switch_color(WHITE, 0);
+            let colors = [first_color, WHITE, switch_color(first_color, 0)];
+            for index in 0..edge_count {
+                let position = index as f32 / (edge_count - 1) as f32;
+                let third = (math::floor(2.875 * position - 1.4375 + 0.5) as i32 + 1).clamp(0, 2);
+                edges[(corners[0] + index) % edge_count].color = colors[third as usize];
+            }
+        }
+        corner_count => {
+            let initial_color = switch_color(WHITE, 0);
+            let (mut color, mut spline) = (initial_color, 0);
+            for offset in 0..edge_count {
+                let index = (corners[0] + offset) % edge_count;
+                if spline + 1 < corner_count && corners[spline + 1] == index {
+                    spline += 1;
+                    let banned = if spline == corner_count - 1 { initial_color } else { 0 };
+                    color = switch_color(color, banned);
+                }
+                edges[index].color = color;
+            }
+        }
+    }
+}
+
+// Picks the next of cyan, magenta, and yellow, avoiding the channels in `banned` if possible.
+fn switch_color(color: u8, banned: u8) -> u8 {
+    let combined = color & banned;
+    if combined == RED || combined == GREEN || combined == BLUE {
+        return combined ^ WHITE;
+    }
+    match color {
+        CYAN => MAGENTA,
+        MAGENTA => YELLOW,
+        YELLOW => CYAN,
+        _ => CYAN,
+    }
+}
+
+// Finds where the contours cross the horizontal line at `y`, with their winding, sorted by `x`.
+fn find_crossings(contours: &[Vec<Edge>], y: f32, crossings: &mut Vec<(f32, i32)>) {
+    crossings.clear();
+    for edge in contours.iter().flat_map(|edges| edges.iter()) {
+        for piece in edge.points.windows(2) {
+            let (from, to) = (piece[0], piece[1]);
+            let winding = if from.y() <= y && to.y() > y {
+                1
+            } else if to.y() <= y && from.y() > y {
+                -1
+            } else {
+                continue;
+            };
+            let x = from.x() + (y - from.y()) * (to.x() - from.x()) / (to.y() - from.y());
+            crossings.push((x, winding));
+        }
+    }
+    crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+}
+
+// The distance to the closest edge, positive inside the outline.
+fn true_distance(contours: &[Vec<Edge>], point: Vector2F, inside: bool) -> f32 {
+    let distance = contours.iter().flat_map(|edges| edges.iter()).fold(f32::INFINITY, |min, edge| {
+        min.min(edge.distance_to(point).distance.abs())
+    });
+    if inside { distance } else { -distance }
+}
+
+// The red, green, and blue distances of a multi-channel field.
+fn channel_distances(contours: &[Vec<Edge>], point: Vector2F, inside: bool) -> [f32; 3] {
+    let mut closest: [Option<(&Edge, EdgeDistance)>; 3] = [None, None, None];
+    for edge in contours.iter().flat_map(|edges| edges.iter()) {
+        let edge_distance = edge.distance_to(point);
+        for (channel, closest) in closest.iter_mut().enumerate() {
+            if edge.color & (1 << channel) == 0 {
+                continue;
+            }
+            match *closest {
+                Some((_, ref closest_distance)) if
+                    !edge_distance.is_closer_than(closest_distance) => {}
+                _ => *closest = Some((edge, edge_distance)),
+            }
+        }
+    }
+
+    let mut distances = [f32::NEG_INFINITY; 3];
+    for (distance, closest) in distances.iter_mut().zip(closest.iter()) {
+        if let Some((edge, edge_distance)) = *closest {
+            *distance = edge.pseudo_distance(point, edge_distance);
+        }
+    }
+
+    // Each edge's sign only depends on which side of it the point is on, which is wrong for
+    // contours wound the other way and where contours overlap. Trust the fill rule instead.
+    let median = median(distances[0], distances[1], distances[2]);
+    if median != 0.0 && (median > 0.0) != inside {
+        for distance in &mut distances {
+            *distance = -*distance;
+        }
+    }
+    distances
+}
+
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+fn encode_distance(distance: f32, spread: f32) -> u8 {
+    let value = 0.5 + distance / (2.0 * spread);
+    math::round(value.clamp(0.0, 1.0) * 255.0) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SDFChannels, SDFOptions, SignedDistanceField, median};
+    use crate::fill::FillRule;
+    use crate::outline::{Contour, Outline};
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+
+    fn square(origin: Vector2F, size: f32) -> Outline {
+        let mut contour = Contour::new();
+        contour.push_endpoint(origin);
+        contour.push_endpoint(origin + Vector2F::new(size, 0.0));
+        contour.push_endpoint(origin + Vector2F::new(size, size));
+        contour.push_endpoint(origin + Vector2F::new(0.0, size));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        outline
+    }
+
+    fn options(channels: SDFChannels) -> SDFOptions {
+        SDFOptions {
+            size: Vector2I::splat(32),
+            transform: Transform2F::default(),
+            spread: 4.0,
+            channels,
+            fill_rule: FillRule::Winding,
+        }
+    }
+
+    #[test]
+    fn test_single_channel_distances() {
+        let outline = square(Vector2F::splat(8.0), 16.0);
+        let field = SignedDistanceField::from_outline(&outline, &options(SDFChannels::Single));
+        assert_eq!(field.texels.len(), 32 * 32);
+        let texel = |x: usize, y: usize| field.texels[y * 32 + x];
+
+        // Deep inside and far outside saturate.
+        assert_eq!(texel(16, 16), 255);
+        assert_eq!(texel(0, 0), 0);
+        // Half a texel inside and outside the left edge.
+        assert_eq!(texel(8, 16), 143);
+        assert_eq!(texel(7, 16), 112);
+        // Texels diagonally outside a corner are measured to the corner.
+        assert_eq!(texel(6, 6), 60);
+    }
+
+    #[test]
+    fn test_multi_channel_matches_single_channel_inside_and_outside() {
+        // Wound both ways, so that the fill rule has to fix the edges' signs for one of them.
+        let mut outline = square(Vector2F::splat(4.0), 12.0);
+        let mut reversed = Contour::new();
+        for &(x, y) in &[(18.0, 18.0), (18.0, 28.0), (28.0, 28.0), (28.0, 18.0)] {
+            reversed.push_endpoint(Vector2F::new(x, y));
+        }
+        reversed.close();
+        outline.push_contour(reversed);
+
+        let single = SignedDistanceField::from_outline(&outline, &options(SDFChannels::Single));
+        let multi = SignedDistanceField::from_outline(&outline, &options(SDFChannels::Multi));
+        assert_eq!(multi.texels.len(), 32 * 32 * 4);
+        for (index, texel) in multi.texels.chunks(4).enumerate() {
+            assert_eq!(texel[3], single.texels[index]);
+            let median = median(texel[0] as f32, texel[1] as f32, texel[2] as f32);
+            assert_eq!(median > 127.5, single.texels[index] > 127, "texel {}", index);
+        }
+    }
+
+    #[test]
+    fn test_multi_channel_keeps_corners_sharp() {
+        // Just outside the corner, along its diagonal, the median is the distance to the nearer
+        // edge's line rather than to the corner, so the corner stays square when magnified.
+        let outline = square(Vector2F::splat(8.0), 16.0);
+        let field = SignedDistanceField::from_outline(&outline, &options(SDFChannels::Multi));
+        let texel = &field.texels[(6 * 32 + 6) * 4..(6 * 32 + 6) * 4 + 4];
+        let median = median(texel[0] as f32, texel[1] as f32, texel[2] as f32);
+        assert_eq!(median, 80.0);
+        assert_eq!(texel[3], 60);
+    }
+}
@@ -15,7 +15,8 @@ use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::util::{self, EPSILON};
 use pathfinder_geometry::vector::Vector2F;
 use pathfinder_simd::default::F32x4;
-use std::f32::consts::SQRT_2;
+use pathfinder_simd::math;
+use core::f32::consts::SQRT_2;
 
 const MAX_NEWTON_ITERATIONS: u32 = 32;
 
@@ -72,7 +73,7 @@ impl Segment {
     ///
     /// The maximum supported sweep angle is π/2 (i.e. 90°).
     pub fn arc(sweep_angle: f32) -> Segment {
-        Segment::arc_from_cos(f32::cos(sweep_angle))
+        Segment::arc_from_cos(math::cos(sweep_angle))
     }
 
     /// Approximates an unit-length arc with a cubic Bézier curve, given the cosine of the sweep
@@ -130,7 +131,7 @@ impl Segment {
     }
 
     #[inline]
-    pub fn as_cubic_segment(&self) -> CubicSegment {
+    pub fn as_cubic_segment(&self) -> CubicSegment<'_> {
         debug_assert!(self.is_cubic());
         CubicSegment(self)
     }
@@ -384,7 +385,7 @@ impl<'s> CubicSegment<'s> {
             debug!("...  t=(newton) {}", t);
         } else {
             // Algebraically compute the values for t.
-            let discrim = f32::sqrt(v1 * v1 - v0 * v2);
+            let discrim = math::sqrt(v1 * v1 - v0 * v2);
             let denom_recip = 1.0 / denom;
 
             t0 = (v0_to_v1 + discrim) * denom_recip;
@@ -10,8 +10,10 @@
 
 //! A vector that maintains sorted order with insertion sort.
 
-use std::cmp::Ordering;
-use std::convert;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert;
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct SortedVector<T>
@@ -16,17 +16,26 @@ use pathfinder_geometry::line_segment::LineSegment2F;
 use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::Vector2F;
-use std::f32;
+use pathfinder_simd::math;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32;
+
+#[cfg(feature = "pf-serde")]
+use serde::{Deserialize, Serialize};
 
 const TOLERANCE: f32 = 0.01;
+const EPSILON: f32 = 1.0e-6;
 
 pub struct OutlineStrokeToFill<'a> {
     input: &'a Outline,
     output: Outline,
     style: StrokeStyle,
+    end_cap: LineCap,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "pf-serde", derive(Serialize, Deserialize))]
 pub struct StrokeStyle {
     pub line_width: f32,
     pub line_cap: LineCap,
@@ -34,6 +43,7 @@ pub struct StrokeStyle {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "pf-serde", derive(Serialize, Deserialize))]
 pub enum LineCap {
     Butt,
     Square,
@@ -41,6 +51,7 @@ pub enum LineCap {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "pf-serde", derive(Serialize, Deserialize))]
 pub enum LineJoin {
     Miter(f32),
     Bevel,
@@ -49,8 +60,15 @@ pub enum LineJoin {
 
 impl<'a> OutlineStrokeToFill<'a> {
     #[inline]
-    pub fn new(input: &Outline, style: StrokeStyle) -> OutlineStrokeToFill {
-        OutlineStrokeToFill { input, output: Outline::new(), style }
+    pub fn new(input: &Outline, style: StrokeStyle) -> OutlineStrokeToFill<'_> {
+        OutlineStrokeToFill { input, output: Outline::new(), style, end_cap: style.line_cap }
+    }
+
+    /// Uses a different cap at the ends of open contours than at their starts, which keep the
+    /// style's `line_cap`. Flash, for one, lets the two differ.
+    #[inline]
+    pub fn set_end_cap(&mut self, end_cap: LineCap) {
+        self.end_cap = end_cap;
     }
 
     pub fn offset(&mut self) {
@@ -70,12 +88,12 @@ impl<'a> OutlineStrokeToFill<'a> {
                                                    self.style.line_width * 0.5,
                                                    self.style.line_join);
             } else {
-                self.add_cap(&mut stroker.output);
+                self.add_cap(&mut stroker.output, self.end_cap);
             }
 
             stroker.offset_backward();
             if !closed {
-                self.add_cap(&mut stroker.output);
+                self.add_cap(&mut stroker.output, self.style.line_cap);
             }
 
             self.push_stroked_contour(&mut new_contours, stroker, closed);
@@ -111,8 +129,8 @@ impl<'a> OutlineStrokeToFill<'a> {
         new_contours.push(stroker.output);
     }
 
-    fn add_cap(&mut self, contour: &mut Contour) {
-        if self.style.line_cap == LineCap::Butt || contour.len() < 2 {
+    fn add_cap(&mut self, contour: &mut Contour, line_cap: LineCap) {
+        if line_cap == LineCap::Butt || contour.len() < 2 {
             return
         }
 
@@ -120,7 +138,7 @@ impl<'a> OutlineStrokeToFill<'a> {
         let (p0, p1) = (contour.position_of_last(2), contour.position_of_last(1));
         let gradient = (p1 - p0).normalize();
 
-        match self.style.line_cap {
+        match line_cap {
             LineCap::Butt => unreachable!(),
 
             LineCap::Square => {
@@ -156,7 +174,8 @@ struct ContourStrokeToFill<'a> {
 
 impl<'a> ContourStrokeToFill<'a> {
     #[inline]
-    fn new(input: &Contour, output: Contour, radius: f32, join: LineJoin) -> ContourStrokeToFill {
+    fn new(input: &Contour, output: Contour, radius: f32, join: LineJoin)
+           -> ContourStrokeToFill<'_> {
         ContourStrokeToFill { input, output, radius, join }
     }
 
@@ -383,6 +402,130 @@ impl Contour {
     }
 }
 
+impl Outline {
+    /// Returns a rectangle that encloses this outline as stroked with the given style.
+    ///
+    /// This accounts for the line width, miter joins, and square caps, but doesn't perform
+    /// stroke-to-fill conversion, so it's cheap enough for culling and invalidation. Like
+    /// `bounds()`, the result is conservative: it may be larger than the stroke, but never smaller.
+    pub fn stroke_bounds(&self, style: &StrokeStyle) -> RectF {
+        let radius = style.line_width * 0.5;
+        let mut bounds: Option<RectF> = None;
+        for contour in &self.contours {
+            if contour.is_empty() {
+                continue;
+            }
+
+            // The stroke lies within `radius` of the curve, which in turn lies within the hull of
+            // its control points. This covers round joins and caps, bevels, and butt caps.
+            let mut contour_bounds = contour.bounds().dilate(Vector2F::splat(radius));
+
+            for point_index in 0..contour.len() {
+                if !contour.point_is_endpoint(point_index) {
+                    continue;
+                }
+
+                let (tangent_in, tangent_out) = (contour.tangent_into(point_index),
+                                                 contour.tangent_out_of(point_index));
+                let point = contour.position_of(point_index);
+                match (tangent_in, tangent_out) {
+                    (Some(tangent_in), Some(tangent_out)) => {
+                        if let LineJoin::Miter(miter_limit) = style.line_join {
+                            if let Some(tip) = miter_tip(point,
+                                                         tangent_in,
+                                                         tangent_out,
+                                                         radius,
+                                                         miter_limit) {
+                                contour_bounds = contour_bounds.union_point(tip);
+                            }
+                        }
+                    }
+                    (Some(tangent), None) | (None, Some(tangent)) => {
+                        if style.line_cap == LineCap::Square {
+                            // Extend the cap away from the contour.
+                            let gradient = if tangent_out.is_some() { -tangent } else { tangent };
+                            let (along, across) = (gradient.scale(radius),
+                                                   gradient.yx().scale_xy(Vector2F::new(-radius,
+                                                                                        radius)));
+                            contour_bounds = contour_bounds.union_point(point + along + across)
+                                                           .union_point(point + along - across);
+                        }
+                    }
+                    (None, None) => {}
+                }
+            }
+
+            bounds = Some(match bounds {
+                None => contour_bounds,
+                Some(bounds) => bounds.union_rect(contour_bounds),
+            });
+        }
+        bounds.unwrap_or_default()
+    }
+}
+
+impl Contour {
+    // Returns the unit direction in which the contour arrives at the given endpoint, or `None` if
+    // it starts there.
+    fn tangent_into(&self, point_index: u32) -> Option<Vector2F> {
+        if point_index == 0 && !self.closed {
+            return None;
+        }
+        let point = self.position_of(point_index);
+        let mut other_index = point_index;
+        for _ in 1..self.len() {
+            other_index = self.prev_point_index_of(other_index);
+            let vector = point - self.position_of(other_index);
+            if vector.square_length() > EPSILON {
+                return Some(vector.normalize());
+            }
+            if other_index == 0 && !self.closed {
+                break;
+            }
+        }
+        None
+    }
+
+    // Returns the unit direction in which the contour leaves the given endpoint, or `None` if it
+    // ends there.
+    fn tangent_out_of(&self, point_index: u32) -> Option<Vector2F> {
+        if point_index == self.len() - 1 && !self.closed {
+            return None;
+        }
+        let point = self.position_of(point_index);
+        let mut other_index = point_index;
+        for _ in 1..self.len() {
+            other_index = self.next_point_index_of(other_index);
+            let vector = self.position_of(other_index) - point;
+            if vector.square_length() > EPSILON {
+                return Some(vector.normalize());
+            }
+            if other_index == self.len() - 1 && !self.closed {
+                break;
+            }
+        }
+        None
+    }
+}
+
+// Returns the outer corner of a miter join, or `None` if the join is beveled because the miter
+// would exceed the limit. This mirrors the test in `Contour::add_join()`.
+fn miter_tip(point: Vector2F,
+             tangent_in: Vector2F,
+             tangent_out: Vector2F,
+             radius: f32,
+             miter_limit: f32)
+             -> Option<Vector2F> {
+    // The miter extends `radius / cos(φ / 2)` from the join point along the bisector, where φ is
+    // the angle that the contour turns through.
+    let cos_half_turn = math::sqrt(f32::max(0.0, (1.0 + tangent_in.dot(tangent_out)) * 0.5));
+    let bisector = tangent_in - tangent_out;
+    if cos_half_turn * miter_limit < 1.0 || bisector.square_length() <= EPSILON {
+        return None;
+    }
+    Some(point + bisector.normalize().scale(radius / cos_half_turn))
+}
+
 impl Default for StrokeStyle {
     #[inline]
     fn default() -> StrokeStyle {
@@ -403,3 +546,83 @@ impl Default for LineJoin {
     #[inline]
     fn default() -> LineJoin { LineJoin::Miter(10.0) }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{LineCap, LineJoin, OutlineStrokeToFill, StrokeStyle};
+    use crate::outline::{Contour, Outline};
+    use pathfinder_geometry::vector::Vector2F;
+
+    fn stroke(outline: &Outline, style: StrokeStyle) -> Outline {
+        let mut stroke_to_fill = OutlineStrokeToFill::new(outline, style);
+        stroke_to_fill.offset();
+        stroke_to_fill.into_outline()
+    }
+
+    #[test]
+    fn test_stroke_bounds_contain_stroke() {
+        // A sharp zigzag, so that miters and caps both poke out past the dilated bounds.
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(0.0, 0.0));
+        contour.push_endpoint(Vector2F::new(10.0, 100.0));
+        contour.push_endpoint(Vector2F::new(20.0, 0.0));
+        contour.push_endpoint(Vector2F::new(50.0, 10.0));
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        for &line_cap in &[LineCap::Butt, LineCap::Square, LineCap::Round] {
+            for &line_join in &[LineJoin::Miter(10.0), LineJoin::Miter(20.0), LineJoin::Bevel,
+                                LineJoin::Round] {
+                let style = StrokeStyle { line_width: 8.0, line_cap, line_join };
+                // The stroked outline's own bounds enclose control points, so sample the curves
+                // instead.
+                let stroke_bounds = outline.stroke_bounds(&style).dilate(Vector2F::splat(0.01));
+                for contour in stroke(&outline, style).contours() {
+                    for segment in contour.iter() {
+                        for step in 0..=8 {
+                            let point = segment.sample(step as f32 / 8.0);
+                            assert!(stroke_bounds.contains_point(point),
+                                    "{:?} doesn't contain {:?} for {:?}",
+                                    stroke_bounds,
+                                    point,
+                                    style);
+                        }
+                    }
+                }
+            }
+        }
+
+        // The miter at the top is about 40 units long. It's only drawn if the limit allows it.
+        let mut style = StrokeStyle {
+            line_width: 8.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter(20.0),
+        };
+        assert!(outline.stroke_bounds(&style).max_y() > 140.0);
+        style.line_join = LineJoin::Miter(10.0);
+        assert!(outline.stroke_bounds(&style).max_y() < 105.0);
+    }
+
+    #[test]
+    fn test_stroke_with_different_end_cap() {
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(0.0, 0.0));
+        contour.push_endpoint(Vector2F::new(100.0, 0.0));
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        let style = StrokeStyle {
+            line_width: 10.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Bevel,
+        };
+        let mut stroke_to_fill = OutlineStrokeToFill::new(&outline, style);
+        stroke_to_fill.set_end_cap(LineCap::Square);
+        stroke_to_fill.offset();
+        let bounds = stroke_to_fill.into_outline().bounds();
+
+        // Only the end of the line is extended by the square cap.
+        assert!((bounds.min_x() - 0.0).abs() < 0.01);
+        assert!((bounds.max_x() - 105.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,320 @@
+// pathfinder/content/src/tessellation.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Tessellation of filled outlines into triangle meshes, for rendering them with other pipelines.
+
+use crate::fill::FillRule;
+use crate::outline::Outline;
+use crate::segment::Segment;
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::vector::Vector2F;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// Curves are split in half at most this many times, so that tiny tolerances can't blow up.
+const MAX_FLATTENING_DEPTH: u32 = 16;
+
+/// An indexed triangle mesh covering the filled area of an outline.
+#[derive(Clone, Debug, Default)]
+pub struct TriangleMesh {
+    pub vertices: Vec<Vector2F>,
+    /// Three indices into `vertices` per triangle. Triangles are clockwise with Y pointing down,
+    /// as in the outline's coordinate space.
+    pub indices: Vec<u32>,
+}
+
+impl TriangleMesh {
+    /// Tessellates the area inside the outline according to the fill rule. Open contours are
+    /// closed first.
+    ///
+    /// Curves are flattened so that they're no further than `tolerance` from the mesh's edges.
+    /// Self-intersecting and overlapping contours are handled by splitting the outline into
+    /// horizontal bands at every vertex and crossing, and emitting two triangles for each span of
+    /// each band that's inside. The mesh has no overlapping triangles, but it isn't minimal.
+    pub fn from_outline(outline: &Outline, fill_rule: FillRule, tolerance: f32) -> TriangleMesh {
+        let mut edges = flatten(outline, tolerance);
+        edges.sort_by(|a, b| cmp_f32(a.line.from_y(), b.line.from_y()));
+
+        let mut ys: Vec<f32> = edges.iter()
+                                    .flat_map(|edge| vec![edge.line.from_y(), edge.line.to_y()])
+                                    .collect();
+        ys.sort_by(|a, b| cmp_f32(*a, *b));
+        ys.dedup();
+
+        let mut builder = MeshBuilder::new();
+        let (mut active_edges, mut next_edge_index): (Vec<FlatEdge>, usize) = (vec![], 0);
+        for band in ys.windows(2) {
+            let (band_top, band_bottom) = (band[0], band[1]);
+            active_edges.retain(|edge| edge.line.to_y() > band_top);
+            while next_edge_index < edges.len() &&
+                    edges[next_edge_index].line.from_y() <= band_top {
+                active_edges.push(edges[next_edge_index]);
+                next_edge_index += 1;
+            }
+
+            // Split the band where edges cross, so that edges keep their order within each part.
+            let mut top = band_top;
+            while top < band_bottom {
+                let bottom = next_crossing(&mut active_edges, top, band_bottom);
+                builder.fill_band(&mut active_edges, top, bottom, fill_rule);
+                top = bottom;
+            }
+        }
+
+        builder.mesh
+    }
+}
+
+// A line of the flattened outline, pointing down, with the winding of the original direction.
+#[derive(Clone, Copy, Debug)]
+struct FlatEdge {
+    line: LineSegment2F,
+    winding: i32,
+}
+
+impl FlatEdge {
+    fn x_at(&self, y: f32) -> f32 {
+        // Return endpoints exactly, so that edges that meet there share vertices.
+        if y <= self.line.from_y() {
+            self.line.from_x()
+        } else if y >= self.line.to_y() {
+            self.line.to_x()
+        } else {
+            self.line.solve_x_for_y(y)
+        }
+    }
+}
+
+fn flatten(outline: &Outline, tolerance: f32) -> Vec<FlatEdge> {
+    let mut edges = vec![];
+    for contour in outline.contours() {
+        if contour.is_empty() {
+            continue;
+        }
+        for segment in contour.iter() {
+            flatten_segment(&segment, tolerance, 0, &mut edges);
+        }
+        // Fills close open contours.
+        if !contour.is_closed() {
+            let closing_line = LineSegment2F::new(contour.last_position().unwrap(),
+                                                  contour.position_of(0));
+            push_line(closing_line, &mut edges);
+        }
+    }
+    edges
+}
+
+fn flatten_segment(segment: &Segment, tolerance: f32, depth: u32, edges: &mut Vec<FlatEdge>) {
+    if segment.is_line() {
+        push_line(segment.baseline, edges);
+        return;
+    }
+
+    let segment = segment.to_cubic();
+    if depth == MAX_FLATTENING_DEPTH || segment.as_cubic_segment().is_flat(tolerance) {
+        push_line(segment.baseline, edges);
+        return;
+    }
+    let (before, after) = segment.as_cubic_segment().split(0.5);
+    flatten_segment(&before, tolerance, depth + 1, edges);
+    flatten_segment(&after, tolerance, depth + 1, edges);
+}
+
+fn push_line(line: LineSegment2F, edges: &mut Vec<FlatEdge>) {
+    // Horizontal lines don't bound any spans.
+    let (from, to) = (line.from(), line.to());
+    if from.y() == to.y() || !from.x().is_finite() || !from.y().is_finite() ||
+            !to.x().is_finite() || !to.y().is_finite() {
+        return;
+    }
+    let winding = line.y_winding();
+    edges.push(FlatEdge { line: line.orient(winding), winding });
+}
+
+// Sorts the edges by their position at `top` and returns the first place between `top` and
+// `bottom` where two of them cross, or `bottom` if none do.
+fn next_crossing(edges: &mut [FlatEdge], top: f32, bottom: f32) -> f32 {
+    edges.sort_by(|a, b| {
+        cmp_f32(a.x_at(top), b.x_at(top)).then_with(|| cmp_f32(a.x_at(bottom), b.x_at(bottom)))
+    });
+
+    // If any edges cross, the first crossing is between edges that are next to each other.
+    let mut crossing_y = bottom;
+    for pair in edges.windows(2) {
+        if pair[0].x_at(bottom) <= pair[1].x_at(bottom) {
+            continue;
+        }
+        // The edges are straight within the band, so the gap between them is linear in y.
+        let top_gap = pair[1].x_at(top) - pair[0].x_at(top);
+        let bottom_gap = pair[0].x_at(bottom) - pair[1].x_at(bottom);
+        let y = top + (bottom - top) * top_gap / (top_gap + bottom_gap);
+        // Crossings that rounding puts at or above the top would never end.
+        if y > top && y < crossing_y {
+            crossing_y = y;
+        }
+    }
+    crossing_y
+}
+
+struct MeshBuilder {
+    mesh: TriangleMesh,
+    vertex_indices: HashMap<(u32, u32), u32>,
+}
+
+impl MeshBuilder {
+    fn new() -> MeshBuilder {
+        MeshBuilder { mesh: TriangleMesh::default(), vertex_indices: HashMap::new() }
+    }
+
+    // Emits the spans between `top` and `bottom` that are inside. No edges cross in between.
+    fn fill_band(&mut self, edges: &mut [FlatEdge], top: f32, bottom: f32, fill_rule: FillRule) {
+        let middle = 0.5 * (top + bottom);
+        edges.sort_by(|a, b| cmp_f32(a.x_at(middle), b.x_at(middle)));
+
+        let mut winding = 0;
+        for pair in edges.windows(2) {
+            winding += pair[0].winding;
+            let inside = match fill_rule {
+                FillRule::Winding => winding != 0,
+                FillRule::EvenOdd => winding % 2 != 0,
+            };
+            if !inside {
+                continue;
+            }
+
+            let (left, right) = (pair[0], pair[1]);
+            let upper_left = self.vertex(Vector2F::new(left.x_at(top), top));
+            let upper_right = self.vertex(Vector2F::new(right.x_at(top), top));
+            let lower_right = self.vertex(Vector2F::new(right.x_at(bottom), bottom));
+            let lower_left = self.vertex(Vector2F::new(left.x_at(bottom), bottom));
+            self.triangle(upper_left, upper_right, lower_right);
+            self.triangle(upper_left, lower_right, lower_left);
+        }
+    }
+
+    fn vertex(&mut self, position: Vector2F) -> u32 {
+        let vertices = &mut self.mesh.vertices;
+        *self.vertex_indices
+             .entry((position.x().to_bits(), position.y().to_bits()))
+             .or_insert_with(|| {
+                 vertices.push(position);
+                 vertices.len() as u32 - 1
+             })
+    }
+
+    // Drops degenerate triangles, along with slivers that rounding turns inside out, which happens
+    // where nearly horizontal edges cross others.
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        let vertices = &self.mesh.vertices;
+        let (a_position, b_position, c_position) =
+            (vertices[a as usize], vertices[b as usize], vertices[c as usize]);
+        if (b_position - a_position).det(c_position - a_position) > 0.0 {
+            self.mesh.indices.extend_from_slice(&[a, b, c]);
+        }
+    }
+}
+
+fn cmp_f32(a: f32, b: f32) -> Ordering {
+    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod test {
+    use super::TriangleMesh;
+    use crate::fill::FillRule;
+    use crate::outline::{Contour, Outline};
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::Vector2F;
+    use std::f32::consts::PI;
+
+    fn polygon(outline: &mut Outline, points: &[(f32, f32)]) {
+        let mut contour = Contour::new();
+        for &(x, y) in points {
+            contour.push_endpoint(Vector2F::new(x, y));
+        }
+        contour.close();
+        outline.push_contour(contour);
+    }
+
+    // Returns the total area, checking that every triangle is clockwise.
+    fn area(mesh: &TriangleMesh) -> f32 {
+        mesh.indices.chunks(3).map(|triangle| {
+            let a = mesh.vertices[triangle[0] as usize];
+            let b = mesh.vertices[triangle[1] as usize];
+            let c = mesh.vertices[triangle[2] as usize];
+            let area = 0.5 * (b - a).det(c - a);
+            assert!(area >= 0.0, "{:?} isn't clockwise", (a, b, c));
+            area
+        }).sum()
+    }
+
+    #[test]
+    fn test_fill_rules() {
+        // Two overlapping squares wound the same way.
+        let mut outline = Outline::new();
+        polygon(&mut outline, &[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        polygon(&mut outline, &[(5.0, 5.0), (15.0, 5.0), (15.0, 15.0), (5.0, 15.0)]);
+
+        let winding = TriangleMesh::from_outline(&outline, FillRule::Winding, 0.25);
+        assert_eq!(area(&winding), 175.0);
+        let even_odd = TriangleMesh::from_outline(&outline, FillRule::EvenOdd, 0.25);
+        assert_eq!(area(&even_odd), 150.0);
+
+        // Corners shared by neighboring triangles are shared vertices.
+        let mut square = Outline::new();
+        polygon(&mut square, &[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        let mesh = TriangleMesh::from_outline(&square, FillRule::Winding, 0.25);
+        assert_eq!((mesh.vertices.len(), mesh.indices.len()), (4, 6));
+    }
+
+    #[test]
+    fn test_self_intersecting_star() {
+        // A pentagram, whose inner pentagon has a winding number of 2.
+        let points: Vec<(f32, f32)> = (0..5).map(|index| {
+            let angle = (index * 2) as f32 * 2.0 * PI / 5.0;
+            (angle.sin() * 10.0, -angle.cos() * 10.0)
+        }).collect();
+        let mut outline = Outline::new();
+        polygon(&mut outline, &points);
+
+        let winding_area = area(&TriangleMesh::from_outline(&outline, FillRule::Winding, 0.25));
+        let even_odd_area = area(&TriangleMesh::from_outline(&outline, FillRule::EvenOdd, 0.25));
+        // The inner pentagon has a circumradius of about 3.82.
+        let inner_radius = 10.0 * (0.4 * PI).cos() / (0.2 * PI).cos();
+        let inner_area = 2.5 * inner_radius * inner_radius * (0.4 * PI).sin();
+        assert!((winding_area - even_odd_area - inner_area).abs() < 0.01,
+                "{} - {} != {}",
+                winding_area,
+                even_odd_area,
+                inner_area);
+    }
+
+    #[test]
+    fn test_curves_are_flattened_within_tolerance() {
+        let mut contour = Contour::new();
+        contour.push_ellipse(&Transform2F::from_scale(Vector2F::splat(100.0)));
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        let mesh = TriangleMesh::from_outline(&outline, FillRule::Winding, 0.1);
+        let circle_area = PI * 100.0 * 100.0;
+        // Every flattened point lies on the circle, so the mesh is slightly smaller, by at most the
+        // tolerance times the circumference.
+        let mesh_area = area(&mesh);
+        assert!(mesh_area < circle_area && mesh_area > circle_area - 0.1 * 2.0 * PI * 100.0,
+                "{} vs. {}",
+                mesh_area,
+                circle_area);
+        for vertex in &mesh.vertices {
+            // `push_ellipse()` approximates the circle with cubics, which bulge out a little.
+            assert!((vertex.length() - 100.0).abs() < 0.05);
+        }
+    }
+}
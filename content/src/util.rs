@@ -11,27 +11,29 @@
 //! Miscellaneous utilities.
 
 use pathfinder_geometry::line_segment::LineSegment2F;
+#[cfg(feature = "pf-std")]
 use pathfinder_geometry::transform2d::Transform2F;
-use pathfinder_simd::default::{F32x2, F32x4};
-use std::hash::{Hash, Hasher};
-use std::mem;
+use pathfinder_simd::default::F32x4;
+#[cfg(feature = "pf-std")]
+use pathfinder_simd::default::F32x2;
+use core::hash::{Hash, Hasher};
+use core::mem;
 
 pub(crate) fn hash_line_segment<H>(line_segment: LineSegment2F, state: &mut H) where H: Hasher {
     hash_f32x4(line_segment.0, state);
 }
 
+#[cfg(feature = "pf-std")]
 pub(crate) fn hash_transform2f<H>(transform: Transform2F, state: &mut H) where H: Hasher {
     hash_f32x4(transform.matrix.0, state);
     hash_f32x2(transform.vector.0, state);
 }
 
 pub(crate) fn hash_f32<H>(value: f32, state: &mut H) where H: Hasher {
-    unsafe {
-        let data: u32 = mem::transmute::<f32, u32>(value);
-        data.hash(state);
-    }
+    value.to_bits().hash(state);
 }
 
+#[cfg(feature = "pf-std")]
 pub(crate) fn hash_f32x2<H>(vector: F32x2, state: &mut H) where H: Hasher {
     unsafe {
         let data: [u32; 2] = mem::transmute::<F32x2, [u32; 2]>(vector);
@@ -0,0 +1,34 @@
+// pathfinder/content/tests/no_std.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checks that the crate still builds without `std`.
+//!
+//! Features are unified across a test run, so this can't be checked in-process: it runs a separate
+//! `cargo build` with `--no-default-features --features pf-libm`.
+
+use std::env;
+use std::process::Command;
+
+#[test]
+fn test_builds_without_std() {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    // Build into a separate target directory so that the build doesn't wait on the lock that the
+    // outer `cargo test` holds, and so that the two feature sets don't invalidate each other.
+    let target_dir = env::temp_dir().join("pathfinder_content_no_std");
+    let mut command = Command::new(env!("CARGO"));
+    command.args(["build", "--no-default-features", "--features", "pf-libm", "--manifest-path"])
+           .arg(format!("{}/Cargo.toml", manifest_dir))
+           .env("CARGO_TARGET_DIR", &target_dir)
+           .env("RUSTFLAGS", "-D warnings");
+    let output = command.output().expect("failed to run cargo");
+    assert!(output.status.success(),
+            "the no_std build failed:\n{}",
+            String::from_utf8_lossy(&output.stderr));
+}
@@ -19,6 +19,7 @@ pub use crate::camera::Mode;
 use crate::camera::Camera;
 use crate::concurrent::DemoExecutor;
 use crate::device::{GroundProgram, GroundVertexArray};
+use crate::recording::FrameRecorder;
 use crate::ui::{DemoUIModel, DemoUIPresenter, ScreenshotInfo, ScreenshotType, UIAction};
 use crate::window::{Event, Keycode, SVGPath, Window, WindowSize};
 use clap::{App, Arg};
@@ -31,19 +32,24 @@ use pathfinder_geometry::transform3d::Transform4F;
 use pathfinder_geometry::vector::{Vector2F, Vector2I, Vector4F};
 use pathfinder_gpu::Device;
 use pathfinder_renderer::concurrent::scene_proxy::{RenderCommandStream, SceneProxy};
+use pathfinder_renderer::gpu::inspector::SceneInspector;
 use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererOptions};
-use pathfinder_renderer::gpu::renderer::{RenderStats, RenderTime, Renderer};
+use pathfinder_renderer::gpu::renderer::{Readback, RenderStats, RenderTime, Renderer};
 use pathfinder_renderer::options::{BuildOptions, RenderTransform};
 use pathfinder_renderer::scene::{RenderTarget, Scene};
 use pathfinder_resources::ResourceLoader;
 use pathfinder_svg::BuiltSVG;
+use pathfinder_svg::text;
 use pathfinder_ui::{MousePosition, UIEvent};
-use std::fs::File;
+use std::collections::VecDeque;
+use std::fs::{self, File};
 use std::io::{BufWriter, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::thread;
-use std::time::Duration;
-use usvg::{Options as UsvgOptions, Tree};
+use std::time::{Duration, SystemTime};
+use usvg::Tree;
 
 #[cfg(any(not(target_os = "macos"), feature = "pf-gl"))]
 use pathfinder_gl::GLDevice as DeviceImpl;
@@ -67,11 +73,19 @@ const APPROX_FONT_SIZE: f32 = 16.0;
 
 const MESSAGE_TIMEOUT_SECS: u64 = 5;
 
+// How often the SVG file being displayed is checked for changes.
+const SVG_WATCH_INTERVAL_MILLIS: u64 = 500;
+
+// Where frames are recorded if the recording hotkey is pressed and no path was given.
+static DEFAULT_RECORDING_PATH: &'static str = "pathfinder-recording";
+const DEFAULT_RECORDING_FPS: u32 = 60;
+
 pub mod window;
 
 mod camera;
 mod concurrent;
 mod device;
+mod recording;
 mod renderer;
 mod ui;
 
@@ -82,6 +96,7 @@ pub struct DemoApp<W> where W: Window {
 
     window_size: WindowSize,
 
+    svg_path: SVGPath,
     svg_tree: Tree,
     scene_metadata: SceneMetadata,
     render_transform: Option<RenderTransform>,
@@ -90,10 +105,14 @@ pub struct DemoApp<W> where W: Window {
     camera: Camera,
     frame_counter: u32,
     pending_screenshot_info: Option<ScreenshotInfo>,
+    frame_recorder: Option<FrameRecorder>,
+    pending_frame_readbacks: VecDeque<Readback<DeviceImpl>>,
     mouselook_enabled: bool,
     pub dirty: bool,
     expire_message_event_id: u32,
     message_epoch: u32,
+    reload_svg_event_id: u32,
+    svg_watch_epoch: Arc<AtomicU32>,
     last_mouse_position: Vector2I,
 
     current_frame: Option<Frame>,
@@ -104,6 +123,7 @@ pub struct DemoApp<W> where W: Window {
 
     scene_proxy: SceneProxy,
     renderer: Renderer<DeviceImpl>,
+    inspector: Option<SceneInspector>,
 
     scene_framebuffer: Option<<DeviceImpl as Device>::Framebuffer>,
 
@@ -114,6 +134,7 @@ pub struct DemoApp<W> where W: Window {
 impl<W> DemoApp<W> where W: Window {
     pub fn new(window: W, window_size: WindowSize, mut options: Options) -> DemoApp<W> {
         let expire_message_event_id = window.create_user_event_id();
+        let reload_svg_event_id = window.create_user_event_id();
 
         let device;
         #[cfg(all(target_os = "macos", not(feature = "pf-gl")))]
@@ -134,7 +155,10 @@ impl<W> DemoApp<W> where W: Window {
         let executor = DemoExecutor::new(options.jobs);
 
         let mut ui_model = DemoUIModel::new(&options);
-        let render_options = RendererOptions { background_color: None };
+        let render_options = RendererOptions {
+            background_color: None,
+            ..RendererOptions::default()
+        };
 
         let effects = build_effects(&ui_model);
 
@@ -142,7 +166,8 @@ impl<W> DemoApp<W> where W: Window {
         let (mut built_svg, svg_tree) = load_scene(resources,
                                                    &options.input_path,
                                                    viewport.size(),
-                                                   effects);
+                                                   effects)
+            .unwrap_or_else(|message| panic!("{}", message));
 
         let message = get_svg_building_message(&built_svg);
 
@@ -175,6 +200,18 @@ impl<W> DemoApp<W> where W: Window {
 
         let ui_presenter = DemoUIPresenter::new(&renderer.device, resources);
 
+        let frame_recorder = options.recording_path.as_ref().map(|path| {
+            FrameRecorder::new(path, options.recording_fps).unwrap_or_else(|err| {
+                panic!("Failed to start recording to {}: {}", path.display(), err)
+            })
+        });
+
+        let svg_path = options.input_path.clone();
+        let svg_watch_epoch = Arc::new(AtomicU32::new(0));
+        watch_svg_file(&svg_path, svg_watch_epoch.clone(), move |epoch| {
+            W::push_user_event(reload_svg_event_id, epoch)
+        });
+
         DemoApp {
             window,
             should_exit: false,
@@ -182,6 +219,7 @@ impl<W> DemoApp<W> where W: Window {
 
             window_size,
 
+            svg_path,
             svg_tree,
             scene_metadata,
             render_transform: None,
@@ -190,10 +228,14 @@ impl<W> DemoApp<W> where W: Window {
             camera,
             frame_counter: 0,
             pending_screenshot_info: None,
+            frame_recorder,
+            pending_frame_readbacks: VecDeque::new(),
             mouselook_enabled: false,
             dirty: true,
             expire_message_event_id,
             message_epoch,
+            reload_svg_event_id,
+            svg_watch_epoch,
             last_mouse_position: Vector2I::default(),
 
             current_frame: None,
@@ -204,6 +246,7 @@ impl<W> DemoApp<W> where W: Window {
 
             scene_proxy,
             renderer,
+            inspector: None,
 
             scene_framebuffer: None,
 
@@ -261,6 +304,7 @@ impl<W> DemoApp<W> where W: Window {
                 Vector2F::default()
             },
             subpixel_aa_enabled: self.ui_model.subpixel_aa_effect_enabled,
+            ..BuildOptions::default()
         };
 
         self.render_command_stream = Some(self.scene_proxy.build_with_stream(build_options));
@@ -305,6 +349,11 @@ impl<W> DemoApp<W> where W: Window {
                         self.dirty = true;
                     }
                 }
+                Event::MouseMoved(new_position) if self.inspector.is_some() => {
+                    // Track the mouse so that the inspector can highlight the hovered path.
+                    self.process_mouse_position(new_position);
+                    self.dirty = true;
+                }
                 Event::MouseDragged(new_position) => {
                     let mouse_position = self.process_mouse_position(new_position);
                     ui_events.push(UIEvent::MouseDragged(mouse_position));
@@ -423,6 +472,19 @@ impl<W> DemoApp<W> where W: Window {
                         self.dirty = true;
                     }
                 }
+                Event::KeyDown(Keycode::Alphanumeric(b'i')) => {
+                    if self.inspector.take().is_none() {
+                        let mut inspector = SceneInspector::new();
+                        inspector.set_scene(&self.scene_proxy.copy_scene());
+                        self.inspector = Some(inspector);
+                    }
+                    self.dirty = true;
+                }
+                Event::KeyDown(Keycode::Alphanumeric(b'o')) => {
+                    self.ui_model.tile_overlay_enabled = !self.ui_model.tile_overlay_enabled;
+                    self.dirty = true;
+                }
+                Event::KeyDown(Keycode::Alphanumeric(b'r')) => self.toggle_recording(),
                 Event::KeyDown(Keycode::Tab) => {
                     self.options.ui = match self.options.ui {
                         UIVisibility::None => UIVisibility::Stats,
@@ -431,27 +493,23 @@ impl<W> DemoApp<W> where W: Window {
                     }
                 }
 
-                Event::OpenSVG(ref svg_path) => {
-                    let viewport = self.window.viewport(self.ui_model.mode.view(0));
-                    let effects = build_effects(&self.ui_model);
-                    let (mut built_svg, svg_tree) = load_scene(self.window.resource_loader(),
-                                                               svg_path,
-                                                               viewport.size(),
-                                                               effects);
-
-                    self.ui_model.message = get_svg_building_message(&built_svg);
-
-                    let viewport_size = self.window.viewport(self.ui_model.mode.view(0)).size();
-                    self.scene_metadata =
-                        SceneMetadata::new_clipping_view_box(&mut built_svg.scene, viewport_size);
-                    self.camera = Camera::new(self.ui_model.mode,
-                                              self.scene_metadata.view_box,
-                                              viewport_size);
-
-                    self.scene_proxy.replace_scene(built_svg.scene);
-                    self.svg_tree = svg_tree;
+                Event::OpenSVG(svg_path) => {
+                    self.svg_path = svg_path;
+                    let reload_svg_event_id = self.reload_svg_event_id;
+                    watch_svg_file(&self.svg_path, self.svg_watch_epoch.clone(), move |epoch| {
+                        W::push_user_event(reload_svg_event_id, epoch)
+                    });
+                    self.load_svg(true);
+                }
 
-                    self.dirty = true;
+                Event::User {
+                    message_type: event_id,
+                    message_data: expected_epoch,
+                } if event_id == self.reload_svg_event_id
+                    && expected_epoch == self.svg_watch_epoch.load(Ordering::SeqCst) =>
+                {
+                    // The file being displayed changed on disk, so keep the camera where it is.
+                    self.load_svg(false);
                 }
 
                 Event::User {
@@ -470,6 +528,71 @@ impl<W> DemoApp<W> where W: Window {
         ui_events
     }
 
+    // Starts recording to the path given on the command line, or stops recording. Starting again
+    // overwrites the previous recording.
+    fn toggle_recording(&mut self) {
+        self.record_finished_frames(true);
+        let message = match self.frame_recorder.take() {
+            Some(_) => "Stopped recording.".to_owned(),
+            None => {
+                let path = match self.options.recording_path {
+                    Some(ref path) => path.clone(),
+                    None => PathBuf::from(DEFAULT_RECORDING_PATH),
+                };
+                match FrameRecorder::new(&path, self.options.recording_fps) {
+                    Ok(frame_recorder) => {
+                        self.frame_recorder = Some(frame_recorder);
+                        format!("Recording to {}.", path.display())
+                    }
+                    Err(err) => format!("Failed to start recording to {}: {}", path.display(), err),
+                }
+            }
+        };
+
+        emit_message::<W>(&mut self.ui_model,
+                          &mut self.message_epoch,
+                          self.expire_message_event_id,
+                          message);
+        self.dirty = true;
+    }
+
+    // Replaces the scene with the current SVG. If loading fails, the old scene stays up, and the
+    // error is shown as a message.
+    fn load_svg(&mut self, reset_camera: bool) {
+        let viewport_size = self.window.viewport(self.ui_model.mode.view(0)).size();
+        let effects = build_effects(&self.ui_model);
+        let (mut built_svg, svg_tree) = match load_scene(self.window.resource_loader(),
+                                                         &self.svg_path,
+                                                         viewport_size,
+                                                         effects) {
+            Ok(scene) => scene,
+            Err(message) => {
+                emit_message::<W>(&mut self.ui_model,
+                                  &mut self.message_epoch,
+                                  self.expire_message_event_id,
+                                  message);
+                self.dirty = true;
+                return;
+            }
+        };
+
+        self.ui_model.message = get_svg_building_message(&built_svg);
+
+        self.scene_metadata =
+            SceneMetadata::new_clipping_view_box(&mut built_svg.scene, viewport_size);
+        if reset_camera {
+            self.camera = Camera::new(self.ui_model.mode,
+                                      self.scene_metadata.view_box,
+                                      viewport_size);
+        }
+
+        self.scene_proxy.replace_scene(built_svg.scene);
+        self.svg_tree = svg_tree;
+        self.refresh_inspector();
+
+        self.dirty = true;
+    }
+
     fn process_mouse_position(&mut self, new_position: Vector2I) -> MousePosition {
         let absolute = new_position.scale(self.window_size.backing_scale_factor as i32);
         let relative = absolute - self.last_mouse_position;
@@ -479,6 +602,7 @@ impl<W> DemoApp<W> where W: Window {
 
     pub fn finish_drawing_frame(&mut self) {
         self.maybe_take_screenshot();
+        self.maybe_record_frame();
         self.update_stats();
         self.draw_debug_ui();
 
@@ -504,6 +628,10 @@ impl<W> DemoApp<W> where W: Window {
             );
         }
 
+        if self.options.ui != UIVisibility::None {
+            self.draw_inspector();
+        }
+
         self.handle_ui_events(frame, &mut ui_action);
 
         self.renderer.device.end_commands();
@@ -512,6 +640,29 @@ impl<W> DemoApp<W> where W: Window {
         self.frame_counter += 1;
     }
 
+    fn draw_inspector(&mut self) {
+        let inspector = match self.inspector {
+            None => return,
+            Some(ref mut inspector) => inspector,
+        };
+        let transform = match self.camera {
+            Camera::TwoD(transform) => Some(transform),
+            Camera::ThreeD { .. } => None,
+        };
+        if let Some(change) = inspector.draw(&self.renderer.device,
+                                             &mut self.renderer.debug_ui_presenter.ui_presenter,
+                                             transform) {
+            self.scene_proxy.set_path_visible(change.path_index, change.visible);
+            self.dirty = true;
+        }
+    }
+
+    fn refresh_inspector(&mut self) {
+        if let Some(ref mut inspector) = self.inspector {
+            inspector.set_scene(&self.scene_proxy.copy_scene());
+        }
+    }
+
     fn update_stats(&mut self) {
         let frame = self.current_frame.as_mut().unwrap();
         if let Some(rendering_time) = self.renderer.shift_rendering_time() {
@@ -598,6 +749,7 @@ impl<W> DemoApp<W> where W: Window {
                 self.scene_metadata =
                     SceneMetadata::new_clipping_view_box(&mut built_svg.scene, viewport_size);
                 self.scene_proxy.replace_scene(built_svg.scene);
+                self.refresh_inspector();
                 self.dirty = true;
             }
             UIAction::TakeScreenshot(ref info) => {
@@ -646,6 +798,8 @@ pub struct Options {
     pub input_path: SVGPath,
     pub ui: UIVisibility,
     pub background_color: BackgroundColor,
+    pub recording_path: Option<PathBuf>,
+    pub recording_fps: u32,
     hidden_field_for_future_proofing: (),
 }
 
@@ -657,6 +811,8 @@ impl Default for Options {
             input_path: SVGPath::Default,
             ui: UIVisibility::All,
             background_color: BackgroundColor::Light,
+            recording_path: None,
+            recording_fps: DEFAULT_RECORDING_FPS,
             hidden_field_for_future_proofing: (),
         }
     }
@@ -703,6 +859,22 @@ impl Options {
                     .possible_values(&["light", "dark", "transparent"])
                     .help("The background color to use"),
             )
+            .arg(
+                Arg::with_name("record")
+                    .short("r")
+                    .long("record")
+                    .value_name("PATH")
+                    .takes_value(true)
+                    .help("Record frames to a directory of PNGs, a .y4m file, or - for y4m on \
+                           standard output"),
+            )
+            .arg(
+                Arg::with_name("record-fps")
+                    .long("record-fps")
+                    .value_name("FPS")
+                    .takes_value(true)
+                    .help("The frame rate to write to recorded y4m streams"),
+            )
             .arg(
                 Arg::with_name("INPUT")
                     .help("Path to the SVG file to render")
@@ -736,6 +908,13 @@ impl Options {
             };
         }
 
+        if let Some(path) = matches.value_of("record") {
+            self.recording_path = Some(PathBuf::from(path));
+        }
+        if let Some(fps) = matches.value_of("record-fps") {
+            self.recording_fps = fps.parse().unwrap_or(DEFAULT_RECORDING_FPS);
+        }
+
         if let Some(path) = matches.value_of("INPUT") {
             self.input_path = SVGPath::Path(PathBuf::from(path));
         };
@@ -753,20 +932,58 @@ fn load_scene(resource_loader: &dyn ResourceLoader,
               input_path: &SVGPath,
               viewport_size: Vector2I,
               effects: Option<Effects>)
-              -> (BuiltSVG, Tree) {
+              -> Result<(BuiltSVG, Tree), String> {
     let mut data;
     match *input_path {
         SVGPath::Default => data = resource_loader.slurp(DEFAULT_SVG_VIRTUAL_PATH).unwrap(),
         SVGPath::Resource(ref name) => data = resource_loader.slurp(name).unwrap(),
         SVGPath::Path(ref path) => {
             data = vec![];
-            File::open(path).unwrap().read_to_end(&mut data).unwrap();
+            File::open(path).and_then(|mut file| file.read_to_end(&mut data)).map_err(|err| {
+                format!("Failed to read {}: {}", path.display(), err)
+            })?;
         }
     };
 
-    let tree = Tree::from_data(&data, &UsvgOptions::default()).expect("Failed to parse the SVG!");
+    let usvg_options = text::usvg_options_with_system_fonts();
+    let tree = Tree::from_data(&data, &usvg_options).map_err(|err| {
+        format!("Failed to parse the SVG: {}", err)
+    })?;
     let built_svg = build_svg_tree(&tree, viewport_size, effects);
-    (built_svg, tree)
+    Ok((built_svg, tree))
+}
+
+// Starts watching the SVG at `svg_path` if it's a file, and stops watching the previous one.
+//
+// A background thread polls the file's modification time and calls `on_change` with the watch
+// epoch whenever it changes. The thread exits once the epoch moves on.
+fn watch_svg_file<F>(svg_path: &SVGPath, svg_watch_epoch: Arc<AtomicU32>, on_change: F)
+                     where F: Fn(u32) + Send + 'static {
+    let epoch = svg_watch_epoch.fetch_add(1, Ordering::SeqCst) + 1;
+    let path = match *svg_path {
+        SVGPath::Path(ref path) => path.clone(),
+        SVGPath::Default | SVGPath::Resource(_) => return,
+    };
+
+    let mut last_modified = modified_time(&path);
+    thread::spawn(move || {
+        loop {
+            thread::sleep(Duration::from_millis(SVG_WATCH_INTERVAL_MILLIS));
+            if svg_watch_epoch.load(Ordering::SeqCst) != epoch {
+                break;
+            }
+            // Editors may briefly remove the file while saving, so wait until it's back.
+            let modified = modified_time(&path);
+            if modified.is_some() && modified != last_modified {
+                last_modified = modified;
+                on_change(epoch);
+            }
+        }
+    });
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
 }
 
 fn build_svg_tree(tree: &Tree, viewport_size: Vector2I, effects: Option<Effects>) -> BuiltSVG {
@@ -897,3 +1114,72 @@ fn build_effects(ui_model: &DemoUIModel) -> Option<Effects> {
         },
     })
 }
+
+#[cfg(test)]
+mod test {
+    use super::{SVG_WATCH_INTERVAL_MILLIS, load_scene, watch_svg_file};
+    use crate::window::SVGPath;
+    use pathfinder_geometry::vector::Vector2I;
+    use pathfinder_resources::fs::FilesystemResourceLoader;
+    use std::env;
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, SystemTime};
+
+    fn temp_svg_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("pathfinder-demo-{}-{}.svg", name, std::process::id()))
+    }
+
+    // Bumps the file's modification time explicitly, since filesystem timestamps can be coarse.
+    fn touch(path: &PathBuf, seconds_from_now: u64) {
+        let modified = SystemTime::now() + Duration::from_secs(seconds_from_now);
+        File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_and_invalid_svgs_are_errors() {
+        let resource_loader = FilesystemResourceLoader { directory: PathBuf::new() };
+
+        let missing_path = temp_svg_path("missing");
+        let svg_path = SVGPath::Path(missing_path);
+        let message = load_scene(&resource_loader, &svg_path, Vector2I::splat(64), None).err();
+        assert!(message.unwrap().starts_with("Failed to read"));
+
+        let invalid_path = temp_svg_path("invalid");
+        fs::write(&invalid_path, "This isn't an SVG.").unwrap();
+        let svg_path = SVGPath::Path(invalid_path.clone());
+        let message = load_scene(&resource_loader, &svg_path, Vector2I::splat(64), None).err();
+        fs::remove_file(&invalid_path).unwrap();
+        assert!(message.unwrap().starts_with("Failed to parse the SVG"));
+    }
+
+    #[test]
+    fn test_changed_svg_files_are_reported_until_replaced() {
+        let path = temp_svg_path("watched");
+        fs::write(&path, "<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+
+        let svg_watch_epoch = Arc::new(AtomicU32::new(0));
+        let (sender, receiver) = mpsc::channel();
+        watch_svg_file(&SVGPath::Path(path.clone()), svg_watch_epoch.clone(), move |epoch| {
+            sender.send(epoch).unwrap();
+        });
+
+        let timeout = Duration::from_millis(SVG_WATCH_INTERVAL_MILLIS * 10);
+        touch(&path, 10);
+        assert_eq!(receiver.recv_timeout(timeout), Ok(1));
+
+        // Displaying a non-file SVG stops the watcher within an interval, so later changes go
+        // unreported.
+        watch_svg_file(&SVGPath::Default, svg_watch_epoch, |_| unreachable!());
+        thread::sleep(Duration::from_millis(SVG_WATCH_INTERVAL_MILLIS * 2));
+        touch(&path, 20);
+        let timeout = Duration::from_millis(SVG_WATCH_INTERVAL_MILLIS * 3);
+        assert!(receiver.recv_timeout(timeout).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
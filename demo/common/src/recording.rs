@@ -0,0 +1,159 @@
+// pathfinder/demo/common/src/recording.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Records rendered frames as a PNG sequence or a YUV4MPEG2 stream.
+
+use image::ColorType;
+use pathfinder_geometry::vector::Vector2I;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+pub(crate) struct FrameRecorder {
+    sink: FrameSink,
+    frame_index: u32,
+}
+
+enum FrameSink {
+    // One numbered PNG per frame in a directory.
+    PNGSequence(PathBuf),
+    // A YUV4MPEG2 stream. The header is written along with the first frame, since it includes the
+    // frame size, which can't change afterward.
+    Y4M { writer: Box<dyn Write>, fps: u32, frame_size: Option<Vector2I> },
+}
+
+impl FrameRecorder {
+    /// Starts recording to `path`.
+    ///
+    /// If `path` is `-`, a YUV4MPEG2 stream is written to standard output, so that it can be piped
+    /// into an encoder. If it ends in `.y4m`, the stream is written to that file. Otherwise, it
+    /// names a directory to write a PNG per frame to, which is created if necessary.
+    pub(crate) fn new(path: &Path, fps: u32) -> io::Result<FrameRecorder> {
+        let sink = if path == Path::new("-") {
+            let writer = Box::new(BufWriter::new(io::stdout()));
+            FrameSink::Y4M { writer, fps, frame_size: None }
+        } else if path.extension().map_or(false, |extension| extension == "y4m") {
+            let writer = Box::new(BufWriter::new(File::create(path)?));
+            FrameSink::Y4M { writer, fps, frame_size: None }
+        } else {
+            fs::create_dir_all(path)?;
+            FrameSink::PNGSequence(path.to_owned())
+        };
+        Ok(FrameRecorder { sink, frame_index: 0 })
+    }
+
+    /// Records one frame of RGBA pixels, top row first.
+    pub(crate) fn record_frame(&mut self, pixels: &[u8], size: Vector2I) -> io::Result<()> {
+        match self.sink {
+            FrameSink::PNGSequence(ref directory) => {
+                let path = directory.join(format!("frame-{:05}.png", self.frame_index));
+                image::save_buffer(path,
+                                   pixels,
+                                   size.x() as u32,
+                                   size.y() as u32,
+                                   ColorType::RGBA(8))?;
+            }
+            FrameSink::Y4M { ref mut writer, fps, ref mut frame_size } => {
+                match *frame_size {
+                    None => {
+                        writeln!(writer,
+                                 "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                                 size.x(),
+                                 size.y(),
+                                 fps)?;
+                        *frame_size = Some(size);
+                    }
+                    Some(frame_size) if frame_size != size => {
+                        return Err(Error::new(ErrorKind::InvalidInput,
+                                              "the window size changed during recording"));
+                    }
+                    Some(_) => {}
+                }
+
+                writeln!(writer, "FRAME")?;
+                writer.write_all(&rgba_to_yuv444(pixels))?;
+                writer.flush()?;
+            }
+        }
+
+        self.frame_index += 1;
+        Ok(())
+    }
+}
+
+// Converts RGBA pixels to planar 4:4:4 Y'CbCr, using the BT.601 coefficients and video range that
+// YUV4MPEG2 consumers assume. Alpha is dropped.
+fn rgba_to_yuv444(pixels: &[u8]) -> Vec<u8> {
+    let pixel_count = pixels.len() / 4;
+    let mut planes = vec![0; pixel_count * 3];
+    for (pixel_index, pixel) in pixels.chunks_exact(4).enumerate() {
+        let (r, g, b) = (pixel[0] as i32, pixel[1] as i32, pixel[2] as i32);
+        let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+        let cb = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+        let cr = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+        planes[pixel_index] = y as u8;
+        planes[pixel_count + pixel_index] = cb as u8;
+        planes[pixel_count * 2 + pixel_index] = cr as u8;
+    }
+    planes
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FrameRecorder, rgba_to_yuv444};
+    use pathfinder_geometry::vector::Vector2I;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("pathfinder-recording-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_rgba_to_yuv444() {
+        let pixels = [0, 0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 0];
+        assert_eq!(rgba_to_yuv444(&pixels), vec![16, 235, 82, 128, 128, 90, 128, 128, 240]);
+    }
+
+    #[test]
+    fn test_y4m_streams_have_one_header_and_fixed_size_frames() {
+        let path = temp_path("stream.y4m");
+        let mut recorder = FrameRecorder::new(&path, 30).unwrap();
+        let size = Vector2I::new(2, 1);
+        let pixels = [255; 8];
+        recorder.record_frame(&pixels, size).unwrap();
+        recorder.record_frame(&pixels, size).unwrap();
+        assert!(recorder.record_frame(&[255; 16], Vector2I::splat(2)).is_err());
+
+        let mut expected = b"YUV4MPEG2 W2 H1 F30:1 Ip A1:1 C444\n".to_vec();
+        for _ in 0..2 {
+            expected.extend_from_slice(b"FRAME\n");
+            expected.extend_from_slice(&[235, 235, 128, 128, 128, 128]);
+        }
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_png_sequences_are_numbered() {
+        let directory = temp_path("frames");
+        let mut recorder = FrameRecorder::new(&directory, 60).unwrap();
+        let pixels = [0, 0, 255, 255, 255, 0, 0, 128];
+        recorder.record_frame(&pixels, Vector2I::new(1, 2)).unwrap();
+        recorder.record_frame(&pixels, Vector2I::new(1, 2)).unwrap();
+
+        let frame = image::open(directory.join("frame-00001.png")).unwrap().to_rgba();
+        assert_eq!(frame.dimensions(), (1, 2));
+        assert_eq!(frame.into_raw(), pixels.to_vec());
+        assert!(directory.join("frame-00000.png").is_file());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
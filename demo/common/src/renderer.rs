@@ -12,15 +12,16 @@
 
 use crate::camera::{Camera, Mode};
 use crate::window::{View, Window};
-use crate::{BackgroundColor, DemoApp, UIVisibility};
+use crate::{BackgroundColor, DemoApp, DeviceImpl, UIVisibility, emit_message};
 use image::ColorType;
 use pathfinder_color::{ColorF, ColorU};
 use pathfinder_gpu::{ClearOps, DepthFunc, DepthState, Device, Primitive, RenderOptions};
-use pathfinder_gpu::{RenderState, RenderTarget, TextureData, TextureFormat, UniformData};
+use pathfinder_gpu::{RenderState, TextureData, TextureFormat, UniformData};
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::transform3d::Transform4F;
 use pathfinder_geometry::vector::{Vector2I, Vector4F};
 use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererOptions};
+use pathfinder_renderer::gpu::renderer::Readback;
 use pathfinder_renderer::gpu_data::RenderCommand;
 use pathfinder_renderer::options::RenderTransform;
 use std::path::PathBuf;
@@ -90,7 +91,11 @@ impl<W> DemoApp<W> where W: Window {
             Mode::ThreeD => None,
             Mode::VR => Some(ColorF::transparent_black()),
         };
-        self.renderer.set_options(RendererOptions { background_color: clear_color });
+        self.renderer.set_options(RendererOptions {
+                                      background_color: clear_color,
+                                      show_tile_overlay: self.ui_model.tile_overlay_enabled,
+                                      ..RendererOptions::default()
+                                  });
 
         scene_count
     }
@@ -259,9 +264,18 @@ impl<W> DemoApp<W> where W: Window {
 
         // Issue render commands!
         for command in self.render_command_stream.as_mut().unwrap() {
-            self.renderer.render_command(&command);
+            let command = match command {
+                Ok(command) => command,
+                Err(error) => {
+                    warn!("Failed to build the scene: {}", error);
+                    continue;
+                }
+            };
+            if let Err(error) = self.renderer.render_command(&command) {
+                warn!("Failed to render a command: {}", error);
+            }
 
-            if let RenderCommand::Finish { build_time } = command {
+            if let RenderCommand::Finish { build_time, .. } = command {
                 self.build_time = Some(build_time);
             }
         }
@@ -276,13 +290,7 @@ impl<W> DemoApp<W> where W: Window {
 
     pub fn take_raster_screenshot(&mut self, path: PathBuf) {
         let drawable_size = self.window_size.device_size();
-        let viewport = RectI::new(Vector2I::default(), drawable_size);
-        let texture_data_receiver =
-            self.renderer.device.read_pixels(&RenderTarget::Default, viewport);
-        let pixels = match self.renderer.device.recv_texture_data(&texture_data_receiver) {
-            TextureData::U8(pixels) => pixels,
-            _ => panic!("Unexpected pixel format for default framebuffer!"),
-        };
+        let pixels = self.read_drawable_pixels();
         image::save_buffer(
             path,
             &pixels,
@@ -293,6 +301,68 @@ impl<W> DemoApp<W> where W: Window {
         .unwrap();
     }
 
+    // Starts reading back this frame for the recording, and records the frames whose readbacks
+    // have finished, in order. The readbacks run in the background, so that recording doesn't stall
+    // the GPU every frame.
+    pub fn maybe_record_frame(&mut self) {
+        if self.frame_recorder.is_none() {
+            return;
+        }
+
+        let readback = self.begin_drawable_readback();
+        self.pending_frame_readbacks.push_back(readback);
+        self.record_finished_frames(false);
+
+        // Keep rendering frames so that the recording runs at a steady rate.
+        self.dirty = true;
+    }
+
+    // Records the frames whose readbacks have finished. If `wait` is true, waits for all of them.
+    pub(crate) fn record_finished_frames(&mut self, wait: bool) {
+        while let Some(readback) = self.pending_frame_readbacks.pop_front() {
+            let size = readback.size();
+            let texture_data = if wait {
+                self.renderer.finish_readback(readback)
+            } else {
+                match self.renderer.poll_readback(&readback) {
+                    Some(texture_data) => texture_data,
+                    None => {
+                        self.pending_frame_readbacks.push_front(readback);
+                        break;
+                    }
+                }
+            };
+
+            let frame_recorder = match self.frame_recorder {
+                None => continue,
+                Some(ref mut frame_recorder) => frame_recorder,
+            };
+            if let Err(err) = frame_recorder.record_frame(&drawable_pixels(texture_data), size) {
+                self.frame_recorder = None;
+                self.pending_frame_readbacks.clear();
+                emit_message::<W>(&mut self.ui_model,
+                                  &mut self.message_epoch,
+                                  self.expire_message_event_id,
+                                  format!("Stopped recording: {}", err));
+            }
+        }
+    }
+
+    // Reads back the window contents as RGBA pixels, top row first, before the debug UI is drawn.
+    fn read_drawable_pixels(&mut self) -> Vec<u8> {
+        let readback = self.begin_drawable_readback();
+        drawable_pixels(self.renderer.finish_readback(readback))
+    }
+
+    fn begin_drawable_readback(&mut self) -> Readback<DeviceImpl> {
+        let drawable_size = self.window_size.device_size();
+        self.renderer.replace_dest_framebuffer(DestFramebuffer::Default {
+            viewport: RectI::new(Vector2I::default(), drawable_size),
+            window_size: drawable_size,
+        });
+        self.renderer.begin_readback(RectI::new(Vector2I::default(), drawable_size))
+    }
+
     pub fn draw_debug_ui(&mut self) {
         if self.options.ui == UIVisibility::None {
             return;
@@ -308,3 +378,10 @@ impl<W> DemoApp<W> where W: Window {
         self.renderer.draw_debug_ui();
     }
 }
+
+fn drawable_pixels(texture_data: TextureData) -> Vec<u8> {
+    match texture_data {
+        TextureData::U8(pixels) => pixels,
+        _ => panic!("Unexpected pixel format for default framebuffer!"),
+    }
+}
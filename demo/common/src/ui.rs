@@ -59,6 +59,7 @@ pub struct DemoUIModel {
     pub gamma_correction_effect_enabled: bool,
     pub stem_darkening_effect_enabled: bool,
     pub subpixel_aa_effect_enabled: bool,
+    pub tile_overlay_enabled: bool,
     pub rotation: i32,
     pub message: String,
 }
@@ -71,6 +72,7 @@ impl DemoUIModel {
             gamma_correction_effect_enabled: false,
             stem_darkening_effect_enabled: false,
             subpixel_aa_effect_enabled: false,
+            tile_overlay_enabled: false,
             rotation: SLIDER_WIDTH / 2,
             message: String::new(),
         }
@@ -20,7 +20,7 @@ use egl::EGLSurface;
 
 use gl::types::GLuint;
 
-use log::info;
+use log::{info, warn};
 
 use pathfinder_demo::DemoApp;
 use pathfinder_demo::Options;
@@ -227,7 +227,9 @@ pub unsafe extern "C" fn magicleap_pathfinder_render(pf: *mut c_void, options: *
         };
 
         let scene_proxy = SceneProxy::from_scene(svg.scene.clone(), SequentialExecutor);
-        scene_proxy.build_and_render(renderer, render_options);
+        if let Err(error) = scene_proxy.build_and_render(renderer, render_options) {
+            warn!("Failed to render the SVG: {}", error);
+        }
     }
 }
 
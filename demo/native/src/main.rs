@@ -311,6 +311,9 @@ impl WindowImpl {
                 message_type: type_,
                 message_data: code as u32,
             }),
+            SDLEvent::DropFile { filename, .. } => {
+                Some(Event::OpenSVG(SVGPath::Path(PathBuf::from(filename))))
+            }
             SDLEvent::MouseButtonDown { x, y, .. } => Some(Event::MouseDown(Vector2I::new(x, y))),
             SDLEvent::MouseMotion {
                 x, y, mousestate, ..
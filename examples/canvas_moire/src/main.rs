@@ -68,7 +68,10 @@ fn main() {
     let renderer = Renderer::new(GLDevice::new(GLVersion::GL3, 0),
                                  &FilesystemResourceLoader::locate(),
                                  DestFramebuffer::full_window(drawable_size),
-                                 RendererOptions { background_color: Some(ColorF::white()) });
+                                 RendererOptions {
+                                     background_color: Some(ColorF::white()),
+                                     ..RendererOptions::default()
+                                 });
     let mut moire_renderer = MoireRenderer::new(renderer, window_size, drawable_size);
 
     // Enter main render loop.
@@ -125,7 +128,10 @@ impl MoireRenderer {
             Vector2F::new(1.0, sin_time).scale(cos_time * INNER_RADIUS);
 
         // Clear to background color.
-        self.renderer.set_options(RendererOptions { background_color: Some(background_color) });
+        self.renderer.set_options(RendererOptions {
+                                      background_color: Some(background_color),
+                                      ..RendererOptions::default()
+                                  });
 
         // Make a canvas.
         let mut canvas = CanvasRenderingContext2D::new(self.font_context.clone(),
@@ -140,7 +146,7 @@ impl MoireRenderer {
 
         // Build and render scene.
         self.scene.replace_scene(canvas.into_scene());
-        self.scene.build_and_render(&mut self.renderer, BuildOptions::default());
+        self.scene.build_and_render(&mut self.renderer, BuildOptions::default()).unwrap();
 
         self.frame += 1;
     }
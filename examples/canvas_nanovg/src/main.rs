@@ -528,6 +528,7 @@ fn main() {
                                      DestFramebuffer::full_window(window_size),
                                      RendererOptions {
                                          background_color: Some(ColorF::new(0.3, 0.3, 0.32, 1.0)),
+                                         ..RendererOptions::default()
                                      });
 
     // Initialize state.
@@ -547,7 +548,7 @@ fn main() {
 
         // Render the canvas to screen.
         let scene = SceneProxy::from_scene(canvas.into_scene(), RayonExecutor);
-        scene.build_and_render(&mut renderer, BuildOptions::default());
+        scene.build_and_render(&mut renderer, BuildOptions::default()).unwrap();
         window.gl_swap_window();
 
         for event in event_pump.poll_iter() {
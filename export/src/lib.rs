@@ -12,12 +12,19 @@ use pathfinder_content::segment::SegmentKind;
 use pathfinder_renderer::paint::Paint;
 use pathfinder_renderer::scene::Scene;
 use pathfinder_geometry::vector::Vector2F;
-use std::fmt;
 use std::io::{self, Write};
 
 mod pdf;
+mod png;
+mod ps;
+mod raster;
+mod svg;
+
 use pdf::Pdf;
 
+pub use crate::raster::{RasterOptions, rasterize_to_png};
+pub use crate::svg::SvgExportOptions;
+
 pub enum FileFormat {
     /// Scalable Vector Graphics
     SVG,
@@ -31,37 +38,25 @@ pub enum FileFormat {
 
 pub trait Export {
     fn export<W: Write>(&self, writer: &mut W, format: FileFormat) -> io::Result<()>;
+
+    /// Exports SVG with the given options. `export()` uses the defaults.
+    fn export_svg<W: Write>(&self, writer: &mut W, options: &SvgExportOptions)
+                            -> io::Result<()>;
 }
 
 impl Export for Scene {
     fn export<W: Write>(&self, writer: &mut W, format: FileFormat) -> io::Result<()> {
         match format {
-            FileFormat::SVG => export_svg(self, writer),
+            FileFormat::SVG => svg::export_svg(self, writer, &SvgExportOptions::default()),
             FileFormat::PDF => export_pdf(self, writer),
-            FileFormat::PS => export_ps(self, writer)
+            FileFormat::PS => ps::export_ps(self, writer)
         }
     }
-}
 
-fn export_svg<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
-    let view_box = scene.view_box();
-    writeln!(
-        writer,
-        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
-        view_box.origin().x(),
-        view_box.origin().y(),
-        view_box.size().x(),
-        view_box.size().y()
-    )?;
-    for (paint, outline, name) in scene.paths() {
-        write!(writer, "    <path")?;
-        if !name.is_empty() {
-            write!(writer, " id=\"{}\"", name)?;
-        }
-        writeln!(writer, " fill=\"{:?}\" d=\"{:?}\" />", paint, outline)?;
+    fn export_svg<W: Write>(&self, writer: &mut W, options: &SvgExportOptions)
+                            -> io::Result<()> {
+        svg::export_svg(self, writer, options)
     }
-    writeln!(writer, "</svg>")?;
-    Ok(())
 }
 
 fn export_pdf<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
@@ -84,6 +79,8 @@ fn export_pdf<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
             Paint::Pattern(_) => {
                 // TODO(pcwalton): Patterns.
             }
+            // Custom paints are computed by GPU shaders, which PDF can't run.
+            Paint::Custom(_) => {}
         }
 
         for contour in outline.contours() {
@@ -118,84 +115,20 @@ fn export_pdf<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
     pdf.write_to(writer)
 }
 
-fn export_ps<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
-    struct P(Vector2F);
-    impl fmt::Display for P {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "{} {}", self.0.x(), self.0.y())
-        }
+#[cfg(test)]
+mod test {
+    use pathfinder_content::outline::{Contour, Outline};
+    use pathfinder_geometry::vector::Vector2F;
+
+    /// A small closed outline shared by the exporters' tests.
+    pub(crate) fn rect_outline() -> Outline {
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(0.0, 0.0));
+        contour.push_endpoint(Vector2F::new(10.0, 0.0));
+        contour.push_endpoint(Vector2F::new(10.0, 10.0));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        outline
     }
-
-    let view_box = scene.view_box();
-    writeln!(writer, "%!PS-Adobe-3.0 EPSF-3.0")?;
-    writeln!(writer, "%%BoundingBox: {:.0} {:.0}",
-        P(view_box.origin()),
-        P(view_box.size()),
-    )?;
-    writeln!(writer, "%%HiResBoundingBox: {} {}",
-        P(view_box.origin()),
-        P(view_box.size()),
-    )?;
-    writeln!(writer, "0 {} translate", view_box.size().y())?;
-    writeln!(writer, "1 -1 scale")?;
-
-    for (paint, outline, name) in scene.paths() {
-        if !name.is_empty() {
-            writeln!(writer, "newpath % {}", name)?;
-        } else {
-            writeln!(writer, "newpath")?;
-        }
-
-        for contour in outline.contours() {
-            for (segment_index, segment) in contour.iter().enumerate() {
-                if segment_index == 0 {
-                    writeln!(writer, "{} moveto", P(segment.baseline.from()))?;
-                }
-
-                match segment.kind {
-                    SegmentKind::None => {}
-                    SegmentKind::Line => {
-                        writeln!(writer, "{} lineto", P(segment.baseline.to()))?;
-                    }
-                    SegmentKind::Quadratic => {
-                        let current = segment.baseline.from();
-                        let c = segment.ctrl.from();
-                        let p = segment.baseline.to();
-                        let c1 = Vector2F::splat(2. / 3.) * c + Vector2F::splat(1. / 3.) * current;
-                        let c2 = Vector2F::splat(2. / 3.) * c + Vector2F::splat(1. / 3.) * p;
-                        writeln!(writer, "{} {} {} curveto", P(c1), P(c2), P(p))?;
-                    }
-                    SegmentKind::Cubic => {
-                        writeln!(writer, "{} {} {} curveto",
-                            P(segment.ctrl.from()),
-                            P(segment.ctrl.to()),
-                            P(segment.baseline.to())
-                        )?;
-                    }
-                }
-            }
-
-            if contour.is_closed() {
-                writeln!(writer, "closepath")?;
-            }
-        }
-
-        match paint {
-            Paint::Color(color) => {
-                writeln!(writer, "{} {} {} setrgbcolor", color.r, color.g, color.b)?;
-            }
-            Paint::Gradient(_) => {
-                // TODO(pcwalton): Gradients.
-            }
-            Paint::Pattern(_) => {
-                // TODO(pcwalton): Patterns.
-            }
-        }
-
-        writeln!(writer, "fill")?;
-    }
-    writeln!(writer, "showpage")?;
-    Ok(())
 }
-
-
@@ -0,0 +1,65 @@
+// pathfinder/export/src/png.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A minimal PNG encoder, for embedding images in exported documents.
+
+use deflate::Compression;
+use pathfinder_color::ColorU;
+use pathfinder_geometry::vector::Vector2I;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Encodes non-premultiplied RGBA pixels, stored in rows from the top down, as a PNG file.
+pub(crate) fn encode_rgba(size: Vector2I, pixels: &[ColorU]) -> Vec<u8> {
+    let (width, height) = (size.x() as usize, size.y() as usize);
+    assert_eq!(width * height, pixels.len());
+
+    let mut header = vec![];
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlacing.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // Each row starts with its filter type. We use none, and let deflate do the work.
+    let mut image_data = Vec::with_capacity((width * 4 + 1) * height);
+    for row in pixels.chunks(width.max(1)).take(height) {
+        image_data.push(0);
+        for pixel in row {
+            image_data.extend_from_slice(&[pixel.r, pixel.g, pixel.b, pixel.a]);
+        }
+    }
+    let image_data = deflate::deflate_bytes_zlib_conf(&image_data, Compression::Default);
+
+    let mut png = SIGNATURE.to_vec();
+    push_chunk(&mut png, b"IHDR", &header);
+    push_chunk(&mut png, b"IDAT", &image_data);
+    push_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn push_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
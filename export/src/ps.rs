@@ -0,0 +1,254 @@
+// pathfinder/export/src/ps.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Encapsulated PostScript export.
+//!
+//! Gradients are written as Level 3 shadings. PostScript has no transparency, so alpha is
+//! ignored, as are opacity and blend modes.
+
+use pathfinder_color::ColorU;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::gradient::{ColorStop, Gradient, GradientGeometry};
+use pathfinder_content::outline::Outline;
+use pathfinder_content::segment::SegmentKind;
+use pathfinder_geometry::util;
+use pathfinder_geometry::vector::Vector2F;
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::Scene;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+
+pub(crate) fn export_ps<W>(scene: &Scene, writer: &mut W) -> io::Result<()> where W: Write {
+    let view_box = scene.view_box();
+    writeln!(writer, "%!PS-Adobe-3.0 EPSF-3.0")?;
+    writeln!(writer, "%%BoundingBox: {:.0} {:.0}", P(view_box.origin()), P(view_box.size()))?;
+    writeln!(writer, "%%HiResBoundingBox: {} {}", P(view_box.origin()), P(view_box.size()))?;
+    writeln!(writer, "%%LanguageLevel: 3")?;
+    writeln!(writer, "%%EndComments")?;
+    writeln!(writer, "0 {} translate", view_box.size().y())?;
+    writeln!(writer, "1 -1 scale")?;
+
+    for path_index in 0..scene.path_count() {
+        let path = scene.path(path_index);
+        if !path.is_visible() {
+            continue;
+        }
+
+        writeln!(writer, "gsave")?;
+        if let Some(clip_path_id) = path.clip_path() {
+            let clip_path = scene.clip_path(clip_path_id);
+            writeln!(writer, "newpath")?;
+            write_outline(writer, clip_path.outline())?;
+            writeln!(writer, "{}", FillOperator("clip", clip_path.fill_rule()))?;
+        }
+
+        if !path.name().is_empty() {
+            writeln!(writer, "newpath % {}", path.name())?;
+        } else {
+            writeln!(writer, "newpath")?;
+        }
+        write_outline(writer, path.outline())?;
+
+        match *scene.paint(path.paint()) {
+            Paint::Color(color) => {
+                writeln!(writer, "{} setrgbcolor", Rgb(color))?;
+                writeln!(writer, "{}", FillOperator("fill", path.fill_rule()))?;
+            }
+            Paint::Gradient(ref gradient) => {
+                writeln!(writer, "{}", FillOperator("clip", path.fill_rule()))?;
+                write_shading(writer, gradient)?;
+            }
+            Paint::Pattern(_) => {
+                // TODO(pcwalton): Patterns.
+            }
+            // Custom paints are computed by GPU shaders, which PostScript can't run.
+            Paint::Custom(_) => {}
+        }
+        writeln!(writer, "grestore")?;
+    }
+
+    writeln!(writer, "showpage")?;
+    Ok(())
+}
+
+fn write_outline<W>(writer: &mut W, outline: &Outline) -> io::Result<()> where W: Write {
+    for contour in outline.contours() {
+        for (segment_index, segment) in contour.iter().enumerate() {
+            if segment_index == 0 {
+                writeln!(writer, "{} moveto", P(segment.baseline.from()))?;
+            }
+
+            match segment.kind {
+                SegmentKind::None => {}
+                SegmentKind::Line => {
+                    writeln!(writer, "{} lineto", P(segment.baseline.to()))?;
+                }
+                SegmentKind::Quadratic => {
+                    let current = segment.baseline.from();
+                    let c = segment.ctrl.from();
+                    let p = segment.baseline.to();
+                    let c1 = Vector2F::splat(2. / 3.) * c + Vector2F::splat(1. / 3.) * current;
+                    let c2 = Vector2F::splat(2. / 3.) * c + Vector2F::splat(1. / 3.) * p;
+                    writeln!(writer, "{} {} {} curveto", P(c1), P(c2), P(p))?;
+                }
+                SegmentKind::Cubic => {
+                    writeln!(writer, "{} {} {} curveto",
+                        P(segment.ctrl.from()),
+                        P(segment.ctrl.to()),
+                        P(segment.baseline.to())
+                    )?;
+                }
+            }
+        }
+
+        if contour.is_closed() {
+            writeln!(writer, "closepath")?;
+        }
+    }
+    Ok(())
+}
+
+// Fills the current clip with the gradient.
+fn write_shading<W>(writer: &mut W, gradient: &Gradient) -> io::Result<()> where W: Write {
+    let stops = gradient.stops();
+    let (first, last) = match (stops.first(), stops.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Ok(()),
+    };
+
+    // Shading functions must cover the whole domain, so pad the stops out to 0 and 1.
+    let mut padded_stops = vec![ColorStop::new(first.color, 0.0)];
+    padded_stops.extend(stops.iter().map(|stop| {
+        ColorStop::new(stop.color, util::clamp(stop.offset, 0.0, 1.0))
+    }));
+    padded_stops.push(ColorStop::new(last.color, 1.0));
+
+    match gradient.geometry {
+        GradientGeometry::Linear(line) => {
+            writeln!(writer,
+                     "<< /ShadingType 2 /ColorSpace /DeviceRGB /Coords [{} {}] \
+                      /Extend [true true]",
+                     P(line.from()),
+                     P(line.to()))?;
+        }
+        GradientGeometry::Radial { line, start_radius, end_radius } => {
+            writeln!(writer,
+                     "<< /ShadingType 3 /ColorSpace /DeviceRGB /Coords [{} {} {} {}] \
+                      /Extend [true true]",
+                     P(line.from()),
+                     start_radius,
+                     P(line.to()),
+                     end_radius)?;
+        }
+    }
+
+    // A stitching function made of one linear interpolation per pair of adjacent stops.
+    writeln!(writer, "   /Function << /FunctionType 3 /Domain [0 1]")?;
+    writeln!(writer, "      /Functions [")?;
+    for pair in padded_stops.windows(2) {
+        writeln!(writer,
+                 "         << /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >>",
+                 Rgb(pair[0].color),
+                 Rgb(pair[1].color))?;
+    }
+    writeln!(writer, "      ]")?;
+    write!(writer, "      /Bounds [")?;
+    for (stop_index, stop) in padded_stops[1..padded_stops.len() - 1].iter().enumerate() {
+        if stop_index > 0 {
+            write!(writer, " ")?;
+        }
+        write!(writer, "{}", stop.offset)?;
+    }
+    writeln!(writer, "]")?;
+    write!(writer, "      /Encode [")?;
+    for pair_index in 0..(padded_stops.len() - 1) {
+        if pair_index > 0 {
+            write!(writer, " ")?;
+        }
+        write!(writer, "0 1")?;
+    }
+    writeln!(writer, "]")?;
+    writeln!(writer, "   >>")?;
+    writeln!(writer, ">> shfill")
+}
+
+struct P(Vector2F);
+
+impl Display for P {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.0.x(), self.0.y())
+    }
+}
+
+struct Rgb(ColorU);
+
+impl Display for Rgb {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let color = self.0.to_f32();
+        write!(f, "{} {} {}", color.r(), color.g(), color.b())
+    }
+}
+
+// Prefixes `operator` with `eo` for the even-odd rule.
+struct FillOperator(&'static str, FillRule);
+
+impl Display for FillOperator {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.1 {
+            FillRule::Winding => write!(f, "{}", self.0),
+            FillRule::EvenOdd => write!(f, "eo{}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::rect_outline;
+    use crate::{Export, FileFormat};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::fill::FillRule;
+    use pathfinder_content::gradient::{ColorStop, Gradient};
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::vector::Vector2F;
+    use pathfinder_renderer::paint::Paint;
+    use pathfinder_renderer::scene::{ClipPath, DrawPath, Scene};
+
+    #[test]
+    fn test_gradient_shading() {
+        let mut scene = Scene::new();
+        let mut clip_path = ClipPath::new(rect_outline());
+        clip_path.set_fill_rule(FillRule::EvenOdd);
+        let clip_path = scene.push_clip_path(clip_path);
+
+        let mut gradient = Gradient::radial(LineSegment2F::new(Vector2F::default(),
+                                                               Vector2F::splat(10.0)),
+                                            1.0,
+                                            5.0);
+        gradient.add_color_stop(ColorStop::new(ColorU::new(255, 0, 0, 255), 0.25));
+        gradient.add_color_stop(ColorStop::new(ColorU::new(0, 0, 255, 255), 0.75));
+        let gradient = scene.push_paint(&Paint::Gradient(gradient));
+        let mut path = DrawPath::new(rect_outline(), gradient);
+        path.set_clip_path(Some(clip_path));
+        scene.push_path(path);
+
+        let mut output = vec![];
+        scene.export(&mut output, FileFormat::PS).unwrap();
+        let ps = String::from_utf8(output).unwrap();
+        assert!(ps.contains("%%LanguageLevel: 3"));
+        assert!(ps.contains("closepath\neoclip\nnewpath"));
+        assert!(ps.contains("closepath\nclip\n<< /ShadingType 3 /ColorSpace /DeviceRGB \
+                             /Coords [0 0 1 10 10 5]"));
+        assert_eq!(ps.matches("/FunctionType 2").count(), 3);
+        assert!(ps.contains("/C0 [1 0 0] /C1 [0 0 1]"));
+        assert!(ps.contains("/Bounds [0.25 0.75]"));
+        assert!(ps.contains("/Encode [0 1 0 1 0 1]"));
+        assert!(ps.contains(">> shfill\ngrestore"));
+    }
+}
@@ -0,0 +1,130 @@
+// pathfinder/export/src/raster.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rasterization to PNG, via the CPU renderer.
+
+use crate::png;
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use pathfinder_renderer::Error;
+use pathfinder_renderer::concurrent::executor::SequentialExecutor;
+use pathfinder_renderer::cpu::renderer::CPURenderer;
+use pathfinder_renderer::gpu::options::{AntialiasingQuality, RendererOptions};
+use pathfinder_renderer::gpu_data::RenderCommand;
+use pathfinder_renderer::options::{BuildOptions, RenderTransform};
+use pathfinder_renderer::scene::Scene;
+use std::sync::{Arc, Mutex};
+
+/// Options for `rasterize_to_png()`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RasterOptions {
+    /// The color behind the scene. If `None`, the background is transparent.
+    pub background_color: Option<ColorF>,
+    /// Enables subpixel antialiasing, for scenes that are mostly text.
+    pub subpixel_aa_enabled: bool,
+    /// `AntialiasingQuality::None` gives jagged edges, which can be fine for small thumbnails.
+    pub antialiasing_quality: AntialiasingQuality,
+}
+
+/// Renders the scene's view box, stretched to `size` pixels, and encodes the result as a PNG
+/// file.
+///
+/// This uses the software renderer, so it needs no GPU or window; it's meant for command-line
+/// tools and tests rather than for interactive use.
+pub fn rasterize_to_png(scene: &Scene, size: Vector2I, options: &RasterOptions)
+                        -> Result<Vec<u8>, Error> {
+    let view_box = scene.view_box();
+    let transform = Transform2F::from_scale(size.to_f32() / view_box.size()) *
+        Transform2F::from_translation(-view_box.origin());
+    let build_options = BuildOptions {
+        transform: RenderTransform::Transform2D(transform),
+        dilation: Default::default(),
+        subpixel_aa_enabled: options.subpixel_aa_enabled,
+        ..BuildOptions::default()
+    };
+
+    // The builder clips to the view box after transforming, so it must be in pixels.
+    let mut scene = scene.clone();
+    scene.set_view_box(RectF::new(Vector2F::default(), size.to_f32()));
+
+    // The sequential executor calls the listener on this thread, before `build()` returns.
+    let commands = Arc::new(Mutex::new(vec![]));
+    let listener_commands = commands.clone();
+    scene.build(build_options,
+                Box::new(move |command: RenderCommand| {
+                    listener_commands.lock().unwrap().push(command)
+                }),
+                &SequentialExecutor)?;
+
+    let mut renderer = CPURenderer::new(size, RendererOptions {
+        antialiasing_quality: options.antialiasing_quality,
+        ..RendererOptions::default()
+    });
+    renderer.begin_scene();
+    for command in commands.lock().unwrap().iter() {
+        renderer.render_command(command);
+    }
+    renderer.end_scene();
+
+    // The background is composited here rather than by the renderer, which only clears the
+    // framebuffer when something is drawn to it. PNG wants unpremultiplied alpha.
+    let background_color = options.background_color.unwrap_or_default();
+    let pixels: Vec<ColorU> = renderer.pixels().iter().map(|&pixel| {
+        let color = pixel.to_f32();
+        let background_alpha = background_color.a() * (1.0 - color.a());
+        let alpha = color.a() + background_alpha;
+        if alpha == 0.0 {
+            return ColorU::transparent_black();
+        }
+        ColorF::new((color.r() + background_color.r() * background_alpha) / alpha,
+                    (color.g() + background_color.g() * background_alpha) / alpha,
+                    (color.b() + background_color.b() * background_alpha) / alpha,
+                    alpha).to_u8()
+    }).collect();
+    Ok(png::encode_rgba(size, &pixels))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RasterOptions, rasterize_to_png};
+    use pathfinder_color::ColorF;
+    use pathfinder_content::outline::{Contour, Outline};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+    use pathfinder_renderer::paint::Paint;
+    use pathfinder_renderer::scene::{DrawPath, Scene};
+
+    #[test]
+    fn test_png_header() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(10.0)));
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(2.0, 2.0));
+        contour.push_endpoint(Vector2F::new(8.0, 2.0));
+        contour.push_endpoint(Vector2F::new(5.0, 8.0));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        let paint = scene.push_paint(&Paint::black());
+        scene.push_path(DrawPath::new(outline, paint));
+
+        let options = RasterOptions {
+            background_color: Some(ColorF::white()),
+            ..RasterOptions::default()
+        };
+        let png = rasterize_to_png(&scene, Vector2I::new(24, 16), &options).unwrap();
+
+        assert_eq!(&png[0..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 24, 0, 0, 0, 16]);
+    }
+}
@@ -0,0 +1,563 @@
+// pathfinder/export/src/svg.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Scalable Vector Graphics export.
+//!
+//! Paths are written as fills, unless the scene recorded the strokes or text they came from (see
+//! `PathSource`), in which case those are written instead so that the output stays editable.
+
+use crate::png;
+use pathfinder_color::ColorU;
+use pathfinder_content::effects::BlendMode;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::gradient::{Gradient, GradientGeometry};
+use pathfinder_content::pattern::{Pattern, PatternFlags, PatternSource};
+use pathfinder_content::stroke::{LineCap, LineJoin, StrokeStyle};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{ClipPathId, DrawPath, PathSource, Scene, TextRun};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// Options that control SVG export.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SvgExportOptions {
+    /// Writes text runs as `<text>` elements instead of glyph outlines.
+    ///
+    /// The output is then only as faithful as the fonts available to whatever displays it.
+    pub text_as_text: bool,
+}
+
+pub(crate) fn export_svg<W>(scene: &Scene, writer: &mut W, options: &SvgExportOptions)
+                            -> io::Result<()>
+                            where W: Write {
+    let view_box = scene.view_box();
+    writeln!(
+        writer,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+         xmlns:xlink=\"http://www.w3.org/1999/xlink\" viewBox=\"{} {} {} {}\">",
+        view_box.origin().x(),
+        view_box.origin().y(),
+        view_box.size().x(),
+        view_box.size().y()
+    )?;
+
+    let mut exporter = SvgExporter {
+        writer,
+        scene,
+        options: *options,
+        written_paints: HashSet::new(),
+        last_text_run: None,
+    };
+    exporter.write_clip_paths()?;
+    for path_index in 0..scene.path_count() {
+        exporter.write_path(path_index)?;
+    }
+
+    writeln!(exporter.writer, "</svg>")?;
+    Ok(())
+}
+
+struct SvgExporter<'a, W> where W: Write {
+    writer: &'a mut W,
+    scene: &'a Scene,
+    options: SvgExportOptions,
+    // The IDs of the gradient and pattern definitions written so far.
+    written_paints: HashSet<String>,
+    last_text_run: Option<Arc<TextRun>>,
+}
+
+impl<'a, W> SvgExporter<'a, W> where W: Write {
+    fn write_clip_paths(&mut self) -> io::Result<()> {
+        let scene = self.scene;
+        let clip_path_ids: BTreeSet<u32> = (0..scene.path_count()).filter_map(|path_index| {
+            scene.path(path_index).clip_path().map(|clip_path_id| clip_path_id.0)
+        }).collect();
+        if clip_path_ids.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(self.writer, "    <defs>")?;
+        for &clip_path_id in &clip_path_ids {
+            let clip_path = self.scene.clip_path(ClipPathId(clip_path_id));
+            writeln!(self.writer,
+                     "        <clipPath id=\"clip-{}\"><path{} d=\"{:?}\" /></clipPath>",
+                     clip_path_id,
+                     FillRuleAttribute("clip-rule", clip_path.fill_rule()),
+                     clip_path.outline())?;
+        }
+        writeln!(self.writer, "    </defs>")
+    }
+
+    fn write_path(&mut self, path_index: usize) -> io::Result<()> {
+        let scene = self.scene;
+        let path = scene.path(path_index);
+        if !path.is_visible() {
+            return Ok(());
+        }
+
+        match path.source() {
+            Some(PathSource::Text(ref text_run)) if self.options.text_as_text => {
+                // Every glyph in the run refers to the same text, so write it once.
+                if let Some(ref last_text_run) = self.last_text_run {
+                    if Arc::ptr_eq(last_text_run, text_run) {
+                        return Ok(());
+                    }
+                }
+                self.last_text_run = Some((*text_run).clone());
+
+                let paint = match text_run.stroke {
+                    None => self.paint_attributes("fill", path, path_index, &text_run.transform)?,
+                    Some(ref style) => {
+                        let stroke = self.paint_attributes("stroke",
+                                                           path,
+                                                           path_index,
+                                                           &text_run.transform)?;
+                        format!(" fill=\"none\"{}{}", stroke, StrokeAttributes(style, &[], 0.0))
+                    }
+                };
+                self.write_element(path, &text_run.transform, |writer, attributes| {
+                    writeln!(writer,
+                             "<text{} font-family=\"{}\" font-size=\"{}\"{}>{}</text>",
+                             attributes,
+                             Escaped(&text_run.font_family),
+                             text_run.font_size,
+                             paint,
+                             Escaped(&text_run.text))
+                })
+            }
+            Some(PathSource::Stroke {
+                ref outline,
+                ref transform,
+                ref style,
+                ref line_dash,
+                line_dash_offset,
+            }) => {
+                let stroke = self.paint_attributes("stroke", path, path_index, transform)?;
+                let stroke = format!(" fill=\"none\"{}{}",
+                                     stroke,
+                                     StrokeAttributes(style, line_dash, *line_dash_offset));
+                self.write_element(path, transform, |writer, attributes| {
+                    writeln!(writer, "<path{}{} d=\"{:?}\" />", attributes, stroke, outline)
+                })
+            }
+            _ => {
+                let transform = Transform2F::default();
+                let fill = self.paint_attributes("fill", path, path_index, &transform)?;
+                let fill_rule = FillRuleAttribute("fill-rule", path.fill_rule());
+                self.write_element(path, &transform, |writer, attributes| {
+                    writeln!(writer,
+                             "<path{}{}{} d=\"{:?}\" />",
+                             attributes,
+                             fill,
+                             fill_rule,
+                             path.outline())
+                })
+            }
+        }
+    }
+
+    // Writes an element with `write`, which receives extra attributes to put on it: either those
+    // that all paths share, or, if the element has its own transform, the transform. In the
+    // latter case, the element is wrapped in a group carrying the shared attributes, so that the
+    // clip path stays in scene space.
+    fn write_element<F>(&mut self, path: &DrawPath, transform: &Transform2F, write: F)
+                        -> io::Result<()>
+                        where F: FnOnce(&mut W, &str) -> io::Result<()> {
+        let mut common = String::new();
+        if !path.name().is_empty() {
+            common.push_str(&format!(" id=\"{}\"", Escaped(path.name())));
+        }
+        if let Some(clip_path_id) = path.clip_path() {
+            common.push_str(&format!(" clip-path=\"url(#clip-{})\"", clip_path_id.0));
+        }
+        if path.opacity() != !0 {
+            common.push_str(&format!(" opacity=\"{}\"", path.opacity() as f32 / 255.0));
+        }
+        if let Some(blend_mode) = css_blend_mode(path.blend_mode()) {
+            common.push_str(&format!(" style=\"mix-blend-mode: {}\"", blend_mode));
+        }
+
+        if transform.is_identity() {
+            write!(self.writer, "    ")?;
+            return write(self.writer, &common);
+        }
+
+        writeln!(self.writer, "    <g{}>", common)?;
+        write!(self.writer, "        ")?;
+        write(self.writer, &format!(" transform=\"{}\"", Matrix(transform)))?;
+        writeln!(self.writer, "    </g>")
+    }
+
+    // Returns attributes that paint with the path's paint, writing a gradient or pattern
+    // definition first if necessary. `transform` is the transform of the element being painted,
+    // which the definition must undo, since paints are in scene space.
+    fn paint_attributes(&mut self,
+                        attribute: &str,
+                        path: &DrawPath,
+                        path_index: usize,
+                        transform: &Transform2F)
+                        -> io::Result<String> {
+        let paint_id = path.paint();
+        let id = if transform.is_identity() {
+            format!("paint-{}", paint_id.0)
+        } else {
+            format!("paint-{}-{}", paint_id.0, path_index)
+        };
+
+        match *self.scene.paint(paint_id) {
+            Paint::Color(color) => Ok(ColorAttributes(attribute, color).to_string()),
+            Paint::Gradient(ref gradient) => {
+                if self.written_paints.insert(id.clone()) {
+                    self.write_gradient(&id, gradient, &transform.inverse())?;
+                }
+                Ok(format!(" {}=\"url(#{})\"", attribute, id))
+            }
+            Paint::Pattern(ref pattern) => {
+                if let PatternSource::Image(_) = pattern.source {
+                    if self.written_paints.insert(id.clone()) {
+                        self.write_pattern(&id, pattern, &transform.inverse())?;
+                    }
+                    Ok(format!(" {}=\"url(#{})\"", attribute, id))
+                } else {
+                    // Render targets and GPU textures have no contents until they're rendered.
+                    Ok(format!(" {}=\"none\"", attribute))
+                }
+            }
+            // Custom paints are computed by GPU shaders, which SVG can't run.
+            Paint::Custom(_) => Ok(format!(" {}=\"none\"", attribute)),
+        }
+    }
+
+    fn write_gradient(&mut self, id: &str, gradient: &Gradient, transform: &Transform2F)
+                      -> io::Result<()> {
+        let element = match gradient.geometry {
+            GradientGeometry::Linear(line) => {
+                writeln!(self.writer,
+                         "    <defs><linearGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" \
+                          gradientTransform=\"{}\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\">",
+                         id,
+                         Matrix(transform),
+                         line.from_x(),
+                         line.from_y(),
+                         line.to_x(),
+                         line.to_y())?;
+                "linearGradient"
+            }
+            GradientGeometry::Radial { line, start_radius, end_radius } => {
+                // The start circle is SVG's focal circle.
+                writeln!(self.writer,
+                         "    <defs><radialGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" \
+                          gradientTransform=\"{}\" fx=\"{}\" fy=\"{}\" fr=\"{}\" cx=\"{}\" \
+                          cy=\"{}\" r=\"{}\">",
+                         id,
+                         Matrix(transform),
+                         line.from_x(),
+                         line.from_y(),
+                         start_radius,
+                         line.to_x(),
+                         line.to_y(),
+                         end_radius)?;
+                "radialGradient"
+            }
+        };
+
+        for stop in gradient.stops() {
+            writeln!(self.writer,
+                     "        <stop offset=\"{}\"{} />",
+                     stop.offset,
+                     ColorAttributes("stop-color", stop.color))?;
+        }
+        writeln!(self.writer, "    </{}></defs>", element)
+    }
+
+    fn write_pattern(&mut self, id: &str, pattern: &Pattern, transform: &Transform2F)
+                     -> io::Result<()> {
+        let image = match pattern.source {
+            PatternSource::Image(ref image) => image,
+            _ => unreachable!(),
+        };
+
+        // SVG patterns always tile, so patterns that don't repeat are approximated.
+        let size = image.size();
+        let rendering = if pattern.flags.contains(PatternFlags::NO_SMOOTHING) {
+            " style=\"image-rendering: pixelated\""
+        } else {
+            ""
+        };
+        let png = png::encode_rgba(size, image.pixels());
+        writeln!(self.writer,
+                 "    <defs><pattern id=\"{}\" patternUnits=\"userSpaceOnUse\" \
+                  patternTransform=\"{}\" width=\"{}\" height=\"{}\"><image width=\"{}\" \
+                  height=\"{}\"{} xlink:href=\"data:image/png;base64,{}\" /></pattern></defs>",
+                 id,
+                 Matrix(&(*transform * pattern.transform)),
+                 size.x(),
+                 size.y(),
+                 size.x(),
+                 size.y(),
+                 rendering,
+                 Base64(&png))
+    }
+}
+
+fn css_blend_mode(blend_mode: BlendMode) -> Option<&'static str> {
+    match blend_mode {
+        BlendMode::Multiply => Some("multiply"),
+        BlendMode::Screen => Some("screen"),
+        BlendMode::Overlay => Some("overlay"),
+        BlendMode::Darken => Some("darken"),
+        BlendMode::Lighten => Some("lighten"),
+        BlendMode::ColorDodge => Some("color-dodge"),
+        BlendMode::ColorBurn => Some("color-burn"),
+        BlendMode::HardLight => Some("hard-light"),
+        BlendMode::SoftLight => Some("soft-light"),
+        BlendMode::Difference => Some("difference"),
+        BlendMode::Exclusion => Some("exclusion"),
+        BlendMode::Hue => Some("hue"),
+        BlendMode::Saturation => Some("saturation"),
+        BlendMode::Color => Some("color"),
+        BlendMode::Luminosity => Some("luminosity"),
+        BlendMode::Lighter => Some("plus-lighter"),
+        // Porter-Duff operators other than source-over have no CSS blend mode.
+        BlendMode::SrcOver |
+        BlendMode::Clear |
+        BlendMode::DestOver |
+        BlendMode::DestOut |
+        BlendMode::SrcAtop |
+        BlendMode::Xor |
+        BlendMode::Copy |
+        BlendMode::SrcIn |
+        BlendMode::DestIn |
+        BlendMode::SrcOut |
+        BlendMode::DestAtop => None,
+    }
+}
+
+// An SVG `matrix()` transform.
+struct Matrix<'a>(&'a Transform2F);
+
+impl<'a> Display for Matrix<'a> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter,
+               "matrix({} {} {} {} {} {})",
+               self.0.m11(),
+               self.0.m21(),
+               self.0.m12(),
+               self.0.m22(),
+               self.0.vector.x(),
+               self.0.vector.y())
+    }
+}
+
+// A color, as a pair of attributes such as `fill` and `fill-opacity`.
+struct ColorAttributes<'a>(&'a str, ColorU);
+
+impl<'a> Display for ColorAttributes<'a> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let ColorAttributes(attribute, color) = *self;
+        write!(formatter, " {}=\"#{:02x}{:02x}{:02x}\"", attribute, color.r, color.g, color.b)?;
+        if !color.is_opaque() {
+            let attribute = match attribute {
+                "stop-color" => "stop",
+                attribute => attribute,
+            };
+            write!(formatter, " {}-opacity=\"{}\"", attribute, color.a as f32 / 255.0)?;
+        }
+        Ok(())
+    }
+}
+
+struct FillRuleAttribute(&'static str, FillRule);
+
+impl Display for FillRuleAttribute {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match self.1 {
+            FillRule::Winding => Ok(()),
+            FillRule::EvenOdd => write!(formatter, " {}=\"evenodd\"", self.0),
+        }
+    }
+}
+
+struct StrokeAttributes<'a>(&'a StrokeStyle, &'a [f32], f32);
+
+impl<'a> Display for StrokeAttributes<'a> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let StrokeAttributes(style, line_dash, line_dash_offset) = *self;
+        write!(formatter, " stroke-width=\"{}\"", style.line_width)?;
+        match style.line_cap {
+            LineCap::Butt => {}
+            LineCap::Square => write!(formatter, " stroke-linecap=\"square\"")?,
+            LineCap::Round => write!(formatter, " stroke-linecap=\"round\"")?,
+        }
+        match style.line_join {
+            LineJoin::Miter(miter_limit) => {
+                write!(formatter, " stroke-miterlimit=\"{}\"", miter_limit)?
+            }
+            LineJoin::Bevel => write!(formatter, " stroke-linejoin=\"bevel\"")?,
+            LineJoin::Round => write!(formatter, " stroke-linejoin=\"round\"")?,
+        }
+        if !line_dash.is_empty() {
+            write!(formatter, " stroke-dasharray=\"")?;
+            for (index, length) in line_dash.iter().enumerate() {
+                if index > 0 {
+                    write!(formatter, " ")?;
+                }
+                write!(formatter, "{}", length)?;
+            }
+            write!(formatter, "\"")?;
+            if line_dash_offset != 0.0 {
+                write!(formatter, " stroke-dashoffset=\"{}\"", line_dash_offset)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Text escaped for use in attribute values and character data.
+struct Escaped<'a>(&'a str);
+
+impl<'a> Display for Escaped<'a> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        for character in self.0.chars() {
+            match character {
+                '&' => formatter.write_str("&amp;")?,
+                '<' => formatter.write_str("&lt;")?,
+                '>' => formatter.write_str("&gt;")?,
+                '"' => formatter.write_str("&quot;")?,
+                character => write!(formatter, "{}", character)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Base64<'a>(&'a [u8]);
+
+impl<'a> Display for Base64<'a> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        const ALPHABET: &[u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        for chunk in self.0.chunks(3) {
+            let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+            let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+            for index in 0..4 {
+                if index <= chunk.len() {
+                    let sextet = (bits >> (18 - index * 6)) & 0x3f;
+                    write!(formatter, "{}", ALPHABET[sextet as usize] as char)?;
+                } else {
+                    formatter.write_str("=")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Base64, SvgExportOptions};
+    use crate::Export;
+    use crate::test::rect_outline;
+    use pathfinder_color::ColorU;
+    use pathfinder_content::effects::BlendMode;
+    use pathfinder_content::gradient::{ColorStop, Gradient};
+    use pathfinder_content::outline::Outline;
+    use pathfinder_content::stroke::{LineCap, LineJoin, StrokeStyle};
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::Vector2F;
+    use pathfinder_renderer::paint::Paint;
+    use pathfinder_renderer::scene::{DrawPath, PathSource, Scene, TextRun};
+    use std::sync::Arc;
+
+    fn export(scene: &Scene, options: &SvgExportOptions) -> String {
+        let mut output = vec![];
+        scene.export_svg(&mut output, options).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_paints_and_strokes() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(100.0)));
+
+        let mut gradient = Gradient::linear(LineSegment2F::new(Vector2F::default(),
+                                                               Vector2F::splat(10.0)));
+        gradient.add_color_stop(ColorStop::new(ColorU::new(255, 0, 0, 255), 0.0));
+        gradient.add_color_stop(ColorStop::new(ColorU::new(0, 0, 255, 128), 1.0));
+        let gradient = scene.push_paint(&Paint::Gradient(gradient));
+        let mut path = DrawPath::new(rect_outline(), gradient);
+        path.set_blend_mode(BlendMode::Multiply);
+        path.set_opacity(128);
+        scene.push_path(path);
+
+        let black = scene.push_paint(&Paint::black());
+        let mut path = DrawPath::new(Outline::new(), black);
+        path.set_source(Some(PathSource::Stroke {
+            outline: rect_outline(),
+            transform: Transform2F::from_uniform_scale(2.0),
+            style: StrokeStyle {
+                line_width: 3.0,
+                line_cap: LineCap::Round,
+                line_join: LineJoin::Bevel,
+            },
+            line_dash: vec![4.0, 2.0],
+            line_dash_offset: 1.0,
+        }));
+        scene.push_path(path);
+
+        let svg = export(&scene, &SvgExportOptions::default());
+        assert!(svg.contains("<linearGradient id=\"paint-0\""));
+        assert!(svg.contains("stop-color=\"#0000ff\" stop-opacity=\"0.5019608\""));
+        assert!(svg.contains("fill=\"url(#paint-0)\""));
+        assert!(svg.contains("style=\"mix-blend-mode: multiply\""));
+        assert!(svg.contains("<path transform=\"matrix(2 0 0 2 0 0)\" fill=\"none\" \
+                              stroke=\"#000000\" stroke-width=\"3\" stroke-linecap=\"round\" \
+                              stroke-linejoin=\"bevel\" stroke-dasharray=\"4 2\" \
+                              stroke-dashoffset=\"1\""));
+    }
+
+    #[test]
+    fn test_text() {
+        let mut scene = Scene::new();
+        let black = scene.push_paint(&Paint::black());
+        let text_run = Arc::new(TextRun {
+            text: "A & B".to_owned(),
+            font_family: "Serif".to_owned(),
+            font_size: 12.0,
+            transform: Transform2F::from_translation(Vector2F::new(5.0, 20.0)),
+            stroke: None,
+        });
+        for _ in 0..3 {
+            let mut path = DrawPath::new(rect_outline(), black);
+            path.set_source(Some(PathSource::Text(text_run.clone())));
+            scene.push_path(path);
+        }
+
+        let svg = export(&scene, &SvgExportOptions::default());
+        assert_eq!(svg.matches("<path").count(), 3);
+
+        let svg = export(&scene, &SvgExportOptions { text_as_text: true });
+        assert_eq!(svg.matches("<text").count(), 1);
+        assert!(svg.contains("font-family=\"Serif\" font-size=\"12\" fill=\"#000000\">A &amp; \
+                              B</text>"));
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(Base64(b"Man").to_string(), "TWFu");
+        assert_eq!(Base64(b"Ma").to_string(), "TWE=");
+        assert_eq!(Base64(b"M").to_string(), "TQ==");
+    }
+}
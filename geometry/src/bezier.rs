@@ -0,0 +1,360 @@
+// pathfinder/geometry/src/bezier.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Cubic Bézier curves and intersections between them.
+//!
+//! Intersections are found with Bézier clipping (Sederberg and Nishita, "Curve intersection using
+//! Bézier clipping", 1990): each curve is repeatedly clipped to the parameter range in which it can
+//! lie inside the "fat line" bounding the other curve, and is split in half whenever clipping
+//! fails to make enough progress.
+
+use crate::line_segment::LineSegment2F;
+use crate::rect::RectF;
+use crate::util::lerp;
+use crate::vector::Vector2F;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// If clipping keeps more than this fraction of a curve, split the curve instead.
+const MAX_CLIPPED_FRACTION: f32 = 0.8;
+
+// Bounds the work done for degenerate inputs, such as overlapping curves, where clipping never
+// converges and every subdivision survives.
+const MAX_CLIPPING_DEPTH: u32 = 64;
+const MAX_CLIPPING_STEPS: u32 = 4096;
+
+const EPSILON: f32 = 1.0e-6;
+
+/// A cubic Bézier curve.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CubicBezier2F {
+    pub from: Vector2F,
+    pub ctrl0: Vector2F,
+    pub ctrl1: Vector2F,
+    pub to: Vector2F,
+}
+
+/// A point at which two curves cross or touch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Intersection {
+    /// The parameter along the curve the intersection method was called on.
+    pub t: f32,
+    /// The parameter along the other curve or line segment.
+    pub other_t: f32,
+    pub point: Vector2F,
+}
+
+impl CubicBezier2F {
+    #[inline]
+    pub fn new(from: Vector2F, ctrl0: Vector2F, ctrl1: Vector2F, to: Vector2F) -> CubicBezier2F {
+        CubicBezier2F { from, ctrl0, ctrl1, to }
+    }
+
+    /// Degree-elevates a quadratic Bézier curve.
+    #[inline]
+    pub fn from_quadratic(from: Vector2F, ctrl: Vector2F, to: Vector2F) -> CubicBezier2F {
+        CubicBezier2F::new(from, from.lerp(ctrl, 2.0 / 3.0), to.lerp(ctrl, 2.0 / 3.0), to)
+    }
+
+    /// Returns a curve tracing the given line segment with uniform speed, so that curve
+    /// parameters are also line segment parameters.
+    #[inline]
+    pub fn from_line_segment(line_segment: LineSegment2F) -> CubicBezier2F {
+        CubicBezier2F::new(line_segment.from(),
+                           line_segment.sample(1.0 / 3.0),
+                           line_segment.sample(2.0 / 3.0),
+                           line_segment.to())
+    }
+
+    #[inline]
+    pub fn baseline(&self) -> LineSegment2F {
+        LineSegment2F::new(self.from, self.to)
+    }
+
+    #[inline]
+    pub fn sample(&self, t: f32) -> Vector2F {
+        let (p01, p12, p23) = (self.from.lerp(self.ctrl0, t),
+                               self.ctrl0.lerp(self.ctrl1, t),
+                               self.ctrl1.lerp(self.to, t));
+        let (p012, p123) = (p01.lerp(p12, t), p12.lerp(p23, t));
+        p012.lerp(p123, t)
+    }
+
+    /// Splits this curve at `t` with de Casteljau's algorithm.
+    pub fn split(&self, t: f32) -> (CubicBezier2F, CubicBezier2F) {
+        let (p01, p12, p23) = (self.from.lerp(self.ctrl0, t),
+                               self.ctrl0.lerp(self.ctrl1, t),
+                               self.ctrl1.lerp(self.to, t));
+        let (p012, p123) = (p01.lerp(p12, t), p12.lerp(p23, t));
+        let p0123 = p012.lerp(p123, t);
+        (CubicBezier2F::new(self.from, p01, p012, p0123),
+         CubicBezier2F::new(p0123, p123, p23, self.to))
+    }
+
+    /// Returns the portion of this curve between the parameters `t0` and `t1`.
+    pub fn subcurve(&self, t0: f32, t1: f32) -> CubicBezier2F {
+        let head = if t1 >= 1.0 { *self } else { self.split(t1).0 };
+        if t0 <= 0.0 || t1 <= 0.0 {
+            head
+        } else {
+            head.split(t0 / t1).1
+        }
+    }
+
+    /// Returns a rectangle that encloses this curve.
+    ///
+    /// This is the bounding box of the control points, so it isn't necessarily tight.
+    #[inline]
+    pub fn bounds(&self) -> RectF {
+        RectF::from_points(self.from.min(self.ctrl0).min(self.ctrl1).min(self.to),
+                           self.from.max(self.ctrl0).max(self.ctrl1).max(self.to))
+    }
+
+    /// Finds the points at which this curve meets `other`.
+    ///
+    /// Intersections are located to within a small multiple of `tolerance`, in the curves'
+    /// coordinate space, and are returned in order of increasing `t`. Overlapping curves produce a bounded number of
+    /// intersections along the overlap rather than a description of it.
+    pub fn intersect(&self, other: &CubicBezier2F, tolerance: f32) -> Vec<Intersection> {
+        let mut clipper = Clipper { tolerance, steps: 0, intersections: vec![] };
+        clipper.clip(&Interval::new(self), &Interval::new(other), false, 0);
+        clipper.intersections.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+
+        // Neighboring subdivisions can converge on the same intersection, especially where the
+        // curves cross at a shallow angle. Two results are the same intersection if the curves
+        // stay close to one another between them. Each result may be off by a couple of
+        // tolerances, since the fat lines are widened, so allow for that.
+        let mut intersections: Vec<Intersection> = vec![];
+        for intersection in clipper.intersections {
+            if let Some(last) = intersections.last() {
+                let this_point = self.sample(lerp(last.t, intersection.t, 0.5));
+                let other_point = other.sample(lerp(last.other_t, intersection.other_t, 0.5));
+                if (this_point - other_point).length() <= tolerance * 4.0 {
+                    continue;
+                }
+            }
+            intersections.push(intersection);
+        }
+        intersections
+    }
+
+    /// Finds the points at which this curve meets the given line segment.
+    ///
+    /// `other_t` in the results is the parameter along the line segment.
+    pub fn intersect_line_segment(&self, line_segment: LineSegment2F, tolerance: f32)
+                                  -> Vec<Intersection> {
+        self.intersect(&CubicBezier2F::from_line_segment(line_segment), tolerance)
+    }
+}
+
+// A piece of a curve, along with the parameter range that it covers on the original curve.
+struct Interval {
+    curve: CubicBezier2F,
+    t_min: f32,
+    t_max: f32,
+}
+
+impl Interval {
+    fn new(curve: &CubicBezier2F) -> Interval {
+        Interval { curve: *curve, t_min: 0.0, t_max: 1.0 }
+    }
+
+    fn subinterval(&self, t0: f32, t1: f32) -> Interval {
+        Interval {
+            curve: self.curve.subcurve(t0, t1),
+            t_min: lerp(self.t_min, self.t_max, t0),
+            t_max: lerp(self.t_min, self.t_max, t1),
+        }
+    }
+
+    fn size(&self) -> f32 {
+        self.curve.bounds().size().length()
+    }
+
+    fn mid_t(&self) -> f32 {
+        lerp(self.t_min, self.t_max, 0.5)
+    }
+}
+
+struct Clipper {
+    tolerance: f32,
+    steps: u32,
+    intersections: Vec<Intersection>,
+}
+
+impl Clipper {
+    // Clips `a` against `b`'s fat line, then continues with the roles reversed. `swapped`
+    // records whether `a` is the curve that the caller passed as `other`.
+    fn clip(&mut self, a: &Interval, b: &Interval, swapped: bool, depth: u32) {
+        self.steps += 1;
+        if self.steps > MAX_CLIPPING_STEPS {
+            return;
+        }
+
+        let tolerance = Vector2F::splat(self.tolerance);
+        if !a.curve.bounds().dilate(tolerance).intersects(b.curve.bounds().dilate(tolerance)) {
+            return;
+        }
+
+        if (a.size() <= self.tolerance && b.size() <= self.tolerance) ||
+                depth >= MAX_CLIPPING_DEPTH {
+            self.push_intersection(a, b, swapped);
+            return;
+        }
+
+        let (t0, t1) = match fat_line_clip_range(&a.curve, &b.curve, self.tolerance) {
+            None => return,
+            Some(range) => range,
+        };
+
+        if t1 - t0 <= MAX_CLIPPED_FRACTION {
+            let a = a.subinterval(t0, t1);
+            self.clip(b, &a, !swapped, depth + 1);
+            return;
+        }
+
+        // Clipping didn't make enough progress, probably because there are multiple
+        // intersections. Split the larger curve and try each half separately.
+        if a.size() >= b.size() {
+            self.clip(b, &a.subinterval(0.0, 0.5), !swapped, depth + 1);
+            self.clip(b, &a.subinterval(0.5, 1.0), !swapped, depth + 1);
+        } else {
+            self.clip(a, &b.subinterval(0.0, 0.5), swapped, depth + 1);
+            self.clip(a, &b.subinterval(0.5, 1.0), swapped, depth + 1);
+        }
+    }
+
+    fn push_intersection(&mut self, a: &Interval, b: &Interval, swapped: bool) {
+        let (this, other) = if swapped { (b, a) } else { (a, b) };
+        self.intersections.push(Intersection {
+            t: this.mid_t(),
+            other_t: other.mid_t(),
+            point: this.curve.sample(0.5),
+        });
+    }
+}
+
+// Returns the parameter range of `a` that may lie within the fat line bounding `b`, or `None`
+// if `a` lies entirely outside it.
+//
+// The fat line is widened by `tolerance` so that curves which already agree to within the
+// tolerance aren't rejected because of rounding error.
+fn fat_line_clip_range(a: &CubicBezier2F, b: &CubicBezier2F, tolerance: f32)
+                       -> Option<(f32, f32)> {
+    // Choose the fat line's direction. Use the baseline if it's not degenerate, as the usual
+    // tighter bounds only apply when both endpoints lie on the line.
+    let mut direction = b.to - b.from;
+    let endpoints_on_line = direction.square_length() > EPSILON;
+    if !endpoints_on_line {
+        direction = if (b.ctrl0 - b.from).square_length() >= (b.ctrl1 - b.from).square_length() {
+            b.ctrl0 - b.from
+        } else {
+            b.ctrl1 - b.from
+        };
+        if direction.square_length() <= EPSILON {
+            // `b` is a point, so the fat line is meaningless. Let the caller subdivide `a`.
+            return Some((0.0, 1.0));
+        }
+    }
+
+    let normal = Vector2F::new(-direction.y(), direction.x()).normalize();
+    let distance = |point: Vector2F| (point - b.from).dot(normal);
+
+    let (d1, d2) = (distance(b.ctrl0), distance(b.ctrl1));
+    let (d_min, d_max) = if endpoints_on_line {
+        let factor = if d1 * d2 > 0.0 { 3.0 / 4.0 } else { 4.0 / 9.0 };
+        (factor * f32::min(0.0, f32::min(d1, d2)), factor * f32::max(0.0, f32::max(d1, d2)))
+    } else {
+        let d3 = distance(b.to);
+        (f32::min(f32::min(0.0, d1), f32::min(d2, d3)),
+         f32::max(f32::max(0.0, d1), f32::max(d2, d3)))
+    };
+    let (d_min, d_max) = (d_min - tolerance, d_max + tolerance);
+
+    // The signed distance from `a` to the fat line is itself a Bézier curve with these control
+    // points. Intersect its convex hull with the band `[d_min, d_max]`. Testing every pair of
+    // control points covers all the hull edges.
+    let points = [
+        (0.0, distance(a.from)),
+        (1.0 / 3.0, distance(a.ctrl0)),
+        (2.0 / 3.0, distance(a.ctrl1)),
+        (1.0, distance(a.to)),
+    ];
+
+    let (mut t_min, mut t_max) = (f32::INFINITY, f32::NEG_INFINITY);
+    for (index, &(t, d)) in points.iter().enumerate() {
+        if d >= d_min && d <= d_max {
+            t_min = f32::min(t_min, t);
+            t_max = f32::max(t_max, t);
+        }
+        for &(other_t, other_d) in &points[(index + 1)..] {
+            for &bound in &[d_min, d_max] {
+                if (d - bound) * (other_d - bound) < 0.0 {
+                    let crossing_t = lerp(t, other_t, (bound - d) / (other_d - d));
+                    t_min = f32::min(t_min, crossing_t);
+                    t_max = f32::max(t_max, crossing_t);
+                }
+            }
+        }
+    }
+
+    if t_min > t_max {
+        None
+    } else {
+        Some((f32::max(t_min - EPSILON, 0.0), f32::min(t_max + EPSILON, 1.0)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bezier::CubicBezier2F;
+    use crate::line_segment::LineSegment2F;
+    use crate::vector::Vector2F;
+
+    const TOLERANCE: f32 = 0.001;
+
+    #[test]
+    fn test_crossing_curves() {
+        // Two S-curves that cross three times.
+        let a = CubicBezier2F::new(Vector2F::new(0.0, 0.0),
+                                   Vector2F::new(100.0, 300.0),
+                                   Vector2F::new(200.0, -200.0),
+                                   Vector2F::new(300.0, 100.0));
+        let b = CubicBezier2F::new(Vector2F::new(0.0, 100.0),
+                                   Vector2F::new(100.0, -200.0),
+                                   Vector2F::new(200.0, 300.0),
+                                   Vector2F::new(300.0, 0.0));
+        let intersections = a.intersect(&b, TOLERANCE);
+        assert_eq!(intersections.len(), 3);
+        for intersection in intersections {
+            let (point_a, point_b) = (a.sample(intersection.t), b.sample(intersection.other_t));
+            assert!((point_a - point_b).length() < 0.01);
+            assert!((point_a - intersection.point).length() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_curve_and_line() {
+        let curve = CubicBezier2F::from_quadratic(Vector2F::new(0.0, 0.0),
+                                                  Vector2F::new(50.0, 100.0),
+                                                  Vector2F::new(100.0, 0.0));
+        let line = LineSegment2F::new(Vector2F::new(-10.0, 25.0), Vector2F::new(110.0, 25.0));
+        let mut intersections = curve.intersect_line_segment(line, TOLERANCE);
+        intersections.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        assert_eq!(intersections.len(), 2);
+        for intersection in intersections {
+            assert!((intersection.point.y() - 25.0).abs() < 0.01);
+            assert!((line.sample(intersection.other_t) - intersection.point).length() < 0.01);
+        }
+
+        let miss = LineSegment2F::new(Vector2F::new(-10.0, 60.0), Vector2F::new(110.0, 60.0));
+        assert!(curve.intersect_line_segment(miss, TOLERANCE).is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+// pathfinder/geometry/src/kurbo.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversions to and from `kurbo` types.
+//!
+//! `kurbo` works in double precision, so converting to Pathfinder types loses precision.
+
+use crate::rect::RectF;
+use crate::transform2d::Transform2F;
+use crate::vector::Vector2F;
+use kurbo::{Affine, Point, Rect, Vec2};
+
+impl From<Point> for Vector2F {
+    #[inline]
+    fn from(point: Point) -> Vector2F {
+        Vector2F::new(point.x as f32, point.y as f32)
+    }
+}
+
+impl From<Vector2F> for Point {
+    #[inline]
+    fn from(vector: Vector2F) -> Point {
+        Point::new(vector.x() as f64, vector.y() as f64)
+    }
+}
+
+impl From<Vec2> for Vector2F {
+    #[inline]
+    fn from(vector: Vec2) -> Vector2F {
+        Vector2F::new(vector.x as f32, vector.y as f32)
+    }
+}
+
+impl From<Vector2F> for Vec2 {
+    #[inline]
+    fn from(vector: Vector2F) -> Vec2 {
+        Vec2::new(vector.x() as f64, vector.y() as f64)
+    }
+}
+
+impl From<Rect> for RectF {
+    #[inline]
+    fn from(rect: Rect) -> RectF {
+        // `kurbo` rectangles may have their corners in either order.
+        let (p0, p1) = (Vector2F::new(rect.x0 as f32, rect.y0 as f32),
+                        Vector2F::new(rect.x1 as f32, rect.y1 as f32));
+        RectF::from_points(p0.min(p1), p0.max(p1))
+    }
+}
+
+impl From<RectF> for Rect {
+    #[inline]
+    fn from(rect: RectF) -> Rect {
+        Rect::new(rect.min_x() as f64,
+                  rect.min_y() as f64,
+                  rect.max_x() as f64,
+                  rect.max_y() as f64)
+    }
+}
+
+// `kurbo` stores the matrix column by column: `[m11, m21, m12, m22, m31, m32]` in our terms.
+impl From<Affine> for Transform2F {
+    #[inline]
+    fn from(affine: Affine) -> Transform2F {
+        let [a, b, c, d, e, f] = affine.as_coeffs();
+        Transform2F::row_major(a as f32, c as f32, b as f32, d as f32, e as f32, f as f32)
+    }
+}
+
+impl From<Transform2F> for Affine {
+    #[inline]
+    fn from(transform: Transform2F) -> Affine {
+        Affine::new([transform.m11() as f64,
+                     transform.m21() as f64,
+                     transform.m12() as f64,
+                     transform.m22() as f64,
+                     transform.vector.x() as f64,
+                     transform.vector.y() as f64])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::transform2d::Transform2F;
+    use crate::vector::Vector2F;
+    use kurbo::{Affine, Point};
+
+    #[test]
+    fn test_affine() {
+        let affine = Affine::new([1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let transform = Transform2F::from(affine);
+        let point = Point::new(-2.0, 0.5);
+        assert_eq!(transform * Vector2F::from(point), Vector2F::from(affine * point));
+        assert_eq!(Affine::from(transform), affine);
+    }
+}
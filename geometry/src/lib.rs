@@ -9,8 +9,15 @@
 // except according to those terms.
 
 //! Basic geometry and linear algebra primitives, optimized with SIMD.
+//!
+//! Without the default `pf-std` feature, this crate is `no_std` and only needs `alloc`.
+
+#![cfg_attr(not(feature = "pf-std"), no_std)]
+
+extern crate alloc;
 
 pub mod angle;
+pub mod bezier;
 pub mod line_segment;
 pub mod rect;
 pub mod transform2d;
@@ -18,3 +25,8 @@ pub mod transform3d;
 pub mod unit_vector;
 pub mod util;
 pub mod vector;
+
+#[cfg(feature = "pf-kurbo")]
+mod kurbo;
+#[cfg(feature = "pf-serde")]
+mod serialization;
@@ -12,9 +12,11 @@
 
 use crate::transform2d::Matrix2x2F;
 use crate::vector::Vector2F;
+#[cfg(feature = "pf-f64")]
+use crate::vector::Vector2D;
 use crate::util;
 use pathfinder_simd::default::F32x4;
-use std::ops::{Add, Sub};
+use core::ops::{Add, Sub};
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct LineSegment2F(pub F32x4);
@@ -260,6 +262,12 @@ impl LineSegment2F {
     pub fn is_zero_length(self) -> bool {
         self.vector().is_zero()
     }
+
+    #[cfg(feature = "pf-f64")]
+    #[inline]
+    pub fn to_f64(self) -> LineSegment2D {
+        LineSegment2D::new(self.from().to_f64(), self.to().to_f64())
+    }
 }
 
 impl Add<Vector2F> for LineSegment2F {
@@ -278,6 +286,98 @@ impl Sub<Vector2F> for LineSegment2F {
     }
 }
 
+/// A line segment with 64-bit floating point coordinates.
+#[cfg(feature = "pf-f64")]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct LineSegment2D {
+    from: Vector2D,
+    to: Vector2D,
+}
+
+#[cfg(feature = "pf-f64")]
+impl LineSegment2D {
+    #[inline]
+    pub fn new(from: Vector2D, to: Vector2D) -> LineSegment2D {
+        LineSegment2D { from, to }
+    }
+
+    #[inline]
+    pub fn from(self) -> Vector2D {
+        self.from
+    }
+
+    #[inline]
+    pub fn to(self) -> Vector2D {
+        self.to
+    }
+
+    #[inline]
+    pub fn set_from(&mut self, point: Vector2D) {
+        self.from = point
+    }
+
+    #[inline]
+    pub fn set_to(&mut self, point: Vector2D) {
+        self.to = point
+    }
+
+    #[inline]
+    pub fn vector(self) -> Vector2D {
+        self.to - self.from
+    }
+
+    #[inline]
+    pub fn square_length(self) -> f64 {
+        self.vector().square_length()
+    }
+
+    #[inline]
+    pub fn sample(self, t: f64) -> Vector2D {
+        self.from + self.vector().scale(t)
+    }
+
+    #[inline]
+    pub fn midpoint(self) -> Vector2D {
+        self.sample(0.5)
+    }
+
+    #[inline]
+    pub fn reversed(self) -> LineSegment2D {
+        LineSegment2D::new(self.to, self.from)
+    }
+
+    #[inline]
+    pub fn is_zero_length(self) -> bool {
+        self.vector().is_zero()
+    }
+
+    /// Converts this line segment to single precision.
+    ///
+    /// Precision is lost here, so this should be done after the view transform has been applied.
+    #[inline]
+    pub fn to_f32(self) -> LineSegment2F {
+        LineSegment2F::new(self.from.to_f32(), self.to.to_f32())
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Add<Vector2D> for LineSegment2D {
+    type Output = LineSegment2D;
+    #[inline]
+    fn add(self, point: Vector2D) -> LineSegment2D {
+        LineSegment2D::new(self.from + point, self.to + point)
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Sub<Vector2D> for LineSegment2D {
+    type Output = LineSegment2D;
+    #[inline]
+    fn sub(self, point: Vector2D) -> LineSegment2D {
+        LineSegment2D::new(self.from - point, self.to - point)
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 #[repr(C)]
 pub struct LineSegmentU4 {
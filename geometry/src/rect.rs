@@ -11,6 +11,8 @@
 //! 2D axis-aligned rectangles, optimized with SIMD.
 
 use crate::vector::{Vector2F, Vector2I};
+#[cfg(feature = "pf-f64")]
+use crate::vector::Vector2D;
 use pathfinder_simd::default::{F32x4, I32x4};
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
@@ -195,6 +197,119 @@ impl RectF {
     pub fn to_i32(&self) -> RectI {
         RectI(self.0.to_i32x4())
     }
+
+    #[cfg(feature = "pf-f64")]
+    #[inline]
+    pub fn to_f64(self) -> RectD {
+        RectD::from_points(self.origin().to_f64(), self.lower_right().to_f64())
+    }
+}
+
+/// An axis-aligned rectangle with 64-bit floating point coordinates.
+#[cfg(feature = "pf-f64")]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct RectD {
+    origin: Vector2D,
+    lower_right: Vector2D,
+}
+
+#[cfg(feature = "pf-f64")]
+impl RectD {
+    #[inline]
+    pub fn new(origin: Vector2D, size: Vector2D) -> RectD {
+        RectD { origin, lower_right: origin + size }
+    }
+
+    #[inline]
+    pub fn from_points(origin: Vector2D, lower_right: Vector2D) -> RectD {
+        RectD { origin, lower_right }
+    }
+
+    #[inline]
+    pub fn origin(self) -> Vector2D {
+        self.origin
+    }
+
+    #[inline]
+    pub fn size(self) -> Vector2D {
+        self.lower_right - self.origin
+    }
+
+    #[inline]
+    pub fn width(self) -> f64 {
+        self.size().x()
+    }
+
+    #[inline]
+    pub fn height(self) -> f64 {
+        self.size().y()
+    }
+
+    #[inline]
+    pub fn upper_right(self) -> Vector2D {
+        Vector2D::new(self.lower_right.x(), self.origin.y())
+    }
+
+    #[inline]
+    pub fn lower_left(self) -> Vector2D {
+        Vector2D::new(self.origin.x(), self.lower_right.y())
+    }
+
+    #[inline]
+    pub fn lower_right(self) -> Vector2D {
+        self.lower_right
+    }
+
+    #[inline]
+    pub fn contains_point(self, point: Vector2D) -> bool {
+        self.origin.x() <= point.x() && self.origin.y() <= point.y() &&
+            point.x() <= self.lower_right.x() && point.y() <= self.lower_right.y()
+    }
+
+    #[inline]
+    pub fn union_point(self, point: Vector2D) -> RectD {
+        RectD::from_points(self.origin.min(point), self.lower_right.max(point))
+    }
+
+    #[inline]
+    pub fn union_rect(self, other: RectD) -> RectD {
+        RectD::from_points(self.origin.min(other.origin),
+                           self.lower_right.max(other.lower_right))
+    }
+
+    #[inline]
+    pub fn intersects(self, other: RectD) -> bool {
+        self.origin.x() < other.lower_right.x() && self.origin.y() < other.lower_right.y() &&
+            other.origin.x() < self.lower_right.x() && other.origin.y() < self.lower_right.y()
+    }
+
+    #[inline]
+    pub fn intersection(self, other: RectD) -> Option<RectD> {
+        if !self.intersects(other) {
+            None
+        } else {
+            Some(RectD::from_points(self.origin.max(other.origin),
+                                    self.lower_right.min(other.lower_right)))
+        }
+    }
+
+    #[inline]
+    pub fn center(self) -> Vector2D {
+        self.origin + self.size().scale(0.5)
+    }
+
+    #[inline]
+    pub fn dilate(self, amount: Vector2D) -> RectD {
+        RectD::from_points(self.origin - amount, self.lower_right + amount)
+    }
+
+    /// Converts this rectangle to single precision.
+    ///
+    /// Precision is lost here, so this should be done after the view transform has been applied.
+    #[inline]
+    pub fn to_f32(self) -> RectF {
+        RectF::from_points(self.origin.to_f32(), self.lower_right.to_f32())
+    }
 }
 
 /// NB: The origin is inclusive, while the lower right point is exclusive.
@@ -318,6 +433,16 @@ impl RectI {
             .all_true()
     }
 
+    #[inline]
+    pub fn contains_rect(self, other: RectI) -> bool {
+        // self.origin <= other.origin && other.lower_right <= self.lower_right
+        self.origin()
+            .0
+            .concat_xy_xy(other.lower_right().0)
+            .packed_le(other.origin().0.concat_xy_xy(self.lower_right().0))
+            .all_true()
+    }
+
     #[inline]
     pub fn contract(self, amount: Vector2I) -> RectI {
         RectI::from_points(self.origin() + amount, self.lower_right() - amount)
@@ -0,0 +1,88 @@
+// pathfinder/geometry/src/serialization.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `serde` support.
+//!
+//! These types are SIMD vectors underneath, so the impls are written by hand: vectors are
+//! `[x, y]`, rects are `{ "origin": [x, y], "size": [width, height] }`, and transforms are the
+//! six entries of `Transform2F::row_major()`.
+
+use crate::rect::RectF;
+use crate::transform2d::Transform2F;
+use crate::vector::Vector2F;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+impl Serialize for Vector2F {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        (self.x(), self.y()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Vector2F {
+    fn deserialize<D>(deserializer: D) -> Result<Vector2F, D::Error> where D: Deserializer<'de> {
+        let (x, y) = Deserialize::deserialize(deserializer)?;
+        Ok(Vector2F::new(x, y))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "RectF")]
+struct RectFRepr {
+    origin: Vector2F,
+    size: Vector2F,
+}
+
+impl Serialize for RectF {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        RectFRepr { origin: self.origin(), size: self.size() }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RectF {
+    fn deserialize<D>(deserializer: D) -> Result<RectF, D::Error> where D: Deserializer<'de> {
+        let repr = RectFRepr::deserialize(deserializer)?;
+        Ok(RectF::new(repr.origin, repr.size))
+    }
+}
+
+impl Serialize for Transform2F {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        (self.m11(), self.m12(), self.m21(), self.m22(), self.vector.x(), self.vector.y())
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Transform2F {
+    fn deserialize<D>(deserializer: D) -> Result<Transform2F, D::Error>
+                      where D: Deserializer<'de> {
+        let (m11, m12, m21, m22, m31, m32) = Deserialize::deserialize(deserializer)?;
+        Ok(Transform2F::row_major(m11, m12, m21, m22, m31, m32))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::rect::RectF;
+    use crate::transform2d::Transform2F;
+    use crate::vector::Vector2F;
+
+    #[test]
+    fn test_round_trip() {
+        let rect = RectF::new(Vector2F::new(1.0, 2.0), Vector2F::new(3.0, 4.0));
+        let json = serde_json::to_string(&rect).unwrap();
+        assert_eq!(json, r#"{"origin":[1.0,2.0],"size":[3.0,4.0]}"#);
+        assert_eq!(serde_json::from_str::<RectF>(&json).unwrap(), rect);
+
+        let transform = Transform2F::row_major(1.0, 2.0, 3.0, 4.0, 5.0, 6.0);
+        let json = serde_json::to_string(&transform).unwrap();
+        assert_eq!(json, "[1.0,2.0,3.0,4.0,5.0,6.0]");
+        assert_eq!(serde_json::from_str::<Transform2F>(&json).unwrap(), transform);
+    }
+}
@@ -15,8 +15,17 @@ use crate::rect::RectF;
 use crate::transform3d::Transform4F;
 use crate::unit_vector::UnitVector;
 use crate::vector::Vector2F;
+#[cfg(feature = "pf-f64")]
+use crate::line_segment::LineSegment2D;
+#[cfg(feature = "pf-f64")]
+use crate::rect::RectD;
+#[cfg(feature = "pf-f64")]
+use crate::vector::Vector2D;
+use crate::util;
 use pathfinder_simd::default::F32x4;
-use std::ops::{Mul, MulAssign, Sub};
+use pathfinder_simd::math;
+use core::f32::consts::PI;
+use core::ops::{Mul, MulAssign, Sub};
 
 /// A 2x2 matrix, optimized with SIMD, in column-major order.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -267,7 +276,7 @@ impl Transform2F {
     /// This decomposition assumes that scale, rotation, and translation are applied in that order.
     #[inline]
     pub fn rotation(&self) -> f32 {
-        f32::atan2(self.m21(), self.m11())
+        math::atan2(self.m21(), self.m11())
     }
 
     /// Returns the scale factor of this matrix.
@@ -284,6 +293,62 @@ impl Transform2F {
         let vector_inv = -(matrix_inv * self.vector);
         Transform2F { matrix: matrix_inv, vector: vector_inv }
     }
+
+    /// Splits this transform into scale, skew, rotation, and translation.
+    ///
+    /// Unlike `rotation()` and `scale_factor()`, this handles every affine transform: recomposing
+    /// the result with `DecomposedTransform2F::to_transform()` yields the original transform, up
+    /// to rounding error. Reflections are represented as a negative Y scale.
+    pub fn decompose(&self) -> DecomposedTransform2F {
+        let (column_0, column_1) = (Vector2F(self.matrix.0.xy()), Vector2F(self.matrix.0.zw()));
+        let scale_x = column_0.length();
+        let (rotation, axis) = if scale_x == 0.0 {
+            (0.0, Vector2F::new(1.0, 0.0))
+        } else {
+            (math::atan2(column_0.y(), column_0.x()), column_0.scale(1.0 / scale_x))
+        };
+
+        // Project the second column onto the rotated axes.
+        let scale_y = axis.x() * column_1.y() - axis.y() * column_1.x();
+        let skew = if scale_y == 0.0 { 0.0 } else { axis.dot(column_1) / scale_y };
+
+        DecomposedTransform2F {
+            scale: Vector2F::new(scale_x, scale_y),
+            skew,
+            rotation,
+            translation: self.vector,
+        }
+    }
+
+    /// Linearly interpolates each entry of this transform toward `other`.
+    ///
+    /// This is cheap, but intermediate transforms of a rotation shrink toward the center. Use
+    /// `slerp()` to preserve shape.
+    #[inline]
+    pub fn lerp(&self, other: &Transform2F, t: f32) -> Transform2F {
+        Transform2F {
+            matrix: Matrix2x2F(self.matrix.0 + (other.matrix.0 - self.matrix.0) * F32x4::splat(t)),
+            vector: self.vector.lerp(other.vector, t),
+        }
+    }
+
+    /// Interpolates between this transform and `other` by decomposing both, turning through the
+    /// smaller angle between their rotations, and linearly interpolating the other components.
+    #[inline]
+    pub fn slerp(&self, other: &Transform2F, t: f32) -> Transform2F {
+        self.decompose().slerp(&other.decompose(), t).to_transform()
+    }
+
+    #[cfg(feature = "pf-f64")]
+    #[inline]
+    pub fn to_f64(&self) -> Transform2D {
+        Transform2D::row_major(self.m11() as f64,
+                               self.m12() as f64,
+                               self.m21() as f64,
+                               self.m22() as f64,
+                               self.vector.x() as f64,
+                               self.vector.y() as f64)
+    }
 }
 
 impl Mul<Transform2F> for Transform2F {
@@ -331,3 +396,358 @@ impl MulAssign for Transform2F {
         *self = *self * other
     }
 }
+
+/// The components of an affine transform, as returned by `Transform2F::decompose()`.
+///
+/// The components are applied in field order: a point is scaled, skewed horizontally so that `x`
+/// increases by `skew * y`, rotated by `rotation` radians, and then translated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DecomposedTransform2F {
+    pub scale: Vector2F,
+    pub skew: f32,
+    pub rotation: f32,
+    pub translation: Vector2F,
+}
+
+impl Default for DecomposedTransform2F {
+    #[inline]
+    fn default() -> DecomposedTransform2F {
+        DecomposedTransform2F {
+            scale: Vector2F::splat(1.0),
+            skew: 0.0,
+            rotation: 0.0,
+            translation: Vector2F::default(),
+        }
+    }
+}
+
+impl DecomposedTransform2F {
+    #[inline]
+    pub fn to_transform(&self) -> Transform2F {
+        let matrix = Matrix2x2F::from_rotation(self.rotation) *
+            Matrix2x2F::row_major(1.0, self.skew, 0.0, 1.0) *
+            Matrix2x2F::from_scale(self.scale);
+        Transform2F { matrix, vector: self.translation }
+    }
+
+    /// Linearly interpolates each component, including the rotation angle.
+    #[inline]
+    pub fn lerp(&self, other: &DecomposedTransform2F, t: f32) -> DecomposedTransform2F {
+        DecomposedTransform2F {
+            scale: self.scale.lerp(other.scale, t),
+            skew: util::lerp(self.skew, other.skew, t),
+            rotation: util::lerp(self.rotation, other.rotation, t),
+            translation: self.translation.lerp(other.translation, t),
+        }
+    }
+
+    /// Like `lerp()`, but turns through the smaller angle between the two rotations.
+    #[inline]
+    pub fn slerp(&self, other: &DecomposedTransform2F, t: f32) -> DecomposedTransform2F {
+        let mut delta = (other.rotation - self.rotation) % (PI * 2.0);
+        if delta > PI {
+            delta -= PI * 2.0;
+        } else if delta < -PI {
+            delta += PI * 2.0;
+        }
+        DecomposedTransform2F {
+            rotation: self.rotation + delta * t,
+            ..self.lerp(other, t)
+        }
+    }
+}
+
+/// A 2x2 matrix with 64-bit floating point entries.
+#[cfg(feature = "pf-f64")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix2x2D {
+    m11: f64,
+    m12: f64,
+    m21: f64,
+    m22: f64,
+}
+
+#[cfg(feature = "pf-f64")]
+impl Default for Matrix2x2D {
+    #[inline]
+    fn default() -> Matrix2x2D {
+        Self::from_scale(Vector2D::splat(1.0))
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Matrix2x2D {
+    #[inline]
+    pub fn from_scale(scale: Vector2D) -> Matrix2x2D {
+        Matrix2x2D::row_major(scale.x(), 0.0, 0.0, scale.y())
+    }
+
+    #[inline]
+    pub fn from_rotation(theta: f64) -> Matrix2x2D {
+        let (sin, cos) = math::f64::sin_cos(theta);
+        Matrix2x2D::row_major(cos, -sin, sin, cos)
+    }
+
+    #[inline]
+    pub fn row_major(m11: f64, m12: f64, m21: f64, m22: f64) -> Matrix2x2D {
+        Matrix2x2D { m11, m12, m21, m22 }
+    }
+
+    #[inline]
+    pub fn det(&self) -> f64 {
+        self.m11 * self.m22 - self.m12 * self.m21
+    }
+
+    #[inline]
+    pub fn inverse(&self) -> Matrix2x2D {
+        let det_inv = 1.0 / self.det();
+        Matrix2x2D::row_major(self.m22 * det_inv,
+                              -self.m12 * det_inv,
+                              -self.m21 * det_inv,
+                              self.m11 * det_inv)
+    }
+
+    #[inline]
+    pub fn m11(&self) -> f64 {
+        self.m11
+    }
+
+    #[inline]
+    pub fn m21(&self) -> f64 {
+        self.m21
+    }
+
+    #[inline]
+    pub fn m12(&self) -> f64 {
+        self.m12
+    }
+
+    #[inline]
+    pub fn m22(&self) -> f64 {
+        self.m22
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Mul<Matrix2x2D> for Matrix2x2D {
+    type Output = Matrix2x2D;
+    #[inline]
+    fn mul(self, other: Matrix2x2D) -> Matrix2x2D {
+        Matrix2x2D::row_major(self.m11 * other.m11 + self.m12 * other.m21,
+                              self.m11 * other.m12 + self.m12 * other.m22,
+                              self.m21 * other.m11 + self.m22 * other.m21,
+                              self.m21 * other.m12 + self.m22 * other.m22)
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Mul<Vector2D> for Matrix2x2D {
+    type Output = Vector2D;
+    #[inline]
+    fn mul(self, vector: Vector2D) -> Vector2D {
+        Vector2D::new(self.m11 * vector.x() + self.m12 * vector.y(),
+                      self.m21 * vector.x() + self.m22 * vector.y())
+    }
+}
+
+/// An affine transform with 64-bit floating point entries.
+///
+/// The typical use is to compose the view transform for a scene with large world coordinates in
+/// double precision, apply it to `Vector2D` points, and only then convert the results to
+/// `Vector2F` for building outlines.
+#[cfg(feature = "pf-f64")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform2D {
+    pub matrix: Matrix2x2D,
+    pub vector: Vector2D,
+}
+
+#[cfg(feature = "pf-f64")]
+impl Default for Transform2D {
+    #[inline]
+    fn default() -> Transform2D {
+        Self::from_scale(Vector2D::splat(1.0))
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Transform2D {
+    #[inline]
+    pub fn from_scale(scale: Vector2D) -> Transform2D {
+        Transform2D { matrix: Matrix2x2D::from_scale(scale), vector: Vector2D::default() }
+    }
+
+    #[inline]
+    pub fn from_uniform_scale(scale: f64) -> Transform2D {
+        Transform2D::from_scale(Vector2D::splat(scale))
+    }
+
+    #[inline]
+    pub fn from_rotation(theta: f64) -> Transform2D {
+        Transform2D { matrix: Matrix2x2D::from_rotation(theta), vector: Vector2D::default() }
+    }
+
+    #[inline]
+    pub fn from_translation(vector: Vector2D) -> Transform2D {
+        Transform2D { matrix: Matrix2x2D::default(), vector }
+    }
+
+    #[inline]
+    pub fn row_major(m11: f64, m12: f64, m21: f64, m22: f64, m31: f64, m32: f64) -> Transform2D {
+        Transform2D {
+            matrix: Matrix2x2D::row_major(m11, m12, m21, m22),
+            vector: Vector2D::new(m31, m32),
+        }
+    }
+
+    #[inline]
+    pub fn is_identity(&self) -> bool {
+        *self == Transform2D::default()
+    }
+
+    #[inline]
+    pub fn translate(&self, vector: Vector2D) -> Transform2D {
+        Transform2D::from_translation(vector) * *self
+    }
+
+    #[inline]
+    pub fn rotate(&self, theta: f64) -> Transform2D {
+        Transform2D::from_rotation(theta) * *self
+    }
+
+    #[inline]
+    pub fn scale(&self, scale: Vector2D) -> Transform2D {
+        Transform2D::from_scale(scale) * *self
+    }
+
+    #[inline]
+    pub fn uniform_scale(&self, scale: f64) -> Transform2D {
+        self.scale(Vector2D::splat(scale))
+    }
+
+    #[inline]
+    pub fn inverse(&self) -> Transform2D {
+        let matrix_inv = self.matrix.inverse();
+        let vector_inv = -(matrix_inv * self.vector);
+        Transform2D { matrix: matrix_inv, vector: vector_inv }
+    }
+
+    /// Converts this transform to single precision.
+    ///
+    /// Only do this once the transform maps into a coordinate space of modest extent, such as
+    /// device pixels; a large translation will lose precision here.
+    #[inline]
+    pub fn to_f32(&self) -> Transform2F {
+        Transform2F::row_major(self.matrix.m11() as f32,
+                               self.matrix.m12() as f32,
+                               self.matrix.m21() as f32,
+                               self.matrix.m22() as f32,
+                               self.vector.x() as f32,
+                               self.vector.y() as f32)
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Mul<Transform2D> for Transform2D {
+    type Output = Transform2D;
+    #[inline]
+    fn mul(self, other: Transform2D) -> Transform2D {
+        Transform2D { matrix: self.matrix * other.matrix, vector: self * other.vector }
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Mul<Vector2D> for Transform2D {
+    type Output = Vector2D;
+    #[inline]
+    fn mul(self, vector: Vector2D) -> Vector2D {
+        self.matrix * vector + self.vector
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Mul<LineSegment2D> for Transform2D {
+    type Output = LineSegment2D;
+    #[inline]
+    fn mul(self, line_segment: LineSegment2D) -> LineSegment2D {
+        LineSegment2D::new(self * line_segment.from(), self * line_segment.to())
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Mul<RectD> for Transform2D {
+    type Output = RectD;
+    #[inline]
+    fn mul(self, rect: RectD) -> RectD {
+        let (upper_left, upper_right) = (self * rect.origin(),     self * rect.upper_right());
+        let (lower_left, lower_right) = (self * rect.lower_left(), self * rect.lower_right());
+        let min_point = upper_left.min(upper_right).min(lower_left).min(lower_right);
+        let max_point = upper_left.max(upper_right).max(lower_left).max(lower_right);
+        RectD::from_points(min_point, max_point)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::transform2d::Transform2F;
+    #[cfg(feature = "pf-f64")]
+    use crate::transform2d::Transform2D;
+    #[cfg(feature = "pf-f64")]
+    use crate::vector::Vector2D;
+    use crate::vector::Vector2F;
+    use core::f32::consts::PI;
+
+    fn assert_transforms_approx_eq(a: &Transform2F, b: &Transform2F) {
+        let entries = |t: &Transform2F| {
+            [t.m11(), t.m12(), t.m21(), t.m22(), t.vector.x(), t.vector.y()]
+        };
+        for (a_entry, b_entry) in entries(a).iter().zip(entries(b).iter()) {
+            assert!((a_entry - b_entry).abs() < 0.0001, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_decompose() {
+        let transform = Transform2F::from_scale(Vector2F::new(2.0, -3.0))
+            * Transform2F::row_major(1.0, 0.0, 0.5, 1.0, 0.0, 0.0)
+            * Transform2F::from_rotation(PI * 0.25);
+        let transform = transform.translate(Vector2F::new(10.0, -20.0));
+        let decomposed = transform.decompose();
+        assert_transforms_approx_eq(&decomposed.to_transform(), &transform);
+        assert_eq!(decomposed.translation, Vector2F::new(10.0, -20.0));
+
+        let rotation = Transform2F::from_rotation(1.0).decompose();
+        assert!((rotation.rotation - 1.0).abs() < 0.0001);
+        assert!((rotation.scale - Vector2F::splat(1.0)).length() < 0.0001);
+        assert!(rotation.skew.abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_slerp() {
+        // Turning from just below half a turn to just above it should go the short way around.
+        let (from, to) = (Transform2F::from_rotation(PI * 0.9),
+                          Transform2F::from_rotation(-PI * 0.9));
+        let halfway = from.slerp(&to, 0.5);
+        assert_transforms_approx_eq(&halfway, &Transform2F::from_rotation(PI));
+        assert!((halfway.scale_factor() - 1.0).abs() < 0.0001);
+
+        let (from, to) = (Transform2F::from_uniform_scale(2.0),
+                          Transform2F::from_translation(Vector2F::new(4.0, 8.0)));
+        assert_transforms_approx_eq(&from.slerp(&to, 0.25), &from.lerp(&to, 0.25));
+    }
+
+    #[cfg(feature = "pf-f64")]
+    #[test]
+    fn test_world_to_view_precision() {
+        // A point a quarter unit away from a camera that sits at a large world coordinate. The
+        // offset is below the resolution of `f32` at this magnitude.
+        let camera = Vector2D::new(123_456_789.0, -987_654_321.0);
+        let point = camera + Vector2D::new(0.25, 0.75);
+        let view = Transform2D::from_translation(-camera).uniform_scale(4.0);
+        assert_eq!((view * point).to_f32(), Vector2F::new(1.0, 3.0));
+
+        let transform = Transform2F::row_major(2.0, 0.0, 0.0, 4.0, 5.0, 7.0);
+        assert_eq!(transform.to_f64().to_f32(), transform);
+        assert_eq!(transform.to_f64().inverse().to_f32(), transform.inverse());
+    }
+}
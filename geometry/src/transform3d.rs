@@ -14,7 +14,8 @@ use crate::vector::{Vector2F, Vector2I, Vector3F, Vector4F};
 use crate::rect::RectF;
 use crate::transform2d::Matrix2x2F;
 use pathfinder_simd::default::F32x4;
-use std::ops::{Add, Mul, MulAssign, Neg};
+use pathfinder_simd::math;
+use core::ops::{Add, Mul, MulAssign, Neg};
 
 /// An transform, optimized with SIMD.
 ///
@@ -91,9 +92,9 @@ impl Transform4F {
 
     // TODO(pcwalton): Optimize.
     pub fn from_rotation(yaw: f32, pitch: f32, roll: f32) -> Transform4F {
-        let (cos_b, sin_b) = (yaw.cos(), yaw.sin());
-        let (cos_c, sin_c) = (pitch.cos(), pitch.sin());
-        let (cos_a, sin_a) = (roll.cos(), roll.sin());
+        let (cos_b, sin_b) = (math::cos(yaw), math::sin(yaw));
+        let (cos_c, sin_c) = (math::cos(pitch), math::sin(pitch));
+        let (cos_a, sin_a) = (math::cos(roll), math::sin(roll));
         let m00 = cos_a * cos_b;
         let m01 = cos_a * sin_b * sin_c - sin_a * cos_c;
         let m02 = cos_a * sin_b * cos_c + sin_a * sin_c;
@@ -189,7 +190,7 @@ impl Transform4F {
     /// Just like `gluPerspective()`.
     #[inline]
     pub fn from_perspective(fov_y: f32, aspect: f32, z_near: f32, z_far: f32) -> Transform4F {
-        let f = 1.0 / (fov_y * 0.5).tan();
+        let f = 1.0 / math::tan(fov_y * 0.5);
         let z_denom = 1.0 / (z_near - z_far);
         let m00 = f / aspect;
         let m11 = f;
@@ -12,6 +12,7 @@
 
 use crate::vector::Vector2F;
 use pathfinder_simd::default::F32x2;
+use pathfinder_simd::math;
 
 #[derive(Clone, Copy, Debug)]
 pub struct UnitVector(pub Vector2F);
@@ -19,7 +20,7 @@ pub struct UnitVector(pub Vector2F);
 impl UnitVector {
     #[inline]
     pub fn from_angle(theta: f32) -> UnitVector {
-        UnitVector(Vector2F::new(theta.cos(), theta.sin()))
+        UnitVector(Vector2F::new(math::cos(theta), math::sin(theta)))
     }
 
     /// Angle addition formula.
@@ -11,8 +11,9 @@
 //! A SIMD-optimized point type.
 
 use pathfinder_simd::default::{F32x2, F32x4, I32x2};
-use std::hash::{Hash, Hasher};
-use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub};
+use pathfinder_simd::math;
+use core::hash::{Hash, Hasher};
+use core::ops::{Add, AddAssign, Div, Mul, Neg, Sub};
 
 /// 2D points with 32-bit floating point coordinates.
 #[derive(Clone, Copy, Debug, Default)]
@@ -121,7 +122,7 @@ impl Vector2F {
     /// Treats this point as a vector and calculates its length.
     #[inline]
     pub fn length(self) -> f32 {
-        f32::sqrt(self.square_length())
+        math::sqrt(self.square_length())
     }
 
     /// Treats this point as a vector and normalizes it.
@@ -159,6 +160,12 @@ impl Vector2F {
     pub fn to_i32(self) -> Vector2I {
         Vector2I(self.0.to_i32x2())
     }
+
+    #[cfg(feature = "pf-f64")]
+    #[inline]
+    pub fn to_f64(self) -> Vector2D {
+        Vector2D::new(self.x() as f64, self.y() as f64)
+    }
 }
 
 impl PartialEq for Vector2F {
@@ -363,7 +370,7 @@ impl Vector3F {
 
     #[inline]
     pub fn length(self) -> f32 {
-        f32::sqrt(self.square_length())
+        math::sqrt(self.square_length())
     }
 
     #[inline]
@@ -567,3 +574,158 @@ impl Default for Vector4F {
         Vector4F(point)
     }
 }
+
+/// 2D points with 64-bit floating point coordinates.
+///
+/// These are not SIMD-accelerated. They're intended for keeping large world coordinates precise
+/// until the view transform has been applied, after which they can be converted to `Vector2F`.
+#[cfg(feature = "pf-f64")]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vector2D {
+    x: f64,
+    y: f64,
+}
+
+#[cfg(feature = "pf-f64")]
+impl Vector2D {
+    #[inline]
+    pub fn new(x: f64, y: f64) -> Vector2D {
+        Vector2D { x, y }
+    }
+
+    #[inline]
+    pub fn splat(value: f64) -> Vector2D {
+        Vector2D { x: value, y: value }
+    }
+
+    #[inline]
+    pub fn x(self) -> f64 {
+        self.x
+    }
+
+    #[inline]
+    pub fn y(self) -> f64 {
+        self.y
+    }
+
+    #[inline]
+    pub fn set_x(&mut self, x: f64) {
+        self.x = x;
+    }
+
+    #[inline]
+    pub fn set_y(&mut self, y: f64) {
+        self.y = y;
+    }
+
+    #[inline]
+    pub fn min(self, other: Vector2D) -> Vector2D {
+        Vector2D::new(f64::min(self.x, other.x), f64::min(self.y, other.y))
+    }
+
+    #[inline]
+    pub fn max(self, other: Vector2D) -> Vector2D {
+        Vector2D::new(f64::max(self.x, other.x), f64::max(self.y, other.y))
+    }
+
+    #[inline]
+    pub fn det(self, other: Vector2D) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    #[inline]
+    pub fn dot(self, other: Vector2D) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    #[inline]
+    pub fn scale(self, x: f64) -> Vector2D {
+        Vector2D::new(self.x * x, self.y * x)
+    }
+
+    #[inline]
+    pub fn scale_xy(self, factors: Vector2D) -> Vector2D {
+        Vector2D::new(self.x * factors.x, self.y * factors.y)
+    }
+
+    /// Treats this point as a vector and calculates its squared length.
+    #[inline]
+    pub fn square_length(self) -> f64 {
+        self.dot(self)
+    }
+
+    /// Treats this point as a vector and calculates its length.
+    #[inline]
+    pub fn length(self) -> f64 {
+        math::f64::sqrt(self.square_length())
+    }
+
+    /// Treats this point as a vector and normalizes it.
+    #[inline]
+    pub fn normalize(self) -> Vector2D {
+        self.scale(1.0 / self.length())
+    }
+
+    #[inline]
+    pub fn is_zero(self) -> bool {
+        self == Vector2D::default()
+    }
+
+    #[inline]
+    pub fn lerp(self, other: Vector2D, t: f64) -> Vector2D {
+        self + (other - self).scale(t)
+    }
+
+    /// Converts this point to single precision.
+    ///
+    /// Precision is lost here, so this should be done after the view transform has been applied.
+    #[inline]
+    pub fn to_f32(self) -> Vector2F {
+        Vector2F::new(self.x as f32, self.y as f32)
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Add<Vector2D> for Vector2D {
+    type Output = Vector2D;
+    #[inline]
+    fn add(self, other: Vector2D) -> Vector2D {
+        Vector2D::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Sub<Vector2D> for Vector2D {
+    type Output = Vector2D;
+    #[inline]
+    fn sub(self, other: Vector2D) -> Vector2D {
+        Vector2D::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Mul<Vector2D> for Vector2D {
+    type Output = Vector2D;
+    #[inline]
+    fn mul(self, other: Vector2D) -> Vector2D {
+        self.scale_xy(other)
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Div<Vector2D> for Vector2D {
+    type Output = Vector2D;
+    #[inline]
+    fn div(self, other: Vector2D) -> Vector2D {
+        Vector2D::new(self.x / other.x, self.y / other.y)
+    }
+}
+
+#[cfg(feature = "pf-f64")]
+impl Neg for Vector2D {
+    type Output = Vector2D;
+    #[inline]
+    fn neg(self) -> Vector2D {
+        Vector2D::new(-self.x, -self.y)
+    }
+}
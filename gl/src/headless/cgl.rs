@@ -0,0 +1,119 @@
+// pathfinder/gl/src/headless/cgl.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Offscreen CGL contexts.
+
+use super::HeadlessContextError;
+use crate::GLVersion;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+
+type CGLContextObj = *mut c_void;
+type CGLPixelFormatObj = *mut c_void;
+type CGLError = c_int;
+
+const K_CGL_PFA_ACCELERATED: c_int = 73;
+const K_CGL_PFA_ALLOW_OFFLINE_RENDERERS: c_int = 96;
+const K_CGL_PFA_OPENGL_PROFILE: c_int = 99;
+const K_CGL_OGLP_VERSION_3_2_CORE: c_int = 0x3200;
+
+const RTLD_LAZY: c_int = 0x1;
+
+static OPENGL_FRAMEWORK_PATH: &str = "/System/Library/Frameworks/OpenGL.framework/OpenGL";
+
+#[link(name = "OpenGL", kind = "framework")]
+extern "C" {
+    fn CGLChoosePixelFormat(attributes: *const c_int,
+                            pixel_format: *mut CGLPixelFormatObj,
+                            pixel_format_count: *mut c_int)
+                            -> CGLError;
+    fn CGLDestroyPixelFormat(pixel_format: CGLPixelFormatObj) -> CGLError;
+    fn CGLCreateContext(pixel_format: CGLPixelFormatObj,
+                        share: CGLContextObj,
+                        context: *mut CGLContextObj)
+                        -> CGLError;
+    fn CGLDestroyContext(context: CGLContextObj) -> CGLError;
+    fn CGLSetCurrentContext(context: CGLContextObj) -> CGLError;
+}
+
+extern "C" {
+    fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
+pub(crate) struct NativeContext {
+    context: CGLContextObj,
+    framework: *mut c_void,
+}
+
+impl NativeContext {
+    pub(crate) fn new() -> Result<NativeContext, HeadlessContextError> {
+        unsafe {
+            let framework_path = CString::new(OPENGL_FRAMEWORK_PATH).unwrap();
+            let framework = dlopen(framework_path.as_ptr(), RTLD_LAZY);
+            if framework.is_null() {
+                let message = format!("couldn't load `{}`", OPENGL_FRAMEWORK_PATH);
+                return Err(HeadlessContextError::Initialization(message));
+            }
+
+            // Allowing offline renderers keeps us from forcing a switch to the discrete GPU.
+            let attributes = [
+                K_CGL_PFA_OPENGL_PROFILE, K_CGL_OGLP_VERSION_3_2_CORE,
+                K_CGL_PFA_ACCELERATED,
+                K_CGL_PFA_ALLOW_OFFLINE_RENDERERS,
+                0,
+            ];
+            let (mut pixel_format, mut pixel_format_count) = (ptr::null_mut(), 0);
+            let error = CGLChoosePixelFormat(attributes.as_ptr(),
+                                             &mut pixel_format,
+                                             &mut pixel_format_count);
+            if error != 0 || pixel_format.is_null() {
+                let message = format!("CGLChoosePixelFormat() failed with error {}", error);
+                return Err(HeadlessContextError::ContextCreation(message));
+            }
+
+            let mut context = ptr::null_mut();
+            let error = CGLCreateContext(pixel_format, ptr::null_mut(), &mut context);
+            CGLDestroyPixelFormat(pixel_format);
+            if error != 0 {
+                let message = format!("CGLCreateContext() failed with error {}", error);
+                return Err(HeadlessContextError::ContextCreation(message));
+            }
+
+            let native_context = NativeContext { context, framework };
+            let error = CGLSetCurrentContext(context);
+            if error != 0 {
+                let message = format!("CGLSetCurrentContext() failed with error {}", error);
+                return Err(HeadlessContextError::MakeCurrent(message));
+            }
+            Ok(native_context)
+        }
+    }
+
+    #[inline]
+    pub(crate) fn gl_version(&self) -> GLVersion {
+        GLVersion::GL3
+    }
+
+    pub(crate) fn get_proc_address(&self, name: &str) -> *const c_void {
+        let name = CString::new(name).unwrap();
+        unsafe { dlsym(self.framework, name.as_ptr()) as *const c_void }
+    }
+}
+
+impl Drop for NativeContext {
+    fn drop(&mut self) {
+        unsafe {
+            CGLSetCurrentContext(ptr::null_mut());
+            CGLDestroyContext(self.context);
+        }
+    }
+}
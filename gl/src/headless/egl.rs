@@ -0,0 +1,236 @@
+// pathfinder/gl/src/headless/egl.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Surfaceless EGL contexts.
+
+use super::HeadlessContextError;
+use crate::GLVersion;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+
+type EGLBoolean = u32;
+type EGLenum = u32;
+type EGLint = i32;
+type EGLConfig = *mut c_void;
+type EGLContext = *mut c_void;
+type EGLDisplay = *mut c_void;
+type EGLSurface = *mut c_void;
+
+type EGLGetPlatformDisplayEXTFn = unsafe extern "C" fn(platform: EGLenum,
+                                                       native_display: *mut c_void,
+                                                       attrib_list: *const EGLint)
+                                                       -> EGLDisplay;
+
+const EGL_FALSE: EGLBoolean = 0;
+const EGL_NONE: EGLint = 0x3038;
+const EGL_ALPHA_SIZE: EGLint = 0x3021;
+const EGL_BLUE_SIZE: EGLint = 0x3022;
+const EGL_GREEN_SIZE: EGLint = 0x3023;
+const EGL_RED_SIZE: EGLint = 0x3024;
+const EGL_STENCIL_SIZE: EGLint = 0x3026;
+const EGL_SURFACE_TYPE: EGLint = 0x3033;
+const EGL_RENDERABLE_TYPE: EGLint = 0x3040;
+const EGL_HEIGHT: EGLint = 0x3056;
+const EGL_WIDTH: EGLint = 0x3057;
+const EGL_CONTEXT_MAJOR_VERSION: EGLint = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: EGLint = 0x30fb;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: EGLint = 0x30fd;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: EGLint = 0x0001;
+const EGL_PBUFFER_BIT: EGLint = 0x0001;
+const EGL_OPENGL_BIT: EGLint = 0x0008;
+const EGL_OPENGL_ES3_BIT: EGLint = 0x0040;
+const EGL_EXTENSIONS: EGLint = 0x3055;
+const EGL_OPENGL_ES_API: EGLenum = 0x30a0;
+const EGL_OPENGL_API: EGLenum = 0x30a2;
+const EGL_PLATFORM_SURFACELESS_MESA: EGLenum = 0x31dd;
+
+#[link(name = "EGL")]
+extern "C" {
+    fn eglGetError() -> EGLint;
+    fn eglGetDisplay(native_display: *mut c_void) -> EGLDisplay;
+    fn eglGetProcAddress(name: *const c_char) -> *const c_void;
+    fn eglInitialize(display: EGLDisplay, major: *mut EGLint, minor: *mut EGLint) -> EGLBoolean;
+    fn eglTerminate(display: EGLDisplay) -> EGLBoolean;
+    fn eglQueryString(display: EGLDisplay, name: EGLint) -> *const c_char;
+    fn eglBindAPI(api: EGLenum) -> EGLBoolean;
+    fn eglChooseConfig(display: EGLDisplay,
+                       attrib_list: *const EGLint,
+                       configs: *mut EGLConfig,
+                       config_size: EGLint,
+                       num_config: *mut EGLint)
+                       -> EGLBoolean;
+    fn eglCreateContext(display: EGLDisplay,
+                        config: EGLConfig,
+                        share_context: EGLContext,
+                        attrib_list: *const EGLint)
+                        -> EGLContext;
+    fn eglDestroyContext(display: EGLDisplay, context: EGLContext) -> EGLBoolean;
+    fn eglCreatePbufferSurface(display: EGLDisplay, config: EGLConfig, attrib_list: *const EGLint)
+                               -> EGLSurface;
+    fn eglDestroySurface(display: EGLDisplay, surface: EGLSurface) -> EGLBoolean;
+    fn eglMakeCurrent(display: EGLDisplay,
+                      draw: EGLSurface,
+                      read: EGLSurface,
+                      context: EGLContext)
+                      -> EGLBoolean;
+}
+
+pub(crate) struct NativeContext {
+    display: EGLDisplay,
+    context: EGLContext,
+    // Only used when the display doesn't support `EGL_KHR_surfaceless_context`.
+    surface: EGLSurface,
+    gl_version: GLVersion,
+}
+
+impl NativeContext {
+    pub(crate) fn new() -> Result<NativeContext, HeadlessContextError> {
+        unsafe {
+            let display = get_display();
+            if display.is_null() {
+                return Err(HeadlessContextError::Initialization(egl_error("eglGetDisplay")));
+            }
+            if eglInitialize(display, ptr::null_mut(), ptr::null_mut()) == EGL_FALSE {
+                return Err(HeadlessContextError::Initialization(egl_error("eglInitialize")));
+            }
+
+            let surfaceless = has_extension(display, "EGL_KHR_surfaceless_context");
+
+            // Prefer desktop GL, and fall back to GLES, which is all that some embedded drivers
+            // offer.
+            let (config, context, gl_version) = match create_context(display,
+                                                                     surfaceless,
+                                                                     GLVersion::GL3) {
+                Ok(result) => result,
+                Err(_) => {
+                    match create_context(display, surfaceless, GLVersion::GLES3) {
+                        Ok(result) => result,
+                        Err(error) => {
+                            eglTerminate(display);
+                            return Err(error);
+                        }
+                    }
+                }
+            };
+
+            let surface = if surfaceless {
+                ptr::null_mut()
+            } else {
+                let attributes = [EGL_WIDTH, 1, EGL_HEIGHT, 1, EGL_NONE];
+                eglCreatePbufferSurface(display, config, attributes.as_ptr())
+            };
+
+            let native_context = NativeContext { display, context, surface, gl_version };
+            if eglMakeCurrent(display, surface, surface, context) == EGL_FALSE {
+                return Err(HeadlessContextError::MakeCurrent(egl_error("eglMakeCurrent")));
+            }
+            Ok(native_context)
+        }
+    }
+
+    #[inline]
+    pub(crate) fn gl_version(&self) -> GLVersion {
+        self.gl_version
+    }
+
+    pub(crate) fn get_proc_address(&self, name: &str) -> *const c_void {
+        let name = CString::new(name).unwrap();
+        unsafe { eglGetProcAddress(name.as_ptr()) }
+    }
+}
+
+impl Drop for NativeContext {
+    fn drop(&mut self) {
+        unsafe {
+            eglMakeCurrent(self.display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+            if !self.surface.is_null() {
+                eglDestroySurface(self.display, self.surface);
+            }
+            eglDestroyContext(self.display, self.context);
+            eglTerminate(self.display);
+        }
+    }
+}
+
+// Uses the Mesa surfaceless platform if we can, which needs neither X11 nor Wayland.
+unsafe fn get_display() -> EGLDisplay {
+    if has_extension(ptr::null_mut(), "EGL_MESA_platform_surfaceless") {
+        let name = CString::new("eglGetPlatformDisplayEXT").unwrap();
+        let function = eglGetProcAddress(name.as_ptr());
+        if !function.is_null() {
+            let get_platform_display: EGLGetPlatformDisplayEXTFn = std::mem::transmute(function);
+            let display = get_platform_display(EGL_PLATFORM_SURFACELESS_MESA,
+                                               ptr::null_mut(),
+                                               ptr::null());
+            if !display.is_null() {
+                return display;
+            }
+        }
+    }
+    eglGetDisplay(ptr::null_mut())
+}
+
+unsafe fn create_context(display: EGLDisplay, surfaceless: bool, gl_version: GLVersion)
+                         -> Result<(EGLConfig, EGLContext, GLVersion), HeadlessContextError> {
+    let (api, renderable_type, context_attributes) = match gl_version {
+        GLVersion::GL3 => {
+            (EGL_OPENGL_API, EGL_OPENGL_BIT, vec![
+                EGL_CONTEXT_MAJOR_VERSION, 3,
+                EGL_CONTEXT_MINOR_VERSION, 3,
+                EGL_CONTEXT_OPENGL_PROFILE_MASK, EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                EGL_NONE,
+            ])
+        }
+        GLVersion::GLES3 => {
+            (EGL_OPENGL_ES_API, EGL_OPENGL_ES3_BIT, vec![EGL_CONTEXT_MAJOR_VERSION, 3, EGL_NONE])
+        }
+    };
+    if eglBindAPI(api) == EGL_FALSE {
+        return Err(HeadlessContextError::ContextCreation(egl_error("eglBindAPI")));
+    }
+
+    let surface_type = if surfaceless { 0 } else { EGL_PBUFFER_BIT };
+    let config_attributes = [
+        EGL_SURFACE_TYPE, surface_type,
+        EGL_RENDERABLE_TYPE, renderable_type,
+        EGL_RED_SIZE, 8,
+        EGL_GREEN_SIZE, 8,
+        EGL_BLUE_SIZE, 8,
+        EGL_ALPHA_SIZE, 8,
+        EGL_STENCIL_SIZE, 8,
+        EGL_NONE,
+    ];
+    let (mut config, mut config_count) = (ptr::null_mut(), 0);
+    if eglChooseConfig(display, config_attributes.as_ptr(), &mut config, 1, &mut config_count) ==
+            EGL_FALSE || config_count == 0 {
+        return Err(HeadlessContextError::ContextCreation(egl_error("eglChooseConfig")));
+    }
+
+    let context = eglCreateContext(display, config, ptr::null_mut(), context_attributes.as_ptr());
+    if context.is_null() {
+        return Err(HeadlessContextError::ContextCreation(egl_error("eglCreateContext")));
+    }
+    Ok((config, context, gl_version))
+}
+
+unsafe fn has_extension(display: EGLDisplay, name: &str) -> bool {
+    let extensions = eglQueryString(display, EGL_EXTENSIONS);
+    if extensions.is_null() {
+        return false;
+    }
+    CStr::from_ptr(extensions).to_string_lossy().split_whitespace().any(|extension| {
+        extension == name
+    })
+}
+
+fn egl_error(function: &str) -> String {
+    unsafe { format!("{}() failed with error 0x{:x}", function, eglGetError()) }
+}
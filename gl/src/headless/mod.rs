@@ -0,0 +1,252 @@
+// pathfinder/gl/src/headless/mod.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Offscreen GL contexts, for rendering without a window.
+//!
+//! On macOS this creates a CGL context. Elsewhere it creates an EGL context, using the
+//! surfaceless platform when it's available so that no display server is needed. Either way,
+//! Pathfinder renders into a framebuffer object, so the context never needs a surface.
+
+use crate::{GLDevice, GLFramebuffer, GLVersion};
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_gpu::{Device, TextureFormat};
+use std::error::Error;
+use std::fmt;
+
+#[cfg(not(target_os = "macos"))]
+mod egl;
+#[cfg(target_os = "macos")]
+mod cgl;
+
+#[cfg(not(target_os = "macos"))]
+use self::egl::NativeContext;
+#[cfg(target_os = "macos")]
+use self::cgl::NativeContext;
+
+/// An offscreen GL context that is current on the thread that created it.
+///
+/// Create the device with `create_device()` and render into the framebuffer from
+/// `create_framebuffer()` by passing it to the renderer as `DestFramebuffer::Other`. The context
+/// must outlive the device.
+pub struct HeadlessContext {
+    native: NativeContext,
+}
+
+#[derive(Clone, Debug)]
+pub enum HeadlessContextError {
+    /// The platform's GL library couldn't be initialized.
+    Initialization(String),
+    /// No context with OpenGL 3.3 or OpenGL ES 3.0 support could be created.
+    ContextCreation(String),
+    /// The context couldn't be made current.
+    MakeCurrent(String),
+}
+
+impl HeadlessContext {
+    /// Creates a context, makes it current, and loads the `gl` function pointers from it.
+    pub fn new() -> Result<HeadlessContext, HeadlessContextError> {
+        let native = NativeContext::new()?;
+        gl::load_with(|name| native.get_proc_address(name));
+        Ok(HeadlessContext { native })
+    }
+
+    /// The dialect of GL the context speaks.
+    #[inline]
+    pub fn gl_version(&self) -> GLVersion {
+        self.native.gl_version()
+    }
+
+    /// Creates a device that renders with this context.
+    ///
+    /// There is no default framebuffer, so render to one from `create_framebuffer()`.
+    #[inline]
+    pub fn create_device(&self) -> GLDevice {
+        GLDevice::new(self.gl_version(), 0)
+    }
+
+    /// Creates an RGBA8 framebuffer to render into, suitable for `DestFramebuffer::Other`.
+    pub fn create_framebuffer(&self, device: &GLDevice, size: Vector2I) -> GLFramebuffer {
+        let texture = device.create_texture(TextureFormat::RGBA8, size);
+        device.create_framebuffer(texture)
+    }
+}
+
+impl fmt::Display for HeadlessContextError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            HeadlessContextError::Initialization(ref message) => {
+                write!(formatter, "failed to initialize GL: {}", message)
+            }
+            HeadlessContextError::ContextCreation(ref message) => {
+                write!(formatter, "failed to create a GL context: {}", message)
+            }
+            HeadlessContextError::MakeCurrent(ref message) => {
+                write!(formatter, "failed to make the GL context current: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for HeadlessContextError {}
+
+#[cfg(test)]
+mod test {
+    use super::HeadlessContext;
+    use crate::GL_TEXTURE_MAX_ANISOTROPY_EXT;
+    use gl::types::{GLenum, GLfloat, GLint};
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::vector::Vector2I;
+    use pathfinder_gpu::{Device, RenderTarget, TextureData, TextureDataRef, TextureFormat};
+    use pathfinder_gpu::TextureSamplingFlags;
+
+    // These tests need a GL driver, so they're ignored by default. Run them with
+    // `cargo test -- --ignored`.
+    fn create_context() -> HeadlessContext {
+        HeadlessContext::new().expect("failed to create a headless GL context")
+    }
+
+    fn get_integers<A>(name: GLenum) -> A where A: Default + AsMut<[GLint]> {
+        let mut values = A::default();
+        unsafe {
+            gl::GetIntegerv(name, values.as_mut().as_mut_ptr());
+        }
+        values
+    }
+
+    #[test]
+    #[ignore]
+    fn test_headless_framebuffer_round_trip() {
+        let context = create_context();
+        let device = context.create_device();
+
+        let size = Vector2I::new(2, 2);
+        let framebuffer = context.create_framebuffer(&device, size);
+        let pixels: Vec<u8> = (0..16).collect();
+        let texture = device.framebuffer_texture(&framebuffer);
+        let viewport = RectI::new(Vector2I::default(), size);
+        device.upload_to_texture(texture, viewport, TextureDataRef::U8(&pixels));
+
+        let receiver = device.read_pixels(&RenderTarget::Framebuffer(&framebuffer), viewport);
+        // Readback flips the rows so that the top row comes first.
+        let expected_pixels: Vec<u8> = pixels[8..16].iter().chain(&pixels[0..8]).cloned().collect();
+        match device.recv_texture_data(&receiver) {
+            TextureData::U8(data) => assert_eq!(data, expected_pixels),
+            texture_data => panic!("unexpected texture data: {:?}", texture_data),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_wrapped_textures_are_not_deleted() {
+        let context = create_context();
+        let device = context.create_device();
+
+        let mut gl_texture = 0;
+        unsafe {
+            gl::GenTextures(1, &mut gl_texture);
+            gl::BindTexture(gl::TEXTURE_2D, gl_texture);
+        }
+        let size = Vector2I::new(4, 4);
+        drop(device.wrap_texture(gl_texture, TextureFormat::RGBA8, size));
+        unsafe {
+            assert_eq!(gl::IsTexture(gl_texture), gl::TRUE);
+            gl::DeleteTextures(1, &gl_texture);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fences_signal() {
+        let context = create_context();
+        let device = context.create_device();
+
+        let fence = device.insert_fence();
+        device.gpu_wait_for_fence(&fence);
+        device.wait_for_fence(&fence);
+        assert!(device.is_fence_signaled(&fence));
+
+        // A fence inserted after more work signals once that work is done.
+        let size = Vector2I::new(16, 16);
+        let framebuffer = context.create_framebuffer(&device, size);
+        let viewport = RectI::new(Vector2I::default(), size);
+        let _receiver = device.read_pixels(&RenderTarget::Framebuffer(&framebuffer), viewport);
+        let fence = device.insert_fence();
+        device.wait_for_fence(&fence);
+        assert!(device.is_fence_signaled(&fence));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_saved_state_is_reset_and_restored() {
+        let context = create_context();
+        let device = context.create_device();
+
+        // Set up some state the way an embedding application might.
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Viewport(1, 2, 3, 4);
+            gl::ClearColor(0.25, 0.5, 0.75, 1.0);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        }
+
+        device.save_state();
+        unsafe {
+            assert_eq!(gl::IsEnabled(gl::BLEND), gl::FALSE);
+            assert_eq!(gl::IsEnabled(gl::SCISSOR_TEST), gl::FALSE);
+        }
+        assert_eq!(get_integers::<[GLint; 1]>(gl::UNPACK_ALIGNMENT), [4]);
+
+        // Saves nest, and Pathfinder's own bindings are undone too.
+        device.save_state();
+        let _framebuffer = context.create_framebuffer(&device, Vector2I::new(4, 4));
+        device.restore_state();
+        device.restore_state();
+
+        unsafe {
+            assert_eq!(gl::IsEnabled(gl::BLEND), gl::TRUE);
+            assert_eq!(gl::IsEnabled(gl::SCISSOR_TEST), gl::TRUE);
+            let mut clear_color: [GLfloat; 4] = [0.0; 4];
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, clear_color.as_mut_ptr());
+            assert_eq!(clear_color, [0.25, 0.5, 0.75, 1.0]);
+        }
+        assert_eq!(get_integers::<[GLint; 1]>(gl::BLEND_SRC_RGB), [gl::SRC_ALPHA as GLint]);
+        assert_eq!(get_integers::<[GLint; 4]>(gl::VIEWPORT), [1, 2, 3, 4]);
+        assert_eq!(get_integers::<[GLint; 1]>(gl::UNPACK_ALIGNMENT), [1]);
+        assert_eq!(get_integers::<[GLint; 1]>(gl::TEXTURE_BINDING_2D), [0]);
+        assert_eq!(get_integers::<[GLint; 1]>(gl::DRAW_FRAMEBUFFER_BINDING), [0]);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_anisotropy_is_clamped_to_the_driver_limit() {
+        let context = create_context();
+        let device = context.create_device();
+
+        let texture = device.create_texture(TextureFormat::RGBA8, Vector2I::new(4, 4));
+        let mut flags = TextureSamplingFlags::empty();
+        flags.set_max_anisotropy(16);
+        device.set_texture_sampling_mode(&texture, flags);
+
+        // Without anisotropic filtering the device never sets the parameter.
+        if device.max_texture_anisotropy == 1.0 {
+            return;
+        }
+        let mut max_anisotropy = 0.0;
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture.gl_texture);
+            gl::GetTexParameterfv(gl::TEXTURE_2D,
+                                  GL_TEXTURE_MAX_ANISOTROPY_EXT,
+                                  &mut max_anisotropy);
+        }
+        assert_eq!(max_anisotropy, f32::min(16.0, device.max_texture_anisotropy));
+    }
+}
@@ -13,42 +13,162 @@
 #[macro_use]
 extern crate log;
 
-use gl::types::{GLboolean, GLchar, GLenum, GLfloat, GLint, GLsizei, GLsizeiptr, GLsync};
-use gl::types::{GLuint, GLvoid};
+use gl::types::{GLboolean, GLchar, GLdouble, GLenum, GLfloat, GLint, GLsizei, GLsizeiptr};
+use gl::types::{GLintptr, GLsync, GLuint, GLvoid};
 use half::f16;
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::vector::Vector2I;
 use pathfinder_gpu::{BlendFactor, BlendOp, BufferData, BufferTarget, BufferUploadMode, ClearOps};
-use pathfinder_gpu::{DepthFunc, Device, Primitive, RenderOptions, RenderState, RenderTarget};
+use pathfinder_gpu::{ComputeDimensions, ComputeState, DepthFunc, Device, DeviceLostError};
+use pathfinder_gpu::Primitive;
+use pathfinder_gpu::{RenderOptions, RenderState, RenderTarget};
 use pathfinder_gpu::{ShaderKind, StencilFunc, TextureData, TextureDataRef, TextureFormat};
 use pathfinder_gpu::{TextureSamplingFlags, UniformData, VertexAttrClass};
 use pathfinder_gpu::{VertexAttrDescriptor, VertexAttrType};
+use pathfinder_gpu::memory::{Allocation, GPUMemoryUsage, MemoryCounter};
 use pathfinder_resources::ResourceLoader;
 use pathfinder_simd::default::F32x4;
-use std::ffi::CString;
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::hash::{Hash, Hasher};
 use std::mem;
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::rc::Rc;
 use std::str;
 use std::time::Duration;
 
+// The number of regions each streaming buffer cycles through, so that the CPU can write one while
+// the GPU is still reading the others.
+const STREAM_REGION_COUNT: usize = 3;
+const STREAM_REGION_MIN_CAPACITY: usize = 64 * 1024;
+
+// Compressed texture formats. Not all of these are in core GL, so the `gl` crate lacks them.
+const GL_COMPRESSED_RGBA_S3TC_DXT1_EXT: GLenum = 0x83f1;
+const GL_COMPRESSED_RGBA_S3TC_DXT5_EXT: GLenum = 0x83f3;
+const GL_COMPRESSED_RGBA_BPTC_UNORM: GLenum = 0x8e8c;
+const GL_COMPRESSED_RGBA8_ETC2_EAC: GLenum = 0x9278;
+const GL_COMPRESSED_RGBA_ASTC_4X4_KHR: GLenum = 0x93b0;
+
+// From `GL_EXT_texture_filter_anisotropic`, which is core in OpenGL 4.6.
+const GL_TEXTURE_MAX_ANISOTROPY_EXT: GLenum = 0x84fe;
+const GL_MAX_TEXTURE_MAX_ANISOTROPY_EXT: GLenum = 0x84ff;
+
+// From `GL_KHR_blend_equation_advanced`, which is core in OpenGL ES 3.2.
+const GL_MULTIPLY_KHR: GLenum = 0x9294;
+const GL_SCREEN_KHR: GLenum = 0x9295;
+const GL_OVERLAY_KHR: GLenum = 0x9296;
+const GL_COLORDODGE_KHR: GLenum = 0x9299;
+const GL_COLORBURN_KHR: GLenum = 0x929a;
+const GL_HARDLIGHT_KHR: GLenum = 0x929b;
+const GL_SOFTLIGHT_KHR: GLenum = 0x929c;
+const GL_DIFFERENCE_KHR: GLenum = 0x929e;
+const GL_EXCLUSION_KHR: GLenum = 0x92a0;
+const GL_HSL_HUE_KHR: GLenum = 0x92ad;
+const GL_HSL_SATURATION_KHR: GLenum = 0x92ae;
+const GL_HSL_COLOR_KHR: GLenum = 0x92af;
+const GL_HSL_LUMINOSITY_KHR: GLenum = 0x92b0;
+
+// The number of texture units whose bindings `save_state()` preserves. Pathfinder's programs use
+// fewer than this.
+const SAVED_TEXTURE_UNIT_COUNT: usize = 8;
+
+#[cfg(feature = "headless")]
+pub mod headless;
+
 pub struct GLDevice {
     version: GLVersion,
     default_framebuffer: GLuint,
+    supports_buffer_storage: bool,
+    supports_compute: bool,
+    supports_advanced_blending: bool,
+    supported_compressed_formats: Vec<TextureFormat>,
+    // 1.0 if anisotropic filtering is unsupported.
+    max_texture_anisotropy: f32,
+    // The buffer most recently bound to `GL_ARRAY_BUFFER` by `bind_buffer()`, which is where
+    // `configure_vertex_attr()` points attributes.
+    bound_vertex_buffer: RefCell<Option<Rc<Cell<GLuint>>>>,
+    program_cache_dir: Option<PathBuf>,
+    // The states saved by `save_state()`, innermost last.
+    saved_states: RefCell<Vec<GLSavedState>>,
+    texture_memory: MemoryCounter,
+    buffer_memory: MemoryCounter,
 }
 
 impl GLDevice {
-    #[inline]
+    /// Creates a device that renders with the current GL context.
+    ///
+    /// The `gl` function pointers must already be loaded. With EGL (for example, on Android or
+    /// embedded Linux), load them with `eglGetProcAddress()`; some older EGL implementations only
+    /// return extension functions from it, in which case core functions must be looked up in
+    /// `libGLESv2` directly.
     pub fn new(version: GLVersion, default_framebuffer: GLuint) -> GLDevice {
-        GLDevice {
+        let mut device = GLDevice {
             version,
             default_framebuffer,
+            supports_buffer_storage: false,
+            supports_compute: false,
+            supports_advanced_blending: false,
+            supported_compressed_formats: vec![],
+            max_texture_anisotropy: 1.0,
+            bound_vertex_buffer: RefCell::new(None),
+            program_cache_dir: None,
+            saved_states: RefCell::new(vec![]),
+            texture_memory: MemoryCounter::new(),
+            buffer_memory: MemoryCounter::new(),
+        };
+        if let GLVersion::GLES3 = version {
+            device.check_gles3_extensions();
         }
+        device.supports_buffer_storage = device.detect_buffer_storage();
+        device.supports_compute = device.detect_compute();
+        device.supports_advanced_blending = device.detect_advanced_blending();
+        device.supported_compressed_formats = device.detect_compressed_formats();
+        device.max_texture_anisotropy = device.detect_max_texture_anisotropy();
+        device
     }
 
     pub fn set_default_framebuffer(&mut self, framebuffer: GLuint) {
         self.default_framebuffer = framebuffer;
     }
 
+    /// Caches linked program binaries in the given directory, so that later launches can skip
+    /// shader compilation.
+    ///
+    /// This must be called before the renderer is created to have any effect. It does nothing if
+    /// the driver can't save program binaries. Stale binaries (for example, after a driver
+    /// update) are detected and replaced.
+    pub fn set_program_cache_dir(&mut self, dir: Option<PathBuf>) {
+        if dir.is_some() && !self.supports_program_binaries() {
+            warn!("The GL driver doesn't support program binaries; not caching programs.");
+            return;
+        }
+        self.program_cache_dir = dir;
+    }
+
+    /// Wraps a `GL_TEXTURE_2D` created outside Pathfinder, so that it can be supplied to the
+    /// renderer as an external texture.
+    ///
+    /// This is the zero-copy path for video frames and camera images: for example, import a
+    /// DMA-BUF as an `EGLImage` and bind it to a texture with `glEGLImageTargetTexture2DOES()`.
+    /// (IOSurfaces can only be bound to rectangle textures under CGL, so use the Metal backend for
+    /// those.) Pathfinder doesn't take ownership of the texture, nor count it in
+    /// `memory_usage()`.
+    pub fn wrap_texture(&self, gl_texture: GLuint, format: TextureFormat, size: Vector2I)
+                        -> GLTexture {
+        let texture = GLTexture {
+            gl_texture,
+            size,
+            format,
+            allocation: self.texture_memory.allocate(0),
+            is_external: true,
+        };
+        self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
+        texture
+    }
+
     fn set_render_state(&self, render_state: &RenderState<GLDevice>) {
         self.bind_render_target(render_state.target);
 
@@ -63,12 +183,29 @@ impl GLDevice {
 
         self.use_program(render_state.program);
         self.bind_vertex_array(render_state.vertex_array);
+        self.rebind_streamed_vertex_attrs(render_state.vertex_array);
         for (texture_unit, texture) in render_state.textures.iter().enumerate() {
             self.bind_texture(texture, texture_unit as u32);
         }
 
         render_state.uniforms.iter().for_each(|(uniform, data)| self.set_uniform(uniform, data));
         self.set_render_options(&render_state.options);
+        if let Some(scissor) = render_state.options.scissor {
+            self.set_scissor(render_state.viewport, scissor);
+        }
+    }
+
+    fn set_scissor(&self, viewport: RectI, scissor: RectI) {
+        // GL window coordinates have Y pointing up, so flip the rect within the viewport.
+        let scissor = scissor.intersection(RectI::new(Vector2I::default(), viewport.size()))
+                             .unwrap_or_default();
+        unsafe {
+            gl::Scissor(viewport.min_x() + scissor.min_x(),
+                        viewport.max_y() - scissor.max_y(),
+                        scissor.width(),
+                        scissor.height()); ck();
+            gl::Enable(gl::SCISSOR_TEST); ck();
+        }
     }
 
     fn set_render_options(&self, render_options: &RenderOptions) {
@@ -178,6 +315,33 @@ impl GLDevice {
         self.unbind_vertex_array();
     }
 
+    fn set_compute_state(&self, compute_state: &ComputeState<GLDevice>) {
+        self.use_program(compute_state.program);
+        for (texture_unit, texture) in compute_state.textures.iter().enumerate() {
+            self.bind_texture(texture, texture_unit as u32);
+        }
+        compute_state.uniforms.iter().for_each(|(uniform, data)| self.set_uniform(uniform, data));
+        for &(storage_buffer, buffer) in compute_state.storage_buffers {
+            unsafe {
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER,
+                                   storage_buffer.binding,
+                                   buffer.current_gl_buffer.get()); ck();
+            }
+        }
+    }
+
+    fn reset_compute_state(&self, compute_state: &ComputeState<GLDevice>) {
+        for &(storage_buffer, _) in compute_state.storage_buffers {
+            unsafe {
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, storage_buffer.binding, 0); ck();
+            }
+        }
+        for texture_unit in 0..(compute_state.textures.len() as u32) {
+            self.unbind_texture(texture_unit);
+        }
+        self.unuse_program();
+    }
+
     fn reset_render_options(&self, render_options: &RenderOptions) {
         unsafe {
             if render_options.blend.is_some() {
@@ -193,16 +357,173 @@ impl GLDevice {
                 gl::Disable(gl::STENCIL_TEST); ck();
             }
 
+            if render_options.scissor.is_some() {
+                gl::Disable(gl::SCISSOR_TEST); ck();
+            }
+
             gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE); ck();
         }
     }
+
+    fn capture_state(&self) -> GLSavedState {
+        unsafe {
+            let mut state = GLSavedState::default();
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut state.program); ck();
+            gl::GetIntegerv(gl::VERTEX_ARRAY_BINDING, &mut state.vertex_array); ck();
+            gl::GetIntegerv(gl::ARRAY_BUFFER_BINDING, &mut state.array_buffer); ck();
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut state.draw_framebuffer); ck();
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut state.read_framebuffer); ck();
+            gl::GetIntegerv(gl::VIEWPORT, state.viewport.as_mut_ptr()); ck();
+
+            gl::GetIntegerv(gl::ACTIVE_TEXTURE, &mut state.active_texture); ck();
+            for unit in 0..SAVED_TEXTURE_UNIT_COUNT {
+                gl::ActiveTexture(gl::TEXTURE0 + unit as GLuint); ck();
+                gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut state.textures[unit]); ck();
+                gl::GetIntegerv(gl::SAMPLER_BINDING, &mut state.samplers[unit]); ck();
+            }
+
+            state.blend = gl::IsEnabled(gl::BLEND); ck();
+            gl::GetIntegerv(gl::BLEND_SRC_RGB, &mut state.blend_src_rgb); ck();
+            gl::GetIntegerv(gl::BLEND_DST_RGB, &mut state.blend_dest_rgb); ck();
+            gl::GetIntegerv(gl::BLEND_SRC_ALPHA, &mut state.blend_src_alpha); ck();
+            gl::GetIntegerv(gl::BLEND_DST_ALPHA, &mut state.blend_dest_alpha); ck();
+            gl::GetIntegerv(gl::BLEND_EQUATION_RGB, &mut state.blend_equation_rgb); ck();
+            gl::GetIntegerv(gl::BLEND_EQUATION_ALPHA, &mut state.blend_equation_alpha); ck();
+
+            state.depth_test = gl::IsEnabled(gl::DEPTH_TEST); ck();
+            gl::GetIntegerv(gl::DEPTH_FUNC, &mut state.depth_func); ck();
+            gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut state.depth_mask); ck();
+
+            state.stencil_test = gl::IsEnabled(gl::STENCIL_TEST); ck();
+            gl::GetIntegerv(gl::STENCIL_FUNC, &mut state.stencil_func); ck();
+            gl::GetIntegerv(gl::STENCIL_REF, &mut state.stencil_ref); ck();
+            gl::GetIntegerv(gl::STENCIL_VALUE_MASK, &mut state.stencil_value_mask); ck();
+            gl::GetIntegerv(gl::STENCIL_WRITEMASK, &mut state.stencil_write_mask); ck();
+            gl::GetIntegerv(gl::STENCIL_FAIL, &mut state.stencil_fail); ck();
+            gl::GetIntegerv(gl::STENCIL_PASS_DEPTH_FAIL, &mut state.stencil_depth_fail); ck();
+            gl::GetIntegerv(gl::STENCIL_PASS_DEPTH_PASS, &mut state.stencil_pass); ck();
+
+            gl::GetBooleanv(gl::COLOR_WRITEMASK, state.color_mask.as_mut_ptr()); ck();
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, state.clear_color.as_mut_ptr()); ck();
+            gl::GetFloatv(gl::DEPTH_CLEAR_VALUE, &mut state.clear_depth); ck();
+            gl::GetIntegerv(gl::STENCIL_CLEAR_VALUE, &mut state.clear_stencil); ck();
+
+            state.scissor_test = gl::IsEnabled(gl::SCISSOR_TEST); ck();
+            gl::GetIntegerv(gl::SCISSOR_BOX, state.scissor_box.as_mut_ptr()); ck();
+            state.cull_face = gl::IsEnabled(gl::CULL_FACE); ck();
+            if let GLVersion::GL3 = self.version {
+                state.framebuffer_srgb = gl::IsEnabled(gl::FRAMEBUFFER_SRGB); ck();
+            }
+
+            gl::GetIntegerv(gl::PACK_ALIGNMENT, &mut state.pack_alignment); ck();
+            gl::GetIntegerv(gl::UNPACK_ALIGNMENT, &mut state.unpack_alignment); ck();
+            gl::GetIntegerv(gl::UNPACK_ROW_LENGTH, &mut state.unpack_row_length); ck();
+            state
+        }
+    }
+
+    // Puts the context into the state that the rest of the device assumes: nothing bound, and no
+    // fixed-function state that would clip, cull, or convert our output.
+    fn reset_state(&self) {
+        unsafe {
+            self.unuse_program();
+            self.unbind_vertex_array();
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0); ck();
+            for unit in 0..SAVED_TEXTURE_UNIT_COUNT {
+                gl::BindSampler(unit as GLuint, 0); ck();
+                self.unbind_texture(unit as u32);
+            }
+            gl::ActiveTexture(gl::TEXTURE0); ck();
+
+            gl::Disable(gl::BLEND); ck();
+            gl::Disable(gl::DEPTH_TEST); ck();
+            gl::Disable(gl::STENCIL_TEST); ck();
+            gl::StencilMask(!0); ck();
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE); ck();
+            gl::Disable(gl::SCISSOR_TEST); ck();
+            gl::Disable(gl::CULL_FACE); ck();
+            if let GLVersion::GL3 = self.version {
+                gl::Disable(gl::FRAMEBUFFER_SRGB); ck();
+            }
+
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4); ck();
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4); ck();
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0); ck();
+        }
+        *self.bound_vertex_buffer.borrow_mut() = None;
+    }
+
+    fn apply_state(&self, state: &GLSavedState) {
+        unsafe {
+            gl::UseProgram(state.program as GLuint); ck();
+            gl::BindVertexArray(state.vertex_array as GLuint); ck();
+            gl::BindBuffer(gl::ARRAY_BUFFER, state.array_buffer as GLuint); ck();
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, state.draw_framebuffer as GLuint); ck();
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, state.read_framebuffer as GLuint); ck();
+            let viewport = state.viewport;
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]); ck();
+
+            for unit in 0..SAVED_TEXTURE_UNIT_COUNT {
+                gl::ActiveTexture(gl::TEXTURE0 + unit as GLuint); ck();
+                gl::BindTexture(gl::TEXTURE_2D, state.textures[unit] as GLuint); ck();
+                gl::BindSampler(unit as GLuint, state.samplers[unit] as GLuint); ck();
+            }
+            gl::ActiveTexture(state.active_texture as GLenum); ck();
+
+            set_capability(gl::BLEND, state.blend);
+            gl::BlendFuncSeparate(state.blend_src_rgb as GLenum,
+                                  state.blend_dest_rgb as GLenum,
+                                  state.blend_src_alpha as GLenum,
+                                  state.blend_dest_alpha as GLenum); ck();
+            gl::BlendEquationSeparate(state.blend_equation_rgb as GLenum,
+                                      state.blend_equation_alpha as GLenum); ck();
+
+            set_capability(gl::DEPTH_TEST, state.depth_test);
+            gl::DepthFunc(state.depth_func as GLenum); ck();
+            gl::DepthMask(state.depth_mask); ck();
+
+            set_capability(gl::STENCIL_TEST, state.stencil_test);
+            gl::StencilFunc(state.stencil_func as GLenum,
+                            state.stencil_ref,
+                            state.stencil_value_mask as GLuint); ck();
+            gl::StencilMask(state.stencil_write_mask as GLuint); ck();
+            gl::StencilOp(state.stencil_fail as GLenum,
+                          state.stencil_depth_fail as GLenum,
+                          state.stencil_pass as GLenum); ck();
+
+            let color_mask = state.color_mask;
+            gl::ColorMask(color_mask[0], color_mask[1], color_mask[2], color_mask[3]); ck();
+            let clear_color = state.clear_color;
+            gl::ClearColor(clear_color[0], clear_color[1], clear_color[2], clear_color[3]); ck();
+            match self.version {
+                GLVersion::GL3 => gl::ClearDepth(state.clear_depth as GLdouble),
+                GLVersion::GLES3 => gl::ClearDepthf(state.clear_depth),
+            }
+            ck();
+            gl::ClearStencil(state.clear_stencil); ck();
+
+            set_capability(gl::SCISSOR_TEST, state.scissor_test);
+            let scissor_box = state.scissor_box;
+            gl::Scissor(scissor_box[0], scissor_box[1], scissor_box[2], scissor_box[3]); ck();
+            set_capability(gl::CULL_FACE, state.cull_face);
+            if let GLVersion::GL3 = self.version {
+                set_capability(gl::FRAMEBUFFER_SRGB, state.framebuffer_srgb);
+            }
+
+            gl::PixelStorei(gl::PACK_ALIGNMENT, state.pack_alignment); ck();
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, state.unpack_alignment); ck();
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, state.unpack_row_length); ck();
+        }
+    }
 }
 
 impl Device for GLDevice {
     type Buffer = GLBuffer;
+    type Fence = GLFence;
     type Framebuffer = GLFramebuffer;
     type Program = GLProgram;
     type Shader = GLShader;
+    type StorageBuffer = GLStorageBuffer;
     type Texture = GLTexture;
     type TextureDataReceiver = GLTextureDataReceiver;
     type TimerQuery = GLTimerQuery;
@@ -211,19 +532,17 @@ impl Device for GLDevice {
     type VertexAttr = GLVertexAttr;
 
     fn create_texture(&self, format: TextureFormat, size: Vector2I) -> GLTexture {
-        let mut texture = GLTexture { gl_texture: 0, size, format };
+        let mut texture = GLTexture {
+            gl_texture: 0,
+            size,
+            format,
+            allocation: self.texture_memory.allocate(texture_byte_size(format, size)),
+            is_external: false,
+        };
         unsafe {
             gl::GenTextures(1, &mut texture.gl_texture); ck();
             self.bind_texture(&texture, 0);
-            gl::TexImage2D(gl::TEXTURE_2D,
-                           0,
-                           format.gl_internal_format(),
-                           size.x() as GLsizei,
-                           size.y() as GLsizei,
-                           0,
-                           format.gl_format(),
-                           format.gl_type(),
-                           ptr::null()); ck();
+            self.tex_image_2d(format, size, ptr::null());
         }
 
         self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
@@ -233,19 +552,17 @@ impl Device for GLDevice {
     fn create_texture_from_data(&self, format: TextureFormat, size: Vector2I, data: TextureDataRef)
                                 -> GLTexture {
         let data_ptr = data.check_and_extract_data_ptr(size, format);
-        let mut texture = GLTexture { gl_texture: 0, size, format: TextureFormat::R8 };
+        let mut texture = GLTexture {
+            gl_texture: 0,
+            size,
+            format,
+            allocation: self.texture_memory.allocate(texture_byte_size(format, size)),
+            is_external: false,
+        };
         unsafe {
             gl::GenTextures(1, &mut texture.gl_texture); ck();
             self.bind_texture(&texture, 0);
-            gl::TexImage2D(gl::TEXTURE_2D,
-                           0,
-                           format.gl_internal_format(),
-                           size.x() as GLsizei,
-                           size.y() as GLsizei,
-                           0,
-                           format.gl_format(),
-                           format.gl_type(),
-                           data_ptr)
+            self.tex_image_2d(format, size, data_ptr);
         }
 
         self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
@@ -254,41 +571,23 @@ impl Device for GLDevice {
 
     fn create_shader_from_source(&self, name: &str, source: &[u8], kind: ShaderKind) -> GLShader {
         // FIXME(pcwalton): Do this once and cache it.
-        let glsl_version_spec = self.version.to_glsl_version_spec();
+        let glsl_version_spec = self.version.glsl_version_spec(kind);
 
         let mut output = vec![];
-        self.preprocess(&mut output, source, glsl_version_spec);
-        let source = output;
+        preprocess(&mut output, source, glsl_version_spec);
 
-        let gl_shader_kind = match kind {
-            ShaderKind::Vertex => gl::VERTEX_SHADER,
-            ShaderKind::Fragment => gl::FRAGMENT_SHADER,
+        let shader = GLShader {
+            gl_shader: Cell::new(0),
+            name: name.to_owned(),
+            kind,
+            source: output,
         };
 
-        unsafe {
-            let gl_shader = gl::CreateShader(gl_shader_kind); ck();
-            gl::ShaderSource(gl_shader,
-                             1,
-                             [source.as_ptr() as *const GLchar].as_ptr(),
-                             [source.len() as GLint].as_ptr()); ck();
-            gl::CompileShader(gl_shader); ck();
-
-            let mut compile_status = 0;
-            gl::GetShaderiv(gl_shader, gl::COMPILE_STATUS, &mut compile_status); ck();
-            if compile_status != gl::TRUE as GLint {
-                let mut info_log_length = 0;
-                gl::GetShaderiv(gl_shader, gl::INFO_LOG_LENGTH, &mut info_log_length); ck();
-                let mut info_log = vec![0; info_log_length as usize];
-                gl::GetShaderInfoLog(gl_shader,
-                                     info_log.len() as GLint,
-                                     ptr::null_mut(),
-                                     info_log.as_mut_ptr() as *mut GLchar); ck();
-                error!("Shader info log:\n{}", String::from_utf8_lossy(&info_log));
-                panic!("{:?} shader '{}' compilation failed", kind, name);
-            }
-
-            GLShader { gl_shader }
+        // With a program cache, don't compile until we know that the program isn't cached.
+        if self.program_cache_dir.is_none() {
+            self.compile_shader(&shader);
         }
+        shader
     }
 
     fn create_program_from_shaders(&self,
@@ -297,35 +596,39 @@ impl Device for GLDevice {
                                    vertex_shader: GLShader,
                                    fragment_shader: GLShader)
                                    -> GLProgram {
-        let gl_program;
-        unsafe {
-            gl_program = gl::CreateProgram(); ck();
-            gl::AttachShader(gl_program, vertex_shader.gl_shader); ck();
-            gl::AttachShader(gl_program, fragment_shader.gl_shader); ck();
-            gl::LinkProgram(gl_program); ck();
-
-            let mut link_status = 0;
-            gl::GetProgramiv(gl_program, gl::LINK_STATUS, &mut link_status); ck();
-            if link_status != gl::TRUE as GLint {
-                let mut info_log_length = 0;
-                gl::GetProgramiv(gl_program, gl::INFO_LOG_LENGTH, &mut info_log_length); ck();
-                let mut info_log = vec![0; info_log_length as usize];
-                gl::GetProgramInfoLog(gl_program,
-                                      info_log.len() as GLint,
-                                      ptr::null_mut(),
-                                      info_log.as_mut_ptr() as *mut GLchar); ck();
-                eprintln!("Program info log:\n{}", String::from_utf8_lossy(&info_log));
-                panic!("Program '{}' linking failed", name);
-            }
+        let gl_program = self.link_program(name, &[&vertex_shader, &fragment_shader]);
+        GLProgram {
+            gl_program,
+            shaders: GLProgramShaders::Raster { vertex: vertex_shader, fragment: fragment_shader },
         }
+    }
 
-        GLProgram { gl_program, vertex_shader, fragment_shader }
+    fn create_compute_program_from_shader(&self,
+                                          _resources: &dyn ResourceLoader,
+                                          name: &str,
+                                          shader: GLShader)
+                                          -> GLProgram {
+        debug_assert_eq!(shader.kind, ShaderKind::Compute);
+        let gl_program = self.link_program(name, &[&shader]);
+        GLProgram { gl_program, shaders: GLProgramShaders::Compute(shader) }
+    }
+
+    // The workgroup size is fixed by the `local_size_*` qualifiers in the shader, so GL has
+    // nothing to do here.
+    #[inline]
+    fn set_compute_program_local_size(&self, _: &mut GLProgram, _: ComputeDimensions) {}
+
+    // GL ES 3.1 has no `glShaderStorageBlockBinding()`, so shaders must declare their bindings
+    // with `layout(binding = N)`.
+    #[inline]
+    fn get_storage_buffer(&self, _: &GLProgram, _: &str, binding: u32) -> GLStorageBuffer {
+        GLStorageBuffer { binding }
     }
 
     #[inline]
     fn create_vertex_array(&self) -> GLVertexArray {
         unsafe {
-            let mut array = GLVertexArray { gl_vertex_array: 0 };
+            let mut array = GLVertexArray { gl_vertex_array: 0, attrs: RefCell::new(vec![]) };
             gl::GenVertexArrays(1, &mut array.gl_vertex_array); ck();
             array
         }
@@ -360,35 +663,25 @@ impl Device for GLDevice {
         self.bind_vertex_array(vertex_array);
 
         unsafe {
-            let attr_type = descriptor.attr_type.to_gl_type();
-            match descriptor.class {
-                VertexAttrClass::Float | VertexAttrClass::FloatNorm => {
-                    let normalized = if descriptor.class == VertexAttrClass::FloatNorm {
-                        gl::TRUE
-                    } else {
-                        gl::FALSE
-                    };
-                    gl::VertexAttribPointer(attr.attr,
-                                            descriptor.size as GLint,
-                                            attr_type,
-                                            normalized,
-                                            descriptor.stride as GLint,
-                                            descriptor.offset as *const GLvoid); ck();
-                }
-                VertexAttrClass::Int => {
-                    gl::VertexAttribIPointer(attr.attr,
-                                             descriptor.size as GLint,
-                                             attr_type,
-                                             descriptor.stride as GLint,
-                                             descriptor.offset as *const GLvoid); ck();
-                }
-            }
-
+            self.set_vertex_attr_pointer(attr.attr, descriptor);
             gl::VertexAttribDivisor(attr.attr, descriptor.divisor); ck();
             gl::EnableVertexAttribArray(attr.attr); ck();
         }
 
         self.unbind_vertex_array();
+
+        // Remember where the attribute points, in case the buffer is later streamed into a
+        // different region.
+        if let Some(ref buffer) = *self.bound_vertex_buffer.borrow() {
+            let mut attrs = vertex_array.attrs.borrow_mut();
+            attrs.retain(|bound_attr| bound_attr.attr != attr.attr);
+            attrs.push(GLBoundVertexAttr {
+                attr: attr.attr,
+                descriptor: *descriptor,
+                buffer: buffer.clone(),
+                bound_gl_buffer: Cell::new(buffer.get()),
+            });
+        }
     }
 
     fn create_framebuffer(&self, texture: GLTexture) -> GLFramebuffer {
@@ -412,7 +705,12 @@ impl Device for GLDevice {
         unsafe {
             let mut gl_buffer = 0;
             gl::GenBuffers(1, &mut gl_buffer); ck();
-            GLBuffer { gl_buffer }
+            GLBuffer {
+                gl_buffer,
+                current_gl_buffer: Rc::new(Cell::new(gl_buffer)),
+                stream: RefCell::new(None),
+                allocation: self.buffer_memory.allocate(0),
+            }
         }
     }
 
@@ -424,17 +722,42 @@ impl Device for GLDevice {
         let target = match target {
             BufferTarget::Vertex => gl::ARRAY_BUFFER,
             BufferTarget::Index => gl::ELEMENT_ARRAY_BUFFER,
+            BufferTarget::Storage => gl::SHADER_STORAGE_BUFFER,
         };
         let (ptr, len) = match data {
             BufferData::Uninitialized(len) => (ptr::null(), len),
             BufferData::Memory(buffer) => (buffer.as_ptr() as *const GLvoid, buffer.len()),
         };
-        let len = (len * mem::size_of::<T>()) as GLsizeiptr;
+        let byte_len = len * mem::size_of::<T>();
+
+        let streaming = match mode {
+            BufferUploadMode::Dynamic => self.supports_buffer_storage,
+            BufferUploadMode::Static => false,
+        };
+        if streaming && target == gl::ARRAY_BUFFER {
+            let mut stream = buffer.stream.borrow_mut();
+            let buffer_memory = &self.buffer_memory;
+            let stream = stream.get_or_insert_with(|| {
+                GLStreamBuffer::new(buffer_memory.allocate(0))
+            });
+            unsafe {
+                let gl_buffer = stream.write(ptr as *const u8, byte_len);
+                buffer.current_gl_buffer.set(gl_buffer);
+            }
+            return;
+        }
+
+        if let Some(ref mut stream) = *buffer.stream.borrow_mut() {
+            stream.leave_current_region();
+        }
+        buffer.current_gl_buffer.set(buffer.gl_buffer);
+
         let usage = mode.to_gl_usage();
         unsafe {
             gl::BindBuffer(target, buffer.gl_buffer); ck();
-            gl::BufferData(target, len, ptr, usage); ck();
+            gl::BufferData(target, byte_len as GLsizeiptr, ptr, usage); ck();
         }
+        buffer.allocation.resize(byte_len as u64);
     }
 
     #[inline]
@@ -444,11 +767,7 @@ impl Device for GLDevice {
 
     #[inline]
     fn destroy_framebuffer(&self, framebuffer: Self::Framebuffer) -> Self::Texture {
-        let texture = GLTexture {
-            gl_texture: framebuffer.texture.gl_texture,
-            size: framebuffer.texture.size,
-            format: framebuffer.texture.format,
-        };
+        let texture = unsafe { ptr::read(&framebuffer.texture) };
         mem::forget(framebuffer);
         texture
     }
@@ -494,6 +813,13 @@ impl Device for GLDevice {
                               } else {
                                   gl::CLAMP_TO_EDGE as GLint
                               }); ck();
+            if self.max_texture_anisotropy > 1.0 {
+                let max_anisotropy = f32::min(flags.max_anisotropy() as f32,
+                                              self.max_texture_anisotropy);
+                gl::TexParameterf(gl::TEXTURE_2D,
+                                  GL_TEXTURE_MAX_ANISOTROPY_EXT,
+                                  max_anisotropy); ck();
+            }
         }
     }
 
@@ -508,15 +834,21 @@ impl Device for GLDevice {
         unsafe {
             self.bind_texture(texture, 0);
             if rect.origin() == Vector2I::default() && rect.size() == texture.size {
-                gl::TexImage2D(gl::TEXTURE_2D,
-                               0,
-                               texture.format.gl_internal_format(),
-                               texture.size.x() as GLsizei,
-                               texture.size.y() as GLsizei,
-                               0,
-                               texture.format.gl_format(),
-                               texture.format.gl_type(),
-                               data_ptr); ck();
+                self.tex_image_2d(texture.format, texture.size, data_ptr);
+            } else if texture.format.is_compressed() {
+                // Partial updates must start on a block boundary.
+                let block_size = texture.format.block_size();
+                assert_eq!(rect.origin().x() % block_size.x(), 0);
+                assert_eq!(rect.origin().y() % block_size.y(), 0);
+                gl::CompressedTexSubImage2D(gl::TEXTURE_2D,
+                                            0,
+                                            rect.origin().x(),
+                                            rect.origin().y(),
+                                            rect.size().x() as GLsizei,
+                                            rect.size().y() as GLsizei,
+                                            texture.format.gl_internal_format() as GLenum,
+                                            texture.format.byte_size(rect.size()) as GLsizei,
+                                            data_ptr); ck();
             } else {
                 gl::TexSubImage2D(gl::TEXTURE_2D,
                                   0,
@@ -619,6 +951,9 @@ impl Device for GLDevice {
 
     #[inline]
     fn begin_timer_query(&self, query: &Self::TimerQuery) {
+        if !self.version.supports_timer_queries() {
+            return;
+        }
         unsafe {
             gl::BeginQuery(gl::TIME_ELAPSED, query.gl_query); ck();
         }
@@ -626,12 +961,18 @@ impl Device for GLDevice {
 
     #[inline]
     fn end_timer_query(&self, _: &Self::TimerQuery) {
+        if !self.version.supports_timer_queries() {
+            return;
+        }
         unsafe {
             gl::EndQuery(gl::TIME_ELAPSED); ck();
         }
     }
 
     fn try_recv_timer_query(&self, query: &Self::TimerQuery) -> Option<Duration> {
+        if !self.version.supports_timer_queries() {
+            return Some(Duration::default());
+        }
         unsafe {
             let mut result = 0;
             gl::GetQueryObjectiv(query.gl_query, gl::QUERY_RESULT_AVAILABLE, &mut result); ck();
@@ -644,6 +985,9 @@ impl Device for GLDevice {
     }
 
     fn recv_timer_query(&self, query: &Self::TimerQuery) -> Duration {
+        if !self.version.supports_timer_queries() {
+            return Duration::default();
+        }
         unsafe {
             let mut result = 0;
             gl::GetQueryObjectui64v(query.gl_query, gl::QUERY_RESULT, &mut result); ck();
@@ -664,6 +1008,116 @@ impl Device for GLDevice {
         }
     }
 
+    fn memory_usage(&self) -> GPUMemoryUsage {
+        GPUMemoryUsage {
+            textures: self.texture_memory.get(),
+            buffers: self.buffer_memory.get(),
+        }
+    }
+
+    fn supports_texture_format(&self, format: TextureFormat) -> bool {
+        !format.is_compressed() || self.supported_compressed_formats.contains(&format)
+    }
+
+    fn insert_fence(&self) -> GLFence {
+        unsafe {
+            let gl_sync = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0); ck();
+            // Flush so that waiting on the fence from another context can't deadlock.
+            gl::Flush(); ck();
+            GLFence { gl_sync }
+        }
+    }
+
+    fn is_fence_signaled(&self, fence: &GLFence) -> bool {
+        unsafe {
+            let result = gl::ClientWaitSync(fence.gl_sync, 0, 0); ck();
+            result == gl::ALREADY_SIGNALED || result == gl::CONDITION_SATISFIED
+        }
+    }
+
+    fn wait_for_fence(&self, fence: &GLFence) {
+        unsafe {
+            let result = gl::ClientWaitSync(fence.gl_sync, gl::SYNC_FLUSH_COMMANDS_BIT, !0); ck();
+            if result == gl::WAIT_FAILED {
+                panic!("Failed to wait for a fence!");
+            }
+        }
+    }
+
+    fn gpu_wait_for_fence(&self, fence: &GLFence) {
+        unsafe {
+            gl::WaitSync(fence.gl_sync, 0, gl::TIMEOUT_IGNORED); ck();
+        }
+    }
+
+    fn check_device(&self) -> Result<(), DeviceLostError> {
+        if !gl::GetGraphicsResetStatus::is_loaded() {
+            return Ok(());
+        }
+        unsafe {
+            match gl::GetGraphicsResetStatus() {
+                gl::NO_ERROR => Ok(()),
+                gl::GUILTY_CONTEXT_RESET => Err(DeviceLostError::GuiltyContextReset),
+                gl::INNOCENT_CONTEXT_RESET => Err(DeviceLostError::InnocentContextReset),
+                _ => Err(DeviceLostError::UnknownContextReset),
+            }
+        }
+    }
+
+    fn save_state(&self) {
+        let state = self.capture_state();
+        self.saved_states.borrow_mut().push(state);
+        self.reset_state();
+    }
+
+    fn restore_state(&self) {
+        let state = self.saved_states.borrow_mut().pop().expect("No GL state was saved!");
+        self.apply_state(&state);
+    }
+
+    // Dual-source blending is core in OpenGL 3.3. OpenGL ES only has it through an extension
+    // whose shader syntax differs, so it's left out there.
+    #[inline]
+    fn supports_dual_source_blending(&self) -> bool {
+        match self.version {
+            GLVersion::GL3 => true,
+            GLVersion::GLES3 => false,
+        }
+    }
+
+    #[inline]
+    fn supports_advanced_blending(&self) -> bool {
+        self.supports_advanced_blending
+    }
+
+    #[inline]
+    fn supports_compute(&self) -> bool {
+        self.supports_compute
+    }
+
+    fn dispatch_compute(&self, dimensions: ComputeDimensions, compute_state: &ComputeState<Self>) {
+        self.set_compute_state(compute_state);
+        unsafe {
+            gl::DispatchCompute(dimensions.x, dimensions.y, dimensions.z); ck();
+            gl::MemoryBarrier(gl::ALL_BARRIER_BITS); ck();
+        }
+        self.reset_compute_state(compute_state);
+    }
+
+    fn dispatch_compute_indirect(&self,
+                                 buffer: &GLBuffer,
+                                 offset: usize,
+                                 compute_state: &ComputeState<Self>) {
+        self.set_compute_state(compute_state);
+        unsafe {
+            gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, buffer.current_gl_buffer.get()); ck();
+            gl::DispatchComputeIndirect(offset as GLintptr); ck();
+            gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, 0); ck();
+            gl::MemoryBarrier(gl::ALL_BARRIER_BITS); ck();
+        }
+        self.reset_compute_state(compute_state);
+    }
+
     fn recv_texture_data(&self, receiver: &Self::TextureDataReceiver) -> TextureData {
         unsafe {
             let result = gl::ClientWaitSync(receiver.gl_sync,
@@ -678,9 +1132,13 @@ impl Device for GLDevice {
     fn bind_buffer(&self, vertex_array: &GLVertexArray, buffer: &GLBuffer, target: BufferTarget) {
         self.bind_vertex_array(vertex_array);
         unsafe {
-            gl::BindBuffer(target.to_gl_target(), buffer.gl_buffer); ck();
+            gl::BindBuffer(target.to_gl_target(), buffer.current_gl_buffer.get()); ck();
         }
         self.unbind_vertex_array();
+
+        if let BufferTarget::Vertex = target {
+            *self.bound_vertex_buffer.borrow_mut() = Some(buffer.current_gl_buffer.clone());
+        }
     }
 
     #[inline]
@@ -693,6 +1151,7 @@ impl Device for GLDevice {
         let suffix = match kind {
             ShaderKind::Vertex => 'v',
             ShaderKind::Fragment => 'f',
+            ShaderKind::Compute => 'c',
         };
         let path = format!("shaders/gl3/{}.{}s.glsl", name, suffix);
         self.create_shader_from_source(name, &resources.slurp(&path).unwrap(), kind)
@@ -757,26 +1216,346 @@ impl GLDevice {
         }
     }
 
-    fn preprocess(&self, output: &mut Vec<u8>, source: &[u8], version: &str) {
-        let mut index = 0;
-        while index < source.len() {
-            if source[index..].starts_with(b"{{") {
-                let end_index = source[index..].iter()
-                                               .position(|character| *character == b'}')
-                                               .expect("Expected `}`!") + index;
-                assert_eq!(source[end_index + 1], b'}');
-                let ident = String::from_utf8_lossy(&source[(index + 2)..end_index]);
-                if ident == "version" {
-                    output.extend_from_slice(version.as_bytes());
-                } else {
-                    panic!("unknown template variable: `{}`", ident);
+    // The fill framebuffer is `R16F`, which OpenGL ES 3.0 can only render to with an extension.
+    // (ES 3.2 makes it core.)
+    fn has_extension(&self, name: &[u8]) -> bool {
+        unsafe {
+            let mut extension_count = 0;
+            gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut extension_count); ck();
+            for extension_index in 0..(extension_count as GLuint) {
+                let extension = gl::GetStringi(gl::EXTENSIONS, extension_index); ck();
+                if !extension.is_null() &&
+                        CStr::from_ptr(extension as *const GLchar).to_bytes() == name {
+                    return true;
                 }
-                index = end_index + 2;
-            } else {
-                output.push(source[index]);
-                index += 1;
             }
         }
+        false
+    }
+
+    fn detect_compressed_formats(&self) -> Vec<TextureFormat> {
+        let mut formats = vec![];
+        if self.has_extension(b"GL_EXT_texture_compression_s3tc") {
+            formats.push(TextureFormat::BC1);
+            formats.push(TextureFormat::BC3);
+        }
+        if self.has_extension(b"GL_ARB_texture_compression_bptc") ||
+                self.has_extension(b"GL_EXT_texture_compression_bptc") {
+            formats.push(TextureFormat::BC7);
+        }
+        // ETC2 is core in OpenGL ES 3.0. Desktop drivers may advertise it, but they usually
+        // decompress it in software, which defeats the purpose.
+        if let GLVersion::GLES3 = self.version {
+            formats.push(TextureFormat::ETC2RGBA8);
+        }
+        if self.has_extension(b"GL_KHR_texture_compression_astc_ldr") {
+            formats.push(TextureFormat::ASTC4x4);
+        }
+        formats
+    }
+
+    fn detect_max_texture_anisotropy(&self) -> f32 {
+        if !self.has_extension(b"GL_EXT_texture_filter_anisotropic") &&
+                !self.has_extension(b"GL_ARB_texture_filter_anisotropic") {
+            return 1.0;
+        }
+        unsafe {
+            let mut max_anisotropy = 1.0;
+            gl::GetFloatv(GL_MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut max_anisotropy); ck();
+            max_anisotropy
+        }
+    }
+
+    // Specifies the whole of the bound texture. `data` may be null to leave it undefined.
+    unsafe fn tex_image_2d(&self, format: TextureFormat, size: Vector2I, data: *const GLvoid) {
+        if format.is_compressed() {
+            gl::CompressedTexImage2D(gl::TEXTURE_2D,
+                                     0,
+                                     format.gl_internal_format() as GLenum,
+                                     size.x() as GLsizei,
+                                     size.y() as GLsizei,
+                                     0,
+                                     format.byte_size(size) as GLsizei,
+                                     data); ck();
+        } else {
+            gl::TexImage2D(gl::TEXTURE_2D,
+                           0,
+                           format.gl_internal_format(),
+                           size.x() as GLsizei,
+                           size.y() as GLsizei,
+                           0,
+                           format.gl_format(),
+                           format.gl_type(),
+                           data); ck();
+        }
+    }
+
+    fn check_gles3_extensions(&self) {
+        if self.has_extension(b"GL_EXT_color_buffer_half_float") ||
+                self.has_extension(b"GL_EXT_color_buffer_float") {
+            return;
+        }
+
+        warn!("Neither `GL_EXT_color_buffer_half_float` nor `GL_EXT_color_buffer_float` is \
+               available; fills will probably not render.");
+    }
+
+    fn compile_shader(&self, shader: &GLShader) {
+        if shader.gl_shader.get() != 0 {
+            return;
+        }
+
+        let gl_shader_kind = match shader.kind {
+            ShaderKind::Vertex => gl::VERTEX_SHADER,
+            ShaderKind::Fragment => gl::FRAGMENT_SHADER,
+            ShaderKind::Compute => gl::COMPUTE_SHADER,
+        };
+
+        unsafe {
+            let gl_shader = gl::CreateShader(gl_shader_kind); ck();
+            gl::ShaderSource(gl_shader,
+                             1,
+                             [shader.source.as_ptr() as *const GLchar].as_ptr(),
+                             [shader.source.len() as GLint].as_ptr()); ck();
+            gl::CompileShader(gl_shader); ck();
+
+            let mut compile_status = 0;
+            gl::GetShaderiv(gl_shader, gl::COMPILE_STATUS, &mut compile_status); ck();
+            if compile_status != gl::TRUE as GLint {
+                let mut info_log_length = 0;
+                gl::GetShaderiv(gl_shader, gl::INFO_LOG_LENGTH, &mut info_log_length); ck();
+                let mut info_log = vec![0; info_log_length as usize];
+                gl::GetShaderInfoLog(gl_shader,
+                                     info_log.len() as GLint,
+                                     ptr::null_mut(),
+                                     info_log.as_mut_ptr() as *mut GLchar); ck();
+                error!("Shader info log:\n{}", String::from_utf8_lossy(&info_log));
+                panic!("{:?} shader '{}' compilation failed", shader.kind, shader.name);
+            }
+
+            shader.gl_shader.set(gl_shader);
+        }
+    }
+
+    fn link_program(&self, name: &str, shaders: &[&GLShader]) -> GLuint {
+        let cache_path = self.program_cache_dir.as_ref().map(|cache_dir| {
+            cache_dir.join(program_cache_file_name(name, shaders))
+        });
+        if let Some(ref cache_path) = cache_path {
+            if let Some(gl_program) = self.load_program_binary(cache_path) {
+                return gl_program;
+            }
+        }
+
+        for shader in shaders {
+            self.compile_shader(shader);
+        }
+
+        let gl_program;
+        unsafe {
+            gl_program = gl::CreateProgram(); ck();
+            for shader in shaders {
+                gl::AttachShader(gl_program, shader.gl_shader.get()); ck();
+            }
+            if cache_path.is_some() {
+                gl::ProgramParameteri(gl_program,
+                                      gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                                      gl::TRUE as GLint); ck();
+            }
+            gl::LinkProgram(gl_program); ck();
+
+            let mut link_status = 0;
+            gl::GetProgramiv(gl_program, gl::LINK_STATUS, &mut link_status); ck();
+            if link_status != gl::TRUE as GLint {
+                let mut info_log_length = 0;
+                gl::GetProgramiv(gl_program, gl::INFO_LOG_LENGTH, &mut info_log_length); ck();
+                let mut info_log = vec![0; info_log_length as usize];
+                gl::GetProgramInfoLog(gl_program,
+                                      info_log.len() as GLint,
+                                      ptr::null_mut(),
+                                      info_log.as_mut_ptr() as *mut GLchar); ck();
+                error!("Program info log:\n{}", String::from_utf8_lossy(&info_log));
+                panic!("Program '{}' linking failed", name);
+            }
+        }
+
+        if let Some(ref cache_path) = cache_path {
+            self.save_program_binary(gl_program, cache_path);
+        }
+
+        gl_program
+    }
+
+    // Program binaries are core in OpenGL ES 3.0 and OpenGL 4.1, and available earlier through
+    // `GL_ARB_get_program_binary`. Drivers may still support no binary formats at all.
+    fn supports_program_binaries(&self) -> bool {
+        let supported = match self.version {
+            GLVersion::GLES3 => true,
+            GLVersion::GL3 => {
+                let (mut major_version, mut minor_version) = (0, 0);
+                unsafe {
+                    gl::GetIntegerv(gl::MAJOR_VERSION, &mut major_version); ck();
+                    gl::GetIntegerv(gl::MINOR_VERSION, &mut minor_version); ck();
+                }
+                gl::GetProgramBinary::is_loaded() &&
+                    ((major_version, minor_version) >= (4, 1) ||
+                     self.has_extension(b"GL_ARB_get_program_binary"))
+            }
+        };
+        supported && !self.program_binary_formats().is_empty()
+    }
+
+    fn program_binary_formats(&self) -> Vec<GLenum> {
+        unsafe {
+            let mut format_count = 0;
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut format_count); ck();
+            let mut formats = vec![0; format_count as usize];
+            if format_count > 0 {
+                gl::GetIntegerv(gl::PROGRAM_BINARY_FORMATS, formats.as_mut_ptr()); ck();
+            }
+            formats.into_iter().map(|format| format as GLenum).collect()
+        }
+    }
+
+    fn load_program_binary(&self, path: &Path) -> Option<GLuint> {
+        let data = fs::read(path).ok()?;
+        let (format, binary) = parse_program_binary(&data)?;
+        if !self.program_binary_formats().contains(&format) {
+            return None;
+        }
+
+        unsafe {
+            let gl_program = gl::CreateProgram(); ck();
+            gl::ProgramBinary(gl_program,
+                              format,
+                              binary.as_ptr() as *const GLvoid,
+                              binary.len() as GLsizei); ck();
+            let mut link_status = 0;
+            gl::GetProgramiv(gl_program, gl::LINK_STATUS, &mut link_status); ck();
+            if link_status == gl::TRUE as GLint {
+                return Some(gl_program);
+            }
+
+            // The driver rejected the binary, probably because it was updated.
+            debug!("Discarding stale program binary `{}`.", path.display());
+            gl::DeleteProgram(gl_program); ck();
+            None
+        }
+    }
+
+    fn save_program_binary(&self, gl_program: GLuint, path: &Path) {
+        let mut data;
+        unsafe {
+            let mut binary_length = 0;
+            gl::GetProgramiv(gl_program, gl::PROGRAM_BINARY_LENGTH, &mut binary_length); ck();
+            if binary_length <= 0 {
+                return;
+            }
+            data = vec![0; 4 + binary_length as usize];
+            let (mut format, mut written_length) = (0, 0);
+            gl::GetProgramBinary(gl_program,
+                                 binary_length,
+                                 &mut written_length,
+                                 &mut format,
+                                 data[4..].as_mut_ptr() as *mut GLvoid); ck();
+            data.truncate(4 + written_length as usize);
+            data[0..4].copy_from_slice(&format.to_le_bytes());
+        }
+
+        // Write to a temporary file first so that a concurrent launch never reads half a binary.
+        let temp_path = path.with_extension("tmp");
+        let result = path.parent()
+                         .map_or(Ok(()), fs::create_dir_all)
+                         .and_then(|_| fs::write(&temp_path, &data))
+                         .and_then(|_| fs::rename(&temp_path, path));
+        if let Err(error) = result {
+            warn!("Failed to save program binary `{}`: {}", path.display(), error);
+        }
+    }
+
+    // Persistently-mapped streaming buffers need `glBufferStorage()`, from OpenGL 4.4 or
+    // `GL_ARB_buffer_storage`. The `gl` crate doesn't load the GLES extension's entry point.
+    fn detect_buffer_storage(&self) -> bool {
+        match self.version {
+            GLVersion::GLES3 => false,
+            GLVersion::GL3 => {
+                let (mut major_version, mut minor_version) = (0, 0);
+                unsafe {
+                    gl::GetIntegerv(gl::MAJOR_VERSION, &mut major_version); ck();
+                    gl::GetIntegerv(gl::MINOR_VERSION, &mut minor_version); ck();
+                }
+                gl::BufferStorage::is_loaded() &&
+                    ((major_version, minor_version) >= (4, 4) ||
+                     self.has_extension(b"GL_ARB_buffer_storage"))
+            }
+        }
+    }
+
+    // Compute shaders and storage buffers are core in OpenGL 4.3 and OpenGL ES 3.1.
+    fn detect_compute(&self) -> bool {
+        let (mut major_version, mut minor_version) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::MAJOR_VERSION, &mut major_version); ck();
+            gl::GetIntegerv(gl::MINOR_VERSION, &mut minor_version); ck();
+        }
+        let supported = match self.version {
+            GLVersion::GLES3 => (major_version, minor_version) >= (3, 1),
+            GLVersion::GL3 => {
+                (major_version, minor_version) >= (4, 3) ||
+                    (self.has_extension(b"GL_ARB_compute_shader") &&
+                     self.has_extension(b"GL_ARB_shader_storage_buffer_object"))
+            }
+        };
+        supported && gl::DispatchCompute::is_loaded()
+    }
+
+    // Only the coherent variant of the extension is used. Without it, overlapping primitives
+    // within a draw call, such as the alpha tiles of different paths in one batch, blend
+    // unpredictably unless separated by `glBlendBarrierKHR()`.
+    fn detect_advanced_blending(&self) -> bool {
+        self.has_extension(b"GL_KHR_blend_equation_advanced_coherent")
+    }
+
+    unsafe fn set_vertex_attr_pointer(&self, attr: GLuint, descriptor: &VertexAttrDescriptor) {
+        let attr_type = descriptor.attr_type.to_gl_type();
+        match descriptor.class {
+            VertexAttrClass::Float | VertexAttrClass::FloatNorm => {
+                let normalized = if descriptor.class == VertexAttrClass::FloatNorm {
+                    gl::TRUE
+                } else {
+                    gl::FALSE
+                };
+                gl::VertexAttribPointer(attr,
+                                        descriptor.size as GLint,
+                                        attr_type,
+                                        normalized,
+                                        descriptor.stride as GLint,
+                                        descriptor.offset as *const GLvoid); ck();
+            }
+            VertexAttrClass::Int => {
+                gl::VertexAttribIPointer(attr,
+                                         descriptor.size as GLint,
+                                         attr_type,
+                                         descriptor.stride as GLint,
+                                         descriptor.offset as *const GLvoid); ck();
+            }
+        }
+    }
+
+    // Points attributes of the bound vertex array at whichever region their streaming buffers
+    // were last written to.
+    fn rebind_streamed_vertex_attrs(&self, vertex_array: &GLVertexArray) {
+        for bound_attr in vertex_array.attrs.borrow().iter() {
+            let gl_buffer = bound_attr.buffer.get();
+            if gl_buffer == bound_attr.bound_gl_buffer.get() {
+                continue;
+            }
+            unsafe {
+                gl::BindBuffer(gl::ARRAY_BUFFER, gl_buffer); ck();
+                self.set_vertex_attr_pointer(bound_attr.attr, &bound_attr.descriptor);
+            }
+            bound_attr.bound_gl_buffer.set(gl_buffer);
+        }
     }
 
     fn clear(&self, ops: &ClearOps) {
@@ -789,7 +1568,11 @@ impl GLDevice {
             }
             if let Some(depth) = ops.depth {
                 gl::DepthMask(gl::TRUE); ck();
-                gl::ClearDepthf(depth as _); ck(); // FIXME(pcwalton): GLES
+                match self.version {
+                    GLVersion::GL3 => gl::ClearDepth(depth as GLdouble),
+                    GLVersion::GLES3 => gl::ClearDepthf(depth as GLfloat),
+                }
+                ck();
                 flags |= gl::DEPTH_BUFFER_BIT;
             }
             if let Some(stencil) = ops.stencil {
@@ -838,13 +1621,29 @@ impl GLDevice {
                     texture_data_len = pixels.len() * mem::size_of::<f32>();
                     texture_data = TextureData::F32(pixels);
                 }
+                _ => panic!("Can't read back compressed textures!"),
             }
 
+            // OpenGL ES has no `glGetBufferSubData()`, so map the buffer instead.
             gl::BindBuffer(gl::PIXEL_PACK_BUFFER, receiver.gl_pixel_buffer); ck();
-            gl::GetBufferSubData(gl::PIXEL_PACK_BUFFER,
-                                 0,
-                                 texture_data_len as GLsizeiptr,
-                                 texture_data_ptr as *mut GLvoid); ck();
+            match self.version {
+                GLVersion::GL3 => {
+                    gl::GetBufferSubData(gl::PIXEL_PACK_BUFFER,
+                                         0,
+                                         texture_data_len as GLsizeiptr,
+                                         texture_data_ptr as *mut GLvoid); ck();
+                }
+                GLVersion::GLES3 => {
+                    let mapped_ptr = gl::MapBufferRange(gl::PIXEL_PACK_BUFFER,
+                                                        0,
+                                                        texture_data_len as GLsizeiptr,
+                                                        gl::MAP_READ_BIT); ck();
+                    ptr::copy_nonoverlapping(mapped_ptr as *const u8,
+                                             texture_data_ptr,
+                                             texture_data_len);
+                    gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER); ck();
+                }
+            }
             gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0); ck();
 
             match texture_data {
@@ -861,6 +1660,14 @@ impl GLDevice {
 
 pub struct GLVertexArray {
     pub gl_vertex_array: GLuint,
+    attrs: RefCell<Vec<GLBoundVertexAttr>>,
+}
+
+struct GLBoundVertexAttr {
+    attr: GLuint,
+    descriptor: VertexAttrDescriptor,
+    buffer: Rc<Cell<GLuint>>,
+    bound_gl_buffer: Cell<GLuint>,
 }
 
 impl Drop for GLVertexArray {
@@ -914,6 +1721,79 @@ impl GLVertexAttr {
     }
 }
 
+// The context state that `save_state()` preserves, as queried from GL.
+#[derive(Default)]
+struct GLSavedState {
+    program: GLint,
+    vertex_array: GLint,
+    array_buffer: GLint,
+    draw_framebuffer: GLint,
+    read_framebuffer: GLint,
+    viewport: [GLint; 4],
+    active_texture: GLint,
+    textures: [GLint; SAVED_TEXTURE_UNIT_COUNT],
+    samplers: [GLint; SAVED_TEXTURE_UNIT_COUNT],
+    blend: GLboolean,
+    blend_src_rgb: GLint,
+    blend_dest_rgb: GLint,
+    blend_src_alpha: GLint,
+    blend_dest_alpha: GLint,
+    blend_equation_rgb: GLint,
+    blend_equation_alpha: GLint,
+    depth_test: GLboolean,
+    depth_func: GLint,
+    depth_mask: GLboolean,
+    stencil_test: GLboolean,
+    stencil_func: GLint,
+    stencil_ref: GLint,
+    stencil_value_mask: GLint,
+    stencil_write_mask: GLint,
+    stencil_fail: GLint,
+    stencil_depth_fail: GLint,
+    stencil_pass: GLint,
+    color_mask: [GLboolean; 4],
+    clear_color: [GLfloat; 4],
+    clear_depth: GLfloat,
+    clear_stencil: GLint,
+    scissor_test: GLboolean,
+    scissor_box: [GLint; 4],
+    cull_face: GLboolean,
+    framebuffer_srgb: GLboolean,
+    pack_alignment: GLint,
+    unpack_alignment: GLint,
+    unpack_row_length: GLint,
+}
+
+/// A GL sync object.
+///
+/// Sync objects are shared between contexts in a share group, so an application's own context
+/// can wait on fences from Pathfinder with `glWaitSync()`, and vice versa via `GLFence::wrap()`.
+pub struct GLFence {
+    gl_sync: GLsync,
+}
+
+impl GLFence {
+    /// Takes ownership of a sync object created outside Pathfinder.
+    #[inline]
+    pub fn wrap(gl_sync: GLsync) -> GLFence {
+        GLFence { gl_sync }
+    }
+
+    #[inline]
+    pub fn gl_sync(&self) -> GLsync {
+        self.gl_sync
+    }
+}
+
+impl Drop for GLFence {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSync(self.gl_sync); ck();
+        }
+    }
+}
+
 pub struct GLFramebuffer {
     pub gl_framebuffer: GLuint,
     pub texture: GLTexture,
@@ -929,6 +1809,11 @@ impl Drop for GLFramebuffer {
 
 pub struct GLBuffer {
     pub gl_buffer: GLuint,
+    // The buffer that vertex arrays should read from: either `gl_buffer` or the region of the
+    // stream most recently written.
+    current_gl_buffer: Rc<Cell<GLuint>>,
+    stream: RefCell<Option<GLStreamBuffer>>,
+    allocation: Allocation,
 }
 
 impl Drop for GLBuffer {
@@ -939,17 +1824,144 @@ impl Drop for GLBuffer {
     }
 }
 
+// A ring of persistently-mapped buffers for data that is rewritten every frame.
+//
+// Writing into a mapped buffer avoids the reallocation and copy that `glBufferData()` incurs. Each
+// region is fenced when we move on from it, and we wait on that fence before writing to it again,
+// so we never overwrite data the GPU has yet to read.
+struct GLStreamBuffer {
+    regions: [GLStreamRegion; STREAM_REGION_COUNT],
+    next_region: usize,
+    current_region: Option<usize>,
+    allocation: Allocation,
+}
+
+struct GLStreamRegion {
+    gl_buffer: GLuint,
+    capacity: usize,
+    mapped: *mut u8,
+    gl_sync: Option<GLsync>,
+}
+
+impl GLStreamBuffer {
+    fn new(allocation: Allocation) -> GLStreamBuffer {
+        GLStreamBuffer {
+            regions: [GLStreamRegion::new(), GLStreamRegion::new(), GLStreamRegion::new()],
+            next_region: 0,
+            current_region: None,
+            allocation,
+        }
+    }
+
+    // Copies `len` bytes from `data`, which may be null to leave the contents undefined, into the
+    // next region, and returns the buffer that region lives in.
+    unsafe fn write(&mut self, data: *const u8, len: usize) -> GLuint {
+        self.leave_current_region();
+        let region_index = self.next_region;
+        self.next_region = (region_index + 1) % STREAM_REGION_COUNT;
+        self.current_region = Some(region_index);
+
+        let region = &mut self.regions[region_index];
+        region.wait();
+        if region.capacity < len {
+            region.reallocate(stream_region_capacity(len));
+        }
+        if !data.is_null() {
+            ptr::copy_nonoverlapping(data, region.mapped, len);
+        }
+        let gl_buffer = region.gl_buffer;
+
+        let capacity: usize = self.regions.iter().map(|region| region.capacity).sum();
+        self.allocation.resize(capacity as u64);
+        gl_buffer
+    }
+
+    fn leave_current_region(&mut self) {
+        if let Some(region_index) = self.current_region.take() {
+            self.regions[region_index].fence();
+        }
+    }
+}
+
+impl GLStreamRegion {
+    fn new() -> GLStreamRegion {
+        GLStreamRegion { gl_buffer: 0, capacity: 0, mapped: ptr::null_mut(), gl_sync: None }
+    }
+
+    fn fence(&mut self) {
+        unsafe {
+            if let Some(gl_sync) = self.gl_sync.take() {
+                gl::DeleteSync(gl_sync); ck();
+            }
+            self.gl_sync = Some(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0)); ck();
+        }
+    }
+
+    fn wait(&mut self) {
+        if let Some(gl_sync) = self.gl_sync.take() {
+            unsafe {
+                gl::ClientWaitSync(gl_sync, gl::SYNC_FLUSH_COMMANDS_BIT, !0); ck();
+                gl::DeleteSync(gl_sync); ck();
+            }
+        }
+    }
+
+    unsafe fn reallocate(&mut self, capacity: usize) {
+        self.destroy();
+
+        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+        gl::GenBuffers(1, &mut self.gl_buffer); ck();
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.gl_buffer); ck();
+        gl::BufferStorage(gl::ARRAY_BUFFER, capacity as GLsizeiptr, ptr::null(), flags); ck();
+        self.mapped = gl::MapBufferRange(gl::ARRAY_BUFFER, 0, capacity as GLsizeiptr, flags) as
+            *mut u8; ck();
+        self.capacity = capacity;
+    }
+
+    unsafe fn destroy(&mut self) {
+        if self.gl_buffer != 0 {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.gl_buffer); ck();
+            gl::UnmapBuffer(gl::ARRAY_BUFFER); ck();
+            gl::DeleteBuffers(1, &self.gl_buffer); ck();
+            self.gl_buffer = 0;
+        }
+        self.mapped = ptr::null_mut();
+        self.capacity = 0;
+    }
+}
+
+impl Drop for GLStreamRegion {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(gl_sync) = self.gl_sync.take() {
+                gl::DeleteSync(gl_sync); ck();
+            }
+            self.destroy();
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GLUniform {
     location: GLint,
 }
 
+#[derive(Debug)]
+pub struct GLStorageBuffer {
+    binding: GLuint,
+}
+
 pub struct GLProgram {
     pub gl_program: GLuint,
     #[allow(dead_code)]
-    vertex_shader: GLShader,
-    #[allow(dead_code)]
-    fragment_shader: GLShader,
+    shaders: GLProgramShaders,
+}
+
+// Shaders are kept alive for as long as the program that links them.
+#[allow(dead_code)]
+enum GLProgramShaders {
+    Raster { vertex: GLShader, fragment: GLShader },
+    Compute(GLShader),
 }
 
 impl Drop for GLProgram {
@@ -961,13 +1973,19 @@ impl Drop for GLProgram {
 }
 
 pub struct GLShader {
-    gl_shader: GLuint,
+    // Zero until the shader is compiled.
+    gl_shader: Cell<GLuint>,
+    name: String,
+    kind: ShaderKind,
+    source: Vec<u8>,
 }
 
 impl Drop for GLShader {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteShader(self.gl_shader); ck();
+        if self.gl_shader.get() != 0 {
+            unsafe {
+                gl::DeleteShader(self.gl_shader.get()); ck();
+            }
         }
     }
 }
@@ -976,6 +1994,21 @@ pub struct GLTexture {
     gl_texture: GLuint,
     pub size: Vector2I,
     pub format: TextureFormat,
+    #[allow(dead_code)]
+    allocation: Allocation,
+    // Whether the texture came from `GLDevice::wrap_texture()` and so belongs to someone else.
+    is_external: bool,
+}
+
+impl Drop for GLTexture {
+    fn drop(&mut self) {
+        if self.is_external {
+            return;
+        }
+        unsafe {
+            gl::DeleteTextures(1, &self.gl_texture); ck();
+        }
+    }
 }
 
 pub struct GLTimerQuery {
@@ -1006,6 +2039,8 @@ impl BlendFactorExt for BlendFactor {
             BlendFactor::DestAlpha => gl::DST_ALPHA,
             BlendFactor::OneMinusDestAlpha => gl::ONE_MINUS_DST_ALPHA,
             BlendFactor::DestColor => gl::DST_COLOR,
+            BlendFactor::Src1Alpha => gl::SRC1_ALPHA,
+            BlendFactor::OneMinusSrc1Alpha => gl::ONE_MINUS_SRC1_ALPHA,
         }
     }
 }
@@ -1023,6 +2058,19 @@ impl BlendOpExt for BlendOp {
             BlendOp::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
             BlendOp::Min => gl::MIN,
             BlendOp::Max => gl::MAX,
+            BlendOp::Multiply => GL_MULTIPLY_KHR,
+            BlendOp::Screen => GL_SCREEN_KHR,
+            BlendOp::Overlay => GL_OVERLAY_KHR,
+            BlendOp::ColorDodge => GL_COLORDODGE_KHR,
+            BlendOp::ColorBurn => GL_COLORBURN_KHR,
+            BlendOp::HardLight => GL_HARDLIGHT_KHR,
+            BlendOp::SoftLight => GL_SOFTLIGHT_KHR,
+            BlendOp::Difference => GL_DIFFERENCE_KHR,
+            BlendOp::Exclusion => GL_EXCLUSION_KHR,
+            BlendOp::Hue => GL_HSL_HUE_KHR,
+            BlendOp::Saturation => GL_HSL_SATURATION_KHR,
+            BlendOp::Color => GL_HSL_COLOR_KHR,
+            BlendOp::Luminosity => GL_HSL_LUMINOSITY_KHR,
         }
     }
 }
@@ -1036,6 +2084,7 @@ impl BufferTargetExt for BufferTarget {
         match self {
             BufferTarget::Vertex => gl::ARRAY_BUFFER,
             BufferTarget::Index => gl::ELEMENT_ARRAY_BUFFER,
+            BufferTarget::Storage => gl::SHADER_STORAGE_BUFFER,
         }
     }
 }
@@ -1103,24 +2152,30 @@ impl TextureFormatExt for TextureFormat {
         match self {
             TextureFormat::R8 => gl::R8 as GLint,
             TextureFormat::R16F => gl::R16F as GLint,
-            TextureFormat::RGBA8 => gl::RGBA as GLint,
+            TextureFormat::RGBA8 => gl::RGBA8 as GLint,
             TextureFormat::RGBA16F => gl::RGBA16F as GLint,
             TextureFormat::RGBA32F => gl::RGBA32F as GLint,
+            TextureFormat::BC1 => GL_COMPRESSED_RGBA_S3TC_DXT1_EXT as GLint,
+            TextureFormat::BC3 => GL_COMPRESSED_RGBA_S3TC_DXT5_EXT as GLint,
+            TextureFormat::BC7 => GL_COMPRESSED_RGBA_BPTC_UNORM as GLint,
+            TextureFormat::ETC2RGBA8 => GL_COMPRESSED_RGBA8_ETC2_EAC as GLint,
+            TextureFormat::ASTC4x4 => GL_COMPRESSED_RGBA_ASTC_4X4_KHR as GLint,
         }
     }
 
+    // Compressed formats have no pixel format or type; they're uploaded whole.
     fn gl_format(self) -> GLuint {
         match self {
             TextureFormat::R8 | TextureFormat::R16F => gl::RED,
-            TextureFormat::RGBA8 | TextureFormat::RGBA16F | TextureFormat::RGBA32F => gl::RGBA,
+            _ => gl::RGBA,
         }
     }
 
     fn gl_type(self) -> GLuint {
         match self {
-            TextureFormat::R8 | TextureFormat::RGBA8 => gl::UNSIGNED_BYTE,
             TextureFormat::R16F | TextureFormat::RGBA16F => gl::HALF_FLOAT,
             TextureFormat::RGBA32F => gl::FLOAT,
+            _ => gl::UNSIGNED_BYTE,
         }
     }
 }
@@ -1168,10 +2223,46 @@ pub enum GLVersion {
 }
 
 impl GLVersion {
-    fn to_glsl_version_spec(&self) -> &'static str {
+    // Compute shaders need GLSL 4.30 or GLSL ES 3.10.
+    fn glsl_version_spec(self, kind: ShaderKind) -> &'static str {
+        match (self, kind) {
+            (GLVersion::GL3, ShaderKind::Compute) => "430",
+            (GLVersion::GL3, ShaderKind::Vertex) | (GLVersion::GL3, ShaderKind::Fragment) => "330",
+            (GLVersion::GLES3, ShaderKind::Compute) => "310 es",
+            (GLVersion::GLES3, ShaderKind::Vertex) |
+            (GLVersion::GLES3, ShaderKind::Fragment) => "300 es",
+        }
+    }
+
+    // `GL_TIME_ELAPSED` is only available on OpenGL ES via `GL_EXT_disjoint_timer_query`, whose
+    // entry points have different names, so GPU timings are simply reported as zero there.
+    #[inline]
+    fn supports_timer_queries(&self) -> bool {
         match *self {
-            GLVersion::GL3 => "330",
-            GLVersion::GLES3 => "300 es",
+            GLVersion::GL3 => true,
+            GLVersion::GLES3 => false,
+        }
+    }
+}
+
+fn preprocess(output: &mut Vec<u8>, source: &[u8], version: &str) {
+    let mut index = 0;
+    while index < source.len() {
+        if source[index..].starts_with(b"{{") {
+            let end_index = source[index..].iter()
+                                           .position(|character| *character == b'}')
+                                           .expect("Expected `}`!") + index;
+            assert_eq!(source[end_index + 1], b'}');
+            let ident = String::from_utf8_lossy(&source[(index + 2)..end_index]);
+            if ident == "version" {
+                output.extend_from_slice(version.as_bytes());
+            } else {
+                panic!("unknown template variable: `{}`", ident);
+            }
+            index = end_index + 2;
+        } else {
+            output.push(source[index]);
+            index += 1;
         }
     }
 }
@@ -1183,8 +2274,10 @@ fn ck() {
     unsafe {
         // Note that ideally we should be calling gl::GetError() in a loop until it
         // returns gl::NO_ERROR, but for now we'll just report the first one we find.
+        // After a context reset, every call fails; let the renderer report that through
+        // `check_device()` instead of panicking here.
         let err = gl::GetError();
-        if err != gl::NO_ERROR {
+        if err != gl::NO_ERROR && err != gl::CONTEXT_LOST {
             panic!("GL error: 0x{:x} ({})", err, match err {
                 gl::INVALID_ENUM => "INVALID_ENUM",
                 gl::INVALID_VALUE => "INVALID_VALUE",
@@ -1204,6 +2297,63 @@ fn ck() {}
 
 // Utilities
 
+fn set_capability(capability: GLenum, enabled: GLboolean) {
+    unsafe {
+        if enabled == gl::TRUE {
+            gl::Enable(capability);
+        } else {
+            gl::Disable(capability);
+        }
+        ck();
+    }
+}
+
+fn texture_byte_size(format: TextureFormat, size: Vector2I) -> u64 {
+    format.byte_size(size) as u64
+}
+
+// Binaries are only valid for the driver that produced them, so key on the driver as well as the
+// shader sources.
+fn program_cache_file_name(name: &str, shaders: &[&GLShader]) -> String {
+    let mut driver_strings = vec![];
+    for &gl_string in &[gl::VENDOR, gl::RENDERER, gl::VERSION] {
+        unsafe {
+            let string = gl::GetString(gl_string); ck();
+            if !string.is_null() {
+                driver_strings.push(CStr::from_ptr(string as *const GLchar).to_bytes());
+            }
+        }
+    }
+    let shader_sources: Vec<&[u8]> = shaders.iter().map(|shader| &shader.source[..]).collect();
+    hash_program_cache_file_name(name, &driver_strings, &shader_sources)
+}
+
+fn hash_program_cache_file_name(name: &str, driver_strings: &[&[u8]], shader_sources: &[&[u8]])
+                                -> String {
+    let mut hasher = DefaultHasher::new();
+    for driver_string in driver_strings {
+        driver_string.hash(&mut hasher);
+    }
+    for shader_source in shader_sources {
+        shader_source.hash(&mut hasher);
+    }
+    format!("{}-{:016x}.bin", name, hasher.finish())
+}
+
+// Cache files consist of the binary format as a little-endian `u32`, followed by the binary.
+fn parse_program_binary(data: &[u8]) -> Option<(GLenum, &[u8])> {
+    if data.len() < 4 {
+        return None;
+    }
+    let format = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+    Some((format, &data[4..]))
+}
+
+// Stream regions grow in powers of two so that slowly growing uploads don't reallocate every frame.
+fn stream_region_capacity(len: usize) -> usize {
+    len.max(STREAM_REGION_MIN_CAPACITY).next_power_of_two()
+}
+
 // Flips a buffer of image data upside-down.
 fn flip_y<T>(pixels: &mut [T], size: Vector2I, channels: usize) {
     let stride = size.x() as usize * channels;
@@ -1214,3 +2364,90 @@ fn flip_y<T>(pixels: &mut [T], size: Vector2I, channels: usize) {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{STREAM_REGION_MIN_CAPACITY, hash_program_cache_file_name, parse_program_binary};
+    use super::{GLVersion, preprocess, stream_region_capacity};
+    use pathfinder_gpu::ShaderKind;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_stream_region_capacity() {
+        assert_eq!(stream_region_capacity(0), STREAM_REGION_MIN_CAPACITY);
+        assert_eq!(stream_region_capacity(1), STREAM_REGION_MIN_CAPACITY);
+        assert_eq!(stream_region_capacity(STREAM_REGION_MIN_CAPACITY), STREAM_REGION_MIN_CAPACITY);
+        assert_eq!(stream_region_capacity(STREAM_REGION_MIN_CAPACITY + 1),
+                   STREAM_REGION_MIN_CAPACITY * 2);
+        assert_eq!(stream_region_capacity(1_000_000), 1 << 20);
+    }
+
+    #[test]
+    fn test_program_cache_file_name() {
+        let driver: &[&[u8]] = &[b"Vendor", b"Renderer", b"4.6"];
+        let sources: &[&[u8]] = &[b"void main() {}", b"void main() { discard; }"];
+        let file_name = hash_program_cache_file_name("fill", driver, sources);
+        assert!(file_name.starts_with("fill-") && file_name.ends_with(".bin"));
+        assert_eq!(file_name, hash_program_cache_file_name("fill", driver, sources));
+
+        // Both a driver update and a shader change must miss the cache.
+        let new_driver: &[&[u8]] = &[b"Vendor", b"Renderer", b"4.6.1"];
+        assert_ne!(file_name, hash_program_cache_file_name("fill", new_driver, sources));
+        assert_ne!(file_name, hash_program_cache_file_name("fill", driver, &sources[0..1]));
+    }
+
+    #[test]
+    fn test_parse_program_binary() {
+        let mut data = 0x8e21_u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(parse_program_binary(&data), Some((0x8e21, &[1, 2, 3][..])));
+        assert_eq!(parse_program_binary(&data[0..4]), Some((0x8e21, &[][..])));
+        assert_eq!(parse_program_binary(&data[0..3]), None);
+    }
+
+    #[test]
+    fn test_glsl_version_spec() {
+        assert_eq!(GLVersion::GL3.glsl_version_spec(ShaderKind::Vertex), "330");
+        assert_eq!(GLVersion::GL3.glsl_version_spec(ShaderKind::Fragment), "330");
+        assert_eq!(GLVersion::GL3.glsl_version_spec(ShaderKind::Compute), "430");
+        assert_eq!(GLVersion::GLES3.glsl_version_spec(ShaderKind::Vertex), "300 es");
+        assert_eq!(GLVersion::GLES3.glsl_version_spec(ShaderKind::Compute), "310 es");
+    }
+
+    #[test]
+    fn test_timer_queries_are_skipped_on_gles3() {
+        assert!(GLVersion::GL3.supports_timer_queries());
+        assert!(!GLVersion::GLES3.supports_timer_queries());
+    }
+
+    #[test]
+    fn test_gl3_shaders_preprocess_for_gles3() {
+        let shader_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../resources/shaders/gl3");
+        let mut shader_count = 0;
+        for entry in fs::read_dir(&shader_dir).unwrap() {
+            let path = entry.unwrap().path();
+            let file_name = path.file_name().unwrap().to_str().unwrap().to_owned();
+            let kind = if file_name.ends_with(".vs.glsl") {
+                ShaderKind::Vertex
+            } else if file_name.ends_with(".fs.glsl") {
+                ShaderKind::Fragment
+            } else {
+                continue;
+            };
+
+            let mut output = vec![];
+            let version = GLVersion::GLES3.glsl_version_spec(kind);
+            preprocess(&mut output, &fs::read(&path).unwrap(), version);
+            let output = String::from_utf8(output).unwrap();
+            assert!(output.starts_with("#version 300 es\n"), "{}", file_name);
+            assert!(!output.contains("GL_GOOGLE_include_directive"), "{}", file_name);
+            assert!(output.contains("precision highp float;"), "{}", file_name);
+            if file_name.starts_with("mask_") {
+                assert!(output.contains("precision highp sampler2D;"), "{}", file_name);
+            }
+            shader_count += 1;
+        }
+        assert!(shader_count > 0);
+    }
+}
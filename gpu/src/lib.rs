@@ -13,6 +13,7 @@
 #[macro_use]
 extern crate bitflags;
 
+use crate::memory::GPUMemoryUsage;
 use half::f16;
 use image::ImageFormat;
 use pathfinder_color::ColorF;
@@ -21,14 +22,20 @@ use pathfinder_geometry::transform3d::Transform4F;
 use pathfinder_geometry::vector::Vector2I;
 use pathfinder_resources::ResourceLoader;
 use pathfinder_simd::default::{F32x2, F32x4};
+use std::error::Error;
+use std::fmt;
 use std::os::raw::c_void;
 use std::time::Duration;
 
+pub mod memory;
+
 pub trait Device: Sized {
     type Buffer;
+    type Fence;
     type Framebuffer;
     type Program;
     type Shader;
+    type StorageBuffer;
     type Texture;
     type TextureDataReceiver;
     type TimerQuery;
@@ -93,6 +100,76 @@ pub trait Device: Sized {
     fn recv_timer_query(&self, query: &Self::TimerQuery) -> Duration;
     fn try_recv_texture_data(&self, receiver: &Self::TextureDataReceiver) -> Option<TextureData>;
     fn recv_texture_data(&self, receiver: &Self::TextureDataReceiver) -> TextureData;
+    fn memory_usage(&self) -> GPUMemoryUsage;
+    /// Returns true if textures of the given format can be created and sampled.
+    ///
+    /// The uncompressed formats are always supported. Compressed formats depend on the hardware
+    /// and driver.
+    fn supports_texture_format(&self, format: TextureFormat) -> bool;
+
+    // Fences order Pathfinder's GPU work against work submitted elsewhere, such as by an
+    // embedding application's own renderer.
+
+    /// Returns a fence that signals once all commands submitted so far have finished executing.
+    fn insert_fence(&self) -> Self::Fence;
+    /// Returns true if the fence has signaled, without blocking.
+    fn is_fence_signaled(&self, fence: &Self::Fence) -> bool;
+    /// Blocks until the fence has signaled.
+    fn wait_for_fence(&self, fence: &Self::Fence);
+    /// Makes the GPU wait for the fence before executing any commands submitted after this call,
+    /// without blocking the CPU.
+    fn gpu_wait_for_fence(&self, fence: &Self::Fence);
+
+    /// Returns an error if the device has been lost, after which nothing created with it can be
+    /// used again.
+    ///
+    /// GL only reports context resets if the context was created with reset notification
+    /// enabled (for example, via `ARB_robustness`).
+    fn check_device(&self) -> Result<(), DeviceLostError>;
+
+    // State saving lets Pathfinder share a context with an embedding application, such as a game
+    // engine drawing into the same GL context, without disturbing the application's pipeline
+    // state. Backends without global context state implement these as no-ops.
+
+    /// Saves the context state that Pathfinder's commands modify and resets it to the defaults
+    /// that they expect. Calls may nest.
+    fn save_state(&self);
+    /// Restores the state saved by the matching `save_state()` call.
+    fn restore_state(&self);
+
+    /// Returns true if blend states may use the `Src1Alpha` factors, which read a second output
+    /// of the fragment shader.
+    fn supports_dual_source_blending(&self) -> bool;
+    /// Returns true if blend states may use the advanced `BlendOp`s, such as `Multiply`. Programs
+    /// drawn with them must be built from shaders written for them, such as
+    /// `tile_alpha_advanced.fs.glsl`.
+    fn supports_advanced_blending(&self) -> bool;
+
+    // Compute support is optional; callers must check `supports_compute()` before creating
+    // compute programs or dispatching.
+
+    /// Returns true if compute shaders and storage buffers are available.
+    fn supports_compute(&self) -> bool;
+    fn create_compute_program_from_shader(&self,
+                                          resources: &dyn ResourceLoader,
+                                          name: &str,
+                                          shader: Self::Shader)
+                                          -> Self::Program;
+    /// Sets the workgroup size of a compute program. This must match the `local_size_*` layout
+    /// qualifiers declared in the shader source.
+    fn set_compute_program_local_size(&self,
+                                      program: &mut Self::Program,
+                                      local_size: ComputeDimensions);
+    /// Looks up the storage buffer block `name`, which the shader binds at index `binding`.
+    fn get_storage_buffer(&self, program: &Self::Program, name: &str, binding: u32)
+                          -> Self::StorageBuffer;
+    fn dispatch_compute(&self, dimensions: ComputeDimensions, compute_state: &ComputeState<Self>);
+    /// Dispatches a compute program with the workgroup counts read from `buffer` at `offset`,
+    /// laid out as three consecutive `u32` values.
+    fn dispatch_compute_indirect(&self,
+                                 buffer: &Self::Buffer,
+                                 offset: usize,
+                                 compute_state: &ComputeState<Self>);
 
     fn create_texture_from_png(&self, resources: &dyn ResourceLoader, name: &str) -> Self::Texture {
         let data = resources.slurp(&format!("textures/{}.png", name)).unwrap();
@@ -119,6 +196,12 @@ pub trait Device: Sized {
     fn create_program(&self, resources: &dyn ResourceLoader, name: &str) -> Self::Program {
         self.create_program_from_shader_names(resources, name, name, name)
     }
+
+    fn create_compute_program(&self, resources: &dyn ResourceLoader, name: &str)
+                              -> Self::Program {
+        let compute_shader = self.create_shader(resources, name, ShaderKind::Compute);
+        self.create_compute_program_from_shader(resources, name, compute_shader)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -128,6 +211,16 @@ pub enum TextureFormat {
     RGBA8,
     RGBA16F,
     RGBA32F,
+    /// S3TC DXT1: RGB with 1-bit alpha, 8 bytes per 4×4 block.
+    BC1,
+    /// S3TC DXT5: RGBA, 16 bytes per 4×4 block.
+    BC3,
+    /// BPTC: RGBA, 16 bytes per 4×4 block.
+    BC7,
+    /// ETC2 with EAC alpha, 16 bytes per 4×4 block.
+    ETC2RGBA8,
+    /// ASTC LDR with a 4×4 block footprint, 16 bytes per block.
+    ASTC4x4,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -149,6 +242,7 @@ pub enum BufferData<'a, T> {
 pub enum BufferTarget {
     Vertex,
     Index,
+    Storage,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -161,6 +255,7 @@ pub enum BufferUploadMode {
 pub enum ShaderKind {
     Vertex,
     Fragment,
+    Compute,
 }
 
 #[derive(Clone, Copy)]
@@ -194,6 +289,51 @@ pub struct RenderState<'a, D> where D: Device {
     pub options: RenderOptions,
 }
 
+#[derive(Clone)]
+pub struct ComputeState<'a, D> where D: Device {
+    pub program: &'a D::Program,
+    pub uniforms: &'a [(&'a D::Uniform, UniformData)],
+    pub textures: &'a [&'a D::Texture],
+    pub storage_buffers: &'a [(&'a D::StorageBuffer, &'a D::Buffer)],
+}
+
+/// Why a device stopped working.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeviceLostError {
+    /// The context was reset because of a fault in commands that we submitted.
+    GuiltyContextReset,
+    /// The context was reset because of a fault elsewhere in the system.
+    InnocentContextReset,
+    /// The context was reset for an unknown reason.
+    UnknownContextReset,
+    /// The GPU was unplugged, or its driver was restarted.
+    DeviceRemoved,
+}
+
+impl fmt::Display for DeviceLostError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            DeviceLostError::GuiltyContextReset => {
+                write!(formatter, "the context was reset because of a fault in our commands")
+            }
+            DeviceLostError::InnocentContextReset => {
+                write!(formatter, "the context was reset because of a fault elsewhere")
+            }
+            DeviceLostError::UnknownContextReset => write!(formatter, "the context was reset"),
+            DeviceLostError::DeviceRemoved => write!(formatter, "the device was removed"),
+        }
+    }
+}
+
+impl Error for DeviceLostError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ComputeDimensions {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
 #[derive(Clone, Debug)]
 pub struct RenderOptions {
     pub blend: Option<BlendState>,
@@ -201,6 +341,10 @@ pub struct RenderOptions {
     pub stencil: Option<StencilState>,
     pub clear_ops: ClearOps,
     pub color_mask: bool,
+    /// If present, drawing is restricted to this rect. It's in pixels relative to the upper left
+    /// of the viewport, with Y pointing down, whatever the device's own conventions. Clears
+    /// ignore it.
+    pub scissor: Option<RectI>,
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -234,6 +378,10 @@ pub enum BlendFactor {
     DestAlpha,
     OneMinusDestAlpha,
     DestColor,
+    /// The alpha of the fragment shader's second output, for the same color attachment. Only
+    /// available if `Device::supports_dual_source_blending()` returns true.
+    Src1Alpha,
+    OneMinusSrc1Alpha,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -243,6 +391,22 @@ pub enum BlendOp {
     ReverseSubtract,
     Min,
     Max,
+    /// The advanced blend equations, which blend premultiplied colors with the given mode per
+    /// "Compositing and Blending Level 1", composite the result with source-over, and ignore the
+    /// blend factors. Only available if `Device::supports_advanced_blending()` returns true.
+    Multiply,
+    Screen,
+    Overlay,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
 }
 
 #[derive(Clone, Copy, Default, Debug)]
@@ -280,6 +444,7 @@ impl Default for RenderOptions {
             stencil: None,
             clear_ops: ClearOps::default(),
             color_mask: true,
+            scissor: None,
         }
     }
 }
@@ -363,9 +528,16 @@ impl TextureFormat {
         match self {
             TextureFormat::R8 | TextureFormat::R16F => 1,
             TextureFormat::RGBA8 | TextureFormat::RGBA16F | TextureFormat::RGBA32F => 4,
+            TextureFormat::BC1 |
+            TextureFormat::BC3 |
+            TextureFormat::BC7 |
+            TextureFormat::ETC2RGBA8 |
+            TextureFormat::ASTC4x4 => 4,
         }
     }
 
+    /// The size of one pixel. Compressed formats have no per-pixel size; use `bytes_per_block()`
+    /// or `byte_size()` for those.
     #[inline]
     pub fn bytes_per_pixel(self) -> usize {
         match self {
@@ -374,8 +546,63 @@ impl TextureFormat {
             TextureFormat::RGBA8 => 4,
             TextureFormat::RGBA16F => 8,
             TextureFormat::RGBA32F => 16,
+            _ => panic!("Compressed texture formats have no per-pixel size!"),
         }
     }
+
+    #[inline]
+    pub fn is_compressed(self) -> bool {
+        match self {
+            TextureFormat::R8 |
+            TextureFormat::R16F |
+            TextureFormat::RGBA8 |
+            TextureFormat::RGBA16F |
+            TextureFormat::RGBA32F => false,
+            TextureFormat::BC1 |
+            TextureFormat::BC3 |
+            TextureFormat::BC7 |
+            TextureFormat::ETC2RGBA8 |
+            TextureFormat::ASTC4x4 => true,
+        }
+    }
+
+    /// The dimensions of a compression block, or 1×1 for uncompressed formats.
+    #[inline]
+    pub fn block_size(self) -> Vector2I {
+        if self.is_compressed() {
+            Vector2I::splat(4)
+        } else {
+            Vector2I::splat(1)
+        }
+    }
+
+    /// The size of one compression block, or of one pixel for uncompressed formats.
+    #[inline]
+    pub fn bytes_per_block(self) -> usize {
+        match self {
+            TextureFormat::BC1 => 8,
+            TextureFormat::BC3 |
+            TextureFormat::BC7 |
+            TextureFormat::ETC2RGBA8 |
+            TextureFormat::ASTC4x4 => 16,
+            _ => self.bytes_per_pixel(),
+        }
+    }
+
+    /// The number of blocks needed to cover an image of the given size.
+    #[inline]
+    pub fn block_count(self, size: Vector2I) -> Vector2I {
+        let block_size = self.block_size();
+        Vector2I::new((size.x() + block_size.x() - 1) / block_size.x(),
+                      (size.y() + block_size.y() - 1) / block_size.y())
+    }
+
+    /// The number of bytes in a tightly-packed image of the given size.
+    #[inline]
+    pub fn byte_size(self, size: Vector2I) -> usize {
+        let block_count = self.block_count(size);
+        block_count.x() as usize * block_count.y() as usize * self.bytes_per_block()
+    }
 }
 
 impl ClearOps {
@@ -404,6 +631,34 @@ bitflags! {
         const REPEAT_V    = 0x02;
         const NEAREST_MIN = 0x04;
         const NEAREST_MAG = 0x08;
+        // Bits 4-6 hold the base-2 logarithm of the maximum anisotropy. Use
+        // `set_max_anisotropy()` rather than inserting several of these at once.
+        const ANISOTROPY_2X  = 0x10;
+        const ANISOTROPY_4X  = 0x20;
+        const ANISOTROPY_8X  = 0x30;
+        const ANISOTROPY_16X = 0x40;
+    }
+}
+
+impl TextureSamplingFlags {
+    const ANISOTROPY_MASK: u8 = 0x70;
+    const ANISOTROPY_SHIFT: u8 = 4;
+
+    /// The most samples to take along the axis of anisotropy when minifying. 1 means that
+    /// anisotropic filtering is off.
+    ///
+    /// Devices clamp this to what the hardware supports.
+    #[inline]
+    pub fn max_anisotropy(self) -> u32 {
+        1 << ((self.bits() & Self::ANISOTROPY_MASK) >> Self::ANISOTROPY_SHIFT)
+    }
+
+    /// Sets the maximum anisotropy, rounding down to a power of two no greater than 16.
+    #[inline]
+    pub fn set_max_anisotropy(&mut self, max_anisotropy: u32) {
+        let log2 = (31 - max_anisotropy.max(1).leading_zeros()).min(4) as u8;
+        *self = TextureSamplingFlags::from_bits_truncate(
+            (self.bits() & !Self::ANISOTROPY_MASK) | (log2 << Self::ANISOTROPY_SHIFT));
     }
 }
 
@@ -411,6 +666,16 @@ impl<'a> TextureDataRef<'a> {
     #[doc(hidden)]
     pub fn check_and_extract_data_ptr(self, minimum_size: Vector2I, format: TextureFormat)
                                       -> *const c_void {
+        if format.is_compressed() {
+            match self {
+                TextureDataRef::U8(data) => {
+                    assert!(data.len() >= format.byte_size(minimum_size));
+                    return data.as_ptr() as *const c_void;
+                }
+                _ => panic!("Compressed texture data must be supplied as bytes!"),
+            }
+        }
+
         let channels = match (format, self) {
             (TextureFormat::R8, TextureDataRef::U8(_)) => 1,
             (TextureFormat::RGBA8, TextureDataRef::U8(_)) => 4,
@@ -437,3 +702,44 @@ impl<'a> TextureDataRef<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{DeviceLostError, TextureSamplingFlags};
+    use std::error::Error;
+
+    #[test]
+    fn test_device_lost_error_is_an_error() {
+        let errors = [
+            DeviceLostError::GuiltyContextReset,
+            DeviceLostError::InnocentContextReset,
+            DeviceLostError::UnknownContextReset,
+            DeviceLostError::DeviceRemoved,
+        ];
+        let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        for (index, message) in messages.iter().enumerate() {
+            assert!(!message.is_empty());
+            assert!(!messages[(index + 1)..].contains(message));
+        }
+
+        let boxed: Box<dyn Error> = Box::new(DeviceLostError::DeviceRemoved);
+        assert_eq!(boxed.to_string(), "the device was removed");
+    }
+
+    #[test]
+    fn test_max_anisotropy() {
+        let mut flags = TextureSamplingFlags::REPEAT_U | TextureSamplingFlags::NEAREST_MAG;
+        assert_eq!(flags.max_anisotropy(), 1);
+        assert_eq!(TextureSamplingFlags::ANISOTROPY_8X.max_anisotropy(), 8);
+
+        // Levels round down to a power of two, up to 16.
+        for &(max_anisotropy, expected) in &[(0, 1), (2, 2), (7, 4), (16, 16), (64, 16)] {
+            flags.set_max_anisotropy(max_anisotropy);
+            assert_eq!(flags.max_anisotropy(), expected);
+            assert!(flags.contains(TextureSamplingFlags::REPEAT_U |
+                                   TextureSamplingFlags::NEAREST_MAG));
+        }
+        flags.set_max_anisotropy(1);
+        assert_eq!(flags, TextureSamplingFlags::REPEAT_U | TextureSamplingFlags::NEAREST_MAG);
+    }
+}
@@ -0,0 +1,121 @@
+// pathfinder/gpu/src/memory.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Helpers for devices to account for the GPU memory they allocate.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// The GPU memory currently allocated through a device, in bytes.
+///
+/// These are estimates based on the sizes and formats of the objects; drivers add padding and
+/// bookkeeping of their own.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GPUMemoryUsage {
+    /// Textures, including those backing framebuffers.
+    pub textures: u64,
+    /// Vertex and index buffers.
+    pub buffers: u64,
+}
+
+impl GPUMemoryUsage {
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.textures + self.buffers
+    }
+}
+
+/// A running total of allocated bytes, shared between a device and the objects it creates.
+#[derive(Clone, Default)]
+pub struct MemoryCounter(Rc<Cell<u64>>);
+
+impl MemoryCounter {
+    #[inline]
+    pub fn new() -> MemoryCounter {
+        MemoryCounter::default()
+    }
+
+    #[inline]
+    pub fn get(&self) -> u64 {
+        self.0.get()
+    }
+
+    /// Starts tracking an allocation of the given size against this counter.
+    pub fn allocate(&self, size: u64) -> Allocation {
+        let allocation = Allocation { counter: self.clone(), size: Cell::new(0) };
+        allocation.resize(size);
+        allocation
+    }
+}
+
+/// Memory belonging to one GPU object. Dropping it returns the memory to its counter.
+pub struct Allocation {
+    counter: MemoryCounter,
+    size: Cell<u64>,
+}
+
+impl Allocation {
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size.get()
+    }
+
+    /// Records that the object has been reallocated with a new size.
+    pub fn resize(&self, new_size: u64) {
+        let counter = &self.counter.0;
+        counter.set(counter.get() - self.size.get() + new_size);
+        self.size.set(new_size);
+    }
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        self.resize(0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GPUMemoryUsage, MemoryCounter};
+
+    #[test]
+    fn test_allocations_update_counter() {
+        let counter = MemoryCounter::new();
+        let texture = counter.allocate(1024);
+        let buffer = counter.allocate(256);
+        assert_eq!(counter.get(), 1280);
+
+        texture.resize(4096);
+        assert_eq!(texture.size(), 4096);
+        assert_eq!(counter.get(), 4352);
+
+        buffer.resize(0);
+        assert_eq!(counter.get(), 4096);
+    }
+
+    #[test]
+    fn test_dropping_allocation_returns_memory() {
+        let counter = MemoryCounter::new();
+        let allocation = counter.allocate(100);
+        {
+            let _temporary = counter.allocate(50);
+            assert_eq!(counter.get(), 150);
+        }
+        assert_eq!(counter.get(), 100);
+        drop(allocation);
+        assert_eq!(counter.get(), 0);
+    }
+
+    #[test]
+    fn test_memory_usage_total() {
+        let usage = GPUMemoryUsage { textures: 3, buffers: 4 };
+        assert_eq!(usage.total(), 7);
+    }
+}
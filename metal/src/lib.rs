@@ -15,42 +15,53 @@
 #[macro_use]
 extern crate bitflags;
 #[macro_use]
+extern crate log;
+#[macro_use]
 extern crate objc;
 
 use block::{Block, ConcreteBlock, RcBlock};
 use byteorder::{NativeEndian, WriteBytesExt};
-use cocoa::foundation::{NSRange, NSUInteger};
+use cocoa::foundation::{NSInteger, NSRange, NSUInteger};
 use core_foundation::base::TCFType;
 use core_foundation::string::{CFString, CFStringRef};
 use foreign_types::{ForeignType, ForeignTypeRef};
 use half::f16;
 use metal::{self, Argument, ArgumentEncoder, Buffer, CommandBuffer, CommandBufferRef};
-use metal::{CommandQueue, CompileOptions, CoreAnimationDrawable, CoreAnimationDrawableRef};
+use metal::{CommandQueue, CompileOptions, ComputeCommandEncoder, ComputeCommandEncoderRef};
+use metal::{ComputePipelineState, CoreAnimationDrawable, CoreAnimationDrawableRef};
 use metal::{CoreAnimationLayer, CoreAnimationLayerRef, DepthStencilDescriptor, Function, Library};
 use metal::{MTLArgument, MTLArgumentEncoder, MTLBlendFactor, MTLBlendOperation, MTLClearColor};
-use metal::{MTLColorWriteMask, MTLCompareFunction, MTLDataType, MTLDevice, MTLFunctionType};
-use metal::{MTLIndexType, MTLLoadAction, MTLOrigin, MTLPixelFormat, MTLPrimitiveType, MTLRegion};
-use metal::{MTLRenderPipelineReflection, MTLRenderPipelineState, MTLResourceOptions};
+use metal::MTLCommandBufferStatus;
+use metal::{MTLColorWriteMask, MTLCompareFunction, MTLComputePipelineState, MTLDataType};
+use metal::{MTLDevice, MTLIndexType, MTLLoadAction, MTLOrigin, MTLPixelFormat, MTLPrimitiveType};
+use metal::{MTLRegion, MTLRenderPipelineReflection, MTLRenderPipelineState, MTLResourceOptions};
+use metal::MTLScissorRect;
 use metal::{MTLResourceUsage, MTLSamplerAddressMode, MTLSamplerMinMagFilter, MTLSize};
-use metal::{MTLStencilOperation, MTLStorageMode, MTLStoreAction, MTLTextureType, MTLTextureUsage};
-use metal::{MTLVertexFormat, MTLVertexStepFunction, MTLViewport, RenderCommandEncoder};
-use metal::{RenderCommandEncoderRef, RenderPassDescriptor, RenderPassDescriptorRef};
-use metal::{RenderPipelineColorAttachmentDescriptorRef, RenderPipelineDescriptor};
-use metal::{RenderPipelineReflection, RenderPipelineReflectionRef, RenderPipelineState};
+use metal::{MTLStencilOperation, MTLStorageMode, MTLStoreAction, MTLTexture, MTLTextureType};
+use metal::{MTLTextureUsage, MTLVertexFormat, MTLVertexStepFunction, MTLViewport};
+use metal::{RenderCommandEncoder, RenderCommandEncoderRef, RenderPassDescriptor};
+use metal::{RenderPassDescriptorRef, RenderPipelineColorAttachmentDescriptorRef};
+use metal::{RenderPipelineDescriptor, RenderPipelineReflection, RenderPipelineReflectionRef};
+use metal::{RenderPipelineState, ResourceRef};
 use metal::{SamplerDescriptor, SamplerState, StencilDescriptor, StructMemberRef, StructType};
 use metal::{StructTypeRef, TextureDescriptor, Texture, TextureRef, VertexAttribute};
 use metal::{VertexAttributeRef, VertexDescriptor, VertexDescriptorRef};
-use objc::runtime::{Class, Object};
+use objc::runtime::{Class, Object, BOOL, NO};
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::vector::Vector2I;
-use pathfinder_gpu::{BlendFactor, BlendOp, BufferData, BufferTarget, BufferUploadMode, DepthFunc};
-use pathfinder_gpu::{Device, Primitive, RenderState, RenderTarget, ShaderKind, StencilFunc};
+use pathfinder_gpu::{BlendFactor, BlendOp, BlendState, BufferData, BufferTarget};
+use pathfinder_gpu::{BufferUploadMode, ComputeDimensions, ComputeState, DepthFunc, Device};
+use pathfinder_gpu::{DeviceLostError, Primitive, RenderState, RenderTarget};
+use pathfinder_gpu::{ShaderKind, StencilFunc};
 use pathfinder_gpu::{TextureData, TextureDataRef, TextureFormat, TextureSamplingFlags};
 use pathfinder_gpu::{UniformData, VertexAttrClass, VertexAttrDescriptor, VertexAttrType};
+use pathfinder_gpu::memory::{Allocation, GPUMemoryUsage, MemoryCounter};
 use pathfinder_resources::ResourceLoader;
 use pathfinder_simd::default::{F32x2, F32x4};
 use std::cell::{Cell, RefCell};
 use std::mem;
+use std::ops::Range;
+use std::path::Path;
 use std::ptr;
 use std::rc::Rc;
 use std::slice;
@@ -59,6 +70,12 @@ use std::time::{Duration, Instant};
 
 const FIRST_VERTEX_BUFFER_INDEX: u64 = 1;
 
+// `MTLCommandBufferError` codes that mean the device is gone.
+const MTL_COMMAND_BUFFER_ERROR_TIMEOUT: NSInteger = 2;
+const MTL_COMMAND_BUFFER_ERROR_PAGE_FAULT: NSInteger = 3;
+const MTL_COMMAND_BUFFER_ERROR_ACCESS_REVOKED: NSInteger = 4;
+const MTL_COMMAND_BUFFER_ERROR_DEVICE_REMOVED: NSInteger = 11;
+
 pub struct MetalDevice {
     device: metal::Device,
     layer: CoreAnimationLayer,
@@ -66,20 +83,58 @@ pub struct MetalDevice {
     main_depth_stencil_texture: Texture,
     command_queue: CommandQueue,
     command_buffers: RefCell<Vec<CommandBuffer>>,
+    // The most recently committed command buffer, whose status tells us if the device was lost.
+    last_command_buffer: RefCell<Option<CommandBuffer>>,
     samplers: Vec<SamplerState>,
     shared_event: SharedEvent,
     shared_event_listener: SharedEventListener,
     next_timer_query_event_value: Cell<u64>,
+    // Fences get their own event, because timer query values aren't signaled in order.
+    fence_event: SharedEvent,
+    next_fence_value: Cell<u64>,
+    pipeline_cache: RefCell<Vec<MetalPipelineCacheEntry>>,
+    binary_archive: Option<MetalBinaryArchive>,
+    texture_memory: MemoryCounter,
+    buffer_memory: MemoryCounter,
+}
+
+pub enum MetalProgram {
+    Raster(MetalRasterProgram),
+    Compute(MetalComputeProgram),
 }
 
-pub struct MetalProgram {
+pub struct MetalRasterProgram {
     vertex: MetalShader,
     fragment: MetalShader,
 }
 
+pub struct MetalComputeProgram {
+    shader: MetalShader,
+    local_size: MTLSize,
+    // Created, along with the shader's uniform reflection, on first dispatch.
+    pipeline_state: RefCell<Option<ComputePipelineState>>,
+}
+
+impl MetalProgram {
+    fn raster(&self) -> &MetalRasterProgram {
+        match *self {
+            MetalProgram::Raster(ref program) => program,
+            MetalProgram::Compute(_) => panic!("Expected a raster program!"),
+        }
+    }
+
+    fn compute(&self) -> &MetalComputeProgram {
+        match *self {
+            MetalProgram::Compute(ref program) => program,
+            MetalProgram::Raster(_) => panic!("Expected a compute program!"),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct MetalBuffer {
     buffer: Rc<RefCell<Option<Buffer>>>,
+    allocation: Rc<Allocation>,
 }
 
 impl MetalDevice {
@@ -90,7 +145,8 @@ impl MetalDevice {
         let drawable = layer.next_drawable().unwrap().retain();
         let command_queue = device.new_command_queue();
 
-        let samplers = (0..16).map(|sampling_flags_value| {
+        // One sampler for every combination of sampling flags, indexed by their bits.
+        let samplers = (0..0x50).map(|sampling_flags_value| {
             let sampling_flags = TextureSamplingFlags::from_bits(sampling_flags_value).unwrap();
             let sampler_descriptor = SamplerDescriptor::new();
             sampler_descriptor.set_support_argument_buffers(true);
@@ -119,6 +175,7 @@ impl MetalDevice {
                 } else {
                     MTLSamplerAddressMode::ClampToEdge
                 });
+            sampler_descriptor.set_max_anisotropy(sampling_flags.max_anisotropy() as u64);
             device.new_sampler(&sampler_descriptor)
         }).collect();
 
@@ -128,6 +185,7 @@ impl MetalDevice {
         let main_depth_stencil_texture = device.create_depth_stencil_texture(framebuffer_size);
 
         let shared_event = device.new_shared_event();
+        let fence_event = device.new_shared_event();
 
         MetalDevice {
             device,
@@ -136,13 +194,74 @@ impl MetalDevice {
             main_depth_stencil_texture,
             command_queue,
             command_buffers: RefCell::new(vec![]),
+            last_command_buffer: RefCell::new(None),
             samplers,
             shared_event,
             shared_event_listener: SharedEventListener::new(),
             next_timer_query_event_value: Cell::new(1),
+            fence_event,
+            next_fence_value: Cell::new(1),
+            pipeline_cache: RefCell::new(vec![]),
+            binary_archive: None,
+            texture_memory: MemoryCounter::new(),
+            buffer_memory: MemoryCounter::new(),
         }
     }
 
+    /// Persists compiled render pipelines to a Metal binary archive at the given path, so that
+    /// later launches can skip compiling them.
+    ///
+    /// The archive is written when the device is dropped, or by `save_pipeline_cache()`. Binary
+    /// archives need macOS 11 or iOS 14; on earlier systems this does nothing, and pipelines are
+    /// only cached in memory.
+    pub fn set_pipeline_cache_path(&mut self, path: Option<&Path>) {
+        self.binary_archive = path.and_then(|path| MetalBinaryArchive::new(&self.device, path));
+    }
+
+    /// Writes any newly compiled pipelines to the binary archive, if there is one.
+    pub fn save_pipeline_cache(&self) {
+        if let Some(ref binary_archive) = self.binary_archive {
+            binary_archive.save();
+        }
+    }
+
+    /// Wraps a texture created outside Pathfinder, so that it can be supplied to the renderer as
+    /// an external texture. It isn't counted in `memory_usage()`.
+    pub fn wrap_texture(&self, texture: Texture) -> MetalTexture {
+        MetalTexture {
+            texture,
+            sampling_flags: Cell::new(TextureSamplingFlags::empty()),
+            dirty: Cell::new(false),
+            allocation: self.texture_memory.allocate(0),
+        }
+    }
+
+    /// Creates a texture that samples directly from a plane of an `IOSurface`, such as a video
+    /// frame from AVFoundation or a camera image, without copying it.
+    ///
+    /// `pixel_format` must be compatible with the surface's format; `BGRA8Unorm` suits most
+    /// surfaces. The texture retains the surface.
+    pub unsafe fn create_texture_from_io_surface(&self,
+                                                 io_surface: *mut Object,
+                                                 plane: NSUInteger,
+                                                 pixel_format: MTLPixelFormat,
+                                                 size: Vector2I)
+                                                 -> MetalTexture {
+        let descriptor = TextureDescriptor::new();
+        descriptor.set_texture_type(MTLTextureType::D2);
+        descriptor.set_pixel_format(pixel_format);
+        descriptor.set_width(size.x() as u64);
+        descriptor.set_height(size.y() as u64);
+        descriptor.set_storage_mode(MTLStorageMode::Shared);
+        descriptor.set_usage(MTLTextureUsage::ShaderRead);
+        let texture: *mut MTLTexture = msg_send![self.device.as_ptr(),
+                                                 newTextureWithDescriptor:descriptor.as_ptr()
+                                                                iosurface:io_surface
+                                                                    plane:plane];
+        assert!(!texture.is_null(), "Failed to create a texture from the IOSurface!");
+        self.wrap_texture(Texture::from_ptr(texture))
+    }
+
     pub fn present_drawable(&mut self) {
         self.begin_commands();
         self.command_buffers.borrow_mut().last().unwrap().present_drawable(&self.drawable);
@@ -170,6 +289,8 @@ pub struct MetalTexture {
     texture: Texture,
     sampling_flags: Cell<TextureSamplingFlags>,
     dirty: Cell<bool>,
+    #[allow(dead_code)]
+    allocation: Allocation,
 }
 
 #[derive(Clone)]
@@ -189,6 +310,35 @@ enum MetalTextureDataReceiverState {
 }
 
 #[derive(Clone)]
+/// A value of an `MTLSharedEvent`. The fence signals when the event reaches the value.
+///
+/// An application's own command buffers can wait on or signal the event directly, including from
+/// other devices or processes.
+pub struct MetalFence {
+    event: SharedEvent,
+    value: u64,
+}
+
+impl MetalFence {
+    /// Creates a fence from a shared event that something outside Pathfinder signals. The event
+    /// is retained.
+    pub unsafe fn from_shared_event(event: *mut Object, value: u64) -> MetalFence {
+        let () = msg_send![event, retain];
+        MetalFence { event: SharedEvent(event), value }
+    }
+
+    /// The `MTLSharedEvent`.
+    #[inline]
+    pub fn shared_event(&self) -> *mut Object {
+        self.event.0
+    }
+
+    #[inline]
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+}
+
 pub struct MetalTimerQuery(Arc<MetalTimerQueryInfo>);
 
 struct MetalTimerQueryInfo {
@@ -208,10 +358,17 @@ pub struct MetalUniform {
     name: String,
 }
 
+#[derive(Clone)]
+pub struct MetalStorageBuffer {
+    indices: RefCell<Option<MetalUniformIndices>>,
+    name: String,
+}
+
 #[derive(Clone, Copy)]
 pub struct MetalUniformIndices {
     vertex: Option<MetalUniformIndex>,
     fragment: Option<MetalUniformIndex>,
+    compute: Option<MetalUniformIndex>,
 }
 
 #[derive(Clone, Copy)]
@@ -228,9 +385,11 @@ pub struct MetalVertexArray {
 
 impl Device for MetalDevice {
     type Buffer = MetalBuffer;
+    type Fence = MetalFence;
     type Framebuffer = MetalFramebuffer;
     type Program = MetalProgram;
     type Shader = MetalShader;
+    type StorageBuffer = MetalStorageBuffer;
     type Texture = MetalTexture;
     type TextureDataReceiver = MetalTextureDataReceiver;
     type TimerQuery = MetalTimerQuery;
@@ -248,6 +407,11 @@ impl Device for MetalDevice {
             TextureFormat::RGBA8 => descriptor.set_pixel_format(MTLPixelFormat::RGBA8Unorm),
             TextureFormat::RGBA16F => descriptor.set_pixel_format(MTLPixelFormat::RGBA16Float),
             TextureFormat::RGBA32F => descriptor.set_pixel_format(MTLPixelFormat::RGBA32Float),
+            TextureFormat::BC1 => descriptor.set_pixel_format(MTLPixelFormat::BC1_RGBA),
+            TextureFormat::BC3 => descriptor.set_pixel_format(MTLPixelFormat::BC3_RGBA),
+            TextureFormat::BC7 => descriptor.set_pixel_format(MTLPixelFormat::BC7_RGBAUnorm),
+            TextureFormat::ETC2RGBA8 => descriptor.set_pixel_format(MTLPixelFormat::EAC_RGBA8),
+            TextureFormat::ASTC4x4 => descriptor.set_pixel_format(MTLPixelFormat::ASTC_4x4_LDR),
         }
         descriptor.set_width(size.x() as u64);
         descriptor.set_height(size.y() as u64);
@@ -257,6 +421,7 @@ impl Device for MetalDevice {
             texture: self.device.new_texture(&descriptor),
             sampling_flags: Cell::new(TextureSamplingFlags::empty()),
             dirty: Cell::new(false),
+            allocation: self.texture_memory.allocate(format.byte_size(size) as u64),
         }
     }
 
@@ -296,6 +461,9 @@ impl Device for MetalDevice {
             BufferTarget::Index => {
                 *vertex_array.index_buffer.borrow_mut() = Some((*buffer).clone())
             }
+            BufferTarget::Storage => {
+                // Storage buffers are bound per dispatch, through `ComputeState`.
+            }
         }
     }
 
@@ -305,12 +473,44 @@ impl Device for MetalDevice {
                                    vertex_shader: MetalShader,
                                    fragment_shader: MetalShader)
                                    -> MetalProgram {
-        MetalProgram { vertex: vertex_shader, fragment: fragment_shader }
+        MetalProgram::Raster(MetalRasterProgram {
+            vertex: vertex_shader,
+            fragment: fragment_shader,
+        })
+    }
+
+    fn create_compute_program_from_shader(&self,
+                                          _: &dyn ResourceLoader,
+                                          _: &str,
+                                          shader: MetalShader)
+                                          -> MetalProgram {
+        MetalProgram::Compute(MetalComputeProgram {
+            shader,
+            local_size: MTLSize { width: 1, height: 1, depth: 1 },
+            pipeline_state: RefCell::new(None),
+        })
+    }
+
+    fn set_compute_program_local_size(&self,
+                                      program: &mut MetalProgram,
+                                      local_size: ComputeDimensions) {
+        match *program {
+            MetalProgram::Compute(ref mut program) => {
+                program.local_size = local_size.to_metal_size();
+            }
+            MetalProgram::Raster(_) => panic!("Raster programs have no local size!"),
+        }
+    }
+
+    // Storage buffers are passed in the shader's argument buffer alongside the uniforms, so the
+    // binding index is unused; they're looked up by the block's instance name instead.
+    fn get_storage_buffer(&self, _: &MetalProgram, name: &str, _: u32) -> MetalStorageBuffer {
+        MetalStorageBuffer { indices: RefCell::new(None), name: name.to_owned() }
     }
 
     fn get_vertex_attr(&self, program: &MetalProgram, name: &str) -> Option<VertexAttribute> {
         // TODO(pcwalton): Cache the function?
-        let attributes = program.vertex.function.real_vertex_attributes();
+        let attributes = program.raster().vertex.function.real_vertex_attributes();
         for attribute_index in 0..attributes.len() {
             let attribute = attributes.object_at(attribute_index);
             let this_name = attribute.name().as_bytes();
@@ -434,7 +634,10 @@ impl Device for MetalDevice {
     }
 
     fn create_buffer(&self) -> MetalBuffer {
-        MetalBuffer { buffer: Rc::new(RefCell::new(None)) }
+        MetalBuffer {
+            buffer: Rc::new(RefCell::new(None)),
+            allocation: Rc::new(self.buffer_memory.allocate(0)),
+        }
     }
 
     fn allocate_buffer<T>(&self,
@@ -453,6 +656,7 @@ impl Device for MetalDevice {
                 let size = (size * mem::size_of::<T>()) as u64;
                 let new_buffer = self.device.new_buffer(size, options);
                 *buffer.buffer.borrow_mut() = Some(new_buffer);
+                buffer.allocation.resize(size);
             }
             BufferData::Memory(slice) => {
                 let size = (slice.len() * mem::size_of::<T>()) as u64;
@@ -460,6 +664,7 @@ impl Device for MetalDevice {
                                                                   size,
                                                                   options);
                 *buffer.buffer.borrow_mut() = Some(new_buffer);
+                buffer.allocation.resize(size);
             }
         }
     }
@@ -481,6 +686,13 @@ impl Device for MetalDevice {
             MTLPixelFormat::RGBA8Unorm => TextureFormat::RGBA8,
             MTLPixelFormat::RGBA16Float => TextureFormat::RGBA16F,
             MTLPixelFormat::RGBA32Float => TextureFormat::RGBA32F,
+            // External textures are often BGRA. Shaders see them as RGBA.
+            MTLPixelFormat::BGRA8Unorm => TextureFormat::RGBA8,
+            MTLPixelFormat::BC1_RGBA => TextureFormat::BC1,
+            MTLPixelFormat::BC3_RGBA => TextureFormat::BC3,
+            MTLPixelFormat::BC7_RGBAUnorm => TextureFormat::BC7,
+            MTLPixelFormat::EAC_RGBA8 => TextureFormat::ETC2RGBA8,
+            MTLPixelFormat::ASTC_4x4_LDR => TextureFormat::ASTC4x4,
             _ => panic!("Unexpected Metal texture format!"),
         }
     }
@@ -510,7 +722,8 @@ impl Device for MetalDevice {
             depth: 1,
         };
         let region = MTLRegion { origin, size };
-        let stride = format.bytes_per_pixel() as u64 * size.width;
+        // For compressed formats, this is the size of one row of blocks.
+        let stride = format.byte_size(Vector2I::new(rect.size().x(), 1)) as u64;
         texture.texture.replace_region(region, 0, stride, data_ptr);
 
         texture.dirty.set(true);
@@ -543,6 +756,7 @@ impl Device for MetalDevice {
     fn end_commands(&self) {
         let command_buffer = self.command_buffers.borrow_mut().pop().unwrap();
         command_buffer.commit();
+        *self.last_command_buffer.borrow_mut() = Some(command_buffer);
     }
 
     fn draw_arrays(&self, index_count: u32, render_state: &RenderState<MetalDevice>) {
@@ -651,6 +865,134 @@ impl Device for MetalDevice {
         }
     }
 
+    fn memory_usage(&self) -> GPUMemoryUsage {
+        GPUMemoryUsage {
+            textures: self.texture_memory.get(),
+            buffers: self.buffer_memory.get(),
+        }
+    }
+
+    // Macs support the BC formats, and iOS GPUs support ETC2 and ASTC.
+    //
+    // TODO: Apple silicon Macs support all of them. Query the GPU family once we can.
+    fn supports_texture_format(&self, format: TextureFormat) -> bool {
+        match format {
+            TextureFormat::BC1 | TextureFormat::BC3 | TextureFormat::BC7 => {
+                cfg!(target_os = "macos")
+            }
+            TextureFormat::ETC2RGBA8 | TextureFormat::ASTC4x4 => cfg!(target_os = "ios"),
+            _ => true,
+        }
+    }
+
+    fn insert_fence(&self) -> MetalFence {
+        let value = self.next_fence_value.get();
+        self.next_fence_value.set(value + 1);
+        self.with_command_buffer(|command_buffer| {
+            command_buffer.encode_signal_event(&self.fence_event, value)
+        });
+        MetalFence { event: self.fence_event.retain(), value }
+    }
+
+    fn is_fence_signaled(&self, fence: &MetalFence) -> bool {
+        fence.event.signaled_value() >= fence.value
+    }
+
+    fn wait_for_fence(&self, fence: &MetalFence) {
+        let signal = Arc::new((Mutex::new(false), Condvar::new()));
+        let captured_signal = signal.clone();
+        let block = ConcreteBlock::new(move |_: *mut Object, _: u64| {
+            *captured_signal.0.lock().unwrap() = true;
+            captured_signal.1.notify_all();
+        });
+        fence.event.notify_listener_at_value(&self.shared_event_listener,
+                                             fence.value,
+                                             block.copy());
+
+        let mut signaled = signal.0.lock().unwrap();
+        while !*signaled {
+            signaled = signal.1.wait(signaled).unwrap();
+        }
+    }
+
+    fn gpu_wait_for_fence(&self, fence: &MetalFence) {
+        self.with_command_buffer(|command_buffer| {
+            command_buffer.encode_wait_for_event(&fence.event, fence.value)
+        });
+    }
+
+    fn check_device(&self) -> Result<(), DeviceLostError> {
+        let last_command_buffer = self.last_command_buffer.borrow();
+        let command_buffer = match *last_command_buffer {
+            Some(ref command_buffer) => command_buffer,
+            None => return Ok(()),
+        };
+        if command_buffer.status() != MTLCommandBufferStatus::Error {
+            return Ok(());
+        }
+        unsafe {
+            let error: *mut Object = msg_send![*command_buffer, error];
+            let code: NSInteger = if error.is_null() { 0 } else { msg_send![error, code] };
+            match code {
+                MTL_COMMAND_BUFFER_ERROR_TIMEOUT |
+                MTL_COMMAND_BUFFER_ERROR_PAGE_FAULT |
+                MTL_COMMAND_BUFFER_ERROR_ACCESS_REVOKED => {
+                    Err(DeviceLostError::GuiltyContextReset)
+                }
+                MTL_COMMAND_BUFFER_ERROR_DEVICE_REMOVED => Err(DeviceLostError::DeviceRemoved),
+                // Other errors, such as running out of memory, leave the device usable.
+                _ => Ok(()),
+            }
+        }
+    }
+
+    // Every encoder that we create starts from a clean state, and we never touch encoders that
+    // the embedder created, so there's nothing to save.
+    #[inline]
+    fn save_state(&self) {}
+
+    #[inline]
+    fn restore_state(&self) {}
+
+    #[inline]
+    fn supports_dual_source_blending(&self) -> bool {
+        true
+    }
+
+    // Metal has no advanced blend equations. Apple GPUs can read the color attachment in the
+    // fragment shader instead, but the blend mode shaders sample a copy of the destination.
+    #[inline]
+    fn supports_advanced_blending(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn supports_compute(&self) -> bool {
+        true
+    }
+
+    fn dispatch_compute(&self,
+                        dimensions: ComputeDimensions,
+                        compute_state: &ComputeState<MetalDevice>) {
+        let encoder = self.prepare_to_compute(compute_state);
+        let local_size = compute_state.program.compute().local_size;
+        encoder.dispatch_thread_groups(dimensions.to_metal_size(), local_size);
+        encoder.end_encoding();
+    }
+
+    fn dispatch_compute_indirect(&self,
+                                 buffer: &MetalBuffer,
+                                 offset: usize,
+                                 compute_state: &ComputeState<MetalDevice>) {
+        let encoder = self.prepare_to_compute(compute_state);
+        let local_size = compute_state.program.compute().local_size;
+        let buffer = buffer.buffer.borrow();
+        let buffer = buffer.as_ref().expect("Indirect dispatch buffer not allocated!");
+        encoder.use_resource(buffer, MTLResourceUsage::Read);
+        encoder.dispatch_thread_groups_indirect(buffer, offset as u64, local_size);
+        encoder.end_encoding();
+    }
+
     fn try_recv_texture_data(&self, receiver: &MetalTextureDataReceiver) -> Option<TextureData> {
         try_recv_texture_data_with_guard(&mut receiver.0.mutex.lock().unwrap())
     }
@@ -676,6 +1018,7 @@ impl Device for MetalDevice {
         let suffix = match kind {
             ShaderKind::Vertex => 'v',
             ShaderKind::Fragment => 'f',
+            ShaderKind::Compute => 'c',
         };
         let path = format!("shaders/metal/{}.{}s.metal", name, suffix);
         self.create_shader_from_source(name, &resources.slurp(&path).unwrap(), kind)
@@ -683,6 +1026,7 @@ impl Device for MetalDevice {
 }
 
 impl MetalDevice {
+    // `name` is the argument buffer member name, including its `u` or `i` prefix.
     fn get_uniform_index(&self, shader: &MetalShader, name: &str) -> Option<MetalUniformIndex> {
         let uniforms = shader.uniforms.borrow();
         let struct_type = match *uniforms {
@@ -690,18 +1034,37 @@ impl MetalDevice {
             ShaderUniforms::NoUniforms => return None,
             ShaderUniforms::Uniforms { ref struct_type, .. } => struct_type,
         };
-        let main_member = match struct_type.member_from_name(&format!("u{}", name)) {
+        let main_member = match struct_type.member_from_name(name) {
             None => return None,
             Some(main_member) => main_member,
         };
         let main_index = main_member.argument_index();
-        let sampler_index = match struct_type.member_from_name(&format!("u{}Smplr", name)) {
+        let sampler_index = match struct_type.member_from_name(&format!("{}Smplr", name)) {
             None => None,
             Some(sampler_member) => Some(sampler_member.argument_index()),
         };
         Some(MetalUniformIndex { main: main_index, sampler: sampler_index })
     }
 
+    fn get_uniform_indices(&self, program: &MetalProgram, name: &str) -> MetalUniformIndices {
+        match *program {
+            MetalProgram::Raster(ref program) => {
+                MetalUniformIndices {
+                    vertex: self.get_uniform_index(&program.vertex, name),
+                    fragment: self.get_uniform_index(&program.fragment, name),
+                    compute: None,
+                }
+            }
+            MetalProgram::Compute(ref program) => {
+                MetalUniformIndices {
+                    vertex: None,
+                    fragment: None,
+                    compute: self.get_uniform_index(&program.shader, name),
+                }
+            }
+        }
+    }
+
     fn populate_uniform_indices_if_necessary(&self,
                                              uniform: &MetalUniform,
                                              program: &MetalProgram) {
@@ -711,10 +1074,7 @@ impl MetalDevice {
             return;
         }
 
-        *indices = Some(MetalUniformIndices {
-            vertex: self.get_uniform_index(&program.vertex, &uniform.name),
-            fragment: self.get_uniform_index(&program.fragment, &uniform.name),
-        });
+        *indices = Some(self.get_uniform_indices(program, &format!("u{}", uniform.name)));
     }
 
     fn render_target_color_texture(&self, render_target: &RenderTarget<MetalDevice>)
@@ -740,13 +1100,12 @@ impl MetalDevice {
         }
     }
 
-    fn prepare_to_draw(&self, render_state: &RenderState<MetalDevice>) -> RenderCommandEncoder {
-        let command_buffers = self.command_buffers.borrow();
-        let command_buffer = command_buffers.last().unwrap();
-
-        // FIXME(pcwalton): Is this necessary?
+    // FIXME(pcwalton): Is this necessary?
+    fn synchronize_dirty_textures(&self,
+                                  command_buffer: &CommandBufferRef,
+                                  textures: &[&MetalTexture]) {
         let mut blit_command_encoder = None;
-        for texture in render_state.textures {
+        for texture in textures {
             if !texture.dirty.get() {
                 continue;
             }
@@ -761,23 +1120,100 @@ impl MetalDevice {
         if let Some(blit_command_encoder) = blit_command_encoder {
             blit_command_encoder.end_encoding();
         }
+    }
+
+    fn prepare_to_draw(&self, render_state: &RenderState<MetalDevice>) -> RenderCommandEncoder {
+        let command_buffers = self.command_buffers.borrow();
+        let command_buffer = command_buffers.last().unwrap();
+        self.synchronize_dirty_textures(command_buffer, render_state.textures);
 
         let render_pass_descriptor = self.create_render_pass_descriptor(render_state);
 
         let encoder = command_buffer.new_render_command_encoder(&render_pass_descriptor).retain();
         self.set_viewport(&encoder, &render_state.viewport);
+        if let Some(scissor) = render_state.options.scissor {
+            self.set_scissor(&encoder, &render_state.viewport, scissor);
+        }
+
+        let render_pipeline_state = self.get_or_create_render_pipeline_state(render_state);
+
+        for (vertex_buffer_index, vertex_buffer) in render_state.vertex_array
+                                                                .vertex_buffers
+                                                                .borrow()
+                                                                .iter()
+                                                                .enumerate() {
+            let real_index = vertex_buffer_index as u64 + FIRST_VERTEX_BUFFER_INDEX;
+            let buffer = vertex_buffer.buffer.borrow();
+            let buffer = buffer.as_ref()
+                               .map(|buffer| buffer.as_ref())
+                               .expect("Where's the vertex buffer?");
+            encoder.set_vertex_buffer(real_index, Some(buffer), 0);
+            encoder.use_resource(buffer, MTLResourceUsage::Read);
+        }
+
+        self.set_raster_uniforms(&encoder, render_state);
+        encoder.set_render_pipeline_state(&render_pipeline_state);
+        self.set_depth_stencil_state(&encoder, render_state);
+        encoder
+    }
+
+    fn prepare_to_compute(&self, compute_state: &ComputeState<MetalDevice>)
+                          -> ComputeCommandEncoder {
+        let program = compute_state.program.compute();
+
+        let command_buffers = self.command_buffers.borrow();
+        let command_buffer = command_buffers.last().unwrap();
+        self.synchronize_dirty_textures(command_buffer, compute_state.textures);
+
+        let encoder = command_buffer.new_compute_command_encoder().retain();
+        let compute_pipeline_state = self.get_or_create_compute_pipeline_state(program);
+        self.set_compute_uniforms(&encoder, compute_state);
+        encoder.set_compute_pipeline_state(&compute_pipeline_state);
+        encoder
+    }
+
+    fn get_or_create_compute_pipeline_state(&self, program: &MetalComputeProgram)
+                                            -> ComputePipelineState {
+        if let Some(ref compute_pipeline_state) = *program.pipeline_state.borrow() {
+            return compute_pipeline_state.clone();
+        }
+
+        let reflection_options = MTLPipelineOption::ArgumentInfo |
+            MTLPipelineOption::BufferTypeInfo;
+        let (compute_pipeline_state, reflection) =
+            self.device.real_new_compute_pipeline_state_with_reflection(&program.shader.function,
+                                                                        reflection_options);
+        self.populate_shader_uniforms_if_necessary(&program.shader, &reflection.arguments());
+
+        *program.pipeline_state.borrow_mut() = Some(compute_pipeline_state.clone());
+        compute_pipeline_state
+    }
+
+    // Creating a pipeline state compiles the shaders for the target GPU, which is far too slow to
+    // do for every draw call, so keep every pipeline we've created.
+    fn get_or_create_render_pipeline_state(&self, render_state: &RenderState<MetalDevice>)
+                                           -> RenderPipelineState {
+        let program = render_state.program.raster();
+        let key = MetalPipelineCacheKey {
+            vertex_function: program.vertex.function.as_ptr() as usize,
+            fragment_function: program.fragment.function.as_ptr() as usize,
+            vertex_descriptor: render_state.vertex_array.descriptor.as_ptr() as usize,
+            pixel_format: self.render_target_color_texture(&render_state.target).pixel_format() as
+                NSUInteger,
+            has_depth: self.render_target_has_depth(render_state.target),
+            blend: render_state.options.blend,
+            color_mask: render_state.options.color_mask,
+        };
+        if let Some(entry) = self.pipeline_cache.borrow().iter().find(|entry| entry.key == key) {
+            return entry.render_pipeline_state.clone();
+        }
 
         let render_pipeline_descriptor = RenderPipelineDescriptor::new();
-        render_pipeline_descriptor.set_vertex_function(Some(&render_state.program
-                                                                         .vertex
-                                                                         .function));
-        render_pipeline_descriptor.set_fragment_function(Some(&render_state.program
-                                                                           .fragment
-                                                                           .function));
+        render_pipeline_descriptor.set_vertex_function(Some(&program.vertex.function));
+        render_pipeline_descriptor.set_fragment_function(Some(&program.fragment.function));
         render_pipeline_descriptor.set_vertex_descriptor(Some(&render_state.vertex_array
                                                                            .descriptor));
 
-        // Create render pipeline state.
         let pipeline_color_attachment =
             render_pipeline_descriptor.color_attachments()
                                       .object_at(0)
@@ -785,56 +1221,51 @@ impl MetalDevice {
         self.prepare_pipeline_color_attachment_for_render(pipeline_color_attachment,
                                                           render_state);
 
-        if self.render_target_has_depth(render_state.target) {
+        if key.has_depth {
             let depth_stencil_format = MTLPixelFormat::Depth32Float_Stencil8;
             render_pipeline_descriptor.set_depth_attachment_pixel_format(depth_stencil_format);
             render_pipeline_descriptor.set_stencil_attachment_pixel_format(depth_stencil_format);
         }
 
+        if let Some(ref binary_archive) = self.binary_archive {
+            binary_archive.attach_to_descriptor(&render_pipeline_descriptor);
+        }
+
         let reflection_options = MTLPipelineOption::ArgumentInfo |
             MTLPipelineOption::BufferTypeInfo;
         let (render_pipeline_state, reflection) =
             self.device.real_new_render_pipeline_state_with_reflection(&render_pipeline_descriptor,
                                                                        reflection_options);
 
-        self.populate_shader_uniforms_if_necessary(&render_state.program.vertex, &reflection);
-        self.populate_shader_uniforms_if_necessary(&render_state.program.fragment, &reflection);
-
-        for (vertex_buffer_index, vertex_buffer) in render_state.vertex_array
-                                                                .vertex_buffers
-                                                                .borrow()
-                                                                .iter()
-                                                                .enumerate() {
-            let real_index = vertex_buffer_index as u64 + FIRST_VERTEX_BUFFER_INDEX;
-            let buffer = vertex_buffer.buffer.borrow();
-            let buffer = buffer.as_ref()
-                               .map(|buffer| buffer.as_ref())
-                               .expect("Where's the vertex buffer?");
-            encoder.set_vertex_buffer(real_index, Some(buffer), 0);
-            encoder.use_resource(buffer, MTLResourceUsage::Read);
+        if let Some(ref binary_archive) = self.binary_archive {
+            binary_archive.add_pipeline(&render_pipeline_descriptor);
         }
 
-        self.set_uniforms(&encoder, render_state);
-        encoder.set_render_pipeline_state(&render_pipeline_state);
-        self.set_depth_stencil_state(&encoder, render_state);
-        encoder
+        self.populate_shader_uniforms_if_necessary(&program.vertex,
+                                                   &reflection.real_vertex_arguments());
+        self.populate_shader_uniforms_if_necessary(&program.fragment,
+                                                   &reflection.real_fragment_arguments());
+
+        // Hold on to the objects the key points to, so that their addresses can't be reused.
+        self.pipeline_cache.borrow_mut().push(MetalPipelineCacheEntry {
+            key,
+            _vertex_function: program.vertex.function.clone(),
+            _fragment_function: program.fragment.function.clone(),
+            _vertex_descriptor: render_state.vertex_array.descriptor.clone(),
+            render_pipeline_state: render_pipeline_state.clone(),
+        });
+        render_pipeline_state
     }
 
     fn populate_shader_uniforms_if_necessary(&self,
                                              shader: &MetalShader,
-                                             reflection: &RenderPipelineReflectionRef) {
+                                             arguments: &ArgumentArray) {
         let mut uniforms = shader.uniforms.borrow_mut();
         match *uniforms {
             ShaderUniforms::Unknown => {}
             ShaderUniforms::NoUniforms | ShaderUniforms::Uniforms { .. } => return,
         }
 
-        let arguments = match shader.function.function_type() {
-            MTLFunctionType::Vertex => reflection.real_vertex_arguments(),
-            MTLFunctionType::Fragment => reflection.real_fragment_arguments(),
-            _ => panic!("Unexpected shader function type!"),
-        };
-
         let mut has_descriptor_set = false;
         for argument_index in 0..arguments.len() {
             let argument = arguments.object_at(argument_index);
@@ -874,14 +1305,15 @@ impl MetalDevice {
         Some(buffer)
     }
 
-    fn set_uniforms(&self,
-                    render_command_encoder: &RenderCommandEncoderRef,
-                    render_state: &RenderState<MetalDevice>) {
-        let vertex_argument_buffer = self.create_argument_buffer(&render_state.program.vertex);
-        let fragment_argument_buffer = self.create_argument_buffer(&render_state.program.fragment);
+    fn set_raster_uniforms(&self,
+                           render_command_encoder: &RenderCommandEncoderRef,
+                           render_state: &RenderState<MetalDevice>) {
+        let program = render_state.program.raster();
+        let vertex_argument_buffer = self.create_argument_buffer(&program.vertex);
+        let fragment_argument_buffer = self.create_argument_buffer(&program.fragment);
 
-        let vertex_uniforms = render_state.program.vertex.uniforms.borrow();
-        let fragment_uniforms = render_state.program.fragment.uniforms.borrow();
+        let vertex_uniforms = program.vertex.uniforms.borrow();
+        let fragment_uniforms = program.fragment.uniforms.borrow();
 
         let (mut have_vertex_uniforms, mut have_fragment_uniforms) = (false, false);
         if let ShaderUniforms::Uniforms { .. } = *vertex_uniforms {
@@ -901,8 +1333,132 @@ impl MetalDevice {
             return;
         }
 
+        let (data_buffer, uniform_buffer_ranges) =
+            self.create_uniform_data_buffer(render_state.uniforms);
+
+        for (&(uniform, ref uniform_data), buffer_range) in
+                render_state.uniforms.iter().zip(uniform_buffer_ranges.iter()) {
+            self.populate_uniform_indices_if_necessary(uniform, &render_state.program);
+            let indices = uniform.indices.borrow_mut();
+            let indices = indices.as_ref().unwrap();
+            if let Some(vertex_index) = indices.vertex {
+                if let ShaderUniforms::Uniforms {
+                    encoder: ref argument_encoder,
+                    ..
+                } = *vertex_uniforms {
+                    self.set_uniform(vertex_index,
+                                     argument_encoder,
+                                     uniform_data,
+                                     &data_buffer,
+                                     buffer_range.start as u64,
+                                     render_command_encoder,
+                                     render_state.textures);
+                }
+            }
+            if let Some(fragment_index) = indices.fragment {
+                if let ShaderUniforms::Uniforms {
+                    encoder: ref argument_encoder,
+                    ..
+                } = *fragment_uniforms {
+                    self.set_uniform(fragment_index,
+                                     argument_encoder,
+                                     uniform_data,
+                                     &data_buffer,
+                                     buffer_range.start as u64,
+                                     render_command_encoder,
+                                     render_state.textures);
+                }
+            }
+        }
+
+        render_command_encoder.use_resource(&data_buffer, MTLResourceUsage::Read);
+
+        // Metal expects the data buffer to remain live. (Issue #199.)
+        // FIXME(pcwalton): When do we deallocate this? What are the expected
+        // lifetime semantics?
+        mem::forget(data_buffer);
+
+        if let Some(vertex_argument_buffer) = vertex_argument_buffer {
+            let range = NSRange::new(0, vertex_argument_buffer.length());
+            vertex_argument_buffer.did_modify_range(range);
+        }
+        if let Some(fragment_argument_buffer) = fragment_argument_buffer {
+            let range = NSRange::new(0, fragment_argument_buffer.length());
+            fragment_argument_buffer.did_modify_range(range);
+        }
+    }
+
+    fn set_compute_uniforms(&self,
+                            compute_command_encoder: &ComputeCommandEncoderRef,
+                            compute_state: &ComputeState<MetalDevice>) {
+        let program = compute_state.program.compute();
+        let argument_buffer = match self.create_argument_buffer(&program.shader) {
+            None => return,
+            Some(argument_buffer) => argument_buffer,
+        };
+        compute_command_encoder.use_resource(&argument_buffer, MTLResourceUsage::Read);
+        compute_command_encoder.set_buffer(0, Some(&argument_buffer), 0);
+
+        let uniforms = program.shader.uniforms.borrow();
+        let argument_encoder = match *uniforms {
+            ShaderUniforms::Uniforms { ref encoder, .. } => encoder,
+            ShaderUniforms::Unknown | ShaderUniforms::NoUniforms => unreachable!(),
+        };
+
+        if !compute_state.uniforms.is_empty() {
+            let (data_buffer, uniform_buffer_ranges) =
+                self.create_uniform_data_buffer(compute_state.uniforms);
+
+            for (&(uniform, ref uniform_data), buffer_range) in
+                    compute_state.uniforms.iter().zip(uniform_buffer_ranges.iter()) {
+                self.populate_uniform_indices_if_necessary(uniform, &compute_state.program);
+                let indices = uniform.indices.borrow();
+                if let Some(compute_index) = indices.as_ref().unwrap().compute {
+                    self.set_uniform(compute_index,
+                                     argument_encoder,
+                                     uniform_data,
+                                     &data_buffer,
+                                     buffer_range.start as u64,
+                                     compute_command_encoder,
+                                     compute_state.textures);
+                }
+            }
+
+            compute_command_encoder.use_resource(&data_buffer, MTLResourceUsage::Read);
+
+            // See the comment in `set_raster_uniforms()`.
+            mem::forget(data_buffer);
+        }
+
+        for &(storage_buffer, buffer) in compute_state.storage_buffers {
+            {
+                let mut indices = storage_buffer.indices.borrow_mut();
+                if indices.is_none() {
+                    let name = format!("i{}", storage_buffer.name);
+                    *indices = Some(self.get_uniform_indices(&compute_state.program, &name));
+                }
+            }
+            let indices = storage_buffer.indices.borrow();
+            if let Some(compute_index) = indices.as_ref().unwrap().compute {
+                let buffer = buffer.buffer.borrow();
+                let buffer = buffer.as_ref().expect("Storage buffer not allocated!");
+                argument_encoder.set_buffer(buffer, 0, compute_index.main);
+                compute_command_encoder.use_resource(buffer,
+                                                     MTLResourceUsage::Read |
+                                                     MTLResourceUsage::Write);
+            }
+        }
+
+        let range = NSRange::new(0, argument_buffer.length());
+        argument_buffer.did_modify_range(range);
+    }
+
+    // Packs the values of the uniforms into a single buffer, returning it along with the byte
+    // range of each uniform.
+    fn create_uniform_data_buffer(&self, uniforms: &[(&MetalUniform, UniformData)])
+                                  -> (Buffer, Vec<Range<usize>>) {
         let (mut uniform_buffer_data, mut uniform_buffer_ranges) = (vec![], vec![]);
-        for &(_, uniform_data) in render_state.uniforms.iter() {
+        for &(_, uniform_data) in uniforms.iter() {
             let start_index = uniform_buffer_data.len();
             match uniform_data {
                 UniformData::Float(value) => {
@@ -957,70 +1513,21 @@ impl MetalDevice {
                               .new_buffer_with_data(uniform_buffer_data.as_ptr() as *const _,
                                                     uniform_buffer_data.len() as u64,
                                                     buffer_options);
-
-        for (&(uniform, ref uniform_data), buffer_range) in
-                render_state.uniforms.iter().zip(uniform_buffer_ranges.iter()) {
-            self.populate_uniform_indices_if_necessary(uniform, &render_state.program);
-            let indices = uniform.indices.borrow_mut();
-            let indices = indices.as_ref().unwrap();
-            if let Some(vertex_index) = indices.vertex {
-                if let ShaderUniforms::Uniforms {
-                    encoder: ref argument_encoder,
-                    ..
-                } = *vertex_uniforms {
-                    self.set_uniform(vertex_index,
-                                     argument_encoder,
-                                     uniform_data,
-                                     &data_buffer,
-                                     buffer_range.start as u64,
-                                     render_command_encoder,
-                                     render_state);
-                }
-            }
-            if let Some(fragment_index) = indices.fragment {
-                if let ShaderUniforms::Uniforms {
-                    encoder: ref argument_encoder,
-                    ..
-                } = *fragment_uniforms {
-                    self.set_uniform(fragment_index,
-                                     argument_encoder,
-                                     uniform_data,
-                                     &data_buffer,
-                                     buffer_range.start as u64,
-                                     render_command_encoder,
-                                     render_state);
-                }
-            }
-        }
-
-        render_command_encoder.use_resource(&data_buffer, MTLResourceUsage::Read);
-
-        // Metal expects the data buffer to remain live. (Issue #199.)
-        // FIXME(pcwalton): When do we deallocate this? What are the expected
-        // lifetime semantics?
-        mem::forget(data_buffer);
-
-        if let Some(vertex_argument_buffer) = vertex_argument_buffer {
-            let range = NSRange::new(0, vertex_argument_buffer.length());
-            vertex_argument_buffer.did_modify_range(range);
-        }
-        if let Some(fragment_argument_buffer) = fragment_argument_buffer {
-            let range = NSRange::new(0, fragment_argument_buffer.length());
-            fragment_argument_buffer.did_modify_range(range);
-        }
-    }
-
-    fn set_uniform(&self,
-                   argument_index: MetalUniformIndex,
-                   argument_encoder: &ArgumentEncoder,
-                   uniform_data: &UniformData,
-                   buffer: &Buffer,
-                   buffer_offset: u64,
-                   render_command_encoder: &RenderCommandEncoderRef,
-                   render_state: &RenderState<MetalDevice>) {
+        (data_buffer, uniform_buffer_ranges)
+    }
+
+    fn set_uniform<E>(&self,
+                      argument_index: MetalUniformIndex,
+                      argument_encoder: &ArgumentEncoder,
+                      uniform_data: &UniformData,
+                      buffer: &Buffer,
+                      buffer_offset: u64,
+                      command_encoder: &E,
+                      textures: &[&MetalTexture])
+                      where E: ResourceUsageEncoder + ?Sized {
         match *uniform_data {
             UniformData::TextureUnit(unit) => {
-                let texture = render_state.textures[unit as usize];
+                let texture = textures[unit as usize];
                 argument_encoder.set_texture(&texture.texture, argument_index.main);
                 let mut resource_usage = MTLResourceUsage::Read;
                 if let Some(sampler_index) = argument_index.sampler {
@@ -1028,7 +1535,7 @@ impl MetalDevice {
                     argument_encoder.set_sampler_state(sampler, sampler_index);
                     resource_usage |= MTLResourceUsage::Sample;
                 }
-                render_command_encoder.use_resource(&texture.texture, resource_usage);
+                command_encoder.use_resource_with_usage(&texture.texture, resource_usage);
             }
             _ => argument_encoder.set_buffer(buffer, buffer_offset, argument_index.main),
         }
@@ -1176,6 +1683,32 @@ impl MetalDevice {
         })
     }
 
+    fn set_scissor(&self, encoder: &RenderCommandEncoderRef, viewport: &RectI, scissor: RectI) {
+        // Metal requires the scissor rect to lie within the render target.
+        let scissor = scissor.intersection(RectI::new(Vector2I::default(), viewport.size()))
+                             .unwrap_or_default();
+        encoder.set_scissor_rect(MTLScissorRect {
+            x: (viewport.min_x() + scissor.min_x()) as u64,
+            y: (viewport.min_y() + scissor.min_y()) as u64,
+            width: scissor.width() as u64,
+            height: scissor.height() as u64,
+        })
+    }
+
+    // Encodes into the command buffer being recorded, if there is one, or else into a new command
+    // buffer that's committed right away.
+    fn with_command_buffer<F>(&self, f: F) where F: FnOnce(&CommandBuffer) {
+        let command_buffers = self.command_buffers.borrow();
+        match command_buffers.last() {
+            Some(command_buffer) => f(command_buffer),
+            None => {
+                let command_buffer = self.command_queue.new_command_buffer().retain();
+                f(&command_buffer);
+                command_buffer.commit();
+            }
+        }
+    }
+
     fn synchronize_texture(&self, texture: &Texture, block: RcBlock<(*mut Object,), ()>) {
         unsafe {
             let command_buffers = self.command_buffers.borrow();
@@ -1225,6 +1758,8 @@ impl BlendFactorExt for BlendFactor {
             BlendFactor::DestAlpha => MTLBlendFactor::DestinationAlpha,
             BlendFactor::OneMinusDestAlpha => MTLBlendFactor::OneMinusDestinationAlpha,
             BlendFactor::DestColor => MTLBlendFactor::DestinationColor,
+            BlendFactor::Src1Alpha => MTLBlendFactor::Source1Alpha,
+            BlendFactor::OneMinusSrc1Alpha => MTLBlendFactor::OneMinusSource1Alpha,
         }
     }
 }
@@ -1242,6 +1777,7 @@ impl BlendOpExt for BlendOp {
             BlendOp::ReverseSubtract => MTLBlendOperation::ReverseSubtract,
             BlendOp::Min => MTLBlendOperation::Min,
             BlendOp::Max => MTLBlendOperation::Max,
+            _ => panic!("Metal doesn't support advanced blend equations!"),
         }
     }
 }
@@ -1272,6 +1808,17 @@ impl PrimitiveExt for Primitive {
     }
 }
 
+trait ComputeDimensionsExt {
+    fn to_metal_size(self) -> MTLSize;
+}
+
+impl ComputeDimensionsExt for ComputeDimensions {
+    #[inline]
+    fn to_metal_size(self) -> MTLSize {
+        MTLSize { width: self.x as u64, height: self.y as u64, depth: self.z as u64 }
+    }
+}
+
 trait StencilFuncExt {
     fn to_metal_compare_function(self) -> MTLCompareFunction;
 }
@@ -1333,6 +1880,11 @@ impl TextureFormatExt for TextureFormat {
             MTLPixelFormat::R8Unorm => Some(TextureFormat::R8),
             MTLPixelFormat::R16Float => Some(TextureFormat::R16F),
             MTLPixelFormat::RGBA8Unorm => Some(TextureFormat::RGBA8),
+            MTLPixelFormat::BC1_RGBA => Some(TextureFormat::BC1),
+            MTLPixelFormat::BC3_RGBA => Some(TextureFormat::BC3),
+            MTLPixelFormat::BC7_RGBAUnorm => Some(TextureFormat::BC7),
+            MTLPixelFormat::EAC_RGBA8 => Some(TextureFormat::ETC2RGBA8),
+            MTLPixelFormat::ASTC_4x4_LDR => Some(TextureFormat::ASTC4x4),
             MTLPixelFormat::BGRA8Unorm => {
                 // FIXME(pcwalton): This is wrong! But it prevents a crash for now.
                 Some(TextureFormat::RGBA8)
@@ -1393,6 +1945,7 @@ impl MetalTextureDataReceiver {
                                          stride as u64 * 4);
                 TextureData::F32(pixels)
             }
+            _ => panic!("Can't read back compressed textures!"),
         };
 
         let mut guard = self.0.mutex.lock().unwrap();
@@ -1451,6 +2004,133 @@ impl ArgumentArray {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+struct MetalPipelineCacheKey {
+    vertex_function: usize,
+    fragment_function: usize,
+    vertex_descriptor: usize,
+    pixel_format: NSUInteger,
+    has_depth: bool,
+    blend: Option<BlendState>,
+    color_mask: bool,
+}
+
+struct MetalPipelineCacheEntry {
+    key: MetalPipelineCacheKey,
+    _vertex_function: Function,
+    _fragment_function: Function,
+    _vertex_descriptor: VertexDescriptor,
+    render_pipeline_state: RenderPipelineState,
+}
+
+// An `MTLBinaryArchive`, which `metal-rs` doesn't wrap yet.
+struct MetalBinaryArchive {
+    archive: *mut Object,
+    url: *mut Object,
+    dirty: Cell<bool>,
+}
+
+impl MetalBinaryArchive {
+    fn new(device: &metal::Device, path: &Path) -> Option<MetalBinaryArchive> {
+        // This class doesn't exist before macOS 11 and iOS 14.
+        let descriptor_class = Class::get("MTLBinaryArchiveDescriptor")?;
+        let path_string = CFString::new(path.to_str()?);
+
+        unsafe {
+            let url: *mut Object = msg_send![class!(NSURL),
+                                             fileURLWithPath:path_string.as_concrete_TypeRef()];
+            let url: *mut Object = msg_send![url, retain];
+
+            // Load the existing archive if there is one. If it's unreadable, start over.
+            let mut archive = ptr::null_mut();
+            if path.exists() {
+                archive = new_binary_archive(device, descriptor_class, url);
+            }
+            if archive.is_null() {
+                archive = new_binary_archive(device, descriptor_class, ptr::null_mut());
+            }
+            if archive.is_null() {
+                let () = msg_send![url, release];
+                return None;
+            }
+
+            Some(MetalBinaryArchive { archive, url, dirty: Cell::new(false) })
+        }
+    }
+
+    // Lets Metal look up the pipeline in the archive instead of compiling it.
+    fn attach_to_descriptor(&self, descriptor: &RenderPipelineDescriptor) {
+        unsafe {
+            let archives: *mut Object = msg_send![class!(NSArray), arrayWithObject:self.archive];
+            let () = msg_send![descriptor.as_ptr(), setBinaryArchives:archives];
+        }
+    }
+
+    fn add_pipeline(&self, descriptor: &RenderPipelineDescriptor) {
+        unsafe {
+            let mut error: *mut Object = ptr::null_mut();
+            let added: BOOL = msg_send![self.archive,
+                                        addRenderPipelineFunctionsWithDescriptor:descriptor.as_ptr()
+                                                                           error:&mut error];
+            if added != NO {
+                self.dirty.set(true);
+            }
+        }
+    }
+
+    fn save(&self) {
+        if !self.dirty.get() {
+            return;
+        }
+        unsafe {
+            let mut error: *mut Object = ptr::null_mut();
+            let saved: BOOL = msg_send![self.archive, serializeToURL:self.url error:&mut error];
+            if saved == NO {
+                warn!("Failed to save the Metal pipeline cache.");
+                return;
+            }
+        }
+        self.dirty.set(false);
+    }
+}
+
+impl Drop for MetalBinaryArchive {
+    fn drop(&mut self) {
+        self.save();
+        unsafe {
+            let () = msg_send![self.archive, release];
+            let () = msg_send![self.url, release];
+        }
+    }
+}
+
+unsafe fn new_binary_archive(device: &metal::Device, descriptor_class: &Class, url: *mut Object)
+                             -> *mut Object {
+    let descriptor: *mut Object = msg_send![descriptor_class, new];
+    let () = msg_send![descriptor, setUrl:url];
+    let mut error: *mut Object = ptr::null_mut();
+    let archive: *mut Object = msg_send![device.as_ptr(),
+                                         newBinaryArchiveWithDescriptor:descriptor
+                                                                  error:&mut error];
+    let () = msg_send![descriptor, release];
+    archive
+}
+
+// An `MTLComputePipelineReflection`, which `metal-rs` doesn't wrap yet.
+struct ComputePipelineReflection(*mut Object);
+
+impl Drop for ComputePipelineReflection {
+    fn drop(&mut self) {
+        unsafe { msg_send![self.0, release] }
+    }
+}
+
+impl ComputePipelineReflection {
+    fn arguments(&self) -> ArgumentArray {
+        unsafe { ArgumentArray::from_ptr(msg_send![self.0, arguments]) }
+    }
+}
+
 struct SharedEvent(*mut Object);
 
 impl Drop for SharedEvent {
@@ -1460,6 +2140,17 @@ impl Drop for SharedEvent {
 }
 
 impl SharedEvent {
+    fn retain(&self) -> SharedEvent {
+        unsafe {
+            let () = msg_send![self.0, retain];
+            SharedEvent(self.0)
+        }
+    }
+
+    fn signaled_value(&self) -> u64 {
+        unsafe { msg_send![self.0, signaledValue] }
+    }
+
     fn notify_listener_at_value(&self,
                                 listener: &SharedEventListener,
                                 value: u64,
@@ -1546,6 +2237,7 @@ impl CoreAnimationLayerExt for CoreAnimationLayer {
 
 trait CommandBufferExt {
     fn encode_signal_event(&self, event: &SharedEvent, value: u64);
+    fn encode_wait_for_event(&self, event: &SharedEvent, value: u64);
 }
 
 impl CommandBufferExt for CommandBuffer {
@@ -1554,6 +2246,12 @@ impl CommandBufferExt for CommandBuffer {
             msg_send![self.as_ptr(), encodeSignalEvent:event.0 value:value]
         }
     }
+
+    fn encode_wait_for_event(&self, event: &SharedEvent, value: u64) {
+        unsafe {
+            msg_send![self.as_ptr(), encodeWaitForEvent:event.0 value:value]
+        }
+    }
 }
 
 trait DeviceExt {
@@ -1564,6 +2262,11 @@ trait DeviceExt {
                                                       options: MTLPipelineOption)
                                                       -> (RenderPipelineState,
                                                           RenderPipelineReflection);
+    fn real_new_compute_pipeline_state_with_reflection(&self,
+                                                       function: &Function,
+                                                       options: MTLPipelineOption)
+                                                       -> (ComputePipelineState,
+                                                           ComputePipelineReflection);
     fn new_shared_event(&self) -> SharedEvent;
 }
 
@@ -1594,6 +2297,32 @@ impl DeviceExt for metal::Device {
         }
     }
 
+    fn real_new_compute_pipeline_state_with_reflection(&self,
+                                                       function: &Function,
+                                                       options: MTLPipelineOption)
+                                                       -> (ComputePipelineState,
+                                                           ComputePipelineReflection) {
+        unsafe {
+            let mut reflection_ptr: *mut Object = ptr::null_mut();
+            let mut error_ptr: *mut Object = ptr::null_mut();
+            let compute_pipeline_state_ptr: *mut MTLComputePipelineState =
+                msg_send![self.as_ptr(),
+                          newComputePipelineStateWithFunction:function.as_ptr()
+                                                      options:options
+                                                   reflection:&mut reflection_ptr
+                                                        error:&mut error_ptr];
+            if !error_ptr.is_null() {
+                let description: CFStringRef = msg_send![error_ptr, description];
+                panic!("Compute pipeline state construction failed: {}",
+                       CFString::wrap_under_get_rule(description).to_string());
+            }
+            assert!(!compute_pipeline_state_ptr.is_null());
+            assert!(!reflection_ptr.is_null());
+            (ComputePipelineState::from_ptr(compute_pipeline_state_ptr),
+             ComputePipelineReflection(msg_send![reflection_ptr, retain]))
+        }
+    }
+
     fn new_shared_event(&self) -> SharedEvent {
         unsafe { SharedEvent(msg_send![self.as_ptr(), newSharedEvent]) }
     }
@@ -1643,6 +2372,26 @@ impl RenderPipelineReflectionExt for RenderPipelineReflectionRef {
     }
 }
 
+// Resources referenced from argument buffers must be declared to whichever kind of command
+// encoder uses them.
+trait ResourceUsageEncoder {
+    fn use_resource_with_usage(&self, resource: &ResourceRef, usage: MTLResourceUsage);
+}
+
+impl ResourceUsageEncoder for RenderCommandEncoderRef {
+    #[inline]
+    fn use_resource_with_usage(&self, resource: &ResourceRef, usage: MTLResourceUsage) {
+        self.use_resource(resource, usage)
+    }
+}
+
+impl ResourceUsageEncoder for ComputeCommandEncoderRef {
+    #[inline]
+    fn use_resource_with_usage(&self, resource: &ResourceRef, usage: MTLResourceUsage) {
+        self.use_resource(resource, usage)
+    }
+}
+
 trait StructMemberExt {
     fn argument_index(&self) -> u64;
 }
@@ -1667,6 +2416,13 @@ impl Retain for CommandBufferRef {
     }
 }
 
+impl Retain for ComputeCommandEncoderRef {
+    type Owned = ComputeCommandEncoder;
+    fn retain(&self) -> ComputeCommandEncoder {
+        unsafe { ComputeCommandEncoder::from_ptr(msg_send![self.as_ptr(), retain]) }
+    }
+}
+
 impl Retain for CoreAnimationDrawableRef {
     type Owned = CoreAnimationDrawable;
     fn retain(&self) -> CoreAnimationDrawable {
@@ -0,0 +1,144 @@
+// pathfinder/reftests/src/backend.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The renderers that reference tests run on.
+
+use crate::Image;
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_gpu::{Device, TextureData, TextureFormat};
+use pathfinder_renderer::concurrent::executor::SequentialExecutor;
+use pathfinder_renderer::cpu::renderer::CPURenderer;
+use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererOptions};
+use pathfinder_renderer::gpu::renderer::Renderer;
+use pathfinder_renderer::gpu_data::RenderCommand;
+use pathfinder_renderer::options::BuildOptions;
+use pathfinder_renderer::scene::Scene;
+use std::sync::{Arc, Mutex};
+
+/// Something that can render a scene into pixels for comparison.
+///
+/// Scenes are rendered over opaque white, so the pixels are the same whether the backend stores
+/// them premultiplied or not.
+pub trait Backend {
+    /// A short name for the backend, used in reports and in the names of output images.
+    fn name(&self) -> &str;
+
+    /// Renders the scene into an image of the given size, top row first.
+    fn render(&mut self, scene: &Scene, size: Vector2I) -> Result<Image, String>;
+}
+
+/// Renders scenes with the software renderer.
+#[derive(Default)]
+pub struct CPUBackend {
+    pub build_options: BuildOptions,
+}
+
+impl CPUBackend {
+    #[inline]
+    pub fn new() -> CPUBackend {
+        CPUBackend::default()
+    }
+}
+
+impl Backend for CPUBackend {
+    fn name(&self) -> &str {
+        "cpu"
+    }
+
+    fn render(&mut self, scene: &Scene, size: Vector2I) -> Result<Image, String> {
+        let commands = build_scene(scene, self.build_options.clone())?;
+
+        let mut renderer = CPURenderer::new(size, renderer_options());
+        renderer.begin_scene();
+        for command in &commands {
+            renderer.render_command(command);
+        }
+        renderer.end_scene();
+
+        Ok(Image { size, pixels: renderer.pixels().to_vec() })
+    }
+}
+
+/// Renders scenes with the GPU renderer, on whatever device the embedder has set it up with.
+///
+/// Each scene is drawn into an offscreen framebuffer of its size and read back, so the renderer's
+/// own destination framebuffer is left as it was. Its options are replaced with ones that draw over
+/// opaque white.
+pub struct GPUBackend<D> where D: Device {
+    pub renderer: Renderer<D>,
+    pub build_options: BuildOptions,
+}
+
+impl<D> GPUBackend<D> where D: Device {
+    #[inline]
+    pub fn new(renderer: Renderer<D>) -> GPUBackend<D> {
+        GPUBackend { renderer, build_options: BuildOptions::default() }
+    }
+}
+
+impl<D> Backend for GPUBackend<D> where D: Device {
+    fn name(&self) -> &str {
+        "gpu"
+    }
+
+    fn render(&mut self, scene: &Scene, size: Vector2I) -> Result<Image, String> {
+        let commands = build_scene(scene, self.build_options.clone())?;
+
+        let texture = self.renderer.device.create_texture(TextureFormat::RGBA8, size);
+        let framebuffer = self.renderer.device.create_framebuffer(texture);
+        let old_dest_framebuffer =
+            self.renderer.replace_dest_framebuffer(DestFramebuffer::Other(framebuffer));
+        self.renderer.set_options(renderer_options());
+
+        self.renderer.begin_scene();
+        let mut result = Ok(());
+        for command in &commands {
+            if let Err(error) = self.renderer.render_command(command) {
+                result = Err(format!("failed to render the scene: {:?}", error));
+                break;
+            }
+        }
+        self.renderer.end_scene();
+
+        let readback = self.renderer.begin_readback(RectI::new(Vector2I::default(), size));
+        let texture_data = self.renderer.finish_readback(readback);
+
+        if let DestFramebuffer::Other(framebuffer) =
+                self.renderer.replace_dest_framebuffer(old_dest_framebuffer) {
+            self.renderer.device.destroy_framebuffer(framebuffer);
+        }
+
+        result?;
+        let bytes = match texture_data {
+            TextureData::U8(bytes) => bytes,
+            _ => return Err("read back pixels in an unexpected format".to_owned()),
+        };
+        let pixels = bytes.chunks(4).map(|texel| {
+            ColorU::new(texel[0], texel[1], texel[2], texel[3])
+        }).collect();
+        Ok(Image { size, pixels })
+    }
+}
+
+fn build_scene(scene: &Scene, build_options: BuildOptions) -> Result<Vec<RenderCommand>, String> {
+    let commands = Arc::new(Mutex::new(vec![]));
+    let listener_commands = commands.clone();
+    let listener = move |command| listener_commands.lock().unwrap().push(command);
+    scene.build(build_options, Box::new(listener), &SequentialExecutor)
+         .map_err(|error| format!("failed to build the scene: {:?}", error))?;
+    let commands = commands.lock().unwrap().drain(..).collect();
+    Ok(commands)
+}
+
+fn renderer_options() -> RendererOptions {
+    RendererOptions { background_color: Some(ColorF::white()), ..RendererOptions::default() }
+}
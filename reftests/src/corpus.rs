@@ -0,0 +1,240 @@
+// pathfinder/reftests/src/corpus.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Pathfinder's own reference tests, whose references are in `reftests/references`.
+//!
+//! Each scene exercises one area of the renderer, drawn with the canvas API so that it reads like
+//! the content embedders draw.
+
+use crate::ReferenceTest;
+use pathfinder_canvas::{CanvasFontContext, CanvasRenderingContext2D, CompositeOperation};
+use pathfinder_canvas::{FillStyle, LineJoin, Path2D};
+use pathfinder_color::ColorU;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::gradient::{ColorStop, Gradient};
+use pathfinder_content::outline::ArcDirection;
+use pathfinder_content::stroke::LineCap;
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::vector::Vector2F;
+use pathfinder_renderer::scene::Scene;
+use std::f32::consts::PI;
+
+const SIZE: f32 = 128.0;
+
+/// Returns every test in the corpus.
+pub fn tests() -> Vec<ReferenceTest> {
+    vec![
+        ReferenceTest::new("rects", rects()),
+        ReferenceTest::new("curves", curves()),
+        ReferenceTest::new("fill_rules", fill_rules()),
+        ReferenceTest::new("strokes", strokes()),
+        ReferenceTest::new("gradients", gradients()),
+        ReferenceTest::new("clips", clips()),
+        ReferenceTest::new("blend_modes", blend_modes()),
+        ReferenceTest::new("shadows", shadows()),
+    ]
+}
+
+fn canvas() -> CanvasRenderingContext2D {
+    CanvasRenderingContext2D::new(CanvasFontContext::from_system_source(), Vector2F::splat(SIZE))
+}
+
+fn color(r: u8, g: u8, b: u8, a: u8) -> FillStyle {
+    FillStyle::Color(ColorU::new(r, g, b, a))
+}
+
+// Opaque and translucent rectangles, overlapping and on and off pixel boundaries.
+fn rects() -> Scene {
+    let mut canvas = canvas();
+    canvas.set_fill_style(color(255, 0, 0, 255));
+    canvas.fill_rect(RectF::new(Vector2F::new(8.0, 8.0), Vector2F::new(64.0, 64.0)));
+    canvas.set_fill_style(color(0, 128, 255, 128));
+    canvas.fill_rect(RectF::new(Vector2F::new(40.5, 40.5), Vector2F::new(64.0, 64.0)));
+    canvas.set_global_alpha(0.5);
+    canvas.set_fill_style(color(0, 160, 0, 255));
+    canvas.fill_rect(RectF::new(Vector2F::new(24.25, 80.75), Vector2F::new(96.0, 24.0)));
+    canvas.into_scene()
+}
+
+// Circles, ellipses, and curves of both degrees, with plenty of antialiased edges.
+fn curves() -> Scene {
+    let mut canvas = canvas();
+    let mut path = Path2D::new();
+    path.ellipse(Vector2F::new(40.0, 40.0), Vector2F::splat(28.0), 0.0, 0.0, PI * 2.0);
+    canvas.set_fill_style(color(32, 32, 160, 255));
+    canvas.fill_path(path, FillRule::Winding);
+
+    let mut path = Path2D::new();
+    path.ellipse(Vector2F::new(88.0, 88.0), Vector2F::new(32.0, 16.0), PI / 6.0, 0.0, PI * 2.0);
+    canvas.set_fill_style(color(200, 120, 0, 255));
+    canvas.fill_path(path, FillRule::Winding);
+
+    let mut path = Path2D::new();
+    path.move_to(Vector2F::new(8.0, 120.0));
+    path.quadratic_curve_to(Vector2F::new(40.0, 40.0), Vector2F::new(72.0, 120.0));
+    path.bezier_curve_to(Vector2F::new(90.0, 60.0),
+                         Vector2F::new(120.0, 140.0),
+                         Vector2F::new(120.0, 8.0));
+    path.close_path();
+    canvas.set_fill_style(color(0, 128, 64, 160));
+    canvas.fill_path(path, FillRule::Winding);
+    canvas.into_scene()
+}
+
+// A self-intersecting star filled with each fill rule.
+fn fill_rules() -> Scene {
+    let mut canvas = canvas();
+    canvas.set_fill_style(color(0, 0, 0, 255));
+    for (index, &fill_rule) in [FillRule::Winding, FillRule::EvenOdd].iter().enumerate() {
+        let center = Vector2F::new(32.0 + 64.0 * index as f32, 64.0);
+        let mut path = Path2D::new();
+        for point_index in 0..5 {
+            let angle = -PI / 2.0 + point_index as f32 * PI * 4.0 / 5.0;
+            let point = center + Vector2F::new(angle.cos(), angle.sin()).scale(28.0);
+            if point_index == 0 {
+                path.move_to(point);
+            } else {
+                path.line_to(point);
+            }
+        }
+        path.close_path();
+        canvas.fill_path(path, fill_rule);
+    }
+    canvas.into_scene()
+}
+
+// Strokes with each cap and join, hairlines, and dashes.
+fn strokes() -> Scene {
+    let mut canvas = canvas();
+    canvas.set_stroke_style(color(0, 0, 0, 255));
+    canvas.set_line_width(8.0);
+    let caps = [LineCap::Butt, LineCap::Square, LineCap::Round];
+    let joins = [LineJoin::Miter, LineJoin::Bevel, LineJoin::Round];
+    for (index, (&cap, &join)) in caps.iter().zip(joins.iter()).enumerate() {
+        let x = 16.0 + 36.0 * index as f32;
+        canvas.set_line_cap(cap);
+        canvas.set_line_join(join);
+        let mut path = Path2D::new();
+        path.move_to(Vector2F::new(x, 48.0));
+        path.line_to(Vector2F::new(x + 12.0, 12.0));
+        path.line_to(Vector2F::new(x + 24.0, 48.0));
+        canvas.stroke_path(path);
+    }
+
+    canvas.set_line_cap(LineCap::Butt);
+    canvas.set_line_width(1.0);
+    canvas.set_stroke_style(color(160, 0, 0, 255));
+    canvas.stroke_rect(RectF::new(Vector2F::new(8.5, 64.5), Vector2F::new(111.0, 20.0)));
+
+    canvas.set_line_width(4.0);
+    canvas.set_line_dash(vec![12.0, 6.0]);
+    canvas.set_stroke_style(color(0, 64, 192, 255));
+    let mut path = Path2D::new();
+    path.arc(Vector2F::new(64.0, 140.0), 40.0, PI * 1.1, PI * 1.9, ArcDirection::CW);
+    canvas.stroke_path(path);
+    canvas.into_scene()
+}
+
+// Linear and radial gradients, including translucent stops.
+fn gradients() -> Scene {
+    let mut canvas = canvas();
+    let line = LineSegment2F::new(Vector2F::new(8.0, 0.0), Vector2F::new(120.0, 0.0));
+    let mut gradient = Gradient::linear(line);
+    gradient.add_color_stop(ColorStop::new(ColorU::new(255, 0, 0, 255), 0.0));
+    gradient.add_color_stop(ColorStop::new(ColorU::new(0, 255, 0, 255), 0.5));
+    gradient.add_color_stop(ColorStop::new(ColorU::new(0, 0, 255, 255), 1.0));
+    canvas.set_fill_style(FillStyle::Gradient(gradient));
+    canvas.fill_rect(RectF::new(Vector2F::new(8.0, 8.0), Vector2F::new(112.0, 48.0)));
+
+    let mut gradient = canvas.create_radial_gradient(Vector2F::new(56.0, 84.0), 4.0,
+                                                     Vector2F::new(64.0, 92.0), 32.0);
+    gradient.add_color_stop(ColorStop::new(ColorU::new(255, 255, 0, 255), 0.0));
+    gradient.add_color_stop(ColorStop::new(ColorU::new(128, 0, 128, 0), 1.0));
+    canvas.set_fill_style(FillStyle::Gradient(gradient));
+    canvas.fill_rect(RectF::new(Vector2F::new(24.0, 60.0), Vector2F::new(80.0, 64.0)));
+    canvas.into_scene()
+}
+
+// A clip nested in another, with curved and rotated edges.
+fn clips() -> Scene {
+    let mut canvas = canvas();
+    let mut path = Path2D::new();
+    path.ellipse(Vector2F::new(64.0, 64.0), Vector2F::splat(52.0), 0.0, 0.0, PI * 2.0);
+    canvas.clip_path(path, FillRule::Winding);
+
+    canvas.save();
+    canvas.translate(Vector2F::new(64.0, 64.0));
+    canvas.rotate(PI / 8.0);
+    let mut path = Path2D::new();
+    path.rect(RectF::new(Vector2F::new(-40.0, -40.0), Vector2F::new(80.0, 80.0)));
+    canvas.clip_path(path, FillRule::Winding);
+    canvas.reset_transform();
+
+    for row in 0..8 {
+        for column in 0..8 {
+            let color = if (row + column) % 2 == 0 {
+                FillStyle::Color(ColorU::new(32, 96, 32, 255))
+            } else {
+                FillStyle::Color(ColorU::new(224, 224, 160, 255))
+            };
+            canvas.set_fill_style(color);
+            let origin = Vector2F::new(column as f32, row as f32).scale(16.0);
+            canvas.fill_rect(RectF::new(origin, Vector2F::splat(16.0)));
+        }
+    }
+    canvas.restore();
+    canvas.into_scene()
+}
+
+// Porter-Duff and separable and non-separable blend modes over a backdrop.
+fn blend_modes() -> Scene {
+    let mut canvas = canvas();
+    let operations = [
+        CompositeOperation::Multiply,
+        CompositeOperation::Screen,
+        CompositeOperation::Difference,
+        CompositeOperation::Hue,
+        CompositeOperation::Xor,
+        CompositeOperation::DestinationOut,
+    ];
+    for (index, &operation) in operations.iter().enumerate() {
+        let origin = Vector2F::new((index % 3) as f32 * 42.0 + 2.0,
+                                   (index / 3) as f32 * 64.0 + 4.0);
+        canvas.set_global_composite_operation(CompositeOperation::SourceOver);
+        canvas.set_fill_style(color(40, 120, 220, 255));
+        canvas.fill_rect(RectF::new(origin, Vector2F::new(28.0, 40.0)));
+
+        canvas.set_global_composite_operation(operation);
+        canvas.set_fill_style(color(230, 160, 20, 192));
+        let mut path = Path2D::new();
+        path.ellipse(origin + Vector2F::new(26.0, 36.0), Vector2F::splat(14.0), 0.0, 0.0, PI * 2.0);
+        canvas.fill_path(path, FillRule::Winding);
+    }
+    canvas.into_scene()
+}
+
+// Blurred and offset shadows.
+fn shadows() -> Scene {
+    let mut canvas = canvas();
+    canvas.set_shadow_color(ColorU::new(0, 0, 0, 160));
+    canvas.set_shadow_offset(Vector2F::new(6.0, 6.0));
+    canvas.set_shadow_blur(8.0);
+    canvas.set_fill_style(color(250, 200, 40, 255));
+    canvas.fill_rect(RectF::new(Vector2F::new(16.0, 16.0), Vector2F::new(56.0, 40.0)));
+
+    canvas.set_shadow_blur(0.0);
+    canvas.set_shadow_offset(Vector2F::new(-4.0, 4.0));
+    let mut path = Path2D::new();
+    path.ellipse(Vector2F::new(84.0, 88.0), Vector2F::splat(28.0), 0.0, 0.0, PI * 2.0);
+    canvas.set_fill_style(color(40, 160, 200, 255));
+    canvas.fill_path(path, FillRule::Winding);
+    canvas.into_scene()
+}
@@ -0,0 +1,197 @@
+// pathfinder/reftests/src/diff.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Perceptual comparison of rendered images.
+//!
+//! Pixels are compared by their distance in OKLab, in which equal distances look about equally
+//! different, rather than by their bytes. A difference of about 0.02 is just noticeable side by
+//! side, so antialiasing that differs by a few levels between backends passes, while a wrong color
+//! or a missing edge fails.
+
+use crate::Image;
+use pathfinder_color::{ColorSpace, ColorU};
+use std::fmt::{self, Display, Formatter};
+
+/// How different a rendering may be from its reference and still pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// Pixels whose OKLab distance from the reference is at most this are the same.
+    pub max_pixel_delta_e: f32,
+    /// The fraction of pixels, from 0 to 1, that may differ by more than `max_pixel_delta_e`.
+    pub max_differing_fraction: f32,
+}
+
+impl Default for Tolerance {
+    #[inline]
+    fn default() -> Tolerance {
+        Tolerance { max_pixel_delta_e: 0.02, max_differing_fraction: 0.001 }
+    }
+}
+
+impl Tolerance {
+    /// A tolerance that only passes identical images.
+    #[inline]
+    pub fn exact() -> Tolerance {
+        Tolerance { max_pixel_delta_e: 0.0, max_differing_fraction: 0.0 }
+    }
+}
+
+/// How an image differs from its reference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffStats {
+    pub pixel_count: usize,
+    /// The number of pixels that differ by more than the tolerance allows.
+    pub differing_pixel_count: usize,
+    pub max_delta_e: f32,
+    pub mean_delta_e: f32,
+}
+
+impl DiffStats {
+    #[inline]
+    pub fn differing_fraction(&self) -> f32 {
+        if self.pixel_count == 0 {
+            0.0
+        } else {
+            self.differing_pixel_count as f32 / self.pixel_count as f32
+        }
+    }
+
+    #[inline]
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        self.differing_fraction() <= tolerance.max_differing_fraction
+    }
+}
+
+impl Display for DiffStats {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter,
+               "{} of {} pixels differ ({:.3}%), max ΔE {:.4}, mean ΔE {:.4}",
+               self.differing_pixel_count,
+               self.pixel_count,
+               self.differing_fraction() * 100.0,
+               self.max_delta_e,
+               self.mean_delta_e)
+    }
+}
+
+/// Compares an image against its reference, returning the differences and an image that shows
+/// where they are: pixels that differ by more than the tolerance allows are red, over a faded copy
+/// of the reference.
+///
+/// Returns an error if the images are different sizes.
+pub fn compare(actual: &Image, expected: &Image, tolerance: &Tolerance)
+               -> Result<(DiffStats, Image), String> {
+    if actual.size != expected.size {
+        return Err(format!("rendered a {}x{} image, but the reference is {}x{}",
+                           actual.size.x(),
+                           actual.size.y(),
+                           expected.size.x(),
+                           expected.size.y()));
+    }
+
+    let mut stats = DiffStats {
+        pixel_count: expected.pixels.len(),
+        differing_pixel_count: 0,
+        max_delta_e: 0.0,
+        mean_delta_e: 0.0,
+    };
+    let mut total_delta_e = 0.0;
+    let mut diff_pixels = Vec::with_capacity(expected.pixels.len());
+    for (&actual_pixel, &expected_pixel) in actual.pixels.iter().zip(expected.pixels.iter()) {
+        let delta_e = delta_e(actual_pixel, expected_pixel);
+        total_delta_e += delta_e as f64;
+        stats.max_delta_e = f32::max(stats.max_delta_e, delta_e);
+
+        let gray = expected_pixel.to_f32().to_space(ColorSpace::Oklab)[0];
+        let faded = (191.0 + gray.clamp(0.0, 1.0) * 64.0) as u8;
+        if delta_e > tolerance.max_pixel_delta_e {
+            stats.differing_pixel_count += 1;
+            let fade = (faded as f32 * (1.0 - f32::min(delta_e * 4.0, 1.0))) as u8;
+            diff_pixels.push(ColorU::new(255, fade, fade, 255));
+        } else {
+            diff_pixels.push(ColorU::new(faded, faded, faded, 255));
+        }
+    }
+    if stats.pixel_count > 0 {
+        stats.mean_delta_e = (total_delta_e / stats.pixel_count as f64) as f32;
+    }
+
+    Ok((stats, Image { size: expected.size, pixels: diff_pixels }))
+}
+
+/// The perceptual distance between two straight-alpha colors, in OKLab.
+///
+/// Translucent colors are compared composited over both black and white, taking whichever
+/// difference is larger, so that a difference in alpha alone counts too.
+pub fn delta_e(a: ColorU, b: ColorU) -> f32 {
+    if a == b {
+        return 0.0;
+    }
+    if a.a == 255 && b.a == 255 {
+        return oklab_distance(a, b);
+    }
+    f32::max(oklab_distance(over(a, ColorU::black()), over(b, ColorU::black())),
+             oklab_distance(over(a, ColorU::white()), over(b, ColorU::white())))
+}
+
+fn oklab_distance(a: ColorU, b: ColorU) -> f32 {
+    let (a, b) = (a.to_f32().to_space(ColorSpace::Oklab), b.to_f32().to_space(ColorSpace::Oklab));
+    let delta = a - b;
+    f32::sqrt(delta[0] * delta[0] + delta[1] * delta[1] + delta[2] * delta[2])
+}
+
+fn over(src: ColorU, dest: ColorU) -> ColorU {
+    let (src, dest) = (src.to_f32(), dest.to_f32());
+    let alpha = src.a();
+    dest.lerp(src, alpha).to_u8()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Tolerance, compare, delta_e};
+    use crate::Image;
+    use pathfinder_color::ColorU;
+    use pathfinder_geometry::vector::Vector2I;
+
+    #[test]
+    fn test_delta_e_is_perceptual() {
+        let gray = ColorU::new(128, 128, 128, 255);
+        assert_eq!(delta_e(gray, gray), 0.0);
+        assert!(delta_e(gray, ColorU::new(129, 128, 128, 255)) < 0.01);
+        assert!(delta_e(gray, ColorU::new(128, 160, 128, 255)) > 0.05);
+        assert!(delta_e(ColorU::black(), ColorU::white()) > 0.99);
+
+        // A difference in alpha alone is a difference.
+        assert!(delta_e(ColorU::new(0, 0, 0, 255), ColorU::new(0, 0, 0, 128)) > 0.3);
+    }
+
+    #[test]
+    fn test_compare_counts_pixels_over_tolerance() {
+        let size = Vector2I::new(10, 10);
+        let expected = Image { size, pixels: vec![ColorU::white(); 100] };
+        let mut actual = expected.clone();
+        actual.pixels[0] = ColorU::new(254, 254, 254, 255);
+        actual.pixels[1] = ColorU::black();
+
+        let (stats, diff) = compare(&actual, &expected, &Tolerance::default()).unwrap();
+        assert_eq!(stats.differing_pixel_count, 1);
+        assert!(stats.max_delta_e > 0.99);
+        assert!(!stats.passes(&Tolerance::default()));
+        assert!(stats.passes(&Tolerance { max_differing_fraction: 0.01, ..Tolerance::default() }));
+        assert_eq!(diff.pixels[1], ColorU::new(255, 0, 0, 255));
+        assert_eq!(diff.pixels[0], diff.pixels[2]);
+
+        let (stats, _) = compare(&actual, &expected, &Tolerance::exact()).unwrap();
+        assert_eq!(stats.differing_pixel_count, 2);
+
+        let smaller = Image { size: Vector2I::new(5, 5), pixels: vec![ColorU::white(); 25] };
+        assert!(compare(&smaller, &expected, &Tolerance::default()).is_err());
+    }
+}
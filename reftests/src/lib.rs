@@ -0,0 +1,272 @@
+// pathfinder/reftests/src/lib.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Golden-image reference tests for Pathfinder.
+//!
+//! A reference test renders a scene and compares the result against a PNG stored alongside the
+//! tests. The same references check both the software and GPU renderers, each within a tolerance.
+//! Pathfinder's own corpus is in `corpus`; embedders can run their own scenes through a `Harness`
+//! pointed at their own references to catch regressions in the content they draw.
+//!
+//! To create or update references, run the harness with `bless` set, then look over the new images
+//! before checking them in.
+
+#[macro_use]
+extern crate log;
+
+pub mod backend;
+pub mod corpus;
+pub mod diff;
+
+use crate::backend::Backend;
+use crate::diff::{DiffStats, Tolerance};
+use image::ColorType;
+use pathfinder_color::ColorU;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_renderer::scene::Scene;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A scene to render and the name of the reference it's compared against.
+pub struct ReferenceTest {
+    /// The name of the test, which is also the name of its reference image, without `.png`.
+    pub name: String,
+    pub scene: Scene,
+    /// The size of the image to render. Defaults to the size of the scene's view box.
+    pub size: Vector2I,
+    /// If set, overrides the harness's tolerance for this test.
+    pub tolerance: Option<Tolerance>,
+}
+
+impl ReferenceTest {
+    pub fn new(name: &str, scene: Scene) -> ReferenceTest {
+        let size = scene.view_box().size().ceil().to_i32();
+        ReferenceTest { name: name.to_owned(), scene, size, tolerance: None }
+    }
+}
+
+/// Runs reference tests and compares their results against the images in a directory.
+pub struct Harness {
+    pub reference_dir: PathBuf,
+    /// If set, the rendered image and the diff image of each failing test are written here, as
+    /// `<name>-<backend>.png` and `<name>-<backend>-diff.png`.
+    pub output_dir: Option<PathBuf>,
+    /// The tolerance for tests that don't set their own.
+    pub tolerance: Tolerance,
+    /// If true, references that are missing or don't match are replaced with the rendered images
+    /// instead of failing.
+    pub bless: bool,
+}
+
+impl Harness {
+    pub fn new<P>(reference_dir: P) -> Harness where P: Into<PathBuf> {
+        Harness {
+            reference_dir: reference_dir.into(),
+            output_dir: None,
+            tolerance: Tolerance::default(),
+            bless: false,
+        }
+    }
+
+    pub fn run(&self, backend: &mut dyn Backend, tests: &[ReferenceTest]) -> Report {
+        Report { results: tests.iter().map(|test| self.run_test(backend, test)).collect() }
+    }
+
+    pub fn run_test(&self, backend: &mut dyn Backend, test: &ReferenceTest) -> TestResult {
+        let outcome = self.run_test_outcome(backend, test);
+        match outcome {
+            Outcome::Passed(_) | Outcome::Blessed => {}
+            _ => warn!("reference test {} failed on {}: {}", test.name, backend.name(), outcome),
+        }
+        TestResult { name: test.name.clone(), backend: backend.name().to_owned(), outcome }
+    }
+
+    fn run_test_outcome(&self, backend: &mut dyn Backend, test: &ReferenceTest) -> Outcome {
+        let actual = match backend.render(&test.scene, test.size) {
+            Ok(actual) => actual,
+            Err(error) => return Outcome::Error(error),
+        };
+
+        let reference_path = self.reference_dir.join(format!("{}.png", test.name));
+        let expected = match Image::load_png(&reference_path) {
+            Ok(expected) => expected,
+            Err(_) if self.bless => return self.bless_reference(&actual, &reference_path),
+            Err(error) => return Outcome::Error(error),
+        };
+
+        let tolerance = test.tolerance.unwrap_or(self.tolerance);
+        let (stats, diff) = match diff::compare(&actual, &expected, &tolerance) {
+            Ok((stats, diff)) => (stats, diff),
+            Err(_) if self.bless => return self.bless_reference(&actual, &reference_path),
+            Err(error) => return Outcome::Error(error),
+        };
+        if stats.passes(&tolerance) {
+            return Outcome::Passed(stats);
+        }
+        if self.bless {
+            return self.bless_reference(&actual, &reference_path);
+        }
+
+        if let Some(ref output_dir) = self.output_dir {
+            let prefix = format!("{}-{}", test.name, backend.name());
+            let result = fs::create_dir_all(output_dir).map_err(|error| error.to_string())
+                .and_then(|()| actual.save_png(&output_dir.join(format!("{}.png", prefix))))
+                .and_then(|()| diff.save_png(&output_dir.join(format!("{}-diff.png", prefix))));
+            if let Err(error) = result {
+                warn!("failed to write the output of reference test {}: {}", test.name, error);
+            }
+        }
+        Outcome::Failed(stats)
+    }
+
+    fn bless_reference(&self, actual: &Image, reference_path: &Path) -> Outcome {
+        let result = fs::create_dir_all(&self.reference_dir).map_err(|error| error.to_string());
+        match result.and_then(|()| actual.save_png(reference_path)) {
+            Ok(()) => Outcome::Blessed,
+            Err(error) => Outcome::Error(error),
+        }
+    }
+}
+
+/// The results of a run of reference tests.
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub results: Vec<TestResult>,
+}
+
+impl Report {
+    #[inline]
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.outcome.passed())
+    }
+
+    #[inline]
+    pub fn failures(&self) -> impl Iterator<Item = &TestResult> {
+        self.results.iter().filter(|result| !result.outcome.passed())
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        for result in &self.results {
+            writeln!(formatter, "{}", result)?;
+        }
+        let failure_count = self.failures().count();
+        write!(formatter,
+               "{} passed, {} failed",
+               self.results.len() - failure_count,
+               failure_count)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub backend: String,
+    pub outcome: Outcome,
+}
+
+impl Display for TestResult {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "{} ({}): {}", self.name, self.backend, self.outcome)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Outcome {
+    /// The rendered image matched the reference within the tolerance.
+    Passed(DiffStats),
+    /// The rendered image differed from the reference by more than the tolerance.
+    Failed(DiffStats),
+    /// The reference was written from the rendered image.
+    Blessed,
+    /// The scene couldn't be rendered, or the reference couldn't be read or compared against.
+    Error(String),
+}
+
+impl Outcome {
+    #[inline]
+    pub fn passed(&self) -> bool {
+        match *self {
+            Outcome::Passed(_) | Outcome::Blessed => true,
+            Outcome::Failed(_) | Outcome::Error(_) => false,
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match *self {
+            Outcome::Passed(ref stats) => write!(formatter, "passed: {}", stats),
+            Outcome::Failed(ref stats) => write!(formatter, "FAILED: {}", stats),
+            Outcome::Blessed => write!(formatter, "blessed"),
+            Outcome::Error(ref error) => write!(formatter, "ERROR: {}", error),
+        }
+    }
+}
+
+/// Straight-alpha RGBA pixels, top row first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub size: Vector2I,
+    pub pixels: Vec<ColorU>,
+}
+
+impl Image {
+    pub fn load_png(path: &Path) -> Result<Image, String> {
+        let image = image::open(path).map_err(|error| {
+            format!("failed to load {}: {}", path.display(), error)
+        })?.to_rgba();
+        let size = Vector2I::new(image.width() as i32, image.height() as i32);
+        let pixels = image.into_raw().chunks(4).map(|texel| {
+            ColorU::new(texel[0], texel[1], texel[2], texel[3])
+        }).collect();
+        Ok(Image { size, pixels })
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<(), String> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+        for pixel in &self.pixels {
+            bytes.extend_from_slice(&[pixel.r, pixel.g, pixel.b, pixel.a]);
+        }
+        image::save_buffer(path,
+                           &bytes,
+                           self.size.x() as u32,
+                           self.size.y() as u32,
+                           ColorType::RGBA(8)).map_err(|error| {
+            format!("failed to save {}: {}", path.display(), error)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Harness;
+    use crate::backend::CPUBackend;
+    use crate::corpus;
+    use std::env;
+    use std::path::Path;
+
+    // Set `PATHFINDER_BLESS_REFTESTS` to rewrite the references from the software renderer. This
+    // needs system fonts for the canvas and references blessed on the machine it runs on, so it
+    // only runs when asked for with `--ignored`.
+    #[test]
+    #[ignore]
+    fn test_cpu_backend_matches_references() {
+        let reference_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("references");
+        let mut harness = Harness::new(reference_dir);
+        harness.output_dir = Some(env::temp_dir().join("pathfinder-reftests"));
+        harness.bless = env::var_os("PATHFINDER_BLESS_REFTESTS").is_some();
+
+        let report = harness.run(&mut CPUBackend::new(), &corpus::tests());
+        assert!(report.passed(), "{}", report);
+    }
+}
@@ -8,8 +8,9 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-//! A simple quadtree-based texture allocator.
+//! A texture allocator that packs paints into atlases with guillotine packing.
 
+use crate::error::Error;
 use crate::gpu_data::{TextureLocation, TexturePageId};
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::vector::{Vector2F, Vector2I};
@@ -23,26 +24,22 @@ pub struct TextureAllocator {
 
 #[derive(Debug)]
 pub enum TexturePageAllocator {
-    // An atlas allocated with our quadtree allocator.
+    // An atlas allocated with our guillotine allocator.
     Atlas(TextureAtlasAllocator),
     // A single image.
     Image { size: Vector2I },
 }
 
+// Packs rectangles into a square page by taking each from the corner of a free rectangle and
+// cutting what's left of that rectangle in two.
 #[derive(Debug)]
 pub struct TextureAtlasAllocator {
-    root: TreeNode,
+    // The free space, as rectangles that don't overlap.
+    free_rects: Vec<RectI>,
+    allocation_count: usize,
     size: u32,
 }
 
-#[derive(Debug)]
-enum TreeNode {
-    EmptyLeaf,
-    FullLeaf,
-    // Top left, top right, bottom left, and bottom right, in that order.
-    Parent([Box<TreeNode>; 4]),
-}
-
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum AllocationMode {
     Atlas,
@@ -55,39 +52,85 @@ impl TextureAllocator {
         TextureAllocator { pages: vec![] }
     }
 
-    pub fn allocate(&mut self, requested_size: Vector2I, mode: AllocationMode) -> TextureLocation {
+    pub fn allocate(&mut self, requested_size: Vector2I, mode: AllocationMode)
+                    -> Result<TextureLocation, Error> {
+        if requested_size.x() < 0 || requested_size.y() < 0 {
+            return Err(Error::TextureAllocationFailed(requested_size));
+        }
+
         // If requested, or if the image is too big, use a separate page.
-        if mode == AllocationMode::OwnPage ||
-                requested_size.x() > ATLAS_TEXTURE_LENGTH as i32 ||
-                requested_size.y() > ATLAS_TEXTURE_LENGTH as i32 {
+        if mode == AllocationMode::OwnPage || !fits_in_atlas(requested_size) {
             return self.allocate_image(requested_size);
         }
 
         // Try to add to each atlas.
+        if let Some(location) = self.allocate_in_existing_atlas(requested_size) {
+            return Ok(location);
+        }
+
+        // Add a new atlas.
+        let page = TexturePageId(self.pages.len() as u32);
+        let mut allocator = TextureAtlasAllocator::new();
+        let rect = match allocator.allocate(requested_size) {
+            Some(rect) => rect,
+            None => return Err(Error::TextureAllocationFailed(requested_size)),
+        };
+        self.pages.push(TexturePageAllocator::Atlas(allocator));
+        Ok(TextureLocation { page, rect })
+    }
+
+    /// Allocates space in one of the atlas pages there already are, without adding a page.
+    pub fn allocate_in_existing_atlas(&mut self, requested_size: Vector2I)
+                                      -> Option<TextureLocation> {
+        if requested_size.x() < 0 || requested_size.y() < 0 || !fits_in_atlas(requested_size) {
+            return None;
+        }
         for (page_index, page) in self.pages.iter_mut().enumerate() {
             match *page {
                 TexturePageAllocator::Image { .. } => {}
                 TexturePageAllocator::Atlas(ref mut allocator) => {
                     if let Some(rect) = allocator.allocate(requested_size) {
-                        return TextureLocation { page: TexturePageId(page_index as u32), rect };
+                        let page = TexturePageId(page_index as u32);
+                        return Some(TextureLocation { page, rect });
                     }
                 }
             }
         }
-
-        // Add a new atlas.
-        let page = TexturePageId(self.pages.len() as u32);
-        let mut allocator = TextureAtlasAllocator::new();
-        let rect = allocator.allocate(requested_size).expect("Allocation failed!");
-        self.pages.push(TexturePageAllocator::Atlas(allocator));
-        TextureLocation { page, rect }
+        None
     }
 
-    pub fn allocate_image(&mut self, requested_size: Vector2I) -> TextureLocation {
+    pub fn allocate_image(&mut self, requested_size: Vector2I) -> Result<TextureLocation, Error> {
+        if requested_size.x() < 0 || requested_size.y() < 0 {
+            return Err(Error::TextureAllocationFailed(requested_size));
+        }
+
         let page = TexturePageId(self.pages.len() as u32);
         let rect = RectI::new(Vector2I::default(), requested_size);
         self.pages.push(TexturePageAllocator::Image { size: rect.size() });
-        TextureLocation { page, rect }
+        Ok(TextureLocation { page, rect })
+    }
+
+    /// Frees space allocated in an atlas page. Image pages are freed with `free_images()`.
+    pub fn free(&mut self, location: TextureLocation) {
+        match self.pages[location.page.0 as usize] {
+            TexturePageAllocator::Atlas(ref mut allocator) => allocator.free(location.rect),
+            TexturePageAllocator::Image { .. } => {}
+        }
+    }
+
+    /// Removes the image pages at the end, leaving the atlas pages before them and the space
+    /// allocated in those. No page is renumbered as long as no atlas page was added after the
+    /// first image page.
+    pub fn free_images(&mut self) {
+        while let Some(&TexturePageAllocator::Image { .. }) = self.pages.last() {
+            self.pages.pop();
+        }
+        debug_assert!(self.pages.iter().all(|page| {
+            match *page {
+                TexturePageAllocator::Atlas(_) => true,
+                TexturePageAllocator::Image { .. } => false,
+            }
+        }));
     }
 
     pub fn page_size(&self, page_index: TexturePageId) -> Vector2I {
@@ -107,166 +150,117 @@ impl TextureAllocator {
     }
 }
 
-impl TextureAtlasAllocator {
+impl Default for TextureAllocator {
     #[inline]
-    fn new() -> TextureAtlasAllocator {
-        TextureAtlasAllocator::with_length(ATLAS_TEXTURE_LENGTH)
-    }
-
-    #[inline]
-    fn with_length(length: u32) -> TextureAtlasAllocator {
-        TextureAtlasAllocator { root: TreeNode::EmptyLeaf, size: length }
+    fn default() -> TextureAllocator {
+        TextureAllocator::new()
     }
+}
 
-    #[inline]
-    fn allocate(&mut self, requested_size: Vector2I) -> Option<RectI> {
-        let requested_length =
-            (requested_size.x().max(requested_size.y()) as u32).next_power_of_two();
-        self.root.allocate(Vector2I::default(), self.size, requested_length)
-    }
+/// Returns true if an image of this size can be allocated in an atlas rather than getting a page
+/// of its own.
+#[inline]
+pub fn fits_in_atlas(size: Vector2I) -> bool {
+    size.x() <= ATLAS_TEXTURE_LENGTH as i32 && size.y() <= ATLAS_TEXTURE_LENGTH as i32
+}
 
+impl TextureAtlasAllocator {
     #[inline]
-    #[allow(dead_code)]
-    fn free(&mut self, rect: RectI) {
-        let requested_length = rect.width() as u32;
-        self.root.free(Vector2I::default(), self.size, rect.origin(), requested_length)
+    fn new() -> TextureAtlasAllocator {
+        TextureAtlasAllocator::with_length(ATLAS_TEXTURE_LENGTH)
     }
 
     #[inline]
-    #[allow(dead_code)]
-    fn is_empty(&self) -> bool {
-        match self.root {
-            TreeNode::EmptyLeaf => true,
-            _ => false,
+    fn with_length(length: u32) -> TextureAtlasAllocator {
+        TextureAtlasAllocator {
+            free_rects: vec![RectI::new(Vector2I::default(), Vector2I::splat(length as i32))],
+            allocation_count: 0,
+            size: length,
         }
     }
-}
 
-impl TreeNode {
-    // Invariant: `requested_size` must be a power of two.
-    fn allocate(&mut self, this_origin: Vector2I, this_size: u32, requested_size: u32)
-                -> Option<RectI> {
-        if let TreeNode::FullLeaf = *self {
-            // No room here.
-            return None;
+    fn allocate(&mut self, requested_size: Vector2I) -> Option<RectI> {
+        // Empty allocations still take a texel, so that they have a location of their own.
+        let requested_size = requested_size.max(Vector2I::splat(1));
+
+        // Take the free rectangle that leaves the least room along its tighter side.
+        let mut best = None;
+        for (index, free_rect) in self.free_rects.iter().enumerate() {
+            let leftover = free_rect.size() - requested_size;
+            if leftover.x() < 0 || leftover.y() < 0 {
+                continue;
+            }
+            let short_side = leftover.x().min(leftover.y());
+            match best {
+                Some((_, best_short_side)) if best_short_side <= short_side => {}
+                _ => best = Some((index, short_side)),
+            }
         }
-        if this_size < requested_size {
-            // Doesn't fit.
-            return None;
+        let free_rect = self.free_rects.swap_remove(best?.0);
+
+        // Cut the rest along the shorter leftover axis, keeping the bigger piece as big as
+        // possible.
+        let origin = free_rect.origin();
+        let leftover = free_rect.size() - requested_size;
+        let (right, below);
+        if leftover.x() < leftover.y() {
+            right = RectI::new(origin + Vector2I::new(requested_size.x(), 0),
+                               Vector2I::new(leftover.x(), requested_size.y()));
+            below = RectI::new(origin + Vector2I::new(0, requested_size.y()),
+                               Vector2I::new(free_rect.width(), leftover.y()));
+        } else {
+            right = RectI::new(origin + Vector2I::new(requested_size.x(), 0),
+                               Vector2I::new(leftover.x(), free_rect.height()));
+            below = RectI::new(origin + Vector2I::new(0, requested_size.y()),
+                               Vector2I::new(requested_size.x(), leftover.y()));
         }
-
-        // Allocate here or split, as necessary.
-        if let TreeNode::EmptyLeaf = *self {
-            // Do we have a perfect fit?
-            if this_size == requested_size {
-                *self = TreeNode::FullLeaf;
-                return Some(RectI::new(this_origin, Vector2I::splat(this_size as i32)));
+        for &piece in &[right, below] {
+            if piece.width() > 0 && piece.height() > 0 {
+                self.free_rects.push(piece);
             }
-
-            // Split.
-            *self = TreeNode::Parent([
-                Box::new(TreeNode::EmptyLeaf),
-                Box::new(TreeNode::EmptyLeaf),
-                Box::new(TreeNode::EmptyLeaf),
-                Box::new(TreeNode::EmptyLeaf),
-            ]);
         }
 
-        // Recurse into children.
-        match *self {
-            TreeNode::Parent(ref mut kids) => {
-                let kid_size = this_size / 2;
-                if let Some(origin) = kids[0].allocate(this_origin, kid_size, requested_size) {
-                    return Some(origin);
-                }
-                if let Some(origin) =
-                        kids[1].allocate(this_origin + Vector2I::new(kid_size as i32, 0),
-                                         kid_size,
-                                         requested_size) {
-                    return Some(origin);
-                }
-                if let Some(origin) =
-                        kids[2].allocate(this_origin + Vector2I::new(0, kid_size as i32),
-                                         kid_size,
-                                         requested_size) {
-                    return Some(origin);
-                }
-                if let Some(origin) =
-                        kids[3].allocate(this_origin + Vector2I::splat(kid_size as i32),
-                                         kid_size,
-                                         requested_size) {
-                    return Some(origin);
-                }
-
-                self.merge_if_necessary();
-                return None;
-            }
-            TreeNode::EmptyLeaf | TreeNode::FullLeaf => unreachable!(),
-        }
+        self.allocation_count += 1;
+        Some(RectI::new(origin, requested_size))
     }
 
-    #[allow(dead_code)]
-    fn free(&mut self,
-            this_origin: Vector2I,
-            this_size: u32,
-            requested_origin: Vector2I,
-            requested_size: u32) {
-        if this_size <= requested_size {
-            if this_size == requested_size && this_origin == requested_origin {
-                *self = TreeNode::EmptyLeaf;
-            }
+    fn free(&mut self, rect: RectI) {
+        debug_assert!(self.allocation_count > 0);
+        self.allocation_count -= 1;
+        if self.allocation_count == 0 {
+            self.free_rects.clear();
+            self.free_rects.push(RectI::new(Vector2I::default(),
+                                            Vector2I::splat(self.size as i32)));
             return;
         }
 
-        let child_size = this_size / 2;
-        let this_center = this_origin + Vector2I::splat(child_size as i32);
-
-        let child_index;
-        let mut child_origin = this_origin;
-
-        if requested_origin.y() < this_center.y() {
-            if requested_origin.x() < this_center.x() {
-                child_index = 0;
-            } else {
-                child_index = 1;
-                child_origin = child_origin + Vector2I::new(child_size as i32, 0);
-            }
-        } else {
-            if requested_origin.x() < this_center.x() {
-                child_index = 2;
-                child_origin = child_origin + Vector2I::new(0, child_size as i32);
-            } else {
-                child_index = 3;
-                child_origin = this_center;
-            }
-        }
-
-        match *self {
-            TreeNode::Parent(ref mut kids) => {
-                kids[child_index].free(child_origin, child_size, requested_origin, requested_size);
-                self.merge_if_necessary();
-            }
-            TreeNode::EmptyLeaf | TreeNode::FullLeaf => unreachable!(),
+        // Merge with free neighbors that share a whole edge, for as long as there are any.
+        let mut rect = rect;
+        while let Some(index) = self.free_rects
+                                    .iter()
+                                    .position(|&free_rect| shares_edge(rect, free_rect)) {
+            let free_rect = self.free_rects.swap_remove(index);
+            rect = RectI::from_points(rect.origin().min(free_rect.origin()),
+                                      rect.lower_right().max(free_rect.lower_right()));
         }
+        self.free_rects.push(rect);
     }
 
-    fn merge_if_necessary(&mut self) {
-        match *self {
-            TreeNode::Parent(ref mut kids) => {
-                if kids.iter().all(|kid| {
-                    match **kid {
-                        TreeNode::EmptyLeaf => true,
-                        _ => false,
-                    }
-                }) {
-                    *self = TreeNode::EmptyLeaf;
-                }
-            }
-            _ => {}
-        }
+    #[inline]
+    #[allow(dead_code)]
+    fn is_empty(&self) -> bool {
+        self.allocation_count == 0
     }
 }
 
+// Returns true if the two rectangles are side by side and together make up a rectangle.
+fn shares_edge(a: RectI, b: RectI) -> bool {
+    let same_columns = a.min_x() == b.min_x() && a.max_x() == b.max_x();
+    let same_rows = a.min_y() == b.min_y() && a.max_y() == b.max_y();
+    (same_columns && (a.max_y() == b.min_y() || b.max_y() == a.min_y())) ||
+        (same_rows && (a.max_x() == b.min_x() || b.max_x() == a.min_x()))
+}
+
 #[cfg(test)]
 mod test {
     use pathfinder_geometry::vector::Vector2I;
@@ -306,4 +300,23 @@ mod test {
             true
         }
     }
+
+    #[test]
+    fn test_allocations_are_not_rounded_up() {
+        // A quadtree would round each of these up to 256 pixels square and fit only 16.
+        let mut allocator = TextureAtlasAllocator::with_length(1024);
+        let mut rects = vec![];
+        for _ in 0..25 {
+            rects.push(allocator.allocate(Vector2I::splat(200)).unwrap());
+        }
+        for (index, rect) in rects.iter().enumerate() {
+            assert_eq!(rect.size(), Vector2I::splat(200));
+            assert!(rects[(index + 1)..].iter().all(|other| !rect.intersects(*other)));
+        }
+
+        // Freed space is reused.
+        assert!(allocator.allocate(Vector2I::splat(200)).is_none());
+        allocator.free(rects[7]);
+        assert_eq!(allocator.allocate(Vector2I::splat(200)), Some(rects[7]));
+    }
 }
@@ -11,44 +11,136 @@
 //! Packs data onto the GPU.
 
 use crate::concurrent::executor::Executor;
+use crate::error::Error;
 use crate::gpu::renderer::{BlendModeProgram, MASK_TILES_ACROSS};
-use crate::gpu_data::{AlphaTile, AlphaTileBatch, AlphaTileVertex, FillBatchPrimitive, MaskTile};
+use crate::gpu_data::{AlphaTile, AlphaTileBatch, AlphaTileVertex, AlphaType, CustomPaintParams};
+use crate::gpu_data::{FillBatchPrimitive, MaskTile};
 use crate::gpu_data::{MaskTileVertex, RenderCommand, SolidTile, SolidTileBatch};
 use crate::gpu_data::{TexturePageId, TileObjectPrimitive};
-use crate::options::{PreparedBuildOptions, RenderCommandListener};
+use crate::options::{PreparedBuildOptions, PreparedRenderTransform, RenderCommandListener};
 use crate::paint::{PaintInfo, PaintMetadata, RenderTargetMetadata};
-use crate::scene::{DisplayItem, Scene};
-use crate::tile_map::DenseTileMap;
+use crate::picture::PictureTilingKey;
+use crate::scene::{DisplayItem, PathTiles, Scene};
+use crate::tile_map::{DenseTileMap, TileMap};
 use crate::tiles::{self, DrawTilingPathInfo, TILE_HEIGHT, TILE_WIDTH, Tiler, TilingPathInfo};
 use crate::z_buffer::{DepthMetadata, ZBuffer};
-use pathfinder_content::effects::BlendMode;
+use pathfinder_content::effects::{BlendMode, Effects};
 use pathfinder_content::fill::FillRule;
+use pathfinder_content::outline::Outline;
 use pathfinder_content::render_target::RenderTargetId;
 use pathfinder_geometry::line_segment::{LineSegment2F, LineSegmentU4, LineSegmentU8};
 use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::util;
 use pathfinder_geometry::vector::{Vector2F, Vector2I};
 use pathfinder_gpu::TextureSamplingFlags;
 use pathfinder_simd::default::{F32x4, I32x4};
+use std::cmp;
+use std::collections::HashMap;
+use std::iter;
+use std::mem;
+use std::slice;
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 use std::u16;
 
 pub(crate) struct SceneBuilder<'a> {
     scene: &'a Scene,
+    // The options of each viewport, in the order they're drawn.
+    viewports: &'a [PreparedBuildOptions],
+    // The options of the viewport being built.
     built_options: &'a PreparedBuildOptions,
+    // Whether each viewport is drawn to its own layer of the destination.
+    layered: bool,
+    // The corner of the chunk that tile positions in render commands are relative to. It's the
+    // same for every viewport.
+    pub(crate) chunk_origin: Vector2I,
 
     next_alpha_tile_index: AtomicUsize,
     next_mask_tile_index: AtomicUsize,
+    // The next mask tile index to give tiles that survive culling. Mask tiles are numbered across
+    // the whole frame, since the mask framebuffer is only cleared at the start of it.
+    next_culled_mask_tile_index: usize,
+    // The number of paths skipped because they lay outside the view box.
+    culled_path_count: AtomicUsize,
 
     pub(crate) listener: Box<dyn RenderCommandListener>,
 }
 
+/// Vectors left over from earlier builds of a scene, for later builds to reuse.
+///
+/// Building allocates several vectors per path, which adds up when a scene is rebuilt every frame
+/// to animate it. Each `Scene` keeps one of these, and built paths hand their vectors back to it
+/// once their tiles have been culled. Culling borrows alpha tile vectors from it too. The fill
+/// vectors aren't pooled, since they're passed on to the render command listener.
+#[derive(Default)]
+pub(crate) struct BuildPool {
+    buffers: Mutex<PooledBuffers>,
+}
+
+#[derive(Default)]
+struct PooledBuffers {
+    mask_tiles: Vec<Vec<MaskTile>>,
+    alpha_tiles: Vec<Vec<AlphaTile>>,
+    solid_tiles: Vec<Vec<SolidTileInfo>>,
+    tiles: Vec<Vec<TileObjectPrimitive>>,
+}
+
+/// Clip paths tiled by earlier builds of a scene, for later builds to reuse.
+///
+/// Tiling a clip path doesn't depend on the paths it clips, and clip paths can't change once
+/// they're pushed, so a rebuild with the same transform and view box can send the fills from the
+/// last build again instead of tiling the path from scratch. An entry is invalidated, and the clip
+/// path retiled, whenever the transform, view box, dilation, or subpixel AA setting differ from
+/// the ones it was built with. Clip paths aren't cached under perspective transforms.
+#[derive(Default)]
+pub(crate) struct ClipCache {
+    // Indexed by clip path ID.
+    entries: Mutex<Vec<Option<CachedClipPath>>>,
+}
+
+struct CachedClipPath {
+    key: ClipCacheKey,
+    clip_rect: Option<RectI>,
+    tiling: CachedTiling,
+}
+
+/// The tiles and fills of a tiled path, kept so that it can be drawn again without tiling it.
+pub(crate) struct CachedTiling {
+    bounds: RectF,
+    // The tiles that differ from the default. Alpha tile indices here and in `fills` are counted
+    // from zero, in the order they were allocated, and are offset each time the tiling is reused.
+    tiles: Vec<(Vector2I, TileObjectPrimitive)>,
+    fills: Vec<FillBatchPrimitive>,
+    alpha_tile_count: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct ClipCacheKey {
+    transform: Transform2F,
+    view_box: RectF,
+    dilation: Vector2F,
+    subpixel_aa_enabled: bool,
+}
+
 #[derive(Debug)]
 pub(crate) struct ObjectBuilder {
     pub built_path: BuiltPath,
     pub fills: Vec<FillBatchPrimitive>,
     pub bounds: RectF,
+    // If set, a copy of every fill sent, for the clip cache.
+    recorded_fills: Option<Vec<FillBatchPrimitive>>,
+}
+
+// What the draw paths of one viewport are built against: the earlier build stages' results.
+struct DrawPathContext<'b> {
+    view_box: RectF,
+    built_options: &'b PreparedBuildOptions,
+    scene: &'b Scene,
+    paint_metadata: &'b [PaintMetadata],
+    built_clip_paths: &'b [BuiltPath],
+    clip_rects: &'b [Option<RectI>],
 }
 
 #[derive(Debug)]
@@ -57,6 +149,11 @@ struct BuiltDrawPath {
     blend_mode: BlendMode,
     sampling_flags: TextureSamplingFlags,
     color_texture_page: TexturePageId,
+    color_texture_alpha_type: AlphaType,
+    custom_paint: Option<CustomPaintParams>,
+    clip_rect: Option<RectI>,
+    // The index of the clip path to apply with the stencil buffer, if any.
+    stencil_clip_path: Option<usize>,
 }
 
 #[derive(Debug)]
@@ -64,8 +161,27 @@ pub(crate) struct BuiltPath {
     pub mask_tiles: Vec<MaskTile>,
     pub alpha_tiles: Vec<AlphaTile>,
     pub solid_tiles: Vec<SolidTileInfo>,
-    pub tiles: DenseTileMap<TileObjectPrimitive>,
+    pub tiles: TileMap<TileObjectPrimitive>,
     pub fill_rule: FillRule,
+    // Set while a deterministic build is tiling the path, until its tiles are renumbered.
+    pub(crate) local_tile_indices: Option<LocalTileIndices>,
+}
+
+/// The alpha and mask tiles that a path took while being tiled in a deterministic build.
+///
+/// Tiles are numbered from zero within the path, and offset by the tiles taken by the paths before
+/// it once every path has been tiled, so that the numbering doesn't depend on the order that the
+/// worker threads got to the paths in.
+#[derive(Debug, Default)]
+pub(crate) struct LocalTileIndices {
+    // The coordinates of the tile given each alpha tile index.
+    alpha_tile_coords: Vec<Vector2I>,
+    mask_tile_count: u16,
+    // The mask tile index of each mask tile, along with its alpha tile index if that's one of
+    // ours. Alpha tiles are pushed one per mask tile index, in order, so they need no record.
+    mask_tiles: Vec<(u16, Option<u16>)>,
+    // Fills are held back until the path's tiles are renumbered, and then sent in path order.
+    fills: Vec<FillBatchPrimitive>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -78,21 +194,59 @@ impl<'a> SceneBuilder<'a> {
         scene: &'a Scene,
         built_options: &'a PreparedBuildOptions,
         listener: Box<dyn RenderCommandListener>,
+    ) -> SceneBuilder<'a> {
+        SceneBuilder::with_viewports(scene, slice::from_ref(built_options), false, listener)
+    }
+
+    // Builds each of `viewports` in turn into one frame. There must be at least one. If `layered`
+    // is set, each is drawn into the layer of the destination with its index.
+    pub(crate) fn with_viewports(
+        scene: &'a Scene,
+        viewports: &'a [PreparedBuildOptions],
+        layered: bool,
+        listener: Box<dyn RenderCommandListener>,
     ) -> SceneBuilder<'a> {
         SceneBuilder {
             scene,
-            built_options,
+            viewports,
+            built_options: &viewports[0],
+            layered,
+            chunk_origin: tiles::chunk_origin(union_view_box(scene, viewports)),
 
             next_alpha_tile_index: AtomicUsize::new(0),
             next_mask_tile_index: AtomicUsize::new(0),
+            next_culled_mask_tile_index: 0,
+            culled_path_count: AtomicUsize::new(0),
 
             listener,
         }
     }
 
-    pub fn build<E>(&mut self, executor: &E) where E: Executor {
+    pub fn build<E>(&mut self, executor: &E) -> Result<(), Error> where E: Executor {
+        trace_span!("build");
         let start_time = Instant::now();
 
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        // Check everything that can fail before sending any commands, so that the listener sees
+        // either a whole scene or nothing. Tile positions are relative to the same chunk in every
+        // viewport, so all of them together must fit in the range of tile coordinates too.
+        let viewports = self.viewports;
+        let effective_view_boxes: Vec<_> = viewports.iter().map(|options| {
+            self.scene.effective_view_box(options)
+        }).collect();
+        if !effective_view_boxes.iter().all(|&view_box| view_box_is_valid(view_box)) ||
+                !view_box_is_valid(union_view_box(self.scene, viewports)) {
+            return Err(Error::InvalidViewBox(self.scene.view_box()));
+        }
+        let PaintInfo {
+            render_commands,
+            paint_metadata,
+            render_target_metadata,
+        } = self.scene.build_paint_info()?;
+
         // Send the start rendering command.
         let bounding_quad = self.built_options.bounding_quad();
 
@@ -106,40 +260,134 @@ impl<'a> SceneBuilder<'a> {
             bounding_quad,
             path_count: total_path_count,
             needs_readable_framebuffer,
+            chunk_origin: self.chunk_origin,
         });
 
-        // Build paint data.
-        let PaintInfo {
-            render_commands,
-            paint_metadata,
-            render_target_metadata,
-        } = self.scene.build_paint_info();
+        // Send paint data.
         for render_command in render_commands {
             self.listener.send(render_command);
         }
 
-        let effective_view_box = self.scene.effective_view_box(self.built_options);
+        let stencil_clipped_paths = self.stencil_clipped_paths(needs_readable_framebuffer);
+        let mut stencil_clip_paths = vec![false; clip_path_count];
+        for (path, &stencil_clipped) in self.scene.paths.iter().zip(&stencil_clipped_paths) {
+            if stencil_clipped {
+                stencil_clip_paths[path.clip_path().unwrap().0 as usize] = true;
+            }
+        }
 
-        let built_clip_paths = executor.build_vector(clip_path_count, |path_index| {
-            self.build_clip_path(path_index, effective_view_box, &self.built_options, &self.scene)
-        });
+        let reused_viewports = self.plan_reused_viewports(&effective_view_boxes);
+        // The display lists of the viewports that later ones reuse.
+        let mut reusable_display_lists = vec![None; viewports.len()];
 
-        let built_draw_paths = executor.build_vector(draw_path_count, |path_index| {
-            self.build_draw_path(path_index,
-                                 effective_view_box,
-                                 &self.built_options,
-                                 &self.scene,
-                                 &paint_metadata,
-                                 &built_clip_paths)
-        });
+        for (viewport_index, built_options) in viewports.iter().enumerate() {
+            if self.layered {
+                self.listener.send(RenderCommand::BeginLayer {
+                    layer: viewport_index as u32,
+                    bounding_quad: built_options.bounding_quad(),
+                });
+            }
+
+            if let Some((source_index, tile_offset)) = reused_viewports[viewport_index] {
+                let display_list: &Vec<_> = reusable_display_lists[source_index].as_ref().unwrap();
+                self.send_translated_display_list(display_list, tile_offset);
+                continue;
+            }
+
+            self.built_options = built_options;
+            let effective_view_box = effective_view_boxes[viewport_index];
+
+            let (mut built_clip_paths, clip_rects): (Vec<_>, Vec<_>) =
+                executor.build_vector(clip_path_count, |path_index| {
+                    self.build_clip_path(path_index,
+                                         effective_view_box,
+                                         &self.built_options,
+                                         &self.scene,
+                                         stencil_clip_paths[path_index])
+                }).into_iter().unzip();
+
+            if self.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            // Draw paths refer to their clip paths' alpha tiles, so those need their final
+            // indices first.
+            self.assign_tile_indices(built_clip_paths.iter_mut());
+
+            let context = DrawPathContext {
+                view_box: effective_view_box,
+                built_options: &self.built_options,
+                scene: &self.scene,
+                paint_metadata: &paint_metadata,
+                built_clip_paths: &built_clip_paths,
+                clip_rects: &clip_rects,
+            };
+            let mut built_draw_paths = executor.build_vector(draw_path_count, |path_index| {
+                self.build_draw_path(path_index, &context, stencil_clipped_paths[path_index])
+            });
+            if self.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            self.assign_tile_indices(built_draw_paths.iter_mut()
+                                                     .map(|draw_path| &mut draw_path.path));
 
-        self.finish_building(&paint_metadata,
-                             &render_target_metadata,
-                             built_clip_paths,
-                             built_draw_paths);
+            let reused = reused_viewports.iter().any(|reused_viewport| {
+                reused_viewport.map(|(source_index, _)| source_index) == Some(viewport_index)
+            });
+            reusable_display_lists[viewport_index] =
+                self.finish_building(&paint_metadata,
+                                     &render_target_metadata,
+                                     built_clip_paths,
+                                     built_draw_paths,
+                                     reused,
+                                     executor);
+        }
 
         let build_time = Instant::now() - start_time;
-        self.listener.send(RenderCommand::Finish { build_time });
+        let culled_path_count = self.culled_path_count.load(Ordering::Relaxed);
+        self.listener.send(RenderCommand::Finish { build_time, culled_path_count });
+        Ok(())
+    }
+
+    // Tiles one draw path against its clip path, as `build()` would in the first viewport, and
+    // returns the tiles it covers. Only the path's fills are sent to the listener.
+    pub(crate) fn build_path_tiles(&self, path_index: usize) -> Result<PathTiles, Error> {
+        let view_box = self.scene.effective_view_box(self.built_options);
+        if !view_box_is_valid(view_box) {
+            return Err(Error::InvalidViewBox(self.scene.view_box()));
+        }
+        let PaintInfo { paint_metadata, .. } = self.scene.build_paint_info()?;
+
+        let (built_clip_paths, clip_rects): (Vec<_>, Vec<_>) =
+            (0..self.scene.clip_paths.len()).map(|clip_path_index| {
+                self.build_clip_path(clip_path_index,
+                                     view_box,
+                                     self.built_options,
+                                     self.scene,
+                                     false)
+            }).unzip();
+        let context = DrawPathContext {
+            view_box,
+            built_options: self.built_options,
+            scene: self.scene,
+            paint_metadata: &paint_metadata,
+            built_clip_paths: &built_clip_paths,
+            clip_rects: &clip_rects,
+        };
+        let built_draw_path = self.build_draw_path(path_index, &context, false);
+
+        let built_path = &built_draw_path.path;
+        let path_tiles = PathTiles {
+            alpha_tiles: built_path.alpha_tiles.iter().map(|alpha_tile| {
+                alpha_tile.upper_left.tile_position() + self.chunk_origin
+            }).collect(),
+            solid_tiles: built_path.solid_tiles.iter().map(|solid_tile| {
+                solid_tile.coords
+            }).collect(),
+        };
+        self.build_pool().recycle(built_clip_paths.into_iter()
+                                                  .chain(iter::once(built_draw_path.path)));
+        Ok(path_tiles)
     }
 
     fn build_clip_path(
@@ -148,9 +396,56 @@ impl<'a> SceneBuilder<'a> {
         view_box: RectF,
         built_options: &PreparedBuildOptions,
         scene: &Scene,
-    ) -> BuiltPath {
+        needs_stencil_tiles: bool,
+    ) -> (BuiltPath, Option<RectI>) {
+        let (mut built_path, clip_rect) =
+            self.tile_clip_path(path_index, view_box, built_options, scene);
+
+        // Paths clipped to a rect use a scissor rect instead of the stencil buffer.
+        if needs_stencil_tiles && clip_rect.is_none() {
+            self.pack_clip_stencil_tiles(&mut built_path, path_index as u16);
+        }
+
+        (built_path, clip_rect)
+    }
+
+    fn tile_clip_path(
+        &self,
+        path_index: usize,
+        view_box: RectF,
+        built_options: &PreparedBuildOptions,
+        scene: &Scene,
+    ) -> (BuiltPath, Option<RectI>) {
+        trace_span!("tile_clip_path", path_index = path_index);
         let path_object = &scene.clip_paths[path_index];
-        let outline = scene.apply_render_options(path_object.outline(), built_options);
+
+        // Once the build is cancelled, the remaining paths are tiled as if empty, and the cache
+        // is left alone. So are paths outside the view box.
+        let skipped = self.is_cancelled() ||
+            self.cull_path(path_object.outline().bounds(), view_box, built_options);
+        let cache_key = if skipped { None } else { ClipCacheKey::new(view_box, built_options) };
+        if let Some(cache_key) = cache_key {
+            if let Some(cached) = scene.clip_cache.take(path_index, cache_key) {
+                let built_path = self.reuse_tiling(&cached.tiling,
+                                                   path_object.fill_rule(),
+                                                   Vector2I::default());
+                let clip_rect = cached.clip_rect;
+                scene.clip_cache.insert(path_index, cached);
+                return (built_path, clip_rect);
+            }
+        }
+
+        let mut outline = if skipped {
+            Outline::new()
+        } else {
+            scene.apply_render_options(path_object.outline(), built_options)
+        };
+
+        // Pixel-aligned rectangles are clipped to with a scissor rect, so they need no tiles.
+        let clip_rect = outline_as_pixel_rect(&outline);
+        if clip_rect.is_some() {
+            outline = Outline::new();
+        }
 
         let mut tiler = Tiler::new(self,
                                    &outline,
@@ -158,29 +453,201 @@ impl<'a> SceneBuilder<'a> {
                                    view_box,
                                    path_index as u16,
                                    TilingPathInfo::Clip);
+        if cache_key.is_some() {
+            tiler.object_builder.record_fills();
+        }
 
         tiler.generate_tiles();
 
-        self.listener.send(RenderCommand::AddFills(tiler.object_builder.fills));
-        tiler.object_builder.built_path
+        tiler.object_builder.send_fills(self);
+        if let Some(cache_key) = cache_key {
+            let cached = CachedClipPath {
+                key: cache_key,
+                clip_rect,
+                tiling: CachedTiling::new(&mut tiler.object_builder),
+            };
+            scene.clip_cache.insert(path_index, cached);
+        }
+        (tiler.object_builder.built_path, clip_rect)
+    }
+
+    // Rebuilds a path from a cached tiling, moved by a number of tiles, with freshly allocated
+    // alpha tiles.
+    fn reuse_tiling(&self, cached: &CachedTiling, fill_rule: FillRule, tile_offset: Vector2I)
+                    -> BuiltPath {
+        let tile_size = Vector2I::new(TILE_WIDTH as i32, TILE_HEIGHT as i32);
+        let bounds = RectF::new(cached.bounds.origin() + tile_offset.scale_xy(tile_size).to_f32(),
+                                cached.bounds.size());
+        let mut built_path = self.new_built_path(bounds, fill_rule);
+
+        // The cached alpha tiles are already numbered from zero, as deterministic builds want.
+        if let Some(ref mut local_tile_indices) = built_path.local_tile_indices {
+            let alpha_tile_coords = &mut local_tile_indices.alpha_tile_coords;
+            alpha_tile_coords.resize(cached.alpha_tile_count, Vector2I::default());
+            for &(tile_coords, tile) in &cached.tiles {
+                let tile_coords = tile_coords + tile_offset;
+                if tile.alpha_tile_index != !0 {
+                    alpha_tile_coords[tile.alpha_tile_index as usize] = tile_coords;
+                }
+                *built_path.tiles.get_mut(tile_coords).unwrap() = tile;
+            }
+            local_tile_indices.fills.extend_from_slice(&cached.fills);
+            return built_path;
+        }
+
+        // FIXME(pcwalton): Check for overflow!
+        let alpha_tile_base =
+            self.next_alpha_tile_index.fetch_add(cached.alpha_tile_count, Ordering::Relaxed) as u16;
+
+        for &(tile_coords, mut tile) in &cached.tiles {
+            if tile.alpha_tile_index != !0 {
+                tile.alpha_tile_index += alpha_tile_base;
+            }
+            *built_path.tiles.get_mut(tile_coords + tile_offset).unwrap() = tile;
+        }
+
+        self.send_fills(&cached.fills, alpha_tile_base);
+        built_path
+    }
+
+    // Sends fills numbered from zero, offsetting them to the given first alpha tile.
+    fn send_fills(&self, fills: &[FillBatchPrimitive], alpha_tile_base: u16) {
+        for fills in fills.chunks(self.built_options.max_fills_per_command) {
+            self.listener.send(RenderCommand::AddFills(fills.iter().map(|fill| {
+                FillBatchPrimitive {
+                    alpha_tile_index: fill.alpha_tile_index + alpha_tile_base,
+                    ..*fill
+                }
+            }).collect()));
+        }
+    }
+
+    // Gives the tiles of paths tiled by a deterministic build their final indices, in path order,
+    // and sends their fills. Paths tiled otherwise are left alone.
+    fn assign_tile_indices<'b, I>(&self, built_paths: I)
+                                  where I: Iterator<Item = &'b mut BuiltPath> {
+        for built_path in built_paths {
+            let local_tile_indices = match built_path.local_tile_indices.take() {
+                None => continue,
+                Some(local_tile_indices) => local_tile_indices,
+            };
+
+            // FIXME(pcwalton): Check for overflow!
+            let alpha_tile_count = local_tile_indices.alpha_tile_coords.len();
+            let alpha_tile_base =
+                self.next_alpha_tile_index.fetch_add(alpha_tile_count, Ordering::Relaxed) as u16;
+            let mask_tile_count = local_tile_indices.mask_tile_count as usize;
+            let mask_tile_base =
+                self.next_mask_tile_index.fetch_add(mask_tile_count, Ordering::Relaxed) as u16;
+
+            for &tile_coords in &local_tile_indices.alpha_tile_coords {
+                built_path.tiles.get_mut(tile_coords).unwrap().alpha_tile_index += alpha_tile_base;
+            }
+
+            for (mask_tile, &(mask_tile_index, alpha_tile_index)) in
+                    built_path.mask_tiles.iter_mut().zip(&local_tile_indices.mask_tiles) {
+                mask_tile.renumber(mask_tile_index + mask_tile_base,
+                                   alpha_tile_index.map(|index| index + alpha_tile_base));
+            }
+
+            debug_assert_eq!(built_path.alpha_tiles.len(), mask_tile_count);
+            for (mask_tile_index, alpha_tile) in built_path.alpha_tiles.iter_mut().enumerate() {
+                alpha_tile.renumber(mask_tile_index as u16 + mask_tile_base);
+            }
+
+            self.send_fills(&local_tile_indices.fills, alpha_tile_base);
+        }
+    }
+
+    // Allocates mask tiles for a clip path, along with tiles to draw its area into the stencil
+    // buffer through them.
+    fn pack_clip_stencil_tiles(&self, built_path: &mut BuiltPath, object_index: u16) {
+        let BuiltPath {
+            ref tiles,
+            ref mut mask_tiles,
+            ref mut alpha_tiles,
+            ref mut local_tile_indices,
+            fill_rule,
+            ..
+        } = *built_path;
+        for (tile_coords, tile) in tiles.iter() {
+            if tile.is_solid() {
+                match (fill_rule, tile.backdrop) {
+                    (FillRule::Winding, 0) => continue,
+                    (FillRule::EvenOdd, backdrop) if backdrop % 2 == 0 => continue,
+                    _ => {}
+                }
+            }
+
+            let mask_tile_index = self.allocate_mask_tile_index(local_tile_indices);
+            ObjectBuilder::push_mask_tile(mask_tiles,
+                                          local_tile_indices,
+                                          tile,
+                                          true,
+                                          mask_tile_index,
+                                          object_index);
+            ObjectBuilder::push_clip_stencil_tile(alpha_tiles,
+                                                  mask_tile_index,
+                                                  tile_coords,
+                                                  self.chunk_origin,
+                                                  object_index);
+        }
     }
 
     fn build_draw_path(
         &self,
         path_index: usize,
-        view_box: RectF,
-        built_options: &PreparedBuildOptions,
-        scene: &Scene,
-        paint_metadata: &[PaintMetadata],
-        built_clip_paths: &[BuiltPath],
+        context: &DrawPathContext,
+        stencil_clipped: bool,
     ) -> BuiltDrawPath {
+        trace_span!("tile_draw_path", path_index = path_index);
+        let DrawPathContext {
+            view_box,
+            built_options,
+            scene,
+            paint_metadata,
+            built_clip_paths,
+            clip_rects,
+        } = *context;
         let path_object = &scene.paths[path_index];
-        let outline = scene.apply_render_options(path_object.outline(), built_options);
+        let original_outline = path_object.outline_for_scale(built_options.lod_scale());
+        let visible = path_object.is_visible() &&
+            !self.is_cancelled() &&
+            !self.cull_path(original_outline.bounds(), view_box, built_options);
+
+        // A path played from a picture may be able to reuse a tiling of the same picture path.
+        let picture_tiling = match path_object.picture_path {
+            Some(ref picture_path) if visible => {
+                let bounds = original_outline.bounds();
+                PictureTilingKey::new(picture_path, bounds, view_box, built_options)
+                    .map(|key| (picture_path, key))
+            }
+            _ => None,
+        };
+        let cached_tiling = picture_tiling.and_then(|(picture_path, ref key)| {
+            picture_path.tile_cache.find(picture_path.path_index, key)
+        });
+
+        let outline = if visible && cached_tiling.is_none() {
+            scene.apply_render_options(original_outline, built_options)
+        } else {
+            Outline::new()
+        };
 
         let paint_id = path_object.paint();
         let paint_metadata = &paint_metadata[paint_id.0 as usize];
-        let built_clip_path =
-            path_object.clip_path().map(|clip_path_id| &built_clip_paths[clip_path_id.0 as usize]);
+        let clip_rect =
+            path_object.clip_path().and_then(|clip_path_id| clip_rects[clip_path_id.0 as usize]);
+        let built_clip_path = match clip_rect {
+            Some(_) => None,
+            None => path_object.clip_path().map(|clip_path_id| {
+                &built_clip_paths[clip_path_id.0 as usize]
+            }),
+        };
+        let stencil_clip_path = match built_clip_path {
+            Some(_) if stencil_clipped => path_object.clip_path().map(|id| id.0 as usize),
+            _ => None,
+        };
 
         let mut tiler = Tiler::new(self,
                                    &outline,
@@ -192,164 +659,357 @@ impl<'a> SceneBuilder<'a> {
             blend_mode: path_object.blend_mode(),
             opacity: path_object.opacity(),
             built_clip_path,
+            clip_rect,
+            stencil_clip: stencil_clip_path.is_some(),
         }));
 
-        tiler.generate_tiles();
+        match cached_tiling {
+            Some((ref cached_tiling, tile_offset)) => {
+                let built_path =
+                    self.reuse_tiling(cached_tiling, path_object.fill_rule(), tile_offset);
+                let empty_path = mem::replace(&mut tiler.object_builder.built_path, built_path);
+                self.build_pool().recycle(iter::once(empty_path));
+                tiler.pack_and_cull();
+            }
+            None => {
+                if picture_tiling.is_some() {
+                    tiler.object_builder.record_fills();
+                }
+                tiler.generate_tiles();
+            }
+        }
 
-        self.listener.send(RenderCommand::AddFills(tiler.object_builder.fills));
+        tiler.object_builder.send_fills(self);
+        if let Some((picture_path, key)) = picture_tiling {
+            if cached_tiling.is_none() {
+                let cached_tiling = CachedTiling::new(&mut tiler.object_builder);
+                picture_path.tile_cache.insert(picture_path.path_index, key, cached_tiling);
+            }
+        }
 
         BuiltDrawPath {
             path: tiler.object_builder.built_path,
             blend_mode: path_object.blend_mode(),
             color_texture_page: paint_metadata.location.page,
+            color_texture_alpha_type: paint_metadata.alpha_type,
             sampling_flags: paint_metadata.sampling_flags,
+            custom_paint: paint_metadata.custom_paint,
+            clip_rect,
+            stencil_clip_path,
+        }
+    }
+
+    // Returns true, counting the path as culled, if an outline with the given bounds in the scene
+    // lies entirely outside the view box once transformed, which saves transforming and tiling
+    // it. Paths in perspective are left to be clipped.
+    fn cull_path(&self, bounds: RectF, view_box: RectF, built_options: &PreparedBuildOptions)
+                 -> bool {
+        let transform = match built_options.tiling_transform_2d() {
+            None => return false,
+            Some(transform) => transform,
+        };
+        if (transform * bounds).dilate(built_options.dilation).intersects(view_box) {
+            return false;
         }
+        self.culled_path_count.fetch_add(1, Ordering::Relaxed);
+        true
     }
 
-    fn cull_tiles(&self,
-                  paint_metadata: &[PaintMetadata],
-                  render_target_metadata: &[RenderTargetMetadata],
-                  built_clip_paths: Vec<BuiltPath>,
-                  built_draw_paths: Vec<BuiltDrawPath>)
-                  -> CulledTiles {
+    // Culling happens in three passes. First, a sequential walk over the display list works out
+    // which Z-buffer and depth each item is tested against. Then the culling itself runs on the
+    // executor, one job per item. Finally, the results are assembled into batches in display list
+    // order, so that the output doesn't depend on how the jobs were scheduled.
+    fn cull_tiles<E>(&self,
+                     paint_metadata: &[PaintMetadata],
+                     render_target_metadata: &[RenderTargetMetadata],
+                     mut built_clip_paths: Vec<BuiltPath>,
+                     built_draw_paths: Vec<BuiltDrawPath>,
+                     next_mask_tile_index: &mut usize,
+                     executor: &E)
+                     -> CulledTiles
+                     where E: Executor {
+        trace_span!("cull_tiles");
         let mut culled_tiles = CulledTiles {
             mask_winding_tiles: vec![],
             mask_evenodd_tiles: vec![],
             display_list: vec![],
         };
 
-        for built_clip_path in built_clip_paths {
-            culled_tiles.push_mask_tiles(&built_clip_path);
-        }
-
-        let mut remaining_layer_z_buffers = self.build_solid_tiles(&built_draw_paths);
-        remaining_layer_z_buffers.reverse();
-
-        // Process first Z-buffer.
-        let first_z_buffer = remaining_layer_z_buffers.pop().unwrap();
-        let first_solid_tiles = first_z_buffer.build_solid_tiles(paint_metadata);
-        for batch in first_solid_tiles.batches {
-            culled_tiles.display_list.push(CulledDisplayItem::DrawSolidTiles(batch));
+        // Mask tiles are numbered again as they're pushed, now that culling has thrown out the
+        // alpha tiles that can't be seen, so that only visible tiles take up room in the mask
+        // framebuffer. Clip paths' stencil tiles come first.
+        for built_clip_path in &mut built_clip_paths {
+            let mut alpha_tiles = mem::take(&mut built_clip_path.alpha_tiles);
+            culled_tiles.push_mask_tiles(built_clip_path,
+                                         &mut alpha_tiles,
+                                         next_mask_tile_index);
+            built_clip_path.alpha_tiles = alpha_tiles;
         }
 
-        let mut layer_z_buffers_stack = vec![first_z_buffer];
-        let mut current_depth = 1;
+        let z_buffers = self.build_solid_tiles(&built_draw_paths);
+        let jobs = self.plan_culling_jobs();
+        let results = executor.build_vector(jobs.len(), |job_index| {
+            self.run_culling_job(jobs[job_index],
+                                 &z_buffers,
+                                 &built_draw_paths,
+                                 paint_metadata,
+                                 render_target_metadata)
+        });
 
-        for display_item in &self.scene.display_list {
-            match *display_item {
-                DisplayItem::PushRenderTarget(render_target_id) => {
+        let dual_source_blending = self.built_options.dual_source_blending;
+        let advanced_blending = self.built_options.advanced_blending;
+        // Alpha tiles may not be moved before this display list index.
+        let mut batch_floor = 0;
+        // The clip path last drawn to the stencil buffer.
+        let mut current_stencil_clip_path = None;
+        // For batch merging: the index of the last display item drawing to each tile.
+        let effective_view_box = self.scene.effective_view_box(self.built_options);
+        let mut last_batch_for_tile =
+            DenseTileMap::with_buffer(tiles::round_rect_out_to_tile_bounds(effective_view_box),
+                                      vec![]);
+
+        // The culling jobs' alpha tile vectors, to hand back to the pool once they're copied.
+        let mut job_alpha_tiles = vec![];
+        for (job, result) in jobs.iter().zip(results) {
+            match (*job, result) {
+                (CullingJob::PushRenderTarget(render_target_id), _) => {
                     culled_tiles.display_list
                                 .push(CulledDisplayItem::PushRenderTarget(render_target_id));
-
-                    let z_buffer = remaining_layer_z_buffers.pop().unwrap();
-                    let solid_tiles = z_buffer.build_solid_tiles(paint_metadata);
-                    for batch in solid_tiles.batches {
-                        culled_tiles.display_list.push(CulledDisplayItem::DrawSolidTiles(batch));
-                    }
-                    layer_z_buffers_stack.push(z_buffer);
+                    batch_floor = culled_tiles.display_list.len();
                 }
-
-                DisplayItem::PopRenderTarget => {
+                (CullingJob::PopRenderTarget, _) => {
                     culled_tiles.display_list.push(CulledDisplayItem::PopRenderTarget);
-                    layer_z_buffers_stack.pop();
+                    batch_floor = culled_tiles.display_list.len();
                 }
-
-                DisplayItem::DrawRenderTarget { render_target, effects } => {
-                    let effective_view_box = self.scene.effective_view_box(self.built_options);
-                    let tile_rect = tiles::round_rect_out_to_tile_bounds(effective_view_box);
-                    let layer_z_buffer = layer_z_buffers_stack.last().unwrap();
-                    let mut tiles = vec![];
-                    let uv_scale = Vector2F::splat(1.0) / tile_rect.lower_right().to_f32();
-                    let metadata = &render_target_metadata[render_target.0 as usize];
-                    for tile_y in tile_rect.min_y()..tile_rect.max_y() {
-                        for tile_x in tile_rect.min_x()..tile_rect.max_x() {
-                            let tile_coords = Vector2I::new(tile_x, tile_y);
-                            if !layer_z_buffer.test(tile_coords, current_depth) {
-                                continue;
-                            }
-
-                            let uv_rect =
-                                RectI::new(tile_coords, Vector2I::splat(1)).to_f32()
-                                                                           .scale_xy(uv_scale);
-                            tiles.push(SolidTile::from_texture_rect(tile_coords, uv_rect));
+                (_, CullingResult::SolidTiles(batches)) => {
+                    for batch in batches {
+                        culled_tiles.display_list.push(CulledDisplayItem::DrawSolidTiles(batch));
+                    }
+                    batch_floor = culled_tiles.display_list.len();
+                }
+                (CullingJob::DrawPath { path_index, .. },
+                 CullingResult::AlphaTiles(mut alpha_tiles)) => {
+                    let built_draw_path = &built_draw_paths[path_index];
+                    culled_tiles.push_mask_tiles(&built_draw_path.path,
+                                                 &mut alpha_tiles,
+                                                 next_mask_tile_index);
+
+                    // Switch stencil clips if necessary. Nothing may move across the switch.
+                    let stencil_clip_path = built_draw_path.stencil_clip_path;
+                    if let Some(clip_path_index) = stencil_clip_path {
+                        if stencil_clip_path != current_stencil_clip_path &&
+                                !alpha_tiles.is_empty() {
+                            let clip_tiles =
+                                built_clip_paths[clip_path_index].alpha_tiles.clone();
+                            culled_tiles.display_list
+                                        .push(CulledDisplayItem::DrawClipStencil(clip_tiles));
+                            current_stencil_clip_path = stencil_clip_path;
+                            batch_floor = culled_tiles.display_list.len();
                         }
                     }
-                    let batch = SolidTileBatch {
-                        tiles,
-                        color_texture_page: metadata.location.page,
-                        sampling_flags: TextureSamplingFlags::empty(),
-                        effects,
+
+                    // Find the earliest batch this path could join. Ordinarily that's only the
+                    // last one. When merging batches, it's the last one to draw to any of this
+                    // path's tiles, since the path has to stay on top of those.
+                    let display_list_len = culled_tiles.display_list.len();
+                    let first_candidate = if self.built_options.merge_batches {
+                        alpha_tiles.iter().filter_map(|alpha_tile| {
+                            let tile_coords =
+                                alpha_tile.upper_left.tile_position() + self.chunk_origin;
+                            last_batch_for_tile.get(tile_coords)
+                        }).fold(batch_floor, |floor, &batch_index| cmp::max(floor, batch_index))
+                    } else {
+                        cmp::max(batch_floor, display_list_len.saturating_sub(1))
                     };
-                    culled_tiles.display_list.push(CulledDisplayItem::DrawSolidTiles(batch));
-                    current_depth += 1;
-                }
 
-                DisplayItem::DrawPaths {
-                    start_index: start_draw_path_index,
-                    end_index: end_draw_path_index,
-                } => {
-                    for draw_path_index in start_draw_path_index..end_draw_path_index {
-                        let built_draw_path = &built_draw_paths[draw_path_index as usize];
-                        culled_tiles.push_mask_tiles(&built_draw_path.path);
-
-                        // Create a new `DrawAlphaTiles` display item if we don't have one or if we
-                        // have to break a batch due to blend mode or paint page. Note that every
-                        // path with a blend mode that requires a readable framebuffer needs its
-                        // own batch.
-                        //
-                        // TODO(pcwalton): If we really wanted to, we could use tile maps to avoid
-                        // batch breaks in some cases…
-                        match culled_tiles.display_list.last() {
-                            Some(&CulledDisplayItem::DrawAlphaTiles(AlphaTileBatch {
+                    // Create a new `DrawAlphaTiles` display item if there's no candidate with the
+                    // same blend mode, paint page, custom paint, and clip. Note that every path
+                    // with a blend mode that requires a readable framebuffer needs its own batch.
+                    let candidate = (first_candidate..display_list_len).rev().find(|&index| {
+                        match culled_tiles.display_list[index] {
+                            CulledDisplayItem::DrawAlphaTiles(AlphaTileBatch {
                                 tiles: _,
                                 color_texture_page,
+                                custom_paint,
                                 blend_mode,
-                                sampling_flags
-                            })) if color_texture_page == built_draw_path.color_texture_page &&
-                                blend_mode == built_draw_path.blend_mode &&
-                                sampling_flags == built_draw_path.sampling_flags &&
-                                !BlendModeProgram::from_blend_mode(
-                                    blend_mode).needs_readable_framebuffer() => {}
-                            _ => {
-                                let batch = AlphaTileBatch {
-                                    tiles: vec![],
-                                    color_texture_page: built_draw_path.color_texture_page,
-                                    blend_mode: built_draw_path.blend_mode,
-                                    sampling_flags: built_draw_path.sampling_flags,
-                                };
-                                culled_tiles.display_list
-                                            .push(CulledDisplayItem::DrawAlphaTiles(batch))
+                                sampling_flags,
+                                color_texture_alpha_type,
+                                scissor,
+                                stencil_clipped,
+                            }) => {
+                                color_texture_page == built_draw_path.color_texture_page &&
+                                    color_texture_alpha_type ==
+                                        built_draw_path.color_texture_alpha_type &&
+                                    custom_paint == built_draw_path.custom_paint &&
+                                    blend_mode == built_draw_path.blend_mode &&
+                                    sampling_flags == built_draw_path.sampling_flags &&
+                                    scissor == built_draw_path.clip_rect &&
+                                    stencil_clipped == stencil_clip_path.is_some() &&
+                                    !BlendModeProgram::from_blend_mode(blend_mode)
+                                        .needs_readable_framebuffer(dual_source_blending,
+                                                                    advanced_blending)
                             }
+                            _ => false,
+                        }
+                    });
+                    let batch_index = match candidate {
+                        Some(batch_index) => batch_index,
+                        None => {
+                            let batch = AlphaTileBatch {
+                                tiles: vec![],
+                                color_texture_page: built_draw_path.color_texture_page,
+                                custom_paint: built_draw_path.custom_paint,
+                                blend_mode: built_draw_path.blend_mode,
+                                sampling_flags: built_draw_path.sampling_flags,
+                                color_texture_alpha_type:
+                                    built_draw_path.color_texture_alpha_type,
+                                scissor: built_draw_path.clip_rect,
+                                stencil_clipped: stencil_clip_path.is_some(),
+                            };
+                            culled_tiles.display_list
+                                        .push(CulledDisplayItem::DrawAlphaTiles(batch));
+                            display_list_len
                         }
+                    };
 
-                        // Fetch the destination alpha tiles buffer.
-                        let culled_alpha_tiles = match *culled_tiles.display_list
-                                                                    .last_mut()
-                                                                    .unwrap() {
-                            CulledDisplayItem::DrawAlphaTiles(AlphaTileBatch {
-                                tiles: ref mut culled_alpha_tiles,
-                                ..
-                            }) => culled_alpha_tiles,
-                            _ => unreachable!(),
-                        };
-
-                        let layer_z_buffer = layer_z_buffers_stack.last().unwrap();
-                        for alpha_tile in &built_draw_path.path.alpha_tiles {
-                            let alpha_tile_coords = alpha_tile.upper_left.tile_position();
-                            if layer_z_buffer.test(alpha_tile_coords, current_depth) {
-                                culled_alpha_tiles.push(*alpha_tile);
+                    if self.built_options.merge_batches {
+                        for alpha_tile in &alpha_tiles {
+                            let tile_coords =
+                                alpha_tile.upper_left.tile_position() + self.chunk_origin;
+                            if let Some(index) = last_batch_for_tile.coords_to_index(tile_coords) {
+                                last_batch_for_tile.data[index] = batch_index;
                             }
                         }
+                    }
 
-                        current_depth += 1;
+                    // Append to the destination alpha tiles buffer.
+                    match culled_tiles.display_list[batch_index] {
+                        CulledDisplayItem::DrawAlphaTiles(AlphaTileBatch {
+                            tiles: ref mut culled_alpha_tiles,
+                            ..
+                        }) => culled_alpha_tiles.extend_from_slice(&alpha_tiles),
+                        _ => unreachable!(),
                     }
+                    job_alpha_tiles.push(alpha_tiles);
                 }
+                (_, CullingResult::AlphaTiles(_)) | (_, CullingResult::None) => unreachable!(),
             }
         }
 
+        let built_draw_paths = built_draw_paths.into_iter().map(|built_draw_path| {
+            built_draw_path.path
+        });
+        self.scene.build_pool.recycle(built_clip_paths.into_iter().chain(built_draw_paths));
+        self.scene.build_pool.recycle_alpha_tiles(job_alpha_tiles.into_iter());
+
         culled_tiles
     }
 
+    fn plan_culling_jobs(&self) -> Vec<CullingJob> {
+        // The first Z-buffer is for the main framebuffer; the rest are for render targets, in the
+        // order they're pushed.
+        let mut jobs = vec![CullingJob::LayerSolidTiles { z_buffer_index: 0 }];
+        let mut z_buffer_index_stack = vec![0];
+        let mut next_z_buffer_index = 1;
+        let mut current_depth = 1;
+
+        for display_item in &self.scene.display_list {
+            match *display_item {
+                DisplayItem::PushRenderTarget(render_target_id) => {
+                    jobs.push(CullingJob::PushRenderTarget(render_target_id));
+                    jobs.push(CullingJob::LayerSolidTiles { z_buffer_index: next_z_buffer_index });
+                    z_buffer_index_stack.push(next_z_buffer_index);
+                    next_z_buffer_index += 1;
+                }
+                DisplayItem::PopRenderTarget => {
+                    jobs.push(CullingJob::PopRenderTarget);
+                    z_buffer_index_stack.pop();
+                }
+                DisplayItem::DrawRenderTarget { render_target, effects } => {
+                    jobs.push(CullingJob::DrawRenderTarget {
+                        render_target,
+                        effects,
+                        z_buffer_index: *z_buffer_index_stack.last().unwrap(),
+                        depth: current_depth,
+                    });
+                    current_depth += 1;
+                }
+                DisplayItem::DrawPaths { start_index, end_index } => {
+                    let z_buffer_index = *z_buffer_index_stack.last().unwrap();
+                    for path_index in start_index..end_index {
+                        jobs.push(CullingJob::DrawPath {
+                            path_index: path_index as usize,
+                            z_buffer_index,
+                            depth: current_depth,
+                        });
+                        current_depth += 1;
+                    }
+                }
+            }
+        }
+
+        jobs
+    }
+
+    fn run_culling_job(&self,
+                       job: CullingJob,
+                       z_buffers: &[ZBuffer],
+                       built_draw_paths: &[BuiltDrawPath],
+                       paint_metadata: &[PaintMetadata],
+                       render_target_metadata: &[RenderTargetMetadata])
+                       -> CullingResult {
+        match job {
+            CullingJob::PushRenderTarget(_) | CullingJob::PopRenderTarget => CullingResult::None,
+
+            CullingJob::LayerSolidTiles { z_buffer_index } => {
+                let solid_tiles = z_buffers[z_buffer_index].build_solid_tiles(paint_metadata,
+                                                                              self.chunk_origin);
+                CullingResult::SolidTiles(solid_tiles.batches)
+            }
+
+            CullingJob::DrawRenderTarget { render_target, effects, z_buffer_index, depth } => {
+                let effective_view_box = self.scene.effective_view_box(self.built_options);
+                let tile_rect = tiles::round_rect_out_to_tile_bounds(effective_view_box);
+                let layer_z_buffer = &z_buffers[z_buffer_index];
+                let mut tiles = vec![];
+                let uv_scale = Vector2F::splat(1.0) / tile_rect.lower_right().to_f32();
+                let metadata = &render_target_metadata[render_target.0 as usize];
+                for tile_y in tile_rect.min_y()..tile_rect.max_y() {
+                    for tile_x in tile_rect.min_x()..tile_rect.max_x() {
+                        let tile_coords = Vector2I::new(tile_x, tile_y);
+                        if !layer_z_buffer.test(tile_coords, depth) {
+                            continue;
+                        }
+
+                        let uv_rect =
+                            RectI::new(tile_coords, Vector2I::splat(1)).to_f32()
+                                                                       .scale_xy(uv_scale);
+                        tiles.push(SolidTile::from_texture_rect(tile_coords - self.chunk_origin,
+                                                                uv_rect));
+                    }
+                }
+                CullingResult::SolidTiles(vec![SolidTileBatch {
+                    tiles,
+                    color_texture_page: metadata.location.page,
+                    sampling_flags: TextureSamplingFlags::empty(),
+                    color_texture_alpha_type: AlphaType::Premultiplied,
+                    effects,
+                }])
+            }
+
+            CullingJob::DrawPath { path_index, z_buffer_index, depth } => {
+                let layer_z_buffer = &z_buffers[z_buffer_index];
+                let alpha_tiles = &built_draw_paths[path_index].path.alpha_tiles;
+                let mut visible_alpha_tiles = self.build_pool().alpha_tiles();
+                visible_alpha_tiles.extend(alpha_tiles.iter().filter(|alpha_tile| {
+                    let tile_coords = alpha_tile.upper_left.tile_position() + self.chunk_origin;
+                    layer_z_buffer.test(tile_coords, depth)
+                }));
+                CullingResult::AlphaTiles(visible_alpha_tiles)
+            }
+        }
+    }
+
     fn build_solid_tiles(&self, built_draw_paths: &[BuiltDrawPath]) -> Vec<ZBuffer> {
         let effective_view_box = self.scene.effective_view_box(self.built_options);
         let mut z_buffers = vec![ZBuffer::new(effective_view_box)];
@@ -390,7 +1050,16 @@ impl<'a> SceneBuilder<'a> {
         z_buffers
     }
 
-    fn pack_tiles(&mut self, culled_tiles: CulledTiles) {
+    // Sends the culled tiles, returning a copy of the display list if `keep_display_list` is set.
+    fn pack_tiles(&mut self, culled_tiles: CulledTiles, keep_display_list: bool)
+                  -> Option<Vec<CulledDisplayItem>> {
+        trace_span!("pack_tiles");
+        let kept_display_list = if keep_display_list {
+            Some(culled_tiles.display_list.clone())
+        } else {
+            None
+        };
+
         if !culled_tiles.mask_winding_tiles.is_empty() {
             self.listener.send(RenderCommand::RenderMaskTiles {
                 tiles: culled_tiles.mask_winding_tiles,
@@ -412,6 +1081,9 @@ impl<'a> SceneBuilder<'a> {
                 CulledDisplayItem::DrawAlphaTiles(batch) => {
                     self.listener.send(RenderCommand::DrawAlphaTiles(batch))
                 }
+                CulledDisplayItem::DrawClipStencil(tiles) => {
+                    self.listener.send(RenderCommand::DrawClipStencil(tiles))
+                }
                 CulledDisplayItem::PushRenderTarget(render_target_id) => {
                     self.listener.send(RenderCommand::PushRenderTarget(render_target_id))
                 }
@@ -420,27 +1092,178 @@ impl<'a> SceneBuilder<'a> {
                 }
             }
         }
+
+        kept_display_list
     }
 
-    fn finish_building(&mut self,
-                       paint_metadata: &[PaintMetadata],
-                       render_target_metadata: &[RenderTargetMetadata],
-                       built_clip_paths: Vec<BuiltPath>,
-                       built_draw_paths: Vec<BuiltDrawPath>) {
+    fn finish_building<E>(&mut self,
+                          paint_metadata: &[PaintMetadata],
+                          render_target_metadata: &[RenderTargetMetadata],
+                          built_clip_paths: Vec<BuiltPath>,
+                          built_draw_paths: Vec<BuiltDrawPath>,
+                          keep_display_list: bool,
+                          executor: &E)
+                          -> Option<Vec<CulledDisplayItem>>
+                          where E: Executor {
         self.listener.send(RenderCommand::FlushFills);
+        let mut next_mask_tile_index = self.next_culled_mask_tile_index;
         let culled_tiles = self.cull_tiles(paint_metadata,
                                            render_target_metadata,
                                            built_clip_paths,
-                                           built_draw_paths);
-        self.pack_tiles(culled_tiles);
+                                           built_draw_paths,
+                                           &mut next_mask_tile_index,
+                                           executor);
+        self.next_culled_mask_tile_index = next_mask_tile_index;
+        self.pack_tiles(culled_tiles, keep_display_list)
+    }
+
+    // Works out which viewports can draw the tiles of an earlier viewport again instead of being
+    // tiled, returning for each the index of that viewport and how many tiles to move its tiles
+    // by.
+    //
+    // A viewport qualifies if it shows exactly what the earlier one does at the same scale, moved
+    // by whole tiles. Moved tiles keep their paints' texture coordinates, and render targets are
+    // drawn in their own space, so only scenes painted with plain colors and without render
+    // targets can be reused.
+    fn plan_reused_viewports(&self, effective_view_boxes: &[RectF])
+                             -> Vec<Option<(usize, Vector2I)>> {
+        let mut reused_viewports = vec![None; self.viewports.len()];
+        if !self.scene.palette().render_targets.is_empty() ||
+                !self.scene.paths.iter().all(|path| self.scene.paint(path.paint()).is_color()) {
+            return reused_viewports;
+        }
+
+        for viewport_index in 1..self.viewports.len() {
+            reused_viewports[viewport_index] = (0..viewport_index).filter(|&source_index| {
+                reused_viewports[source_index].is_none()
+            }).find_map(|source_index| {
+                let tile_offset = self.viewport_tile_offset(source_index,
+                                                            viewport_index,
+                                                            effective_view_boxes)?;
+                Some((source_index, tile_offset))
+            });
+        }
+        reused_viewports
+    }
+
+    // Returns how many tiles the second viewport's tiles are moved by from the first's, if it
+    // shows the same part of the scene at the same scale, moved by whole tiles.
+    fn viewport_tile_offset(&self,
+                            source_index: usize,
+                            viewport_index: usize,
+                            effective_view_boxes: &[RectF])
+                            -> Option<Vector2I> {
+        let transform_2d = |options: &PreparedBuildOptions| {
+            match options.transform {
+                PreparedRenderTransform::None => Some(Transform2F::default()),
+                PreparedRenderTransform::Transform2D(transform) => Some(transform),
+                PreparedRenderTransform::Perspective { .. } => None,
+            }
+        };
+        let (source_options, options) = (&self.viewports[source_index],
+                                         &self.viewports[viewport_index]);
+        let (source_transform, transform) = (transform_2d(source_options)?,
+                                             transform_2d(options)?);
+        if options.subpixel_aa_enabled || transform.matrix != source_transform.matrix {
+            return None;
+        }
+
+        let tile_size = Vector2F::new(TILE_WIDTH as f32, TILE_HEIGHT as f32);
+        let offset = transform.vector - source_transform.vector;
+        let tile_offset = (offset / tile_size).to_i32();
+        let source_view_box = effective_view_boxes[source_index];
+        if tile_offset.to_f32() * tile_size != offset ||
+                RectF::new(source_view_box.origin() + offset, source_view_box.size()) !=
+                effective_view_boxes[viewport_index] {
+            return None;
+        }
+        Some(tile_offset)
+    }
+
+    // Draws the tiles of an earlier viewport again, moved by `tile_offset`. Their masks are still
+    // in the mask framebuffer, so only the draw commands are sent.
+    fn send_translated_display_list(&self,
+                                    display_list: &[CulledDisplayItem],
+                                    tile_offset: Vector2I) {
+        let tile_size = Vector2I::new(TILE_WIDTH as i32, TILE_HEIGHT as i32);
+        let pixel_offset = tile_offset.scale_xy(tile_size);
+        for display_item in display_list {
+            let command = match *display_item {
+                CulledDisplayItem::DrawSolidTiles(ref batch) => {
+                    let mut batch = batch.clone();
+                    for tile in &mut batch.tiles {
+                        tile.translate(tile_offset);
+                    }
+                    RenderCommand::DrawSolidTiles(batch)
+                }
+                CulledDisplayItem::DrawAlphaTiles(ref batch) => {
+                    let mut batch = batch.clone();
+                    for tile in &mut batch.tiles {
+                        tile.translate(tile_offset);
+                    }
+                    batch.scissor = batch.scissor.map(|scissor| {
+                        RectI::new(scissor.origin() + pixel_offset, scissor.size())
+                    });
+                    RenderCommand::DrawAlphaTiles(batch)
+                }
+                CulledDisplayItem::DrawClipStencil(ref tiles) => {
+                    let mut tiles = tiles.clone();
+                    for tile in &mut tiles {
+                        tile.translate(tile_offset);
+                    }
+                    RenderCommand::DrawClipStencil(tiles)
+                }
+                CulledDisplayItem::PushRenderTarget(render_target_id) => {
+                    RenderCommand::PushRenderTarget(render_target_id)
+                }
+                CulledDisplayItem::PopRenderTarget => RenderCommand::PopRenderTarget,
+            };
+            self.listener.send(command);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn build_pool(&self) -> &BuildPool {
+        &self.scene.build_pool
     }
 
-    pub(crate) fn allocate_mask_tile_index(&self) -> u16 {
+    pub(crate) fn allocate_mask_tile_index(&self,
+                                           local_tile_indices: &mut Option<LocalTileIndices>)
+                                           -> u16 {
+        if let Some(ref mut local_tile_indices) = *local_tile_indices {
+            local_tile_indices.mask_tile_count += 1;
+            return local_tile_indices.mask_tile_count - 1;
+        }
+
         // FIXME(pcwalton): Check for overflow!
         self.next_mask_tile_index.fetch_add(1, Ordering::Relaxed) as u16
     }
 
-    fn needs_readable_framebuffer(&self) -> bool {
+    // Creates an empty built path, numbering its tiles locally if the build is deterministic.
+    fn is_cancelled(&self) -> bool {
+        match self.built_options.cancellation_token {
+            Some(ref token) => token.is_cancelled(),
+            None => false,
+        }
+    }
+
+    pub(crate) fn new_built_path(&self, bounds: RectF, fill_rule: FillRule) -> BuiltPath {
+        let mut built_path = self.build_pool().built_path(bounds, fill_rule);
+        if self.built_options.deterministic {
+            built_path.local_tile_indices = Some(LocalTileIndices::default());
+        }
+        built_path
+    }
+
+    // Returns, for each draw path, whether it's clipped with the stencil buffer. That's possible
+    // only for clipped paths drawn straight to the destination framebuffer, since render targets
+    // and the intermediate framebuffer used for blending have no stencil buffer.
+    fn stencil_clipped_paths(&self, needs_readable_framebuffer: bool) -> Vec<bool> {
+        let mut stencil_clipped_paths = vec![false; self.scene.paths.len()];
+        if !self.built_options.stencil_clipping || needs_readable_framebuffer {
+            return stencil_clipped_paths;
+        }
+
         let mut framebuffer_nesting = 0;
         for display_item in &self.scene.display_list {
             match *display_item {
@@ -451,30 +1274,164 @@ impl<'a> SceneBuilder<'a> {
                     if framebuffer_nesting > 0 {
                         continue;
                     }
-                    for path_index in start_index..end_index {
-                        let blend_mode = self.scene.paths[path_index as usize].blend_mode();
-                        let blend_mode_program = BlendModeProgram::from_blend_mode(blend_mode);
-                        if blend_mode_program.needs_readable_framebuffer() {
-                            return true;
-                        }
+                    let path_range = start_index as usize..end_index as usize;
+                    for (stencil_clipped, path) in stencil_clipped_paths[path_range.clone()]
+                            .iter_mut()
+                            .zip(&self.scene.paths[path_range]) {
+                        *stencil_clipped = path.clip_path().is_some();
                     }
                 }
             }
         }
-        false
+        stencil_clipped_paths
     }
-}
 
-impl BuiltPath {
-    fn new(bounds: RectF, fill_rule: FillRule) -> BuiltPath {
-        BuiltPath {
-            mask_tiles: vec![],
-            alpha_tiles: vec![],
-            solid_tiles: vec![],
-            tiles: DenseTileMap::new(tiles::round_rect_out_to_tile_bounds(bounds)),
-            fill_rule,
-        }
-    }
+    fn needs_readable_framebuffer(&self) -> bool {
+        let mut framebuffer_nesting = 0;
+        for display_item in &self.scene.display_list {
+            match *display_item {
+                DisplayItem::DrawRenderTarget { .. } => {}
+                DisplayItem::PushRenderTarget(_) => framebuffer_nesting += 1,
+                DisplayItem::PopRenderTarget => framebuffer_nesting -= 1,
+                DisplayItem::DrawPaths { start_index, end_index } => {
+                    if framebuffer_nesting > 0 {
+                        continue;
+                    }
+                    for path_index in start_index..end_index {
+                        let blend_mode = self.scene.paths[path_index as usize].blend_mode();
+                        let blend_mode_program = BlendModeProgram::from_blend_mode(blend_mode);
+                        let options = &self.built_options;
+                        if blend_mode_program.needs_readable_framebuffer(
+                                options.dual_source_blending,
+                                options.advanced_blending) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+impl BuildPool {
+    // Creates an empty built path, reusing pooled vectors if there are any.
+    fn built_path(&self, bounds: RectF, fill_rule: FillRule) -> BuiltPath {
+        let mut buffers = self.buffers.lock().unwrap();
+        let mut mask_tiles = buffers.mask_tiles.pop().unwrap_or_default();
+        let mut alpha_tiles = buffers.alpha_tiles.pop().unwrap_or_default();
+        let mut solid_tiles = buffers.solid_tiles.pop().unwrap_or_default();
+        let tile_data = buffers.tiles.pop().unwrap_or_default();
+        drop(buffers);
+
+        mask_tiles.clear();
+        alpha_tiles.clear();
+        solid_tiles.clear();
+        let tile_rect = tiles::round_rect_out_to_tile_bounds(bounds);
+        BuiltPath {
+            mask_tiles,
+            alpha_tiles,
+            solid_tiles,
+            tiles: TileMap::new(tile_rect, tile_data),
+            fill_rule,
+            local_tile_indices: None,
+        }
+    }
+
+    // Returns an empty alpha tile vector, reusing a pooled one if there is one.
+    fn alpha_tiles(&self) -> Vec<AlphaTile> {
+        let mut alpha_tiles = self.buffers.lock().unwrap().alpha_tiles.pop().unwrap_or_default();
+        alpha_tiles.clear();
+        alpha_tiles
+    }
+
+    fn recycle_alpha_tiles<I>(&self, alpha_tiles: I) where I: Iterator<Item = Vec<AlphaTile>> {
+        self.buffers.lock().unwrap().alpha_tiles.extend(alpha_tiles);
+    }
+
+    fn recycle<I>(&self, built_paths: I) where I: Iterator<Item = BuiltPath> {
+        let mut buffers = self.buffers.lock().unwrap();
+        for built_path in built_paths {
+            buffers.mask_tiles.push(built_path.mask_tiles);
+            buffers.alpha_tiles.push(built_path.alpha_tiles);
+            buffers.solid_tiles.push(built_path.solid_tiles);
+            buffers.tiles.push(built_path.tiles.into_buffer());
+        }
+    }
+}
+
+impl ClipCache {
+    fn take(&self, path_index: usize, key: ClipCacheKey) -> Option<CachedClipPath> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get_mut(path_index) {
+            Some(entry @ Some(_)) if entry.as_ref().unwrap().key == key => entry.take(),
+            _ => None,
+        }
+    }
+
+    fn insert(&self, path_index: usize, cached: CachedClipPath) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() <= path_index {
+            entries.resize_with(path_index + 1, || None);
+        }
+        entries[path_index] = Some(cached);
+    }
+}
+
+// Clones start out with an empty cache, since their clip paths may diverge from the original's.
+impl Clone for ClipCache {
+    #[inline]
+    fn clone(&self) -> ClipCache {
+        ClipCache::default()
+    }
+}
+
+impl CachedTiling {
+    fn new(object_builder: &mut ObjectBuilder) -> CachedTiling {
+        let mut fills = object_builder.recorded_fills.take().unwrap_or_default();
+
+        // Renumber the alpha tiles from zero.
+        let mut alpha_tile_indices = HashMap::new();
+        for fill in &mut fills {
+            let next_alpha_tile_index = alpha_tile_indices.len() as u16;
+            fill.alpha_tile_index = *alpha_tile_indices.entry(fill.alpha_tile_index)
+                                                       .or_insert(next_alpha_tile_index);
+        }
+
+        let tiles = object_builder.built_path.tiles.iter().filter_map(|(tile_coords, &tile)| {
+            let mut tile = tile;
+            if tile.alpha_tile_index != !0 {
+                tile.alpha_tile_index = alpha_tile_indices[&tile.alpha_tile_index];
+            } else if tile.backdrop == 0 {
+                return None;
+            }
+            Some((tile_coords, tile))
+        }).collect();
+
+        CachedTiling {
+            bounds: object_builder.bounds,
+            tiles,
+            fills,
+            alpha_tile_count: alpha_tile_indices.len(),
+        }
+    }
+}
+
+impl ClipCacheKey {
+    // Returns `None` if clip paths built with these options can't be cached.
+    fn new(view_box: RectF, options: &PreparedBuildOptions) -> Option<ClipCacheKey> {
+        let transform = match options.transform {
+            PreparedRenderTransform::None => Transform2F::default(),
+            PreparedRenderTransform::Transform2D(transform) => transform,
+            PreparedRenderTransform::Perspective { .. } => return None,
+        };
+        Some(ClipCacheKey {
+            transform,
+            view_box,
+            dilation: options.dilation,
+            subpixel_aa_enabled: options.subpixel_aa_enabled,
+        })
+    }
 }
 
 impl SolidTileInfo {
@@ -490,29 +1447,193 @@ struct CulledTiles {
     display_list: Vec<CulledDisplayItem>,
 }
 
+#[derive(Clone, Copy)]
+enum CullingJob {
+    // The solid tiles of a whole layer: the main framebuffer or a render target.
+    LayerSolidTiles { z_buffer_index: usize },
+    PushRenderTarget(RenderTargetId),
+    PopRenderTarget,
+    DrawRenderTarget {
+        render_target: RenderTargetId,
+        effects: Effects,
+        z_buffer_index: usize,
+        depth: u32,
+    },
+    DrawPath { path_index: usize, z_buffer_index: usize, depth: u32 },
+}
+
+enum CullingResult {
+    None,
+    SolidTiles(Vec<SolidTileBatch>),
+    AlphaTiles(Vec<AlphaTile>),
+}
+
+#[derive(Clone)]
 enum CulledDisplayItem {
     DrawSolidTiles(SolidTileBatch),
     DrawAlphaTiles(AlphaTileBatch),
+    DrawClipStencil(Vec<AlphaTile>),
     PushRenderTarget(RenderTargetId),
     PopRenderTarget,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
-pub struct TileStats {
-    pub solid_tile_count: u32,
-    pub alpha_tile_count: u32,
+// Tile coordinates are stored as `i16`s relative to the view box's chunk, so the view box must
+// fit in that many tiles past the chunk's corner. The view box can lie anywhere on the canvas, as
+// long as its tile coordinates stay exact in `f32`s. This also rejects non-finite view boxes.
+fn view_box_is_valid(view_box: RectF) -> bool {
+    let tile_size = Vector2F::new(TILE_WIDTH as f32, TILE_HEIGHT as f32);
+    let (min, max) = (view_box.origin() / tile_size, view_box.lower_right() / tile_size);
+    let limit = (1 << 24) as f32;
+    if !(view_box.width() >= 0.0 && view_box.height() >= 0.0 &&
+         [min.x(), min.y(), max.x(), max.y()].iter().all(|value| value.abs() < limit)) {
+        return false;
+    }
+    let extent = max - tiles::chunk_origin(view_box).to_f32();
+    extent.x() < i16::MAX as f32 && extent.y() < i16::MAX as f32
+}
+
+// Returns the smallest rect that holds the effective view boxes of all the viewports.
+fn union_view_box(scene: &Scene, viewports: &[PreparedBuildOptions]) -> RectF {
+    let mut view_boxes = viewports.iter().map(|options| scene.effective_view_box(options));
+    let first_view_box = view_boxes.next().unwrap();
+    view_boxes.fold(first_view_box, RectF::union_rect)
 }
 
 // Utilities for built objects
 
+// Returns the rect that the outline covers if the outline is exactly a rectangle with integral,
+// axis-aligned edges. Clipping to such a rect with a scissor rect gives the same result as
+// clipping to it with a mask.
+fn outline_as_pixel_rect(outline: &Outline) -> Option<RectI> {
+    let contour = match outline.contours() {
+        [contour] => contour,
+        _ => return None,
+    };
+
+    let bounds = contour.bounds();
+    let rect = bounds.to_i32();
+    if rect.to_f32() != bounds || rect.width() <= 0 || rect.height() <= 0 {
+        return None;
+    }
+
+    // Every point must be a corner, all four corners must appear, and each edge must be
+    // horizontal or vertical. The last point may repeat the first.
+    let point_count = contour.len();
+    if !(4..=5).contains(&point_count) {
+        return None;
+    }
+    let mut corners_seen = 0u8;
+    for point_index in 0..point_count {
+        if !contour.point_is_endpoint(point_index) {
+            return None;
+        }
+        let point = contour.position_of(point_index);
+        let next_point = contour.position_of((point_index + 1) % point_count);
+        if point.x() != next_point.x() && point.y() != next_point.y() {
+            return None;
+        }
+        let is_right = match point.x() {
+            x if x == bounds.min_x() => false,
+            x if x == bounds.max_x() => true,
+            _ => return None,
+        };
+        let is_bottom = match point.y() {
+            y if y == bounds.min_y() => false,
+            y if y == bounds.max_y() => true,
+            _ => return None,
+        };
+        corners_seen |= 1 << (is_right as u8 | (is_bottom as u8) << 1);
+    }
+
+    if corners_seen == 0xf {
+        Some(rect)
+    } else {
+        None
+    }
+}
+
+// A fill in 4.8 fixed point relative to the tile origin, along with its whole pixels.
+struct PackedFill {
+    subpx: I32x4,
+    px: I32x4,
+}
+
+fn pack_fill(segment: LineSegment2F, tile_coords: Vector2I) -> PackedFill {
+    debug_assert_eq!(TILE_WIDTH, TILE_HEIGHT);
+
+    // Compute the upper left corner of the tile.
+    let tile_size = F32x4::splat(TILE_WIDTH as f32);
+    let tile_upper_left = tile_coords.to_f32().0.to_f32x4().xyxy() * tile_size;
+
+    // Convert to 4.8 fixed point.
+    let segment = (segment.0 - tile_upper_left) * F32x4::splat(256.0);
+    let (min, max) = (F32x4::default(), F32x4::splat((TILE_WIDTH * 256 - 1) as f32));
+    let subpx = segment.clamp(min, max).to_i32x4();
+
+    // Pack whole pixels.
+    let px = (subpx & I32x4::splat(0xf00)).to_u32x4();
+    let px = (px >> 8).to_i32x4() | (px >> 4).to_i32x4().yxwz();
+
+    PackedFill { subpx, px }
+}
+
+// The fills of four horizontally adjacent tiles, packed like `pack_fill()` but stored as a
+// structure of arrays, one lane per tile.
+struct PackedFillsX4 {
+    from_x: I32x4,
+    from_y: I32x4,
+    to_x: I32x4,
+    to_y: I32x4,
+    from_px: I32x4,
+    to_px: I32x4,
+}
+
+fn pack_fills_x4(from_x: F32x4,
+                 from_y: F32x4,
+                 to_x: F32x4,
+                 to_y: F32x4,
+                 tile_left: F32x4,
+                 tile_y: i32)
+                 -> PackedFillsX4 {
+    debug_assert_eq!(TILE_WIDTH, TILE_HEIGHT);
+    let tile_top = F32x4::splat((tile_y * TILE_HEIGHT as i32) as f32);
+
+    // Convert to 4.8 fixed point.
+    let (min, max) = (F32x4::default(), F32x4::splat((TILE_WIDTH * 256 - 1) as f32));
+    let to_fixed = |value: F32x4, origin: F32x4| {
+        ((value - origin) * F32x4::splat(256.0)).clamp(min, max).to_i32x4()
+    };
+    let (from_x, from_y) = (to_fixed(from_x, tile_left), to_fixed(from_y, tile_top));
+    let (to_x, to_y) = (to_fixed(to_x, tile_left), to_fixed(to_y, tile_top));
+
+    // Pack whole pixels.
+    let pack_px = |x: I32x4, y: I32x4| {
+        (x.to_u32x4() >> 8).to_i32x4() | ((y & I32x4::splat(0xf00)).to_u32x4() >> 4).to_i32x4()
+    };
+    let (from_px, to_px) = (pack_px(from_x, from_y), pack_px(to_x, to_y));
+
+    PackedFillsX4 { from_x, from_y, to_x, to_y, from_px, to_px }
+}
+
 impl ObjectBuilder {
-    pub(crate) fn new(bounds: RectF, fill_rule: FillRule) -> ObjectBuilder {
-        ObjectBuilder { built_path: BuiltPath::new(bounds, fill_rule), bounds, fills: vec![] }
+    pub(crate) fn new(bounds: RectF, fill_rule: FillRule, scene_builder: &SceneBuilder)
+                      -> ObjectBuilder {
+        ObjectBuilder {
+            built_path: scene_builder.new_built_path(bounds, fill_rule),
+            bounds,
+            fills: vec![],
+            recorded_fills: None,
+        }
+    }
+
+    // Keeps a copy of every fill sent from now on.
+    pub(crate) fn record_fills(&mut self) {
+        self.recorded_fills = Some(vec![]);
     }
 
     #[inline]
     pub(crate) fn tile_rect(&self) -> RectI {
-        self.built_path.tiles.rect
+        self.built_path.tiles.rect()
     }
 
     fn add_fill(
@@ -524,24 +1645,31 @@ impl ObjectBuilder {
         debug!("add_fill({:?} ({:?}))", segment, tile_coords);
 
         // Ensure this fill is in bounds. If not, cull it.
-        if self.tile_coords_to_local_index(tile_coords).is_none() {
+        if !self.tile_rect().contains_point(tile_coords) {
             return;
         };
 
-        debug_assert_eq!(TILE_WIDTH, TILE_HEIGHT);
-
-        // Compute the upper left corner of the tile.
-        let tile_size = F32x4::splat(TILE_WIDTH as f32);
-        let tile_upper_left = tile_coords.to_f32().0.to_f32x4().xyxy() * tile_size;
-
-        // Convert to 4.8 fixed point.
-        let segment = (segment.0 - tile_upper_left) * F32x4::splat(256.0);
-        let (min, max) = (F32x4::default(), F32x4::splat((TILE_WIDTH * 256 - 1) as f32));
-        let segment = segment.clamp(min, max).to_i32x4();
-        let (from_x, from_y, to_x, to_y) = (segment[0], segment[1], segment[2], segment[3]);
+        let PackedFill { subpx, px } = pack_fill(segment, tile_coords);
+        self.push_fill(scene_builder,
+                       LineSegmentU4 { from: px[0] as u8, to: px[2] as u8 },
+                       LineSegmentU8 {
+                           from_x: subpx[0] as u8,
+                           from_y: subpx[1] as u8,
+                           to_x:   subpx[2] as u8,
+                           to_y:   subpx[3] as u8,
+                       },
+                       tile_coords);
+    }
 
-        // Cull degenerate fills.
-        if from_x == to_x {
+    fn push_fill(
+        &mut self,
+        scene_builder: &SceneBuilder,
+        px: LineSegmentU4,
+        subpx: LineSegmentU8,
+        tile_coords: Vector2I,
+    ) {
+        // Cull degenerate fills. The whole pixels of x are in the low bits of `px`.
+        if subpx.from_x == subpx.to_x && px.from & 0xf == px.to & 0xf {
             debug!("... culling!");
             return;
         }
@@ -549,22 +1677,31 @@ impl ObjectBuilder {
         // Allocate global tile if necessary.
         let alpha_tile_index = self.get_or_allocate_alpha_tile_index(scene_builder, tile_coords);
 
-        // Pack whole pixels.
-        let px = (segment & I32x4::splat(0xf00)).to_u32x4();
-        let px = (px >> 8).to_i32x4() | (px >> 4).to_i32x4().yxwz();
-
         // Pack instance data.
         debug!("... OK, pushing");
-        self.fills.push(FillBatchPrimitive {
-            px: LineSegmentU4 { from: px[0] as u8, to: px[2] as u8 },
-            subpx: LineSegmentU8 {
-                from_x: from_x as u8,
-                from_y: from_y as u8,
-                to_x:   to_x   as u8,
-                to_y:   to_y   as u8,
-            },
-            alpha_tile_index,
-        });
+        self.fills.push(FillBatchPrimitive { px, subpx, alpha_tile_index });
+
+        // Stream out big paths' fills in chunks rather than holding on to all of them.
+        if self.fills.len() >= scene_builder.built_options.max_fills_per_command {
+            self.send_fills(scene_builder);
+        }
+    }
+
+    pub(crate) fn send_fills(&mut self, scene_builder: &SceneBuilder) {
+        if !self.fills.is_empty() {
+            if let Some(ref mut recorded_fills) = self.recorded_fills {
+                recorded_fills.extend_from_slice(&self.fills);
+            }
+            match self.built_path.local_tile_indices {
+                Some(ref mut local_tile_indices) => {
+                    local_tile_indices.fills.append(&mut self.fills)
+                }
+                None => {
+                    let fills = mem::take(&mut self.fills);
+                    scene_builder.listener.send(RenderCommand::AddFills(fills));
+                }
+            }
+        }
     }
 
     fn get_or_allocate_alpha_tile_index(
@@ -572,17 +1709,20 @@ impl ObjectBuilder {
         scene_builder: &SceneBuilder,
         tile_coords: Vector2I,
     ) -> u16 {
-        let local_tile_index = self.built_path.tiles.coords_to_index_unchecked(tile_coords);
-        let alpha_tile_index = self.built_path.tiles.data[local_tile_index].alpha_tile_index;
+        let alpha_tile_index = self.built_path.tiles.get(tile_coords).unwrap().alpha_tile_index;
         if alpha_tile_index != !0 {
             return alpha_tile_index;
         }
 
-        // FIXME(pcwalton): Check for overflow!
-        let alpha_tile_index = scene_builder
-            .next_alpha_tile_index
-            .fetch_add(1, Ordering::Relaxed) as u16;
-        self.built_path.tiles.data[local_tile_index].alpha_tile_index = alpha_tile_index;
+        let alpha_tile_index = match self.built_path.local_tile_indices {
+            Some(ref mut local_tile_indices) => {
+                local_tile_indices.alpha_tile_coords.push(tile_coords);
+                (local_tile_indices.alpha_tile_coords.len() - 1) as u16
+            }
+            // FIXME(pcwalton): Check for overflow!
+            None => scene_builder.next_alpha_tile_index.fetch_add(1, Ordering::Relaxed) as u16,
+        };
+        self.built_path.tiles.get_mut(tile_coords).unwrap().alpha_tile_index = alpha_tile_index;
         alpha_tile_index
     }
 
@@ -625,7 +1765,7 @@ impl ObjectBuilder {
     pub(crate) fn generate_fill_primitives_for_line(
         &mut self,
         scene_builder: &SceneBuilder,
-        mut segment: LineSegment2F,
+        segment: LineSegment2F,
         tile_y: i32,
     ) {
         debug!(
@@ -637,16 +1777,20 @@ impl ObjectBuilder {
         );
 
         let winding = segment.from_x() > segment.to_x();
-        let (segment_left, segment_right) = if !winding {
-            (segment.from_x(), segment.to_x())
+        let (left, right) = if !winding {
+            (segment.from(), segment.to())
         } else {
-            (segment.to_x(), segment.from_x())
+            (segment.to(), segment.from())
         };
 
-        // FIXME(pcwalton): Optimize this.
-        let segment_tile_left = f32::floor(segment_left) as i32 / TILE_WIDTH as i32;
+        // Vertical lines only produce degenerate fills, which would be culled anyway.
+        if left.x() == right.x() {
+            return;
+        }
+
+        let segment_tile_left = f32::floor(left.x() / TILE_WIDTH as f32) as i32;
         let segment_tile_right =
-            util::alignup_i32(f32::ceil(segment_right) as i32, TILE_WIDTH as i32);
+            util::alignup_i32(f32::ceil(right.x()) as i32, TILE_WIDTH as i32);
         debug!(
             "segment_tile_left={} segment_tile_right={} tile_rect={:?}",
             segment_tile_left,
@@ -654,96 +1798,167 @@ impl ObjectBuilder {
             self.tile_rect()
         );
 
-        for subsegment_tile_x in segment_tile_left..segment_tile_right {
-            let (mut fill_from, mut fill_to) = (segment.from(), segment.to());
-            let subsegment_tile_right =
-                ((i32::from(subsegment_tile_x) + 1) * TILE_HEIGHT as i32) as f32;
-            if subsegment_tile_right < segment_right {
-                let x = subsegment_tile_right;
-                let point = Vector2F::new(x, segment.solve_y_for_x(x));
-                if !winding {
-                    fill_to = point;
-                    segment = LineSegment2F::new(point, segment.to());
-                } else {
-                    fill_from = point;
-                    segment = LineSegment2F::new(segment.from(), point);
-                }
+        // Split the line at tile boundaries and pack the pieces four tiles at a time. Every
+        // boundary is solved from the left endpoint, so that adjacent tiles agree on where the
+        // line crosses between them.
+        let slope = F32x4::splat((right.y() - left.y()) / (right.x() - left.x()));
+        let (left_x, left_y) = (F32x4::splat(left.x()), F32x4::splat(left.y()));
+        let tile_width = F32x4::splat(TILE_WIDTH as f32);
+
+        let mut chunk_tile_left = segment_tile_left;
+        while chunk_tile_left < segment_tile_right {
+            let tile_lefts = (I32x4::splat(chunk_tile_left) + I32x4::new(0, 1, 2, 3)).to_f32x4() *
+                tile_width;
+            let left_xs = tile_lefts.max(left_x);
+            let right_xs = (tile_lefts + tile_width).min(F32x4::splat(right.x()));
+            let left_ys = left_y + (left_xs - left_x) * slope;
+            let mut right_ys = left_y + (right_xs - left_x) * slope;
+
+            // Land exactly on the right endpoint in the last tile.
+            let lane_count = i32::min(segment_tile_right - chunk_tile_left, 4);
+            if chunk_tile_left + lane_count == segment_tile_right {
+                right_ys[lane_count as usize - 1] = right.y();
             }
 
-            let fill_segment = LineSegment2F::new(fill_from, fill_to);
-            let fill_tile_coords = Vector2I::new(subsegment_tile_x, tile_y);
-            self.add_fill(scene_builder, fill_segment, fill_tile_coords);
-        }
-    }
+            let fills = if !winding {
+                pack_fills_x4(left_xs, left_ys, right_xs, right_ys, tile_lefts, tile_y)
+            } else {
+                pack_fills_x4(right_xs, right_ys, left_xs, left_ys, tile_lefts, tile_y)
+            };
 
-    #[inline]
-    pub(crate) fn tile_coords_to_local_index(&self, coords: Vector2I) -> Option<u32> {
-        self.built_path.tiles.coords_to_index(coords).map(|index| index as u32)
-    }
+            for lane in 0..(lane_count as usize) {
+                let tile_coords = Vector2I::new(chunk_tile_left + lane as i32, tile_y);
+                if !self.tile_rect().contains_point(tile_coords) {
+                    continue;
+                }
+                self.push_fill(scene_builder,
+                               LineSegmentU4 {
+                                   from: fills.from_px[lane] as u8,
+                                   to: fills.to_px[lane] as u8,
+                               },
+                               LineSegmentU8 {
+                                   from_x: fills.from_x[lane] as u8,
+                                   from_y: fills.from_y[lane] as u8,
+                                   to_x:   fills.to_x[lane]   as u8,
+                                   to_y:   fills.to_y[lane]   as u8,
+                               },
+                               tile_coords);
+            }
 
-    #[inline]
-    pub(crate) fn local_tile_index_to_coords(&self, tile_index: u32) -> Vector2I {
-        self.built_path.tiles.index_to_coords(tile_index as usize)
+            chunk_tile_left += 4;
+        }
     }
 
+    // `fill_tile_is_ours` is false if the fill tile belongs to another path, such as a clip path,
+    // so that its alpha tile index isn't renumbered along with ours.
     pub(crate) fn push_mask_tile(mask_tiles: &mut Vec<MaskTile>,
+                                 local_tile_indices: &mut Option<LocalTileIndices>,
                                  fill_tile: &TileObjectPrimitive,
+                                 fill_tile_is_ours: bool,
                                  mask_tile_index: u16,
                                  object_index: u16) {
+        if let Some(ref mut local_tile_indices) = *local_tile_indices {
+            let alpha_tile_index = if fill_tile_is_ours && !fill_tile.is_solid() {
+                Some(fill_tile.alpha_tile_index)
+            } else {
+                None
+            };
+            local_tile_indices.mask_tiles.push((mask_tile_index, alpha_tile_index));
+        }
+
+        let backdrop = clamp_backdrop(fill_tile.backdrop);
         mask_tiles.push(MaskTile {
             upper_left: MaskTileVertex::new(mask_tile_index,
                                             fill_tile.alpha_tile_index as u16,
                                             Vector2I::default(),
                                             object_index,
-                                            fill_tile.backdrop as i16),
+                                            backdrop),
             upper_right: MaskTileVertex::new(mask_tile_index,
                                              fill_tile.alpha_tile_index as u16,
                                              Vector2I::new(1, 0),
                                              object_index,
-                                             fill_tile.backdrop as i16),
+                                             backdrop),
             lower_left: MaskTileVertex::new(mask_tile_index,
                                             fill_tile.alpha_tile_index as u16,
                                             Vector2I::new(0, 1),
                                             object_index,
-                                            fill_tile.backdrop as i16),
+                                            backdrop),
             lower_right: MaskTileVertex::new(mask_tile_index,
                                              fill_tile.alpha_tile_index as u16,
                                              Vector2I::splat(1),
                                              object_index,
-                                             fill_tile.backdrop as i16),
+                                             backdrop),
+        });
+    }
+
+    fn push_clip_stencil_tile(alpha_tiles: &mut Vec<AlphaTile>,
+                              mask_tile_index: u16,
+                              tile_coords: Vector2I,
+                              chunk_origin: Vector2I,
+                              object_index: u16) {
+        let vertex = |tile_offset| {
+            AlphaTileVertex::for_clip_stencil(tile_coords - chunk_origin,
+                                              mask_tile_index,
+                                              tile_offset,
+                                              object_index)
+        };
+        alpha_tiles.push(AlphaTile {
+            upper_left: vertex(Vector2I::default()),
+            upper_right: vertex(Vector2I::new(1, 0)),
+            lower_left: vertex(Vector2I::new(0, 1)),
+            lower_right: vertex(Vector2I::splat(1)),
         });
     }
 
     pub(crate) fn push_alpha_tile(alpha_tiles: &mut Vec<AlphaTile>,
                                   mask_tile_index: u16,
                                   tile_coords: Vector2I,
+                                  chunk_origin: Vector2I,
                                   object_index: u16,
                                   draw_tiling_path_info: &DrawTilingPathInfo) {
+        let vertex = |tile_offset| {
+            AlphaTileVertex::new(tile_coords,
+                                 chunk_origin,
+                                 mask_tile_index,
+                                 tile_offset,
+                                 object_index,
+                                 draw_tiling_path_info)
+        };
         alpha_tiles.push(AlphaTile {
-            upper_left: AlphaTileVertex::new(tile_coords,
-                                             mask_tile_index,
-                                             Vector2I::default(),
-                                             object_index,
-                                             draw_tiling_path_info),
-            upper_right: AlphaTileVertex::new(tile_coords,
-                                              mask_tile_index,
-                                              Vector2I::new(1, 0),
-                                              object_index,
-                                              draw_tiling_path_info),
-            lower_left: AlphaTileVertex::new(tile_coords,
-                                             mask_tile_index,
-                                             Vector2I::new(0, 1),
-                                             object_index,
-                                             draw_tiling_path_info),
-            lower_right: AlphaTileVertex::new(tile_coords,
-                                              mask_tile_index,
-                                              Vector2I::splat(1),
-                                              object_index,
-                                              draw_tiling_path_info),
+            upper_left: vertex(Vector2I::default()),
+            upper_right: vertex(Vector2I::new(1, 0)),
+            lower_left: vertex(Vector2I::new(0, 1)),
+            lower_right: vertex(Vector2I::splat(1)),
         });
     }
 }
 
+impl MaskTile {
+    // Points the tile at a new mask tile and, if given, a new alpha tile.
+    fn renumber(&mut self, mask_tile_index: u16, alpha_tile_index: Option<u16>) {
+        self.upper_left.renumber(mask_tile_index, alpha_tile_index, Vector2I::default());
+        self.upper_right.renumber(mask_tile_index, alpha_tile_index, Vector2I::new(1, 0));
+        self.lower_left.renumber(mask_tile_index, alpha_tile_index, Vector2I::new(0, 1));
+        self.lower_right.renumber(mask_tile_index, alpha_tile_index, Vector2I::splat(1));
+    }
+}
+
+impl AlphaTile {
+    fn renumber(&mut self, mask_tile_index: u16) {
+        self.upper_left.renumber(mask_tile_index, Vector2I::default());
+        self.upper_right.renumber(mask_tile_index, Vector2I::new(1, 0));
+        self.lower_left.renumber(mask_tile_index, Vector2I::new(0, 1));
+        self.lower_right.renumber(mask_tile_index, Vector2I::splat(1));
+    }
+
+    fn translate(&mut self, tile_offset: Vector2I) {
+        self.upper_left.translate(tile_offset);
+        self.upper_right.translate(tile_offset);
+        self.lower_left.translate(tile_offset);
+        self.lower_right.translate(tile_offset);
+    }
+}
+
 impl MaskTileVertex {
     #[inline]
     fn new(mask_index: u16,
@@ -763,11 +1978,25 @@ impl MaskTileVertex {
             object_index,
         }
     }
+
+    #[inline]
+    fn renumber(&mut self, mask_index: u16, fill_index: Option<u16>, tile_offset: Vector2I) {
+        let mask_uv = calculate_mask_uv(mask_index, tile_offset);
+        self.mask_u = mask_uv.x() as u16;
+        self.mask_v = mask_uv.y() as u16;
+        if let Some(fill_index) = fill_index {
+            let fill_uv = calculate_mask_uv(fill_index, tile_offset);
+            self.fill_u = fill_uv.x() as u16;
+            self.fill_v = fill_uv.y() as u16;
+        }
+    }
 }
 
 impl AlphaTileVertex {
+    // The tile origin is absolute, but the vertex's position is relative to the chunk.
     #[inline]
     fn new(tile_origin: Vector2I,
+           chunk_origin: Vector2I,
            tile_index: u16,
            tile_offset: Vector2I,
            object_index: u16,
@@ -775,6 +2004,7 @@ impl AlphaTileVertex {
            -> AlphaTileVertex {
         let tile_position = tile_origin + tile_offset;
         let color_uv = draw_tiling_path_info.paint_metadata.calculate_tex_coords(tile_position);
+        let tile_position = tile_position - chunk_origin;
         let mask_uv = calculate_mask_uv(tile_index, tile_offset);
         AlphaTileVertex {
             tile_x: tile_position.x() as i16,
@@ -789,12 +2019,60 @@ impl AlphaTileVertex {
         }
     }
 
+    // Clip stencil tiles have no paint.
+    #[inline]
+    fn for_clip_stencil(tile_origin: Vector2I,
+                        tile_index: u16,
+                        tile_offset: Vector2I,
+                        object_index: u16)
+                        -> AlphaTileVertex {
+        let tile_position = tile_origin + tile_offset;
+        let mask_uv = calculate_mask_uv(tile_index, tile_offset);
+        AlphaTileVertex {
+            tile_x: tile_position.x() as i16,
+            tile_y: tile_position.y() as i16,
+            color_u: 0.0,
+            color_v: 0.0,
+            mask_u: mask_uv.x() as u16,
+            mask_v: mask_uv.y() as u16,
+            object_index,
+            opacity: !0,
+            pad: 0,
+        }
+    }
+
+    #[inline]
+    fn renumber(&mut self, tile_index: u16, tile_offset: Vector2I) {
+        let mask_uv = calculate_mask_uv(tile_index, tile_offset);
+        self.mask_u = mask_uv.x() as u16;
+        self.mask_v = mask_uv.y() as u16;
+    }
+
+    #[inline]
+    fn translate(&mut self, tile_offset: Vector2I) {
+        self.tile_x += tile_offset.x() as i16;
+        self.tile_y += tile_offset.y() as i16;
+    }
+
     #[inline]
     pub fn tile_position(&self) -> Vector2I {
         Vector2I::new(self.tile_x as i32, self.tile_y as i32)
     }
 }
 
+// Clamps a backdrop to what mask tile vertices can hold, keeping its sign and parity so that the
+// tile comes out the same under either fill rule. The winding stays far beyond anything the
+// tile's own fills could cancel out, short of tens of thousands of them.
+fn clamp_backdrop(backdrop: i32) -> i16 {
+    if backdrop > i16::MAX as i32 {
+        if backdrop % 2 == 0 { i16::MAX - 1 } else { i16::MAX }
+    } else if backdrop < i16::MIN as i32 {
+        if backdrop % 2 == 0 { i16::MIN } else { i16::MIN + 1 }
+    } else {
+        backdrop as i16
+    }
+}
+
 fn calculate_mask_uv(tile_index: u16, tile_offset: Vector2I) -> Vector2I {
     let mask_u = tile_index as i32 % MASK_TILES_ACROSS as i32;
     let mask_v = tile_index as i32 / MASK_TILES_ACROSS as i32;
@@ -804,10 +2082,1253 @@ fn calculate_mask_uv(tile_index: u16, tile_offset: Vector2I) -> Vector2I {
 }
 
 impl CulledTiles {
-    fn push_mask_tiles(&mut self, built_path: &BuiltPath) {
-        match built_path.fill_rule {
-            FillRule::Winding => self.mask_winding_tiles.extend_from_slice(&built_path.mask_tiles),
-            FillRule::EvenOdd => self.mask_evenodd_tiles.extend_from_slice(&built_path.mask_tiles),
+    // Pushes the mask tiles that `alpha_tiles`, the tiles of `built_path` that survived culling,
+    // are drawn through. They're given consecutive mask tile indices from `next_mask_tile_index`
+    // on, and the alpha tiles are renumbered to match. Mask tiles for culled alpha tiles are
+    // dropped.
+    fn push_mask_tiles(&mut self,
+                       built_path: &BuiltPath,
+                       alpha_tiles: &mut [AlphaTile],
+                       next_mask_tile_index: &mut usize) {
+        // An alpha tile and its mask tiles share a mask tile index, so they can be matched up by
+        // their mask texture coordinates.
+        let mut mask_tile_indices = HashMap::with_capacity(alpha_tiles.len());
+        for alpha_tile in alpha_tiles.iter_mut() {
+            // FIXME(pcwalton): Check for overflow!
+            let mask_tile_index = *next_mask_tile_index as u16;
+            *next_mask_tile_index += 1;
+            let mask_uv = (alpha_tile.upper_left.mask_u, alpha_tile.upper_left.mask_v);
+            mask_tile_indices.insert(mask_uv, mask_tile_index);
+            alpha_tile.renumber(mask_tile_index);
+        }
+
+        let dest = match built_path.fill_rule {
+            FillRule::Winding => &mut self.mask_winding_tiles,
+            FillRule::EvenOdd => &mut self.mask_evenodd_tiles,
+        };
+        for mask_tile in &built_path.mask_tiles {
+            let mask_uv = (mask_tile.upper_left.mask_u, mask_tile.upper_left.mask_v);
+            if let Some(&mask_tile_index) = mask_tile_indices.get(&mask_uv) {
+                let mut mask_tile = *mask_tile;
+                mask_tile.renumber(mask_tile_index, None);
+                dest.push(mask_tile);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BuildPool, PackedFill, SolidTileInfo, calculate_mask_uv, clamp_backdrop};
+    use super::{pack_fill, pack_fills_x4};
+    use crate::concurrent::executor::{Executor, SequentialExecutor};
+    use crate::concurrent::rayon::RayonExecutor;
+    use crate::error::Error;
+    use crate::gpu_data::{AlphaTile, MaskTile, RenderCommand};
+    use crate::options::{BuildOptions, CancellationToken, RenderTransform, Viewport};
+    use crate::paint::{CustomPaint, CustomPaintShaderId, Paint};
+    use crate::picture::Picture;
+    use crate::scene::{ClipPath, DrawPath, PathTiles, Scene};
+    use crate::tiles::{CHUNK_SIZE, TILE_WIDTH};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::effects::BlendMode;
+    use pathfinder_content::fill::FillRule;
+    use pathfinder_content::outline::{Contour, Outline};
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::rect::{RectF, RectI};
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+    use pathfinder_simd::default::F32x4;
+    use std::sync::{Arc, Mutex};
+
+    fn rect_outline(rect: RectF) -> Outline {
+        let mut contour = Contour::new();
+        contour.push_endpoint(rect.origin());
+        contour.push_endpoint(rect.upper_right());
+        contour.push_endpoint(rect.lower_right());
+        contour.push_endpoint(rect.lower_left());
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        outline
+    }
+
+    fn build_commands(scene: &Scene, options: BuildOptions) -> Vec<RenderCommand> {
+        let commands = Arc::new(Mutex::new(vec![]));
+        let listener_commands = commands.clone();
+        let listener = move |command| listener_commands.lock().unwrap().push(command);
+        scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+        let commands = commands.lock().unwrap().drain(..).collect();
+        commands
+    }
+
+    #[test]
+    fn test_pack_fills_x4_matches_pack_fill() {
+        let tile_y = 3;
+        let segments = [
+            LineSegment2F::new(Vector2F::new(2.5, 50.0), Vector2F::new(16.0, 62.25)),
+            LineSegment2F::new(Vector2F::new(32.0, 63.5), Vector2F::new(17.75, 48.0)),
+            LineSegment2F::new(Vector2F::new(33.125, 49.0), Vector2F::new(47.0, 49.0)),
+            LineSegment2F::new(Vector2F::new(60.0, 40.0), Vector2F::new(48.5, 70.0)),
+        ];
+
+        let gather = |f: fn(LineSegment2F) -> f32| {
+            F32x4::new(f(segments[0]), f(segments[1]), f(segments[2]), f(segments[3]))
+        };
+        let tile_lefts = F32x4::new(0.0, 1.0, 2.0, 3.0) * F32x4::splat(TILE_WIDTH as f32);
+        let fills = pack_fills_x4(gather(LineSegment2F::from_x),
+                                  gather(LineSegment2F::from_y),
+                                  gather(LineSegment2F::to_x),
+                                  gather(LineSegment2F::to_y),
+                                  tile_lefts,
+                                  tile_y);
+
+        for (index, &segment) in segments.iter().enumerate() {
+            let PackedFill { subpx, px } = pack_fill(segment, Vector2I::new(index as i32, tile_y));
+            assert_eq!([fills.from_x[index], fills.from_y[index]], [subpx[0], subpx[1]]);
+            assert_eq!([fills.to_x[index], fills.to_y[index]], [subpx[2], subpx[3]]);
+            assert_eq!([fills.from_px[index], fills.to_px[index]], [px[0], px[2]]);
+        }
+    }
+
+    #[test]
+    fn test_clamp_backdrop_keeps_sign_and_parity() {
+        assert_eq!(clamp_backdrop(-200), -200);
+        assert_eq!(clamp_backdrop(32767), 32767);
+        assert_eq!(clamp_backdrop(40000), 32766);
+        assert_eq!(clamp_backdrop(40001), 32767);
+        assert_eq!(clamp_backdrop(-40000), -32768);
+        assert_eq!(clamp_backdrop(-40001), -32767);
+    }
+
+    #[test]
+    fn test_fills_are_chunked() {
+        // A sawtooth spanning the whole scene, which produces plenty of fills.
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(256.0)));
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(0.0, 256.0));
+        for index in 0..32 {
+            contour.push_endpoint(Vector2F::new(index as f32 * 8.0 + 4.0, 0.0));
+            contour.push_endpoint(Vector2F::new(index as f32 * 8.0 + 8.0, 256.0));
+        }
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        let paint = scene.push_paint(&Paint::black());
+        scene.push_path(DrawPath::new(outline, paint));
+
+        let fill_command_sizes = |max_fills_per_command| {
+            let sizes = Arc::new(Mutex::new(vec![]));
+            let listener_sizes = sizes.clone();
+            let listener = move |command| {
+                if let RenderCommand::AddFills(fills) = command {
+                    listener_sizes.lock().unwrap().push(fills.len());
+                }
+            };
+            let options = BuildOptions { max_fills_per_command, ..BuildOptions::default() };
+            scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+            let sizes = sizes.lock().unwrap().clone();
+            sizes
+        };
+
+        let unchunked = fill_command_sizes(usize::MAX);
+        let chunked = fill_command_sizes(100);
+        assert_eq!(unchunked.len(), 1);
+        assert!(chunked.len() > 1);
+        assert!(chunked.iter().all(|&size| size <= 100));
+        assert_eq!(chunked.iter().sum::<usize>(), unchunked[0]);
+    }
+
+    #[test]
+    fn test_merge_batches() {
+        // A row of small squares, each in its own tile, alternating between two blend modes.
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::new(256.0, 16.0)));
+        let paint = scene.push_paint(&Paint::black());
+        for index in 0..16 {
+            let origin = Vector2F::new(index as f32 * 16.0 + 4.5, 4.5);
+            let mut contour = Contour::new();
+            contour.push_endpoint(origin);
+            contour.push_endpoint(origin + Vector2F::new(7.0, 0.0));
+            contour.push_endpoint(origin + Vector2F::splat(7.0));
+            contour.push_endpoint(origin + Vector2F::new(0.0, 7.0));
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            let mut path = DrawPath::new(outline, paint);
+            if index % 2 == 1 {
+                path.set_blend_mode(BlendMode::Lighter);
+            }
+            scene.push_path(path);
+        }
+
+        let alpha_batch_sizes = |merge_batches| {
+            let sizes = Arc::new(Mutex::new(vec![]));
+            let listener_sizes = sizes.clone();
+            let listener = move |command| {
+                if let RenderCommand::DrawAlphaTiles(batch) = command {
+                    listener_sizes.lock().unwrap().push(batch.tiles.len());
+                }
+            };
+            let options = BuildOptions { merge_batches, ..BuildOptions::default() };
+            scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+            let sizes = sizes.lock().unwrap().clone();
+            sizes
+        };
+
+        assert_eq!(alpha_batch_sizes(false), vec![1; 16]);
+        assert_eq!(alpha_batch_sizes(true), vec![8, 8]);
+    }
+
+    #[test]
+    fn test_advanced_blending_merges_batches_without_readable_framebuffer() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::new(256.0, 16.0)));
+        let paint = scene.push_paint(&Paint::black());
+        for index in 0..16 {
+            let origin = Vector2F::new(index as f32 * 16.0 + 4.5, 4.5);
+            let mut contour = Contour::new();
+            contour.push_endpoint(origin);
+            contour.push_endpoint(origin + Vector2F::new(7.0, 0.0));
+            contour.push_endpoint(origin + Vector2F::splat(7.0));
+            contour.push_endpoint(origin + Vector2F::new(0.0, 7.0));
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            let mut path = DrawPath::new(outline, paint);
+            if index % 2 == 1 {
+                path.set_blend_mode(BlendMode::Multiply);
+            }
+            scene.push_path(path);
+        }
+
+        let build = |advanced_blending| {
+            let commands = Arc::new(Mutex::new((false, vec![])));
+            let listener_commands = commands.clone();
+            let listener = move |command| {
+                let mut commands = listener_commands.lock().unwrap();
+                match command {
+                    RenderCommand::Start { needs_readable_framebuffer, .. } => {
+                        commands.0 = needs_readable_framebuffer;
+                    }
+                    RenderCommand::DrawAlphaTiles(batch) => commands.1.push(batch.tiles.len()),
+                    _ => {}
+                }
+            };
+            let options = BuildOptions {
+                merge_batches: true,
+                advanced_blending,
+                ..BuildOptions::default()
+            };
+            scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+            let commands = commands.lock().unwrap().clone();
+            commands
+        };
+
+        assert_eq!(build(false), (true, vec![8, 1, 1, 1, 1, 1, 1, 1, 1]));
+        assert_eq!(build(true), (false, vec![8, 8]));
+    }
+
+    #[test]
+    fn test_custom_paints_are_batched_by_shader_and_params() {
+        let custom_paint = |shader, params| {
+            Paint::Custom(CustomPaint {
+                shader: CustomPaintShaderId(shader),
+                transform: Transform2F::default(),
+                params,
+            })
+        };
+
+        // A row of small squares, each in its own tile.
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::new(80.0, 16.0)));
+        let paints = [
+            scene.push_paint(&custom_paint(0, [1.0, 0.0, 0.0, 0.0])),
+            scene.push_paint(&custom_paint(1, [1.0, 0.0, 0.0, 0.0])),
+            scene.push_paint(&custom_paint(0, [1.0, 0.0, 0.0, 0.0])),
+            scene.push_paint(&custom_paint(0, [2.0, 0.0, 0.0, 0.0])),
+            scene.push_paint(&Paint::black()),
+        ];
+        for (index, &paint) in paints.iter().enumerate() {
+            let origin = Vector2F::new(index as f32 * 16.0 + 4.5, 4.5);
+            let mut contour = Contour::new();
+            contour.push_endpoint(origin);
+            contour.push_endpoint(origin + Vector2F::new(7.0, 0.0));
+            contour.push_endpoint(origin + Vector2F::splat(7.0));
+            contour.push_endpoint(origin + Vector2F::new(0.0, 7.0));
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            scene.push_path(DrawPath::new(outline, paint));
+        }
+
+        let batches = Arc::new(Mutex::new(vec![]));
+        let listener_batches = batches.clone();
+        let listener = move |command| {
+            if let RenderCommand::DrawAlphaTiles(batch) = command {
+                let custom_paint = batch.custom_paint.map(|custom_paint| {
+                    (custom_paint.shader.0, custom_paint.params[0])
+                });
+                listener_batches.lock().unwrap().push((custom_paint, batch.tiles.len()));
+            }
+        };
+        let options = BuildOptions { merge_batches: true, ..BuildOptions::default() };
+        scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+        assert_eq!(*batches.lock().unwrap(),
+                   vec![(Some((0, 1.0)), 2), (Some((1, 1.0)), 1), (Some((0, 2.0)), 1), (None, 1)]);
+    }
+
+    #[test]
+    fn test_fills_with_same_subpixel_x_are_kept() {
+        // The horizontal edges of this square start and end half a pixel into a pixel.
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(16.0)));
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(4.5, 4.5));
+        contour.push_endpoint(Vector2F::new(11.5, 4.5));
+        contour.push_endpoint(Vector2F::new(11.5, 11.5));
+        contour.push_endpoint(Vector2F::new(4.5, 11.5));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        let paint = scene.push_paint(&Paint::black());
+        scene.push_path(DrawPath::new(outline, paint));
+
+        let fill_count = Arc::new(Mutex::new(0));
+        let listener_fill_count = fill_count.clone();
+        let listener = move |command| {
+            if let RenderCommand::AddFills(fills) = command {
+                *listener_fill_count.lock().unwrap() += fills.len();
+            }
+        };
+        scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor).unwrap();
+        assert_eq!(*fill_count.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_clip_rect_limits_built_tiles() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(64.0)));
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(0.5, 0.5));
+        contour.push_endpoint(Vector2F::new(63.5, 0.5));
+        contour.push_endpoint(Vector2F::new(0.5, 63.5));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        let paint = scene.push_paint(&Paint::black());
+        scene.push_path(DrawPath::new(outline, paint));
+
+        // Returns the column of each alpha tile.
+        let build = |clip_rect| {
+            let tile_xs = Arc::new(Mutex::new(vec![]));
+            let listener_tile_xs = tile_xs.clone();
+            let listener = move |command| {
+                if let RenderCommand::DrawAlphaTiles(batch) = command {
+                    let mut tile_xs = listener_tile_xs.lock().unwrap();
+                    tile_xs.extend(batch.tiles.iter().map(|tile| tile.upper_left.tile_x));
+                }
+            };
+            let options = BuildOptions { clip_rect, ..BuildOptions::default() };
+            scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+            let tile_xs = tile_xs.lock().unwrap().clone();
+            tile_xs
+        };
+
+        let unclipped = build(None);
+        assert!(unclipped.iter().any(|&tile_x| tile_x >= 2));
+        let clipped = build(Some(RectF::new(Vector2F::default(), Vector2F::new(32.0, 64.0))));
+        assert!(!clipped.is_empty());
+        assert!(clipped.iter().all(|&tile_x| tile_x < 2));
+    }
+
+    #[test]
+    fn test_lod_is_selected_by_scale() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(256.0)));
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(0.5, 0.5));
+        contour.push_endpoint(Vector2F::new(63.5, 0.5));
+        contour.push_endpoint(Vector2F::new(0.5, 63.5));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        let paint = scene.push_paint(&Paint::black());
+        let mut path = DrawPath::new(outline, paint);
+        path.add_lod(0.5, Outline::new());
+        scene.push_path(path);
+
+        // Returns the number of fills sent.
+        let build = |scale| {
+            let fill_count = Arc::new(Mutex::new(0));
+            let listener_fill_count = fill_count.clone();
+            let listener = move |command| {
+                if let RenderCommand::AddFills(fills) = command {
+                    *listener_fill_count.lock().unwrap() += fills.len();
+                }
+            };
+            let transform = Transform2F::from_uniform_scale(scale);
+            let options = BuildOptions {
+                transform: RenderTransform::Transform2D(transform),
+                ..BuildOptions::default()
+            };
+            scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+            let fill_count = *fill_count.lock().unwrap();
+            fill_count
+        };
+
+        assert_eq!(build(0.25), 0);
+        assert_eq!(build(0.5), 0);
+        assert!(build(1.0) > 0);
+    }
+
+    #[test]
+    fn test_rectangular_clips_use_scissor_rects() {
+        // Returns the scissor rect of each alpha tile.
+        let build = |clip_rect: RectF| {
+            let mut scene = Scene::new();
+            scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(64.0)));
+            let clip_path = scene.push_clip_path(ClipPath::new(rect_outline(clip_rect)));
+            let paint = scene.push_paint(&Paint::black());
+            let mut path = DrawPath::new(rect_outline(scene.view_box()), paint);
+            path.set_clip_path(Some(clip_path));
+            scene.push_path(path);
+
+            let mut scissors = vec![];
+            for command in build_commands(&scene, BuildOptions::default()) {
+                if let RenderCommand::DrawAlphaTiles(batch) = command {
+                    scissors.extend(batch.tiles.iter().map(|_| batch.scissor));
+                }
+            }
+            scissors
+        };
+
+        // Pixel-aligned clips need no mask, and tiles outside them are culled.
+        let unclipped_tile_count =
+            build(RectF::new(Vector2F::default(), Vector2F::splat(64.0))).len();
+        let clip_rect = RectI::new(Vector2I::splat(8), Vector2I::splat(32));
+        let scissors = build(clip_rect.to_f32());
+        assert!(!scissors.is_empty() && scissors.len() <= unclipped_tile_count);
+        assert!(scissors.iter().all(|&scissor| scissor == Some(clip_rect)));
+
+        // Clips with fractional edges still need masks.
+        let scissors = build(RectF::new(Vector2F::splat(8.5), Vector2F::splat(32.0)));
+        assert!(!scissors.is_empty());
+        assert!(scissors.iter().all(|scissor| scissor.is_none()));
+    }
+
+    #[test]
+    fn test_clip_cache() {
+        let triangle = |points: [Vector2F; 3]| {
+            let mut contour = Contour::new();
+            for &point in &points {
+                contour.push_endpoint(point);
+            }
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            outline
+        };
+
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(64.0)));
+        let clip_outline = triangle([
+            Vector2F::new(4.0, 2.0),
+            Vector2F::new(60.0, 30.5),
+            Vector2F::new(10.0, 58.0),
+        ]);
+        let clip_path = scene.push_clip_path(ClipPath::new(clip_outline));
+        let paint = scene.push_paint(&Paint::black());
+        let draw_outline =
+            triangle([Vector2F::new(0.0, 0.0), Vector2F::new(64.0, 0.0), Vector2F::new(0.0, 64.0)]);
+        let mut path = DrawPath::new(draw_outline, paint);
+        path.set_clip_path(Some(clip_path));
+        scene.push_path(path);
+
+        // Returns the fills and tiles that the build sends.
+        let build = |scene: &Scene, transform: Transform2F| {
+            let results = Arc::new(Mutex::new(vec![]));
+            let listener_results = results.clone();
+            let listener = move |command| {
+                let result = match command {
+                    RenderCommand::AddFills(fills) => format!("{:?}", fills),
+                    RenderCommand::RenderMaskTiles { tiles, .. } => format!("{:?}", tiles),
+                    RenderCommand::DrawAlphaTiles(batch) => format!("{:?}", batch.tiles),
+                    _ => return,
+                };
+                listener_results.lock().unwrap().push(result);
+            };
+            let options = BuildOptions {
+                transform: RenderTransform::Transform2D(transform),
+                ..BuildOptions::default()
+            };
+            scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+            let results = results.lock().unwrap().clone();
+            results
+        };
+
+        // Building from the cache gives the same result as building from scratch.
+        let identity = Transform2F::default();
+        let uncached = build(&scene.clone(), identity);
+        assert!(!uncached.is_empty());
+        assert_eq!(build(&scene, identity), uncached);
+        assert!(scene.clip_cache.entries.lock().unwrap()[0].is_some());
+        assert_eq!(build(&scene, identity), uncached);
+
+        // Changing the transform invalidates the cache.
+        let scale = Transform2F::from_scale(Vector2F::splat(0.5));
+        let scaled = build(&scene, scale);
+        assert_ne!(scaled, uncached);
+        assert_eq!(scaled, build(&scene.clone(), scale));
+    }
+
+    #[test]
+    fn test_non_finite_points_are_dropped() {
+        // Returns the fills that the build sends for a triangle, after calling `splice` between
+        // its first and second points.
+        let build = |splice: &dyn Fn(&mut Contour)| {
+            let mut contour = Contour::new();
+            contour.push_endpoint(Vector2F::new(4.0, 2.0));
+            splice(&mut contour);
+            contour.push_endpoint(Vector2F::new(60.0, 30.5));
+            contour.push_endpoint(Vector2F::new(10.0, 58.0));
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+
+            let mut scene = Scene::new();
+            scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(64.0)));
+            let paint = scene.push_paint(&Paint::black());
+            scene.push_path(DrawPath::new(outline, paint));
+
+            let fills = Arc::new(Mutex::new(vec![]));
+            let listener_fills = fills.clone();
+            let listener = move |command| {
+                if let RenderCommand::AddFills(fills) = command {
+                    listener_fills.lock().unwrap().push(format!("{:?}", fills));
+                }
+            };
+            scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor).unwrap();
+            let fills = fills.lock().unwrap().clone();
+            fills
+        };
+
+        let expected = build(&|_| {});
+        assert!(!expected.is_empty());
+        assert_eq!(build(&|contour| contour.push_endpoint(Vector2F::new(f32::NAN, 3.0))),
+                   expected);
+        assert_eq!(build(&|contour| {
+            contour.push_endpoint(Vector2F::new(f32::INFINITY, f32::NEG_INFINITY));
+            contour.push_endpoint(Vector2F::splat(f32::NAN));
+        }), expected);
+
+        // Curves with non-finite control points become lines.
+        assert_eq!(build(&|contour| {
+            contour.push_quadratic(Vector2F::new(f32::NAN, 0.0), Vector2F::new(60.0, 30.5));
+        }), build(&|contour| contour.push_endpoint(Vector2F::new(60.0, 30.5))));
+    }
+
+    #[test]
+    fn test_invalid_view_boxes_are_rejected() {
+        // Returns the result of the build and the number of commands it sent.
+        let build = |view_box: RectF| {
+            let mut scene = Scene::new();
+            scene.set_view_box(view_box);
+            let mut contour = Contour::new();
+            contour.push_endpoint(Vector2F::new(2.0, 2.0));
+            contour.push_endpoint(Vector2F::new(8.0, 2.0));
+            contour.push_endpoint(Vector2F::new(5.0, 8.0));
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            let paint = scene.push_paint(&Paint::black());
+            scene.push_path(DrawPath::new(outline, paint));
+
+            let command_count = Arc::new(Mutex::new(0));
+            let listener_command_count = command_count.clone();
+            let listener = move |_| *listener_command_count.lock().unwrap() += 1;
+            let options = BuildOptions::default();
+            let result = scene.build(options, Box::new(listener), &SequentialExecutor);
+            let command_count = *command_count.lock().unwrap();
+            (result, command_count)
+        };
+
+        let view_box = RectF::new(Vector2F::default(), Vector2F::splat(16.0));
+        let (result, command_count) = build(view_box);
+        assert_eq!(result, Ok(()));
+        assert!(command_count > 0);
+        assert_eq!(build(RectF::default()).0, Ok(()));
+
+        for &view_box in &[
+            RectF::new(Vector2F::default(), Vector2F::new(f32::NAN, 16.0)),
+            RectF::new(Vector2F::splat(f32::NEG_INFINITY), Vector2F::splat(f32::INFINITY)),
+            RectF::new(Vector2F::default(), Vector2F::new(-16.0, 16.0)),
+            RectF::new(Vector2F::default(), Vector2F::splat(1.0e7)),
+            RectF::new(Vector2F::splat(1.0e9), Vector2F::splat(16.0)),
+        ] {
+            // The view box can hold NaNs, so it can't be compared.
+            let (result, command_count) = build(view_box);
+            match result {
+                Err(Error::InvalidViewBox(_)) => {}
+                _ => panic!("{:?} wasn't rejected: {:?}", view_box, result),
+            }
+            assert_eq!(command_count, 0);
+        }
+    }
+
+    #[test]
+    fn test_cancelled_builds_skip_remaining_paths() {
+        // A row of triangles, each far enough apart to get its own fills.
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::new(256.0, 16.0)));
+        let paint = scene.push_paint(&Paint::black());
+        for index in 0..16 {
+            let origin = Vector2F::new(index as f32 * 16.0, 0.0);
+            let mut contour = Contour::new();
+            contour.push_endpoint(origin + Vector2F::new(2.0, 2.0));
+            contour.push_endpoint(origin + Vector2F::new(14.0, 2.0));
+            contour.push_endpoint(origin + Vector2F::new(8.0, 14.0));
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            scene.push_path(DrawPath::new(outline, paint));
         }
+
+        // Cancels the build when the first path's fills arrive, and returns the result along
+        // with the kinds of commands sent.
+        let build = |token: CancellationToken| {
+            let commands = Arc::new(Mutex::new(vec![]));
+            let (listener_commands, listener_token) = (commands.clone(), token.clone());
+            let listener = move |command: RenderCommand| {
+                let name = match command {
+                    RenderCommand::AddFills(_) => {
+                        listener_token.cancel();
+                        "AddFills"
+                    }
+                    RenderCommand::DrawAlphaTiles(_) => "DrawAlphaTiles",
+                    RenderCommand::Finish { .. } => "Finish",
+                    _ => "Other",
+                };
+                listener_commands.lock().unwrap().push(name);
+            };
+            let options = BuildOptions {
+                cancellation_token: Some(token),
+                max_fills_per_command: usize::MAX,
+                ..BuildOptions::default()
+            };
+            let result = scene.build(options, Box::new(listener), &SequentialExecutor);
+            let commands = commands.lock().unwrap().clone();
+            (result, commands)
+        };
+
+        let (result, commands) = build(CancellationToken::new());
+        assert_eq!(result, Err(Error::Cancelled));
+        assert_eq!(commands.iter().filter(|&&name| name == "AddFills").count(), 1);
+        assert!(!commands.contains(&"DrawAlphaTiles"));
+        assert!(!commands.contains(&"Finish"));
+
+        let token = CancellationToken::new();
+        token.cancel();
+        assert_eq!(build(token), (Err(Error::Cancelled), vec![]));
+    }
+
+    #[test]
+    fn test_parallel_culling_matches_sequential_culling() {
+        let rect_outline = |rect: RectF| {
+            let mut contour = Contour::new();
+            contour.push_endpoint(rect.origin());
+            contour.push_endpoint(rect.upper_right());
+            contour.push_endpoint(rect.lower_right());
+            contour.push_endpoint(rect.lower_left());
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            outline
+        };
+
+        // Overlapping squares, some opaque enough to hide what's behind them, in a few blend
+        // modes, with every third one clipped.
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(256.0)));
+        let clip_rect = RectF::new(Vector2F::splat(24.5), Vector2F::splat(200.0));
+        let clip_path = scene.push_clip_path(ClipPath::new(rect_outline(clip_rect)));
+        let paints = [
+            scene.push_paint(&Paint::black()),
+            scene.push_paint(&Paint::Color(ColorU::new(255, 0, 0, 128))),
+            scene.push_paint(&Paint::Color(ColorU::new(0, 0, 255, 255))),
+        ];
+        for index in 0..64 {
+            let origin = Vector2F::new((index * 37 % 200) as f32, (index * 53 % 200) as f32);
+            let size = Vector2F::splat(16.0 + (index % 5) as f32 * 12.25);
+            let mut path = DrawPath::new(rect_outline(RectF::new(origin, size)),
+                                         paints[index % paints.len()]);
+            if index % 7 == 3 {
+                path.set_blend_mode(BlendMode::Multiply);
+            }
+            if index % 3 == 1 {
+                path.set_clip_path(Some(clip_path));
+            }
+            scene.push_path(path);
+        }
+
+        // Returns the tiles that survive culling, in the order they're drawn.
+        fn culled_tiles<E>(scene: &Scene, options: BuildOptions, executor: &E) -> Vec<String>
+                           where E: Executor {
+            let tiles = Arc::new(Mutex::new(vec![]));
+            let listener_tiles = tiles.clone();
+            let listener = move |command| {
+                let tiles = match command {
+                    RenderCommand::RenderMaskTiles { tiles, fill_rule } => {
+                        format!("mask {:?} {:?}", fill_rule, tiles)
+                    }
+                    RenderCommand::DrawAlphaTiles(batch) => {
+                        format!("alpha {:?} {:?}", batch.blend_mode, batch.tiles)
+                    }
+                    RenderCommand::DrawSolidTiles(batch) => format!("solid {:?}", batch.tiles),
+                    _ => return,
+                };
+                listener_tiles.lock().unwrap().push(tiles);
+            };
+            scene.build(options, Box::new(listener), executor).unwrap();
+            let tiles = tiles.lock().unwrap().clone();
+            tiles
+        }
+
+        for &merge_batches in &[false, true] {
+            let options = BuildOptions { merge_batches, ..BuildOptions::default() };
+            let sequential = culled_tiles(&scene, options.clone(), &SequentialExecutor);
+            let parallel = culled_tiles(&scene, options, &RayonExecutor);
+            assert!(sequential.iter().any(|tiles| tiles.starts_with("solid")));
+            assert_eq!(parallel, sequential);
+        }
+    }
+
+    #[test]
+    fn test_culled_alpha_tiles_take_no_mask_space() {
+        let rect_outline = |rect: RectF| {
+            let mut contour = Contour::new();
+            contour.push_endpoint(rect.origin());
+            contour.push_endpoint(rect.upper_right());
+            contour.push_endpoint(rect.lower_right());
+            contour.push_endpoint(rect.lower_left());
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            outline
+        };
+
+        // A small square hidden behind an opaque one covering the scene, then a visible one.
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(64.0)));
+        let paint = scene.push_paint(&Paint::black());
+        let small_square = |origin| RectF::new(origin, Vector2F::splat(7.0));
+        scene.push_path(DrawPath::new(rect_outline(small_square(Vector2F::splat(20.5))), paint));
+        scene.push_path(DrawPath::new(rect_outline(scene.view_box()), paint));
+        scene.push_path(DrawPath::new(rect_outline(small_square(Vector2F::splat(36.5))), paint));
+
+        let mask_tiles = Arc::new(Mutex::new(vec![]));
+        let alpha_tiles = Arc::new(Mutex::new(vec![]));
+        let (listener_mask_tiles, listener_alpha_tiles) = (mask_tiles.clone(), alpha_tiles.clone());
+        let listener = move |command| {
+            match command {
+                RenderCommand::RenderMaskTiles { tiles, .. } => {
+                    listener_mask_tiles.lock().unwrap().extend(tiles)
+                }
+                RenderCommand::DrawAlphaTiles(batch) => {
+                    listener_alpha_tiles.lock().unwrap().extend(batch.tiles)
+                }
+                _ => {}
+            }
+        };
+        scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor).unwrap();
+
+        // The hidden square is culled, and has no mask tile. The drawn tiles use the first mask
+        // tiles, one each.
+        let (mask_tiles, alpha_tiles) = (mask_tiles.lock().unwrap(), alpha_tiles.lock().unwrap());
+        let tile_positions: Vec<_> = alpha_tiles.iter().map(|alpha_tile| {
+            alpha_tile.upper_left.tile_position()
+        }).collect();
+        assert!(!tile_positions.contains(&Vector2I::splat(1)));
+        assert!(tile_positions.contains(&Vector2I::splat(2)));
+        assert_eq!(mask_tiles.len(), alpha_tiles.len());
+        for (index, alpha_tile) in alpha_tiles.iter().enumerate() {
+            let mask_uv = calculate_mask_uv(index as u16, Vector2I::default());
+            let mask_uv = (mask_uv.x() as u16, mask_uv.y() as u16);
+            assert_eq!((alpha_tile.upper_left.mask_u, alpha_tile.upper_left.mask_v), mask_uv);
+            assert_eq!((mask_tiles[index].upper_left.mask_u, mask_tiles[index].upper_left.mask_v),
+                       mask_uv);
+        }
+    }
+
+    #[test]
+    fn test_far_view_boxes_give_tile_positions_relative_to_their_chunk() {
+        let rect_outline = |rect: RectF| {
+            let mut contour = Contour::new();
+            contour.push_endpoint(rect.origin());
+            contour.push_endpoint(rect.upper_right());
+            contour.push_endpoint(rect.lower_right());
+            contour.push_endpoint(rect.lower_left());
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            outline
+        };
+
+        // A small square within one tile, then a tile-aligned square two tiles across, all well
+        // beyond what `i16` tile coordinates can reach.
+        let view_origin = Vector2F::new(1.0e6, -2.0e6);
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(view_origin, Vector2F::splat(64.0)));
+        let paint = scene.push_paint(&Paint::black());
+        let small_square = RectF::new(view_origin + Vector2F::splat(20.5), Vector2F::splat(7.0));
+        let big_square = RectF::new(view_origin + Vector2F::splat(32.0), Vector2F::splat(32.0));
+        scene.push_path(DrawPath::new(rect_outline(small_square), paint));
+        scene.push_path(DrawPath::new(rect_outline(big_square), paint));
+
+        let chunk_origin = Arc::new(Mutex::new(None));
+        let tiles = Arc::new(Mutex::new((vec![], vec![])));
+        let (listener_chunk_origin, listener_tiles) = (chunk_origin.clone(), tiles.clone());
+        let listener = move |command| {
+            match command {
+                RenderCommand::Start { chunk_origin, .. } => {
+                    *listener_chunk_origin.lock().unwrap() = Some(chunk_origin)
+                }
+                RenderCommand::DrawAlphaTiles(batch) => {
+                    listener_tiles.lock().unwrap().0.extend(batch.tiles.iter().map(|tile| {
+                        tile.upper_left.tile_position()
+                    }))
+                }
+                RenderCommand::DrawSolidTiles(batch) => {
+                    listener_tiles.lock().unwrap().1.extend(batch.tiles.iter().map(|tile| {
+                        Vector2I::new(tile.upper_left.tile_x as i32, tile.upper_left.tile_y as i32)
+                    }))
+                }
+                _ => {}
+            }
+        };
+        scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor).unwrap();
+
+        // The view box starts at tile (62500, -125000), in the chunk at (61, -123).
+        let chunk_origin = chunk_origin.lock().unwrap().unwrap();
+        assert_eq!(chunk_origin, Vector2I::new(61, -123).scale(CHUNK_SIZE));
+        let (ref alpha_tiles, ref solid_tiles) = *tiles.lock().unwrap();
+        let mut tiles: Vec<_> = alpha_tiles.iter().chain(solid_tiles).map(|&tile| {
+            tile + chunk_origin
+        }).collect();
+        tiles.sort_by_key(|tile| (tile.y(), tile.x()));
+        assert_eq!(tiles, vec![
+            Vector2I::new(62501, -124999),
+            Vector2I::new(62502, -124998),
+            Vector2I::new(62503, -124998),
+            Vector2I::new(62502, -124997),
+            Vector2I::new(62503, -124997),
+        ]);
+    }
+
+    #[test]
+    fn test_viewports_share_masks_and_reuse_tiling() {
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::splat(20.5));
+        contour.push_endpoint(Vector2F::new(27.5, 20.5));
+        contour.push_endpoint(Vector2F::splat(27.5));
+        contour.push_endpoint(Vector2F::new(20.5, 27.5));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::new(128.0, 96.0)));
+        let paint = scene.push_paint(&Paint::black());
+        scene.push_path(DrawPath::new(outline, paint));
+
+        // The main view, the same view four tiles to the right, and a minimap below at half
+        // scale.
+        let viewport_size = Vector2F::splat(64.0);
+        let viewports = [
+            Viewport { transform: Transform2F::default(), rect: RectF::new(Vector2F::default(),
+                                                                           viewport_size) },
+            Viewport {
+                transform: Transform2F::from_translation(Vector2F::new(64.0, 0.0)),
+                rect: RectF::new(Vector2F::new(64.0, 0.0), viewport_size),
+            },
+            Viewport {
+                transform: Transform2F::from_translation(Vector2F::new(0.0, 64.0)) *
+                    Transform2F::from_scale(Vector2F::splat(0.5)),
+                rect: RectF::new(Vector2F::new(0.0, 64.0), viewport_size.scale(0.5)),
+            },
+        ];
+
+        let commands = Arc::new(Mutex::new(vec![]));
+        let listener_commands = commands.clone();
+        let listener = move |command| listener_commands.lock().unwrap().push(command);
+        scene.build_viewports(BuildOptions::default(),
+                              &viewports,
+                              Box::new(listener),
+                              &SequentialExecutor).unwrap();
+
+        let commands = commands.lock().unwrap();
+        let (mut start_count, mut finish_count) = (0, 0);
+        let (mut mask_tiles, mut alpha_tiles): (Vec<MaskTile>, Vec<AlphaTile>) = (vec![], vec![]);
+        for command in commands.iter() {
+            match *command {
+                RenderCommand::Start { .. } => start_count += 1,
+                RenderCommand::Finish { .. } => finish_count += 1,
+                RenderCommand::RenderMaskTiles { ref tiles, .. } => mask_tiles.extend(tiles),
+                RenderCommand::DrawAlphaTiles(ref batch) => alpha_tiles.extend(&batch.tiles),
+                _ => {}
+            }
+        }
+        assert_eq!((start_count, finish_count), (1, 1));
+
+        // The second viewport draws the first one's tile again, through the same mask. The
+        // minimap is tiled itself, and its mask comes after the first one's.
+        let tile_positions: Vec<_> = alpha_tiles.iter().map(|alpha_tile| {
+            alpha_tile.upper_left.tile_position()
+        }).collect();
+        assert_eq!(tile_positions, vec![
+            Vector2I::new(1, 1),
+            Vector2I::new(5, 1),
+            Vector2I::new(0, 4),
+        ]);
+        assert_eq!(mask_tiles.len(), 2);
+        let mask_uvs: Vec<_> = alpha_tiles.iter().map(|alpha_tile| {
+            (alpha_tile.upper_left.mask_u, alpha_tile.upper_left.mask_v)
+        }).collect();
+        let mask_uv = |index| {
+            let mask_uv = calculate_mask_uv(index, Vector2I::default());
+            (mask_uv.x() as u16, mask_uv.y() as u16)
+        };
+        assert_eq!(mask_uvs, vec![mask_uv(0), mask_uv(0), mask_uv(1)]);
+    }
+
+    #[test]
+    fn test_stereo_builds_draw_each_eye_into_its_own_layer() {
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::splat(20.5));
+        contour.push_endpoint(Vector2F::new(27.5, 20.5));
+        contour.push_endpoint(Vector2F::splat(27.5));
+        contour.push_endpoint(Vector2F::new(20.5, 27.5));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(64.0)));
+        let paint = scene.push_paint(&Paint::black());
+        scene.push_path(DrawPath::new(outline, paint));
+
+        let eye_transforms = [
+            RenderTransform::Transform2D(Transform2F::from_translation(Vector2F::new(-3.0, 0.0))),
+            RenderTransform::Transform2D(Transform2F::from_translation(Vector2F::new(3.0, 0.0))),
+        ];
+        let commands = Arc::new(Mutex::new(vec![]));
+        let listener_commands = commands.clone();
+        let listener = move |command| listener_commands.lock().unwrap().push(command);
+        scene.build_stereo(BuildOptions::default(),
+                           eye_transforms,
+                           Box::new(listener),
+                           &SequentialExecutor).unwrap();
+
+        // Paints are uploaded once, before the first layer, and each layer gets its own draws.
+        let commands = commands.lock().unwrap();
+        let (mut start_count, mut finish_count, mut page_allocation_count) = (0, 0, 0);
+        let mut layers = vec![];
+        let mut alpha_tile_counts = vec![];
+        for command in commands.iter() {
+            match *command {
+                RenderCommand::Start { .. } => start_count += 1,
+                RenderCommand::Finish { .. } => finish_count += 1,
+                RenderCommand::AllocateTexturePages(_) => {
+                    assert!(layers.is_empty());
+                    page_allocation_count += 1;
+                }
+                RenderCommand::BeginLayer { layer, .. } => {
+                    layers.push(layer);
+                    alpha_tile_counts.push(0);
+                }
+                RenderCommand::DrawAlphaTiles(ref batch) => {
+                    *alpha_tile_counts.last_mut().unwrap() += batch.tiles.len();
+                }
+                _ => {}
+            }
+        }
+        assert_eq!((start_count, finish_count, page_allocation_count), (1, 1, 1));
+        assert_eq!(layers, vec![0, 1]);
+        assert_eq!(alpha_tile_counts, vec![1, 1]);
+    }
+
+    #[test]
+    fn test_pictures_reuse_tilings_at_whole_tile_offsets() {
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::splat(20.5));
+        contour.push_endpoint(Vector2F::new(27.5, 20.5));
+        contour.push_endpoint(Vector2F::splat(27.5));
+        contour.push_endpoint(Vector2F::new(20.5, 27.5));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        let mut recording = Scene::new();
+        let paint = recording.push_paint(&Paint::black());
+        recording.push_path(DrawPath::new(outline.clone(), paint));
+        let picture = Picture::new(recording);
+
+        // The picture played at whole-tile offsets, and once at a subpixel offset, which needs a
+        // tiling of its own, next to the same paths drawn directly.
+        let view_box = RectF::new(Vector2F::default(), Vector2F::splat(128.0));
+        let (mut played_scene, mut drawn_scene) = (Scene::new(), Scene::new());
+        played_scene.set_view_box(view_box);
+        drawn_scene.set_view_box(view_box);
+        let drawn_paint = drawn_scene.push_paint(&Paint::black());
+        let offsets = [Vector2F::default(), Vector2F::new(32.0, 48.0), Vector2F::new(0.25, 64.0)];
+        for &offset in &offsets {
+            let transform = Transform2F::from_translation(offset);
+            played_scene.draw_picture(&picture, &transform);
+            let mut drawn_outline = outline.clone();
+            drawn_outline.transform(&transform);
+            drawn_scene.push_path(DrawPath::new(drawn_outline, drawn_paint));
+        }
+
+        fn build(scene: &Scene) -> (Vec<[u16; 7]>, Vec<Vector2I>) {
+            let commands = Arc::new(Mutex::new(vec![]));
+            let listener_commands = commands.clone();
+            let listener = move |command| listener_commands.lock().unwrap().push(command);
+            scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor).unwrap();
+
+            let (mut fills, mut tile_positions) = (vec![], vec![]);
+            for command in commands.lock().unwrap().iter() {
+                match *command {
+                    RenderCommand::AddFills(ref batch) => {
+                        fills.extend(batch.iter().map(|&fill| {
+                            let (px, subpx) = (fill.px, fill.subpx);
+                            [px.from as u16, px.to as u16,
+                             subpx.from_x as u16, subpx.from_y as u16,
+                             subpx.to_x as u16, subpx.to_y as u16,
+                             fill.alpha_tile_index]
+                        }));
+                    }
+                    RenderCommand::DrawAlphaTiles(ref batch) => {
+                        tile_positions.extend(batch.tiles.iter().map(|alpha_tile| {
+                            alpha_tile.upper_left.tile_position()
+                        }));
+                    }
+                    _ => {}
+                }
+            }
+            (fills, tile_positions)
+        }
+
+        let expected = build(&drawn_scene);
+        assert_eq!(build(&played_scene), expected);
+        assert_eq!(picture.tile_cache.tiling_count(0), 2);
+
+        // Building again tiles nothing.
+        assert_eq!(build(&played_scene), expected);
+        assert_eq!(picture.tile_cache.tiling_count(0), 2);
+    }
+
+    #[test]
+    fn test_paths_outside_the_view_box_are_culled() {
+        fn square(origin: Vector2F) -> Outline {
+            let mut contour = Contour::new();
+            contour.push_endpoint(origin);
+            contour.push_endpoint(origin + Vector2F::new(7.0, 0.0));
+            contour.push_endpoint(origin + Vector2F::splat(7.0));
+            contour.push_endpoint(origin + Vector2F::new(0.0, 7.0));
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            outline
+        }
+
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(64.0)));
+        let paint = scene.push_paint(&Paint::black());
+        scene.push_path(DrawPath::new(square(Vector2F::splat(20.5)), paint));
+        scene.push_path(DrawPath::new(square(Vector2F::splat(100.5)), paint));
+
+        let build = |transform| {
+            let commands = Arc::new(Mutex::new(vec![]));
+            let listener_commands = commands.clone();
+            let listener = move |command| listener_commands.lock().unwrap().push(command);
+            let options = BuildOptions {
+                transform: RenderTransform::Transform2D(transform),
+                ..BuildOptions::default()
+            };
+            scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+
+            let commands = commands.lock().unwrap();
+            let (mut alpha_tile_count, mut culled_path_count) = (0, None);
+            for command in commands.iter() {
+                match *command {
+                    RenderCommand::DrawAlphaTiles(ref batch) => {
+                        alpha_tile_count += batch.tiles.len()
+                    }
+                    RenderCommand::Finish { culled_path_count: count, .. } => {
+                        culled_path_count = Some(count)
+                    }
+                    _ => {}
+                }
+            }
+            (alpha_tile_count, culled_path_count.unwrap())
+        };
+
+        assert_eq!(build(Transform2F::default()), (1, 1));
+
+        // Moving the second square into the view box moves the first one out.
+        let transform = Transform2F::from_translation(Vector2F::splat(-80.0));
+        assert_eq!(build(transform), (1, 1));
+
+        let transform = Transform2F::from_scale(Vector2F::splat(0.5));
+        assert_eq!(build(transform), (2, 0));
+    }
+
+    #[test]
+    fn test_pooled_buffers_are_reused_empty() {
+        let pool = BuildPool::default();
+        let bounds = RectF::new(Vector2F::default(), Vector2F::splat(64.0));
+        let mut built_path = pool.built_path(bounds, FillRule::Winding);
+        for y in 0..4 {
+            built_path.solid_tiles.push(SolidTileInfo::new(Vector2I::new(0, y)));
+            built_path.tiles.get_mut(Vector2I::new(1, y)).unwrap().backdrop = 1;
+        }
+        let solid_tiles_ptr = built_path.solid_tiles.as_ptr();
+        pool.recycle(vec![built_path].into_iter());
+
+        let built_path = pool.built_path(bounds, FillRule::EvenOdd);
+        assert_eq!(built_path.solid_tiles.as_ptr(), solid_tiles_ptr);
+        assert!(built_path.solid_tiles.is_empty());
+        assert!(built_path.tiles.iter().all(|(_, tile)| tile.backdrop == 0));
+        assert_eq!(built_path.fill_rule, FillRule::EvenOdd);
+    }
+
+    #[test]
+    fn test_rebuilds_with_pooled_buffers_match_the_first_build() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(128.0)));
+        let paint = scene.push_paint(&Paint::black());
+        for index in 0..8 {
+            let origin = Vector2F::splat(index as f32 * 12.5);
+            let mut contour = Contour::new();
+            contour.push_endpoint(origin);
+            contour.push_endpoint(origin + Vector2F::new(20.0, 0.0));
+            contour.push_endpoint(origin + Vector2F::splat(20.0));
+            contour.push_endpoint(origin + Vector2F::new(0.0, 20.0));
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            scene.push_path(DrawPath::new(outline, paint));
+        }
+
+        // Returns the commands describing fills and tiles.
+        let build = || {
+            let commands = Arc::new(Mutex::new(vec![]));
+            let listener_commands = commands.clone();
+            let listener = move |command| {
+                match command {
+                    RenderCommand::AddFills(_) |
+                    RenderCommand::RenderMaskTiles { .. } |
+                    RenderCommand::DrawAlphaTiles(_) |
+                    RenderCommand::DrawSolidTiles(_) => {
+                        listener_commands.lock().unwrap().push(format!("{:?}", command))
+                    }
+                    _ => {}
+                }
+            };
+            scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor).unwrap();
+            let commands = commands.lock().unwrap().clone();
+            commands
+        };
+
+        let first_commands = build();
+        assert!(!scene.build_pool.buffers.lock().unwrap().tiles.is_empty());
+        assert_eq!(build(), first_commands);
+        assert_eq!(build(), first_commands);
+    }
+
+    #[test]
+    fn test_hidden_paths_build_no_tiles() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(64.0)));
+        let paint = scene.push_paint(&Paint::black());
+        let outline = rect_outline(RectF::new(Vector2F::splat(8.5), Vector2F::splat(32.0)));
+        scene.push_path(DrawPath::new(outline, paint));
+
+        // Returns the number of tiles drawn.
+        let build = |scene: &Scene| -> usize {
+            build_commands(scene, BuildOptions::default()).into_iter().map(|command| {
+                match command {
+                    RenderCommand::DrawAlphaTiles(batch) => batch.tiles.len(),
+                    RenderCommand::DrawSolidTiles(batch) => batch.tiles.len(),
+                    _ => 0,
+                }
+            }).sum()
+        };
+
+        assert!(build(&scene) > 0);
+        scene.set_path_visible(0, false).unwrap();
+        assert_eq!(build(&scene), 0);
+        scene.set_path_visible(0, true).unwrap();
+        assert!(build(&scene) > 0);
+        assert_eq!(scene.set_path_visible(1, false), Err(Error::NoSuchPath(1)));
+    }
+
+    #[test]
+    fn test_path_tiles_are_the_tiles_a_path_builds_into() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(128.0)));
+        let paint = scene.push_paint(&Paint::black());
+        let outline = rect_outline(RectF::new(Vector2F::splat(4.5), Vector2F::splat(88.0)));
+        scene.push_path(DrawPath::new(outline.clone(), paint));
+        let clip_outline = rect_outline(RectF::new(Vector2F::default(), Vector2F::splat(40.5)));
+        let clip_path = scene.push_clip_path(ClipPath::new(clip_outline));
+        let mut clipped_path = DrawPath::new(outline, paint);
+        clipped_path.set_clip_path(Some(clip_path));
+        scene.push_path(clipped_path);
+
+        // The square covers tiles 0 to 5 across and down. Its edges are partly covered.
+        let mut path_tiles = scene.build_path_tiles(0, BuildOptions::default()).unwrap();
+        path_tiles.alpha_tiles.sort_by_key(|tile| (tile.y(), tile.x()));
+        path_tiles.solid_tiles.sort_by_key(|tile| (tile.y(), tile.x()));
+        let (mut alpha_tiles, mut solid_tiles) = (vec![], vec![]);
+        for y in 0..6 {
+            for x in 0..6 {
+                if x == 0 || x == 5 || y == 0 || y == 5 {
+                    alpha_tiles.push(Vector2I::new(x, y));
+                } else {
+                    solid_tiles.push(Vector2I::new(x, y));
+                }
+            }
+        }
+        assert_eq!(path_tiles.alpha_tiles, alpha_tiles);
+        assert_eq!(path_tiles.solid_tiles, solid_tiles);
+
+        // Tiles follow the transform.
+        let options = BuildOptions {
+            transform: RenderTransform::Transform2D(Transform2F::from_translation(
+                Vector2F::new(16.0, 0.0))),
+            ..BuildOptions::default()
+        };
+        let mut moved_tiles = scene.build_path_tiles(0, options).unwrap();
+        moved_tiles.solid_tiles.sort_by_key(|tile| (tile.y(), tile.x()));
+        let expected_tiles: Vec<_> = solid_tiles.iter().map(|&tile| {
+            tile + Vector2I::new(1, 0)
+        }).collect();
+        assert_eq!(moved_tiles.solid_tiles, expected_tiles);
+
+        // The clip path is applied.
+        let clipped_tiles = scene.build_path_tiles(1, BuildOptions::default()).unwrap();
+        assert!(!clipped_tiles.alpha_tiles.is_empty());
+        assert!(clipped_tiles.alpha_tiles.len() + clipped_tiles.solid_tiles.len() <
+                alpha_tiles.len() + solid_tiles.len());
+        assert!(clipped_tiles.alpha_tiles.iter().chain(&clipped_tiles.solid_tiles).all(|tile| {
+            tile.x() < 3 && tile.y() < 3
+        }));
+
+        // Hidden and missing paths cover nothing.
+        scene.set_path_visible(1, false).unwrap();
+        assert_eq!(scene.build_path_tiles(1, BuildOptions::default()), Ok(PathTiles::default()));
+        assert_eq!(scene.build_path_tiles(2, BuildOptions::default()), Err(Error::NoSuchPath(2)));
     }
 }
@@ -17,22 +17,32 @@
 //!
 //!   * Avoiding UI latency by building scenes off the main thread.
 //!
+//!   * Overlapping CPU tiling of one frame with GPU rendering of the last, with
+//!     `SceneProxy::build_pipelined()`.
+//!
 //! You don't need to use this API to use Pathfinder; it's only a convenience.
 
 use crate::concurrent::executor::Executor;
+use crate::error::Error;
 use crate::gpu::renderer::Renderer;
 use crate::gpu_data::RenderCommand;
 use crate::options::{BuildOptions, RenderCommandListener};
 use crate::scene::Scene;
 use pathfinder_geometry::rect::RectF;
 use pathfinder_gpu::Device;
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::collections::BTreeSet;
+use std::mem;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 
 const MAX_MESSAGES_IN_FLIGHT: usize = 1024;
 
+const DEFAULT_MAX_FRAMES_IN_FLIGHT: usize = 2;
+
 pub struct SceneProxy {
     sender: Sender<MainToWorkerMsg>,
+    pipeline: Arc<FramePipeline>,
 }
 
 impl SceneProxy {
@@ -43,8 +53,12 @@ impl SceneProxy {
     pub fn from_scene<E>(scene: Scene, executor: E) -> SceneProxy
                          where E: Executor + Send + 'static {
         let (main_to_worker_sender, main_to_worker_receiver) = mpsc::channel();
-        thread::spawn(move || scene_thread(scene, executor, main_to_worker_receiver));
-        SceneProxy { sender: main_to_worker_sender }
+        let pipeline = Arc::new(FramePipeline::new());
+        let worker_pipeline = pipeline.clone();
+        thread::spawn(move || {
+            scene_thread(scene, executor, worker_pipeline, main_to_worker_receiver)
+        });
+        SceneProxy { sender: main_to_worker_sender, pipeline }
     }
 
     #[inline]
@@ -52,44 +66,127 @@ impl SceneProxy {
         self.sender.send(MainToWorkerMsg::ReplaceScene(new_scene)).unwrap();
     }
 
+    /// Swaps `new_scene` in for the worker's scene, returning the old one.
+    ///
+    /// Apps that rebuild their scene every frame can double-buffer this way: fill in the returned
+    /// scene for the next frame, reusing its allocations, while the worker builds this one. This
+    /// blocks until the worker has finished any builds queued before it.
+    #[inline]
+    pub fn swap_scene(&self, new_scene: Scene) -> Scene {
+        let (sender, receiver) = mpsc::channel();
+        self.sender.send(MainToWorkerMsg::SwapScene(new_scene, sender)).unwrap();
+        receiver.recv().unwrap()
+    }
+
+    /// Modifies the worker's scene in place, without copying it.
+    ///
+    /// `update` runs on the worker thread, after any builds queued before it and before any
+    /// queued after it.
+    #[inline]
+    pub fn update<F>(&self, update: F) where F: FnOnce(&mut Scene) + Send + 'static {
+        self.sender.send(MainToWorkerMsg::Update(Box::new(update))).unwrap();
+    }
+
     #[inline]
     pub fn set_view_box(&self, new_view_box: RectF) {
         self.sender.send(MainToWorkerMsg::SetViewBox(new_view_box)).unwrap();
     }
 
+    /// Shows or hides a single path. See `Scene::set_path_visible()`.
+    ///
+    /// The change is applied on the worker thread, so an out-of-range index is only logged.
+    #[inline]
+    pub fn set_path_visible(&self, path_index: usize, visible: bool) {
+        self.sender.send(MainToWorkerMsg::SetPathVisible(path_index, visible)).unwrap();
+    }
+
+    /// Builds the scene on the worker thread, sending the commands to `listener`.
+    ///
+    /// This returns before the scene is built, so only a missing worker thread is reported here;
+    /// build errors are logged. Use `build_with_stream()` to receive them.
     #[inline]
     pub fn build_with_listener(&self,
                                options: BuildOptions,
-                               listener: Box<dyn RenderCommandListener>) {
-        self.sender.send(MainToWorkerMsg::Build(options, listener)).unwrap();
+                               listener: Box<dyn RenderCommandListener>)
+                               -> Result<(), Error> {
+        self.sender
+            .send(MainToWorkerMsg::Build(options, listener))
+            .map_err(|_| Error::SceneThreadExited)
     }
 
+    /// Builds the scene on the worker thread, returning a stream of its commands. If the build
+    /// fails, the stream yields the error instead.
+    ///
+    /// To abandon a frame that's been superseded, cancel the `CancellationToken` in its options.
+    /// A build that's still queued behind others returns as soon as it's reached.
     #[inline]
     pub fn build_with_stream(&self, options: BuildOptions) -> RenderCommandStream {
         let (sender, receiver) = mpsc::sync_channel(MAX_MESSAGES_IN_FLIGHT);
-        let listener = Box::new(move |command| drop(sender.send(command)));
-        self.build_with_listener(options, listener);
+        // If the worker thread has exited, the sender is dropped along with the message, and the
+        // stream reports that.
+        drop(self.sender.send(MainToWorkerMsg::BuildWithStream(options, sender)));
         RenderCommandStream::new(receiver)
     }
 
+    /// Builds the scene on the worker thread without waiting for earlier frames to be rendered,
+    /// returning a stream of its commands.
+    ///
+    /// This lets the worker tile frame N+1 while the render thread is still consuming frame N's
+    /// stream. A frame is in flight from this call until its stream ends or is dropped. If
+    /// `max_frames_in_flight()` earlier frames are still in flight, the worker waits for the oldest
+    /// to be rendered before building the next, and any messages queued behind it wait too. Don't
+    /// block on the proxy (for example, in `copy_scene()`) while holding that many unfinished
+    /// streams.
+    ///
+    /// Unlike `build_with_stream()`, the worker never waits on the render thread mid-build, so a
+    /// frame's commands are buffered in full.
+    pub fn build_pipelined(&self, options: BuildOptions) -> RenderCommandStream {
+        let (sender, receiver) = mpsc::channel();
+        let frame = self.pipeline.begin_frame();
+        drop(self.sender.send(MainToWorkerMsg::BuildPipelined(options, sender, frame)));
+        RenderCommandStream::pipelined(receiver, FrameGuard {
+            pipeline: self.pipeline.clone(),
+            frame,
+        })
+    }
+
+    /// Returns the number of pipelined frames that may be built ahead of the render thread,
+    /// counting the one being built. The default is 2.
+    #[inline]
+    pub fn max_frames_in_flight(&self) -> usize {
+        self.pipeline.state.lock().unwrap().max_frames_in_flight
+    }
+
+    /// Sets the number of pipelined frames that may be built ahead of the render thread. Each
+    /// extra frame overlaps more CPU work with rendering, at the cost of a frame of latency and
+    /// the memory for its commands.
+    ///
+    /// Panics if `max_frames_in_flight` is zero.
+    pub fn set_max_frames_in_flight(&self, max_frames_in_flight: usize) {
+        assert!(max_frames_in_flight > 0, "At least one frame must be allowed in flight!");
+        self.pipeline.state.lock().unwrap().max_frames_in_flight = max_frames_in_flight;
+        self.pipeline.cond.notify_all();
+    }
+
     /// A convenience method to build a scene and send the resulting commands
     /// to the given renderer.
     ///
-    /// Exactly equivalent to:
-    ///
-    /// ```norun
-    /// for command in scene_proxy.build_with_stream(options) {
-    ///     renderer.render_command(&command)
-    /// }
-    /// ```
+    /// Commands that fail to render are skipped, and the first error is returned once the scene
+    /// has ended.
     #[inline]
     pub fn build_and_render<D>(&self, renderer: &mut Renderer<D>, build_options: BuildOptions)
+                               -> Result<(), Error>
                                where D: Device {
+        let mut result = Ok(());
         renderer.begin_scene();
         for command in self.build_with_stream(build_options) {
-            renderer.render_command(&command);
+            let command_result = command.and_then(|command| renderer.render_command(&command));
+            if result.is_ok() {
+                result = command_result;
+            }
         }
         renderer.end_scene();
+        result
     }
 
     #[inline]
@@ -102,14 +199,47 @@ impl SceneProxy {
 
 fn scene_thread<E>(mut scene: Scene,
                    executor: E,
+                   pipeline: Arc<FramePipeline>,
                    main_to_worker_receiver: Receiver<MainToWorkerMsg>)
                    where E: Executor {
     while let Ok(msg) = main_to_worker_receiver.recv() {
         match msg {
             MainToWorkerMsg::ReplaceScene(new_scene) => scene = new_scene,
             MainToWorkerMsg::CopyScene(sender) => sender.send(scene.clone()).unwrap(),
+            MainToWorkerMsg::SwapScene(new_scene, sender) => {
+                sender.send(mem::replace(&mut scene, new_scene)).unwrap()
+            }
+            MainToWorkerMsg::Update(update) => update(&mut scene),
             MainToWorkerMsg::SetViewBox(new_view_box) => scene.set_view_box(new_view_box),
-            MainToWorkerMsg::Build(options, listener) => scene.build(options, listener, &executor)
+            MainToWorkerMsg::SetPathVisible(path_index, visible) => {
+                if let Err(error) = scene.set_path_visible(path_index, visible) {
+                    warn!("Failed to set a path's visibility: {}", error);
+                }
+            }
+            MainToWorkerMsg::Build(options, listener) => {
+                match scene.build(options, listener, &executor) {
+                    Ok(()) | Err(Error::Cancelled) => {}
+                    Err(error) => warn!("Failed to build the scene: {}", error),
+                }
+            }
+            MainToWorkerMsg::BuildWithStream(options, sender) => {
+                let listener_sender = sender.clone();
+                let listener = Box::new(move |command| drop(listener_sender.send(Ok(command))));
+                if let Err(error) = scene.build(options, listener, &executor) {
+                    drop(sender.send(Err(error)));
+                }
+            }
+            MainToWorkerMsg::BuildPipelined(options, sender, frame) => {
+                if !pipeline.wait_for_turn(frame) {
+                    // The stream was dropped before the frame was built.
+                    continue;
+                }
+                let listener_sender = sender.clone();
+                let listener = Box::new(move |command| drop(listener_sender.send(Ok(command))));
+                if let Err(error) = scene.build(options, listener, &executor) {
+                    drop(sender.send(Err(error)));
+                }
+            }
         }
     }
 }
@@ -117,34 +247,186 @@ fn scene_thread<E>(mut scene: Scene,
 enum MainToWorkerMsg {
     ReplaceScene(Scene),
     CopyScene(Sender<Scene>),
+    SwapScene(Scene, Sender<Scene>),
+    Update(Box<dyn FnOnce(&mut Scene) + Send>),
     SetViewBox(RectF),
+    SetPathVisible(usize, bool),
     Build(BuildOptions, Box<dyn RenderCommandListener>),
+    BuildWithStream(BuildOptions, SyncSender<Result<RenderCommand, Error>>),
+    BuildPipelined(BuildOptions, Sender<Result<RenderCommand, Error>>, u64),
+}
+
+/// Tracks the pipelined frames whose streams haven't finished yet.
+struct FramePipeline {
+    state: Mutex<FramePipelineState>,
+    cond: Condvar,
+}
+
+struct FramePipelineState {
+    next_frame: u64,
+    unfinished_frames: BTreeSet<u64>,
+    max_frames_in_flight: usize,
+}
+
+impl FramePipeline {
+    fn new() -> FramePipeline {
+        FramePipeline {
+            state: Mutex::new(FramePipelineState {
+                next_frame: 0,
+                unfinished_frames: BTreeSet::new(),
+                max_frames_in_flight: DEFAULT_MAX_FRAMES_IN_FLIGHT,
+            }),
+            cond: Condvar::new(),
+        }
+    }
+
+    fn begin_frame(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let frame = state.next_frame;
+        state.next_frame += 1;
+        state.unfinished_frames.insert(frame);
+        frame
+    }
+
+    fn end_frame(&self, frame: u64) {
+        self.state.lock().unwrap().unfinished_frames.remove(&frame);
+        self.cond.notify_all();
+    }
+
+    /// Blocks until few enough earlier frames are unfinished to build `frame`. Returns false if
+    /// `frame` itself has been abandoned.
+    ///
+    /// Frames are built in order, so every earlier frame still in the set has been built and is
+    /// waiting on the render thread.
+    fn wait_for_turn(&self, frame: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if !state.unfinished_frames.contains(&frame) {
+                return false;
+            }
+            let earlier_frame_count = state.unfinished_frames.range(..frame).count();
+            if earlier_frame_count < state.max_frames_in_flight {
+                return true;
+            }
+            state = self.cond.wait(state).unwrap();
+        }
+    }
+}
+
+/// Takes a pipelined frame out of flight when dropped.
+struct FrameGuard {
+    pipeline: Arc<FramePipeline>,
+    frame: u64,
+}
+
+impl Drop for FrameGuard {
+    fn drop(&mut self) {
+        self.pipeline.end_frame(self.frame);
+    }
 }
 
+/// The commands of a scene being built on the worker thread.
+///
+/// This ends after the `Finish` command, or after an error.
 pub struct RenderCommandStream {
-    receiver: Receiver<RenderCommand>,
+    receiver: Receiver<Result<RenderCommand, Error>>,
+    frame_guard: Option<FrameGuard>,
     done: bool,
 }
 
 impl RenderCommandStream {
-    fn new(receiver: Receiver<RenderCommand>) -> RenderCommandStream {
-        RenderCommandStream { receiver, done: false }
+    fn new(receiver: Receiver<Result<RenderCommand, Error>>) -> RenderCommandStream {
+        RenderCommandStream { receiver, frame_guard: None, done: false }
+    }
+
+    fn pipelined(receiver: Receiver<Result<RenderCommand, Error>>, frame_guard: FrameGuard)
+                 -> RenderCommandStream {
+        RenderCommandStream { receiver, frame_guard: Some(frame_guard), done: false }
     }
 }
 
 impl Iterator for RenderCommandStream {
-    type Item = RenderCommand;
+    type Item = Result<RenderCommand, Error>;
 
     #[inline]
-    fn next(&mut self) -> Option<RenderCommand> {
+    fn next(&mut self) -> Option<Result<RenderCommand, Error>> {
         if self.done {
-            None
-        } else {
-            let command = self.receiver.recv().unwrap();
-            if let RenderCommand::Finish { .. } = command {
+            return None;
+        }
+
+        // The channel disconnects without an error only if the worker thread went away.
+        let command = self.receiver.recv().unwrap_or(Err(Error::SceneThreadExited));
+        match command {
+            Ok(RenderCommand::Finish { .. }) | Err(_) => {
                 self.done = true;
+                self.frame_guard = None;
             }
-            Some(command)
+            Ok(_) => {}
         }
+        Some(command)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SceneProxy;
+    use crate::concurrent::executor::SequentialExecutor;
+    use crate::gpu_data::RenderCommand;
+    use crate::options::BuildOptions;
+    use crate::paint::Paint;
+    use crate::scene::{DrawPath, Scene};
+    use pathfinder_content::outline::Outline;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::Vector2F;
+
+    #[test]
+    fn test_update_and_swap_scene() {
+        let view_box = |length| RectF::new(Vector2F::default(), Vector2F::splat(length));
+        let scene_proxy = SceneProxy::new(SequentialExecutor);
+
+        scene_proxy.update(move |scene| scene.set_view_box(view_box(16.0)));
+        assert_eq!(scene_proxy.copy_scene().view_box(), view_box(16.0));
+
+        let mut next_scene = Scene::new();
+        next_scene.set_view_box(view_box(32.0));
+        let old_scene = scene_proxy.swap_scene(next_scene);
+        assert_eq!(old_scene.view_box(), view_box(16.0));
+        assert_eq!(scene_proxy.copy_scene().view_box(), view_box(32.0));
+    }
+
+    #[test]
+    fn test_build_pipelined() {
+        let scene_proxy = SceneProxy::new(SequentialExecutor);
+        scene_proxy.set_max_frames_in_flight(1);
+
+        // With one frame in flight, the worker builds each frame only after the one before it has
+        // been consumed or dropped.
+        let abandoned_frame = scene_proxy.build_pipelined(BuildOptions::default());
+        let first_frame = scene_proxy.build_pipelined(BuildOptions::default());
+        let second_frame = scene_proxy.build_pipelined(BuildOptions::default());
+        drop(abandoned_frame);
+        for frame in [first_frame, second_frame] {
+            let commands: Vec<_> = frame.map(Result::unwrap).collect();
+            match commands.last() {
+                Some(RenderCommand::Finish { .. }) => {}
+                _ => panic!("The frame didn't finish!"),
+            }
+        }
+
+        assert!(scene_proxy.pipeline.state.lock().unwrap().unfinished_frames.is_empty());
+    }
+
+    #[test]
+    fn test_set_path_visible_ignores_missing_paths() {
+        let mut scene = Scene::new();
+        let paint = scene.push_paint(&Paint::black());
+        scene.push_path(DrawPath::new(Outline::new(), paint));
+        let scene_proxy = SceneProxy::from_scene(scene, SequentialExecutor);
+
+        scene_proxy.set_path_visible(0, false);
+        scene_proxy.set_path_visible(1, false);
+        let scene = scene_proxy.copy_scene();
+        assert!(!scene.path(0).is_visible());
+        assert_eq!(scene.path_count(), 1);
     }
 }
@@ -0,0 +1,1692 @@
+// pathfinder/renderer/src/cpu/renderer.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rasterizes fills and composites tiles on the CPU.
+//!
+//! This consumes the same render command stream as the GPU renderer and follows it stage by
+//! stage: fills accumulate signed area into per-tile coverage buffers, mask tiles resolve that
+//! coverage with the fill rule, and alpha and solid tiles are composited into the current render
+//! target. Where the shaders approximate—the area lookup table, the blend mode formulas—this
+//! computes the exact result, so that it can serve as a reference for correctness tests.
+//!
+//! The 3D stencil and gamma correction in the text filter aren't supported yet.
+
+use crate::gpu::options::{AntialiasingQuality, RendererOptions};
+use crate::gpu::renderer::{MASK_TILES_ACROSS, RenderStats};
+use crate::gpu::spot::SpotColorPalette;
+use crate::gpu_data::{AlphaTile, FillBatchPrimitive, MaskTile, RenderCommand, SolidTile};
+use crate::gpu_data::{AlphaType, TextureLocation, TexturePageDescriptor, TexturePageId};
+use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_content::effects::{BlendMode, CompositeOp, Effects, Filter};
+#[cfg(feature = "pf-filter-blur")]
+use pathfinder_content::effects::BlurDirection;
+#[cfg(feature = "pf-filter-text")]
+use pathfinder_content::effects::DefringingKernel;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use pathfinder_gpu::TextureSamplingFlags;
+use pathfinder_simd::default::F32x4;
+use smallvec::SmallVec;
+use std::f32;
+use std::mem;
+
+const TILE_AREA: usize = TILE_WIDTH as usize * TILE_HEIGHT as usize;
+
+// 1.0 / sqrt(2*pi)
+#[cfg(feature = "pf-filter-blur")]
+const SQRT_2_PI_INV: f32 = 0.3989423;
+
+static EMPTY_FILL_TILE: [f32; TILE_AREA] = [0.0; TILE_AREA];
+static FULL_MASK_TILE: [u8; TILE_AREA] = [!0; TILE_AREA];
+
+pub struct CPURenderer {
+    // Core data
+    options: RendererOptions,
+    dest_framebuffer: Image,
+    // Every layer of the destination, for layered builds. The current layer's slot is empty,
+    // since that layer lives in `dest_framebuffer`.
+    layers: Vec<Image>,
+    texture_pages: Vec<TexturePage>,
+    render_targets: Vec<TextureLocation>,
+    render_target_stack: Vec<RenderTargetId>,
+
+    // Signed area coverage, indexed by alpha tile index. This plays the role of the GPU
+    // renderer's fill framebuffer.
+    fill_tiles: Vec<[f32; TILE_AREA]>,
+    // 8-bit coverage, indexed by mask tile index. This plays the role of the GPU renderer's mask
+    // framebuffer.
+    mask_tiles: Vec<[u8; TILE_AREA]>,
+    // Whether each pixel of the destination framebuffer is inside the current stencil clip. This
+    // plays the role of the GPU renderer's stencil buffer.
+    clip_stencil: Vec<bool>,
+
+    // The lookup table that the finished image is remapped through, if spot color rendering is
+    // enabled.
+    spot_lut: Option<Vec<ColorU>>,
+
+    // Rendering state
+    must_preserve_dest_contents: bool,
+    // The tile that this frame's tile positions are relative to.
+    chunk_origin: Vector2I,
+    current_layer: usize,
+
+    // Debug
+    pub stats: RenderStats,
+}
+
+struct TexturePage {
+    image: Image,
+    must_preserve_contents: bool,
+    alpha_type: AlphaType,
+}
+
+#[derive(Default)]
+struct Image {
+    size: Vector2I,
+    pixels: Vec<ColorU>,
+}
+
+impl CPURenderer {
+    pub fn new(framebuffer_size: Vector2I, options: RendererOptions) -> CPURenderer {
+        let spot_lut = options.spot_colors.as_ref().map(|palette| palette.build_lut());
+        CPURenderer {
+            options,
+            dest_framebuffer: Image::new(framebuffer_size),
+            layers: vec![Image::default()],
+            texture_pages: vec![],
+            render_targets: vec![],
+            render_target_stack: vec![],
+            fill_tiles: vec![],
+            mask_tiles: vec![],
+            clip_stencil: vec![],
+            spot_lut,
+            must_preserve_dest_contents: false,
+            chunk_origin: Vector2I::default(),
+            current_layer: 0,
+            stats: RenderStats::default(),
+        }
+    }
+
+    #[inline]
+    pub fn framebuffer_size(&self) -> Vector2I {
+        self.dest_framebuffer.size
+    }
+
+    pub fn set_framebuffer_size(&mut self, new_framebuffer_size: Vector2I) {
+        self.dest_framebuffer = Image::new(new_framebuffer_size);
+        self.layers = vec![Image::default()];
+        self.current_layer = 0;
+    }
+
+    /// Returns the rendered image as premultiplied RGBA, row by row from the top.
+    #[inline]
+    pub fn pixels(&self) -> &[ColorU] {
+        &self.dest_framebuffer.pixels
+    }
+
+    /// Returns the rendered image of a layer of the destination, in the same format as
+    /// `pixels()`. Scenes built with `Scene::build_stereo()` draw each eye into its own layer;
+    /// layer 0 is the one that `pixels()` returns. Layers that were never drawn into are empty.
+    pub fn layer_pixels(&self, layer: usize) -> &[ColorU] {
+        if layer == self.current_layer {
+            return &self.dest_framebuffer.pixels;
+        }
+        match self.layers.get(layer) {
+            Some(image) => &image.pixels,
+            None => &[],
+        }
+    }
+
+    pub fn begin_scene(&mut self) {
+        self.select_layer(0);
+        self.must_preserve_dest_contents = false;
+        self.fill_tiles.clear();
+        self.mask_tiles.clear();
+        self.clip_stencil.clear();
+        self.stats = RenderStats::default();
+    }
+
+    pub fn render_command(&mut self, command: &RenderCommand) {
+        trace_span!("render_command", command = command.name());
+        match *command {
+            RenderCommand::Start { path_count, chunk_origin, .. } => {
+                self.stats.path_count = path_count;
+                self.chunk_origin = chunk_origin;
+            }
+            RenderCommand::BeginLayer { layer, .. } => {
+                // Each layer has contents and a stencil clip of its own.
+                self.select_layer(layer as usize);
+                self.must_preserve_dest_contents = false;
+                self.clip_stencil.clear();
+            }
+            RenderCommand::AllocateTexturePages(ref texture_page_descriptors) => {
+                self.allocate_texture_pages(texture_page_descriptors)
+            }
+            // Pages are allocated afresh for each scene, so they're always uploaded in full.
+            RenderCommand::UploadTexelData { ref texels, location, alpha_type, .. } => {
+                self.upload_texel_data(texels, location);
+                self.validate_alpha_type(location.page, alpha_type, "UploadTexelData");
+            }
+            RenderCommand::UploadCompressedTexelData { location, .. } => {
+                // We don't decode block-compressed formats, so the page stays transparent.
+                warn!("The CPU renderer doesn't support compressed images; not drawing page {:?}.",
+                      location.page);
+            }
+            RenderCommand::DeclareRenderTarget { id, location } => {
+                self.declare_render_target(id, location);
+                self.validate_alpha_type(location.page,
+                                         AlphaType::Premultiplied,
+                                         "DeclareRenderTarget");
+            }
+            RenderCommand::AddFills(ref fills) => self.add_fills(fills),
+            RenderCommand::FlushFills => {
+                // Fills are rasterized as soon as they arrive, so there's nothing to flush.
+            }
+            RenderCommand::RenderMaskTiles { tiles: ref mask_tiles, fill_rule } => {
+                self.draw_mask_tiles(mask_tiles, fill_rule)
+            }
+            RenderCommand::PushRenderTarget(render_target_id) => {
+                self.render_target_stack.push(render_target_id)
+            }
+            RenderCommand::PopRenderTarget => {
+                self.render_target_stack.pop().expect("Render target stack underflow!");
+            }
+            RenderCommand::DrawSolidTiles(ref batch) => {
+                self.stats.solid_tile_count += batch.tiles.len();
+                self.validate_alpha_type(batch.color_texture_page,
+                                         batch.color_texture_alpha_type,
+                                         "DrawSolidTiles");
+                self.draw_solid_tiles(&batch.tiles,
+                                      batch.color_texture_page,
+                                      batch.sampling_flags,
+                                      batch.effects);
+            }
+            RenderCommand::DrawClipStencil(ref tiles) => self.draw_clip_stencil(tiles),
+            RenderCommand::DrawAlphaTiles(ref batch) => {
+                self.stats.alpha_tile_count += batch.tiles.len();
+                if batch.blend_mode != BlendMode::Clear {
+                    self.validate_alpha_type(batch.color_texture_page,
+                                             batch.color_texture_alpha_type,
+                                             "DrawAlphaTiles");
+                }
+                self.draw_alpha_tiles(&batch.tiles,
+                                      batch.color_texture_page,
+                                      batch.sampling_flags,
+                                      batch.blend_mode,
+                                      batch.scissor,
+                                      batch.stencil_clipped);
+            }
+            RenderCommand::Finish { culled_path_count, .. } => {
+                self.stats.culled_path_count = culled_path_count;
+            }
+        }
+    }
+
+    pub fn end_scene(&mut self) {
+        self.select_layer(0);
+
+        // Like the GPU renderer's final blit, spot color remapping applies to the finished image.
+        if let Some(ref spot_lut) = self.spot_lut {
+            let images = Some(&mut self.dest_framebuffer).into_iter().chain(&mut self.layers);
+            for pixel in images.flat_map(|image| &mut image.pixels) {
+                // The LUT maps straight colors.
+                let color = unpack_color(*pixel);
+                let mut remapped = sample_spot_lut(spot_lut, unpremultiply(color)) * color.wwww();
+                remapped[3] = color[3];
+                *pixel = pack_color(remapped);
+            }
+        }
+    }
+
+    // Swaps the given layer into `dest_framebuffer`, creating it if it doesn't exist yet.
+    fn select_layer(&mut self, layer: usize) {
+        if layer == self.current_layer {
+            return;
+        }
+        let size = self.dest_framebuffer.size;
+        if self.layers.len() <= layer {
+            self.layers.resize_with(layer + 1, || Image::new(size));
+        }
+        let current = mem::take(&mut self.dest_framebuffer);
+        self.layers[self.current_layer] = current;
+        self.dest_framebuffer = mem::take(&mut self.layers[layer]);
+        self.current_layer = layer;
+    }
+
+    fn allocate_texture_pages(&mut self, texture_page_descriptors: &[TexturePageDescriptor]) {
+        self.texture_pages.clear();
+        self.render_targets.clear();
+
+        for texture_page_descriptor in texture_page_descriptors {
+            // Pages for external textures stay transparent, since there's no GPU texture to read.
+            if let Some(external_texture_id) = texture_page_descriptor.external_texture {
+                warn!("The CPU renderer doesn't support external textures; not drawing {:?}.",
+                      external_texture_id);
+            }
+            self.texture_pages.push(TexturePage {
+                image: Image::new(texture_page_descriptor.size),
+                must_preserve_contents: false,
+                alpha_type: texture_page_descriptor.alpha_type,
+            });
+        }
+    }
+
+    fn upload_texel_data(&mut self, texels: &[ColorU], location: TextureLocation) {
+        let texture_page = &mut self.texture_pages[location.page.0 as usize];
+        let width = location.rect.width() as usize;
+        if width > 0 {
+            for (row_index, row) in texels.chunks(width).enumerate() {
+                let row_origin = location.rect.origin() + Vector2I::new(0, row_index as i32);
+                let start = texture_page.image.pixel_index(row_origin);
+                texture_page.image.pixels[start..(start + row.len())].copy_from_slice(row);
+            }
+        }
+        texture_page.must_preserve_contents = true;
+    }
+
+    // Flags an upload to or read from a page that treats it as having another alpha type than it
+    // was allocated with, if alpha type validation is on.
+    fn validate_alpha_type(&mut self, id: TexturePageId, alpha_type: AlphaType, usage: &str) {
+        if !self.options.validate_alpha_types {
+            return;
+        }
+        let page_alpha_type = match self.texture_pages.get(id.0 as usize) {
+            Some(texture_page) => texture_page.alpha_type,
+            None => return,
+        };
+        if alpha_type != page_alpha_type {
+            warn!("{} treats {:?} as {:?}, but it's {:?}.", usage, id, alpha_type, page_alpha_type);
+            self.stats.alpha_type_mismatch_count += 1;
+        }
+    }
+
+    fn declare_render_target(&mut self,
+                             render_target_id: RenderTargetId,
+                             location: TextureLocation) {
+        while self.render_targets.len() < render_target_id.0 as usize + 1 {
+            self.render_targets.push(TextureLocation {
+                page: TexturePageId(!0),
+                rect: RectI::default(),
+            });
+        }
+        let render_target = &mut self.render_targets[render_target_id.0 as usize];
+        debug_assert_eq!(render_target.page, TexturePageId(!0));
+        *render_target = location;
+    }
+
+    fn add_fills(&mut self, fills: &[FillBatchPrimitive]) {
+        self.stats.fill_count += fills.len();
+
+        for fill in fills {
+            // Copy the fields out, since the primitive is packed.
+            let FillBatchPrimitive { px, subpx, alpha_tile_index } = *fill;
+            let from = Vector2F::new((px.from & 15) as f32, (px.from >> 4) as f32) +
+                Vector2F::new(subpx.from_x as f32, subpx.from_y as f32).scale(1.0 / 255.0);
+            let to = Vector2F::new((px.to & 15) as f32, (px.to >> 4) as f32) +
+                Vector2F::new(subpx.to_x as f32, subpx.to_y as f32).scale(1.0 / 255.0);
+
+            // There's no lookup table to sample here, so `Fast` is as exact as `Full`.
+            let antialiased = self.options.antialiasing_quality != AntialiasingQuality::None;
+            let coverage = tile_buffer(&mut self.fill_tiles, alpha_tile_index as usize, 0.0);
+            rasterize_line(coverage, from, to, antialiased);
+        }
+    }
+
+    fn draw_mask_tiles(&mut self, mask_tiles: &[MaskTile], fill_rule: FillRule) {
+        for mask_tile in mask_tiles {
+            let vertex = &mask_tile.upper_left;
+            let fill_tile_index = mask_uv_to_tile_index(vertex.fill_u, vertex.fill_v);
+            let mask_tile_index = mask_uv_to_tile_index(vertex.mask_u, vertex.mask_v);
+            let backdrop = vertex.backdrop as f32;
+
+            let fill = self.fill_tiles.get(fill_tile_index).unwrap_or(&EMPTY_FILL_TILE);
+            let mask = tile_buffer(&mut self.mask_tiles, mask_tile_index, !0);
+            for (mask_value, &area) in mask.iter_mut().zip(fill.iter()) {
+                let coverage = match fill_rule {
+                    FillRule::Winding => f32::abs(area + backdrop),
+                    FillRule::EvenOdd => 1.0 - f32::abs(1.0 - (area + backdrop).rem_euclid(2.0)),
+                };
+                // Clip paths share mask tiles with the paths they clip, so intersect.
+                *mask_value = (*mask_value).min(unit_to_u8(coverage));
+            }
+        }
+    }
+
+    // Like the GPU renderer's stencil, this only keeps pixels that are at least half covered.
+    fn draw_clip_stencil(&mut self, tiles: &[AlphaTile]) {
+        let viewport = self.draw_viewport();
+        let dest_framebuffer = &self.dest_framebuffer;
+        self.clip_stencil.clear();
+        self.clip_stencil.resize(dest_framebuffer.pixels.len(), false);
+
+        for tile in tiles {
+            let mask_tile_index = mask_uv_to_tile_index(tile.upper_left.mask_u,
+                                                        tile.upper_left.mask_v);
+            let mask = self.mask_tiles.get(mask_tile_index).unwrap_or(&FULL_MASK_TILE);
+            let tile_position = tile.upper_left.tile_position() + self.chunk_origin;
+            let tile_origin = tile_origin(viewport, tile_position);
+            for (pixel_index, &coverage) in mask.iter().enumerate() {
+                let position = tile_origin + tile_pixel_offset(pixel_index);
+                if coverage >= 0x80 &&
+                        viewport.contains_point(position) &&
+                        dest_framebuffer.contains(position) {
+                    self.clip_stencil[dest_framebuffer.pixel_index(position)] = true;
+                }
+            }
+        }
+    }
+
+    fn draw_alpha_tiles(&mut self,
+                        tiles: &[AlphaTile],
+                        color_texture_page: TexturePageId,
+                        sampling_flags: TextureSamplingFlags,
+                        blend_mode: BlendMode,
+                        scissor: Option<RectI>,
+                        stencil_clipped: bool) {
+        let (mut target, viewport) = self.begin_draw();
+        let scissor = scissor.map(|scissor| {
+            RectI::new(viewport.origin() + scissor.origin(), scissor.size())
+        });
+
+        let paint_texture_page = &self.texture_pages[color_texture_page.0 as usize];
+        let paint_texture = &paint_texture_page.image;
+        for tile in tiles {
+            let mask_tile_index = mask_uv_to_tile_index(tile.upper_left.mask_u,
+                                                        tile.upper_left.mask_v);
+            let mask = self.mask_tiles.get(mask_tile_index).unwrap_or(&FULL_MASK_TILE);
+            let opacity = tile.upper_left.opacity as f32 / 255.0;
+
+            let tile_position = tile.upper_left.tile_position() + self.chunk_origin;
+            let tile_origin = tile_origin(viewport, tile_position);
+            let color_tex_coords = [
+                Vector2F::new(tile.upper_left.color_u, tile.upper_left.color_v),
+                Vector2F::new(tile.upper_right.color_u, tile.upper_right.color_v),
+                Vector2F::new(tile.lower_left.color_u, tile.lower_left.color_v),
+            ];
+
+            for (pixel_index, &coverage) in mask.iter().enumerate() {
+                let tile_offset = tile_pixel_offset(pixel_index);
+                let position = tile_origin + tile_offset;
+                if !viewport.contains_point(position) || !target.contains(position) {
+                    continue;
+                }
+                if let Some(scissor) = scissor {
+                    if !scissor.contains_point(position) {
+                        continue;
+                    }
+                }
+                if stencil_clipped {
+                    let inside_clip = self.clip_stencil.get(target.pixel_index(position));
+                    if inside_clip != Some(&true) {
+                        continue;
+                    }
+                }
+
+                let mut src = match blend_mode {
+                    // The paint color is irrelevant; only the coverage matters.
+                    BlendMode::Clear => F32x4::new(0.0, 0.0, 0.0, 1.0),
+                    _ => {
+                        let tex_coords = interpolate_tex_coords(&color_tex_coords, tile_offset);
+                        to_straight(paint_texture.sample(tex_coords, sampling_flags),
+                                    paint_texture_page.alpha_type)
+                    }
+                };
+                src[3] *= opacity;
+
+                let dest = target.get(position);
+                target.set(position, blend(blend_mode, src, coverage as f32 / 255.0, dest));
+            }
+        }
+
+        self.end_draw(target);
+    }
+
+    fn draw_solid_tiles(&mut self,
+                        tiles: &[SolidTile],
+                        color_texture_page: TexturePageId,
+                        sampling_flags: TextureSamplingFlags,
+                        effects: Effects) {
+        let (mut target, viewport) = self.begin_draw();
+
+        let composite_op = match effects.filter {
+            Filter::Composite(composite_op) => composite_op,
+            Filter::Blur { .. } | Filter::Text { .. } | Filter::Custom { .. } => {
+                CompositeOp::SrcOver
+            }
+        };
+
+        let color_texture_page = &self.texture_pages[color_texture_page.0 as usize];
+        let color_texture = &color_texture_page.image;
+        let alpha_type = color_texture_page.alpha_type;
+        for tile in tiles {
+            let tile_position = Vector2I::new(tile.upper_left.tile_x as i32,
+                                              tile.upper_left.tile_y as i32) + self.chunk_origin;
+            let tile_origin = tile_origin(viewport, tile_position);
+            let color_tex_coords = [
+                Vector2F::new(tile.upper_left.color_u, tile.upper_left.color_v),
+                Vector2F::new(tile.upper_right.color_u, tile.upper_right.color_v),
+                Vector2F::new(tile.lower_left.color_u, tile.lower_left.color_v),
+            ];
+
+            for pixel_index in 0..TILE_AREA {
+                let tile_offset = tile_pixel_offset(pixel_index);
+                let position = tile_origin + tile_offset;
+                if !viewport.contains_point(position) || !target.contains(position) {
+                    continue;
+                }
+
+                let tex_coords = interpolate_tex_coords(&color_tex_coords, tile_offset);
+                let src = match effects.filter {
+                    #[cfg(feature = "pf-filter-blur")]
+                    Filter::Blur { direction, sigma } => {
+                        sample_blur_filter(color_texture,
+                                           alpha_type,
+                                           tex_coords,
+                                           sampling_flags,
+                                           direction,
+                                           sigma)
+                    }
+                    #[cfg(feature = "pf-filter-text")]
+                    Filter::Text { fg_color, bg_color, defringing_kernel, .. } => {
+                        sample_text_filter(color_texture,
+                                           tex_coords,
+                                           sampling_flags,
+                                           fg_color,
+                                           bg_color,
+                                           defringing_kernel)
+                    }
+                    // Custom filters are GPU shaders, so the layer is composited unfiltered, as
+                    // are layers whose filter was compiled out.
+                    _ => {
+                        to_premultiplied(color_texture.sample(tex_coords, sampling_flags),
+                                         alpha_type)
+                    }
+                };
+
+                let dest = target.get(position);
+                target.set(position, composite(composite_op, src, dest));
+            }
+        }
+
+        self.end_draw(target);
+    }
+
+    // Detaches the current render target so that it can be drawn to while the texture pages are
+    // sampled, clearing it first if necessary.
+    fn begin_draw(&mut self) -> (Image, RectI) {
+        let clear_color = self.clear_color_for_draw_operation();
+        let viewport = self.draw_viewport();
+
+        let mut target = match self.render_target_stack.last() {
+            Some(&render_target_id) => {
+                let texture_page = self.render_target_location(render_target_id).page;
+                mem::take(&mut self.texture_pages[texture_page.0 as usize].image)
+            }
+            None => mem::take(&mut self.dest_framebuffer),
+        };
+
+        if let Some(clear_color) = clear_color {
+            target.clear(clear_color);
+        }
+
+        (target, viewport)
+    }
+
+    fn end_draw(&mut self, target: Image) {
+        match self.render_target_stack.last() {
+            Some(&render_target_id) => {
+                let texture_page = self.render_target_location(render_target_id).page;
+                let texture_page = &mut self.texture_pages[texture_page.0 as usize];
+                texture_page.image = target;
+                texture_page.must_preserve_contents = true;
+            }
+            None => {
+                self.dest_framebuffer = target;
+                self.must_preserve_dest_contents = true;
+            }
+        }
+    }
+
+    fn clear_color_for_draw_operation(&self) -> Option<ColorF> {
+        let must_preserve_contents = match self.render_target_stack.last() {
+            Some(&render_target_id) => {
+                let texture_page = self.render_target_location(render_target_id).page;
+                self.texture_pages[texture_page.0 as usize].must_preserve_contents
+            }
+            None => self.must_preserve_dest_contents,
+        };
+
+        if must_preserve_contents {
+            None
+        } else if self.render_target_stack.is_empty() {
+            self.options.background_color
+        } else {
+            Some(ColorF::default())
+        }
+    }
+
+    fn draw_viewport(&self) -> RectI {
+        match self.render_target_stack.last() {
+            Some(&render_target_id) => self.render_target_location(render_target_id).rect,
+            None => RectI::new(Vector2I::default(), self.dest_framebuffer.size),
+        }
+    }
+
+    fn render_target_location(&self, render_target_id: RenderTargetId) -> TextureLocation {
+        self.render_targets[render_target_id.0 as usize]
+    }
+}
+
+impl Image {
+    fn new(size: Vector2I) -> Image {
+        let length = size.x() as usize * size.y() as usize;
+        Image { size, pixels: vec![ColorU::transparent_black(); length] }
+    }
+
+    fn clear(&mut self, color: ColorF) {
+        let color = pack_color(color.0);
+        for pixel in &mut self.pixels {
+            *pixel = color;
+        }
+    }
+
+    #[inline]
+    fn contains(&self, position: Vector2I) -> bool {
+        position.x() >= 0 && position.y() >= 0 &&
+            position.x() < self.size.x() && position.y() < self.size.y()
+    }
+
+    #[inline]
+    fn pixel_index(&self, position: Vector2I) -> usize {
+        position.y() as usize * self.size.x() as usize + position.x() as usize
+    }
+
+    #[inline]
+    fn get(&self, position: Vector2I) -> F32x4 {
+        unpack_color(self.pixels[self.pixel_index(position)])
+    }
+
+    #[inline]
+    fn set(&mut self, position: Vector2I, color: F32x4) {
+        let index = self.pixel_index(position);
+        self.pixels[index] = pack_color(color);
+    }
+
+    fn texel(&self, x: i32, y: i32, sampling_flags: TextureSamplingFlags) -> F32x4 {
+        let x = wrap_texel_coord(x, self.size.x(), sampling_flags, TextureSamplingFlags::REPEAT_U);
+        let y = wrap_texel_coord(y, self.size.y(), sampling_flags, TextureSamplingFlags::REPEAT_V);
+        self.get(Vector2I::new(x, y))
+    }
+
+    // Returns RGBA with the image's alpha type, filtered as a shader `texture()` call would.
+    fn sample(&self, tex_coords: Vector2F, sampling_flags: TextureSamplingFlags) -> F32x4 {
+        if self.pixels.is_empty() {
+            return F32x4::default();
+        }
+
+        let position = tex_coords.scale_xy(self.size.to_f32());
+        if sampling_flags.intersects(TextureSamplingFlags::NEAREST_MIN |
+                                     TextureSamplingFlags::NEAREST_MAG) {
+            let position = position.floor().to_i32();
+            return self.texel(position.x(), position.y(), sampling_flags);
+        }
+
+        let position = position - Vector2F::splat(0.5);
+        let origin = position.floor();
+        let (fraction, origin) = (position - origin, origin.to_i32());
+        let (x, y) = (origin.x(), origin.y());
+        let upper = lerp(self.texel(x, y, sampling_flags),
+                         self.texel(x + 1, y, sampling_flags),
+                         fraction.x());
+        let lower = lerp(self.texel(x, y + 1, sampling_flags),
+                         self.texel(x + 1, y + 1, sampling_flags),
+                         fraction.x());
+        lerp(upper, lower, fraction.y())
+    }
+}
+
+// Accumulates the signed area between the line and the bottom of the tile into each pixel it
+// passes over, like the fill shader does.
+//
+// If `antialiased` is false, a pixel the line passes through counts as covered only if the line
+// is above its center, which is what the fill shader does with the aliased area LUT.
+fn rasterize_line(coverage: &mut [f32; TILE_AREA],
+                  from: Vector2F,
+                  to: Vector2F,
+                  antialiased: bool) {
+    // Vertical lines enclose no area.
+    if from.x() == to.x() {
+        return;
+    }
+
+    // Split the line wherever it crosses a pixel boundary, so that each piece lies within a
+    // single pixel.
+    let mut split_points: SmallVec<[f32; 36]> = SmallVec::new();
+    split_points.push(0.0);
+    split_points.push(1.0);
+    push_pixel_boundary_crossings(&mut split_points, from.x(), to.x());
+    push_pixel_boundary_crossings(&mut split_points, from.y(), to.y());
+    split_points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    for pair in split_points.windows(2) {
+        let (piece_from, piece_to) = (from.lerp(to, pair[0]), from.lerp(to, pair[1]));
+        let width = piece_from.x() - piece_to.x();
+        if width == 0.0 {
+            continue;
+        }
+
+        let midpoint = piece_from.lerp(piece_to, 0.5);
+        let x = f32::floor(midpoint.x()) as i32;
+        let y = f32::floor(midpoint.y());
+        if x < 0 || x >= TILE_WIDTH as i32 || y >= TILE_HEIGHT as f32 {
+            continue;
+        }
+        let x = x as usize;
+
+        // The pixel containing the piece is partially covered; everything below it, fully.
+        let mut first_full_row = 0;
+        if y >= 0.0 {
+            let area = if antialiased {
+                width * (y + 1.0 - midpoint.y())
+            } else if midpoint.y() < y + 0.5 {
+                width
+            } else {
+                0.0
+            };
+            coverage[y as usize * TILE_WIDTH as usize + x] += area;
+            first_full_row = y as usize + 1;
+        }
+        for row in first_full_row..(TILE_HEIGHT as usize) {
+            coverage[row * TILE_WIDTH as usize + x] += width;
+        }
+    }
+}
+
+fn push_pixel_boundary_crossings(split_points: &mut SmallVec<[f32; 36]>, from: f32, to: f32) {
+    if from == to {
+        return;
+    }
+
+    let max = f32::max(from, to);
+    let mut boundary = f32::floor(f32::min(from, to)) + 1.0;
+    while boundary < max {
+        split_points.push((boundary - from) / (to - from));
+        boundary += 1.0;
+    }
+}
+
+fn tile_buffer<T>(tiles: &mut Vec<[T; TILE_AREA]>, index: usize, clear_value: T)
+                  -> &mut [T; TILE_AREA] where T: Copy {
+    if tiles.len() <= index {
+        tiles.resize(index + 1, [clear_value; TILE_AREA]);
+    }
+    &mut tiles[index]
+}
+
+// Inverts `calculate_mask_uv()` in the scene builder.
+fn mask_uv_to_tile_index(u: u16, v: u16) -> usize {
+    let scale = MASK_TILES_ACROSS as f32 / 65535.0;
+    let x = f32::round(u as f32 * scale) as usize;
+    let y = f32::round(v as f32 * scale) as usize;
+    y * MASK_TILES_ACROSS as usize + x
+}
+
+#[inline]
+fn tile_origin(viewport: RectI, tile_position: Vector2I) -> Vector2I {
+    let tile_size = Vector2I::new(TILE_WIDTH as i32, TILE_HEIGHT as i32);
+    viewport.origin() + tile_position.scale_xy(tile_size)
+}
+
+#[inline]
+fn tile_pixel_offset(pixel_index: usize) -> Vector2I {
+    Vector2I::new((pixel_index % TILE_WIDTH as usize) as i32,
+                  (pixel_index / TILE_WIDTH as usize) as i32)
+}
+
+// Texture coordinates are affine across a tile, so the upper left, upper right, and lower left
+// corners determine them. Samples are taken at pixel centers.
+fn interpolate_tex_coords(corners: &[Vector2F; 3], tile_offset: Vector2I) -> Vector2F {
+    let tile_size = Vector2F::new(TILE_WIDTH as f32, TILE_HEIGHT as f32);
+    let t = (tile_offset.to_f32() + Vector2F::splat(0.5)).scale_xy(Vector2F::new(
+        1.0 / tile_size.x(),
+        1.0 / tile_size.y(),
+    ));
+    corners[0] + (corners[1] - corners[0]).scale(t.x()) + (corners[2] - corners[0]).scale(t.y())
+}
+
+fn wrap_texel_coord(coord: i32,
+                    length: i32,
+                    sampling_flags: TextureSamplingFlags,
+                    repeat_flag: TextureSamplingFlags)
+                    -> i32 {
+    if sampling_flags.contains(repeat_flag) {
+        coord.rem_euclid(length)
+    } else {
+        coord.max(0).min(length - 1)
+    }
+}
+
+// Mirrors `tile_solid_filter_blur.fs.glsl`, including its incremental Gaussian. Returns a
+// premultiplied color.
+#[cfg(feature = "pf-filter-blur")]
+fn sample_blur_filter(texture: &Image,
+                      alpha_type: AlphaType,
+                      tex_coords: Vector2F,
+                      sampling_flags: TextureSamplingFlags,
+                      direction: BlurDirection,
+                      sigma: f32)
+                      -> F32x4 {
+    let sigma_inv = 1.0 / sigma;
+    let gauss_coeff_y = f32::exp(-0.5 * sigma_inv * sigma_inv);
+    let mut gauss_coeff = [SQRT_2_PI_INV * sigma_inv, gauss_coeff_y, gauss_coeff_y * gauss_coeff_y];
+    let support = f32::ceil(1.5 * sigma) as i32 * 2;
+
+    let src_offset = match direction {
+        BlurDirection::X => Vector2F::new(1.0, 0.0),
+        BlurDirection::Y => Vector2F::new(0.0, 1.0),
+    };
+    let texture_size = texture.size.to_f32();
+    let src_offset_scale =
+        src_offset.scale_xy(Vector2F::new(1.0 / texture_size.x(), 1.0 / texture_size.y()));
+
+    fn advance(gauss_coeff: &mut [f32; 3]) {
+        gauss_coeff[0] *= gauss_coeff[1];
+        gauss_coeff[1] *= gauss_coeff[2];
+    }
+
+    let mut gauss_sum = gauss_coeff[0];
+    let mut color = texture.sample(tex_coords, sampling_flags) * F32x4::splat(gauss_coeff[0]);
+    advance(&mut gauss_coeff);
+
+    let mut index = 1;
+    while index <= support {
+        let mut gauss_partial_sum = gauss_coeff[0];
+        advance(&mut gauss_coeff);
+        gauss_partial_sum += gauss_coeff[0];
+
+        let offset = src_offset_scale.scale(index as f32 + gauss_coeff[0] / gauss_partial_sum);
+        color += (texture.sample(tex_coords - offset, sampling_flags) +
+                  texture.sample(tex_coords + offset, sampling_flags)) *
+            F32x4::splat(gauss_partial_sum);
+
+        gauss_sum += 2.0 * gauss_partial_sum;
+        advance(&mut gauss_coeff);
+        index += 2;
+    }
+
+    to_premultiplied(color * F32x4::splat(1.0 / gauss_sum), alpha_type)
+}
+
+// Mirrors `tile_solid_filter_text.fs.glsl`, without gamma correction.
+#[cfg(feature = "pf-filter-text")]
+fn sample_text_filter(texture: &Image,
+                      tex_coords: Vector2F,
+                      sampling_flags: TextureSamplingFlags,
+                      fg_color: ColorF,
+                      bg_color: ColorF,
+                      defringing_kernel: Option<DefringingKernel>)
+                      -> F32x4 {
+    let one_pixel = 1.0 / texture.size.x() as f32;
+    let sample = |offset: i32| {
+        let offset = Vector2F::new(offset as f32 * one_pixel, 0.0);
+        texture.sample(tex_coords + offset, sampling_flags)[0]
+    };
+
+    let alpha = match defringing_kernel {
+        None => F32x4::splat(sample(0)),
+        Some(DefringingKernel(kernel)) => {
+            // Take nine taps around the pixel, and convolve seven of them for each subpixel.
+            let mut taps = [0.0; 9];
+            for (tap_index, tap) in taps.iter_mut().enumerate() {
+                let offset = tap_index as i32 - 4;
+                if offset.abs() < 4 || kernel[0] > 0.0 {
+                    *tap = sample(offset);
+                }
+            }
+            let weights = [kernel[0], kernel[1], kernel[2], kernel[3], kernel[2], kernel[1],
+                           kernel[0]];
+            let convolve = |start: usize| -> f32 {
+                taps[start..(start + 7)].iter().zip(weights.iter()).map(|(a, b)| a * b).sum()
+            };
+            F32x4::new(convolve(0), convolve(1), convolve(2), 0.0)
+        }
+    };
+
+    let mut color = bg_color.0 + (fg_color.0 - bg_color.0) * alpha;
+    color[3] = 1.0;
+    color
+}
+
+// Composites an alpha tile pixel. `src` is unpremultiplied, with opacity folded into its alpha;
+// `dest` is premultiplied.
+fn blend(blend_mode: BlendMode, mut src: F32x4, coverage: f32, dest: F32x4) -> F32x4 {
+    // Porter-Duff blend modes mix the whole operator's result with the destination by coverage,
+    // like `tile_alpha_porterduff_dual.fs.glsl`. For the others, folding coverage into the
+    // source's alpha amounts to the same thing.
+    if let Some(composite_op) = blend_mode.porter_duff_op() {
+        let composited = composite(composite_op, premultiply(src), dest);
+        return dest + (composited - dest) * F32x4::splat(coverage);
+    }
+
+    src[3] *= coverage;
+    let (src_alpha, dest_alpha) = (src[3], dest[3]);
+    let (src_factor, dest_factor) = match blend_mode {
+        BlendMode::Clear => (0.0, 1.0 - src_alpha),
+        BlendMode::SrcOver => (1.0, 1.0 - src_alpha),
+        BlendMode::DestOver => (1.0 - dest_alpha, 1.0),
+        BlendMode::DestOut => (0.0, 1.0 - src_alpha),
+        BlendMode::SrcAtop => (dest_alpha, 1.0 - src_alpha),
+        BlendMode::Xor => (1.0 - dest_alpha, 1.0 - src_alpha),
+        BlendMode::Lighter => (1.0, 1.0),
+        BlendMode::Lighten |
+        BlendMode::Darken |
+        BlendMode::Multiply |
+        BlendMode::Screen |
+        BlendMode::HardLight |
+        BlendMode::Overlay |
+        BlendMode::ColorDodge |
+        BlendMode::ColorBurn |
+        BlendMode::SoftLight |
+        BlendMode::Difference |
+        BlendMode::Exclusion |
+        BlendMode::Hue |
+        BlendMode::Saturation |
+        BlendMode::Color |
+        BlendMode::Luminosity => return blend_colors(blend_mode, src, dest),
+        BlendMode::Copy |
+        BlendMode::SrcIn |
+        BlendMode::DestIn |
+        BlendMode::SrcOut |
+        BlendMode::DestAtop => unreachable!(),
+    };
+    premultiply(src) * F32x4::splat(src_factor) + dest * F32x4::splat(dest_factor)
+}
+
+// Composites a solid tile pixel. Both `src` and `dest` are premultiplied.
+fn composite(composite_op: CompositeOp, src: F32x4, dest: F32x4) -> F32x4 {
+    let (src_alpha, dest_alpha) = (src[3], dest[3]);
+    let (src_factor, dest_factor) = match composite_op {
+        CompositeOp::Clear => (0.0, 0.0),
+        CompositeOp::Copy => (1.0, 0.0),
+        CompositeOp::SrcOver => (1.0, 1.0 - src_alpha),
+        CompositeOp::SrcIn => (dest_alpha, 0.0),
+        CompositeOp::DestIn => (0.0, src_alpha),
+        CompositeOp::SrcOut => (1.0 - dest_alpha, 0.0),
+        CompositeOp::DestAtop => (1.0 - dest_alpha, src_alpha),
+    };
+    src * F32x4::splat(src_factor) + dest * F32x4::splat(dest_factor)
+}
+
+// Applies a separable or non-separable blend mode and composites the result with source-over,
+// per "Compositing and Blending Level 1" § 5.8.
+fn blend_colors(blend_mode: BlendMode, src: F32x4, dest: F32x4) -> F32x4 {
+    let (src_alpha, dest_alpha) = (src[3], dest[3]);
+    let dest_color = if dest_alpha > 0.0 {
+        dest * F32x4::splat(1.0 / dest_alpha)
+    } else {
+        F32x4::default()
+    };
+
+    let blended = match blend_mode {
+        BlendMode::Hue => {
+            set_lum(set_sat(src, saturation(dest_color)), luminosity(dest_color))
+        }
+        BlendMode::Saturation => {
+            set_lum(set_sat(dest_color, saturation(src)), luminosity(dest_color))
+        }
+        BlendMode::Color => set_lum(src, luminosity(dest_color)),
+        BlendMode::Luminosity => set_lum(dest_color, luminosity(src)),
+        _ => {
+            F32x4::new(blend_channel(blend_mode, dest_color[0], src[0]),
+                       blend_channel(blend_mode, dest_color[1], src[1]),
+                       blend_channel(blend_mode, dest_color[2], src[2]),
+                       0.0)
+        }
+    };
+
+    let mut result = src * F32x4::splat(src_alpha * (1.0 - dest_alpha)) +
+        blended * F32x4::splat(src_alpha * dest_alpha) +
+        dest * F32x4::splat(1.0 - src_alpha);
+    result[3] = src_alpha + dest_alpha * (1.0 - src_alpha);
+    result
+}
+
+fn blend_channel(blend_mode: BlendMode, dest: f32, src: f32) -> f32 {
+    match blend_mode {
+        BlendMode::Lighten => f32::max(dest, src),
+        BlendMode::Darken => f32::min(dest, src),
+        BlendMode::Multiply => dest * src,
+        BlendMode::Screen => screen(dest, src),
+        BlendMode::HardLight => hard_light(dest, src),
+        BlendMode::Overlay => hard_light(src, dest),
+        BlendMode::ColorDodge => {
+            if dest == 0.0 {
+                0.0
+            } else if src >= 1.0 {
+                1.0
+            } else {
+                f32::min(1.0, dest / (1.0 - src))
+            }
+        }
+        BlendMode::ColorBurn => {
+            if dest >= 1.0 {
+                1.0
+            } else if src <= 0.0 {
+                0.0
+            } else {
+                1.0 - f32::min(1.0, (1.0 - dest) / src)
+            }
+        }
+        BlendMode::SoftLight => {
+            if src <= 0.5 {
+                dest - (1.0 - 2.0 * src) * dest * (1.0 - dest)
+            } else {
+                let d = if dest <= 0.25 {
+                    ((16.0 * dest - 12.0) * dest + 4.0) * dest
+                } else {
+                    f32::sqrt(dest)
+                };
+                dest + (2.0 * src - 1.0) * (d - dest)
+            }
+        }
+        BlendMode::Difference => f32::abs(dest - src),
+        BlendMode::Exclusion => dest + src - 2.0 * dest * src,
+        _ => unreachable!(),
+    }
+}
+
+#[inline]
+fn screen(dest: f32, src: f32) -> f32 {
+    dest + src - dest * src
+}
+
+#[inline]
+fn hard_light(dest: f32, src: f32) -> f32 {
+    if src <= 0.5 {
+        dest * 2.0 * src
+    } else {
+        screen(dest, 2.0 * src - 1.0)
+    }
+}
+
+fn luminosity(color: F32x4) -> f32 {
+    0.3 * color[0] + 0.59 * color[1] + 0.11 * color[2]
+}
+
+fn saturation(color: F32x4) -> f32 {
+    max_rgb(color) - min_rgb(color)
+}
+
+fn set_lum(color: F32x4, lum: f32) -> F32x4 {
+    let color = color + F32x4::splat(lum - luminosity(color));
+
+    // Clip the color back into gamut, preserving luminosity.
+    let (lum, min, max) = (luminosity(color), min_rgb(color), max_rgb(color));
+    let lum_vector = F32x4::splat(lum);
+    if min < 0.0 {
+        lum_vector + (color - lum_vector) * F32x4::splat(lum / (lum - min))
+    } else if max > 1.0 {
+        lum_vector + (color - lum_vector) * F32x4::splat((1.0 - lum) / (max - lum))
+    } else {
+        color
+    }
+}
+
+fn set_sat(color: F32x4, sat: f32) -> F32x4 {
+    let (min, max) = (min_rgb(color), max_rgb(color));
+    if max > min {
+        (color - F32x4::splat(min)) * F32x4::splat(sat / (max - min))
+    } else {
+        F32x4::default()
+    }
+}
+
+#[inline]
+fn min_rgb(color: F32x4) -> f32 {
+    f32::min(f32::min(color[0], color[1]), color[2])
+}
+
+#[inline]
+fn max_rgb(color: F32x4) -> f32 {
+    f32::max(f32::max(color[0], color[1]), color[2])
+}
+
+// Mirrors `sampleSpotLUT()` in `blit_spot.fs.glsl`: bilinear within a blue slice, and linear
+// between slices.
+fn sample_spot_lut(spot_lut: &[ColorU], color: F32x4) -> F32x4 {
+    let lut_size = SpotColorPalette::lut_size().y() as usize;
+    let max_coord = (lut_size - 1) as f32;
+    let coords = color.min(F32x4::splat(1.0)).max(F32x4::default()) * F32x4::splat(max_coord);
+
+    let texel = |red: usize, green: usize, blue: usize| {
+        unpack_color(spot_lut[green * lut_size * lut_size + blue * lut_size + red])
+    };
+    let split = |coord: f32| {
+        let lower = f32::floor(coord);
+        (lower as usize, usize::min(lower as usize + 1, lut_size - 1), coord - lower)
+    };
+
+    let (red_0, red_1, red_t) = split(coords[0]);
+    let (green_0, green_1, green_t) = split(coords[1]);
+    let (blue_0, blue_1, blue_t) = split(coords[2]);
+    let sample_slice = |blue: usize| {
+        lerp(lerp(texel(red_0, green_0, blue), texel(red_1, green_0, blue), red_t),
+             lerp(texel(red_0, green_1, blue), texel(red_1, green_1, blue), red_t),
+             green_t)
+    };
+    lerp(sample_slice(blue_0), sample_slice(blue_1), blue_t)
+}
+
+#[inline]
+fn lerp(a: F32x4, b: F32x4, t: f32) -> F32x4 {
+    a + (b - a) * F32x4::splat(t)
+}
+
+#[inline]
+fn premultiply(color: F32x4) -> F32x4 {
+    let mut result = color * color.wwww();
+    result[3] = color[3];
+    result
+}
+
+#[inline]
+fn unpremultiply(color: F32x4) -> F32x4 {
+    if color[3] == 0.0 {
+        return color;
+    }
+    let mut result = color * F32x4::splat(1.0 / color[3]);
+    result[3] = color[3];
+    result
+}
+
+// Converts a color sampled from a texture with the given alpha type to premultiplied alpha.
+#[inline]
+fn to_premultiplied(color: F32x4, alpha_type: AlphaType) -> F32x4 {
+    match alpha_type {
+        AlphaType::Straight => premultiply(color),
+        AlphaType::Premultiplied => color,
+    }
+}
+
+// Converts a color sampled from a texture with the given alpha type to straight alpha.
+#[inline]
+fn to_straight(color: F32x4, alpha_type: AlphaType) -> F32x4 {
+    match alpha_type {
+        AlphaType::Straight => color,
+        AlphaType::Premultiplied => unpremultiply(color),
+    }
+}
+
+#[inline]
+fn unpack_color(color: ColorU) -> F32x4 {
+    color.to_f32().0
+}
+
+#[inline]
+fn pack_color(color: F32x4) -> ColorU {
+    let color = (color.min(F32x4::splat(1.0)).max(F32x4::default()) * F32x4::splat(255.0) +
+                 F32x4::splat(0.5)).floor().to_i32x4();
+    ColorU::new(color[0] as u8, color[1] as u8, color[2] as u8, color[3] as u8)
+}
+
+#[inline]
+fn unit_to_u8(value: f32) -> u8 {
+    f32::round(value.clamp(0.0, 1.0) * 255.0) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CPURenderer, TILE_AREA, rasterize_line};
+    use crate::concurrent::executor::{Executor, SequentialExecutor};
+    use crate::gpu::options::RendererOptions;
+    use crate::gpu_data::{AlphaType, RenderCommand};
+    use crate::options::BuildOptions;
+    use crate::paint::Paint;
+    use crate::scene::{ClipPath, DrawPath, RenderTarget, Scene};
+    use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
+    use pathfinder_color::{ColorF, ColorU};
+    use pathfinder_content::effects::{BlendMode, BlurDirection, CompositeOp, CustomFilterId};
+    use pathfinder_content::effects::{Effects, Filter};
+    use pathfinder_content::fill::FillRule;
+    use pathfinder_content::outline::{Contour, Outline};
+    use pathfinder_content::pattern::{Pattern, PatternFlags, PatternSource};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_rasterize_horizontal_line() {
+        let mut coverage = [0.0; TILE_AREA];
+        rasterize_line(&mut coverage,
+                       Vector2F::new(TILE_WIDTH as f32, 4.5),
+                       Vector2F::new(0.0, 4.5),
+                       true);
+        for (index, &area) in coverage.iter().enumerate() {
+            let expected = match index / TILE_WIDTH as usize {
+                row if row < 4 => 0.0,
+                4 => 0.5,
+                _ => 1.0,
+            };
+            assert!((area - expected).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn test_rasterize_diagonal_line() {
+        let mut coverage = [0.0; TILE_AREA];
+        rasterize_line(&mut coverage,
+                       Vector2F::new(TILE_WIDTH as f32, 0.0),
+                       Vector2F::new(0.0, TILE_HEIGHT as f32),
+                       true);
+        let total_area: f32 = coverage.iter().sum();
+        assert!((total_area - TILE_AREA as f32 * 0.5).abs() < 0.001);
+        assert!(coverage.iter().all(|&area| (0.0..=1.0).contains(&area)));
+    }
+
+    #[test]
+    fn test_rasterize_aliased_line() {
+        let mut coverage = [0.0; TILE_AREA];
+        rasterize_line(&mut coverage,
+                       Vector2F::new(TILE_WIDTH as f32, 4.25),
+                       Vector2F::new(0.0, 4.25),
+                       false);
+        for (index, &area) in coverage.iter().enumerate() {
+            let expected = if index / (TILE_WIDTH as usize) < 4 { 0.0 } else { 1.0 };
+            assert_eq!(area, expected);
+        }
+    }
+
+    #[test]
+    fn test_stencil_clipping_matches_mask_clipping() {
+        let polygon = |points: &[Vector2F]| {
+            let mut contour = Contour::new();
+            for &point in points {
+                contour.push_endpoint(point);
+            }
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            outline
+        };
+
+        let size = Vector2I::splat(64);
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), size.to_f32()));
+        let clip_path = scene.push_clip_path(ClipPath::new(polygon(&[
+            Vector2F::new(4.0, 2.0),
+            Vector2F::new(60.0, 30.5),
+            Vector2F::new(10.0, 58.0),
+        ])));
+        let paint = scene.push_paint(&Paint::black());
+        let mut path = DrawPath::new(polygon(&[
+            Vector2F::new(1.0, 1.0),
+            Vector2F::new(63.0, 1.0),
+            Vector2F::new(63.0, 63.0),
+            Vector2F::new(1.0, 63.0),
+        ]), paint);
+        path.set_clip_path(Some(clip_path));
+        scene.push_path(path);
+
+        let render = |stencil_clipping: bool| {
+            let commands = Arc::new(Mutex::new(vec![]));
+            let listener_commands = commands.clone();
+            let listener = move |command| listener_commands.lock().unwrap().push(command);
+            let options = BuildOptions { stencil_clipping, ..BuildOptions::default() };
+            scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+
+            let mut renderer = CPURenderer::new(size, RendererOptions::default());
+            renderer.begin_scene();
+            let mut clip_stencil_count = 0;
+            for command in commands.lock().unwrap().iter() {
+                if let RenderCommand::DrawClipStencil(_) = *command {
+                    clip_stencil_count += 1;
+                }
+                renderer.render_command(command);
+            }
+            renderer.end_scene();
+            (renderer.pixels().to_vec(), clip_stencil_count)
+        };
+
+        let (mask_clipped_pixels, mask_clip_stencil_count) = render(false);
+        let (stencil_clipped_pixels, clip_stencil_count) = render(true);
+        assert_eq!((mask_clip_stencil_count, clip_stencil_count), (0, 1));
+        assert!(mask_clipped_pixels.contains(&ColorU::black()));
+
+        // Both kinds of clipping agree away from the clip's edges. Stencil clips are aliased, so
+        // pixels along the edges are either fully drawn or not at all.
+        for (mask_clipped_pixel, stencil_clipped_pixel) in
+                mask_clipped_pixels.iter().zip(&stencil_clipped_pixels) {
+            match mask_clipped_pixel.a {
+                0 | 255 => assert_eq!(mask_clipped_pixel, stencil_clipped_pixel),
+                _ => assert!(stencil_clipped_pixel.a == 0 || stencil_clipped_pixel.a == 255),
+            }
+        }
+    }
+
+    #[test]
+    fn test_backdrops_wider_than_i8() {
+        let size = Vector2I::splat(64);
+        let render = |fill_rule: FillRule, square_count: usize| {
+            // The squares span several tiles, so the ones in the middle get their coverage from
+            // the backdrop alone. The paint is translucent so that those tiles go through the
+            // mask rather than being drawn as solid tiles.
+            let mut outline = Outline::new();
+            for _ in 0..square_count {
+                let mut contour = Contour::new();
+                contour.push_endpoint(Vector2F::new(4.0, 4.0));
+                contour.push_endpoint(Vector2F::new(60.0, 4.0));
+                contour.push_endpoint(Vector2F::new(60.0, 60.0));
+                contour.push_endpoint(Vector2F::new(4.0, 60.0));
+                contour.close();
+                outline.push_contour(contour);
+            }
+
+            let mut scene = Scene::new();
+            scene.set_view_box(RectF::new(Vector2F::default(), size.to_f32()));
+            let paint = scene.push_paint(&Paint::Color(ColorU::new(0, 0, 0, 128)));
+            let mut path = DrawPath::new(outline, paint);
+            path.set_fill_rule(fill_rule);
+            scene.push_path(path);
+
+            let commands = Arc::new(Mutex::new(vec![]));
+            let listener_commands = commands.clone();
+            let listener = move |command| listener_commands.lock().unwrap().push(command);
+            scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor).unwrap();
+
+            let mut renderer = CPURenderer::new(size, RendererOptions::default());
+            renderer.begin_scene();
+            for command in commands.lock().unwrap().iter() {
+                renderer.render_command(command);
+            }
+            renderer.end_scene();
+            renderer.pixels()[(32 * size.x() + 32) as usize].a
+        };
+
+        assert_eq!(render(FillRule::Winding, 1), 128);
+        assert_eq!(render(FillRule::Winding, 256), 128);
+        assert_eq!(render(FillRule::EvenOdd, 257), 128);
+        assert_eq!(render(FillRule::EvenOdd, 256), 0);
+    }
+
+    #[test]
+    fn test_porter_duff_blend_modes_leave_dest_outside_path() {
+        let size = Vector2I::splat(64);
+        let rect = |min: Vector2F, max: Vector2F| {
+            let mut contour = Contour::new();
+            contour.push_endpoint(min);
+            contour.push_endpoint(Vector2F::new(max.x(), min.y()));
+            contour.push_endpoint(max);
+            contour.push_endpoint(Vector2F::new(min.x(), max.y()));
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            outline
+        };
+
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), size.to_f32()));
+        // The backdrop is translucent, so that it's drawn with alpha tiles.
+        let backdrop = scene.push_paint(&Paint::Color(ColorU::new(0, 0, 0, 200)));
+        let translucent = scene.push_paint(&Paint::Color(ColorU::new(255, 0, 0, 128)));
+        scene.push_path(DrawPath::new(rect(Vector2F::default(), size.to_f32()), backdrop));
+        let mut path = DrawPath::new(rect(Vector2F::splat(4.0), Vector2F::new(32.0, 60.0)),
+                                     translucent);
+        path.set_blend_mode(BlendMode::DestIn);
+        scene.push_path(path);
+
+        let render = |dual_source_blending: bool| {
+            let commands = Arc::new(Mutex::new(vec![]));
+            let listener_commands = commands.clone();
+            let listener = move |command| listener_commands.lock().unwrap().push(command);
+            let options = BuildOptions { dual_source_blending, ..BuildOptions::default() };
+            scene.build(options, Box::new(listener), &SequentialExecutor).unwrap();
+
+            let mut renderer = CPURenderer::new(size, RendererOptions::default());
+            renderer.begin_scene();
+            for command in commands.lock().unwrap().iter() {
+                renderer.render_command(command);
+            }
+            renderer.end_scene();
+            renderer.pixels().to_vec()
+        };
+
+        // Inside the path, the destination is kept in proportion to the source's alpha; outside
+        // it, the destination is left alone, unlike the unbounded canvas operator.
+        let pixels = render(false);
+        assert_eq!(pixels[(32 * size.x() + 16) as usize], ColorU::new(0, 0, 0, 100));
+        assert_eq!(pixels[(32 * size.x() + 48) as usize], ColorU::new(0, 0, 0, 200));
+        assert_eq!(pixels[(2 * size.x() + 16) as usize], ColorU::new(0, 0, 0, 200));
+        assert_eq!(render(true), pixels);
+    }
+
+    // Builds vectors back to front, as a parallel executor might.
+    struct ReverseExecutor;
+
+    impl Executor for ReverseExecutor {
+        fn build_vector<T, F>(&self, length: usize, builder: F) -> Vec<T>
+                              where T: Send, F: Fn(usize) -> T + Send + Sync {
+            let mut vector: Vec<T> = (0..length).rev().map(builder).collect();
+            vector.reverse();
+            vector
+        }
+    }
+
+    #[test]
+    fn test_deterministic_builds_dont_depend_on_path_order() {
+        let polygon = |points: &[Vector2F]| {
+            let mut contour = Contour::new();
+            for &point in points {
+                contour.push_endpoint(point);
+            }
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            outline
+        };
+
+        let size = Vector2I::splat(64);
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), size.to_f32()));
+        let clip_path = scene.push_clip_path(ClipPath::new(polygon(&[
+            Vector2F::new(4.0, 2.0),
+            Vector2F::new(60.0, 30.5),
+            Vector2F::new(10.0, 58.0),
+        ])));
+        for (index, &color) in [ColorU::new(255, 0, 0, 128), ColorU::new(0, 0, 255, 255)]
+                                   .iter()
+                                   .enumerate() {
+            let paint = scene.push_paint(&Paint::Color(color));
+            let offset = index as f32 * 13.5;
+            let mut path = DrawPath::new(polygon(&[
+                Vector2F::new(1.5 + offset, 3.0),
+                Vector2F::new(40.0 + offset, 9.25),
+                Vector2F::new(30.0 + offset, 61.0),
+            ]), paint);
+            if index == 0 {
+                path.set_clip_path(Some(clip_path));
+            }
+            scene.push_path(path);
+        }
+
+        // Returns the render commands, with their tiles and fills, and the pixels drawn.
+        fn render<E>(scene: &Scene, options: BuildOptions, executor: &E)
+                     -> (Vec<String>, Vec<ColorU>)
+                     where E: Executor {
+            let commands = Arc::new(Mutex::new(vec![]));
+            let listener_commands = commands.clone();
+            let listener = move |command| listener_commands.lock().unwrap().push(command);
+            scene.build(options, Box::new(listener), executor).unwrap();
+
+            let size = scene.view_box().size().to_i32();
+            let mut renderer = CPURenderer::new(size, RendererOptions::default());
+            let mut descriptions = vec![];
+            renderer.begin_scene();
+            for command in commands.lock().unwrap().iter() {
+                match *command {
+                    RenderCommand::AddFills(ref fills) => {
+                        descriptions.push(format!("{:?}", fills))
+                    }
+                    RenderCommand::RenderMaskTiles { ref tiles, .. } => {
+                        descriptions.push(format!("{:?}", tiles))
+                    }
+                    RenderCommand::DrawClipStencil(ref tiles) => {
+                        descriptions.push(format!("{:?}", tiles))
+                    }
+                    RenderCommand::DrawAlphaTiles(ref batch) => {
+                        descriptions.push(format!("{:?}", batch.tiles))
+                    }
+                    // Each build's uploads are identified afresh.
+                    RenderCommand::UploadTexelData { ref texels, location, .. } => {
+                        descriptions.push(format!("{:?} {:?}", texels, location))
+                    }
+                    RenderCommand::Finish { .. } => {}
+                    _ => descriptions.push(format!("{:?}", command)),
+                }
+                renderer.render_command(command);
+            }
+            renderer.end_scene();
+            (descriptions, renderer.pixels().to_vec())
+        }
+
+        for &stencil_clipping in &[false, true] {
+            let options = BuildOptions { stencil_clipping, ..BuildOptions::default() };
+            let (_, expected_pixels) = render(&scene, options.clone(), &SequentialExecutor);
+
+            let options = BuildOptions { deterministic: true, ..options };
+            let (commands, pixels) = render(&scene, options.clone(), &SequentialExecutor);
+            let (reversed_commands, reversed_pixels) = render(&scene, options, &ReverseExecutor);
+            assert_eq!(commands, reversed_commands);
+            assert_eq!(pixels, reversed_pixels);
+            assert_eq!(pixels, expected_pixels);
+        }
+    }
+
+    #[test]
+    fn test_render_targets_are_read_as_premultiplied() {
+        let size = Vector2I::splat(64);
+        let rect = |min: Vector2F, max: Vector2F| {
+            let mut contour = Contour::new();
+            contour.push_endpoint(min);
+            contour.push_endpoint(Vector2F::new(max.x(), min.y()));
+            contour.push_endpoint(max);
+            contour.push_endpoint(Vector2F::new(min.x(), max.y()));
+            contour.close();
+            let mut outline = Outline::new();
+            outline.push_contour(contour);
+            outline
+        };
+
+        // Draws a translucent layer, and then either composites it or fills a path with it.
+        let render = |as_pattern: bool, tamper: bool| {
+            let mut scene = Scene::new();
+            scene.set_view_box(RectF::new(Vector2F::default(), size.to_f32()));
+            let render_target =
+                scene.push_render_target(RenderTarget::new(size, "layer".to_owned()));
+            let translucent = scene.push_paint(&Paint::Color(ColorU::new(255, 0, 0, 128)));
+            scene.push_path(DrawPath::new(rect(Vector2F::default(), size.to_f32()), translucent));
+            scene.pop_render_target();
+            if as_pattern {
+                let pattern = Pattern::new(PatternSource::RenderTarget(render_target),
+                                           Transform2F::default(),
+                                           PatternFlags::empty());
+                let paint = scene.push_paint(&Paint::Pattern(pattern));
+                scene.push_path(DrawPath::new(rect(Vector2F::splat(8.0), Vector2F::splat(56.0)),
+                                              paint));
+            } else {
+                let effects = Effects::new(Filter::Composite(CompositeOp::SrcOver));
+                scene.draw_render_target(render_target, effects);
+            }
+
+            let commands = Arc::new(Mutex::new(vec![]));
+            let listener_commands = commands.clone();
+            let listener = move |command| listener_commands.lock().unwrap().push(command);
+            scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor).unwrap();
+
+            let options = RendererOptions { validate_alpha_types: true, ..Default::default() };
+            let mut renderer = CPURenderer::new(size, options);
+            renderer.begin_scene();
+            for command in commands.lock().unwrap().iter_mut() {
+                if tamper {
+                    match *command {
+                        RenderCommand::DrawSolidTiles(ref mut batch) => {
+                            batch.color_texture_alpha_type = AlphaType::Straight;
+                        }
+                        RenderCommand::UploadTexelData { ref mut alpha_type, .. } => {
+                            *alpha_type = AlphaType::Premultiplied;
+                        }
+                        _ => {}
+                    }
+                }
+                renderer.render_command(command);
+            }
+            renderer.end_scene();
+            (renderer.pixels()[(32 * size.x() + 32) as usize],
+             renderer.stats.alpha_type_mismatch_count)
+        };
+
+        // Without the conversion, the layer's color would be multiplied by its alpha twice.
+        assert_eq!(render(false, false), (ColorU::new(128, 0, 0, 128), 0));
+        assert_eq!(render(true, false), (ColorU::new(128, 0, 0, 128), 0));
+        assert_eq!(render(false, true).1, 2);
+    }
+
+    // Fills the left half of a 32x32 layer with translucent green, and draws the layer with
+    // `filter`.
+    fn render_filtered_layer(filter: Filter) -> Vec<ColorU> {
+        let size = Vector2I::splat(32);
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::default());
+        contour.push_endpoint(Vector2F::new(16.0, 0.0));
+        contour.push_endpoint(Vector2F::new(16.0, 32.0));
+        contour.push_endpoint(Vector2F::new(0.0, 32.0));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), size.to_f32()));
+        let render_target = scene.push_render_target(RenderTarget::new(size, "layer".to_owned()));
+        let paint = scene.push_paint(&Paint::Color(ColorU::new(0, 255, 0, 192)));
+        scene.push_path(DrawPath::new(outline, paint));
+        scene.pop_render_target();
+        scene.draw_render_target(render_target, Effects::new(filter));
+
+        let commands = Arc::new(Mutex::new(vec![]));
+        let listener_commands = commands.clone();
+        let listener = move |command| listener_commands.lock().unwrap().push(command);
+        scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor).unwrap();
+
+        let mut renderer = CPURenderer::new(size, RendererOptions::default());
+        renderer.begin_scene();
+        for command in commands.lock().unwrap().iter() {
+            renderer.render_command(command);
+        }
+        renderer.end_scene();
+        renderer.pixels().to_vec()
+    }
+
+    #[test]
+    fn test_custom_filters_composite_layers_unchanged() {
+        let custom_filter = Filter::Custom { shader: CustomFilterId(0), params: [1.0; 4] };
+        assert_eq!(render_filtered_layer(custom_filter),
+                   render_filtered_layer(Filter::Composite(CompositeOp::SrcOver)));
+    }
+
+    #[test]
+    fn test_compiled_out_filters_draw_layers_unfiltered() {
+        let unfiltered = render_filtered_layer(Filter::Composite(CompositeOp::SrcOver));
+        let blur = Filter::Blur { direction: BlurDirection::X, sigma: 4.0 };
+        assert_eq!(render_filtered_layer(blur) == unfiltered, !cfg!(feature = "pf-filter-blur"));
+        let text = Filter::Text {
+            fg_color: ColorF::new(1.0, 0.0, 0.0, 1.0),
+            bg_color: ColorF::white(),
+            defringing_kernel: None,
+            gamma_correction: false,
+        };
+        assert_eq!(render_filtered_layer(text) == unfiltered, !cfg!(feature = "pf-filter-text"));
+    }
+
+    // Records the names of the spans entered, and the `command` field of each span that has one.
+    #[cfg(feature = "pf-tracing")]
+    struct SpanRecorder {
+        spans: Arc<Mutex<Vec<String>>>,
+        next_id: Mutex<u64>,
+    }
+
+    #[cfg(feature = "pf-tracing")]
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _: &tracing::Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, attributes: &tracing::span::Attributes) -> tracing::span::Id {
+            struct CommandVisitor(Option<String>);
+            impl tracing::field::Visit for CommandVisitor {
+                fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+                    if field.name() == "command" {
+                        self.0 = Some(value.to_owned());
+                    }
+                }
+                fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+            }
+
+            let mut visitor = CommandVisitor(None);
+            attributes.record(&mut visitor);
+            let name = attributes.metadata().name();
+            let span = match visitor.0 {
+                Some(command) => format!("{} {}", name, command),
+                None => name.to_owned(),
+            };
+            self.spans.lock().unwrap().push(span);
+
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            tracing::span::Id::from_u64(*next_id)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record) {}
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+        fn event(&self, _: &tracing::Event) {}
+        fn enter(&self, _: &tracing::span::Id) {}
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "pf-tracing")]
+    #[test]
+    fn test_building_and_rendering_enter_spans() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2F::splat(32.0)));
+        let paint = scene.push_paint(&Paint::black());
+        let mut contour = Contour::new();
+        contour.push_endpoint(Vector2F::new(2.0, 2.0));
+        contour.push_endpoint(Vector2F::new(30.0, 2.5));
+        contour.push_endpoint(Vector2F::new(16.0, 30.0));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        scene.push_path(DrawPath::new(outline, paint));
+
+        let spans = Arc::new(Mutex::new(vec![]));
+        let recorder = SpanRecorder { spans: spans.clone(), next_id: Mutex::new(0) };
+        tracing::subscriber::with_default(recorder, || {
+            let commands = Arc::new(Mutex::new(vec![]));
+            let listener_commands = commands.clone();
+            let listener = move |command| listener_commands.lock().unwrap().push(command);
+            scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor).unwrap();
+
+            let mut renderer = CPURenderer::new(Vector2I::splat(32), RendererOptions::default());
+            renderer.begin_scene();
+            for command in commands.lock().unwrap().iter() {
+                renderer.render_command(command);
+            }
+            renderer.end_scene();
+        });
+
+        let spans = spans.lock().unwrap();
+        let expected_spans = [
+            "build",
+            "build_paint_info",
+            "tile_draw_path",
+            "cull_tiles",
+            "pack_tiles",
+            "render_command AddFills",
+        ];
+        for &expected_span in &expected_spans {
+            assert!(spans.iter().any(|span| span == expected_span),
+                    "no `{}` span in {:?}",
+                    expected_span,
+                    spans);
+        }
+    }
+}
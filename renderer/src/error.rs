@@ -0,0 +1,80 @@
+// pathfinder/renderer/src/error.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Errors that building or rendering a scene can report.
+
+use crate::gpu_data::TexturePageId;
+use crate::paint::CustomPaintShaderId;
+use pathfinder_content::effects::CustomFilterId;
+use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::vector::Vector2I;
+use std::error;
+use std::fmt;
+
+/// An error that prevented a scene from being built or a render command from being executed.
+///
+/// None of these are fatal: the scene or command is skipped, and the renderer can be used again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// The scene's view box is non-finite, has a negative size, or is too large to divide into
+    /// tiles.
+    InvalidViewBox(RectF),
+    /// A texture of this size can't be allocated.
+    TextureAllocationFailed(Vector2I),
+    /// A paint or command refers to a render target that hasn't been declared.
+    NoSuchRenderTarget(RenderTargetId),
+    /// A command refers to a texture page that hasn't been allocated.
+    NoSuchTexturePage(TexturePageId),
+    /// A command uses a texture page in a way its format doesn't support, such as uploading
+    /// texels to an external texture or rendering to a page that isn't a framebuffer.
+    IncompatibleTexturePage(TexturePageId),
+    /// A paint refers to a custom paint shader that hasn't been registered with the renderer.
+    NoSuchCustomPaintShader(CustomPaintShaderId),
+    /// A layer's filter refers to a custom filter that hasn't been registered with the renderer.
+    NoSuchCustomFilter(CustomFilterId),
+    /// A render target was popped with none pushed.
+    RenderTargetStackUnderflow,
+    /// A command draws to a layer that the destination framebuffer doesn't have.
+    NoSuchLayer(u32),
+    /// A path index is past the end of the scene's paths.
+    NoSuchPath(usize),
+    /// The scene proxy's worker thread has exited, so it can't build the scene.
+    SceneThreadExited,
+    /// The build's `CancellationToken` was cancelled.
+    Cancelled,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::InvalidViewBox(view_box) => write!(formatter, "invalid view box {:?}", view_box),
+            Error::TextureAllocationFailed(size) => {
+                write!(formatter, "couldn't allocate a texture of size {:?}", size)
+            }
+            Error::NoSuchRenderTarget(id) => write!(formatter, "no such render target {:?}", id),
+            Error::NoSuchTexturePage(id) => write!(formatter, "no such texture page {:?}", id),
+            Error::IncompatibleTexturePage(id) => {
+                write!(formatter, "texture page {:?} doesn't support this operation", id)
+            }
+            Error::NoSuchCustomPaintShader(id) => {
+                write!(formatter, "no such custom paint shader {:?}", id)
+            }
+            Error::NoSuchCustomFilter(id) => write!(formatter, "no such custom filter {:?}", id),
+            Error::RenderTargetStackUnderflow => write!(formatter, "render target stack underflow"),
+            Error::NoSuchLayer(layer) => write!(formatter, "no such layer {}", layer),
+            Error::NoSuchPath(path_index) => write!(formatter, "no such path {}", path_index),
+            Error::SceneThreadExited => write!(formatter, "the scene thread has exited"),
+            Error::Cancelled => write!(formatter, "the build was cancelled"),
+        }
+    }
+}
+
+impl error::Error for Error {}
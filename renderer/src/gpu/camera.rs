@@ -0,0 +1,279 @@
+// pathfinder/renderer/src/gpu/camera.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A 2D camera that reuses the previous frame when the view is only panned.
+
+use crate::concurrent::executor::Executor;
+use crate::error::Error;
+use crate::gpu::options::DestFramebuffer;
+use crate::gpu::renderer::Renderer;
+use crate::gpu_data::RenderCommand;
+use crate::options::{BuildOptions, RenderTransform};
+use crate::scene::Scene;
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use pathfinder_gpu::{Device, TextureFormat};
+use std::sync::{Arc, Mutex};
+
+// How far from a whole number of pixels a pan may be and still reuse the previous frame.
+const SCROLL_EPSILON: f32 = 0.001;
+
+/// Manages the view transform of a 2D scene, such as a map, and renders it.
+///
+/// The camera keeps the last frame it rendered. When the view has only been panned by a whole
+/// number of pixels since then, and `invalidate()` hasn't been called, it shifts that frame over
+/// and builds and renders only the strips of the scene that have newly come into view. Any other
+/// change to the view renders the whole scene again.
+///
+/// Frames are rendered into framebuffers that the camera owns and then copied to the renderer's
+/// destination, so the camera doesn't support `RendererOptions::spot_colors`.
+pub struct Camera2D<D> where D: Device {
+    transform: Transform2F,
+    // The last frame rendered, and the transform it was rendered with.
+    front: Option<CameraFrame<D>>,
+    // A framebuffer the size of the front one, recycled to render the next frame into.
+    back: Option<D::Framebuffer>,
+    dirty: bool,
+}
+
+struct CameraFrame<D> where D: Device {
+    framebuffer: D::Framebuffer,
+    transform: Transform2F,
+}
+
+impl<D> Camera2D<D> where D: Device {
+    #[inline]
+    pub fn new() -> Camera2D<D> {
+        Camera2D::with_transform(Transform2F::default())
+    }
+
+    /// Creates a camera with the given transform from scene coordinates to pixels.
+    #[inline]
+    pub fn with_transform(transform: Transform2F) -> Camera2D<D> {
+        Camera2D { transform, front: None, back: None, dirty: true }
+    }
+
+    /// Returns the transform from scene coordinates to pixels.
+    #[inline]
+    pub fn transform(&self) -> Transform2F {
+        self.transform
+    }
+
+    #[inline]
+    pub fn set_transform(&mut self, new_transform: Transform2F) {
+        self.transform = new_transform;
+    }
+
+    /// Moves the view so that the scene appears `delta` pixels further right and down.
+    #[inline]
+    pub fn pan(&mut self, delta: Vector2F) {
+        self.transform = Transform2F::from_translation(delta) * self.transform;
+    }
+
+    /// Scales the view by `scale` about `center`, which is in pixels and stays put on screen.
+    pub fn zoom(&mut self, scale: f32, center: Vector2F) {
+        self.transform = Transform2F::from_translation(center) *
+            Transform2F::from_uniform_scale(scale) *
+            Transform2F::from_translation(-center) *
+            self.transform;
+    }
+
+    /// Discards the previous frame, so that the next one is rendered from scratch. Call this
+    /// whenever the scene changes.
+    #[inline]
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Builds the scene with the camera's transform and renders it to the renderer's destination,
+    /// reusing as much of the previous frame as possible.
+    ///
+    /// `options` are used for every build, except that their transform and clip rect are replaced
+    /// by the camera's. The scene's view box should cover the viewport. If building or rendering
+    /// fails, the first error is returned, but the frame is still finished, so the camera and
+    /// renderer remain usable.
+    pub fn render<E>(&mut self,
+                     scene: &Scene,
+                     options: BuildOptions,
+                     renderer: &mut Renderer<D>,
+                     executor: &E)
+                     -> Result<(), Error>
+                     where E: Executor {
+        let viewport_size = renderer.main_viewport().size();
+        let front_size = self.front.as_ref().map(|front| {
+            renderer.device.texture_size(renderer.device.framebuffer_texture(&front.framebuffer))
+        });
+        if front_size != Some(viewport_size) {
+            self.front = None;
+            self.back = None;
+        }
+
+        let scroll = match self.front {
+            Some(ref front) if !self.dirty => {
+                scroll_offset(front.transform, self.transform, viewport_size)
+            }
+            _ => None,
+        };
+
+        let back = match self.back.take() {
+            Some(back) => back,
+            None => {
+                let texture = renderer.device.create_texture(TextureFormat::RGBA8, viewport_size);
+                renderer.device.create_framebuffer(texture)
+            }
+        };
+        let dest_framebuffer = renderer.replace_dest_framebuffer(DestFramebuffer::Other(back));
+        renderer.begin_scene();
+
+        let strips = match (scroll, self.front.as_ref()) {
+            (Some(offset), Some(front)) => {
+                renderer.draw_scrolled_framebuffer(Some(&front.framebuffer), offset);
+                exposed_strips(offset, viewport_size)
+            }
+            _ => {
+                renderer.draw_scrolled_framebuffer(None, Vector2I::default());
+                vec![RectI::new(Vector2I::default(), viewport_size)]
+            }
+        };
+
+        let mut result = Ok(());
+        for strip in strips {
+            let build_options = BuildOptions {
+                transform: RenderTransform::Transform2D(self.transform),
+                clip_rect: Some(strip.to_f32()),
+                ..options.clone()
+            };
+
+            // With the sequential executor, the listener is called on this thread, but other
+            // executors may call it from theirs, so collect the commands before rendering them.
+            let commands = Arc::new(Mutex::new(vec![]));
+            let listener_commands = commands.clone();
+            let build_result = scene.build(build_options,
+                                           Box::new(move |command: RenderCommand| {
+                                               listener_commands.lock().unwrap().push(command)
+                                           }),
+                                           executor);
+            for command in commands.lock().unwrap().iter() {
+                let render_result = renderer.render_command(command);
+                if result.is_ok() {
+                    result = render_result;
+                }
+            }
+            if result.is_ok() {
+                result = build_result;
+            }
+        }
+
+        // Present the frame.
+        let back = match renderer.replace_dest_framebuffer(dest_framebuffer) {
+            DestFramebuffer::Other(back) => back,
+            _ => unreachable!(),
+        };
+        renderer.draw_scrolled_framebuffer(Some(&back), Vector2I::default());
+        renderer.end_scene();
+
+        let new_front = CameraFrame { framebuffer: back, transform: self.transform };
+        self.back = self.front.replace(new_front).map(|front| front.framebuffer);
+        self.dirty = false;
+        result
+    }
+}
+
+impl<D> Default for Camera2D<D> where D: Device {
+    #[inline]
+    fn default() -> Camera2D<D> {
+        Camera2D::new()
+    }
+}
+
+// Returns the whole number of pixels that the view has been panned by, if that's the only way it
+// has changed and some of the old frame is still in view.
+fn scroll_offset(old_transform: Transform2F, new_transform: Transform2F, viewport_size: Vector2I)
+                 -> Option<Vector2I> {
+    if old_transform.matrix != new_transform.matrix {
+        return None;
+    }
+    let delta = new_transform.vector - old_transform.vector;
+    let offset = delta.round();
+    if (delta - offset).x().abs() > SCROLL_EPSILON || (delta - offset).y().abs() > SCROLL_EPSILON {
+        return None;
+    }
+    let offset = offset.to_i32();
+    if offset.x().abs() >= viewport_size.x() || offset.y().abs() >= viewport_size.y() {
+        return None;
+    }
+    Some(offset)
+}
+
+// Returns the parts of the viewport that scrolling the old frame by `offset` leaves uncovered: a
+// vertical strip on the side it moved away from, and a horizontal strip along the rest of the top
+// or bottom. They don't overlap, so that no pixel is drawn twice.
+fn exposed_strips(offset: Vector2I, viewport_size: Vector2I) -> Vec<RectI> {
+    let mut strips = vec![];
+    let mut remaining = RectI::new(Vector2I::default(), viewport_size);
+    if offset.x() > 0 {
+        strips.push(RectI::from_points(remaining.origin(),
+                                       Vector2I::new(offset.x(), remaining.max_y())));
+        remaining = RectI::from_points(Vector2I::new(offset.x(), 0), remaining.lower_right());
+    } else if offset.x() < 0 {
+        let min_x = viewport_size.x() + offset.x();
+        strips.push(RectI::from_points(Vector2I::new(min_x, 0), remaining.lower_right()));
+        remaining = RectI::from_points(remaining.origin(), Vector2I::new(min_x, remaining.max_y()));
+    }
+    if offset.y() > 0 {
+        strips.push(RectI::from_points(remaining.origin(),
+                                       Vector2I::new(remaining.max_x(), offset.y())));
+    } else if offset.y() < 0 {
+        let min_y = viewport_size.y() + offset.y();
+        strips.push(RectI::from_points(Vector2I::new(remaining.min_x(), min_y),
+                                       remaining.lower_right()));
+    }
+    strips
+}
+
+#[cfg(test)]
+mod test {
+    use super::{exposed_strips, scroll_offset};
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+
+    #[test]
+    fn test_scroll_offset() {
+        let size = Vector2I::new(640, 480);
+        let old_transform = Transform2F::from_uniform_scale(2.0);
+        let pan = |delta| Transform2F::from_translation(delta) * old_transform;
+
+        assert_eq!(scroll_offset(old_transform, pan(Vector2F::new(3.0, -7.0)), size),
+                   Some(Vector2I::new(3, -7)));
+        assert_eq!(scroll_offset(old_transform, pan(Vector2F::new(3.5, 0.0)), size), None);
+        assert_eq!(scroll_offset(old_transform, pan(Vector2F::new(640.0, 0.0)), size), None);
+        let zoomed = Transform2F::from_uniform_scale(1.5) * old_transform;
+        assert_eq!(scroll_offset(old_transform, zoomed, size), None);
+    }
+
+    #[test]
+    fn test_exposed_strips_cover_the_uncovered_area_once() {
+        let size = Vector2I::new(64, 48);
+        for &offset in &[Vector2I::new(5, 0), Vector2I::new(-5, 7), Vector2I::new(0, -9)] {
+            let strips = exposed_strips(offset, size);
+            let scrolled = RectI::new(offset, size);
+            for y in 0..size.y() {
+                for x in 0..size.x() {
+                    let point = Vector2I::new(x, y);
+                    let count = strips.iter().filter(|strip| strip.contains_point(point)).count();
+                    let expected = if scrolled.contains_point(point) { 0 } else { 1 };
+                    assert_eq!(count, expected, "offset {:?}, point {:?}", offset, point);
+                }
+            }
+        }
+    }
+}
@@ -16,9 +16,12 @@
 //! The debug font atlas was generated using: https://evanw.github.io/font-texture-generator/
 
 use crate::gpu::renderer::{RenderStats, RenderTime};
+use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
+use pathfinder_color::ColorU;
 use pathfinder_geometry::vector::Vector2I;
 use pathfinder_geometry::rect::RectI;
 use pathfinder_gpu::Device;
+use pathfinder_gpu::memory::GPUMemoryUsage;
 use pathfinder_resources::ResourceLoader;
 use pathfinder_ui::{FONT_ASCENT, LINE_HEIGHT, PADDING, UIPresenter, WINDOW_COLOR};
 use std::collections::VecDeque;
@@ -33,6 +36,28 @@ const STATS_WINDOW_HEIGHT: i32 = LINE_HEIGHT * 4 + PADDING + 2;
 const PERFORMANCE_WINDOW_WIDTH: i32 = 400;
 const PERFORMANCE_WINDOW_HEIGHT: i32 = LINE_HEIGHT * 4 + PADDING + 2;
 
+const GRAPH_LABEL_WIDTH: i32 = 300;
+const GRAPH_WIDTH: i32 = SAMPLE_BUFFER_SIZE as i32 * 4;
+const GRAPH_WINDOW_WIDTH: i32 = PADDING + GRAPH_LABEL_WIDTH + GRAPH_WIDTH + PADDING;
+
+const TILE_OVERLAY_WINDOW_WIDTH: i32 = 325;
+const TILE_OVERLAY_WINDOW_HEIGHT: i32 = LINE_HEIGHT * 3 + PADDING + 2;
+
+// Batches cycle through these, so that neighboring batches can be told apart.
+static BATCH_COLORS: [ColorU; 6] = [
+    ColorU { r: 255, g: 0,   b: 0,   a: 80 },
+    ColorU { r: 0,   g: 255, b: 0,   a: 80 },
+    ColorU { r: 0,   g: 0,   b: 255, a: 80 },
+    ColorU { r: 255, g: 255, b: 0,   a: 80 },
+    ColorU { r: 0,   g: 255, b: 255, a: 80 },
+    ColorU { r: 255, g: 0,   b: 255, a: 80 },
+];
+static GRAPH_COLOR: ColorU = ColorU { r: 0, g: 192, b: 255, a: 255 };
+static COUNTER_GRAPH_COLOR: ColorU = ColorU { r: 255, g: 192, b: 0, a: 255 };
+
+static TILE_GRID_COLOR: ColorU = ColorU { r: 128, g: 128, b: 128, a: 64 };
+static MASK_TILE_OUTLINE_COLOR: ColorU = ColorU { r: 255, g: 64, b: 0, a: 255 };
+
 pub struct DebugUIPresenter<D>
 where
     D: Device,
@@ -41,6 +66,8 @@ where
 
     cpu_samples: SampleBuffer<CPUSample>,
     gpu_samples: SampleBuffer<GPUSample>,
+    memory_samples: SampleBuffer<f32>,
+    counters: Vec<Counter>,
 }
 
 impl<D> DebugUIPresenter<D>
@@ -57,6 +84,8 @@ where
             ui_presenter,
             cpu_samples: SampleBuffer::new(),
             gpu_samples: SampleBuffer::new(),
+            memory_samples: SampleBuffer::new(),
+            counters: vec![],
         }
     }
 
@@ -75,10 +104,97 @@ where
         }
     }
 
+    /// Records the GPU memory in use. The renderer does this at the end of every scene.
+    pub fn add_memory_sample(&mut self, memory_usage: GPUMemoryUsage) {
+        self.memory_samples.push(memory_usage.total() as f32 / (1024.0 * 1024.0));
+    }
+
+    /// Records a value of a counter of the embedder's own, which is graphed below the built-in
+    /// ones.
+    ///
+    /// A counter is created the first time a sample is added under its name and is shown from
+    /// then on. Like the built-in graphs, it keeps the last 60 samples.
+    pub fn add_counter_sample(&mut self, name: &str, value: f32) {
+        push_counter_sample(&mut self.counters, name, value);
+    }
+
     pub fn draw(&self, device: &D) {
         let mean_cpu_sample = self.cpu_samples.mean();
         self.draw_stats_window(device, &mean_cpu_sample);
         self.draw_performance_window(device, &mean_cpu_sample);
+        self.draw_graph_window(device);
+    }
+
+    /// Draws the tile grid over `viewport`, tints each tile by the batch that drew it, and
+    /// outlines the tiles that needed the mask framebuffer.
+    pub(crate) fn draw_tile_overlay(&self, device: &D, overlay: &TileOverlay, viewport: RectI) {
+        self.ui_presenter.draw_solid_rects(device, &tile_grid_lines(viewport), TILE_GRID_COLOR);
+
+        let mut mask_tile_rects = vec![];
+        for (batch_index, batch) in overlay.batches.iter().enumerate() {
+            let tile_rects = tile_rects(&batch.tiles, viewport);
+            let color = BATCH_COLORS[batch_index % BATCH_COLORS.len()];
+            self.ui_presenter.draw_solid_rects(device, &tile_rects, color);
+            if batch.uses_mask {
+                mask_tile_rects.extend_from_slice(&tile_rects);
+            }
+        }
+        self.ui_presenter.draw_rect_outlines(device, &mask_tile_rects, MASK_TILE_OUTLINE_COLOR);
+
+        let window_rect = RectI::new(Vector2I::splat(PADDING),
+                                     Vector2I::new(TILE_OVERLAY_WINDOW_WIDTH,
+                                                   TILE_OVERLAY_WINDOW_HEIGHT));
+        self.ui_presenter.draw_solid_rounded_rect(device, window_rect, WINDOW_COLOR);
+        let origin = window_rect.origin() + Vector2I::new(PADDING, PADDING + FONT_ASCENT);
+        self.ui_presenter.draw_text(device,
+                                    &format!("Batches: {}", overlay.batches.len()),
+                                    origin,
+                                    false);
+        self.ui_presenter.draw_text(device,
+                                    &format!("Mask Tiles: {}", overlay.mask_tile_count),
+                                    origin + Vector2I::new(0, LINE_HEIGHT),
+                                    false);
+        self.ui_presenter.draw_text(device,
+                                    &format!("Mask Batches: {}", overlay.mask_batch_count),
+                                    origin + Vector2I::new(0, LINE_HEIGHT * 2),
+                                    false);
+    }
+
+    fn draw_graph_window(&self, device: &D) {
+        let graphs = graphs(&self.cpu_samples,
+                            &self.gpu_samples,
+                            &self.memory_samples,
+                            &self.counters);
+
+        let framebuffer_size = self.ui_presenter.framebuffer_size();
+        let bottom = framebuffer_size.y() - PADDING - PERFORMANCE_WINDOW_HEIGHT - PADDING -
+            STATS_WINDOW_HEIGHT - PADDING;
+        let window_height = LINE_HEIGHT * graphs.len() as i32 + PADDING + 2;
+        let window_rect = RectI::new(
+            Vector2I::new(framebuffer_size.x() - PADDING - GRAPH_WINDOW_WIDTH,
+                          bottom - window_height),
+            Vector2I::new(GRAPH_WINDOW_WIDTH, window_height),
+        );
+
+        self.ui_presenter.draw_solid_rounded_rect(device, window_rect, WINDOW_COLOR);
+
+        let origin = window_rect.origin() + Vector2I::splat(PADDING);
+        for (graph_index, graph) in graphs.iter().enumerate() {
+            let row_origin = origin + Vector2I::new(0, LINE_HEIGHT * graph_index as i32);
+            self.ui_presenter.draw_text(device,
+                                        &graph.label,
+                                        row_origin + Vector2I::new(0, FONT_ASCENT),
+                                        false);
+
+            let graph_rect = RectI::new(row_origin + Vector2I::new(GRAPH_LABEL_WIDTH, 0),
+                                        Vector2I::new(GRAPH_WIDTH, FONT_ASCENT));
+            let max_value = graph.values.iter().cloned().fold(0.0, f32::max);
+            self.ui_presenter.draw_bar_graph(device,
+                                             graph_rect,
+                                             &graph.values,
+                                             max_value,
+                                             graph.color);
+        }
     }
 
     fn draw_stats_window(&self, device: &D, mean_cpu_sample: &CPUSample) {
@@ -97,7 +213,9 @@ where
         let origin = window_rect.origin() + Vector2I::new(PADDING, PADDING + FONT_ASCENT);
         self.ui_presenter.draw_text(
             device,
-            &format!("Paths: {}", mean_cpu_sample.stats.path_count),
+            &format!("Paths: {} ({} culled)",
+                     mean_cpu_sample.stats.path_count,
+                     mean_cpu_sample.stats.culled_path_count),
             origin,
             false,
         );
@@ -178,17 +296,158 @@ where
 
 }
 
-struct SampleBuffer<S>
-where
-    S: Add<S, Output = S> + Div<usize, Output = S> + Clone + Default,
-{
+/// The tiles drawn to the main framebuffer during one scene, for the tile overlay.
+#[derive(Default)]
+pub(crate) struct TileOverlay {
+    batches: Vec<TileOverlayBatch>,
+    mask_tile_count: usize,
+    mask_batch_count: usize,
+}
+
+struct TileOverlayBatch {
+    tiles: Vec<Vector2I>,
+    // Whether the tiles read their coverage from the mask framebuffer, as alpha tiles do.
+    uses_mask: bool,
+}
+
+impl TileOverlay {
+    pub(crate) fn clear(&mut self) {
+        self.batches.clear();
+        self.mask_tile_count = 0;
+        self.mask_batch_count = 0;
+    }
+
+    pub(crate) fn push_batch<I>(&mut self, tiles: I, uses_mask: bool)
+                                where I: Iterator<Item = Vector2I> {
+        self.batches.push(TileOverlayBatch { tiles: tiles.collect(), uses_mask });
+    }
+
+    pub(crate) fn push_mask_batch(&mut self, tile_count: usize) {
+        self.mask_tile_count += tile_count;
+        self.mask_batch_count += 1;
+    }
+}
+
+// The grid lines of the tiles covering `viewport`.
+fn tile_grid_lines(viewport: RectI) -> Vec<RectI> {
+    let mut grid_lines = vec![];
+    for x in (viewport.min_x()..viewport.max_x()).step_by(TILE_WIDTH as usize) {
+        grid_lines.push(RectI::new(Vector2I::new(x, viewport.min_y()),
+                                   Vector2I::new(1, viewport.height())));
+    }
+    for y in (viewport.min_y()..viewport.max_y()).step_by(TILE_HEIGHT as usize) {
+        grid_lines.push(RectI::new(Vector2I::new(viewport.min_x(), y),
+                                   Vector2I::new(viewport.width(), 1)));
+    }
+    grid_lines
+}
+
+// The framebuffer rects of `tiles`, which are tile coordinates relative to the viewport origin.
+fn tile_rects(tiles: &[Vector2I], viewport: RectI) -> Vec<RectI> {
+    let tile_size = Vector2I::new(TILE_WIDTH as i32, TILE_HEIGHT as i32);
+    tiles.iter().map(|&tile| {
+        RectI::new(viewport.origin() + tile.scale_xy(tile_size), tile_size)
+    }).collect()
+}
+
+// The rows of the graph window: the built-in graphs, then the embedder's counters in the order
+// they were first sampled.
+fn graphs(cpu_samples: &SampleBuffer<CPUSample>,
+          gpu_samples: &SampleBuffer<GPUSample>,
+          memory_samples: &SampleBuffer<f32>,
+          counters: &[Counter])
+          -> Vec<Graph> {
+    let cpu_samples = &cpu_samples.samples;
+    let gpu_samples = &gpu_samples.samples;
+    let mut graphs = vec![
+        Graph::new("CPU",
+                   "ms",
+                   cpu_samples.iter().map(|sample| duration_to_ms(sample.elapsed) as f32),
+                   GRAPH_COLOR),
+        Graph::new("GPU 0",
+                   "ms",
+                   gpu_samples.iter().map(|sample| {
+                       duration_to_ms(sample.time.stage_0) as f32
+                   }),
+                   GRAPH_COLOR),
+        Graph::new("GPU 1",
+                   "ms",
+                   gpu_samples.iter().map(|sample| {
+                       duration_to_ms(sample.time.stage_1) as f32
+                   }),
+                   GRAPH_COLOR),
+        Graph::new("Fills",
+                   "",
+                   cpu_samples.iter().map(|sample| sample.stats.fill_count as f32),
+                   GRAPH_COLOR),
+        Graph::new("Tiles",
+                   "",
+                   cpu_samples.iter().map(|sample| {
+                       (sample.stats.solid_tile_count + sample.stats.alpha_tile_count) as f32
+                   }),
+                   GRAPH_COLOR),
+        Graph::new("Memory",
+                   "MB",
+                   memory_samples.samples.iter().cloned(),
+                   GRAPH_COLOR),
+    ];
+    for counter in counters {
+        graphs.push(Graph::new(&counter.name,
+                               "",
+                               counter.samples.samples.iter().cloned(),
+                               COUNTER_GRAPH_COLOR));
+    }
+    graphs
+}
+
+fn push_counter_sample(counters: &mut Vec<Counter>, name: &str, value: f32) {
+    let counter_index = match counters.iter().position(|counter| counter.name == name) {
+        Some(counter_index) => counter_index,
+        None => {
+            counters.push(Counter { name: name.to_owned(), samples: SampleBuffer::new() });
+            counters.len() - 1
+        }
+    };
+    counters[counter_index].samples.push(value);
+}
+
+// One row of the graph window.
+struct Graph {
+    label: String,
+    // Padded at the front with zeroes to `SAMPLE_BUFFER_SIZE`, so that the latest sample is
+    // always at the right edge.
+    values: Vec<f32>,
+    color: ColorU,
+}
+
+impl Graph {
+    fn new<I>(name: &str, unit: &str, values: I, color: ColorU) -> Graph
+              where I: ExactSizeIterator<Item = f32> {
+        let padding = SAMPLE_BUFFER_SIZE.saturating_sub(values.len());
+        let mut padded_values = vec![0.0; padding];
+        padded_values.extend(values);
+
+        let latest = padded_values.last().cloned().unwrap_or(0.0);
+        let label = if unit.is_empty() {
+            format!("{}: {}", name, latest)
+        } else {
+            format!("{}: {:.3} {}", name, latest, unit)
+        };
+
+        Graph { label, values: padded_values, color }
+    }
+}
+
+struct Counter {
+    name: String,
+    samples: SampleBuffer<f32>,
+}
+
+struct SampleBuffer<S> {
     samples: VecDeque<S>,
 }
 
-impl<S> SampleBuffer<S>
-where
-    S: Add<S, Output = S> + Div<usize, Output = S> + Clone + Default,
-{
+impl<S> SampleBuffer<S> {
     fn new() -> SampleBuffer<S> {
         SampleBuffer {
             samples: VecDeque::with_capacity(SAMPLE_BUFFER_SIZE),
@@ -201,7 +460,12 @@ where
             self.samples.pop_front();
         }
     }
+}
 
+impl<S> SampleBuffer<S>
+where
+    S: Add<S, Output = S> + Div<usize, Output = S> + Clone + Default,
+{
     fn mean(&self) -> S {
         let mut mean = Default::default();
         if self.samples.is_empty() {
@@ -271,3 +535,124 @@ impl Div<usize> for GPUSample {
 fn duration_to_ms(time: Duration) -> f64 {
     time.as_secs() as f64 * 1000.0 + time.subsec_nanos() as f64 / 1000000.0
 }
+
+#[cfg(test)]
+mod test {
+    use super::{CPUSample, GPUSample, SAMPLE_BUFFER_SIZE, SampleBuffer, TileOverlay, graphs};
+    use super::{push_counter_sample, tile_grid_lines, tile_rects};
+    use crate::gpu::renderer::{RenderStats, RenderTime};
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::vector::Vector2I;
+    use std::time::Duration;
+
+    #[test]
+    fn test_tile_overlay_records_batches_until_cleared() {
+        let mut overlay = TileOverlay::default();
+        overlay.push_batch(vec![Vector2I::new(0, 0), Vector2I::new(1, 0)].into_iter(), false);
+        overlay.push_batch(vec![Vector2I::new(2, 3)].into_iter(), true);
+        overlay.push_mask_batch(5);
+        overlay.push_mask_batch(2);
+
+        assert_eq!(overlay.batches.len(), 2);
+        assert_eq!(overlay.batches[0].tiles, vec![Vector2I::new(0, 0), Vector2I::new(1, 0)]);
+        assert!(!overlay.batches[0].uses_mask);
+        assert_eq!(overlay.batches[1].tiles, vec![Vector2I::new(2, 3)]);
+        assert!(overlay.batches[1].uses_mask);
+        assert_eq!((overlay.mask_tile_count, overlay.mask_batch_count), (7, 2));
+
+        overlay.clear();
+        assert!(overlay.batches.is_empty());
+        assert_eq!((overlay.mask_tile_count, overlay.mask_batch_count), (0, 0));
+    }
+
+    #[test]
+    fn test_tile_overlay_geometry_follows_the_viewport() {
+        let viewport = RectI::new(Vector2I::new(10, 20), Vector2I::new(40, 16));
+        assert_eq!(tile_grid_lines(viewport), vec![
+            RectI::new(Vector2I::new(10, 20), Vector2I::new(1, 16)),
+            RectI::new(Vector2I::new(26, 20), Vector2I::new(1, 16)),
+            RectI::new(Vector2I::new(42, 20), Vector2I::new(1, 16)),
+            RectI::new(Vector2I::new(10, 20), Vector2I::new(40, 1)),
+        ]);
+        assert!(tile_grid_lines(RectI::default()).is_empty());
+
+        assert_eq!(tile_rects(&[Vector2I::new(0, 0), Vector2I::new(2, 1)], viewport), vec![
+            RectI::new(Vector2I::new(10, 20), Vector2I::splat(16)),
+            RectI::new(Vector2I::new(42, 36), Vector2I::splat(16)),
+        ]);
+    }
+
+    #[test]
+    fn test_sample_buffers_keep_the_latest_samples() {
+        let mut samples: SampleBuffer<CPUSample> = SampleBuffer::new();
+        assert_eq!(samples.mean().elapsed, Duration::default());
+        for millis in 0..(SAMPLE_BUFFER_SIZE as u64 + 10) {
+            samples.push(CPUSample {
+                elapsed: Duration::from_millis(millis),
+                stats: RenderStats::default(),
+            });
+        }
+        assert_eq!(samples.samples.len(), SAMPLE_BUFFER_SIZE);
+        assert_eq!(samples.samples[0].elapsed, Duration::from_millis(10));
+        let last = SAMPLE_BUFFER_SIZE as u64 + 9;
+        assert_eq!(samples.mean().elapsed, Duration::from_micros((10 + last) * 1000 / 2));
+    }
+
+    #[test]
+    fn test_graphs_show_the_latest_sample_at_the_right() {
+        let mut cpu_samples = SampleBuffer::new();
+        for &(fill_count, tile_count) in &[(1, 2), (3, 4)] {
+            cpu_samples.push(CPUSample {
+                elapsed: Duration::from_micros(1500),
+                stats: RenderStats {
+                    fill_count,
+                    solid_tile_count: tile_count,
+                    alpha_tile_count: 1,
+                    ..RenderStats::default()
+                },
+            });
+        }
+        let mut gpu_samples = SampleBuffer::new();
+        gpu_samples.push(GPUSample {
+            time: RenderTime {
+                stage_0: Duration::from_millis(2),
+                stage_1: Duration::from_millis(3),
+            },
+        });
+        let mut memory_samples = SampleBuffer::new();
+        memory_samples.push(12.5);
+
+        let graphs = graphs(&cpu_samples, &gpu_samples, &memory_samples, &[]);
+        let labels: Vec<&str> = graphs.iter().map(|graph| &*graph.label).collect();
+        assert_eq!(labels, vec![
+            "CPU: 1.500 ms",
+            "GPU 0: 2.000 ms",
+            "GPU 1: 3.000 ms",
+            "Fills: 3",
+            "Tiles: 5",
+            "Memory: 12.500 MB",
+        ]);
+        for graph in &graphs {
+            assert_eq!(graph.values.len(), SAMPLE_BUFFER_SIZE);
+        }
+        let tiles = &graphs[4].values;
+        assert_eq!(&tiles[(SAMPLE_BUFFER_SIZE - 3)..], &[0.0, 3.0, 5.0]);
+    }
+
+    #[test]
+    fn test_counters_are_graphed_in_the_order_first_sampled() {
+        let mut counters = vec![];
+        push_counter_sample(&mut counters, "Glyphs", 10.0);
+        push_counter_sample(&mut counters, "Layers", 2.0);
+        push_counter_sample(&mut counters, "Glyphs", 12.0);
+
+        let graphs = graphs(&SampleBuffer::new(),
+                            &SampleBuffer::new(),
+                            &SampleBuffer::new(),
+                            &counters);
+        let labels: Vec<&str> = graphs[6..].iter().map(|graph| &*graph.label).collect();
+        assert_eq!(labels, vec!["Glyphs: 12", "Layers: 2"]);
+        assert_eq!(&graphs[6].values[(SAMPLE_BUFFER_SIZE - 2)..], &[10.0, 12.0]);
+        assert_eq!(graphs[0].label, "CPU: 0.000 ms");
+    }
+}
@@ -0,0 +1,372 @@
+// pathfinder/renderer/src/gpu/inspector.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An interactive scene inspector, drawn with the debug UI.
+//!
+//! The inspector lists the display items of a scene along with the paths they draw. Clicking a
+//! path toggles its visibility, and hovering over a path highlights the tiles it builds into and
+//! shows its paint and blend mode. This is handy for tracking down which path in an imported
+//! document with thousands of paths is misbehaving.
+
+use crate::options::{BuildOptions, RenderTransform};
+use crate::scene::{DisplayItem, PathTiles, Scene};
+use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
+use pathfinder_color::ColorU;
+use pathfinder_content::effects::BlendMode;
+use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_gpu::Device;
+use pathfinder_ui::{FONT_ASCENT, LINE_HEIGHT, PADDING, TEXT_COLOR, UIPresenter, WINDOW_COLOR};
+
+const INSPECTOR_WINDOW_WIDTH: i32 = 520;
+const INSPECTOR_ROW_COUNT: usize = 12;
+const INSPECTOR_DETAIL_LINE_COUNT: i32 = 3;
+const INSPECTOR_WINDOW_HEIGHT: i32 = LINE_HEIGHT * (INSPECTOR_ROW_COUNT as i32 +
+                                                    INSPECTOR_DETAIL_LINE_COUNT + 2) +
+    PADDING * 2;
+
+const PAGER_BUTTON_WIDTH: i32 = 80;
+
+static HIGHLIGHT_SOLID_COLOR:   ColorU = ColorU { r: 64,  g: 192, b: 255, a: 64  };
+static HIGHLIGHT_ALPHA_COLOR:   ColorU = ColorU { r: 255, g: 64,  b: 192, a: 64  };
+static HIGHLIGHT_OUTLINE_COLOR: ColorU = ColorU { r: 255, g: 64,  b: 192, a: 192 };
+
+/// Lists the contents of a scene and lets the user toggle and inspect individual paths.
+///
+/// The inspector works on a snapshot of the scene taken with `set_scene()`. Visibility changes are
+/// reported back to the caller, which is responsible for applying them to the real scene (for
+/// example, with `SceneProxy::set_path_visible()`).
+pub struct SceneInspector {
+    scene: Scene,
+    rows: Vec<InspectorRow>,
+    paths: Vec<InspectedPath>,
+    first_row: usize,
+    hovered_path: Option<usize>,
+    // The tiles of the last highlighted path, along with its index and the transform it was built
+    // with, so that it's only tiled again when either changes.
+    highlighted_tiles: Option<(usize, Transform2F, PathTiles)>,
+}
+
+/// A change that the user made in the inspector.
+#[derive(Clone, Copy, Debug)]
+pub struct PathVisibilityChange {
+    pub path_index: usize,
+    pub visible: bool,
+}
+
+enum InspectorRow {
+    DisplayItem(String),
+    Path(usize),
+}
+
+struct InspectedPath {
+    name: String,
+    paint: String,
+    blend_mode: BlendMode,
+    bounds: RectF,
+    visible: bool,
+}
+
+impl SceneInspector {
+    #[inline]
+    pub fn new() -> SceneInspector {
+        SceneInspector {
+            scene: Scene::new(),
+            rows: vec![],
+            paths: vec![],
+            first_row: 0,
+            hovered_path: None,
+            highlighted_tiles: None,
+        }
+    }
+
+    /// Takes a new snapshot of the scene to inspect.
+    pub fn set_scene(&mut self, scene: &Scene) {
+        let paints = &scene.palette().paints;
+        self.paths = scene.paths.iter().map(|path| {
+            InspectedPath {
+                name: path.name().to_owned(),
+                paint: format!("{:?}", paints[path.paint().0 as usize]),
+                blend_mode: path.blend_mode(),
+                bounds: path.outline().bounds(),
+                visible: path.is_visible(),
+            }
+        }).collect();
+
+        self.rows.clear();
+        for display_item in scene.display_list() {
+            match *display_item {
+                DisplayItem::DrawPaths { start_index, end_index } => {
+                    self.rows.push(InspectorRow::DisplayItem(format!("Draw Paths {}..{}",
+                                                                     start_index,
+                                                                     end_index)));
+                    for path_index in start_index..end_index {
+                        self.rows.push(InspectorRow::Path(path_index as usize));
+                    }
+                }
+                DisplayItem::DrawRenderTarget { render_target, .. } => {
+                    self.rows.push(InspectorRow::DisplayItem(
+                        format!("Draw Render Target {}", render_target.0)));
+                }
+                DisplayItem::PushRenderTarget(render_target) => {
+                    self.rows.push(InspectorRow::DisplayItem(
+                        format!("Push Render Target {}", render_target.0)));
+                }
+                DisplayItem::PopRenderTarget => {
+                    self.rows.push(InspectorRow::DisplayItem("Pop Render Target".to_owned()));
+                }
+            }
+        }
+
+        self.scene = scene.clone();
+        self.first_row = 0;
+        self.hovered_path = None;
+        self.highlighted_tiles = None;
+    }
+
+    #[inline]
+    pub fn hovered_path(&self) -> Option<usize> {
+        self.hovered_path
+    }
+
+    /// Draws the inspector window and handles any clicks inside it.
+    ///
+    /// `transform` maps scene coordinates to framebuffer coordinates, as `BuildOptions::transform`
+    /// does when the scene is built. If it is `None` (e.g. in 3D mode), the hovered path's tiles
+    /// aren't highlighted.
+    pub fn draw<D>(&mut self,
+                   device: &D,
+                   ui_presenter: &mut UIPresenter<D>,
+                   transform: Option<Transform2F>)
+                   -> Option<PathVisibilityChange>
+                   where D: Device {
+        let framebuffer_size = ui_presenter.framebuffer_size();
+        let window_rect = RectI::new(
+            Vector2I::new(framebuffer_size.x() - PADDING - INSPECTOR_WINDOW_WIDTH, PADDING),
+            Vector2I::new(INSPECTOR_WINDOW_WIDTH, INSPECTOR_WINDOW_HEIGHT),
+        );
+
+        // Draw the tile highlight first so that the window stays legible on top of it.
+        if let (Some(path_index), Some(transform)) = (self.hovered_path, transform) {
+            self.draw_tile_highlight(device, ui_presenter, path_index, transform);
+        }
+
+        ui_presenter.draw_solid_rounded_rect(device, window_rect, WINDOW_COLOR);
+
+        let origin = window_rect.origin() + Vector2I::new(PADDING, PADDING + FONT_ASCENT);
+        ui_presenter.draw_text(device,
+                               &format!("Scene: {} Paths, {} Display Items",
+                                        self.paths.len(),
+                                        self.rows.len().saturating_sub(self.paths.len())),
+                               origin,
+                               false);
+
+        // Draw the list.
+        let mut change = None;
+        self.hovered_path = None;
+        let row_width = INSPECTOR_WINDOW_WIDTH - PADDING;
+        let last_row = (self.first_row + INSPECTOR_ROW_COUNT).min(self.rows.len());
+        for (row_offset, row_index) in (self.first_row..last_row).enumerate() {
+            let row_rect = RectI::new(
+                window_rect.origin() +
+                    Vector2I::new(PADDING / 2, PADDING + LINE_HEIGHT * (row_offset as i32 + 1)),
+                Vector2I::new(row_width, LINE_HEIGHT));
+            let text_origin = origin + Vector2I::new(0, LINE_HEIGHT * (row_offset as i32 + 1));
+
+            let path_index = match self.rows[row_index] {
+                InspectorRow::DisplayItem(ref label) => {
+                    ui_presenter.draw_text(device, label, text_origin, false);
+                    continue;
+                }
+                InspectorRow::Path(path_index) => path_index,
+            };
+
+            if ui_presenter.event_queue.handle_mouse_down_in_rect(row_rect).is_some() {
+                let path = &mut self.paths[path_index];
+                path.visible = !path.visible;
+                // Keep the snapshot in step, so that the highlight matches what gets built. The
+                // list was made from the snapshot, so the path is there.
+                self.scene.set_path_visible(path_index, path.visible).unwrap();
+                self.highlighted_tiles = None;
+                change = Some(PathVisibilityChange { path_index, visible: path.visible });
+            }
+
+            let hovered = row_rect.to_f32().contains_point(ui_presenter.mouse_position);
+            if hovered {
+                self.hovered_path = Some(path_index);
+                ui_presenter.draw_solid_rounded_rect(device, row_rect, TEXT_COLOR);
+            }
+
+            let path = &self.paths[path_index];
+            let label = format!("  {} #{} {}",
+                                if path.visible { "[x]" } else { "[ ]" },
+                                path_index,
+                                if path.name.is_empty() { "(unnamed)" } else { &path.name });
+            let label = truncate_to_width(ui_presenter, &label, row_width - PADDING);
+            ui_presenter.draw_text(device, &label, text_origin, hovered);
+        }
+
+        // Draw the details of the hovered path.
+        let detail_origin = origin +
+            Vector2I::new(0, LINE_HEIGHT * (INSPECTOR_ROW_COUNT as i32 + 1));
+        let detail_width = INSPECTOR_WINDOW_WIDTH - PADDING * 2;
+        match self.hovered_path {
+            None => {
+                ui_presenter.draw_text(device,
+                                       "Hover over a path to inspect it.",
+                                       detail_origin,
+                                       false);
+            }
+            Some(path_index) => {
+                let path = &self.paths[path_index];
+                let lines = [
+                    format!("Paint: {}", path.paint),
+                    format!("Blend Mode: {:?}", path.blend_mode),
+                    format!("Bounds: {:.1},{:.1} {:.1}x{:.1}",
+                            path.bounds.origin_x(),
+                            path.bounds.origin_y(),
+                            path.bounds.width(),
+                            path.bounds.height()),
+                ];
+                for (line_index, line) in lines.iter().enumerate() {
+                    let line = truncate_to_width(ui_presenter, line, detail_width);
+                    ui_presenter.draw_text(device,
+                                           &line,
+                                           detail_origin + Vector2I::new(0,
+                                                                         LINE_HEIGHT *
+                                                                         line_index as i32),
+                                           false);
+                }
+            }
+        }
+
+        // Draw the pager.
+        let pager_origin = window_rect.origin() +
+            Vector2I::new(PADDING,
+                          PADDING + LINE_HEIGHT * (INSPECTOR_ROW_COUNT as i32 +
+                                                   INSPECTOR_DETAIL_LINE_COUNT + 1));
+        if self.draw_pager_button(device, ui_presenter, pager_origin, "Prev") {
+            self.first_row = self.first_row.saturating_sub(INSPECTOR_ROW_COUNT);
+        }
+        let next_origin = pager_origin + Vector2I::new(PAGER_BUTTON_WIDTH + PADDING, 0);
+        if self.draw_pager_button(device, ui_presenter, next_origin, "Next") &&
+                self.first_row + INSPECTOR_ROW_COUNT < self.rows.len() {
+            self.first_row += INSPECTOR_ROW_COUNT;
+        }
+        ui_presenter.draw_text(device,
+                               &format!("Rows {}-{} of {}",
+                                        self.first_row.min(self.rows.len()),
+                                        last_row,
+                                        self.rows.len()),
+                               next_origin + Vector2I::new(PAGER_BUTTON_WIDTH + PADDING,
+                                                           FONT_ASCENT + PADDING / 2),
+                               false);
+
+        change
+    }
+
+    fn draw_pager_button<D>(&self,
+                            device: &D,
+                            ui_presenter: &mut UIPresenter<D>,
+                            origin: Vector2I,
+                            label: &str)
+                            -> bool
+                            where D: Device {
+        let button_rect = RectI::new(origin, Vector2I::new(PAGER_BUTTON_WIDTH, LINE_HEIGHT));
+        ui_presenter.draw_rounded_rect_outline(device, button_rect, TEXT_COLOR);
+        let label_width = ui_presenter.measure_text(label);
+        ui_presenter.draw_text(device,
+                               label,
+                               origin + Vector2I::new(PAGER_BUTTON_WIDTH / 2 - label_width / 2,
+                                                      FONT_ASCENT + PADDING / 2),
+                               false);
+        ui_presenter.event_queue.handle_mouse_down_in_rect(button_rect).is_some()
+    }
+
+    // Highlights the tiles that the path builds into: solid tiles in one color, and alpha tiles
+    // in another with their outlines.
+    fn draw_tile_highlight<D>(&mut self,
+                              device: &D,
+                              ui_presenter: &UIPresenter<D>,
+                              path_index: usize,
+                              transform: Transform2F)
+                              where D: Device {
+        let up_to_date = match self.highlighted_tiles {
+            Some((highlighted_path, highlighted_transform, _)) => {
+                highlighted_path == path_index && highlighted_transform == transform
+            }
+            None => false,
+        };
+        if !up_to_date {
+            let options = BuildOptions {
+                transform: RenderTransform::Transform2D(transform),
+                ..BuildOptions::default()
+            };
+            let path_tiles = self.scene.build_path_tiles(path_index, options).unwrap_or_default();
+            self.highlighted_tiles = Some((path_index, transform, path_tiles));
+        }
+        let path_tiles = &self.highlighted_tiles.as_ref().unwrap().2;
+
+        let framebuffer_size = ui_presenter.framebuffer_size();
+        let solid_rects = tile_rects(&path_tiles.solid_tiles, framebuffer_size);
+        let alpha_rects = tile_rects(&path_tiles.alpha_tiles, framebuffer_size);
+        ui_presenter.draw_solid_rects(device, &solid_rects, HIGHLIGHT_SOLID_COLOR);
+        ui_presenter.draw_solid_rects(device, &alpha_rects, HIGHLIGHT_ALPHA_COLOR);
+        ui_presenter.draw_rect_outlines(device, &alpha_rects, HIGHLIGHT_OUTLINE_COLOR);
+    }
+}
+
+impl Default for SceneInspector {
+    #[inline]
+    fn default() -> SceneInspector {
+        SceneInspector::new()
+    }
+}
+
+// Returns the framebuffer rects of the tiles, leaving out those outside the framebuffer.
+fn tile_rects(tiles: &[Vector2I], framebuffer_size: Vector2I) -> Vec<RectI> {
+    let tile_size = Vector2I::new(TILE_WIDTH as i32, TILE_HEIGHT as i32);
+    let framebuffer_rect = RectI::new(Vector2I::default(), framebuffer_size);
+    tiles.iter()
+         .map(|&tile| RectI::new(tile.scale_xy(tile_size), tile_size))
+         .filter(|&rect| rect.intersects(framebuffer_rect))
+         .collect()
+}
+
+fn truncate_to_width<D>(ui_presenter: &UIPresenter<D>, text: &str, max_width: i32) -> String
+                        where D: Device {
+    if ui_presenter.measure_text(text) <= max_width {
+        return text.to_owned();
+    }
+    let mut truncated: String = text.to_owned();
+    while !truncated.is_empty() && ui_presenter.measure_text(&format!("{}...", truncated)) >
+            max_width {
+        truncated.pop();
+    }
+    truncated.push_str("...");
+    truncated
+}
+
+#[cfg(test)]
+mod test {
+    use super::tile_rects;
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::vector::Vector2I;
+
+    #[test]
+    fn test_tile_rects_are_clipped_to_the_framebuffer() {
+        let tiles = [Vector2I::new(0, 0), Vector2I::new(2, 1), Vector2I::new(-1, 0),
+                     Vector2I::new(4, 0)];
+        assert_eq!(tile_rects(&tiles, Vector2I::new(64, 32)),
+                   vec![RectI::new(Vector2I::new(0, 0), Vector2I::splat(16)),
+                        RectI::new(Vector2I::new(32, 16), Vector2I::splat(16))]);
+    }
+}
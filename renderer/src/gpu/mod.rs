@@ -10,8 +10,13 @@
 
 //! The GPU renderer for Pathfinder 3.
 
+pub mod camera;
+#[cfg(feature = "pf-debug-ui")]
 pub mod debug;
+#[cfg(feature = "pf-debug-ui")]
+pub mod inspector;
 pub mod options;
 pub mod renderer;
+pub mod spot;
 
 pub(crate) mod shaders;
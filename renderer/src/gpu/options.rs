@@ -8,6 +8,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use crate::gpu::spot::SpotColorPalette;
 use pathfinder_color::ColorF;
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::vector::Vector2I;
@@ -17,6 +18,64 @@ use pathfinder_gpu::Device;
 #[derive(Default)]
 pub struct RendererOptions {
     pub background_color: Option<ColorF>,
+    /// If set, all colors are remapped to tints of these inks at composite time.
+    pub spot_colors: Option<SpotColorPalette>,
+    /// If true, draws a debugging overlay over the scene at the end of each frame: the tile grid,
+    /// each tile tinted by the batch that drew it, and outlines around the tiles that needed the
+    /// mask framebuffer. Useful for finding out why a scene breaks into many batches or produces
+    /// many alpha tiles.
+    pub show_tile_overlay: bool,
+    /// How much effort to spend antialiasing the edges of paths.
+    pub antialiasing_quality: AntialiasingQuality,
+    /// If set, tiles drawn onto the destination framebuffer use its depth buffer, so that vector
+    /// content can be occluded by, or occlude, 3D geometry drawn in the same pass.
+    pub depth: Option<DepthOptions>,
+    /// If true, checks that every texture page is uploaded to and sampled with the alpha type it
+    /// was allocated with, logging a warning and counting each mismatch in the render stats. A
+    /// paint sampled as straight when it's premultiplied, or the other way around, draws with
+    /// dark or light fringes wherever it's translucent.
+    pub validate_alpha_types: bool,
+}
+
+/// How tiles drawn onto the destination framebuffer interact with its depth buffer.
+///
+/// These only apply when the destination has a depth buffer, as the default framebuffer of a game
+/// usually does. Render targets, and scenes whose blend modes need a readable framebuffer, are
+/// drawn into intermediate framebuffers without depth buffers, so they ignore these.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthOptions {
+    /// The depth of every tile, as a normalized device coordinate between -1 and 1, like the Z
+    /// coordinates that the 3D scene's projection produces.
+    pub z: f32,
+    /// Whether tiles are hidden wherever the depth buffer holds a nearer value.
+    pub test: bool,
+    /// Whether tiles write their depth to the depth buffer.
+    ///
+    /// Depth is written over the whole area of each tile, including the uncovered parts of tiles
+    /// along the edges of paths.
+    pub write: bool,
+}
+
+/// How the coverage of pixels along the edges of paths is computed.
+///
+/// Every level still goes through the fill and mask passes, since those resolve the fill rule;
+/// the levels differ in the area lookup table the fill pass samples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AntialiasingQuality {
+    /// No antialiasing: a pixel is covered if its center is inside the path. Edges are jagged.
+    None,
+    /// Area coverage sampled from the lookup table without filtering, which quantizes it
+    /// slightly in exchange for cheaper texture fetches on low-power GPUs.
+    Fast,
+    /// Exact analytic area coverage.
+    Full,
+}
+
+impl Default for AntialiasingQuality {
+    #[inline]
+    fn default() -> AntialiasingQuality {
+        AntialiasingQuality::Full
+    }
 }
 
 #[derive(Clone)]
@@ -29,6 +88,25 @@ where
         window_size: Vector2I,
     },
     Other(D::Framebuffer),
+    /// The device's default render target, shared with an embedding application that has drawn
+    /// into it and will keep drawing after Pathfinder does, such as a game engine compositing
+    /// vector UI into its own frame.
+    ///
+    /// For GL, this is the framebuffer set with `GLDevice::set_default_framebuffer()`. The
+    /// renderer saves the device state before each command and restores it afterward, so the
+    /// application's pipeline state survives. Pathfinder draws over the existing contents
+    /// without clearing them, so `RendererOptions::background_color` is ignored.
+    ForeignTarget {
+        viewport: RectI,
+        window_size: Vector2I,
+    },
+    /// A framebuffer for each layer of a layered destination, such as one for each eye of a VR
+    /// headset. They may be separate textures or the layers of an array texture.
+    ///
+    /// Scenes built with `Scene::build_stereo()` draw each eye into its own layer, and other
+    /// scenes are drawn into the first. There must be at least one layer. Otherwise the layers
+    /// are treated like `Other` framebuffers, except that spot color rendering isn't supported.
+    Layers(Vec<D::Framebuffer>),
 }
 
 impl<D> Default for DestFramebuffer<D> where D: Device {
@@ -48,13 +126,32 @@ where
         DestFramebuffer::Default { viewport, window_size }
     }
 
+    #[inline]
+    pub fn foreign_target(viewport: RectI, window_size: Vector2I) -> DestFramebuffer<D> {
+        DestFramebuffer::ForeignTarget { viewport, window_size }
+    }
+
+    #[inline]
+    pub fn is_foreign(&self) -> bool {
+        match *self {
+            DestFramebuffer::ForeignTarget { .. } => true,
+            DestFramebuffer::Default { .. } |
+            DestFramebuffer::Other(_) |
+            DestFramebuffer::Layers(_) => false,
+        }
+    }
+
     #[inline]
     pub fn window_size(&self, device: &D) -> Vector2I {
         match *self {
-            DestFramebuffer::Default { window_size, .. } => window_size,
+            DestFramebuffer::Default { window_size, .. } |
+            DestFramebuffer::ForeignTarget { window_size, .. } => window_size,
             DestFramebuffer::Other(ref framebuffer) => {
                 device.texture_size(device.framebuffer_texture(framebuffer))
             }
+            DestFramebuffer::Layers(ref framebuffers) => {
+                device.texture_size(device.framebuffer_texture(&framebuffers[0]))
+            }
         }
     }
 }
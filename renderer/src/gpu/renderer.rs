@@ -8,31 +8,49 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::gpu::debug::DebugUIPresenter;
-use crate::gpu::options::{DestFramebuffer, RendererOptions};
+use crate::error::Error;
+#[cfg(feature = "pf-debug-ui")]
+use crate::gpu::debug::{DebugUIPresenter, TileOverlay};
+use crate::gpu::options::{AntialiasingQuality, DepthOptions, DestFramebuffer, RendererOptions};
 use crate::gpu::shaders::{AlphaTileBlendModeProgram, AlphaTileDodgeBurnProgram};
-use crate::gpu::shaders::{AlphaTileHSLProgram, AlphaTileOverlayProgram};
-use crate::gpu::shaders::{AlphaTileProgram, AlphaTileVertexArray, BlitProgram, BlitVertexArray};
+use crate::gpu::shaders::{AlphaTileDualSourceProgram, AlphaTileHSLProgram};
+use crate::gpu::shaders::{AlphaTileOverlayProgram, AlphaTilePorterDuffProgram};
+use crate::gpu::shaders::{AlphaTileProgram, AlphaTileVertexArray, BlitProgram, BlitSpotProgram};
+use crate::gpu::shaders::{BlitVertexArray, CoverageReduceProgram, CustomFilterProgram};
+use crate::gpu::shaders::CustomPaintProgram;
+use crate::gpu::spot::SpotColorPalette;
 use crate::gpu::shaders::{CopyTileProgram, CopyTileVertexArray, FillProgram, FillVertexArray};
 use crate::gpu::shaders::{MAX_FILLS_PER_BATCH, MaskTileProgram, MaskTileVertexArray};
-use crate::gpu::shaders::{ReprojectionProgram, ReprojectionVertexArray, SolidTileBlurFilterProgram, SolidTileProgram, SolidTileTextFilterProgram};
+use crate::gpu::shaders::{ReprojectionProgram, ReprojectionVertexArray, SolidTileProgram};
+#[cfg(feature = "pf-filter-blur")]
+use crate::gpu::shaders::SolidTileBlurFilterProgram;
+#[cfg(feature = "pf-filter-text")]
+use crate::gpu::shaders::SolidTileTextFilterProgram;
 use crate::gpu::shaders::{SolidTileVertexArray, StencilProgram, StencilVertexArray};
-use crate::gpu_data::{AlphaTile, FillBatchPrimitive, MaskTile, RenderCommand, SolidTile};
-use crate::gpu_data::{TextureLocation, TexturePageDescriptor, TexturePageId};
+use crate::gpu_data::{AlphaTile, AlphaTileBatch, FillBatchPrimitive, MaskTile, RenderCommand};
+use crate::gpu_data::{SolidTile, TexelChanges, TexelsId};
+use crate::gpu_data::{AlphaType, TextureLocation, TexturePageDescriptor, TexturePageId};
 use crate::options::BoundingQuad;
+use crate::paint::CustomPaintShaderId;
 use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
 use pathfinder_color::{self as color, ColorF, ColorU};
-use pathfinder_content::effects::{BlendMode, BlurDirection, CompositeOp, DefringingKernel};
-use pathfinder_content::effects::{Effects, Filter};
+use pathfinder_content::effects::{BlendMode, CompositeOp, CustomFilterId, Effects, Filter};
+#[cfg(feature = "pf-filter-blur")]
+use pathfinder_content::effects::BlurDirection;
+#[cfg(feature = "pf-filter-text")]
+use pathfinder_content::effects::DefringingKernel;
 use pathfinder_content::fill::FillRule;
+use pathfinder_content::pattern::ExternalTextureId;
 use pathfinder_content::render_target::RenderTargetId;
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::transform3d::Transform4F;
 use pathfinder_geometry::vector::{Vector2F, Vector2I, Vector4F};
 use pathfinder_gpu::{BlendFactor, BlendOp, BlendState, BufferData, BufferTarget, BufferUploadMode};
-use pathfinder_gpu::{ClearOps, DepthFunc, DepthState, Device, Primitive, RenderOptions};
+use pathfinder_gpu::{ClearOps, DepthFunc, DepthState, Device, DeviceLostError, Primitive};
+use pathfinder_gpu::RenderOptions;
 use pathfinder_gpu::{RenderState, RenderTarget, StencilFunc, StencilState, TextureDataRef};
-use pathfinder_gpu::{TextureFormat, TextureSamplingFlags, UniformData};
+use pathfinder_gpu::{TextureData, TextureFormat, TextureSamplingFlags, UniformData};
+use pathfinder_gpu::memory::GPUMemoryUsage;
 use pathfinder_resources::ResourceLoader;
 use pathfinder_simd::default::{F32x2, F32x4};
 use std::cmp;
@@ -50,9 +68,11 @@ pub(crate) const MASK_TILES_ACROSS: u32 = 256;
 pub(crate) const MASK_TILES_DOWN: u32 = 256;
 
 // 1.0 / sqrt(2*pi)
+#[cfg(feature = "pf-filter-blur")]
 const SQRT_2_PI_INV: f32 = 0.3989422804014327;
 
-const TEXTURE_CACHE_SIZE: usize = 8;
+// Textures and framebuffers in the pool that go unused for this many frames are freed.
+const TEXTURE_POOL_MAX_AGE: u32 = 2;
 
 // FIXME(pcwalton): Shrink this again!
 const MASK_FRAMEBUFFER_WIDTH:  i32 = TILE_WIDTH as i32  * MASK_TILES_ACROSS as i32;
@@ -66,6 +86,18 @@ const OVERLAY_BLEND_MODE_SCREEN:     i32 = 1;
 const OVERLAY_BLEND_MODE_HARD_LIGHT: i32 = 2;
 const OVERLAY_BLEND_MODE_OVERLAY:    i32 = 3;
 
+const PORTER_DUFF_OP_COPY:      i32 = 0;
+const PORTER_DUFF_OP_SRC_IN:    i32 = 1;
+const PORTER_DUFF_OP_DEST_IN:   i32 = 2;
+const PORTER_DUFF_OP_SRC_OUT:   i32 = 3;
+const PORTER_DUFF_OP_DEST_ATOP: i32 = 4;
+
+// Stencil clips are numbered, and each writes its number to the upper seven bits of the stencil
+// buffer, so that drawing a new clip doesn't require clearing the old one. The lowest bit is the
+// 3D scene's stencil.
+const CLIP_STENCIL_MASK: u32 = 0xfe;
+const CLIP_STENCIL_MAX_VALUE: u32 = CLIP_STENCIL_MASK >> 1;
+
 pub struct Renderer<D>
 where
     D: Device,
@@ -77,6 +109,8 @@ where
     dest_framebuffer: DestFramebuffer<D>,
     options: RendererOptions,
     blit_program: BlitProgram<D>,
+    blit_spot_program: BlitSpotProgram<D>,
+    coverage_reduce_program: CoverageReduceProgram<D>,
     fill_program: FillProgram<D>,
     mask_winding_tile_program: MaskTileProgram<D>,
     mask_evenodd_tile_program: MaskTileProgram<D>,
@@ -88,7 +122,15 @@ where
     alpha_tile_difference_program: AlphaTileBlendModeProgram<D>,
     alpha_tile_exclusion_program: AlphaTileBlendModeProgram<D>,
     alpha_tile_hsl_program: AlphaTileHSLProgram<D>,
+    alpha_tile_porterduff_program: AlphaTilePorterDuffProgram<D>,
+    // Only present if the device supports dual-source blending.
+    alpha_tile_dual_source_program:
+        Option<(AlphaTileDualSourceProgram<D>, AlphaTileVertexArray<D>)>,
+    // Only present if the device supports advanced blend equations.
+    alpha_tile_advanced_program: Option<(AlphaTileProgram<D>, AlphaTileVertexArray<D>)>,
     blit_vertex_array: BlitVertexArray<D>,
+    blit_spot_vertex_array: BlitVertexArray<D>,
+    coverage_reduce_vertex_array: BlitVertexArray<D>,
     mask_winding_tile_vertex_array: MaskTileVertexArray<D>,
     mask_evenodd_tile_vertex_array: MaskTileVertexArray<D>,
     copy_tile_vertex_array: CopyTileVertexArray<D>,
@@ -99,7 +141,13 @@ where
     alpha_tile_difference_vertex_array: AlphaTileVertexArray<D>,
     alpha_tile_exclusion_vertex_array: AlphaTileVertexArray<D>,
     alpha_tile_hsl_vertex_array: AlphaTileVertexArray<D>,
+    alpha_tile_porterduff_vertex_array: AlphaTileVertexArray<D>,
+    // Indexed by `CustomPaintShaderId`.
+    custom_paint_shaders: Vec<CustomPaintShader<D>>,
     area_lut_texture: D::Texture,
+    // Used instead of the area LUT when antialiasing is off: each pixel is either fully covered
+    // or not covered at all.
+    aliased_area_lut_texture: D::Texture,
     alpha_tile_vertex_buffer: D::Buffer,
     quad_vertex_positions_buffer: D::Buffer,
     quad_vertex_indices_buffer: D::Buffer,
@@ -112,6 +160,8 @@ where
     intermediate_dest_framebuffer: D::Framebuffer,
     texture_pages: Vec<TexturePage<D>>,
     render_targets: Vec<RenderTargetInfo>,
+    // Indexed by `ExternalTextureId`.
+    external_textures: Vec<Option<D::Texture>>,
     render_target_stack: Vec<RenderTargetId>,
 
     // This is a dummy texture consisting solely of a single `rgba(0, 0, 0, 255)` texel. It serves
@@ -119,20 +169,35 @@ where
     // used, then the transparent black paint would zero out the alpha mask.
     clear_paint_texture: D::Texture,
 
+    // The lookup table that the final blit remaps colors through, if spot color rendering is
+    // enabled.
+    spot_lut_texture: Option<D::Texture>,
+
     // Solid tiles
     solid_tile_program: SolidTileProgram<D>,
+    #[cfg(feature = "pf-filter-blur")]
     solid_tile_blur_filter_program: SolidTileBlurFilterProgram<D>,
+    #[cfg(feature = "pf-filter-text")]
     solid_tile_text_filter_program: SolidTileTextFilterProgram<D>,
     solid_tile_vertex_array: SolidTileVertexArray<D>,
+    // Indexed by `CustomFilterId`.
+    custom_filters: Vec<CustomFilter<D>>,
+    #[cfg(feature = "pf-filter-blur")]
     solid_tile_blur_filter_vertex_array: SolidTileVertexArray<D>,
+    #[cfg(feature = "pf-filter-text")]
     solid_tile_text_filter_vertex_array: SolidTileVertexArray<D>,
     solid_tile_vertex_buffer: D::Buffer,
+    #[cfg(feature = "pf-filter-text")]
     gamma_lut_texture: D::Texture,
 
     // Stencil shader
     stencil_program: StencilProgram<D>,
     stencil_vertex_array: StencilVertexArray<D>,
 
+    // Stencil clipping
+    clip_stencil_program: AlphaTileProgram<D>,
+    clip_stencil_vertex_array: AlphaTileVertexArray<D>,
+
     // Reprojection shader
     reprojection_program: ReprojectionProgram<D>,
     reprojection_vertex_array: ReprojectionVertexArray<D>,
@@ -140,17 +205,29 @@ where
     // Rendering state
     framebuffer_flags: FramebufferFlags,
     buffered_fills: Vec<FillBatchPrimitive>,
-    texture_cache: TextureCache<D>,
+    texture_pool: TexturePool<D>,
+    bounding_quad: BoundingQuad,
+    // The tile that this frame's tile positions are relative to.
+    chunk_origin: Vector2I,
+    // The layer of the destination that tiles are drawn into.
+    current_layer: usize,
+    // The number of the current stencil clip, or 0 if there's been none this frame.
+    clip_stencil_value: u32,
 
     // Debug
     pub stats: RenderStats,
     current_timers: RenderTimers<D>,
     pending_timers: VecDeque<RenderTimers<D>>,
     free_timer_queries: Vec<D::TimerQuery>,
+    #[cfg(feature = "pf-debug-ui")]
     pub debug_ui_presenter: DebugUIPresenter<D>,
+    #[cfg(feature = "pf-debug-ui")]
+    tile_overlay: TileOverlay,
 
     // Extra info
     flags: RendererFlags,
+    // Set once the device is found to be lost, after which commands are ignored.
+    device_lost: Option<DeviceLostError>,
 }
 
 impl<D> Renderer<D>
@@ -163,6 +240,8 @@ where
                options: RendererOptions)
                -> Renderer<D> {
         let blit_program = BlitProgram::new(&device, resources);
+        let blit_spot_program = BlitSpotProgram::new(&device, resources);
+        let coverage_reduce_program = CoverageReduceProgram::new(&device, resources);
         let fill_program = FillProgram::new(&device, resources);
         let mask_winding_tile_program = MaskTileProgram::new(FillRule::Winding,
                                                              &device,
@@ -184,12 +263,20 @@ where
                                                                           resources,
                                                                           "tile_alpha_exclusion");
         let alpha_tile_hsl_program = AlphaTileHSLProgram::new(&device, resources);
+        let alpha_tile_porterduff_program = AlphaTilePorterDuffProgram::new(&device, resources);
+        #[cfg(feature = "pf-filter-blur")]
         let solid_tile_blur_filter_program = SolidTileBlurFilterProgram::new(&device, resources);
+        #[cfg(feature = "pf-filter-text")]
         let solid_tile_text_filter_program = SolidTileTextFilterProgram::new(&device, resources);
         let stencil_program = StencilProgram::new(&device, resources);
+        let clip_stencil_program = AlphaTileProgram::new_clip_stencil(&device, resources);
         let reprojection_program = ReprojectionProgram::new(&device, resources);
 
         let area_lut_texture = device.create_texture_from_png(resources, "area-lut");
+        device.set_texture_sampling_mode(&area_lut_texture,
+                                         area_lut_sampling_flags(options.antialiasing_quality));
+        let aliased_area_lut_texture = create_aliased_area_lut_texture(&device);
+        #[cfg(feature = "pf-filter-text")]
         let gamma_lut_texture = device.create_texture_from_png(resources, "gamma-lut");
 
         let alpha_tile_vertex_buffer = device.create_buffer();
@@ -216,6 +303,18 @@ where
             &quad_vertex_positions_buffer,
             &quad_vertex_indices_buffer,
         );
+        let blit_spot_vertex_array = BlitVertexArray::new(
+            &device,
+            &blit_spot_program.blit_program,
+            &quad_vertex_positions_buffer,
+            &quad_vertex_indices_buffer,
+        );
+        let coverage_reduce_vertex_array = BlitVertexArray::new(
+            &device,
+            &coverage_reduce_program.blit_program,
+            &quad_vertex_positions_buffer,
+            &quad_vertex_indices_buffer,
+        );
         let fill_vertex_array = FillVertexArray::new(
             &device,
             &fill_program,
@@ -280,18 +379,46 @@ where
             &alpha_tile_vertex_buffer,
             &quads_vertex_indices_buffer,
         );
+        let alpha_tile_porterduff_vertex_array = AlphaTileVertexArray::new(
+            &device,
+            &alpha_tile_porterduff_program.alpha_tile_blend_mode_program.alpha_tile_program,
+            &alpha_tile_vertex_buffer,
+            &quads_vertex_indices_buffer,
+        );
+        let alpha_tile_dual_source_program = if device.supports_dual_source_blending() {
+            let program = AlphaTileDualSourceProgram::new(&device, resources);
+            let vertex_array = AlphaTileVertexArray::new(&device,
+                                                         &program.alpha_tile_program,
+                                                         &alpha_tile_vertex_buffer,
+                                                         &quads_vertex_indices_buffer);
+            Some((program, vertex_array))
+        } else {
+            None
+        };
+        let alpha_tile_advanced_program = if device.supports_advanced_blending() {
+            let program = AlphaTileProgram::new_advanced(&device, resources);
+            let vertex_array = AlphaTileVertexArray::new(&device,
+                                                         &program,
+                                                         &alpha_tile_vertex_buffer,
+                                                         &quads_vertex_indices_buffer);
+            Some((program, vertex_array))
+        } else {
+            None
+        };
         let solid_tile_vertex_array = SolidTileVertexArray::new(
             &device,
             &solid_tile_program,
             &solid_tile_vertex_buffer,
             &quads_vertex_indices_buffer,
         );
+        #[cfg(feature = "pf-filter-blur")]
         let solid_tile_blur_filter_vertex_array = SolidTileVertexArray::new(
             &device,
             &solid_tile_blur_filter_program.solid_tile_program,
             &solid_tile_vertex_buffer,
             &quads_vertex_indices_buffer,
         );
+        #[cfg(feature = "pf-filter-text")]
         let solid_tile_text_filter_vertex_array = SolidTileVertexArray::new(
             &device,
             &solid_tile_text_filter_program.solid_tile_program,
@@ -299,6 +426,12 @@ where
             &quads_vertex_indices_buffer,
         );
         let stencil_vertex_array = StencilVertexArray::new(&device, &stencil_program);
+        let clip_stencil_vertex_array = AlphaTileVertexArray::new(
+            &device,
+            &clip_stencil_program,
+            &alpha_tile_vertex_buffer,
+            &quads_vertex_indices_buffer,
+        );
         let reprojection_vertex_array = ReprojectionVertexArray::new(
             &device,
             &reprojection_program,
@@ -329,6 +462,11 @@ where
                                             Vector2I::splat(1),
                                             TextureDataRef::U8(&[0, 0, 0, 255]));
 
+        let spot_lut_texture = options.spot_colors
+                                      .as_ref()
+                                      .map(|palette| create_spot_lut_texture(&device, palette));
+
+        #[cfg(feature = "pf-debug-ui")]
         let debug_ui_presenter = DebugUIPresenter::new(&device, resources, window_size);
 
         Renderer {
@@ -337,6 +475,8 @@ where
             dest_framebuffer,
             options,
             blit_program,
+            blit_spot_program,
+            coverage_reduce_program,
             fill_program,
             mask_winding_tile_program,
             mask_evenodd_tile_program,
@@ -349,7 +489,12 @@ where
             alpha_tile_difference_program,
             alpha_tile_exclusion_program,
             alpha_tile_hsl_program,
+            alpha_tile_porterduff_program,
+            alpha_tile_dual_source_program,
+            alpha_tile_advanced_program,
             blit_vertex_array,
+            blit_spot_vertex_array,
+            coverage_reduce_vertex_array,
             mask_winding_tile_vertex_array,
             mask_evenodd_tile_vertex_array,
             copy_tile_vertex_array,
@@ -360,7 +505,10 @@ where
             alpha_tile_difference_vertex_array,
             alpha_tile_exclusion_vertex_array,
             alpha_tile_hsl_vertex_array,
+            alpha_tile_porterduff_vertex_array,
+            custom_paint_shaders: vec![],
             area_lut_texture,
+            aliased_area_lut_texture,
             alpha_tile_vertex_buffer,
             quad_vertex_positions_buffer,
             quad_vertex_indices_buffer,
@@ -373,20 +521,31 @@ where
             intermediate_dest_framebuffer,
             texture_pages: vec![],
             render_targets: vec![],
+            external_textures: vec![],
             render_target_stack: vec![],
             clear_paint_texture,
+            spot_lut_texture,
 
             solid_tile_vertex_array,
+            custom_filters: vec![],
+            #[cfg(feature = "pf-filter-blur")]
             solid_tile_blur_filter_program,
+            #[cfg(feature = "pf-filter-blur")]
             solid_tile_blur_filter_vertex_array,
+            #[cfg(feature = "pf-filter-text")]
             solid_tile_text_filter_program,
+            #[cfg(feature = "pf-filter-text")]
             solid_tile_text_filter_vertex_array,
             solid_tile_vertex_buffer,
+            #[cfg(feature = "pf-filter-text")]
             gamma_lut_texture,
 
             stencil_program,
             stencil_vertex_array,
 
+            clip_stencil_program,
+            clip_stencil_vertex_array,
+
             reprojection_program,
             reprojection_vertex_array,
 
@@ -394,35 +553,97 @@ where
             current_timers: RenderTimers::new(),
             pending_timers: VecDeque::new(),
             free_timer_queries: vec![],
+            #[cfg(feature = "pf-debug-ui")]
             debug_ui_presenter,
+            #[cfg(feature = "pf-debug-ui")]
+            tile_overlay: TileOverlay::default(),
 
             framebuffer_flags: FramebufferFlags::empty(),
             buffered_fills: vec![],
-            texture_cache: TextureCache::new(),
+            texture_pool: TexturePool::new(),
+            bounding_quad: [Vector4F::default(); 4],
+            chunk_origin: Vector2I::default(),
+            current_layer: 0,
+            clip_stencil_value: 0,
 
             flags: RendererFlags::empty(),
+            device_lost: None,
         }
     }
 
     pub fn begin_scene(&mut self) {
+        if self.check_device().is_err() {
+            return;
+        }
+
         self.framebuffer_flags = FramebufferFlags::empty();
         self.device.begin_commands();
         self.stats = RenderStats::default();
+        #[cfg(feature = "pf-debug-ui")]
+        self.tile_overlay.clear();
+    }
+
+    /// Executes one render command.
+    ///
+    /// When rendering to `DestFramebuffer::ForeignTarget`, the device state is saved before the
+    /// command and restored after it, so the embedder may issue its own draw calls between
+    /// commands.
+    ///
+    /// A command that refers to texture pages or render targets that don't exist, or uses them
+    /// in a way they don't support, is skipped and reported as an error. Commands are ignored
+    /// once the device has been lost.
+    pub fn render_command(&mut self, command: &RenderCommand) -> Result<(), Error> {
+        trace_span!("render_command", command = command.name());
+        if self.device_lost.is_some() {
+            return Ok(());
+        }
+
+        let foreign = self.dest_framebuffer.is_foreign();
+        if foreign {
+            self.device.save_state();
+        }
+        let result = self.execute_command(command);
+        if foreign {
+            self.device.restore_state();
+        }
+        result
     }
 
-    pub fn render_command(&mut self, command: &RenderCommand) {
+    fn execute_command(&mut self, command: &RenderCommand) -> Result<(), Error> {
         match *command {
-            RenderCommand::Start { bounding_quad, path_count, needs_readable_framebuffer } => {
+            RenderCommand::Start {
+                bounding_quad,
+                path_count,
+                needs_readable_framebuffer,
+                chunk_origin,
+            } => {
                 self.start_rendering(bounding_quad, path_count, needs_readable_framebuffer);
+                self.chunk_origin = chunk_origin;
+            }
+            RenderCommand::BeginLayer { layer, bounding_quad } => {
+                self.begin_layer(layer, bounding_quad)?
             }
             RenderCommand::AllocateTexturePages(ref texture_page_descriptors) => {
                 self.allocate_texture_pages(texture_page_descriptors)
             }
-            RenderCommand::UploadTexelData { ref texels, location } => {
-                self.upload_texel_data(texels, location)
+            RenderCommand::UploadTexelData {
+                ref texels,
+                location,
+                id,
+                ref changes,
+                alpha_type,
+            } => {
+                self.upload_texel_data(texels, location, id, changes.as_ref())?;
+                self.validate_alpha_type(location.page, alpha_type, "UploadTexelData");
+            }
+            RenderCommand::UploadCompressedTexelData { ref data, location } => {
+                self.upload_compressed_texel_data(data, location)?
             }
             RenderCommand::DeclareRenderTarget { id, location } => {
-                self.declare_render_target(id, location)
+                self.declare_render_target(id, location)?;
+                self.validate_alpha_type(location.page,
+                                         AlphaType::Premultiplied,
+                                         "DeclareRenderTarget");
             }
             RenderCommand::AddFills(ref fills) => self.add_fills(fills),
             RenderCommand::FlushFills => {
@@ -431,61 +652,293 @@ where
             }
             RenderCommand::RenderMaskTiles { tiles: ref mask_tiles, fill_rule } => {
                 let count = mask_tiles.len();
+                #[cfg(feature = "pf-debug-ui")]
+                if self.tile_overlay_enabled() {
+                    self.tile_overlay.push_mask_batch(count);
+                }
                 self.upload_mask_tiles(mask_tiles, fill_rule);
                 self.draw_mask_tiles(count as u32, fill_rule);
             }
             RenderCommand::PushRenderTarget(render_target_id) => {
-                self.push_render_target(render_target_id)
+                self.push_render_target(render_target_id)?
             }
-            RenderCommand::PopRenderTarget => self.pop_render_target(),
+            RenderCommand::PopRenderTarget => self.pop_render_target()?,
             RenderCommand::DrawSolidTiles(ref batch) => {
+                self.check_texture_page(batch.color_texture_page)?;
+                self.validate_alpha_type(batch.color_texture_page,
+                                         batch.color_texture_alpha_type,
+                                         "DrawSolidTiles");
+                if let Filter::Custom { shader, .. } = batch.effects.filter {
+                    if shader.0 as usize >= self.custom_filters.len() {
+                        return Err(Error::NoSuchCustomFilter(shader));
+                    }
+                }
                 let count = batch.tiles.len();
                 self.stats.solid_tile_count += count;
+                #[cfg(feature = "pf-debug-ui")]
+                if self.tile_overlay_enabled() {
+                    let chunk_origin = self.chunk_origin;
+                    let tiles = batch.tiles.iter().map(|tile| {
+                        let tile_x = tile.upper_left.tile_x as i32;
+                        Vector2I::new(tile_x, tile.upper_left.tile_y as i32) + chunk_origin
+                    });
+                    self.tile_overlay.push_batch(tiles, false);
+                }
                 self.upload_solid_tiles(&batch.tiles);
                 self.draw_solid_tiles(count as u32,
                                       batch.color_texture_page,
                                       batch.sampling_flags,
                                       batch.effects);
             }
+            RenderCommand::DrawClipStencil(ref tiles) => {
+                self.upload_alpha_tiles(tiles);
+                self.draw_clip_stencil(tiles.len() as u32);
+            }
             RenderCommand::DrawAlphaTiles(ref batch) => {
+                // Clearing doesn't sample the paint texture.
+                if batch.blend_mode != BlendMode::Clear {
+                    self.check_texture_page(batch.color_texture_page)?;
+                    self.validate_alpha_type(batch.color_texture_page,
+                                             batch.color_texture_alpha_type,
+                                             "DrawAlphaTiles");
+                }
                 let count = batch.tiles.len();
                 self.stats.alpha_tile_count += count;
+                #[cfg(feature = "pf-debug-ui")]
+                if self.tile_overlay_enabled() {
+                    let chunk_origin = self.chunk_origin;
+                    let tiles = batch.tiles.iter().map(|tile| {
+                        let tile_x = tile.upper_left.tile_x as i32;
+                        Vector2I::new(tile_x, tile.upper_left.tile_y as i32) + chunk_origin
+                    });
+                    self.tile_overlay.push_batch(tiles, true);
+                }
+                if let Some(custom_paint) = batch.custom_paint {
+                    if custom_paint.shader.0 as usize >= self.custom_paint_shaders.len() {
+                        return Err(Error::NoSuchCustomPaintShader(custom_paint.shader));
+                    }
+                }
                 self.upload_alpha_tiles(&batch.tiles);
-                self.draw_alpha_tiles(count as u32,
-                                      batch.color_texture_page,
-                                      batch.sampling_flags,
-                                      batch.blend_mode)
+                self.draw_alpha_tiles(batch)
+            }
+            RenderCommand::Finish { culled_path_count, .. } => {
+                self.stats.culled_path_count = culled_path_count;
             }
-            RenderCommand::Finish { .. } => {}
         }
+        Ok(())
     }
 
     pub fn end_scene(&mut self) {
+        trace_span!("end_scene");
+        if self.device_lost.is_some() {
+            return;
+        }
+
+        let foreign = self.dest_framebuffer.is_foreign();
+        if foreign {
+            self.device.save_state();
+        }
         self.blit_intermediate_dest_framebuffer_if_necessary();
+        #[cfg(feature = "pf-debug-ui")]
+        if self.options.show_tile_overlay {
+            self.debug_ui_presenter.draw_tile_overlay(&self.device,
+                                                      &self.tile_overlay,
+                                                      self.main_viewport());
+        }
+        if foreign {
+            self.device.restore_state();
+        }
 
         self.end_composite_timer_query();
         self.pending_timers.push_back(mem::replace(&mut self.current_timers, RenderTimers::new()));
 
         self.device.end_commands();
+        self.texture_pool.end_frame();
+
+        #[cfg(feature = "pf-debug-ui")]
+        self.debug_ui_presenter.add_memory_sample(self.device.memory_usage());
+    }
+
+    // Only tiles drawn to the main framebuffer are recorded; render targets are drawn elsewhere.
+    #[cfg(feature = "pf-debug-ui")]
+    #[inline]
+    fn tile_overlay_enabled(&self) -> bool {
+        self.options.show_tile_overlay && self.render_target_stack.is_empty()
     }
 
     fn start_rendering(&mut self,
                        bounding_quad: BoundingQuad,
                        path_count: usize,
                        mut needs_readable_framebuffer: bool) {
-        if let DestFramebuffer::Other(_) = self.dest_framebuffer {
-            needs_readable_framebuffer = false;
+        match self.dest_framebuffer {
+            DestFramebuffer::Other(_) | DestFramebuffer::Layers(_) => {
+                needs_readable_framebuffer = false;
+            }
+            DestFramebuffer::Default { .. } | DestFramebuffer::ForeignTarget { .. } => {}
+        }
+        self.current_layer = 0;
+
+        // Spot color remapping happens during the final blit, so we always need to render to the
+        // intermediate framebuffer first.
+        if self.spot_lut_texture.is_some() {
+            needs_readable_framebuffer = true;
         }
 
         if self.flags.contains(RendererFlags::USE_DEPTH) {
             self.draw_stencil(&bounding_quad);
         }
+        self.bounding_quad = bounding_quad;
+        self.clip_stencil_value = 0;
         self.stats.path_count = path_count;
 
         self.flags.set(RendererFlags::INTERMEDIATE_DEST_FRAMEBUFFER_NEEDED,
                        needs_readable_framebuffer);
     }
 
+    fn begin_layer(&mut self, layer: u32, bounding_quad: BoundingQuad) -> Result<(), Error> {
+        let layer_count = match self.dest_framebuffer {
+            DestFramebuffer::Layers(ref framebuffers) => framebuffers.len(),
+            _ => 1,
+        };
+        if layer as usize >= layer_count {
+            return Err(Error::NoSuchLayer(layer));
+        }
+        self.current_layer = layer as usize;
+
+        // The layer has contents and a stencil buffer of its own.
+        self.framebuffer_flags.remove(FramebufferFlags::MUST_PRESERVE_DEST_FRAMEBUFFER_CONTENTS);
+        if self.flags.contains(RendererFlags::USE_DEPTH) {
+            self.draw_stencil(&bounding_quad);
+        }
+        self.bounding_quad = bounding_quad;
+        self.clip_stencil_value = 0;
+        Ok(())
+    }
+
+    /// Returns an error if the device has been lost.
+    ///
+    /// Once the device is lost, the renderer ignores all commands until
+    /// `recreate_after_device_loss()` is called. This is checked at the start of every scene, so
+    /// it's enough to call this after `end_scene()`.
+    pub fn check_device(&mut self) -> Result<(), DeviceLostError> {
+        if self.device_lost.is_none() {
+            self.device_lost = self.device.check_device().err();
+        }
+        match self.device_lost {
+            None => Ok(()),
+            Some(error) => Err(error),
+        }
+    }
+
+    /// Rebuilds all GPU resources with a new device, replacing one that has been lost.
+    ///
+    /// The renderer keeps its options and depth setting, and compiles its custom paint and
+    /// filter shaders again so that their IDs stay valid. Nothing else on the GPU survives, so
+    /// the destination framebuffer must be created anew with `device`, external textures must be
+    /// supplied again, and the scene must be rebuilt so that its paint textures are uploaded
+    /// again.
+    pub fn recreate_after_device_loss(&mut self,
+                                      device: D,
+                                      resources: &dyn ResourceLoader,
+                                      dest_framebuffer: DestFramebuffer<D>) {
+        let options = mem::take(&mut self.options);
+        let mut renderer = Renderer::new(device, resources, dest_framebuffer, options);
+        renderer.flags.set(RendererFlags::USE_DEPTH,
+                           self.flags.contains(RendererFlags::USE_DEPTH));
+        for custom_paint_shader in &self.custom_paint_shaders {
+            renderer.register_custom_paint_shader(resources,
+                                                  &custom_paint_shader.name,
+                                                  &custom_paint_shader.source);
+        }
+        for custom_filter in &self.custom_filters {
+            renderer.register_custom_filter(resources, &custom_filter.name, &custom_filter.source);
+        }
+
+        // The old objects belong to a context that no longer exists. Deleting them could delete
+        // objects in the new context that happen to have reused their names, so leak them.
+        mem::forget(mem::replace(self, renderer));
+    }
+
+    /// Compiles a fragment shader that computes the color of custom paints, and returns the ID
+    /// that `CustomPaint`s use to refer to it.
+    ///
+    /// The source is a complete fragment shader in the device's shading language, and is
+    /// preprocessed like Pathfinder's own shaders (so GLSL may begin with `#version {{version}}`).
+    /// It replaces the `tile_alpha` fragment shader and receives the same inputs:
+    ///
+    /// * `vColorTexCoord`: The fragment's position in the paint's coordinate space, as given by
+    ///   `CustomPaint::transform`.
+    ///
+    /// * `vMaskTexCoord` and `uStencilTexture`: The coverage mask; the red channel of
+    ///   `texture(uStencilTexture, vMaskTexCoord)` is the fraction of the pixel the path covers.
+    ///
+    /// * `vOpacity`: The opacity of the path.
+    ///
+    /// * `uParams`: A `vec4` holding `CustomPaint::params`.
+    ///
+    /// The shader writes a premultiplied color, multiplied by the coverage and opacity, to
+    /// `oFragColor`. Blend modes that read the framebuffer aren't supported, and custom paints
+    /// with them are drawn with the hardware blend state alone.
+    pub fn register_custom_paint_shader(&mut self,
+                                        resources: &dyn ResourceLoader,
+                                        name: &str,
+                                        fragment_shader_source: &[u8])
+                                        -> CustomPaintShaderId {
+        let program =
+            CustomPaintProgram::new(&self.device, resources, name, fragment_shader_source);
+        let vertex_array = AlphaTileVertexArray::new(&self.device,
+                                                     &program.alpha_tile_program,
+                                                     &self.alpha_tile_vertex_buffer,
+                                                     &self.quads_vertex_indices_buffer);
+        let id = CustomPaintShaderId(self.custom_paint_shaders.len() as u32);
+        self.custom_paint_shaders.push(CustomPaintShader {
+            name: name.to_owned(),
+            source: fragment_shader_source.to_vec(),
+            program,
+            vertex_array,
+        });
+        id
+    }
+
+    /// Compiles a fragment shader that `Filter::Custom` applies when compositing a layer, and
+    /// returns the ID that refers to it.
+    ///
+    /// The source is a complete fragment shader in the device's shading language, preprocessed
+    /// like Pathfinder's own shaders. It replaces the `tile_solid` fragment shader and receives
+    /// the same inputs, plus some more:
+    ///
+    /// * `uColorTexture` and `vColorTexCoord`: The layer's contents, which are premultiplied, and
+    ///   the coordinate of the fragment in it.
+    ///
+    /// * `uColorTextureSize`: A `vec2` holding the size of the texture in texels, for sampling
+    ///   neighbouring texels.
+    ///
+    /// * `uParams`: A `vec4` holding the filter's parameters.
+    ///
+    /// The shader writes a premultiplied color to `oFragColor`, which is composited onto the
+    /// destination with source-over.
+    pub fn register_custom_filter(&mut self,
+                                  resources: &dyn ResourceLoader,
+                                  name: &str,
+                                  fragment_shader_source: &[u8])
+                                  -> CustomFilterId {
+        let program =
+            CustomFilterProgram::new(&self.device, resources, name, fragment_shader_source);
+        let vertex_array = SolidTileVertexArray::new(&self.device,
+                                                     &program.solid_tile_program,
+                                                     &self.solid_tile_vertex_buffer,
+                                                     &self.quads_vertex_indices_buffer);
+        let id = CustomFilterId(self.custom_filters.len() as u32);
+        self.custom_filters.push(CustomFilter {
+            name: name.to_owned(),
+            source: fragment_shader_source.to_vec(),
+            program,
+            vertex_array,
+        });
+        id
+    }
+
+    #[cfg(feature = "pf-debug-ui")]
     pub fn draw_debug_ui(&self) {
         self.debug_ui_presenter.draw(&self.device);
     }
@@ -519,6 +972,147 @@ where
         Some(RenderTime { stage_0: total_stage_0_time, stage_1: stage_1_time })
     }
 
+    /// Starts copying the pixels in `rect` of the destination framebuffer back to the CPU, without
+    /// waiting for the GPU to finish drawing them.
+    ///
+    /// `rect` is relative to the main viewport, in the device's framebuffer coordinates, so its
+    /// origin is the lower left corner on OpenGL. The copy includes everything rendered so far, so
+    /// call this after `end_scene()` to capture the scene. The device copies the pixels into a
+    /// buffer as the GPU gets to them; check on the copy with `poll_readback()` on later frames
+    /// instead of waiting for it, so that screenshots and thumbnails don't stall the pipeline.
+    pub fn begin_readback(&mut self, rect: RectI) -> Readback<D> {
+        let main_viewport = self.main_viewport();
+        let rect = RectI::new(rect.origin() + main_viewport.origin(), rect.size());
+        let rect = rect.intersection(main_viewport).unwrap_or_default();
+        let target = match self.dest_framebuffer {
+            DestFramebuffer::Default { .. } |
+            DestFramebuffer::ForeignTarget { .. } => RenderTarget::Default,
+            DestFramebuffer::Other(ref framebuffer) => RenderTarget::Framebuffer(framebuffer),
+            DestFramebuffer::Layers(ref framebuffers) => {
+                RenderTarget::Framebuffer(&framebuffers[self.current_layer])
+            }
+        };
+        let receiver = self.device.read_pixels(&target, rect);
+        Readback { receiver, size: rect.size() }
+    }
+
+    /// Returns the pixels of a readback, top row first, if the GPU has finished copying them.
+    /// Never blocks.
+    #[inline]
+    pub fn poll_readback(&self, readback: &Readback<D>) -> Option<TextureData> {
+        self.device.try_recv_texture_data(&readback.receiver)
+    }
+
+    /// Waits for the GPU to finish copying the pixels of a readback, and returns them, top row
+    /// first.
+    ///
+    /// This stalls until everything drawn before the readback began has been rendered, like a
+    /// synchronous readback would.
+    #[inline]
+    pub fn finish_readback(&self, readback: Readback<D>) -> TextureData {
+        self.device.recv_texture_data(&readback.receiver)
+    }
+
+    /// Starts measuring how much of `rect` of the destination framebuffer is covered, without
+    /// waiting for the GPU to finish drawing it.
+    ///
+    /// `rect` is relative to the main viewport, as for `begin_readback()`. Coverage is the alpha of
+    /// the framebuffer, so call this after `end_scene()`, before anything else is drawn, and clear
+    /// to a transparent background. If the destination framebuffer is a texture, it's reduced on
+    /// the GPU to a single texel, so only that is read back; the counts are then approximate, to
+    /// about one part in a thousand. Otherwise, the pixels in `rect` are read back and counted.
+    /// Either way, check on the result with `poll_coverage_query()` on later frames.
+    pub fn begin_coverage_query(&mut self, rect: RectI) -> CoverageQuery<D> {
+        let main_viewport = self.main_viewport();
+        let rect = RectI::new(rect.origin() + main_viewport.origin(), rect.size());
+        let rect = rect.intersection(main_viewport).unwrap_or_default();
+        let pixel_count = rect.width() as u32 * rect.height() as u32;
+
+        let src_framebuffer = match self.dest_framebuffer {
+            DestFramebuffer::Other(ref framebuffer) => framebuffer,
+            DestFramebuffer::Layers(ref framebuffers) => &framebuffers[self.current_layer],
+            DestFramebuffer::Default { .. } | DestFramebuffer::ForeignTarget { .. } => {
+                let receiver = self.device.read_pixels(&RenderTarget::Default, rect);
+                return CoverageQuery { receiver, pixel_count, reduction: None };
+            }
+        };
+        if pixel_count == 0 {
+            let receiver = self.device.read_pixels(&RenderTarget::Framebuffer(src_framebuffer),
+                                                   rect);
+            return CoverageQuery { receiver, pixel_count, reduction: None };
+        }
+
+        // Each step averages blocks of 4x4 texels, until one is left.
+        let (mut src_rect, mut scale) = (rect, 1.0);
+        let mut prev_framebuffer: Option<D::Framebuffer> = None;
+        loop {
+            let dest_size = Vector2I::new((src_rect.width() + 3) / 4, (src_rect.height() + 3) / 4);
+            let dest_framebuffer = self.texture_pool.create_framebuffer(&mut self.device,
+                                                                        TextureFormat::RGBA16F,
+                                                                        dest_size);
+            let src_texture = match prev_framebuffer {
+                Some(ref prev_framebuffer) => self.device.framebuffer_texture(prev_framebuffer),
+                None => self.device.framebuffer_texture(src_framebuffer),
+            };
+            let program = &self.coverage_reduce_program;
+            let src_rect_data = F32x4::new(src_rect.min_x() as f32,
+                                           src_rect.min_y() as f32,
+                                           src_rect.max_x() as f32,
+                                           src_rect.max_y() as f32);
+            let src_is_stats = if prev_framebuffer.is_some() { 1 } else { 0 };
+            self.device.draw_elements(6, &RenderState {
+                target: &RenderTarget::Framebuffer(&dest_framebuffer),
+                program: &program.blit_program.program,
+                vertex_array: &self.coverage_reduce_vertex_array.vertex_array,
+                primitive: Primitive::Triangles,
+                textures: &[src_texture],
+                uniforms: &[
+                    (&program.blit_program.src_uniform, UniformData::TextureUnit(0)),
+                    (&program.src_rect_uniform, UniformData::Vec4(src_rect_data)),
+                    (&program.src_is_stats_uniform, UniformData::Int(src_is_stats)),
+                ],
+                viewport: RectI::new(Vector2I::default(), dest_size),
+                options: RenderOptions::default(),
+            });
+            scale *= 16.0;
+
+            if let Some(prev_framebuffer) = prev_framebuffer.take() {
+                self.texture_pool.release_framebuffer(&self.device, prev_framebuffer);
+            }
+            if dest_size == Vector2I::splat(1) {
+                let target = RenderTarget::Framebuffer(&dest_framebuffer);
+                let receiver = self.device.read_pixels(&target,
+                                                       RectI::new(Vector2I::default(), dest_size));
+                self.texture_pool.release_framebuffer(&self.device, dest_framebuffer);
+                return CoverageQuery { receiver, pixel_count, reduction: Some(scale) };
+            }
+            src_rect = RectI::new(Vector2I::default(), dest_size);
+            prev_framebuffer = Some(dest_framebuffer);
+        }
+    }
+
+    /// Returns the result of a coverage query, if the GPU has finished it. Never blocks.
+    #[inline]
+    pub fn poll_coverage_query(&self, query: &CoverageQuery<D>) -> Option<Coverage> {
+        self.device.try_recv_texture_data(&query.receiver).map(|data| query.coverage(data))
+    }
+
+    /// Waits for the GPU to finish a coverage query, and returns its result.
+    #[inline]
+    pub fn finish_coverage_query(&self, query: CoverageQuery<D>) -> Coverage {
+        let data = self.device.recv_texture_data(&query.receiver);
+        query.coverage(data)
+    }
+
+    /// Returns the GPU memory the device has allocated, including texture pages, the fill and
+    /// mask framebuffers, and vertex buffers.
+    ///
+    /// Totals that keep growing from frame to frame indicate a leak.
+    #[inline]
+    pub fn gpu_memory_usage(&self) -> GPUMemoryUsage {
+        self.device.memory_usage()
+    }
+
     #[inline]
     pub fn dest_framebuffer(&self) -> &DestFramebuffer<D> {
         &self.dest_framebuffer
@@ -532,11 +1126,35 @@ where
         mem::replace(&mut self.dest_framebuffer, new_dest_framebuffer)
     }
 
+    /// Supplies the texture that patterns with the given external texture ID sample from, and
+    /// returns the texture previously supplied, if any.
+    ///
+    /// The texture is sampled as is, so it can be updated between frames (for example, with each
+    /// video frame) without rebuilding the scene. Patterns whose ID has no texture draw nothing.
+    pub fn set_external_texture(&mut self, id: ExternalTextureId, texture: Option<D::Texture>)
+                                -> Option<D::Texture> {
+        let index = id.0 as usize;
+        while self.external_textures.len() < index + 1 {
+            self.external_textures.push(None);
+        }
+        mem::replace(&mut self.external_textures[index], texture)
+    }
+
     #[inline]
     pub fn set_options(&mut self, new_options: RendererOptions) {
+        if new_options.spot_colors != self.options.spot_colors {
+            self.spot_lut_texture = new_options.spot_colors.as_ref().map(|palette| {
+                create_spot_lut_texture(&self.device, palette)
+            });
+        }
+        if new_options.antialiasing_quality != self.options.antialiasing_quality {
+            let sampling_flags = area_lut_sampling_flags(new_options.antialiasing_quality);
+            self.device.set_texture_sampling_mode(&self.area_lut_texture, sampling_flags);
+        }
         self.options = new_options
     }
 
+    #[cfg(feature = "pf-debug-ui")]
     #[inline]
     pub fn set_main_framebuffer_size(&mut self, new_framebuffer_size: Vector2I) {
         self.debug_ui_presenter.ui_presenter.set_framebuffer_size(new_framebuffer_size);
@@ -563,37 +1181,176 @@ where
     }
 
     fn allocate_texture_pages(&mut self, texture_page_descriptors: &[TexturePageDescriptor]) {
-        // Clear out old paint textures.
-        for old_texture_page in self.texture_pages.drain(..) {
-            let old_texture = self.device.destroy_framebuffer(old_texture_page.framebuffer);
-            self.texture_cache.release_texture(old_texture);
+        // Keep the pages that are the same as the new ones, along with the texels uploaded to
+        // them, so that unchanged texels needn't be uploaded again. Return the other old paint
+        // textures and render targets to the pool.
+        let old_texture_pages: Vec<_> = self.texture_pages.drain(..).collect();
+        let mut kept_texture_pages = vec![];
+        for (page_index, old_texture_page) in old_texture_pages.into_iter().enumerate() {
+            if let Some(descriptor) = texture_page_descriptors.get(page_index) {
+                if self.texture_page_matches(&old_texture_page, descriptor) {
+                    kept_texture_pages.push(Some(old_texture_page));
+                    continue;
+                }
+            }
+            kept_texture_pages.push(None);
+            match old_texture_page.storage {
+                TexturePageStorage::Framebuffer(framebuffer) => {
+                    self.texture_pool.release_framebuffer(&self.device, framebuffer)
+                }
+                TexturePageStorage::Texture(texture) => {
+                    self.texture_pool.release_texture(&self.device, texture)
+                }
+                TexturePageStorage::External(_) => {}
+            }
         }
+        kept_texture_pages.resize_with(texture_page_descriptors.len(), || None);
 
         // Clear out old render targets.
         self.render_targets.clear();
 
         // Allocate textures.
-        for texture_page_descriptor in texture_page_descriptors {
+        for (texture_page_descriptor, kept_texture_page) in
+                texture_page_descriptors.iter().zip(kept_texture_pages) {
+            if let Some(mut texture_page) = kept_texture_page {
+                // The page is cleared if it's rendered to before anything is uploaded to it.
+                texture_page.must_preserve_contents = false;
+                texture_page.alpha_type = texture_page_descriptor.alpha_type;
+                self.texture_pages.push(texture_page);
+                continue;
+            }
+
             let texture_size = texture_page_descriptor.size;
-            let texture = self.texture_cache.create_texture(&mut self.device,
-                                                            TextureFormat::RGBA8,
-                                                            texture_size);
-            let framebuffer = self.device.create_framebuffer(texture);
-            self.texture_pages.push(TexturePage { framebuffer, must_preserve_contents: false });
+            let mut texture_format = texture_page_descriptor.format;
+
+            if let Some(external_texture_id) = texture_page_descriptor.external_texture {
+                match self.external_textures.get(external_texture_id.0 as usize) {
+                    Some(&Some(_)) => {
+                        self.texture_pages.push(TexturePage {
+                            storage: TexturePageStorage::External(external_texture_id),
+                            must_preserve_contents: true,
+                            alpha_type: texture_page_descriptor.alpha_type,
+                            texels_id: None,
+                        });
+                        continue;
+                    }
+                    _ => {
+                        warn!("No texture was supplied for {:?}; not drawing it.",
+                              external_texture_id)
+                    }
+                }
+            }
+
+            // Compressed pages are only ever sampled from, so they don't get framebuffers. If the
+            // device can't handle the format, fall back to an empty page so that the paint draws
+            // as transparent.
+            if texture_format.is_compressed() {
+                if self.device.supports_texture_format(texture_format) {
+                    let texture = self.texture_pool.create_texture(&mut self.device,
+                                                                   texture_format,
+                                                                   texture_size);
+                    self.texture_pages.push(TexturePage {
+                        storage: TexturePageStorage::Texture(texture),
+                        must_preserve_contents: true,
+                        alpha_type: texture_page_descriptor.alpha_type,
+                        texels_id: None,
+                    });
+                    continue;
+                }
+                warn!("Compressed texture format {:?} is unsupported; not drawing the image.",
+                      texture_format);
+                texture_format = TextureFormat::RGBA8;
+            }
+
+            let framebuffer = self.texture_pool.create_framebuffer(&mut self.device,
+                                                                   texture_format,
+                                                                   texture_size);
+            self.texture_pages.push(TexturePage {
+                storage: TexturePageStorage::Framebuffer(framebuffer),
+                must_preserve_contents: false,
+                alpha_type: texture_page_descriptor.alpha_type,
+                texels_id: None,
+            });
         }
     }
 
-    fn upload_texel_data(&mut self, texels: &[ColorU], location: TextureLocation) {
+    // Only plain RGBA8 pages are kept, since they're the only ones texels are uploaded to in
+    // parts.
+    fn texture_page_matches(&self,
+                            texture_page: &TexturePage<D>,
+                            descriptor: &TexturePageDescriptor)
+                            -> bool {
+        if descriptor.external_texture.is_some() || descriptor.format != TextureFormat::RGBA8 {
+            return false;
+        }
+        match texture_page.storage {
+            TexturePageStorage::Framebuffer(ref framebuffer) => {
+                let texture = self.device.framebuffer_texture(framebuffer);
+                self.device.texture_size(texture) == descriptor.size &&
+                    self.device.texture_format(texture) == TextureFormat::RGBA8
+            }
+            TexturePageStorage::Texture(_) | TexturePageStorage::External(_) => false,
+        }
+    }
+
+    fn upload_texel_data(&mut self,
+                         texels: &[ColorU],
+                         location: TextureLocation,
+                         id: TexelsId,
+                         changes: Option<&TexelChanges>)
+                         -> Result<(), Error> {
+        self.check_framebuffer_texture_page(location.page)?;
         let texture_page = &mut self.texture_pages[location.page.0 as usize];
-        let texture = self.device.framebuffer_texture(&texture_page.framebuffer);
-        let texels = color::color_slice_to_u8_slice(texels);
-        self.device.upload_to_texture(texture, location.rect, TextureDataRef::U8(texels));
+        let texture = match texture_page.storage {
+            TexturePageStorage::Framebuffer(ref framebuffer) => {
+                self.device.framebuffer_texture(framebuffer)
+            }
+            TexturePageStorage::Texture(_) | TexturePageStorage::External(_) => unreachable!(),
+        };
+        match changes {
+            Some(changes) if texture_page.texels_id == Some(changes.since) => {
+                let width = location.rect.width();
+                for rows in &changes.rows {
+                    let rect = RectI::new(location.rect.origin() +
+                                          Vector2I::new(0, rows.start as i32),
+                                          Vector2I::new(width, (rows.end - rows.start) as i32));
+                    let texels = &texels[(rows.start as usize * width as usize)..
+                                         (rows.end as usize * width as usize)];
+                    let texels = color::color_slice_to_u8_slice(texels);
+                    self.device.upload_to_texture(texture, rect, TextureDataRef::U8(texels));
+                }
+            }
+            _ => {
+                let texels = color::color_slice_to_u8_slice(texels);
+                self.device.upload_to_texture(texture, location.rect, TextureDataRef::U8(texels));
+            }
+        }
+        texture_page.texels_id = Some(id);
         texture_page.must_preserve_contents = true;
+        Ok(())
+    }
+
+    fn upload_compressed_texel_data(&mut self, data: &[u8], location: TextureLocation)
+                                    -> Result<(), Error> {
+        match self.check_texture_page(location.page)?.storage {
+            TexturePageStorage::Texture(ref texture) => {
+                self.device.upload_to_texture(texture, location.rect, TextureDataRef::U8(data));
+            }
+            TexturePageStorage::Framebuffer(_) => {
+                // The format was unsupported, and we already warned about it.
+            }
+            TexturePageStorage::External(_) => {
+                return Err(Error::IncompatibleTexturePage(location.page))
+            }
+        }
+        Ok(())
     }
 
     fn declare_render_target(&mut self,
                              render_target_id: RenderTargetId,
-                             location: TextureLocation) {
+                             location: TextureLocation)
+                             -> Result<(), Error> {
+        self.check_framebuffer_texture_page(location.page)?;
         while self.render_targets.len() < render_target_id.0 as usize + 1 {
             self.render_targets.push(RenderTargetInfo {
                 location: TextureLocation { page: TexturePageId(!0), rect: RectI::default() },
@@ -602,6 +1359,7 @@ where
         let mut render_target = &mut self.render_targets[render_target_id.0 as usize];
         debug_assert_eq!(render_target.location.page, TexturePageId(!0));
         render_target.location = location;
+        Ok(())
     }
 
     fn upload_mask_tiles(&mut self, mask_tiles: &[MaskTile], fill_rule: FillRule) {
@@ -700,13 +1458,18 @@ where
         let timer_query = self.allocate_timer_query();
         self.device.begin_timer_query(&timer_query);
 
+        let area_lut_texture = match self.options.antialiasing_quality {
+            AntialiasingQuality::None => &self.aliased_area_lut_texture,
+            AntialiasingQuality::Fast | AntialiasingQuality::Full => &self.area_lut_texture,
+        };
+
         debug_assert!(self.buffered_fills.len() <= u32::MAX as usize);
         self.device.draw_elements_instanced(6, self.buffered_fills.len() as u32, &RenderState {
             target: &RenderTarget::Framebuffer(&self.fill_framebuffer),
             program: &self.fill_program.program,
             vertex_array: &self.fill_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
-            textures: &[&self.area_lut_texture],
+            textures: &[area_lut_texture],
             uniforms: &[
                 (&self.fill_program.framebuffer_size_uniform,
                  UniformData::Vec2(F32x2::new(MASK_FRAMEBUFFER_WIDTH as f32,
@@ -739,7 +1502,34 @@ where
     fn tile_transform(&self) -> Transform4F {
         let draw_viewport = self.draw_viewport().size().to_f32();
         let scale = Vector4F::new(2.0 / draw_viewport.x(), -2.0 / draw_viewport.y(), 1.0, 1.0);
-        Transform4F::from_scale(scale).translate(Vector4F::new(-1.0, 1.0, 0.0, 1.0))
+        let z = match self.dest_depth_options() {
+            Some(depth_options) => depth_options.z,
+            None => 0.0,
+        };
+        // Tile positions are relative to the chunk, so move them back to its corner first.
+        let tile_size = Vector2I::new(TILE_WIDTH as i32, TILE_HEIGHT as i32);
+        let chunk_origin = self.chunk_origin.scale_xy(tile_size).to_f32();
+        let chunk_translation = Vector4F::new(chunk_origin.x(), chunk_origin.y(), 0.0, 1.0);
+        Transform4F::from_scale(scale).translate(Vector4F::new(-1.0, 1.0, z, 1.0)) *
+            Transform4F::from_translation(chunk_translation)
+    }
+
+    // The depth options apply only while tiles are drawn directly onto the destination.
+    fn dest_depth_options(&self) -> Option<DepthOptions> {
+        if !self.render_target_stack.is_empty() ||
+                self.flags.contains(RendererFlags::INTERMEDIATE_DEST_FRAMEBUFFER_NEEDED) {
+            return None;
+        }
+        self.options.depth
+    }
+
+    fn composite_depth_state(&self) -> Option<DepthState> {
+        let depth_options = self.dest_depth_options()?;
+        match (depth_options.test, depth_options.write) {
+            (false, false) => None,
+            (true, write) => Some(DepthState { func: DepthFunc::Less, write }),
+            (false, true) => Some(DepthState { func: DepthFunc::Always, write: true }),
+        }
     }
 
     fn draw_mask_tiles(&mut self, tile_count: u32, fill_rule: FillRule) {
@@ -788,20 +1578,48 @@ where
         self.framebuffer_flags.insert(FramebufferFlags::MUST_PRESERVE_MASK_FRAMEBUFFER_CONTENTS);
     }
 
-    fn draw_alpha_tiles(&mut self,
-                        tile_count: u32,
-                        color_texture_page: TexturePageId,
-                        sampling_flags: TextureSamplingFlags,
-                        blend_mode: BlendMode) {
-        let blend_mode_program = BlendModeProgram::from_blend_mode(blend_mode);
-        if blend_mode_program.needs_readable_framebuffer() {
+    // Draws the alpha tiles of `batch`, which must already be uploaded.
+    fn draw_alpha_tiles(&mut self, batch: &AlphaTileBatch) {
+        let tile_count = batch.tiles.len() as u32;
+        let (color_texture_page, custom_paint) = (batch.color_texture_page, batch.custom_paint);
+        let (sampling_flags, blend_mode) = (batch.sampling_flags, batch.blend_mode);
+        let (scissor, stencil_clipped) = (batch.scissor, batch.stencil_clipped);
+        // Custom paint shaders can't read the destination, so they only get hardware blending.
+        let blend_mode_program = match custom_paint {
+            Some(_) => BlendModeProgram::Regular,
+            None => BlendModeProgram::from_blend_mode(blend_mode),
+        };
+        let dual_source_blending = self.alpha_tile_dual_source_program.is_some();
+        let advanced_blending = self.alpha_tile_advanced_program.is_some();
+        if blend_mode_program.needs_readable_framebuffer(dual_source_blending, advanced_blending) {
             self.copy_alpha_tiles_to_dest_blend_texture(tile_count);
         }
 
+        // Advanced blend equations do in hardware what the blend mode programs do by reading
+        // the destination.
+        let advanced_blend_op = match blend_mode_program {
+            BlendModeProgram::Regular | BlendModeProgram::PorterDuff => None,
+            _ if advanced_blending => Some(advanced_blend_op(blend_mode)),
+            _ => None,
+        };
+
         let clear_color = self.clear_color_for_draw_operation();
 
         let (alpha_tile_program, alpha_tile_vertex_array) = match blend_mode_program {
-            BlendModeProgram::Regular => (&self.alpha_tile_program, &self.alpha_tile_vertex_array),
+            _ if advanced_blend_op.is_some() => {
+                let (ref program, ref vertex_array) =
+                    *self.alpha_tile_advanced_program.as_ref().unwrap();
+                (program, vertex_array)
+            }
+            BlendModeProgram::Regular => {
+                match custom_paint {
+                    Some(custom_paint) => {
+                        let shader = &self.custom_paint_shaders[custom_paint.shader.0 as usize];
+                        (&shader.program.alpha_tile_program, &shader.vertex_array)
+                    }
+                    None => (&self.alpha_tile_program, &self.alpha_tile_vertex_array),
+                }
+            }
             BlendModeProgram::Overlay => {
                 (&self.alpha_tile_overlay_program.alpha_tile_blend_mode_program.alpha_tile_program,
                  &self.alpha_tile_overlay_vertex_array)
@@ -828,6 +1646,19 @@ where
                 (&self.alpha_tile_hsl_program.alpha_tile_blend_mode_program.alpha_tile_program,
                  &self.alpha_tile_hsl_vertex_array)
             }
+            BlendModeProgram::PorterDuff => {
+                match self.alpha_tile_dual_source_program {
+                    Some((ref program, ref vertex_array)) => {
+                        (&program.alpha_tile_program, vertex_array)
+                    }
+                    None => {
+                        (&self.alpha_tile_porterduff_program
+                              .alpha_tile_blend_mode_program
+                              .alpha_tile_program,
+                         &self.alpha_tile_porterduff_vertex_array)
+                    }
+                }
+            }
         };
 
         let draw_viewport = self.draw_viewport();
@@ -843,21 +1674,34 @@ where
              UniformData::Vec2(draw_viewport.size().to_f32().0)),
         ];
 
-        let paint_texture = match blend_mode {
+        let (paint_texture, paint_alpha_type) = match blend_mode {
             BlendMode::Clear => {
                 // Use a special dummy paint texture containing `rgba(0, 0, 0, 255)` so that the
                 // transparent black paint color doesn't zero out the mask.
-                &self.clear_paint_texture
+                (&self.clear_paint_texture, AlphaType::Straight)
+            }
+            _ => {
+                (self.texture_page(color_texture_page),
+                 self.texture_pages[color_texture_page.0 as usize].alpha_type)
             }
-            _ => self.texture_page(color_texture_page),
         };
 
         self.device.set_texture_sampling_mode(paint_texture, sampling_flags);
 
         textures.push(paint_texture);
         uniforms.push((&alpha_tile_program.paint_texture_uniform, UniformData::TextureUnit(1)));
+        uniforms.push((&alpha_tile_program.paint_texture_premultiplied_uniform,
+                       UniformData::Int((paint_alpha_type == AlphaType::Premultiplied) as i32)));
+
+        if let Some(custom_paint) = custom_paint {
+            let shader = &self.custom_paint_shaders[custom_paint.shader.0 as usize];
+            let params = custom_paint.params;
+            let params = F32x4::new(params[0], params[1], params[2], params[3]);
+            uniforms.push((&shader.program.params_uniform, UniformData::Vec4(params)));
+        }
 
         match blend_mode_program {
+            _ if advanced_blend_op.is_some() => {}
             BlendModeProgram::Regular => {}
             BlendModeProgram::Overlay => {
                 self.set_uniforms_for_overlay_blend_mode(&mut textures, &mut uniforms, blend_mode);
@@ -885,8 +1729,21 @@ where
             BlendModeProgram::HSL => {
                 self.set_uniforms_for_hsl_blend_mode(&mut textures, &mut uniforms, blend_mode);
             }
+            BlendModeProgram::PorterDuff => {
+                self.set_uniforms_for_porter_duff_blend_mode(&mut textures,
+                                                             &mut uniforms,
+                                                             blend_mode);
+            }
         }
 
+        let blend = match (blend_mode_program, advanced_blend_op) {
+            (_, Some(op)) => Some(BlendState { op, ..BlendState::default() }),
+            (BlendModeProgram::PorterDuff, None) if dual_source_blending => {
+                Some(dual_source_blend_state(blend_mode))
+            }
+            _ => blend_mode.to_blend_state(),
+        };
+
         self.device.draw_elements(tile_count * 6, &RenderState {
             target: &self.draw_render_target(),
             program: &alpha_tile_program.program,
@@ -896,9 +1753,11 @@ where
             uniforms: &uniforms,
             viewport: draw_viewport,
             options: RenderOptions {
-                blend: blend_mode.to_blend_state(),
-                stencil: self.stencil_state(),
+                blend,
+                depth: self.composite_depth_state(),
+                stencil: self.stencil_state(stencil_clipped),
                 clear_ops: ClearOps { color: clear_color, ..ClearOps::default() },
+                scissor,
                 ..RenderOptions::default()
             },
         });
@@ -972,6 +1831,35 @@ where
                                               .alpha_tile_blend_mode_program);
     }
 
+    fn set_uniforms_for_porter_duff_blend_mode<'a>(
+            &'a self,
+            textures: &mut Vec<&'a D::Texture>,
+            uniforms: &mut Vec<(&'a D::Uniform, UniformData)>,
+            blend_mode: BlendMode) {
+        let porter_duff_op = match blend_mode.porter_duff_op() {
+            Some(CompositeOp::Copy)     => PORTER_DUFF_OP_COPY,
+            Some(CompositeOp::SrcIn)    => PORTER_DUFF_OP_SRC_IN,
+            Some(CompositeOp::DestIn)   => PORTER_DUFF_OP_DEST_IN,
+            Some(CompositeOp::SrcOut)   => PORTER_DUFF_OP_SRC_OUT,
+            Some(CompositeOp::DestAtop) => PORTER_DUFF_OP_DEST_ATOP,
+            _                           => unreachable!(),
+        };
+
+        match self.alpha_tile_dual_source_program {
+            Some((ref program, _)) => {
+                uniforms.push((&program.porter_duff_op_uniform, UniformData::Int(porter_duff_op)));
+            }
+            None => {
+                uniforms.push((&self.alpha_tile_porterduff_program.porter_duff_op_uniform,
+                               UniformData::Int(porter_duff_op)));
+                self.set_uniforms_for_blend_mode(textures,
+                                                 uniforms,
+                                                 &self.alpha_tile_porterduff_program
+                                                      .alpha_tile_blend_mode_program);
+            }
+        }
+    }
+
     fn copy_alpha_tiles_to_dest_blend_texture(&mut self, tile_count: u32) {
         let draw_viewport = self.draw_viewport();
 
@@ -1020,17 +1908,22 @@ where
         let clear_color = self.clear_color_for_draw_operation();
 
         let (solid_tile_program, solid_tile_vertex_array) = match effects.filter {
-            Filter::Composite(_) => {
-                (&self.solid_tile_program, &self.solid_tile_vertex_array)
-            }
+            #[cfg(feature = "pf-filter-text")]
             Filter::Text { .. } => {
                 (&self.solid_tile_text_filter_program.solid_tile_program,
                  &self.solid_tile_text_filter_vertex_array)
             }
+            #[cfg(feature = "pf-filter-blur")]
             Filter::Blur { .. } => {
                 (&self.solid_tile_blur_filter_program.solid_tile_program,
                  &self.solid_tile_blur_filter_vertex_array)
             }
+            Filter::Custom { shader, .. } => {
+                let custom_filter = &self.custom_filters[shader.0 as usize];
+                (&custom_filter.program.solid_tile_program, &custom_filter.vertex_array)
+            }
+            // Layers whose filter was compiled out are drawn unfiltered.
+            _ => (&self.solid_tile_program, &self.solid_tile_vertex_array),
         };
 
         let mut textures = vec![];
@@ -1046,14 +1939,19 @@ where
         self.device.set_texture_sampling_mode(texture_page, sampling_flags);
         textures.push(texture_page);
         uniforms.push((&solid_tile_program.color_texture_uniform, UniformData::TextureUnit(0)));
+        let alpha_type = self.texture_pages[color_texture_page.0 as usize].alpha_type;
+        uniforms.push((&solid_tile_program.color_texture_premultiplied_uniform,
+                       UniformData::Int((alpha_type == AlphaType::Premultiplied) as i32)));
 
         let blend_state = match effects.filter {
             Filter::Composite(composite_op) => composite_op.to_blend_state(),
-            Filter::Blur { .. } | Filter::Text { .. } => CompositeOp::SrcOver.to_blend_state(),
+            Filter::Blur { .. } | Filter::Text { .. } | Filter::Custom { .. } => {
+                CompositeOp::SrcOver.to_blend_state()
+            }
         };
 
         match effects.filter {
-            Filter::Composite(_) => {}
+            #[cfg(feature = "pf-filter-text")]
             Filter::Text { fg_color, bg_color, defringing_kernel, gamma_correction } => {
                 self.set_uniforms_for_text_filter(&mut textures,
                                                   &mut uniforms,
@@ -1062,9 +1960,20 @@ where
                                                   defringing_kernel,
                                                   gamma_correction);
             }
+            #[cfg(feature = "pf-filter-blur")]
             Filter::Blur { direction, sigma } => {
                 self.set_uniforms_for_blur_filter(&mut uniforms, texture_size, direction, sigma);
             }
+            Filter::Custom { shader, params } => {
+                let program = &self.custom_filters[shader.0 as usize].program;
+                let params = F32x4::new(params[0], params[1], params[2], params[3]);
+                uniforms.extend_from_slice(&[
+                    (&program.color_texture_size_uniform,
+                     UniformData::Vec2(texture_size.to_f32().0)),
+                    (&program.params_uniform, UniformData::Vec4(params)),
+                ]);
+            }
+            _ => {}
         }
 
         self.device.draw_elements(6 * tile_count, &RenderState {
@@ -1077,7 +1986,8 @@ where
             viewport: self.draw_viewport(),
             options: RenderOptions {
                 blend: blend_state,
-                stencil: self.stencil_state(),
+                depth: self.composite_depth_state(),
+                stencil: self.stencil_state(false),
                 clear_ops: ClearOps { color: clear_color, ..ClearOps::default() },
                 ..RenderOptions::default()
             },
@@ -1086,6 +1996,60 @@ where
         self.preserve_draw_framebuffer();
     }
 
+    // Writes a new clip to the stencil buffer. The tiles must already be uploaded.
+    fn draw_clip_stencil(&mut self, tile_count: u32) {
+        // The stencil buffer needs clearing the first time it's used in a frame, unless the 3D
+        // stencil has just cleared it, and whenever the clip numbers run out.
+        let use_depth = self.flags.contains(RendererFlags::USE_DEPTH);
+        let mut clear_ops = ClearOps {
+            color: self.clear_color_for_draw_operation(),
+            ..ClearOps::default()
+        };
+        if self.clip_stencil_value == CLIP_STENCIL_MAX_VALUE ||
+                (self.clip_stencil_value == 0 && !use_depth) {
+            if use_depth {
+                let bounding_quad = self.bounding_quad;
+                self.draw_stencil(&bounding_quad);
+            } else {
+                clear_ops.stencil = Some(0);
+            }
+            self.clip_stencil_value = 0;
+        }
+        self.clip_stencil_value += 1;
+
+        let textures = [self.device.framebuffer_texture(&self.mask_framebuffer)];
+        let uniforms = [
+            (&self.clip_stencil_program.transform_uniform,
+             UniformData::Mat4(self.tile_transform().to_columns())),
+            (&self.clip_stencil_program.tile_size_uniform,
+             UniformData::Vec2(F32x2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32))),
+            (&self.clip_stencil_program.stencil_texture_uniform, UniformData::TextureUnit(0)),
+        ];
+
+        self.device.draw_elements(tile_count * 6, &RenderState {
+            target: &self.draw_render_target(),
+            program: &self.clip_stencil_program.program,
+            vertex_array: &self.clip_stencil_vertex_array.vertex_array,
+            primitive: Primitive::Triangles,
+            textures: &textures,
+            uniforms: &uniforms,
+            viewport: self.draw_viewport(),
+            options: RenderOptions {
+                stencil: Some(StencilState {
+                    func: StencilFunc::Always,
+                    reference: self.clip_stencil_value << 1,
+                    mask: CLIP_STENCIL_MASK,
+                    write: true,
+                }),
+                color_mask: false,
+                clear_ops,
+                ..RenderOptions::default()
+            },
+        });
+
+        self.preserve_draw_framebuffer();
+    }
+
     fn draw_stencil(&mut self, quad_positions: &[Vector4F]) {
         self.device.allocate_buffer(
             &self.stencil_vertex_array.vertex_buffer,
@@ -1164,6 +2128,63 @@ where
         self.preserve_draw_framebuffer();
     }
 
+    /// Copies `previous` into the destination, shifted `offset` pixels to the right and down, for
+    /// scrolling content that has already been rendered. `previous` must be the size of the
+    /// viewport.
+    ///
+    /// This replaces the destination's contents, and clears the pixels that `previous` doesn't
+    /// cover (all of them if it's `None`) to the background color, or to transparent black if
+    /// there is none. Over a foreign target, `previous` is blended with source-over instead, and
+    /// nothing is cleared.
+    pub fn draw_scrolled_framebuffer(&mut self,
+                                     previous: Option<&D::Framebuffer>,
+                                     offset: Vector2I) {
+        let draw_viewport = self.draw_viewport();
+        let foreign = self.render_target_stack.is_empty() && self.dest_framebuffer.is_foreign();
+        let (blend, clear_color) = if foreign {
+            (BlendMode::SrcOver.to_blend_state(), None)
+        } else {
+            let background_color = self.options.background_color;
+            (None, Some(background_color.unwrap_or_else(ColorF::transparent_black)))
+        };
+
+        // The reprojection program maps its unit quad to normalized device coordinates with the
+        // new transform, and back to texture coordinates with the old one.
+        let unit_to_ndc = Transform4F::from_scale(Vector4F::new(2.0, 2.0, 1.0, 1.0))
+                                      .translate(Vector4F::new(-1.0, -1.0, 0.0, 1.0));
+        let ndc_offset = offset.to_f32() * Vector2F::new(2.0, -2.0) / draw_viewport.size().to_f32();
+        let new_transform = unit_to_ndc.translate(ndc_offset.to_4d());
+
+        // With nothing to copy, this only clears.
+        let (index_count, textures) = match previous {
+            Some(framebuffer) => (6, vec![self.device.framebuffer_texture(framebuffer)]),
+            None => (0, vec![]),
+        };
+
+        self.device.draw_elements(index_count, &RenderState {
+            target: &self.draw_render_target(),
+            program: &self.reprojection_program.program,
+            vertex_array: &self.reprojection_vertex_array.vertex_array,
+            primitive: Primitive::Triangles,
+            textures: &textures,
+            uniforms: &[
+                (&self.reprojection_program.old_transform_uniform,
+                 UniformData::from_transform_3d(&unit_to_ndc)),
+                (&self.reprojection_program.new_transform_uniform,
+                 UniformData::from_transform_3d(&new_transform)),
+                (&self.reprojection_program.texture_uniform, UniformData::TextureUnit(0)),
+            ],
+            viewport: draw_viewport,
+            options: RenderOptions {
+                blend,
+                clear_ops: ClearOps { color: clear_color, ..ClearOps::default() },
+                ..RenderOptions::default()
+            },
+        });
+
+        self.preserve_draw_framebuffer();
+    }
+
     pub fn draw_render_target(&self) -> RenderTarget<D> {
         match self.render_target_stack.last() {
             Some(&render_target_id) => {
@@ -1176,24 +2197,39 @@ where
                     RenderTarget::Framebuffer(&self.intermediate_dest_framebuffer)
                 } else {
                     match self.dest_framebuffer {
-                        DestFramebuffer::Default { .. } => RenderTarget::Default,
+                        DestFramebuffer::Default { .. } |
+                        DestFramebuffer::ForeignTarget { .. } => RenderTarget::Default,
                         DestFramebuffer::Other(ref framebuffer) => {
                             RenderTarget::Framebuffer(framebuffer)
                         }
+                        DestFramebuffer::Layers(ref framebuffers) => {
+                            RenderTarget::Framebuffer(&framebuffers[self.current_layer])
+                        }
                     }
                 }
             }
         }
     }
 
-    fn push_render_target(&mut self, render_target_id: RenderTargetId) {
+    fn push_render_target(&mut self, render_target_id: RenderTargetId) -> Result<(), Error> {
+        // Undeclared render targets are padded out with placeholders; see
+        // `declare_render_target()`.
+        match self.render_targets.get(render_target_id.0 as usize) {
+            Some(render_target) if render_target.location.page != TexturePageId(!0) => {}
+            _ => return Err(Error::NoSuchRenderTarget(render_target_id)),
+        }
         self.render_target_stack.push(render_target_id);
+        Ok(())
     }
 
-    fn pop_render_target(&mut self) {
-        self.render_target_stack.pop().expect("Render target stack underflow!");
+    fn pop_render_target(&mut self) -> Result<(), Error> {
+        match self.render_target_stack.pop() {
+            Some(_) => Ok(()),
+            None => Err(Error::RenderTargetStackUnderflow),
+        }
     }
 
+    #[cfg(feature = "pf-filter-text")]
     fn set_uniforms_for_text_filter<'a>(&'a self,
                                         textures: &mut Vec<&'a D::Texture>,
                                         uniforms: &mut Vec<(&'a D::Uniform, UniformData)>,
@@ -1225,6 +2261,7 @@ where
         }
     }
 
+    #[cfg(feature = "pf-filter-blur")]
     fn set_uniforms_for_blur_filter<'a>(&'a self,
                                         uniforms: &mut Vec<(&'a D::Uniform, UniformData)>,
                                         src_texture_size: Vector2I,
@@ -1258,32 +2295,64 @@ where
 
         let main_viewport = self.main_viewport();
 
-        let uniforms = [(&self.blit_program.src_uniform, UniformData::TextureUnit(0))];
-        let textures = [(self.device.framebuffer_texture(&self.intermediate_dest_framebuffer))];
+        let (blit_program, blit_vertex_array) = match self.spot_lut_texture {
+            None => (&self.blit_program, &self.blit_vertex_array),
+            Some(_) => (&self.blit_spot_program.blit_program, &self.blit_spot_vertex_array),
+        };
+
+        let intermediate_dest_texture =
+            self.device.framebuffer_texture(&self.intermediate_dest_framebuffer);
+        let mut textures = vec![intermediate_dest_texture];
+        let mut uniforms = vec![(&blit_program.src_uniform, UniformData::TextureUnit(0))];
+        if let Some(ref spot_lut_texture) = self.spot_lut_texture {
+            textures.push(spot_lut_texture);
+            uniforms.push((&self.blit_spot_program.spot_lut_uniform, UniformData::TextureUnit(1)));
+        }
+
+        let target = match self.dest_framebuffer {
+            DestFramebuffer::Default { .. } |
+            DestFramebuffer::ForeignTarget { .. } => RenderTarget::Default,
+            DestFramebuffer::Other(ref framebuffer) => RenderTarget::Framebuffer(framebuffer),
+            DestFramebuffer::Layers(ref framebuffers) => {
+                RenderTarget::Framebuffer(&framebuffers[self.current_layer])
+            }
+        };
+
+        // A foreign target already has the embedder's content in it, so composite over that
+        // instead of replacing it.
+        let blend = if self.dest_framebuffer.is_foreign() {
+            BlendMode::SrcOver.to_blend_state()
+        } else {
+            None
+        };
 
         self.device.draw_elements(6, &RenderState {
-            target: &RenderTarget::Default,
-            program: &self.blit_program.program,
-            vertex_array: &self.blit_vertex_array.vertex_array,
+            target: &target,
+            program: &blit_program.program,
+            vertex_array: &blit_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
-            textures: &textures[..],
-            uniforms: &uniforms[..],
+            textures: &textures,
+            uniforms: &uniforms,
             viewport: main_viewport,
-            options: RenderOptions::default(),
+            options: RenderOptions { blend, ..RenderOptions::default() },
         });
     }
 
-    fn stencil_state(&self) -> Option<StencilState> {
-        if !self.flags.contains(RendererFlags::USE_DEPTH) {
+    fn stencil_state(&self, stencil_clipped: bool) -> Option<StencilState> {
+        let (mut reference, mut mask) = (0, 0);
+        if self.flags.contains(RendererFlags::USE_DEPTH) {
+            reference |= 1;
+            mask |= 1;
+        }
+        if stencil_clipped {
+            reference |= self.clip_stencil_value << 1;
+            mask |= CLIP_STENCIL_MASK;
+        }
+        if mask == 0 {
             return None;
         }
 
-        Some(StencilState {
-            func: StencilFunc::Equal,
-            reference: 1,
-            mask: 1,
-            write: false,
-        })
+        Some(StencilState { func: StencilFunc::Equal, reference, mask, write: false })
     }
 
     fn clear_color_for_draw_operation(&self) -> Option<ColorF> {
@@ -1301,7 +2370,14 @@ where
         if must_preserve_contents {
             None
         } else if self.render_target_stack.is_empty() {
-            self.options.background_color
+            if !self.dest_framebuffer.is_foreign() {
+                self.options.background_color
+            } else if self.flags.contains(RendererFlags::INTERMEDIATE_DEST_FRAMEBUFFER_NEEDED) {
+                // The intermediate framebuffer is blended over the foreign target at the end.
+                Some(ColorF::transparent_black())
+            } else {
+                None
+            }
         } else {
             Some(ColorF::default())
         }
@@ -1311,7 +2387,10 @@ where
         match self.render_target_stack.last() {
             Some(&render_target_id) => {
                 let texture_page = self.render_target_location(render_target_id).page;
-                self.texture_pages[texture_page.0 as usize].must_preserve_contents = true;
+                let texture_page = &mut self.texture_pages[texture_page.0 as usize];
+                texture_page.must_preserve_contents = true;
+                // The page no longer holds the texels uploaded to it.
+                texture_page.texels_id = None;
             }
             None => {
                 self.framebuffer_flags
@@ -1327,15 +2406,23 @@ where
         }
     }
 
-    fn main_viewport(&self) -> RectI {
+    pub(crate) fn main_viewport(&self) -> RectI {
         match self.dest_framebuffer {
-            DestFramebuffer::Default { viewport, .. } => viewport,
+            DestFramebuffer::Default { viewport, .. } |
+            DestFramebuffer::ForeignTarget { viewport, .. } => viewport,
             DestFramebuffer::Other(ref framebuffer) => {
                 let size = self
                     .device
                     .texture_size(self.device.framebuffer_texture(framebuffer));
                 RectI::new(Vector2I::default(), size)
             }
+            DestFramebuffer::Layers(ref framebuffers) => {
+                let framebuffer = &framebuffers[self.current_layer];
+                let size = self
+                    .device
+                    .texture_size(self.device.framebuffer_texture(framebuffer));
+                RectI::new(Vector2I::default(), size)
+            }
         }
     }
 
@@ -1348,12 +2435,52 @@ where
         self.render_targets[render_target_id.0 as usize].location
     }
 
+    fn check_texture_page(&self, id: TexturePageId) -> Result<&TexturePage<D>, Error> {
+        self.texture_pages.get(id.0 as usize).ok_or(Error::NoSuchTexturePage(id))
+    }
+
+    fn check_framebuffer_texture_page(&self, id: TexturePageId) -> Result<(), Error> {
+        match self.check_texture_page(id)?.storage {
+            TexturePageStorage::Framebuffer(_) => Ok(()),
+            TexturePageStorage::Texture(_) | TexturePageStorage::External(_) => {
+                Err(Error::IncompatibleTexturePage(id))
+            }
+        }
+    }
+
+    // Flags an upload to or read from a page that treats it as having another alpha type than it
+    // was allocated with, if alpha type validation is on. The page must exist.
+    fn validate_alpha_type(&mut self, id: TexturePageId, alpha_type: AlphaType, usage: &str) {
+        if !self.options.validate_alpha_types {
+            return;
+        }
+        let page_alpha_type = self.texture_pages[id.0 as usize].alpha_type;
+        if alpha_type != page_alpha_type {
+            warn!("{} treats {:?} as {:?}, but it's {:?}.", usage, id, alpha_type, page_alpha_type);
+            self.stats.alpha_type_mismatch_count += 1;
+        }
+    }
+
+    // Render targets' pages are checked when they're declared, so this can't fail for them.
     fn texture_page_framebuffer(&self, id: TexturePageId) -> &D::Framebuffer {
-        &self.texture_pages[id.0 as usize].framebuffer
+        match self.texture_pages[id.0 as usize].storage {
+            TexturePageStorage::Framebuffer(ref framebuffer) => framebuffer,
+            TexturePageStorage::Texture(_) | TexturePageStorage::External(_) => {
+                panic!("Can't render to that texture page!")
+            }
+        }
     }
 
     fn texture_page(&self, id: TexturePageId) -> &D::Texture {
-        self.device.framebuffer_texture(&self.texture_page_framebuffer(id))
+        match self.texture_pages[id.0 as usize].storage {
+            TexturePageStorage::Framebuffer(ref framebuffer) => {
+                self.device.framebuffer_texture(framebuffer)
+            }
+            TexturePageStorage::Texture(ref texture) => texture,
+            TexturePageStorage::External(id) => {
+                self.external_textures[id.0 as usize].as_ref().unwrap()
+            }
+        }
     }
 
     fn allocate_timer_query(&mut self) -> D::TimerQuery {
@@ -1376,6 +2503,36 @@ where
     }
 }
 
+fn create_spot_lut_texture<D>(device: &D, palette: &SpotColorPalette) -> D::Texture
+                              where D: Device {
+    let texels = palette.build_lut();
+    device.create_texture_from_data(TextureFormat::RGBA8,
+                                    SpotColorPalette::lut_size(),
+                                    TextureDataRef::U8(color::color_slice_to_u8_slice(&texels)))
+}
+
+fn area_lut_sampling_flags(quality: AntialiasingQuality) -> TextureSamplingFlags {
+    match quality {
+        AntialiasingQuality::Full => TextureSamplingFlags::empty(),
+        AntialiasingQuality::None | AntialiasingQuality::Fast => {
+            TextureSamplingFlags::NEAREST_MIN | TextureSamplingFlags::NEAREST_MAG
+        }
+    }
+}
+
+// The area LUT is indexed horizontally by the height of the edge above the pixel center, from -8
+// to 8 pixels, and vertically by its slope. Ignoring the slope and thresholding at the center
+// turns area coverage into point sampling.
+fn create_aliased_area_lut_texture<D>(device: &D) -> D::Texture where D: Device {
+    const WIDTH: usize = 256;
+    let texels: Vec<u8> = (0..WIDTH).map(|x| if x < WIDTH / 2 { 255 } else { 0 }).collect();
+    let texture = device.create_texture_from_data(TextureFormat::R8,
+                                                  Vector2I::new(WIDTH as i32, 1),
+                                                  TextureDataRef::U8(&texels));
+    device.set_texture_sampling_mode(&texture, area_lut_sampling_flags(AntialiasingQuality::None));
+    texture
+}
+
 // Render stats
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -1384,6 +2541,11 @@ pub struct RenderStats {
     pub fill_count: usize,
     pub alpha_tile_count: usize,
     pub solid_tile_count: usize,
+    /// The number of paths skipped without being tiled because they lay outside the view box.
+    pub culled_path_count: usize,
+    /// The number of texture uploads and reads whose alpha type didn't match their page's, if
+    /// `RendererOptions::validate_alpha_types` is set.
+    pub alpha_type_mismatch_count: usize,
 }
 
 impl Add<RenderStats> for RenderStats {
@@ -1394,6 +2556,9 @@ impl Add<RenderStats> for RenderStats {
             solid_tile_count: self.solid_tile_count + other.solid_tile_count,
             alpha_tile_count: self.alpha_tile_count + other.alpha_tile_count,
             fill_count: self.fill_count + other.fill_count,
+            culled_path_count: self.culled_path_count + other.culled_path_count,
+            alpha_type_mismatch_count: self.alpha_type_mismatch_count +
+                other.alpha_type_mismatch_count,
         }
     }
 }
@@ -1406,6 +2571,8 @@ impl Div<usize> for RenderStats {
             solid_tile_count: self.solid_tile_count / divisor,
             alpha_tile_count: self.alpha_tile_count / divisor,
             fill_count: self.fill_count / divisor,
+            culled_path_count: self.culled_path_count / divisor,
+            alpha_type_mismatch_count: self.alpha_type_mismatch_count / divisor,
         }
     }
 }
@@ -1421,6 +2588,103 @@ impl<D> RenderTimers<D> where D: Device {
     }
 }
 
+/// Pixels being copied back from the destination framebuffer. See `Renderer::begin_readback()`.
+pub struct Readback<D> where D: Device {
+    receiver: D::TextureDataReceiver,
+    size: Vector2I,
+}
+
+impl<D> Readback<D> where D: Device {
+    /// The size of the pixel rectangle being read back.
+    #[inline]
+    pub fn size(&self) -> Vector2I {
+        self.size
+    }
+}
+
+/// A measurement of how much of a region is covered. See `Renderer::begin_coverage_query()`.
+pub struct CoverageQuery<D> where D: Device {
+    receiver: D::TextureDataReceiver,
+    pixel_count: u32,
+    // The number of pixels each unit of the reduced statistics stands for, if the region was
+    // reduced on the GPU.
+    reduction: Option<f32>,
+}
+
+/// How much of a region of the destination framebuffer is covered.
+///
+/// The counts form a histogram of the region's pixels: `pixel_count - touched_pixel_count` are
+/// empty, `touched_pixel_count - full_pixel_count` are partly covered, and `full_pixel_count` are
+/// opaque.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Coverage {
+    /// The number of pixels in the region.
+    pub pixel_count: u32,
+    /// The covered area, in pixels: the sum of the alpha of every pixel.
+    pub area: f32,
+    /// The number of pixels that are at least partly covered.
+    pub touched_pixel_count: u32,
+    /// The number of pixels that are completely covered.
+    pub full_pixel_count: u32,
+}
+
+impl<D> CoverageQuery<D> where D: Device {
+    #[inline]
+    fn coverage(&self, data: TextureData) -> Coverage {
+        Coverage::from_texture_data(data, self.pixel_count, self.reduction)
+    }
+}
+
+impl Coverage {
+    // Computes the coverage of `pixel_count` pixels from the RGBA8 pixels that were read back, or
+    // from the statistics they were reduced to, each unit of which stands for `reduction` pixels.
+    fn from_texture_data(data: TextureData, pixel_count: u32, reduction: Option<f32>) -> Coverage {
+        let mut coverage = Coverage { pixel_count, ..Coverage::default() };
+        match reduction {
+            Some(scale) => {
+                let stats = match data {
+                    TextureData::F16(texels) => {
+                        [texels[0].to_f32(), texels[1].to_f32(), texels[2].to_f32()]
+                    }
+                    TextureData::F32(texels) => [texels[0], texels[1], texels[2]],
+                    _ => panic!("Unexpected texture data format for a coverage query!"),
+                };
+                coverage.area = (stats[0] * scale).min(pixel_count as f32);
+                coverage.touched_pixel_count =
+                    ((stats[1] * scale).round() as u32).min(pixel_count);
+                coverage.full_pixel_count =
+                    ((stats[2] * scale).round() as u32).min(coverage.touched_pixel_count);
+            }
+            None => {
+                let pixels = match data {
+                    TextureData::U8(pixels) => pixels,
+                    _ => panic!("Unexpected texture data format for a coverage query!"),
+                };
+                for pixel in pixels.chunks(4) {
+                    coverage.area += pixel[3] as f32 / 255.0;
+                    if pixel[3] > 0 {
+                        coverage.touched_pixel_count += 1;
+                    }
+                    if pixel[3] == 255 {
+                        coverage.full_pixel_count += 1;
+                    }
+                }
+            }
+        }
+        coverage
+    }
+
+    /// The fraction of the region that's covered, from 0 to 1.
+    #[inline]
+    pub fn fraction(&self) -> f32 {
+        if self.pixel_count == 0 {
+            0.0
+        } else {
+            self.area / self.pixel_count as f32
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct RenderTime {
     pub stage_0: Duration,
@@ -1454,45 +2718,116 @@ bitflags! {
     }
 }
 
-struct TextureCache<D> where D: Device {
-    textures: Vec<D::Texture>,
+/// Textures and framebuffers from previous frames, kept for reuse by paint texture pages and
+/// render targets of the same size and format.
+struct TexturePool<D> where D: Device {
+    textures: Vec<PooledResource<D::Texture>>,
+    framebuffers: Vec<PooledResource<D::Framebuffer>>,
+}
+
+struct PooledResource<T> {
+    resource: T,
+    format: TextureFormat,
+    size: Vector2I,
+    // The number of frames that have ended since this resource was released.
+    age: u32,
 }
 
-impl<D> TextureCache<D> where D: Device {
-    fn new() -> TextureCache<D> {
-        TextureCache { textures: vec![] }
+impl<D> TexturePool<D> where D: Device {
+    fn new() -> TexturePool<D> {
+        TexturePool { textures: vec![], framebuffers: vec![] }
     }
 
     fn create_texture(&mut self, device: &mut D, format: TextureFormat, size: Vector2I)
                       -> D::Texture {
-        for index in 0..self.textures.len() {
-            if device.texture_size(&self.textures[index]) != size ||
-                    device.texture_format(&self.textures[index]) != format {
-                continue;
+        match take_pooled_resource(&mut self.textures, format, size) {
+            Some(texture) => texture,
+            None => device.create_texture(format, size),
+        }
+    }
+
+    fn create_framebuffer(&mut self, device: &mut D, format: TextureFormat, size: Vector2I)
+                          -> D::Framebuffer {
+        match take_pooled_resource(&mut self.framebuffers, format, size) {
+            Some(framebuffer) => framebuffer,
+            None => {
+                let texture = self.create_texture(device, format, size);
+                device.create_framebuffer(texture)
             }
-            return self.textures.remove(index);
         }
+    }
 
-        device.create_texture(format, size)
+    fn release_texture(&mut self, device: &D, texture: D::Texture) {
+        let (format, size) = (device.texture_format(&texture), device.texture_size(&texture));
+        self.textures.push(PooledResource { resource: texture, format, size, age: 0 });
     }
 
-    fn release_texture(&mut self, texture: D::Texture) {
-        if self.textures.len() == TEXTURE_CACHE_SIZE {
-            self.textures.pop();
-        }
-        self.textures.insert(0, texture);
+    fn release_framebuffer(&mut self, device: &D, framebuffer: D::Framebuffer) {
+        let texture = device.framebuffer_texture(&framebuffer);
+        let (format, size) = (device.texture_format(texture), device.texture_size(texture));
+        self.framebuffers.push(PooledResource { resource: framebuffer, format, size, age: 0 });
+    }
+
+    /// Frees whatever hasn't been reused for a while, so that the pool tracks what recent frames
+    /// needed.
+    fn end_frame(&mut self) {
+        age_pooled_resources(&mut self.textures);
+        age_pooled_resources(&mut self.framebuffers);
     }
 }
 
+fn age_pooled_resources<T>(pool: &mut Vec<PooledResource<T>>) {
+    for pooled in pool.iter_mut() {
+        pooled.age += 1;
+    }
+    pool.retain(|pooled| pooled.age <= TEXTURE_POOL_MAX_AGE);
+}
+
+// Takes the most recently released resource with the given format and size.
+fn take_pooled_resource<T>(pool: &mut Vec<PooledResource<T>>,
+                           format: TextureFormat,
+                           size: Vector2I)
+                           -> Option<T> {
+    let index = pool.iter().rposition(|pooled| pooled.format == format && pooled.size == size)?;
+    Some(pool.remove(index).resource)
+}
+
 struct TexturePage<D> where D: Device {
-    framebuffer: D::Framebuffer,
+    storage: TexturePageStorage<D>,
     must_preserve_contents: bool,
+    alpha_type: AlphaType,
+    // The texels last uploaded to the page, if it hasn't been rendered to since.
+    texels_id: Option<TexelsId>,
+}
+
+enum TexturePageStorage<D> where D: Device {
+    Framebuffer(D::Framebuffer),
+    // A texture that can only be sampled from, used for compressed images.
+    Texture(D::Texture),
+    // A texture in `Renderer::external_textures`.
+    External(ExternalTextureId),
 }
 
 struct RenderTargetInfo {
     location: TextureLocation,
 }
 
+struct CustomFilter<D> where D: Device {
+    // As with custom paint shaders, kept for compiling again after device loss.
+    name: String,
+    source: Vec<u8>,
+    program: CustomFilterProgram<D>,
+    vertex_array: SolidTileVertexArray<D>,
+}
+
+struct CustomPaintShader<D> where D: Device {
+    // The name and source are kept so that the shader can be compiled again after device loss.
+    name: String,
+    source: Vec<u8>,
+    program: CustomPaintProgram<D>,
+    vertex_array: AlphaTileVertexArray<D>,
+}
+
 trait ToBlendState {
     fn to_blend_state(self) -> Option<BlendState>;
 }
@@ -1581,6 +2916,15 @@ impl ToBlendState for BlendMode {
                     op: BlendOp::Min,
                 })
             }
+            BlendMode::Copy |
+            BlendMode::SrcIn |
+            BlendMode::DestIn |
+            BlendMode::SrcOut |
+            BlendMode::DestAtop => {
+                // Blending is done manually in the shader, unless the device supports
+                // dual-source blending. See `dual_source_blend_state()`.
+                None
+            }
             BlendMode::Multiply |
             BlendMode::Screen |
             BlendMode::HardLight |
@@ -1671,6 +3015,46 @@ impl ToBlendState for CompositeOp {
     }
 }
 
+// The blend state for a Porter-Duff blend mode drawn with `tile_alpha_porterduff_dual`, which
+// outputs the source color times coverage, and the operator's destination factor mixed with one
+// by coverage as its second output.
+fn dual_source_blend_state(blend_mode: BlendMode) -> BlendState {
+    let src_factor = match blend_mode.porter_duff_op() {
+        Some(CompositeOp::Copy) => BlendFactor::One,
+        Some(CompositeOp::DestIn) => BlendFactor::Zero,
+        Some(CompositeOp::SrcIn) => BlendFactor::DestAlpha,
+        Some(CompositeOp::SrcOut) | Some(CompositeOp::DestAtop) => BlendFactor::OneMinusDestAlpha,
+        _ => unreachable!(),
+    };
+    BlendState {
+        src_rgb_factor: src_factor,
+        dest_rgb_factor: BlendFactor::Src1Alpha,
+        src_alpha_factor: src_factor,
+        dest_alpha_factor: BlendFactor::Src1Alpha,
+        ..BlendState::default()
+    }
+}
+
+// The blend factors are ignored.
+fn advanced_blend_op(blend_mode: BlendMode) -> BlendOp {
+    match blend_mode {
+        BlendMode::Multiply => BlendOp::Multiply,
+        BlendMode::Screen => BlendOp::Screen,
+        BlendMode::HardLight => BlendOp::HardLight,
+        BlendMode::Overlay => BlendOp::Overlay,
+        BlendMode::ColorDodge => BlendOp::ColorDodge,
+        BlendMode::ColorBurn => BlendOp::ColorBurn,
+        BlendMode::SoftLight => BlendOp::SoftLight,
+        BlendMode::Difference => BlendOp::Difference,
+        BlendMode::Exclusion => BlendOp::Exclusion,
+        BlendMode::Hue => BlendOp::Hue,
+        BlendMode::Saturation => BlendOp::Saturation,
+        BlendMode::Color => BlendOp::Color,
+        BlendMode::Luminosity => BlendOp::Luminosity,
+        _ => unreachable!(),
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) enum BlendModeProgram {
     Regular,
@@ -1680,6 +3064,7 @@ pub(crate) enum BlendModeProgram {
     Difference,
     Exclusion,
     HSL,
+    PorterDuff,
 }
 
 impl BlendModeProgram {
@@ -1707,18 +3092,27 @@ impl BlendModeProgram {
             BlendMode::Saturation |
             BlendMode::Color |
             BlendMode::Luminosity => BlendModeProgram::HSL,
+            BlendMode::Copy |
+            BlendMode::SrcIn |
+            BlendMode::DestIn |
+            BlendMode::SrcOut |
+            BlendMode::DestAtop => BlendModeProgram::PorterDuff,
         }
     }
 
-    pub(crate) fn needs_readable_framebuffer(self) -> bool {
+    pub(crate) fn needs_readable_framebuffer(self,
+                                             dual_source_blending: bool,
+                                             advanced_blending: bool)
+                                             -> bool {
         match self {
             BlendModeProgram::Regular => false,
+            BlendModeProgram::PorterDuff => !dual_source_blending,
             BlendModeProgram::Overlay |
             BlendModeProgram::DodgeBurn |
             BlendModeProgram::SoftLight |
             BlendModeProgram::Difference |
             BlendModeProgram::Exclusion |
-            BlendModeProgram::HSL => true,
+            BlendModeProgram::HSL => !advanced_blending,
         }
     }
 }
@@ -1734,3 +3128,393 @@ bitflags! {
         const INTERMEDIATE_DEST_FRAMEBUFFER_NEEDED = 0x02;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Coverage, PooledResource, Renderer, RendererFlags, TEXTURE_POOL_MAX_AGE};
+    use super::age_pooled_resources;
+    use super::take_pooled_resource;
+    use crate::concurrent::executor::SequentialExecutor;
+    use crate::error::Error;
+    use crate::gpu::options::{DepthOptions, DestFramebuffer, RendererOptions};
+    use crate::options::BuildOptions;
+    use crate::paint::Paint;
+    use crate::scene::{DrawPath, RenderTarget, Scene};
+    use pathfinder_color::{ColorF, ColorU};
+    use pathfinder_content::effects::{BlurDirection, CompositeOp, CustomFilterId, Effects};
+    use pathfinder_content::effects::Filter;
+    use pathfinder_content::outline::{Contour, Outline};
+    use pathfinder_content::render_target::RenderTargetId;
+    use pathfinder_geometry::rect::{RectF, RectI};
+    use pathfinder_geometry::vector::{Vector2F, Vector2I, Vector4F};
+    use pathfinder_gl::GLDevice;
+    use pathfinder_gl::headless::HeadlessContext;
+    use pathfinder_gpu::{DepthFunc, TextureData, TextureFormat};
+    use pathfinder_resources::ResourceLoader;
+    use pathfinder_resources::embedded::EmbeddedResourceLoader;
+    use pathfinder_resources::fs::FilesystemResourceLoader;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    const SIZE: i32 = 32;
+
+    // Renders into an offscreen framebuffer. The tests that need one are ignored by default,
+    // since they need a GL driver; run them with `cargo test -- --ignored`. The context is
+    // returned last so that it outlives the renderer.
+    fn create_renderer(options: RendererOptions) -> (Renderer<GLDevice>, HeadlessContext) {
+        create_renderer_with_resources(options, &FilesystemResourceLoader::locate())
+    }
+
+    fn create_renderer_with_resources(options: RendererOptions, resources: &dyn ResourceLoader)
+                                      -> (Renderer<GLDevice>, HeadlessContext) {
+        let context = HeadlessContext::new().expect("failed to create a headless GL context");
+        let device = context.create_device();
+        let framebuffer = context.create_framebuffer(&device, Vector2I::splat(SIZE));
+        let renderer = Renderer::new(device,
+                                     resources,
+                                     DestFramebuffer::Other(framebuffer),
+                                     RendererOptions {
+                                         background_color: Some(ColorF::black()),
+                                         ..options
+                                     });
+        (renderer, context)
+    }
+
+    fn new_scene() -> Scene {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::default(), Vector2I::splat(SIZE).to_f32()));
+        scene
+    }
+
+    fn fill_rect(scene: &mut Scene, rect: RectF, color: ColorU) {
+        let mut contour = Contour::new();
+        contour.push_endpoint(rect.origin());
+        contour.push_endpoint(rect.upper_right());
+        contour.push_endpoint(rect.lower_right());
+        contour.push_endpoint(rect.lower_left());
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        let paint = scene.push_paint(&Paint::Color(color));
+        scene.push_path(DrawPath::new(outline, paint));
+    }
+
+    // Fills the whole scene with white in a layer, and draws the layer with `filter`.
+    fn layer_scene(filter: Filter) -> Scene {
+        let mut scene = new_scene();
+        let size = Vector2I::splat(SIZE);
+        let render_target = scene.push_render_target(RenderTarget::new(size, "layer".to_owned()));
+        fill_rect(&mut scene, RectF::new(Vector2F::default(), size.to_f32()), ColorU::white());
+        scene.pop_render_target();
+        scene.draw_render_target(render_target, Effects::new(filter));
+        scene
+    }
+
+    fn render(renderer: &mut Renderer<GLDevice>, scene: &Scene) -> Result<(), Error> {
+        let commands = Arc::new(Mutex::new(vec![]));
+        let listener_commands = commands.clone();
+        let listener = move |command| listener_commands.lock().unwrap().push(command);
+        scene.build(BuildOptions::default(), Box::new(listener), &SequentialExecutor)?;
+
+        renderer.begin_scene();
+        let result = commands.lock().unwrap().iter().try_for_each(|command| {
+            renderer.render_command(command)
+        });
+        renderer.end_scene();
+        result
+    }
+
+    fn read_pixel(renderer: &mut Renderer<GLDevice>, position: Vector2I) -> ColorU {
+        let readback = renderer.begin_readback(RectI::new(position, Vector2I::splat(1)));
+        match renderer.finish_readback(readback) {
+            TextureData::U8(pixels) => ColorU::new(pixels[0], pixels[1], pixels[2], pixels[3]),
+            _ => panic!("the destination should be RGBA8"),
+        }
+    }
+
+    fn pooled(resource: u32, format: TextureFormat, size: i32) -> PooledResource<u32> {
+        PooledResource { resource, format, size: Vector2I::splat(size), age: 0 }
+    }
+
+    #[test]
+    fn test_pooled_resources_match_format_and_size() {
+        let mut pool = vec![
+            pooled(0, TextureFormat::RGBA8, 64),
+            pooled(1, TextureFormat::RGBA8, 128),
+            pooled(2, TextureFormat::RGBA16F, 64),
+            pooled(3, TextureFormat::RGBA8, 64),
+        ];
+
+        // The most recently released match comes first.
+        let size = Vector2I::splat(64);
+        assert_eq!(take_pooled_resource(&mut pool, TextureFormat::RGBA8, size), Some(3));
+        assert_eq!(take_pooled_resource(&mut pool, TextureFormat::RGBA8, size), Some(0));
+        assert_eq!(take_pooled_resource(&mut pool, TextureFormat::RGBA8, size), None);
+        assert_eq!(take_pooled_resource(&mut pool, TextureFormat::R8, size), None);
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_unused_pooled_resources_are_freed() {
+        let mut pool = vec![pooled(0, TextureFormat::RGBA8, 64)];
+        for _ in 0..TEXTURE_POOL_MAX_AGE {
+            age_pooled_resources(&mut pool);
+        }
+        assert_eq!(pool.len(), 1);
+
+        // A resource released since the last frame outlives the older one.
+        pool.push(pooled(1, TextureFormat::RGBA8, 64));
+        age_pooled_resources(&mut pool);
+        assert_eq!(pool.iter().map(|pooled| pooled.resource).collect::<Vec<_>>(), [1]);
+    }
+
+    // Tints the layer's coverage with the filter's parameters.
+    static TINT_FILTER_SHADER: &str = "#version {{version}}
+        precision highp float;
+        uniform sampler2D uColorTexture;
+        uniform vec4 uParams;
+        in vec2 vColorTexCoord;
+        out vec4 oFragColor;
+        void main() {
+            oFragColor = uParams * texture(uColorTexture, vColorTexCoord).a;
+        }";
+
+    #[test]
+    #[ignore]
+    fn test_custom_filters_are_applied_when_compositing() {
+        let (mut renderer, _context) = create_renderer(RendererOptions::default());
+        let resources = FilesystemResourceLoader::locate();
+        let shader = renderer.register_custom_filter(&resources,
+                                                     "tint",
+                                                     TINT_FILTER_SHADER.as_bytes());
+        assert_eq!(shader, CustomFilterId(0));
+
+        let params = [0.0, 0.5, 1.0, 1.0];
+        render(&mut renderer, &layer_scene(Filter::Custom { shader, params })).unwrap();
+        let color = read_pixel(&mut renderer, Vector2I::splat(SIZE / 2));
+        assert_eq!((color.r, color.b, color.a), (0, 255, 255));
+        assert!((127..=128).contains(&color.g));
+
+        // Composite filters still use the built-in shader.
+        render(&mut renderer, &layer_scene(Filter::Composite(CompositeOp::SrcOver))).unwrap();
+        assert_eq!(read_pixel(&mut renderer, Vector2I::splat(SIZE / 2)), ColorU::white());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_unregistered_custom_filters_are_errors() {
+        let (mut renderer, _context) = create_renderer(RendererOptions::default());
+        let shader = CustomFilterId(3);
+        let scene = layer_scene(Filter::Custom { shader, params: [0.0; 4] });
+        match render(&mut renderer, &scene) {
+            Err(Error::NoSuchCustomFilter(id)) => assert_eq!(id, shader),
+            _ => panic!("drawing with an unregistered custom filter should fail"),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_depth_options_set_tile_depth_and_state() {
+        let (mut renderer, _context) = create_renderer(RendererOptions::default());
+        let tile_z = |renderer: &Renderer<GLDevice>| {
+            (renderer.tile_transform() * Vector4F::new(0.0, 0.0, 0.0, 1.0)).z()
+        };
+        assert_eq!(tile_z(&renderer), 0.0);
+        assert!(renderer.composite_depth_state().is_none());
+
+        let mut set_depth = |test, write| {
+            renderer.options.depth = Some(DepthOptions { z: 0.5, test, write });
+            renderer.composite_depth_state().map(|state| (state.func, state.write))
+        };
+        assert!(matches!(set_depth(true, false), Some((DepthFunc::Less, false))));
+        assert!(matches!(set_depth(true, true), Some((DepthFunc::Less, true))));
+        assert!(matches!(set_depth(false, true), Some((DepthFunc::Always, true))));
+        assert!(set_depth(false, false).is_none());
+
+        renderer.options.depth = Some(DepthOptions { z: 0.5, test: true, write: true });
+        assert_eq!(tile_z(&renderer), 0.5);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_depth_options_only_apply_to_the_destination() {
+        let depth = Some(DepthOptions { z: -0.5, test: true, write: true });
+        let (mut renderer, _context) = create_renderer(RendererOptions {
+            depth,
+            ..RendererOptions::default()
+        });
+        assert_eq!(renderer.dest_depth_options(), depth);
+
+        // Render targets have no depth buffers.
+        renderer.render_target_stack.push(RenderTargetId(0));
+        assert_eq!(renderer.dest_depth_options(), None);
+        renderer.render_target_stack.clear();
+
+        // Neither do the intermediate framebuffers that some blend modes draw into.
+        renderer.flags.insert(RendererFlags::INTERMEDIATE_DEST_FRAMEBUFFER_NEEDED);
+        assert!(renderer.composite_depth_state().is_none());
+    }
+
+    const RED: ColorU = ColorU { r: 255, g: 0, b: 0, a: 255 };
+    const BLUE: ColorU = ColorU { r: 0, g: 0, b: 255, a: 255 };
+
+    // Fills the top half of the scene with red, and the bottom half with blue.
+    fn split_scene() -> Scene {
+        let mut scene = new_scene();
+        let half_size = Vector2I::new(SIZE, SIZE / 2).to_f32();
+        fill_rect(&mut scene, RectF::new(Vector2F::default(), half_size), RED);
+        fill_rect(&mut scene, RectF::new(Vector2F::new(0.0, half_size.y()), half_size), BLUE);
+        scene
+    }
+
+    fn pixels(data: TextureData) -> Vec<ColorU> {
+        match data {
+            TextureData::U8(pixels) => {
+                pixels.chunks(4).map(|pixel| {
+                    ColorU::new(pixel[0], pixel[1], pixel[2], pixel[3])
+                }).collect()
+            }
+            _ => panic!("the destination should be RGBA8"),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_readbacks_return_the_top_row_first() {
+        let (mut renderer, _context) = create_renderer(RendererOptions::default());
+        render(&mut renderer, &split_scene()).unwrap();
+
+        // OpenGL framebuffers are bottom up, so this covers two rows of red above one of blue.
+        let readback = renderer.begin_readback(RectI::new(Vector2I::new(4, SIZE / 2 - 1),
+                                                          Vector2I::splat(3)));
+        assert_eq!(readback.size(), Vector2I::splat(3));
+        let pixels = pixels(renderer.finish_readback(readback));
+        assert_eq!(pixels[..6], [RED; 6]);
+        assert_eq!(pixels[6..], [BLUE; 3]);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_readbacks_are_clipped_to_the_viewport() {
+        let (mut renderer, _context) = create_renderer(RendererOptions::default());
+        render(&mut renderer, &split_scene()).unwrap();
+
+        let readback = renderer.begin_readback(RectI::new(Vector2I::new(SIZE - 2, -4),
+                                                          Vector2I::splat(8)));
+        assert_eq!(readback.size(), Vector2I::new(2, 4));
+        let pixels = pixels(renderer.finish_readback(readback));
+        assert_eq!(pixels, vec![BLUE; 8]);
+
+        let readback = renderer.begin_readback(RectI::new(Vector2I::splat(SIZE),
+                                                          Vector2I::splat(8)));
+        assert_eq!(readback.size(), Vector2I::default());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_readbacks_can_be_polled() {
+        let (mut renderer, _context) = create_renderer(RendererOptions::default());
+        render(&mut renderer, &split_scene()).unwrap();
+
+        let rect = RectI::new(Vector2I::default(), Vector2I::splat(SIZE));
+        let readback = renderer.begin_readback(rect);
+        let start = Instant::now();
+        let data = loop {
+            if let Some(data) = renderer.poll_readback(&readback) {
+                break data;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10), "the readback never finished");
+            thread::sleep(Duration::from_millis(1));
+        };
+        let readback = renderer.begin_readback(rect);
+        assert_eq!(pixels(data), pixels(renderer.finish_readback(readback)));
+    }
+
+    #[test]
+    fn test_reduced_coverage_is_scaled_and_clamped() {
+        let coverage = |stats: [f32; 3], pixel_count| {
+            let data = TextureData::F32(vec![stats[0], stats[1], stats[2], 0.0]);
+            Coverage::from_texture_data(data, pixel_count, Some(64.0))
+        };
+        assert_eq!(coverage([0.25, 0.5, 0.125], 100), Coverage {
+            pixel_count: 100,
+            area: 16.0,
+            touched_pixel_count: 32,
+            full_pixel_count: 8,
+        });
+
+        // Rounding errors in the reduction can't make the counts exceed the region, or make more
+        // pixels full than are touched.
+        assert_eq!(coverage([2.0, 2.0, 2.0], 100), Coverage {
+            pixel_count: 100,
+            area: 100.0,
+            touched_pixel_count: 100,
+            full_pixel_count: 100,
+        });
+        assert_eq!(coverage([0.125, 0.125, 0.25], 100).full_pixel_count, 8);
+    }
+
+    #[test]
+    fn test_read_back_coverage_counts_pixels() {
+        let data = TextureData::U8(vec![0, 0, 0, 0, 0, 0, 0, 51, 0, 0, 0, 255, 0, 0, 0, 255]);
+        let coverage = Coverage::from_texture_data(data, 4, None);
+        assert!((coverage.area - 2.2).abs() < 0.001);
+        assert_eq!((coverage.touched_pixel_count, coverage.full_pixel_count), (3, 2));
+        assert!((coverage.fraction() - 0.55).abs() < 0.001);
+        assert_eq!(Coverage::default().fraction(), 0.0);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_coverage_queries_reduce_the_region() {
+        let (mut renderer, _context) = create_renderer(RendererOptions::default());
+        renderer.set_options(RendererOptions {
+            background_color: Some(ColorF::transparent_black()),
+            ..RendererOptions::default()
+        });
+        let mut scene = new_scene();
+        let half_size = Vector2I::new(SIZE / 2, SIZE).to_f32();
+        fill_rect(&mut scene, RectF::new(Vector2F::default(), half_size), ColorU::white());
+        render(&mut renderer, &scene).unwrap();
+
+        let query = renderer.begin_coverage_query(RectI::new(Vector2I::default(),
+                                                             Vector2I::splat(SIZE)));
+        let coverage = renderer.finish_coverage_query(query);
+        assert_eq!(coverage.pixel_count, (SIZE * SIZE) as u32);
+        assert!((coverage.fraction() - 0.5).abs() < 0.01);
+        let half_pixel_count = (SIZE * SIZE / 2) as u32;
+        let about_half = (half_pixel_count - 2)..=(half_pixel_count + 2);
+        assert!(about_half.contains(&coverage.touched_pixel_count));
+        assert!(about_half.contains(&coverage.full_pixel_count));
+
+        // Regions are clipped to the viewport.
+        let query = renderer.begin_coverage_query(RectI::new(Vector2I::new(-SIZE, 0),
+                                                             Vector2I::new(SIZE + 4, 4)));
+        let coverage = renderer.finish_coverage_query(query);
+        assert_eq!(coverage.pixel_count, 16);
+        assert!((coverage.fraction() - 1.0).abs() < 0.01);
+
+        let query = renderer.begin_coverage_query(RectI::new(Vector2I::splat(SIZE),
+                                                             Vector2I::splat(4)));
+        assert_eq!(renderer.finish_coverage_query(query), Coverage::default());
+    }
+
+    // Creating the renderer loads the debug UI's resources, if it's enabled, and drawing with the
+    // filters loads theirs.
+    #[test]
+    #[ignore]
+    fn test_embedded_resources_cover_the_enabled_features() {
+        let (options, resources) = (RendererOptions::default(), EmbeddedResourceLoader::new());
+        let (mut renderer, _context) = create_renderer_with_resources(options, &resources);
+        let blur = Filter::Blur { direction: BlurDirection::X, sigma: 1.0 };
+        render(&mut renderer, &layer_scene(blur)).unwrap();
+        let text = Filter::Text {
+            fg_color: ColorF::black(),
+            bg_color: ColorF::white(),
+            defringing_kernel: None,
+            gamma_correction: true,
+        };
+        render(&mut renderer, &layer_scene(text)).unwrap();
+    }
+}
@@ -10,8 +10,8 @@
 
 use crate::gpu_data::FillBatchPrimitive;
 use pathfinder_content::fill::FillRule;
-use pathfinder_gpu::{BufferData, BufferTarget, BufferUploadMode, Device, VertexAttrClass};
-use pathfinder_gpu::{VertexAttrDescriptor, VertexAttrType};
+use pathfinder_gpu::{BufferData, BufferTarget, BufferUploadMode, Device, ShaderKind};
+use pathfinder_gpu::{VertexAttrClass, VertexAttrDescriptor, VertexAttrType};
 use pathfinder_resources::ResourceLoader;
 
 // TODO(pcwalton): Replace with `mem::size_of` calls?
@@ -218,11 +218,12 @@ impl<D> AlphaTileVertexArray<D> where D: Device {
 
         let tile_position_attr =
             device.get_vertex_attr(&alpha_tile_program.program, "TilePosition").unwrap();
-        let color_tex_coord_attr = device.get_vertex_attr(&alpha_tile_program.program,
-                                                          "ColorTexCoord").unwrap();
         let mask_tex_coord_attr = device.get_vertex_attr(&alpha_tile_program.program,
                                                          "MaskTexCoord").unwrap();
-        let opacity_attr = device.get_vertex_attr(&alpha_tile_program.program, "Opacity").unwrap();
+        // The clip stencil program reads neither of these, so drivers may optimize them out.
+        let color_tex_coord_attr = device.get_vertex_attr(&alpha_tile_program.program,
+                                                          "ColorTexCoord");
+        let opacity_attr = device.get_vertex_attr(&alpha_tile_program.program, "Opacity");
 
         device.bind_buffer(&vertex_array, alpha_tile_vertex_buffer, BufferTarget::Vertex);
         device.configure_vertex_attr(&vertex_array, &tile_position_attr, &VertexAttrDescriptor {
@@ -243,24 +244,30 @@ impl<D> AlphaTileVertexArray<D> where D: Device {
             divisor: 0,
             buffer_index: 0,
         });
-        device.configure_vertex_attr(&vertex_array, &color_tex_coord_attr, &VertexAttrDescriptor {
-            size: 2,
-            class: VertexAttrClass::Float,
-            attr_type: VertexAttrType::F32,
-            stride: ALPHA_TILE_VERTEX_SIZE,
-            offset: 8,
-            divisor: 0,
-            buffer_index: 0,
-        });
-        device.configure_vertex_attr(&vertex_array, &opacity_attr, &VertexAttrDescriptor {
-            size: 1,
-            class: VertexAttrClass::FloatNorm,
-            attr_type: VertexAttrType::U8,
-            stride: ALPHA_TILE_VERTEX_SIZE,
-            offset: 18,
-            divisor: 0,
-            buffer_index: 0,
-        });
+        if let Some(color_tex_coord_attr) = color_tex_coord_attr {
+            device.configure_vertex_attr(&vertex_array,
+                                         &color_tex_coord_attr,
+                                         &VertexAttrDescriptor {
+                size: 2,
+                class: VertexAttrClass::Float,
+                attr_type: VertexAttrType::F32,
+                stride: ALPHA_TILE_VERTEX_SIZE,
+                offset: 8,
+                divisor: 0,
+                buffer_index: 0,
+            });
+        }
+        if let Some(opacity_attr) = opacity_attr {
+            device.configure_vertex_attr(&vertex_array, &opacity_attr, &VertexAttrDescriptor {
+                size: 1,
+                class: VertexAttrClass::FloatNorm,
+                attr_type: VertexAttrType::U8,
+                stride: ALPHA_TILE_VERTEX_SIZE,
+                offset: 18,
+                divisor: 0,
+                buffer_index: 0,
+            });
+        }
         device.bind_buffer(&vertex_array, quads_vertex_indices_buffer, BufferTarget::Index);
 
         AlphaTileVertexArray { vertex_array }
@@ -358,13 +365,53 @@ pub struct BlitProgram<D> where D: Device {
 }
 
 impl<D> BlitProgram<D> where D: Device {
+    #[inline]
     pub fn new(device: &D, resources: &dyn ResourceLoader) -> BlitProgram<D> {
-        let program = device.create_program(resources, "blit");
+        BlitProgram::from_fragment_shader_name(device, resources, "blit")
+    }
+
+    fn from_fragment_shader_name(device: &D,
+                                 resources: &dyn ResourceLoader,
+                                 fragment_shader_name: &str)
+                                 -> BlitProgram<D> {
+        let program = device.create_program_from_shader_names(resources,
+                                                              fragment_shader_name,
+                                                              "blit",
+                                                              fragment_shader_name);
         let src_uniform = device.get_uniform(&program, "Src");
         BlitProgram { program, src_uniform }
     }
 }
 
+pub struct BlitSpotProgram<D> where D: Device {
+    pub blit_program: BlitProgram<D>,
+    pub spot_lut_uniform: D::Uniform,
+}
+
+impl<D> BlitSpotProgram<D> where D: Device {
+    pub fn new(device: &D, resources: &dyn ResourceLoader) -> BlitSpotProgram<D> {
+        let blit_program = BlitProgram::from_fragment_shader_name(device, resources, "blit_spot");
+        let spot_lut_uniform = device.get_uniform(&blit_program.program, "SpotLUT");
+        BlitSpotProgram { blit_program, spot_lut_uniform }
+    }
+}
+
+pub struct CoverageReduceProgram<D> where D: Device {
+    pub blit_program: BlitProgram<D>,
+    pub src_rect_uniform: D::Uniform,
+    pub src_is_stats_uniform: D::Uniform,
+}
+
+impl<D> CoverageReduceProgram<D> where D: Device {
+    pub fn new(device: &D, resources: &dyn ResourceLoader) -> CoverageReduceProgram<D> {
+        let blit_program =
+            BlitProgram::from_fragment_shader_name(device, resources, "coverage_reduce");
+        let src_rect_uniform = device.get_uniform(&blit_program.program, "SrcRect");
+        let src_is_stats_uniform = device.get_uniform(&blit_program.program, "SrcIsStats");
+        CoverageReduceProgram { blit_program, src_rect_uniform, src_is_stats_uniform }
+    }
+}
+
 pub struct FillProgram<D>
 where
     D: Device,
@@ -421,6 +468,7 @@ pub struct SolidTileProgram<D> where D: Device {
     pub transform_uniform: D::Uniform,
     pub tile_size_uniform: D::Uniform,
     pub color_texture_uniform: D::Uniform,
+    pub color_texture_premultiplied_uniform: D::Uniform,
 }
 
 impl<D> SolidTileProgram<D> where D: Device {
@@ -430,14 +478,21 @@ impl<D> SolidTileProgram<D> where D: Device {
                                                               program_name,
                                                               "tile_solid",
                                                               program_name);
+        SolidTileProgram::from_program(device, program)
+    }
+
+    fn from_program(device: &D, program: D::Program) -> SolidTileProgram<D> {
         let transform_uniform = device.get_uniform(&program, "Transform");
         let tile_size_uniform = device.get_uniform(&program, "TileSize");
         let color_texture_uniform = device.get_uniform(&program, "ColorTexture");
+        let color_texture_premultiplied_uniform =
+            device.get_uniform(&program, "ColorTexturePremultiplied");
         SolidTileProgram {
             program,
             transform_uniform,
             tile_size_uniform,
             color_texture_uniform,
+            color_texture_premultiplied_uniform,
         }
     }
 }
@@ -449,6 +504,7 @@ pub struct AlphaTileProgram<D> where D: Device {
     pub framebuffer_size_uniform: D::Uniform,
     pub stencil_texture_uniform: D::Uniform,
     pub paint_texture_uniform: D::Uniform,
+    pub paint_texture_premultiplied_uniform: D::Uniform,
 }
 
 impl<D> AlphaTileProgram<D> where D: Device {
@@ -457,6 +513,20 @@ impl<D> AlphaTileProgram<D> where D: Device {
         AlphaTileProgram::from_fragment_shader_name(device, resources, "tile_alpha")
     }
 
+    /// Creates a program that draws only where a tile's mask is at least half covered, for
+    /// writing clip paths to the stencil buffer.
+    #[inline]
+    pub fn new_clip_stencil(device: &D, resources: &dyn ResourceLoader) -> AlphaTileProgram<D> {
+        AlphaTileProgram::from_fragment_shader_name(device, resources, "tile_alpha_clip")
+    }
+
+    /// Creates a program whose output can be blended with advanced blend equations, for devices
+    /// that support them.
+    #[inline]
+    pub fn new_advanced(device: &D, resources: &dyn ResourceLoader) -> AlphaTileProgram<D> {
+        AlphaTileProgram::from_fragment_shader_name(device, resources, "tile_alpha_advanced")
+    }
+
     fn from_fragment_shader_name(device: &D,
                                  resources: &dyn ResourceLoader,
                                  fragment_shader_name: &str)
@@ -465,11 +535,17 @@ impl<D> AlphaTileProgram<D> where D: Device {
                                                               fragment_shader_name,
                                                               "tile_alpha",
                                                               fragment_shader_name);
+        AlphaTileProgram::from_program(device, program)
+    }
+
+    fn from_program(device: &D, program: D::Program) -> AlphaTileProgram<D> {
         let transform_uniform = device.get_uniform(&program, "Transform");
         let tile_size_uniform = device.get_uniform(&program, "TileSize");
         let framebuffer_size_uniform = device.get_uniform(&program, "FramebufferSize");
         let stencil_texture_uniform = device.get_uniform(&program, "StencilTexture");
         let paint_texture_uniform = device.get_uniform(&program, "PaintTexture");
+        let paint_texture_premultiplied_uniform =
+            device.get_uniform(&program, "PaintTexturePremultiplied");
         AlphaTileProgram {
             program,
             transform_uniform,
@@ -477,10 +553,33 @@ impl<D> AlphaTileProgram<D> where D: Device {
             framebuffer_size_uniform,
             stencil_texture_uniform,
             paint_texture_uniform,
+            paint_texture_premultiplied_uniform,
         }
     }
 }
 
+pub struct CustomPaintProgram<D> where D: Device {
+    pub alpha_tile_program: AlphaTileProgram<D>,
+    pub params_uniform: D::Uniform,
+}
+
+impl<D> CustomPaintProgram<D> where D: Device {
+    pub fn new(device: &D,
+               resources: &dyn ResourceLoader,
+               name: &str,
+               fragment_shader_source: &[u8])
+               -> CustomPaintProgram<D> {
+        let vertex_shader = device.create_shader(resources, "tile_alpha", ShaderKind::Vertex);
+        let fragment_shader =
+            device.create_shader_from_source(name, fragment_shader_source, ShaderKind::Fragment);
+        let program =
+            device.create_program_from_shaders(resources, name, vertex_shader, fragment_shader);
+        let alpha_tile_program = AlphaTileProgram::from_program(device, program);
+        let params_uniform = device.get_uniform(&alpha_tile_program.program, "Params");
+        CustomPaintProgram { alpha_tile_program, params_uniform }
+    }
+}
+
 pub struct CopyTileProgram<D> where D: Device {
     pub program: D::Program,
     pub transform_uniform: D::Uniform,
@@ -569,6 +668,67 @@ impl<D> AlphaTileDodgeBurnProgram<D> where D: Device {
     }
 }
 
+pub struct AlphaTilePorterDuffProgram<D> where D: Device {
+    pub alpha_tile_blend_mode_program: AlphaTileBlendModeProgram<D>,
+    pub porter_duff_op_uniform: D::Uniform,
+}
+
+impl<D> AlphaTilePorterDuffProgram<D> where D: Device {
+    pub fn new(device: &D, resources: &dyn ResourceLoader) -> AlphaTilePorterDuffProgram<D> {
+        let alpha_tile_blend_mode_program =
+            AlphaTileBlendModeProgram::new(device, resources, "tile_alpha_porterduff");
+        let porter_duff_op_uniform =
+            device.get_uniform(&alpha_tile_blend_mode_program.alpha_tile_program.program,
+                               "PorterDuffOp");
+        AlphaTilePorterDuffProgram { alpha_tile_blend_mode_program, porter_duff_op_uniform }
+    }
+}
+
+/// Applies the same operators as `AlphaTilePorterDuffProgram` with dual-source blending, without
+/// reading the destination. Only create this if the device supports dual-source blending.
+pub struct AlphaTileDualSourceProgram<D> where D: Device {
+    pub alpha_tile_program: AlphaTileProgram<D>,
+    pub porter_duff_op_uniform: D::Uniform,
+}
+
+impl<D> AlphaTileDualSourceProgram<D> where D: Device {
+    pub fn new(device: &D, resources: &dyn ResourceLoader) -> AlphaTileDualSourceProgram<D> {
+        let alpha_tile_program = AlphaTileProgram::from_fragment_shader_name(
+            device,
+            resources,
+            "tile_alpha_porterduff_dual");
+        let porter_duff_op_uniform = device.get_uniform(&alpha_tile_program.program,
+                                                        "PorterDuffOp");
+        AlphaTileDualSourceProgram { alpha_tile_program, porter_duff_op_uniform }
+    }
+}
+
+pub struct CustomFilterProgram<D> where D: Device {
+    pub solid_tile_program: SolidTileProgram<D>,
+    pub color_texture_size_uniform: D::Uniform,
+    pub params_uniform: D::Uniform,
+}
+
+impl<D> CustomFilterProgram<D> where D: Device {
+    pub fn new(device: &D,
+               resources: &dyn ResourceLoader,
+               name: &str,
+               fragment_shader_source: &[u8])
+               -> CustomFilterProgram<D> {
+        let vertex_shader = device.create_shader(resources, "tile_solid", ShaderKind::Vertex);
+        let fragment_shader =
+            device.create_shader_from_source(name, fragment_shader_source, ShaderKind::Fragment);
+        let program =
+            device.create_program_from_shaders(resources, name, vertex_shader, fragment_shader);
+        let solid_tile_program = SolidTileProgram::from_program(device, program);
+        let color_texture_size_uniform =
+            device.get_uniform(&solid_tile_program.program, "ColorTextureSize");
+        let params_uniform = device.get_uniform(&solid_tile_program.program, "Params");
+        CustomFilterProgram { solid_tile_program, color_texture_size_uniform, params_uniform }
+    }
+}
+
+#[cfg(feature = "pf-filter-blur")]
 pub struct SolidTileBlurFilterProgram<D> where D: Device {
     pub solid_tile_program: SolidTileProgram<D>,
     pub src_offset_scale_uniform: D::Uniform,
@@ -576,6 +736,7 @@ pub struct SolidTileBlurFilterProgram<D> where D: Device {
     pub support_uniform: D::Uniform,
 }
 
+#[cfg(feature = "pf-filter-blur")]
 impl<D> SolidTileBlurFilterProgram<D> where D: Device {
     pub fn new(device: &D, resources: &dyn ResourceLoader) -> SolidTileBlurFilterProgram<D> {
         let solid_tile_program = SolidTileProgram::new(device,
@@ -595,6 +756,7 @@ impl<D> SolidTileBlurFilterProgram<D> where D: Device {
     }
 }
 
+#[cfg(feature = "pf-filter-text")]
 pub struct SolidTileTextFilterProgram<D> where D: Device {
     pub solid_tile_program: SolidTileProgram<D>,
     pub kernel_uniform: D::Uniform,
@@ -604,6 +766,7 @@ pub struct SolidTileTextFilterProgram<D> where D: Device {
     pub bg_color_uniform: D::Uniform,
 }
 
+#[cfg(feature = "pf-filter-text")]
 impl<D> SolidTileTextFilterProgram<D> where D: Device {
     pub fn new(device: &D, resources: &dyn ResourceLoader) -> SolidTileTextFilterProgram<D> {
         let solid_tile_program = SolidTileProgram::new(device,
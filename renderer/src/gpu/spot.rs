@@ -0,0 +1,334 @@
+// pathfinder/renderer/src/gpu/spot.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Spot color (duotone, risograph, screen printing) previews.
+//!
+//! When a spot color palette is supplied in the renderer options, the final composited image is
+//! remapped through a 3D lookup table that separates every color into tints of a small set of
+//! inks printed on paper. The lookup table is computed on the CPU once per palette and applied on
+//! the GPU as part of the final blit.
+
+use pathfinder_color::ColorU;
+use pathfinder_geometry::util;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use std::f32;
+
+/// The number of samples along each edge of the color cube.
+///
+/// The lookup table is stored as a row of `SPOT_LUT_SIZE` slices along the blue axis, each of
+/// which is `SPOT_LUT_SIZE` texels square.
+pub(crate) const SPOT_LUT_SIZE: i32 = 16;
+
+// The number of tint steps to search when separating a color into two overprinted inks.
+const OVERPRINT_SEARCH_STEPS: u32 = 16;
+
+/// A small set of inks that all paints are remapped to at composite time.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SpotColorPalette {
+    /// The color of the unprinted substrate.
+    pub paper: ColorU,
+    /// The inks, in printing order.
+    pub inks: Vec<SpotInk>,
+}
+
+/// A single spot color ink.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SpotInk {
+    /// The color of the ink at full (100%) coverage on the paper.
+    pub color: ColorU,
+    /// Maps the ideal tint of this ink to the tint that actually ends up on the paper.
+    pub tint_curve: TintCurve,
+}
+
+/// A monotonic transfer curve from requested tint to printed tint, both in the range [0, 1].
+///
+/// The curve is piecewise linear between its control points. Points outside the range of the
+/// control points clamp to the nearest endpoint.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TintCurve {
+    points: Vec<Vector2F>,
+}
+
+impl SpotColorPalette {
+    #[inline]
+    pub fn new(paper: ColorU) -> SpotColorPalette {
+        SpotColorPalette { paper, inks: vec![] }
+    }
+
+    #[inline]
+    pub fn push_ink(&mut self, ink: SpotInk) {
+        self.inks.push(ink)
+    }
+
+    /// Returns a two-ink palette on white paper.
+    pub fn duotone(ink_0: ColorU, ink_1: ColorU) -> SpotColorPalette {
+        SpotColorPalette {
+            paper: ColorU::white(),
+            inks: vec![SpotInk::new(ink_0), SpotInk::new(ink_1)],
+        }
+    }
+
+    /// Builds the RGBA8 lookup table that the final blit samples.
+    pub(crate) fn build_lut(&self) -> Vec<ColorU> {
+        let paper = color_to_rgb(self.paper);
+        let inks: Vec<_> = self.inks.iter().map(|ink| InkFilter::new(paper, ink)).collect();
+
+        let length = SPOT_LUT_SIZE as usize;
+        let mut texels = vec![ColorU::transparent_black(); length * length * length];
+        for blue in 0..length {
+            for green in 0..length {
+                for red in 0..length {
+                    let target = [
+                        red as f32 / (length - 1) as f32,
+                        green as f32 / (length - 1) as f32,
+                        blue as f32 / (length - 1) as f32,
+                    ];
+                    let separated = separate(paper, &inks, target);
+                    texels[lut_index(red, green, blue)] = rgb_to_color(separated);
+                }
+            }
+        }
+        texels
+    }
+
+    #[inline]
+    pub(crate) fn lut_size() -> Vector2I {
+        Vector2I::new(SPOT_LUT_SIZE * SPOT_LUT_SIZE, SPOT_LUT_SIZE)
+    }
+}
+
+// Returns the index of the texel for a color in the lookup table. Green selects the row, and blue
+// selects the slice within it, matching the lookup in the blit shader.
+#[inline]
+fn lut_index(red: usize, green: usize, blue: usize) -> usize {
+    let length = SPOT_LUT_SIZE as usize;
+    green * length * length + blue * length + red
+}
+
+impl SpotInk {
+    #[inline]
+    pub fn new(color: ColorU) -> SpotInk {
+        SpotInk { color, tint_curve: TintCurve::identity() }
+    }
+
+    #[inline]
+    pub fn with_tint_curve(color: ColorU, tint_curve: TintCurve) -> SpotInk {
+        SpotInk { color, tint_curve }
+    }
+}
+
+impl TintCurve {
+    #[inline]
+    pub fn identity() -> TintCurve {
+        TintCurve { points: vec![Vector2F::new(0.0, 0.0), Vector2F::new(1.0, 1.0)] }
+    }
+
+    /// Creates a tint curve from control points, which are sorted by requested tint.
+    pub fn from_points(mut points: Vec<Vector2F>) -> TintCurve {
+        if points.is_empty() {
+            return TintCurve::identity();
+        }
+        points.sort_by(|a, b| a.x().partial_cmp(&b.x()).unwrap_or(std::cmp::Ordering::Equal));
+        TintCurve { points }
+    }
+
+    /// Creates a simple dot gain curve, which darkens midtones by `gain` (e.g. 0.15 for 15%).
+    pub fn dot_gain(gain: f32) -> TintCurve {
+        let midtone = util::clamp(0.5 + gain, 0.0, 1.0);
+        TintCurve::from_points(vec![
+            Vector2F::new(0.0, 0.0),
+            Vector2F::new(0.5, midtone),
+            Vector2F::new(1.0, 1.0),
+        ])
+    }
+
+    #[inline]
+    pub fn points(&self) -> &[Vector2F] {
+        &self.points
+    }
+
+    pub fn sample(&self, tint: f32) -> f32 {
+        let first = self.points[0];
+        if tint <= first.x() {
+            return util::clamp(first.y(), 0.0, 1.0);
+        }
+        for window in self.points.windows(2) {
+            let (from, to) = (window[0], window[1]);
+            if tint <= to.x() {
+                let span = to.x() - from.x();
+                let t = if span > 0.0 { (tint - from.x()) / span } else { 1.0 };
+                return util::clamp(util::lerp(from.y(), to.y(), t), 0.0, 1.0);
+            }
+        }
+        util::clamp(self.points[self.points.len() - 1].y(), 0.0, 1.0)
+    }
+}
+
+impl Default for TintCurve {
+    #[inline]
+    fn default() -> TintCurve {
+        TintCurve::identity()
+    }
+}
+
+// Inks are modeled as transparent filters over the paper: a tint `t` of an ink scales each channel
+// of the paper by `lerp(1, ink / paper, t)`. Overprinted inks multiply.
+struct InkFilter<'a> {
+    transmittance: [f32; 3],
+    tint_curve: &'a TintCurve,
+}
+
+impl<'a> InkFilter<'a> {
+    fn new(paper: [f32; 3], ink: &'a SpotInk) -> InkFilter<'a> {
+        let color = color_to_rgb(ink.color);
+        let mut transmittance = [0.0; 3];
+        for channel in 0..3 {
+            transmittance[channel] = if paper[channel] > 0.0 {
+                util::clamp(color[channel] / paper[channel], 0.0, 1.0)
+            } else {
+                1.0
+            };
+        }
+        InkFilter { transmittance, tint_curve: &ink.tint_curve }
+    }
+
+    #[inline]
+    fn filter(&self, tint: f32) -> [f32; 3] {
+        let mut result = [0.0; 3];
+        for (channel, value) in result.iter_mut().enumerate() {
+            *value = util::lerp(1.0, self.transmittance[channel], tint);
+        }
+        result
+    }
+
+    // Finds the tint of this ink alone that best reproduces `target` on `paper`.
+    fn solve_tint(&self, paper: [f32; 3], target: [f32; 3]) -> f32 {
+        let (mut numerator, mut denominator) = (0.0, 0.0);
+        for channel in 0..3 {
+            let full = paper[channel] * (1.0 - self.transmittance[channel]);
+            numerator += (paper[channel] - target[channel]) * full;
+            denominator += full * full;
+        }
+        if denominator <= f32::EPSILON {
+            0.0
+        } else {
+            util::clamp(numerator / denominator, 0.0, 1.0)
+        }
+    }
+}
+
+fn separate(paper: [f32; 3], inks: &[InkFilter], target: [f32; 3]) -> [f32; 3] {
+    let mut best = (paper, square_distance(paper, target));
+
+    // Single inks.
+    for ink in inks {
+        let tint = ink.solve_tint(paper, target);
+        let candidate = print(paper, &[(ink, tint)]);
+        let distance = square_distance(candidate, target);
+        if distance < best.1 {
+            best = (print_with_curves(paper, &[(ink, tint)]), distance);
+        }
+    }
+
+    // Pairs of overprinted inks.
+    for (index_0, ink_0) in inks.iter().enumerate() {
+        for ink_1 in &inks[(index_0 + 1)..] {
+            for step_0 in 1..OVERPRINT_SEARCH_STEPS {
+                let tint_0 = step_0 as f32 / OVERPRINT_SEARCH_STEPS as f32;
+                for step_1 in 1..OVERPRINT_SEARCH_STEPS {
+                    let tint_1 = step_1 as f32 / OVERPRINT_SEARCH_STEPS as f32;
+                    let layers = [(ink_0, tint_0), (ink_1, tint_1)];
+                    let distance = square_distance(print(paper, &layers), target);
+                    if distance < best.1 {
+                        best = (print_with_curves(paper, &layers), distance);
+                    }
+                }
+            }
+        }
+    }
+
+    best.0
+}
+
+fn print(paper: [f32; 3], layers: &[(&InkFilter, f32)]) -> [f32; 3] {
+    let mut result = paper;
+    for &(ink, tint) in layers {
+        let filter = ink.filter(tint);
+        for channel in 0..3 {
+            result[channel] *= filter[channel];
+        }
+    }
+    result
+}
+
+fn print_with_curves(paper: [f32; 3], layers: &[(&InkFilter, f32)]) -> [f32; 3] {
+    let mut result = paper;
+    for &(ink, tint) in layers {
+        let filter = ink.filter(ink.tint_curve.sample(tint));
+        for channel in 0..3 {
+            result[channel] *= filter[channel];
+        }
+    }
+    result
+}
+
+#[inline]
+fn square_distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let (dr, dg, db) = (a[0] - b[0], a[1] - b[1], a[2] - b[2]);
+    dr * dr + dg * dg + db * db
+}
+
+#[inline]
+fn color_to_rgb(color: ColorU) -> [f32; 3] {
+    let color = color.to_f32();
+    [color.r(), color.g(), color.b()]
+}
+
+#[inline]
+fn rgb_to_color(rgb: [f32; 3]) -> ColorU {
+    let channel = |value: f32| (util::clamp(value, 0.0, 1.0) * 255.0).round() as u8;
+    ColorU::new(channel(rgb[0]), channel(rgb[1]), channel(rgb[2]), 255)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SPOT_LUT_SIZE, SpotColorPalette, SpotInk, lut_index};
+    use pathfinder_color::ColorU;
+
+    #[test]
+    fn test_lut_layout() {
+        let length = SPOT_LUT_SIZE as usize;
+        let lut_size = SpotColorPalette::lut_size();
+        for green in 0..length {
+            for blue in 0..length {
+                for red in 0..length {
+                    let (x, y) = (blue * length + red, green);
+                    assert_eq!(lut_index(red, green, blue), y * lut_size.x() as usize + x);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lut_places_colors_by_channel() {
+        // Pure red prints as the ink at full tint. Pure green and pure blue are equally far from
+        // the ink, so they come out as the same half tint.
+        let mut palette = SpotColorPalette::new(ColorU::white());
+        palette.push_ink(SpotInk::new(ColorU::new(255, 0, 0, 255)));
+        let lut = palette.build_lut();
+        assert_eq!(lut.len(), (SPOT_LUT_SIZE * SPOT_LUT_SIZE * SPOT_LUT_SIZE) as usize);
+
+        let max = SPOT_LUT_SIZE as usize - 1;
+        assert_eq!(lut[lut_index(max, 0, 0)], ColorU::new(255, 0, 0, 255));
+        assert_eq!(lut[lut_index(0, max, 0)], ColorU::new(255, 128, 128, 255));
+        assert_eq!(lut[lut_index(0, 0, max)], ColorU::new(255, 128, 128, 255));
+        assert_eq!(lut[lut_index(max, max, max)], ColorU::white());
+    }
+}
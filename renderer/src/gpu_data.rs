@@ -11,15 +11,19 @@
 //! Packed data ready to be sent to the GPU.
 
 use crate::options::BoundingQuad;
+use crate::paint::CustomPaintShaderId;
 use pathfinder_color::ColorU;
 use pathfinder_content::effects::{BlendMode, Effects};
 use pathfinder_content::fill::FillRule;
+use pathfinder_content::pattern::ExternalTextureId;
 use pathfinder_content::render_target::RenderTargetId;
 use pathfinder_geometry::line_segment::{LineSegmentU4, LineSegmentU8};
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::vector::Vector2I;
-use pathfinder_gpu::TextureSamplingFlags;
+use pathfinder_gpu::{TextureFormat, TextureSamplingFlags};
 use std::fmt::{Debug, Formatter, Result as DebugResult};
+use std::ops::Range;
+use std::sync::Arc;
 use std::time::Duration;
 
 pub enum RenderCommand {
@@ -36,15 +40,39 @@ pub enum RenderCommand {
         /// This is needed if a path that renders directly to the output framebuffer (i.e. not to a
         /// render target) uses one of the more exotic blend modes.
         needs_readable_framebuffer: bool,
+
+        /// The corner of the chunk, in tiles, that tile positions in this frame are relative to.
+        ///
+        /// Tiles are drawn at their position plus this origin.
+        chunk_origin: Vector2I,
     },
 
+    // Directs the commands that follow to a layer of the destination, such as one eye's view in
+    // a stereo build. The layer is cleared to the background color when it's first drawn to, and
+    // `bounding_quad` takes the place of the one given at the start.
+    BeginLayer { layer: u32, bounding_quad: BoundingQuad },
+
     // Allocates texture pages for the frame.
     AllocateTexturePages(Vec<TexturePageDescriptor>),
 
-    // Uploads data to a texture page.
-    UploadTexelData { texels: Vec<ColorU>, location: TextureLocation },
+    // Uploads data to a texture page. `texels` covers all of `location`, and `id` identifies
+    // them. `alpha_type` is how the texels store alpha, which must match the page's.
+    //
+    // If `changes` is set and the page still holds the texels it names, only the rows it lists
+    // differ from those, so only they need uploading.
+    UploadTexelData {
+        texels: Arc<Vec<ColorU>>,
+        location: TextureLocation,
+        id: TexelsId,
+        changes: Option<TexelChanges>,
+        alpha_type: AlphaType,
+    },
+
+    // Uploads block-compressed data to a texture page, in the page's format.
+    UploadCompressedTexelData { data: Arc<Vec<u8>>, location: TextureLocation },
 
-    // Associates a render target with a texture page.
+    // Associates a render target with a texture page. Render targets hold premultiplied colors,
+    // so the page must be premultiplied.
     //
     // TODO(pcwalton): Add a rect to this so we can render to subrects of a page.
     DeclareRenderTarget { id: RenderTargetId, location: TextureLocation },
@@ -65,6 +93,14 @@ pub enum RenderCommand {
     // Pops a render target from the stack.
     PopRenderTarget,
 
+    // Replaces the stencil clip with the area that these tiles cover, as given by their masks.
+    // Only pixels at least half covered are inside the clip. Batches of alpha tiles marked as
+    // stencil clipped draw only inside it.
+    //
+    // This draws to the destination framebuffer's stencil buffer, so it's only sent when no render
+    // target is pushed.
+    DrawClipStencil(Vec<AlphaTile>),
+
     // Draws a batch of alpha tiles to the render target on top of the stack.
     DrawAlphaTiles(AlphaTileBatch),
 
@@ -72,15 +108,46 @@ pub enum RenderCommand {
     DrawSolidTiles(SolidTileBatch),
 
     // Presents a rendered frame.
-    Finish { build_time: Duration },
+    Finish { build_time: Duration, culled_path_count: usize },
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct TexturePageId(pub u32);
 
+/// Identifies the texels uploaded to a texture page by an `UploadTexelData` command.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TexelsId(pub u64);
+
+/// The rows of a texture page that differ from texels uploaded earlier.
+#[derive(Clone, Debug)]
+pub struct TexelChanges {
+    /// The earlier texels.
+    pub since: TexelsId,
+    /// The ranges of rows, relative to the top of the uploaded rectangle, that differ.
+    pub rows: Vec<Range<u32>>,
+}
+
 #[derive(Clone, Debug)]
 pub struct TexturePageDescriptor {
     pub size: Vector2I,
+    pub format: TextureFormat,
+    // If set, the page is an application-supplied texture rather than one the renderer allocates.
+    pub external_texture: Option<ExternalTextureId>,
+    /// How the colors in the page store alpha.
+    pub alpha_type: AlphaType,
+}
+
+/// How the color channels of a texture relate to its alpha channel.
+///
+/// Paints are uploaded with straight alpha, since that's how `Image`s and colors store it, but
+/// render targets are drawn into, so they hold premultiplied colors like any framebuffer. Shaders
+/// that sample a texture convert its colors according to the page's alpha type.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AlphaType {
+    /// The color channels are independent of alpha.
+    Straight,
+    /// The color channels have been multiplied by alpha.
+    Premultiplied,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -93,8 +160,24 @@ pub struct TextureLocation {
 pub struct AlphaTileBatch {
     pub tiles: Vec<AlphaTile>,
     pub color_texture_page: TexturePageId,
+    /// If set, the tiles are drawn with this custom paint shader instead of sampling the paint
+    /// texture.
+    pub custom_paint: Option<CustomPaintParams>,
     pub blend_mode: BlendMode,
     pub sampling_flags: TextureSamplingFlags,
+    /// How the batch expects the paint texture to store alpha.
+    pub color_texture_alpha_type: AlphaType,
+    /// A rectangular clip to apply while drawing, in pixels.
+    pub scissor: Option<RectI>,
+    /// Whether to draw only inside the area of the last `DrawClipStencil` command.
+    pub stencil_clipped: bool,
+}
+
+/// The custom paint shader that draws a batch, and the parameters to pass to it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CustomPaintParams {
+    pub shader: CustomPaintShaderId,
+    pub params: [f32; 4],
 }
 
 #[derive(Clone, Debug)]
@@ -102,6 +185,8 @@ pub struct SolidTileBatch {
     pub tiles: Vec<SolidTile>,
     pub color_texture_page: TexturePageId,
     pub sampling_flags: TextureSamplingFlags,
+    /// How the batch expects the color texture to store alpha.
+    pub color_texture_alpha_type: AlphaType,
     pub effects: Effects,
 }
 
@@ -118,7 +203,9 @@ pub struct FillObjectPrimitive {
 pub struct TileObjectPrimitive {
     /// If `u16::MAX`, then this is a solid tile.
     pub alpha_tile_index: u16,
-    pub backdrop: i8,
+    /// The winding number at the tile's left edge. This is clamped to the range of an `i16` when
+    /// mask tiles are built.
+    pub backdrop: i32,
 }
 
 // FIXME(pcwalton): Move `subpx` before `px` and remove `repr(packed)`.
@@ -191,15 +278,57 @@ pub struct AlphaTileVertex {
     pub pad: u8,
 }
 
+impl RenderCommand {
+    #[cfg(feature = "pf-tracing")]
+    pub(crate) fn name(&self) -> &'static str {
+        match *self {
+            RenderCommand::Start { .. } => "Start",
+            RenderCommand::BeginLayer { .. } => "BeginLayer",
+            RenderCommand::AllocateTexturePages(_) => "AllocateTexturePages",
+            RenderCommand::UploadTexelData { .. } => "UploadTexelData",
+            RenderCommand::UploadCompressedTexelData { .. } => "UploadCompressedTexelData",
+            RenderCommand::DeclareRenderTarget { .. } => "DeclareRenderTarget",
+            RenderCommand::AddFills(_) => "AddFills",
+            RenderCommand::FlushFills => "FlushFills",
+            RenderCommand::RenderMaskTiles { .. } => "RenderMaskTiles",
+            RenderCommand::PushRenderTarget(_) => "PushRenderTarget",
+            RenderCommand::PopRenderTarget => "PopRenderTarget",
+            RenderCommand::DrawClipStencil(_) => "DrawClipStencil",
+            RenderCommand::DrawAlphaTiles(_) => "DrawAlphaTiles",
+            RenderCommand::DrawSolidTiles(_) => "DrawSolidTiles",
+            RenderCommand::Finish { .. } => "Finish",
+        }
+    }
+}
+
 impl Debug for RenderCommand {
     fn fmt(&self, formatter: &mut Formatter) -> DebugResult {
         match *self {
             RenderCommand::Start { .. } => write!(formatter, "Start"),
+            RenderCommand::BeginLayer { layer, .. } => write!(formatter, "BeginLayer({})", layer),
             RenderCommand::AllocateTexturePages(ref pages) => {
                 write!(formatter, "AllocateTexturePages(x{})", pages.len())
             }
-            RenderCommand::UploadTexelData { ref texels, location } => {
-                write!(formatter, "UploadTexelData({:?}, {:?})", texels, location)
+            RenderCommand::UploadTexelData {
+                ref texels,
+                location,
+                id,
+                ref changes,
+                alpha_type,
+            } => {
+                write!(formatter,
+                       "UploadTexelData({:?}, {:?}, {:?}, {:?}, {:?})",
+                       texels,
+                       location,
+                       id,
+                       changes,
+                       alpha_type)
+            }
+            RenderCommand::UploadCompressedTexelData { ref data, location } => {
+                write!(formatter,
+                       "UploadCompressedTexelData({} bytes, {:?})",
+                       data.len(),
+                       location)
             }
             RenderCommand::DeclareRenderTarget { id, location } => {
                 write!(formatter, "DeclareRenderTarget({:?}, {:?})", id, location)
@@ -213,13 +342,18 @@ impl Debug for RenderCommand {
                 write!(formatter, "PushRenderTarget({:?})", render_target_id)
             }
             RenderCommand::PopRenderTarget => write!(formatter, "PopRenderTarget"),
+            RenderCommand::DrawClipStencil(ref tiles) => {
+                write!(formatter, "DrawClipStencil(x{})", tiles.len())
+            }
             RenderCommand::DrawAlphaTiles(ref batch) => {
                 write!(formatter,
-                       "DrawAlphaTiles(x{}, {:?}, {:?}, {:?})",
+                       "DrawAlphaTiles(x{}, {:?}, {:?}, {:?}, {:?}{})",
                        batch.tiles.len(),
                        batch.color_texture_page,
                        batch.blend_mode,
-                       batch.sampling_flags)
+                       batch.sampling_flags,
+                       batch.scissor,
+                       if batch.stencil_clipped { ", stencil clipped" } else { "" })
             }
             RenderCommand::DrawSolidTiles(ref batch) => {
                 write!(formatter,
@@ -15,15 +15,34 @@ extern crate bitflags;
 #[macro_use]
 extern crate log;
 
+// Enters a `tracing` span that lasts until the end of the enclosing block if the `pf-tracing`
+// feature is enabled, and does nothing otherwise.
+#[cfg(feature = "pf-tracing")]
+macro_rules! trace_span {
+    ($name:expr $(, $field:ident = $value:expr)*) => {
+        let _span = tracing::trace_span!($name $(, $field = $value)*).entered();
+    };
+}
+#[cfg(not(feature = "pf-tracing"))]
+macro_rules! trace_span {
+    ($name:expr $(, $field:ident = $value:expr)*) => {};
+}
+
 pub mod concurrent;
+pub mod cpu;
+pub mod error;
 pub mod gpu;
 pub mod gpu_data;
 pub mod options;
 pub mod paint;
+pub mod picture;
 pub mod scene;
 
 mod allocator;
 mod builder;
+mod rtree;
 mod tile_map;
 mod tiles;
 mod z_buffer;
+
+pub use crate::error::Error;
@@ -10,12 +10,15 @@
 
 //! Options that control how rendering is to be performed.
 
+use crate::gpu::shaders::MAX_FILLS_PER_BATCH;
 use crate::gpu_data::RenderCommand;
 use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
-use pathfinder_geometry::transform3d::Perspective;
-use pathfinder_geometry::vector::{Vector2F, Vector4F};
+use pathfinder_geometry::transform3d::{Perspective, Transform4F};
+use pathfinder_geometry::vector::{Vector2F, Vector2I, Vector4F};
 use pathfinder_content::clip::PolygonClipper3D;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub trait RenderCommandListener: Send + Sync {
     fn send(&self, command: RenderCommand);
@@ -32,11 +35,81 @@ where
 }
 
 /// Options that influence scene building.
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct BuildOptions {
     pub transform: RenderTransform,
     pub dilation: Vector2F,
     pub subpixel_aa_enabled: bool,
+    /// The most fills that a single `AddFills` command may carry. Paths that generate more fills
+    /// than this send them in several commands as they're tiled, instead of all at once.
+    ///
+    /// The default matches the GPU renderer's fill batch size, so that each command is uploaded
+    /// in one go. Use `usize::MAX` to send each path's fills in one command.
+    pub max_fills_per_command: usize,
+    /// Whether paths may be drawn in an earlier alpha tile batch than the last one, as long as
+    /// they don't overlap anything drawn in between. This takes more work to build, but scenes
+    /// that alternate between paints or blend modes need far fewer draw calls.
+    pub merge_batches: bool,
+    /// Whether clip paths are applied with the destination framebuffer's stencil buffer instead
+    /// of by intersecting masks.
+    ///
+    /// This suits embedders that already manage stencil state, and it takes the pressure off the
+    /// mask texture when many paths share a clip. The destination framebuffer must have a stencil
+    /// buffer of at least 8 bits, and the renderer must draw to it directly, so spot color
+    /// rendering can't be used. Paths drawn to render targets, and all paths in scenes that need a
+    /// readable framebuffer, still use masks. Stencil clips have aliased edges.
+    pub stencil_clipping: bool,
+    /// Whether the renderer's device supports dual-source blending, as reported by
+    /// `Device::supports_dual_source_blending()`.
+    ///
+    /// Paths with Porter-Duff blend modes such as `BlendMode::DestIn` are then blended in
+    /// hardware, so, unlike with other blend modes that the hardware can't do, they can share
+    /// batches and don't make the scene need a readable framebuffer. Leave this off if the device
+    /// doesn't support it: the renderer reads the destination to blend these paths instead.
+    pub dual_source_blending: bool,
+    /// Whether the renderer's device supports advanced blend equations, as reported by
+    /// `Device::supports_advanced_blending()`.
+    ///
+    /// Paths with separable and non-separable blend modes, such as `BlendMode::Multiply` and
+    /// `BlendMode::Hue`, are then blended in hardware and likewise neither break batches nor make
+    /// the scene need a readable framebuffer. The renderer falls back to reading the destination
+    /// if the device doesn't support them.
+    pub advanced_blending: bool,
+    /// Whether the render commands must be the same every time the scene is built.
+    ///
+    /// Paths are tiled in parallel, and by default they take alpha and mask tiles in whatever
+    /// order the worker threads get to them, so the commands, and occasionally the pixels where
+    /// paths tie, vary between builds. In deterministic builds, tiles are numbered within each
+    /// path and renumbered in path order once tiling is done, and fills are sent in path order.
+    /// This suits golden image tests, at the cost of holding on to every path's fills until all
+    /// of them have been tiled.
+    /// Texel uploads still differ, since each is identified so that the next build can upload
+    /// only what changed.
+    pub deterministic: bool,
+    /// A token that abandons the build when cancelled. See `CancellationToken`.
+    pub cancellation_token: Option<CancellationToken>,
+    /// If set, only the part of the view box within this rectangle is built, for redrawing part
+    /// of a frame whose other pixels are kept. Like the view box, it's in pixels.
+    pub clip_rect: Option<RectF>,
+}
+
+impl Default for BuildOptions {
+    #[inline]
+    fn default() -> BuildOptions {
+        BuildOptions {
+            transform: RenderTransform::default(),
+            dilation: Vector2F::default(),
+            subpixel_aa_enabled: false,
+            max_fills_per_command: MAX_FILLS_PER_BATCH,
+            merge_batches: false,
+            stencil_clipping: false,
+            dual_source_blending: false,
+            advanced_blending: false,
+            deterministic: false,
+            cancellation_token: None,
+            clip_rect: None,
+        }
+    }
 }
 
 impl BuildOptions {
@@ -45,13 +118,82 @@ impl BuildOptions {
             transform: self.transform.prepare(bounds),
             dilation: self.dilation,
             subpixel_aa_enabled: self.subpixel_aa_enabled,
+            max_fills_per_command: self.max_fills_per_command.max(1),
+            merge_batches: self.merge_batches,
+            stencil_clipping: self.stencil_clipping,
+            dual_source_blending: self.dual_source_blending,
+            advanced_blending: self.advanced_blending,
+            deterministic: self.deterministic,
+            cancellation_token: self.cancellation_token,
+            clip_rect: self.clip_rect,
         }
     }
+
+    pub(crate) fn prepare_viewport(&self, viewport: &Viewport, bounds: RectF)
+                                   -> PreparedBuildOptions {
+        let clip_rect = match self.clip_rect {
+            None => viewport.rect,
+            Some(clip_rect) => {
+                clip_rect.intersection(viewport.rect).unwrap_or(RectF::new(viewport.rect.origin(),
+                                                                           Vector2F::default()))
+            }
+        };
+        BuildOptions {
+            transform: RenderTransform::Transform2D(viewport.transform),
+            clip_rect: Some(clip_rect),
+            ..self.clone()
+        }.prepare(bounds)
+    }
+}
+
+/// One of several views of a scene built into the same frame by `Scene::build_viewports()`, such
+/// as the main view and a minimap, or the panes of a 4-up editor.
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport {
+    /// How scene coordinates map to device pixels in this viewport. It takes the place of
+    /// `BuildOptions::transform`.
+    pub transform: Transform2F,
+    /// The part of the destination that the viewport covers, in device pixels. Paths are clipped
+    /// to it as well as to the scene's view box and `BuildOptions::clip_rect`.
+    pub rect: RectF,
 }
 
+/// Abandons a build that's no longer wanted, such as a frame superseded by a newer one while the
+/// user is zooming.
+///
+/// Pass a clone of the token in `BuildOptions`, and cancel it from any thread. Paths that haven't
+/// been tiled yet are skipped, and the build stops before sending any tiles, returning
+/// `Error::Cancelled`. Fills sent before then should be discarded along with the rest of the
+/// frame's commands.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    #[inline]
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    #[inline]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How scene coordinates map to device pixels.
 #[derive(Clone)]
 pub enum RenderTransform {
+    /// An affine transform, applied before tiling.
     Transform2D(Transform2F),
+    /// A projective transform, such as for a tilted map view or a panel in a VR scene.
+    ///
+    /// Paths are clipped to the view frustum and projected before tiling, with curves subdivided
+    /// so that they stay accurate under the projection. Subpixel antialiasing is unsupported.
     Perspective(Perspective),
 }
 
@@ -63,6 +205,13 @@ impl Default for RenderTransform {
 }
 
 impl RenderTransform {
+    /// A projective transform from scene coordinates to clip space, followed by the viewport
+    /// transform to a window of the given size.
+    #[inline]
+    pub fn perspective(transform: &Transform4F, window_size: Vector2I) -> RenderTransform {
+        RenderTransform::Perspective(Perspective::new(transform, window_size))
+    }
+
     fn prepare(&self, bounds: RectF) -> PreparedRenderTransform {
         let perspective = match self {
             RenderTransform::Transform2D(ref transform) => {
@@ -118,6 +267,14 @@ pub(crate) struct PreparedBuildOptions {
     pub(crate) transform: PreparedRenderTransform,
     pub(crate) dilation: Vector2F,
     pub(crate) subpixel_aa_enabled: bool,
+    pub(crate) max_fills_per_command: usize,
+    pub(crate) merge_batches: bool,
+    pub(crate) stencil_clipping: bool,
+    pub(crate) dual_source_blending: bool,
+    pub(crate) advanced_blending: bool,
+    pub(crate) deterministic: bool,
+    pub(crate) cancellation_token: Option<CancellationToken>,
+    pub(crate) clip_rect: Option<RectF>,
 }
 
 impl PreparedBuildOptions {
@@ -128,6 +285,34 @@ impl PreparedBuildOptions {
             _ => [Vector4F::default(); 4],
         }
     }
+
+    /// The factor by which the transform scales scene coordinates on their way to pixels, used to
+    /// pick each path's level of detail. Perspective transforms scale different parts of the
+    /// scene differently, so they get `None` and full detail.
+    pub(crate) fn lod_scale(&self) -> Option<f32> {
+        match self.transform {
+            PreparedRenderTransform::None => Some(1.0),
+            PreparedRenderTransform::Transform2D(ref transform) => {
+                Some(transform.matrix.det().abs().sqrt())
+            }
+            PreparedRenderTransform::Perspective { .. } => None,
+        }
+    }
+
+    /// The transform from the scene to the space that paths are tiled in, which is stretched
+    /// horizontally when subpixel AA is enabled, or `None` if the transform is a perspective one.
+    /// It has to match `Scene::apply_render_options()`.
+    pub(crate) fn tiling_transform_2d(&self) -> Option<Transform2F> {
+        let mut transform = match self.transform {
+            PreparedRenderTransform::None => Transform2F::default(),
+            PreparedRenderTransform::Transform2D(transform) => transform,
+            PreparedRenderTransform::Perspective { .. } => return None,
+        };
+        if self.subpixel_aa_enabled {
+            transform *= Transform2F::from_scale(Vector2F::new(3.0, 1.0))
+        }
+        Some(transform)
+    }
 }
 
 pub(crate) type BoundingQuad = [Vector4F; 4];
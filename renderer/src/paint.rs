@@ -8,22 +8,30 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::allocator::{AllocationMode, TextureAllocator};
-use crate::gpu_data::{RenderCommand, TextureLocation, TexturePageDescriptor, TexturePageId};
+use crate::allocator::{self, AllocationMode, TextureAllocator};
+use crate::error::Error;
+use crate::gpu_data::{AlphaType, CustomPaintParams, RenderCommand, TexelChanges, TexelsId};
+use crate::gpu_data::{TextureLocation, TexturePageDescriptor, TexturePageId};
 use crate::scene::RenderTarget;
 use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
 use hashbrown::HashMap;
 use pathfinder_color::ColorU;
 use pathfinder_content::gradient::{Gradient, GradientGeometry};
-use pathfinder_content::pattern::{Image, Pattern, PatternFlags, PatternSource};
+use pathfinder_content::pattern::{CompressedImageFormat, Image, Pattern, PatternFlags};
+use pathfinder_content::pattern::PatternSource;
 use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_geometry::line_segment::LineSegment2F;
 use pathfinder_geometry::rect::{RectF, RectI};
 use pathfinder_geometry::transform2d::{Matrix2x2F, Transform2F};
 use pathfinder_geometry::util;
 use pathfinder_geometry::vector::{Vector2F, Vector2I};
-use pathfinder_gpu::TextureSamplingFlags;
-use pathfinder_simd::default::{F32x2, F32x4};
+use pathfinder_gpu::{TextureFormat, TextureSamplingFlags};
+use pathfinder_simd::default::{self, F32x4, F32x8, WideVectors};
 use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 // The size of a gradient tile.
 //
@@ -38,6 +46,44 @@ pub struct Palette {
     pub(crate) paints: Vec<Paint>,
     pub(crate) render_targets: Vec<RenderTarget>,
     cache: HashMap<Paint, PaintId>,
+    atlas_cache: PaintAtlasCache,
+}
+
+/// Keeps the atlas locations of images and radial gradients from build to build.
+///
+/// Each build allocates space only for the paints that the scene's paths use. When the atlases are
+/// full, the paints that have gone unused the longest are evicted to make room, and an atlas page
+/// is added only once there's nothing left to evict. Solid colors are allocated afresh for each
+/// build, as are paints that get pages of their own.
+///
+/// It also keeps the texels last uploaded to each page, so that when only some paints change,
+/// as when a gradient's stops are edited, the next build can upload just the rows that differ.
+#[derive(Default)]
+struct PaintAtlasCache(Mutex<PaintAtlasCacheData>);
+
+#[derive(Default)]
+struct PaintAtlasCacheData {
+    // Atlas pages come first, so that they keep their IDs when the image pages after them are
+    // freed at the start of the next build.
+    allocator: TextureAllocator,
+    // Indexed by paint ID.
+    entries: Vec<Option<CachedPaintLocation>>,
+    // Space in the atlases allocated for the current build only.
+    temporary_locations: Vec<TextureLocation>,
+    // Indexed by page ID.
+    uploaded_texels: Vec<Option<UploadedTexels>>,
+    build_index: u64,
+}
+
+struct CachedPaintLocation {
+    location: TextureLocation,
+    last_used: u64,
+}
+
+struct UploadedTexels {
+    id: TexelsId,
+    texels: Arc<Vec<ColorU>>,
+    size: Vector2I,
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -45,11 +91,32 @@ pub enum Paint {
     Color(ColorU),
     Gradient(Gradient),
     Pattern(Pattern),
+    Custom(CustomPaint),
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct PaintId(pub u16);
 
+/// A paint computed by a fragment shader that the embedder supplies, for procedural fills such as
+/// hatching or animated water that can't be baked into a texture.
+///
+/// The shader must be registered with `Renderer::register_custom_paint_shader()`, which describes
+/// what it's given. Paths with custom paints are always drawn as alpha tiles, and tiles that share
+/// a shader and parameters are batched together. The CPU renderer and the exporters don't run
+/// custom shaders, so these paints draw nothing there.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CustomPaint {
+    pub shader: CustomPaintShaderId,
+    /// Maps the paint's coordinate space, which the shader receives positions in, to the scene.
+    pub transform: Transform2F,
+    /// Passed to the shader as the `uParams` uniform.
+    pub params: [f32; 4],
+}
+
+/// A custom paint shader registered with a renderer.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CustomPaintShaderId(pub u32);
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct GradientId(pub u32);
 
@@ -62,6 +129,21 @@ impl Debug for Paint {
                 write!(formatter, "(gradient)")
             }
             Paint::Pattern(ref pattern) => pattern.fmt(formatter),
+            Paint::Custom(ref custom_paint) => custom_paint.fmt(formatter),
+        }
+    }
+}
+
+impl Eq for CustomPaint {}
+
+impl Hash for CustomPaint {
+    fn hash<H>(&self, state: &mut H) where H: Hasher {
+        self.shader.hash(state);
+        let matrix = self.transform.matrix;
+        let vector = self.transform.vector;
+        for value in [matrix.m11(), matrix.m12(), matrix.m21(), matrix.m22(), vector.x(),
+                      vector.y()].iter().chain(self.params.iter()) {
+            value.to_bits().hash(state);
         }
     }
 }
@@ -69,7 +151,12 @@ impl Debug for Paint {
 impl Palette {
     #[inline]
     pub fn new() -> Palette {
-        Palette { paints: vec![], render_targets: vec![], cache: HashMap::new() }
+        Palette {
+            paints: vec![],
+            render_targets: vec![],
+            cache: HashMap::new(),
+            atlas_cache: PaintAtlasCache::default(),
+        }
     }
 }
 
@@ -91,6 +178,8 @@ impl Paint {
                 gradient.stops().iter().all(|stop| stop.color.is_opaque())
             }
             Paint::Pattern(ref pattern) => pattern.source.is_opaque(),
+            // We can't tell what the shader will output.
+            Paint::Custom(_) => false,
         }
     }
 
@@ -100,7 +189,7 @@ impl Paint {
             Paint::Gradient(ref gradient) => {
                 gradient.stops().iter().all(|stop| stop.color.is_fully_transparent())
             }
-            Paint::Pattern(_) => {
+            Paint::Pattern(_) | Paint::Custom(_) => {
                 // TODO(pcwalton): Should we support this?
                 false
             }
@@ -111,7 +200,7 @@ impl Paint {
     pub fn is_color(&self) -> bool {
         match *self {
             Paint::Color(_) => true,
-            Paint::Gradient(_) | Paint::Pattern(_) => false,
+            Paint::Gradient(_) | Paint::Pattern(_) | Paint::Custom(_) => false,
         }
     }
 
@@ -146,6 +235,9 @@ impl Paint {
                 }
             }
             Paint::Pattern(ref mut pattern) => pattern.transform = *transform * pattern.transform,
+            Paint::Custom(ref mut custom_paint) => {
+                custom_paint.transform = *transform * custom_paint.transform
+            }
         }
     }
 }
@@ -173,6 +265,11 @@ pub struct PaintMetadata {
     pub sampling_flags: TextureSamplingFlags,
     /// True if this paint is fully opaque.
     pub is_opaque: bool,
+    /// How the paint's texture stores alpha: premultiplied for render targets, and straight for
+    /// everything else.
+    pub alpha_type: AlphaType,
+    /// The shader and parameters that draw this paint, if it's a custom paint.
+    pub custom_paint: Option<CustomPaintParams>,
 }
 
 #[derive(Debug)]
@@ -200,85 +297,157 @@ impl Palette {
         id
     }
 
-    pub fn build_paint_info(&self, view_box_size: Vector2I) -> PaintInfo {
-        let mut allocator = TextureAllocator::new();
-        let (mut paint_metadata, mut render_target_metadata) = (vec![], vec![]);
+    /// Builds the textures for the paints, allocating space only for those that `used_paints`,
+    /// indexed by paint ID, marks as used.
+    pub fn build_paint_info(&self, view_box_size: Vector2I, used_paints: &[bool])
+                            -> Result<PaintInfo, Error> {
+        trace_span!("build_paint_info");
+        let mut atlas_cache = self.atlas_cache.0.lock().unwrap();
+        let atlas_cache = &mut *atlas_cache;
+        atlas_cache.begin_build();
+        let is_used = |paint_index: usize| used_paints.get(paint_index).cloned().unwrap_or(false);
+
+        // Assign atlas locations first, so that no atlas page is added after an image page.
+        let mut locations = vec![None; self.paints.len()];
+        let mut solid_color_tile_builder = SolidColorTileBuilder::new();
+        for (paint_index, paint) in self.paints.iter().enumerate() {
+            if !is_used(paint_index) {
+                continue;
+            }
+            locations[paint_index] = match *paint {
+                // Custom paint shaders draw the paint, but the tiles still need somewhere in a
+                // texture page to take their UVs from. The texel is left transparent.
+                Paint::Color(_) | Paint::Custom(_) => {
+                    Some(solid_color_tile_builder.allocate(atlas_cache)?)
+                }
+                Paint::Gradient(Gradient { geometry: GradientGeometry::Radial { .. }, .. }) => {
+                    // TODO(pcwalton): Optimize this:
+                    // 1. Use repeating/clamp on the sides.
+                    // 2. Choose an optimal size for the gradient that minimizes memory usage while
+                    //    retaining quality.
+                    let size = Vector2I::splat(GRADIENT_TILE_LENGTH as i32);
+                    Some(atlas_cache.allocate_cached(paint_index, size)?)
+                }
+                // TODO(pcwalton): We should be able to use tile cleverness to repeat inside the
+                // atlas in some cases.
+                Paint::Pattern(Pattern { source: PatternSource::Image(ref image), flags, .. })
+                        if flags == PatternFlags::empty() &&
+                        allocator::fits_in_atlas(image.size()) => {
+                    Some(atlas_cache.allocate_cached(paint_index, image.size())?)
+                }
+                _ => None,
+            };
+        }
 
         // Assign render target locations.
+        let allocator = &mut atlas_cache.allocator;
+        let mut render_target_metadata = vec![];
         for render_target in &self.render_targets {
             render_target_metadata.push(RenderTargetMetadata {
-                location: allocator.allocate_image(render_target.size()),
+                location: allocator.allocate_image(render_target.size())?,
             });
         }
 
-        // Assign paint locations.
-        let mut solid_color_tile_builder = SolidColorTileBuilder::new();
+        // Assign the rest of the paint locations, and build the metadata.
+        let mut paint_metadata = vec![];
         let mut gradient_tile_builder = GradientTileBuilder::new();
-        for paint in &self.paints {
-            let (texture_location, mut sampling_flags);
-            match paint {
-                Paint::Color(_) => {
-                    texture_location = solid_color_tile_builder.allocate(&mut allocator);
-                    sampling_flags = TextureSamplingFlags::empty();
-                }
-                Paint::Gradient(Gradient { geometry: GradientGeometry::Linear(_), .. }) => {
-                    // FIXME(pcwalton): The gradient size might not be big enough. Detect this.
-                    texture_location = gradient_tile_builder.allocate(&mut allocator);
-                    sampling_flags = TextureSamplingFlags::empty();
+        for (paint_index, paint) in self.paints.iter().enumerate() {
+            let texture_location = match locations[paint_index] {
+                Some(location) => location,
+                None if !is_used(paint_index) => {
+                    // No path refers to this paint, so it needs no space.
+                    TextureLocation { page: TexturePageId(0), rect: RectI::default() }
                 }
-                Paint::Gradient(Gradient { geometry: GradientGeometry::Radial { .. }, .. }) => {
-                    // TODO(pcwalton): Optimize this:
-                    // 1. Use repeating/clamp on the sides.
-                    // 2. Choose an optimal size for the gradient that minimizes memory usage while
-                    //    retaining quality.
-                    texture_location =
-                        allocator.allocate(Vector2I::splat(GRADIENT_TILE_LENGTH as i32),
-                                           AllocationMode::Atlas);
-                    sampling_flags = TextureSamplingFlags::empty();
-                }
-                Paint::Pattern(ref pattern) => {
-                    match pattern.source {
-                        PatternSource::RenderTarget(render_target_id) => {
-                            texture_location =
-                                render_target_metadata[render_target_id.0 as usize].location;
+                None => {
+                    match *paint {
+                        Paint::Gradient(Gradient {
+                            geometry: GradientGeometry::Linear(_),
+                            ..
+                        }) => {
+                            // FIXME(pcwalton): The gradient size might not be big enough. Detect
+                            // this.
+                            gradient_tile_builder.allocate(allocator)?
                         }
-                        PatternSource::Image(ref image) => {
-                            // TODO(pcwalton): We should be able to use tile cleverness to repeat
-                            // inside the atlas in some cases.
-                            let allocation_mode = if pattern.flags == PatternFlags::empty() {
-                                AllocationMode::Atlas
-                            } else {
-                                AllocationMode::OwnPage
-                            };
-
-                            texture_location = allocator.allocate(image.size(), allocation_mode);
+                        Paint::Pattern(ref pattern) => {
+                            match pattern.source {
+                                PatternSource::RenderTarget(render_target_id) => {
+                                    match render_target_metadata
+                                            .get(render_target_id.0 as usize) {
+                                        Some(metadata) => metadata.location,
+                                        None => {
+                                            return Err(Error::NoSuchRenderTarget(
+                                                render_target_id))
+                                        }
+                                    }
+                                }
+                                // Images with flags, or too big for an atlas, get pages of their
+                                // own.
+                                PatternSource::Image(ref image) => {
+                                    allocator.allocate(image.size(), AllocationMode::OwnPage)?
+                                }
+                                // Compressed data can't be blitted into an atlas, so it always
+                                // gets a page of its own in its own format.
+                                PatternSource::CompressedImage(ref image) => {
+                                    allocator.allocate_image(image.size())?
+                                }
+                                // The renderer substitutes the external texture for this page.
+                                PatternSource::External(ref external_texture) => {
+                                    allocator.allocate_image(external_texture.size)?
+                                }
+                            }
+                        }
+                        Paint::Color(_) | Paint::Gradient(_) | Paint::Custom(_) => {
+                            unreachable!()
                         }
-                    }
-
-                    sampling_flags = TextureSamplingFlags::empty();
-                    if pattern.flags.contains(PatternFlags::REPEAT_X) {
-                        sampling_flags.insert(TextureSamplingFlags::REPEAT_U);
-                    }
-                    if pattern.flags.contains(PatternFlags::REPEAT_Y) {
-                        sampling_flags.insert(TextureSamplingFlags::REPEAT_V);
-                    }
-                    if pattern.flags.contains(PatternFlags::NO_SMOOTHING) {
-                        sampling_flags.insert(TextureSamplingFlags::NEAREST_MIN |
-                                              TextureSamplingFlags::NEAREST_MAG);
                     }
                 }
             };
 
+            let mut sampling_flags = TextureSamplingFlags::empty();
+            if let Paint::Pattern(ref pattern) = *paint {
+                if pattern.flags.contains(PatternFlags::REPEAT_X) {
+                    sampling_flags.insert(TextureSamplingFlags::REPEAT_U);
+                }
+                if pattern.flags.contains(PatternFlags::REPEAT_Y) {
+                    sampling_flags.insert(TextureSamplingFlags::REPEAT_V);
+                }
+                if pattern.flags.contains(PatternFlags::NO_SMOOTHING) {
+                    sampling_flags.insert(TextureSamplingFlags::NEAREST_MIN |
+                                          TextureSamplingFlags::NEAREST_MAG);
+                } else if pattern.flags.contains(PatternFlags::ANISOTROPIC) {
+                    sampling_flags.insert(TextureSamplingFlags::ANISOTROPY_16X);
+                }
+            }
+
             paint_metadata.push(PaintMetadata {
                 location: texture_location,
                 texture_transform: Transform2F::default(),
                 sampling_flags,
                 is_opaque: paint.is_opaque(),
+                alpha_type: match *paint {
+                    Paint::Pattern(Pattern { source: PatternSource::RenderTarget(_), .. }) => {
+                        AlphaType::Premultiplied
+                    }
+                    _ => AlphaType::Straight,
+                },
+                custom_paint: match *paint {
+                    Paint::Custom(ref custom_paint) => {
+                        Some(CustomPaintParams {
+                            shader: custom_paint.shader,
+                            params: custom_paint.params,
+                        })
+                    }
+                    _ => None,
+                },
             });
         }
 
         // Calculate texture transforms.
-        for (paint, metadata) in self.paints.iter().zip(paint_metadata.iter_mut()) {
+        for (paint_index, (paint, metadata)) in
+                self.paints.iter().zip(paint_metadata.iter_mut()).enumerate() {
+            if !is_used(paint_index) {
+                continue;
+            }
             let texture_scale = allocator.page_scale(metadata.location.page);
             metadata.texture_transform = match paint {
                 Paint::Color(_) => {
@@ -304,7 +473,13 @@ impl Palette {
                     Transform2F::from_translation(texture_origin_uv) *
                         Transform2F::from_scale(gradient_tile_scale / view_box_size.to_f32())
                 }
-                Paint::Pattern(Pattern { source: PatternSource::Image(_), transform, .. }) => {
+                Paint::Pattern(Pattern { source: PatternSource::Image(_), transform, .. }) |
+                Paint::Pattern(Pattern {
+                    source: PatternSource::CompressedImage(_),
+                    transform,
+                    ..
+                }) |
+                Paint::Pattern(Pattern { source: PatternSource::External(_), transform, .. }) => {
                     let texture_origin_uv =
                         rect_to_uv(metadata.location.rect, texture_scale).origin();
                     Transform2F::from_translation(texture_origin_uv) *
@@ -323,6 +498,8 @@ impl Palette {
                         Transform2F::from_scale(texture_scale.scale_xy(Vector2F::new(1.0, -1.0))) *
                         transform.inverse()
                 }
+                // Custom paint shaders get positions in paint space in place of UVs.
+                Paint::Custom(ref custom_paint) => custom_paint.transform.inverse(),
             }
         }
 
@@ -330,7 +507,41 @@ impl Palette {
         let mut texture_page_descriptors = vec![];
         for page_index in 0..allocator.page_count() {
             let page_size = allocator.page_size(TexturePageId(page_index));
-            texture_page_descriptors.push(TexturePageDescriptor { size: page_size });
+            texture_page_descriptors.push(TexturePageDescriptor {
+                size: page_size,
+                format: TextureFormat::RGBA8,
+                external_texture: None,
+                alpha_type: AlphaType::Straight,
+            });
+        }
+        for metadata in &render_target_metadata {
+            let descriptor = &mut texture_page_descriptors[metadata.location.page.0 as usize];
+            descriptor.alpha_type = AlphaType::Premultiplied;
+        }
+
+        // Compressed images' pages take the image's format, and external textures' pages are
+        // supplied by the application.
+        for (paint_index, (paint, metadata)) in
+                self.paints.iter().zip(paint_metadata.iter()).enumerate() {
+            if !is_used(paint_index) {
+                continue;
+            }
+            let descriptor = &mut texture_page_descriptors[metadata.location.page.0 as usize];
+            match *paint {
+                Paint::Pattern(Pattern {
+                    source: PatternSource::CompressedImage(ref image),
+                    ..
+                }) => {
+                    descriptor.format = image.format().to_texture_format();
+                }
+                Paint::Pattern(Pattern {
+                    source: PatternSource::External(ref external_texture),
+                    ..
+                }) => {
+                    descriptor.external_texture = Some(external_texture.id);
+                }
+                _ => {}
+            }
         }
 
         // Allocate the texels.
@@ -344,7 +555,11 @@ impl Palette {
         // Draw to texels.
         //
         // TODO(pcwalton): Do more of this on GPU.
-        for (paint, metadata) in self.paints.iter().zip(paint_metadata.iter()) {
+        for (paint_index, (paint, metadata)) in
+                self.paints.iter().zip(paint_metadata.iter()).enumerate() {
+            if !is_used(paint_index) {
+                continue;
+            }
             let texture_page = metadata.location.page;
             let texels = &mut page_texels[texture_page.0 as usize];
 
@@ -353,19 +568,26 @@ impl Palette {
                     texels.put_texel(metadata.location.rect.origin(), *color);
                 }
                 Paint::Gradient(ref gradient) => {
+                    // Gradients are evaluated in scene space, where their geometry lives.
+                    let page_size = allocator.page_size(texture_page).to_f32();
+                    let texel_to_scene = (Transform2F::from_scale(page_size) *
+                                          metadata.texture_transform).inverse();
                     self.render_gradient(gradient,
                                          metadata.location.rect,
-                                         &metadata.texture_transform,
+                                         &texel_to_scene,
                                          texels);
                 }
                 Paint::Pattern(ref pattern) => {
                     match pattern.source {
-                        PatternSource::RenderTarget(_) => {}
+                        PatternSource::RenderTarget(_) |
+                        PatternSource::CompressedImage(_) |
+                        PatternSource::External(_) => {}
                         PatternSource::Image(ref image) => {
                             self.render_image(image, metadata.location.rect, texels);
                         }
                     }
                 }
+                Paint::Custom(_) => {}
             }
         }
 
@@ -380,26 +602,67 @@ impl Palette {
                 location: metadata.location,
             });
         }
+        atlas_cache.uploaded_texels.resize_with(page_texels.len(), || None);
+        atlas_cache.uploaded_texels.truncate(page_texels.len());
         for (page_index, texels) in page_texels.into_iter().enumerate() {
-            if let Some(texel_data) = texels.data {
-                let page_id = TexturePageId(page_index as u32);
-                let page_size = allocator.page_size(page_id);
-                let rect = RectI::new(Vector2I::default(), page_size);
-                render_commands.push(RenderCommand::UploadTexelData {
-                    texels: texel_data,
-                    location: TextureLocation { page: page_id, rect },
+            let uploaded_texels = &mut atlas_cache.uploaded_texels[page_index];
+            let texel_data = match texels.data {
+                Some(texel_data) => Arc::new(texel_data),
+                None => {
+                    *uploaded_texels = None;
+                    continue;
+                }
+            };
+            let page_id = TexturePageId(page_index as u32);
+            let page_size = allocator.page_size(page_id);
+            let rect = RectI::new(Vector2I::default(), page_size);
+            let changes = match *uploaded_texels {
+                Some(ref uploaded) if uploaded.size == page_size => {
+                    Some(TexelChanges {
+                        since: uploaded.id,
+                        rows: changed_rows(&uploaded.texels, &texel_data, page_size.x()),
+                    })
+                }
+                _ => None,
+            };
+            let id = next_texels_id();
+            *uploaded_texels = Some(UploadedTexels {
+                id,
+                texels: texel_data.clone(),
+                size: page_size,
+            });
+            render_commands.push(RenderCommand::UploadTexelData {
+                texels: texel_data,
+                location: TextureLocation { page: page_id, rect },
+                id,
+                changes,
+                alpha_type: AlphaType::Straight,
+            });
+        }
+
+        // Compressed images are uploaded as is.
+        for (paint_index, (paint, metadata)) in
+                self.paints.iter().zip(paint_metadata.iter()).enumerate() {
+            if !is_used(paint_index) {
+                continue;
+            }
+            if let Paint::Pattern(Pattern { source: PatternSource::CompressedImage(ref image), .. })
+                    = *paint {
+                render_commands.push(RenderCommand::UploadCompressedTexelData {
+                    data: image.data().clone(),
+                    location: metadata.location,
                 });
             }
         }
 
-        PaintInfo { render_commands, paint_metadata, render_target_metadata }
+        Ok(PaintInfo { render_commands, paint_metadata, render_target_metadata })
     }
 
     // TODO(pcwalton): This is slow. Do on GPU instead.
     fn render_gradient(&self,
                        gradient: &Gradient,
                        tex_rect: RectI,
-                       tex_transform: &Transform2F,
+                       texel_to_scene: &Transform2F,
                        texels: &mut Texels) {
         match *gradient.geometry() {
             GradientGeometry::Linear(_) => {
@@ -415,10 +678,6 @@ impl Palette {
             }
 
             GradientGeometry::Radial { line, start_radius: r0, end_radius: r1 } => {
-                // FIXME(pcwalton): Paint transparent if line has zero size and radii are equal,
-                // per spec.
-                let line = *tex_transform * line;
-
                 // This is based on Pixman (MIT license). Copy and pasting the excellent comment
                 // from there:
 
@@ -502,34 +761,32 @@ impl Palette {
                 //   <=> for every p, the radiuses associated with the two t solutions
                 //       have opposite sign
 
-                let cd = line.vector();
-                let dr = r1 - r0;
-                let a = cd.square_length() - dr * dr;
-                let a_inv = 1.0 / a;
+                // Like HTML canvas and SVG, we extend the gradient in both directions, so t isn't
+                // limited to [0,1]; values outside it take the color of the nearest end. The
+                // degenerate cases follow the canvas specification too: if A = 0, the equation is
+                // linear, and if the circles are identical, nothing is painted.
+
+                let params = RadialGradientParams::new(line, r0, r1);
+                if let Some(wide_vectors) = default::wide_vectors_accelerated() {
+                    unsafe {
+                        render_radial_gradient_wide(wide_vectors,
+                                                    gradient,
+                                                    &params,
+                                                    tex_rect,
+                                                    texel_to_scene,
+                                                    texels);
+                    }
+                    return;
+                }
 
                 for y in 0..(GRADIENT_TILE_LENGTH as i32) {
                     for x in 0..(GRADIENT_TILE_LENGTH as i32) {
                         let point = tex_rect.origin() + Vector2I::new(x, y);
-                        let point_f = point.to_f32();
-                        let pd = point_f - line.from();
-
-                        let b = pd.dot(cd) + r0 * dr;
-                        let c = pd.square_length() - r0 * r0;
-                        let discrim = b * b - a * c;
-
-                        let mut color = ColorU::transparent_black();
-                        if !util::approx_eq(discrim, 0.0) {
-                            let discrim_sqrt = f32::sqrt(discrim);
-                            let discrim_sqrts = F32x2::new(discrim_sqrt, -discrim_sqrt);
-                            let ts = (discrim_sqrts + F32x2::splat(b)) * F32x2::splat(a_inv);
-                            let t_min = f32::min(ts.x(), ts.y());
-                            let t_max = f32::max(ts.x(), ts.y());
-                            let t = if t_max <= 1.0 { t_max } else { t_min };
-                            if t >= 0.0 {
-                                color = gradient.sample(t);
-                            }
+                        let scene_point = *texel_to_scene * (point.to_f32() + Vector2F::splat(0.5));
+                        let color = match params.t_at_point(scene_point) {
+                            Some(t) => gradient.sample(t),
+                            None => ColorU::transparent_black(),
                         };
-
                         texels.put_texel(point, color);
                     }
                 }
@@ -558,6 +815,106 @@ impl PaintMetadata {
     }
 }
 
+// The per-gradient constants of the radial gradient equation. See `render_gradient()` for the
+// derivation.
+struct RadialGradientParams {
+    line: LineSegment2F,
+    cd: Vector2F,
+    r0: f32,
+    dr: f32,
+    a: f32,
+    a_inv: f32,
+    // Whether A is zero, making the equation linear.
+    linear: bool,
+}
+
+impl RadialGradientParams {
+    fn new(line: LineSegment2F, r0: f32, r1: f32) -> RadialGradientParams {
+        let cd = line.vector();
+        let dr = r1 - r0;
+        let a = cd.square_length() - dr * dr;
+
+        // Treat A as zero if it's tiny relative to the terms it's the difference of.
+        let linear = f32::abs(a) <= util::EPSILON * (cd.square_length() + dr * dr);
+
+        RadialGradientParams { line, cd, r0, dr, a, a_inv: 1.0 / a, linear }
+    }
+
+    fn t_at_point(&self, point: Vector2F) -> Option<f32> {
+        let pd = point - self.line.from();
+        let b = pd.dot(self.cd) + self.r0 * self.dr;
+        let c = pd.square_length() - self.r0 * self.r0;
+        self.solve(b, c, f32::sqrt(b * b - self.a * c))
+    }
+
+    // Returns the value of t for a point, given B, C, and the square root of the discriminant
+    // (NaN if it's negative), or `None` if the point lies on no circle with a nonnegative radius.
+    fn solve(&self, b: f32, c: f32, discrim_sqrt: f32) -> Option<f32> {
+        let t = if self.linear {
+            // The equation is -2Bt + C = 0. B is also zero if the circles are identical.
+            if b == 0.0 {
+                return None;
+            }
+            c / (2.0 * b)
+        } else {
+            let t0 = (b + discrim_sqrt) * self.a_inv;
+            let t1 = (b - discrim_sqrt) * self.a_inv;
+            let (t_min, t_max) = (f32::min(t0, t1), f32::max(t0, t1));
+            if self.r0 + t_max * self.dr >= 0.0 { t_max } else { t_min }
+        };
+
+        // This also fails if the discriminant was negative.
+        if self.r0 + t * self.dr >= 0.0 { Some(t) } else { None }
+    }
+}
+
+// Renders a radial gradient eight texels at a time.
+//
+// The `WideVectors` token proves that the CPU supports the features enabled here.
+#[cfg_attr(any(target_arch = "x86", target_arch = "x86_64"), target_feature(enable = "avx2"))]
+unsafe fn render_radial_gradient_wide(wide: WideVectors,
+                                      gradient: &Gradient,
+                                      params: &RadialGradientParams,
+                                      tex_rect: RectI,
+                                      texel_to_scene: &Transform2F,
+                                      texels: &mut Texels) {
+    let (cd_x, cd_y) = (F32x8::splat(wide, params.cd.x()), F32x8::splat(wide, params.cd.y()));
+    let (r0, dr) = (params.r0, params.dr);
+    let a = F32x8::splat(wide, params.a);
+    let lane_offsets = F32x8::new(wide, 0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0);
+
+    // How far one texel to the right moves in scene space.
+    let step = texel_to_scene.matrix * Vector2F::new(1.0, 0.0);
+    let (step_x, step_y) = (F32x8::splat(wide, step.x()), F32x8::splat(wide, step.y()));
+
+    let mut colors = [ColorU::transparent_black(); 8];
+    for y in 0..(GRADIENT_TILE_LENGTH as i32) {
+        let row_origin = tex_rect.origin() + Vector2I::new(0, y);
+        let row_pd = *texel_to_scene * (row_origin.to_f32() + Vector2F::splat(0.5)) -
+            params.line.from();
+        let (row_pd_x, row_pd_y) =
+            (F32x8::splat(wide, row_pd.x()), F32x8::splat(wide, row_pd.y()));
+        for x in (0..(GRADIENT_TILE_LENGTH as i32)).step_by(8) {
+            let offsets = F32x8::splat(wide, x as f32) + lane_offsets;
+            let pd_x = row_pd_x + offsets * step_x;
+            let pd_y = row_pd_y + offsets * step_y;
+
+            let b = pd_x * cd_x + pd_y * cd_y + F32x8::splat(wide, r0 * dr);
+            let c = pd_x * pd_x + pd_y * pd_y - F32x8::splat(wide, r0 * r0);
+            let discrim_sqrt = (b * b - a * c).sqrt();
+
+            for (lane, color) in colors.iter_mut().enumerate() {
+                *color = match params.solve(b[lane], c[lane], discrim_sqrt[lane]) {
+                    Some(t) => gradient.sample(t),
+                    None => ColorU::transparent_black(),
+                };
+            }
+
+            texels.blit_scanline(row_origin + Vector2I::new(x, 0), &colors);
+        }
+    }
+}
+
 struct Texels {
     data: Option<Vec<ColorU>>,
     size: Vector2I,
@@ -591,6 +948,106 @@ impl Texels {
     }
 }
 
+impl PaintAtlasCacheData {
+    fn begin_build(&mut self) {
+        self.build_index += 1;
+        for location in self.temporary_locations.drain(..) {
+            self.allocator.free(location);
+        }
+        self.allocator.free_images();
+    }
+
+    // Returns the paint's location from an earlier build if it still has one, and allocates one
+    // otherwise.
+    fn allocate_cached(&mut self, paint_index: usize, size: Vector2I)
+                       -> Result<TextureLocation, Error> {
+        if self.entries.len() <= paint_index {
+            self.entries.resize_with(paint_index + 1, || None);
+        }
+        if let Some(ref mut entry) = self.entries[paint_index] {
+            entry.last_used = self.build_index;
+            return Ok(entry.location);
+        }
+        let location = self.allocate_in_atlas(size)?;
+        let last_used = self.build_index;
+        self.entries[paint_index] = Some(CachedPaintLocation { location, last_used });
+        Ok(location)
+    }
+
+    // Allocates space that's freed at the start of the next build.
+    fn allocate_temporary(&mut self, size: Vector2I) -> Result<TextureLocation, Error> {
+        let location = self.allocate_in_atlas(size)?;
+        self.temporary_locations.push(location);
+        Ok(location)
+    }
+
+    fn allocate_in_atlas(&mut self, size: Vector2I) -> Result<TextureLocation, Error> {
+        loop {
+            if let Some(location) = self.allocator.allocate_in_existing_atlas(size) {
+                return Ok(location);
+            }
+            if !self.evict_least_recently_used() {
+                return self.allocator.allocate(size, AllocationMode::Atlas);
+            }
+        }
+    }
+
+    // Frees the location of the paint that has gone unused the longest. Returns false if every
+    // cached paint is used by the current build.
+    fn evict_least_recently_used(&mut self) -> bool {
+        let build_index = self.build_index;
+        let least_recently_used = self.entries.iter().enumerate().filter_map(|(index, entry)| {
+            match *entry {
+                Some(ref entry) if entry.last_used < build_index => Some((index, entry.last_used)),
+                _ => None,
+            }
+        }).min_by_key(|&(_, last_used)| last_used);
+        match least_recently_used {
+            Some((index, _)) => {
+                let entry = self.entries[index].take().unwrap();
+                self.allocator.free(entry.location);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Clone for PaintAtlasCache {
+    // Clones start out with no cached locations.
+    #[inline]
+    fn clone(&self) -> PaintAtlasCache {
+        PaintAtlasCache::default()
+    }
+}
+
+// IDs are unique across palettes, since scenes may take turns rendering with the same renderer.
+fn next_texels_id() -> TexelsId {
+    static NEXT_TEXELS_ID: AtomicU64 = AtomicU64::new(0);
+    TexelsId(NEXT_TEXELS_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+// Returns the ranges of rows that differ between two images of the same size.
+fn changed_rows(old_texels: &[ColorU], new_texels: &[ColorU], width: i32) -> Vec<Range<u32>> {
+    let mut rows: Vec<Range<u32>> = vec![];
+    if width <= 0 {
+        return rows;
+    }
+    let old_rows = old_texels.chunks(width as usize);
+    let new_rows = new_texels.chunks(width as usize);
+    for (row_index, (old_row, new_row)) in old_rows.zip(new_rows).enumerate() {
+        if old_row == new_row {
+            continue;
+        }
+        let row_index = row_index as u32;
+        match rows.last_mut() {
+            Some(range) if range.end == row_index => range.end += 1,
+            _ => rows.push(row_index..(row_index + 1)),
+        }
+    }
+    rows
+}
+
 fn rect_to_uv(rect: RectI, texture_scale: Vector2F) -> RectF {
     rect.to_f32().scale_xy(texture_scale)
 }
@@ -613,12 +1070,12 @@ impl SolidColorTileBuilder {
         SolidColorTileBuilder(None)
     }
 
-    fn allocate(&mut self, allocator: &mut TextureAllocator) -> TextureLocation {
+    fn allocate(&mut self, atlas_cache: &mut PaintAtlasCacheData)
+                -> Result<TextureLocation, Error> {
         if self.0.is_none() {
-            // TODO(pcwalton): Handle allocation failure gracefully!
+            let size = Vector2I::splat(SOLID_COLOR_TILE_LENGTH as i32);
             self.0 = Some(SolidColorTileBuilderData {
-                tile_location: allocator.allocate(Vector2I::splat(SOLID_COLOR_TILE_LENGTH as i32),
-                                                  AllocationMode::Atlas),
+                tile_location: atlas_cache.allocate_temporary(size)?,
                 next_index: 0,
             });
         }
@@ -641,7 +1098,7 @@ impl SolidColorTileBuilder {
             self.0 = None;
         }
 
-        location
+        Ok(location)
     }
 }
 
@@ -659,11 +1116,11 @@ impl GradientTileBuilder {
         GradientTileBuilder(None)
     }
 
-    fn allocate(&mut self, allocator: &mut TextureAllocator) -> TextureLocation {
+    fn allocate(&mut self, allocator: &mut TextureAllocator) -> Result<TextureLocation, Error> {
         if self.0.is_none() {
             let size = Vector2I::splat(GRADIENT_TILE_LENGTH as i32);
             self.0 = Some(GradientTileBuilderData {
-                page: allocator.allocate(size, AllocationMode::OwnPage).page,
+                page: allocator.allocate(size, AllocationMode::OwnPage)?.page,
                 next_index: 0,
             })
         }
@@ -684,6 +1141,217 @@ impl GradientTileBuilder {
             self.0 = None;
         }
 
-        location
+        Ok(location)
+    }
+}
+
+trait CompressedImageFormatExt {
+    fn to_texture_format(self) -> TextureFormat;
+}
+
+impl CompressedImageFormatExt for CompressedImageFormat {
+    fn to_texture_format(self) -> TextureFormat {
+        match self {
+            CompressedImageFormat::BC1 => TextureFormat::BC1,
+            CompressedImageFormat::BC3 => TextureFormat::BC3,
+            CompressedImageFormat::BC7 => TextureFormat::BC7,
+            CompressedImageFormat::ETC2RGBA8 => TextureFormat::ETC2RGBA8,
+            CompressedImageFormat::ASTC4x4 => TextureFormat::ASTC4x4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Paint, Palette, RadialGradientParams, changed_rows};
+    use crate::gpu_data::{RenderCommand, TextureLocation};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::pattern::{ExternalTexture, ExternalTextureId, Image, Pattern};
+    use pathfinder_content::pattern::{PatternFlags, PatternSource};
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::util;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+    use pathfinder_gpu::TextureSamplingFlags;
+
+    fn radial_params(from: Vector2F, r0: f32, to: Vector2F, r1: f32) -> RadialGradientParams {
+        RadialGradientParams::new(LineSegment2F::new(from, to), r0, r1)
+    }
+
+    fn assert_t(params: &RadialGradientParams, point: Vector2F, expected: f32) {
+        let t = params.t_at_point(point).unwrap();
+        assert!(util::approx_eq(t, expected), "expected t = {}, got {}", expected, t);
+    }
+
+    #[test]
+    fn test_radial_gradient_extends_past_circles() {
+        let params = radial_params(Vector2F::default(), 5.0, Vector2F::default(), 15.0);
+        assert_t(&params, Vector2F::new(10.0, 0.0), 0.5);
+        assert_t(&params, Vector2F::new(0.0, 25.0), 2.0);
+        assert_t(&params, Vector2F::new(0.0, 0.0), -0.5);
+    }
+
+    #[test]
+    fn test_radial_gradient_with_distinct_centers() {
+        // The start circle lies inside the end circle, away from its center.
+        let params = radial_params(Vector2F::new(2.0, 0.0), 1.0, Vector2F::new(0.0, 0.0), 10.0);
+        assert_t(&params, Vector2F::new(3.0, 0.0), 0.0);
+        assert_t(&params, Vector2F::new(-10.0, 0.0), 1.0);
+        assert_t(&params, Vector2F::new(0.0, 10.0), 1.0);
+
+        // Disjoint circles form a cone; points outside it aren't painted.
+        let params = radial_params(Vector2F::new(0.0, 0.0), 1.0, Vector2F::new(10.0, 0.0), 2.0);
+        assert_t(&params, Vector2F::new(12.0, 0.0), 13.0 / 9.0);
+        assert_t(&params, Vector2F::new(-3.0, 0.0), -2.0 / 9.0);
+        assert!(params.t_at_point(Vector2F::new(0.0, 20.0)).is_none());
+    }
+
+    #[test]
+    fn test_radial_gradient_degenerate_cases() {
+        // If the start circle touches the end circle from inside, the equation is linear, and
+        // only the half-plane in front of the focal point is painted.
+        let params = radial_params(Vector2F::default(), 0.0, Vector2F::new(10.0, 0.0), 10.0);
+        assert_t(&params, Vector2F::new(5.0, 0.0), 0.25);
+        assert!(params.t_at_point(Vector2F::new(-5.0, 0.0)).is_none());
+
+        // Identical circles paint nothing.
+        let params = radial_params(Vector2F::new(3.0, 4.0), 5.0, Vector2F::new(3.0, 4.0), 5.0);
+        assert!(params.t_at_point(Vector2F::new(3.0, 9.0)).is_none());
+        assert!(params.t_at_point(Vector2F::new(50.0, 50.0)).is_none());
+    }
+
+    #[test]
+    fn test_atlas_locations_are_kept_and_evicted_when_unused() {
+        // Four of these fill an atlas page.
+        let mut palette = Palette::new();
+        for index in 0..5 {
+            let pixels = vec![ColorU::new(index, 0, 0, 255); 512 * 512];
+            let source = PatternSource::Image(Image::new(Vector2I::splat(512), pixels));
+            let pattern = Pattern::new(source, Transform2F::default(), PatternFlags::empty());
+            palette.push_paint(&Paint::Pattern(pattern));
+        }
+
+        // Returns the location of each paint and the number of pages.
+        let build = |used_paints: &[bool]| {
+            let paint_info = palette.build_paint_info(Vector2I::splat(100), used_paints).unwrap();
+            let page_count = match paint_info.render_commands[0] {
+                RenderCommand::AllocateTexturePages(ref descriptors) => descriptors.len(),
+                _ => unreachable!(),
+            };
+            let locations: Vec<TextureLocation> =
+                paint_info.paint_metadata.iter().map(|metadata| metadata.location).collect();
+            (locations, page_count)
+        };
+
+        let (first_locations, page_count) = build(&[true, true, true, true, false]);
+        assert_eq!(page_count, 1);
+        let (locations, _) = build(&[true, true, true, true, false]);
+        assert_eq!(locations[0..4], first_locations[0..4]);
+
+        // The paint that has gone unused makes room for the new one.
+        let (locations, page_count) = build(&[false, true, true, true, true]);
+        assert_eq!(page_count, 1);
+        assert_eq!(locations[4], first_locations[0]);
+        assert_eq!(locations[1..4], first_locations[1..4]);
+
+        // Once every paint is in use, another page is added.
+        let (_, page_count) = build(&[true; 5]);
+        assert_eq!(page_count, 2);
+    }
+
+    #[test]
+    fn test_only_changed_rows_are_uploaded_again() {
+        let mut palette = Palette::new();
+        for &color in &[ColorU::white(), ColorU::black()] {
+            let source = PatternSource::Image(Image::new(Vector2I::splat(8), vec![color; 64]));
+            let pattern = Pattern::new(source, Transform2F::default(), PatternFlags::empty());
+            palette.push_paint(&Paint::Pattern(pattern));
+        }
+
+        let mut uploads = vec![];
+        for used_paints in &[[true, false], [true, false], [false, true]] {
+            let paint_info = palette.build_paint_info(Vector2I::splat(100), used_paints).unwrap();
+            for command in paint_info.render_commands {
+                if let RenderCommand::UploadTexelData { id, changes, .. } = command {
+                    uploads.push((id, changes));
+                }
+            }
+        }
+
+        assert_eq!(uploads.len(), 3);
+        assert!(uploads[0].1.is_none());
+        let changes = uploads[1].1.as_ref().unwrap();
+        assert_eq!(changes.since, uploads[0].0);
+        assert!(changes.rows.is_empty());
+        let changes = uploads[2].1.as_ref().unwrap();
+        assert_eq!(changes.since, uploads[1].0);
+        assert!(!changes.rows.is_empty());
+        assert!(changes.rows.iter().all(|rows| rows.end <= 16));
+    }
+
+    #[test]
+    fn test_external_textures_get_pages_of_their_own() {
+        let mut palette = Palette::new();
+        let image = Image::new(Vector2I::splat(8), vec![ColorU::white(); 64]);
+        let external_texture = ExternalTexture::new(ExternalTextureId(3), Vector2I::new(640, 480));
+        let sources = [PatternSource::Image(image), PatternSource::External(external_texture)];
+        for source in sources.iter().cloned() {
+            let pattern = Pattern::new(source, Transform2F::default(), PatternFlags::empty());
+            palette.push_paint(&Paint::Pattern(pattern));
+        }
+
+        let paint_info = palette.build_paint_info(Vector2I::splat(100), &[true, true]).unwrap();
+        let descriptors = match paint_info.render_commands[0] {
+            RenderCommand::AllocateTexturePages(ref descriptors) => descriptors,
+            _ => unreachable!(),
+        };
+        let image_page = paint_info.paint_metadata[0].location.page;
+        let external_page = paint_info.paint_metadata[1].location.page;
+        assert_ne!(image_page, external_page);
+        assert_eq!(descriptors[image_page.0 as usize].external_texture, None);
+        let external_descriptor = &descriptors[external_page.0 as usize];
+        assert_eq!(external_descriptor.external_texture, Some(ExternalTextureId(3)));
+        assert_eq!(external_descriptor.size, Vector2I::new(640, 480));
+
+        // Nothing is uploaded to the external texture's page.
+        assert!(paint_info.render_commands.iter().all(|command| {
+            match *command {
+                RenderCommand::UploadTexelData { location, .. } => location.page != external_page,
+                _ => true,
+            }
+        }));
+    }
+
+    #[test]
+    fn test_anisotropic_patterns_get_pages_of_their_own() {
+        let mut palette = Palette::new();
+        for &flags in &[PatternFlags::empty(),
+                        PatternFlags::ANISOTROPIC,
+                        PatternFlags::ANISOTROPIC | PatternFlags::NO_SMOOTHING] {
+            let image = Image::new(Vector2I::splat(8), vec![ColorU::white(); 64]);
+            let pattern = Pattern::new(PatternSource::Image(image), Transform2F::default(), flags);
+            palette.push_paint(&Paint::Pattern(pattern));
+        }
+
+        let paint_info = palette.build_paint_info(Vector2I::splat(100), &[true; 3]).unwrap();
+        let metadata = &paint_info.paint_metadata;
+        assert_eq!(metadata[0].sampling_flags.max_anisotropy(), 1);
+        assert_eq!(metadata[1].sampling_flags.max_anisotropy(), 16);
+        assert_ne!(metadata[1].location.page, metadata[0].location.page);
+
+        // Nearest-neighbor sampling takes precedence.
+        assert_eq!(metadata[2].sampling_flags.max_anisotropy(), 1);
+        assert!(metadata[2].sampling_flags.contains(TextureSamplingFlags::NEAREST_MIN));
+    }
+
+    #[test]
+    fn test_changed_rows() {
+        let old_texels = vec![ColorU::black(); 12];
+        let mut new_texels = old_texels.clone();
+        new_texels[1] = ColorU::white();
+        new_texels[4] = ColorU::white();
+        new_texels[11] = ColorU::white();
+        assert_eq!(changed_rows(&old_texels, &new_texels, 3), vec![0..2, 3..4]);
+        assert!(changed_rows(&old_texels, &old_texels, 3).is_empty());
     }
 }
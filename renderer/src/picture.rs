@@ -0,0 +1,176 @@
+// pathfinder/renderer/src/picture.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Recorded drawing that can be played into scenes over and over.
+
+use crate::builder::CachedTiling;
+use crate::options::PreparedBuildOptions;
+use crate::scene::Scene;
+use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+// The number of tilings kept for each path of a picture, for pictures drawn at several scales or
+// subpixel offsets at once.
+const MAX_CACHED_TILINGS_PER_PATH: usize = 4;
+
+// How far, in pixels, a path may be from a whole number of tiles away from a tiling of it for
+// that tiling to be reused. Fills only have 1/256 pixel of precision anyway.
+const MAX_TILE_OFFSET_ERROR: f32 = 1.0 / 256.0;
+
+/// A recording of drawing commands that can be played into any number of scenes, any number of
+/// times, with a different transform each time, like Skia's `SkPicture`.
+///
+/// A picture is recorded by drawing into a scene, with the canvas API for instance, and played
+/// with `Scene::draw_picture()`. The paths that a picture plays share a cache of their tilings.
+/// Once a path has been tiled, drawing it again at the same scale and rotation reuses the tiling,
+/// as long as it's a whole number of tiles away and doesn't cross the edge of the view box. So
+/// repeated widgets and map tiles laid out on the tile grid are only tiled once, and pictures
+/// that don't move between frames aren't tiled again. Copies of a picture share the cache.
+#[derive(Clone)]
+pub struct Picture {
+    pub(crate) scene: Arc<Scene>,
+    pub(crate) tile_cache: Arc<PictureTileCache>,
+}
+
+impl Picture {
+    #[inline]
+    pub fn new(scene: Scene) -> Picture {
+        Picture { scene: Arc::new(scene), tile_cache: Arc::new(PictureTileCache::default()) }
+    }
+
+    /// The drawing commands the picture plays, as a scene.
+    #[inline]
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// The bounds of everything the picture draws, in its own coordinate space.
+    #[inline]
+    pub fn bounds(&self) -> RectF {
+        self.scene.bounds()
+    }
+}
+
+/// Where a path in a scene was played from.
+#[derive(Clone)]
+pub(crate) struct PicturePath {
+    pub(crate) tile_cache: Arc<PictureTileCache>,
+    // The index of the path in the picture.
+    pub(crate) path_index: usize,
+    // Maps the picture's coordinate space to the scene's.
+    pub(crate) transform: Transform2F,
+}
+
+/// The tilings of a picture's paths, shared by the builds of every scene it's played into.
+#[derive(Default)]
+pub(crate) struct PictureTileCache {
+    // Indexed by the picture's path index, oldest tiling first.
+    entries: Mutex<Vec<Vec<CachedPictureTiling>>>,
+}
+
+struct CachedPictureTiling {
+    key: PictureTilingKey,
+    tiling: Arc<CachedTiling>,
+}
+
+/// What a tiling of a picture path depends on.
+#[derive(Clone, Copy)]
+pub(crate) struct PictureTilingKey {
+    // Maps the picture's coordinate space to the space the path is tiled in.
+    transform: Transform2F,
+    lod_scale: Option<f32>,
+    dilation: Vector2F,
+}
+
+impl PictureTileCache {
+    // Returns a tiling that can be reused for the key, and the number of tiles it has to be moved
+    // by.
+    pub(crate) fn find(&self, path_index: usize, key: &PictureTilingKey)
+                       -> Option<(Arc<CachedTiling>, Vector2I)> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(path_index)?.iter().rev().filter_map(|cached| {
+            cached.key.tile_offset_to(key).map(|tile_offset| (cached.tiling.clone(), tile_offset))
+        }).next()
+    }
+
+    pub(crate) fn insert(&self,
+                         path_index: usize,
+                         key: PictureTilingKey,
+                         cached_tiling: CachedTiling) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() <= path_index {
+            entries.resize_with(path_index + 1, Vec::new);
+        }
+        let tilings = &mut entries[path_index];
+        if tilings.len() == MAX_CACHED_TILINGS_PER_PATH {
+            tilings.remove(0);
+        }
+        tilings.push(CachedPictureTiling { key, tiling: Arc::new(cached_tiling) });
+    }
+
+    #[cfg(test)]
+    pub(crate) fn tiling_count(&self, path_index: usize) -> usize {
+        self.entries.lock().unwrap().get(path_index).map(Vec::len).unwrap_or(0)
+    }
+}
+
+impl PictureTilingKey {
+    // Returns `None` if the path can't share tilings: if it's drawn in perspective, or if it
+    // crosses the edge of the view box, which changes its tiles. `bounds` are the path's bounds in
+    // the scene.
+    pub(crate) fn new(picture_path: &PicturePath,
+                      bounds: RectF,
+                      view_box: RectF,
+                      options: &PreparedBuildOptions)
+                      -> Option<PictureTilingKey> {
+        let transform = options.tiling_transform_2d()?;
+        if !view_box.contains_rect((transform * bounds).dilate(options.dilation)) {
+            return None;
+        }
+
+        Some(PictureTilingKey {
+            transform: transform * picture_path.transform,
+            lod_scale: options.lod_scale(),
+            dilation: options.dilation,
+        })
+    }
+
+    // Returns the number of tiles that a tiling with this key has to be moved by to be a tiling
+    // with the other one, if it can be.
+    fn tile_offset_to(&self, other: &PictureTilingKey) -> Option<Vector2I> {
+        if self.transform.matrix != other.transform.matrix ||
+                self.lod_scale != other.lod_scale ||
+                self.dilation != other.dilation {
+            return None;
+        }
+
+        let tile_size = Vector2F::new(TILE_WIDTH as f32, TILE_HEIGHT as f32);
+        let offset = other.transform.vector - self.transform.vector;
+        let tile_offset = (offset / tile_size).round();
+        let error = offset - tile_offset * tile_size;
+        if error.x().abs() > MAX_TILE_OFFSET_ERROR || error.y().abs() > MAX_TILE_OFFSET_ERROR {
+            return None;
+        }
+        Some(tile_offset.to_i32())
+    }
+}
+
+impl Debug for PicturePath {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.debug_struct("PicturePath")
+                 .field("path_index", &self.path_index)
+                 .field("transform", &self.transform)
+                 .finish()
+    }
+}
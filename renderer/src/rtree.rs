@@ -0,0 +1,246 @@
+// pathfinder/renderer/src/rtree.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An R-tree over bounding rectangles, for finding the paths in a region of a scene.
+//!
+//! Entries are inserted one at a time as paths are pushed, and overflowing nodes are split with a
+//! simplified version of Guttman's quadratic split.
+
+use pathfinder_geometry::rect::RectF;
+use std::mem;
+
+const MAX_ENTRIES: usize = 8;
+const MIN_ENTRIES: usize = 3;
+
+#[derive(Clone, Debug)]
+pub(crate) struct RTree<T> where T: Copy {
+    root: Node<T>,
+}
+
+#[derive(Clone, Debug)]
+enum Node<T> where T: Copy {
+    Leaf(Vec<Entry<T>>),
+    Branch(Vec<Entry<Box<Node<T>>>>),
+}
+
+#[derive(Clone, Debug)]
+struct Entry<V> {
+    bounds: RectF,
+    value: V,
+}
+
+impl<T> RTree<T> where T: Copy {
+    #[inline]
+    pub(crate) fn new() -> RTree<T> {
+        RTree { root: Node::Leaf(vec![]) }
+    }
+
+    pub(crate) fn insert(&mut self, bounds: RectF, value: T) {
+        if let Some((sibling_bounds, sibling)) = self.root.insert(bounds, value) {
+            let old_root = mem::replace(&mut self.root, Node::Leaf(vec![]));
+            self.root = Node::Branch(vec![
+                Entry { bounds: old_root.bounds(), value: Box::new(old_root) },
+                Entry { bounds: sibling_bounds, value: Box::new(sibling) },
+            ]);
+        }
+    }
+
+    /// Appends the values of all entries whose bounds touch `rect`, in no particular order.
+    pub(crate) fn query(&self, rect: RectF, results: &mut Vec<T>) {
+        self.root.query(rect, results)
+    }
+}
+
+impl<T> Node<T> where T: Copy {
+    // Returns the new sibling of this node if it had to be split.
+    fn insert(&mut self, bounds: RectF, value: T) -> Option<(RectF, Node<T>)> {
+        match *self {
+            Node::Leaf(ref mut entries) => {
+                entries.push(Entry { bounds, value });
+                if entries.len() <= MAX_ENTRIES {
+                    return None;
+                }
+                let sibling = split(entries);
+                Some((union_of(&sibling), Node::Leaf(sibling)))
+            }
+            Node::Branch(ref mut entries) => {
+                let index = choose_subtree(entries, bounds);
+                match entries[index].value.insert(bounds, value) {
+                    None => entries[index].bounds = entries[index].bounds.union_rect(bounds),
+                    Some((sibling_bounds, sibling)) => {
+                        entries[index].bounds = entries[index].value.bounds();
+                        entries.push(Entry { bounds: sibling_bounds, value: Box::new(sibling) });
+                    }
+                }
+                if entries.len() <= MAX_ENTRIES {
+                    return None;
+                }
+                let sibling = split(entries);
+                Some((union_of(&sibling), Node::Branch(sibling)))
+            }
+        }
+    }
+
+    fn query(&self, rect: RectF, results: &mut Vec<T>) {
+        match *self {
+            Node::Leaf(ref entries) => {
+                results.extend(entries.iter()
+                                      .filter(|entry| overlaps(entry.bounds, rect))
+                                      .map(|entry| entry.value));
+            }
+            Node::Branch(ref entries) => {
+                for entry in entries {
+                    if overlaps(entry.bounds, rect) {
+                        entry.value.query(rect, results);
+                    }
+                }
+            }
+        }
+    }
+
+    fn bounds(&self) -> RectF {
+        match *self {
+            Node::Leaf(ref entries) => union_of(entries),
+            Node::Branch(ref entries) => union_of(entries),
+        }
+    }
+}
+
+// Picks the child that needs to grow the least to take in `bounds`, preferring smaller children.
+fn choose_subtree<V>(entries: &[Entry<V>], bounds: RectF) -> usize {
+    let mut best_index = 0;
+    let (mut best_growth, mut best_area) = (f32::INFINITY, f32::INFINITY);
+    for (index, entry) in entries.iter().enumerate() {
+        let area = area(entry.bounds);
+        let growth = area_of_union(entry.bounds, bounds) - area;
+        if growth < best_growth || (growth == best_growth && area < best_area) {
+            best_index = index;
+            best_growth = growth;
+            best_area = area;
+        }
+    }
+    best_index
+}
+
+// Divides `entries` into two groups, leaving one in `entries` and returning the other.
+fn split<V>(entries: &mut Vec<Entry<V>>) -> Vec<Entry<V>> {
+    // Seed the groups with the pair of entries that would waste the most area if grouped together.
+    let (mut seed_a, mut seed_b, mut worst_waste) = (0, 1, f32::NEG_INFINITY);
+    for a in 0..entries.len() {
+        for b in (a + 1)..entries.len() {
+            let (bounds_a, bounds_b) = (entries[a].bounds, entries[b].bounds);
+            let waste = area_of_union(bounds_a, bounds_b) - area(bounds_a) - area(bounds_b);
+            if waste > worst_waste {
+                seed_a = a;
+                seed_b = b;
+                worst_waste = waste;
+            }
+        }
+    }
+
+    let mut remaining = mem::take(entries);
+    // `seed_b` is the greater index, so removing it first leaves `seed_a` in place.
+    let entry_b = remaining.swap_remove(seed_b);
+    let entry_a = remaining.swap_remove(seed_a);
+    let (mut bounds_a, mut bounds_b) = (entry_a.bounds, entry_b.bounds);
+    let (mut group_a, mut group_b) = (vec![entry_a], vec![entry_b]);
+
+    while let Some(entry) = remaining.pop() {
+        // Each group must end up with at least the minimum number of entries.
+        let left = remaining.len() + 1;
+        let to_a = if group_a.len() + left <= MIN_ENTRIES {
+            true
+        } else if group_b.len() + left <= MIN_ENTRIES {
+            false
+        } else {
+            let growth_a = area_of_union(bounds_a, entry.bounds) - area(bounds_a);
+            let growth_b = area_of_union(bounds_b, entry.bounds) - area(bounds_b);
+            growth_a < growth_b || (growth_a == growth_b && group_a.len() <= group_b.len())
+        };
+
+        if to_a {
+            bounds_a = bounds_a.union_rect(entry.bounds);
+            group_a.push(entry);
+        } else {
+            bounds_b = bounds_b.union_rect(entry.bounds);
+            group_b.push(entry);
+        }
+    }
+
+    *entries = group_a;
+    group_b
+}
+
+fn union_of<V>(entries: &[Entry<V>]) -> RectF {
+    let mut entries = entries.iter();
+    let first = match entries.next() {
+        None => return RectF::default(),
+        Some(first) => first.bounds,
+    };
+    entries.fold(first, |bounds, entry| bounds.union_rect(entry.bounds))
+}
+
+// Unlike `RectF::intersects()`, this counts touching edges, so that degenerate bounds such as
+// those of horizontal lines can be found.
+#[inline]
+fn overlaps(a: RectF, b: RectF) -> bool {
+    a.min_x() <= b.max_x() && b.min_x() <= a.max_x() &&
+        a.min_y() <= b.max_y() && b.min_y() <= a.max_y()
+}
+
+#[inline]
+fn area(rect: RectF) -> f32 {
+    rect.width() * rect.height()
+}
+
+#[inline]
+fn area_of_union(a: RectF, b: RectF) -> f32 {
+    area(a.union_rect(b))
+}
+
+#[cfg(test)]
+mod test {
+    use super::RTree;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::Vector2F;
+
+    #[test]
+    fn test_query_matches_linear_scan() {
+        // A deterministic scatter of rects, enough to split nodes a few levels deep.
+        let rects: Vec<RectF> = (0..500).map(|index| {
+            let x = ((index * 37) % 101) as f32 * 10.0;
+            let y = ((index * 53) % 97) as f32 * 10.0;
+            let size = Vector2F::new((index % 7) as f32 * 5.0, (index % 5) as f32 * 5.0);
+            RectF::new(Vector2F::new(x, y), size)
+        }).collect();
+
+        let mut tree = RTree::new();
+        for (index, &rect) in rects.iter().enumerate() {
+            tree.insert(rect, index);
+        }
+
+        for &query in &[
+            RectF::new(Vector2F::new(100.0, 100.0), Vector2F::new(200.0, 150.0)),
+            RectF::new(Vector2F::new(500.0, 0.0), Vector2F::new(10.0, 1000.0)),
+            RectF::new(rects[42].origin(), Vector2F::default()),
+        ] {
+            let mut results = vec![];
+            tree.query(query, &mut results);
+            results.sort();
+            let expected: Vec<usize> = (0..rects.len()).filter(|&index| {
+                let rect = rects[index];
+                rect.min_x() <= query.max_x() && query.min_x() <= rect.max_x() &&
+                    rect.min_y() <= query.max_y() && query.min_y() <= rect.max_y()
+            }).collect();
+            assert!(!expected.is_empty());
+            assert_eq!(results, expected);
+        }
+    }
+}
@@ -10,18 +10,25 @@
 
 //! A set of paths to be rendered.
 
-use crate::builder::SceneBuilder;
+use crate::builder::{BuildPool, ClipCache, SceneBuilder};
 use crate::concurrent::executor::Executor;
-use crate::options::{BuildOptions, PreparedBuildOptions};
+use crate::error::Error;
+use crate::options::{BuildOptions, PreparedBuildOptions, RenderTransform, Viewport};
 use crate::options::{PreparedRenderTransform, RenderCommandListener};
 use crate::paint::{Paint, PaintId, PaintInfo, Palette};
+use crate::picture::{Picture, PicturePath};
+use crate::rtree::RTree;
 use pathfinder_content::effects::{BlendMode, Effects};
 use pathfinder_content::fill::FillRule;
 use pathfinder_content::outline::Outline;
+use pathfinder_content::pattern::PatternSource;
 use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_content::stroke::StrokeStyle;
 use pathfinder_geometry::vector::{Vector2F, Vector2I};
 use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
+use std::ops::Range;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Scene {
@@ -31,6 +38,11 @@ pub struct Scene {
     palette: Palette,
     bounds: RectF,
     view_box: RectF,
+    path_index: RTree<PathId>,
+    // Clones share the pool. That's harmless, since each build takes vectors out of it for its
+    // own use and only gives them back when it's done with them.
+    pub(crate) build_pool: Arc<BuildPool>,
+    pub(crate) clip_cache: ClipCache,
 }
 
 impl Scene {
@@ -43,11 +55,17 @@ impl Scene {
             palette: Palette::new(),
             bounds: RectF::default(),
             view_box: RectF::default(),
+            path_index: RTree::new(),
+            build_pool: Arc::new(BuildPool::default()),
+            clip_cache: ClipCache::default(),
         }
     }
 
-    pub fn push_path(&mut self, path: DrawPath) {
-        self.bounds = self.bounds.union_rect(path.outline.bounds());
+    pub fn push_path(&mut self, path: DrawPath) -> PathId {
+        let path_id = PathId(self.paths.len() as u32);
+        let path_bounds = path.outline.bounds();
+        self.bounds = self.bounds.union_rect(path_bounds);
+        self.path_index.insert(path_bounds, path_id);
         self.paths.push(path);
 
         let new_path_count = self.paths.len() as u32;
@@ -62,6 +80,8 @@ impl Scene {
                 end_index: new_path_count,
             });
         }
+
+        path_id
     }
 
     pub fn push_clip_path(&mut self, clip_path: ClipPath) -> ClipPathId {
@@ -85,9 +105,81 @@ impl Scene {
         self.display_list.push(DisplayItem::DrawRenderTarget { render_target, effects });
     }
 
+    /// Plays a picture into the scene, drawing everything it recorded on top of what's already
+    /// there, with `transform` mapping the picture's coordinate space to the scene's.
+    ///
+    /// The picture's paints, clip paths, and render targets are added to the scene. Its render
+    /// targets are sized to this scene's view box, as the canvas API sizes them.
+    pub fn draw_picture(&mut self, picture: &Picture, transform: &Transform2F) {
+        let recording = &*picture.scene;
+
+        let render_target_size = self.view_box.size().ceil().to_i32();
+        let render_target_ids: Vec<_> =
+            recording.palette.render_targets.iter().map(|render_target| {
+                let render_target = RenderTarget::new(render_target_size,
+                                                      render_target.name.clone());
+                self.palette.push_render_target(render_target)
+            }).collect();
+
+        let paint_ids: Vec<_> = recording.palette.paints.iter().map(|paint| {
+            let mut paint = paint.clone();
+            paint.apply_transform(transform);
+            if let Paint::Pattern(ref mut pattern) = paint {
+                if let PatternSource::RenderTarget(ref mut render_target_id) = pattern.source {
+                    *render_target_id = render_target_ids[render_target_id.0 as usize];
+                }
+            }
+            self.push_paint(&paint)
+        }).collect();
+
+        let first_clip_path_id = self.clip_paths.len() as u32;
+        for clip_path in &recording.clip_paths {
+            let mut clip_path = clip_path.clone();
+            clip_path.outline.transform(transform);
+            self.push_clip_path(clip_path);
+        }
+
+        // Glyphs of the same text run share it, so runs are transformed once.
+        let mut last_text_run: Option<(Arc<TextRun>, Arc<TextRun>)> = None;
+        for display_item in &recording.display_list {
+            match *display_item {
+                DisplayItem::DrawPaths { start_index, end_index } => {
+                    for path_index in start_index as usize..end_index as usize {
+                        let mut path = recording.paths[path_index].clone();
+                        path.transform(transform, &mut last_text_run);
+                        path.paint = paint_ids[path.paint.0 as usize];
+                        path.clip_path = path.clip_path.map(|clip_path_id| {
+                            ClipPathId(first_clip_path_id + clip_path_id.0)
+                        });
+                        path.picture_path = Some(PicturePath {
+                            tile_cache: picture.tile_cache.clone(),
+                            path_index,
+                            transform: *transform,
+                        });
+                        self.push_path(path);
+                    }
+                }
+                DisplayItem::DrawRenderTarget { render_target, effects } => {
+                    self.draw_render_target(render_target_ids[render_target.0 as usize], effects)
+                }
+                DisplayItem::PushRenderTarget(render_target) => {
+                    let render_target = render_target_ids[render_target.0 as usize];
+                    self.display_list.push(DisplayItem::PushRenderTarget(render_target));
+                }
+                DisplayItem::PopRenderTarget => self.pop_render_target(),
+            }
+        }
+    }
+
     #[inline]
-    pub fn build_paint_info(&self) -> PaintInfo {
-        self.palette.build_paint_info(self.view_box.size().to_i32())
+    pub fn build_paint_info(&self) -> Result<PaintInfo, Error> {
+        let mut used_paints = vec![false; self.palette.paints.len()];
+        for path in &self.paths {
+            if let Some(used) = used_paints.get_mut(path.paint.0 as usize) {
+                *used = true;
+            }
+        }
+        self.palette.build_paint_info(self.view_box.size().to_i32(), &used_paints)
     }
 
     #[allow(clippy::trivially_copy_pass_by_ref)]
@@ -95,11 +187,88 @@ impl Scene {
         self.palette.push_paint(paint)
     }
 
+    #[inline]
+    pub(crate) fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    #[inline]
+    pub fn paint(&self, paint_id: PaintId) -> &Paint {
+        &self.palette.paints[paint_id.0 as usize]
+    }
+
     #[inline]
     pub fn path_count(&self) -> usize {
         self.paths.len()
     }
 
+    #[inline]
+    pub fn path(&self, path_index: usize) -> &DrawPath {
+        &self.paths[path_index]
+    }
+
+    #[inline]
+    pub fn clip_path(&self, clip_path_id: ClipPathId) -> &ClipPath {
+        &self.clip_paths[clip_path_id.0 as usize]
+    }
+
+    #[inline]
+    pub fn display_list(&self) -> &[DisplayItem] {
+        &self.display_list
+    }
+
+    /// Shows or hides a single path without removing it from the scene.
+    ///
+    /// Hidden paths are skipped during building, so they generate no tiles. This is mainly useful
+    /// for debugging.
+    pub fn set_path_visible(&mut self, path_index: usize, visible: bool) -> Result<(), Error> {
+        match self.paths.get_mut(path_index) {
+            None => Err(Error::NoSuchPath(path_index)),
+            Some(path) => {
+                path.visible = visible;
+                Ok(())
+            }
+        }
+    }
+
+    /// Records how a path was produced. See `PathSource`.
+    #[inline]
+    pub fn set_path_source(&mut self, path_index: usize, source: Option<PathSource>) {
+        self.paths[path_index].source = source;
+    }
+
+    /// Returns the bounding box of a path's outline, in scene coordinates.
+    #[inline]
+    pub fn path_bounds(&self, path_id: PathId) -> RectF {
+        self.paths[path_id.0 as usize].outline.bounds()
+    }
+
+    /// Returns the union of the bounding boxes of the paths in `range`, or an empty rect at the
+    /// origin if the range is empty.
+    pub fn bounds_of_range(&self, range: Range<PathId>) -> RectF {
+        let paths = &self.paths[range.start.0 as usize..range.end.0 as usize];
+        let mut bounds = match paths.first() {
+            None => return RectF::default(),
+            Some(path) => path.outline.bounds(),
+        };
+        for path in &paths[1..] {
+            bounds = bounds.union_rect(path.outline.bounds());
+        }
+        bounds
+    }
+
+    /// Returns the visible paths whose bounding boxes touch `rect`, in painting order.
+    ///
+    /// This is a bounding box test only: neither the exact shape of the outline nor its clip path
+    /// is taken into account. A zero-sized `rect` can be used to pick the paths under a point.
+    pub fn paths_in_rect(&self, rect: RectF) -> Vec<PathId> {
+        let mut path_ids = vec![];
+        self.path_index.query(rect, &mut path_ids);
+        path_ids.retain(|path_id| self.paths[path_id.0 as usize].visible);
+        path_ids.sort();
+        path_ids
+    }
+
     #[inline]
     pub fn bounds(&self) -> RectF {
         self.bounds
@@ -138,8 +307,10 @@ impl Scene {
                     outline = Outline::new();
                 } else {
                     outline = (*original_outline).clone();
+                    remove_non_finite_points(&mut outline);
                     outline.clip_against_polygon(clip_polygon);
                     outline.apply_perspective(perspective);
+                    remove_non_finite_points(&mut outline);
 
                     // TODO(pcwalton): Support subpixel AA in 3D.
                 }
@@ -158,6 +329,7 @@ impl Scene {
                     }
                     outline.transform(&transform);
                 }
+                remove_non_finite_points(&mut outline);
                 outline.clip_against_rect(effective_view_box);
             }
         }
@@ -174,23 +346,100 @@ impl Scene {
 
     #[inline]
     pub(crate) fn effective_view_box(&self, render_options: &PreparedBuildOptions) -> RectF {
+        let view_box = match render_options.clip_rect {
+            None => self.view_box,
+            Some(clip_rect) => {
+                self.view_box.intersection(clip_rect).unwrap_or(RectF::new(clip_rect.origin(),
+                                                                           Vector2F::default()))
+            }
+        };
         if render_options.subpixel_aa_enabled {
-            self.view_box.scale_xy(Vector2F::new(3.0, 1.0))
+            view_box.scale_xy(Vector2F::new(3.0, 1.0))
         } else {
-            self.view_box
+            view_box
         }
     }
 
+    /// Builds the scene, sending render commands to `listener`.
+    ///
+    /// If this returns an error other than `Error::Cancelled`, nothing was sent.
     #[inline]
     pub fn build<E>(&self,
                     options: BuildOptions,
                     listener: Box<dyn RenderCommandListener>,
                     executor: &E)
+                    -> Result<(), Error>
                     where E: Executor {
         let prepared_options = options.prepare(self.bounds);
         SceneBuilder::new(self, &prepared_options, listener).build(executor)
     }
 
+    /// Builds the scene once for each viewport, in order, sending the render commands for all of
+    /// them to `listener` as one frame.
+    ///
+    /// Paints are rendered once and shared by all the viewports. As with
+    /// `BuildOptions::transform`, the viewports' transforms don't apply to paints, whose texture
+    /// coordinates are in device pixels.
+    ///
+    /// A viewport that shows the same part of the scene as an earlier one at the same scale,
+    /// moved by a whole number of tiles, draws that viewport's tiles again instead of tiling the
+    /// scene anew, as long as the scene is painted with plain colors and draws no render targets.
+    ///
+    /// If this returns an error other than `Error::Cancelled`, nothing was sent. If there are no
+    /// viewports, nothing is sent either.
+    pub fn build_viewports<E>(&self,
+                              options: BuildOptions,
+                              viewports: &[Viewport],
+                              listener: Box<dyn RenderCommandListener>,
+                              executor: &E)
+                              -> Result<(), Error>
+                              where E: Executor {
+        if viewports.is_empty() {
+            return Ok(());
+        }
+        let prepared_options: Vec<_> = viewports.iter().map(|viewport| {
+            options.prepare_viewport(viewport, self.bounds)
+        }).collect();
+        SceneBuilder::with_viewports(self, &prepared_options, false, listener).build(executor)
+    }
+
+    /// Builds the scene once for each eye of a stereo display, such as a VR headset, sending the
+    /// render commands for both to `listener` as one frame.
+    ///
+    /// Each eye's transform takes the place of `options.transform`. Each eye's commands start
+    /// with a `BeginLayer` command, so that the left eye is drawn into the first layer of a
+    /// `DestFramebuffer::Layers` destination and the right eye into the second. The eyes share
+    /// the paint data, which is uploaded once, and the renderer's fill and mask framebuffers.
+    ///
+    /// If this returns an error other than `Error::Cancelled`, nothing was sent.
+    pub fn build_stereo<E>(&self,
+                           options: BuildOptions,
+                           eye_transforms: [RenderTransform; 2],
+                           listener: Box<dyn RenderCommandListener>,
+                           executor: &E)
+                           -> Result<(), Error>
+                           where E: Executor {
+        let prepared_options: Vec<_> = eye_transforms.iter().map(|eye_transform| {
+            let eye_options = BuildOptions { transform: eye_transform.clone(), ..options.clone() };
+            eye_options.prepare(self.bounds)
+        }).collect();
+        SceneBuilder::with_viewports(self, &prepared_options, true, listener).build(executor)
+    }
+
+    /// Tiles a single path as `build()` would with `options`, and returns the tiles it covers.
+    ///
+    /// Nothing is rendered. Clip paths are applied, but occlusion by the paths above isn't, and
+    /// hidden paths cover no tiles. This is mainly useful for debugging.
+    pub fn build_path_tiles(&self, path_index: usize, options: BuildOptions)
+                            -> Result<PathTiles, Error> {
+        if path_index >= self.paths.len() {
+            return Err(Error::NoSuchPath(path_index));
+        }
+        let prepared_options = options.prepare(self.bounds);
+        let listener = Box::new(|_| {});
+        SceneBuilder::new(self, &prepared_options, listener).build_path_tiles(path_index)
+    }
+
     pub fn paths<'a>(&'a self) -> PathIter {
         PathIter {
             scene: self,
@@ -222,12 +471,18 @@ impl<'a> Iterator for PathIter<'a> {
 #[derive(Clone, Debug)]
 pub struct DrawPath {
     outline: Outline,
+    // Simplified outlines and the largest scale each is drawn at, sorted by scale.
+    lods: Vec<(f32, Outline)>,
     paint: PaintId,
     clip_path: Option<ClipPathId>,
     fill_rule: FillRule,
     blend_mode: BlendMode,
     opacity: u8,
+    visible: bool,
     name: String,
+    source: Option<PathSource>,
+    // Set if the path was played from a picture, so that it can share the picture's tilings.
+    pub(crate) picture_path: Option<PicturePath>,
 }
 
 #[derive(Clone, Debug)]
@@ -237,15 +492,63 @@ pub struct ClipPath {
     name: String,
 }
 
+/// Identifies a path by the order in which it was pushed to the scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PathId(pub u32);
+
 #[derive(Clone, Copy, Debug)]
 pub struct ClipPathId(pub u32);
 
+/// The tiles that one path covers once built. See `Scene::build_path_tiles()`.
+///
+/// Tile coordinates are in units of tiles, from the origin of the device pixel space that
+/// `BuildOptions::transform` maps the scene into.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PathTiles {
+    /// The tiles that are partly covered or translucent, and so are drawn with a mask.
+    pub alpha_tiles: Vec<Vector2I>,
+    /// The tiles that the path covers completely with an opaque paint.
+    pub solid_tiles: Vec<Vector2I>,
+}
+
 #[derive(Clone, Debug)]
 pub struct RenderTarget {
     size: Vector2I,
     name: String,
 }
 
+/// What a path was drawn from, before it was converted into the fill that the renderer draws.
+///
+/// The renderer ignores this. It's there so that exporters can write strokes and text in editable
+/// form. The canvas API only records it when asked to, since it keeps a copy of every stroke.
+#[derive(Clone, Debug)]
+pub enum PathSource {
+    /// A stroke of `outline`, which is in the coordinate space that `transform` maps to the
+    /// scene. `line_dash` is empty if the stroke is solid.
+    Stroke {
+        outline: Outline,
+        transform: Transform2F,
+        style: StrokeStyle,
+        line_dash: Vec<f32>,
+        line_dash_offset: f32,
+    },
+
+    /// One glyph of a run of text. All the glyphs in a run share the same `TextRun`.
+    Text(Arc<TextRun>),
+}
+
+#[derive(Clone, Debug)]
+pub struct TextRun {
+    pub text: String,
+    pub font_family: String,
+    pub font_size: f32,
+    /// Maps the text's coordinate space, with the start of the baseline at the origin, to the
+    /// scene.
+    pub transform: Transform2F,
+    /// The style that the text was stroked with, or `None` if it was filled.
+    pub stroke: Option<StrokeStyle>,
+}
+
 /// Drawing commands.
 #[derive(Clone, Debug)]
 pub enum DisplayItem {
@@ -271,12 +574,55 @@ impl DrawPath {
     pub fn new(outline: Outline, paint: PaintId) -> DrawPath {
         DrawPath {
             outline,
+            lods: vec![],
             paint,
             clip_path: None,
             fill_rule: FillRule::Winding,
             blend_mode: BlendMode::SrcOver,
             opacity: !0,
+            visible: true,
             name: String::new(),
+            source: None,
+            picture_path: None,
+        }
+    }
+
+    // Moves the path, its levels of detail, and its source by `transform`. `last_text_run` holds
+    // the text run last transformed and its transformed copy.
+    fn transform(&mut self,
+                 transform: &Transform2F,
+                 last_text_run: &mut Option<(Arc<TextRun>, Arc<TextRun>)>) {
+        self.outline.transform(transform);
+
+        // A level of detail is picked by the build's scale, which this transform now adds to.
+        let scale = transform.matrix.det().abs().sqrt();
+        if scale == 0.0 {
+            self.lods.clear();
+        }
+        for &mut (ref mut max_scale, ref mut outline) in &mut self.lods {
+            *max_scale /= scale;
+            outline.transform(transform);
+        }
+
+        match self.source {
+            None => {}
+            Some(PathSource::Stroke { transform: ref mut stroke_transform, .. }) => {
+                *stroke_transform = *transform * *stroke_transform;
+            }
+            Some(PathSource::Text(ref mut text_run)) => {
+                match *last_text_run {
+                    Some((ref original, ref transformed)) if Arc::ptr_eq(original, text_run) => {
+                        *text_run = transformed.clone();
+                    }
+                    _ => {
+                        let original = text_run.clone();
+                        let mut transformed = (**text_run).clone();
+                        transformed.transform = *transform * transformed.transform;
+                        *text_run = Arc::new(transformed);
+                        *last_text_run = Some((original, text_run.clone()));
+                    }
+                }
+            }
         }
     }
 
@@ -285,8 +631,40 @@ impl DrawPath {
         &self.outline
     }
 
+    /// Adds a simplified version of the outline, to be drawn instead when the scene is scaled by
+    /// at most `max_scale`, so that detailed geometry viewed from far away doesn't produce huge
+    /// numbers of subpixel fills.
+    ///
+    /// Of the levels of detail whose `max_scale` is at least the build's scale, the one with the
+    /// smallest is drawn; if there are none, the full outline is. The scale of a 2D transform is
+    /// the square root of its determinant's magnitude. Perspective builds always use the full
+    /// outline.
+    pub fn add_lod(&mut self, max_scale: f32, outline: Outline) {
+        let index = self.lods.iter().position(|&(scale, _)| scale > max_scale);
+        let index = index.unwrap_or(self.lods.len());
+        self.lods.insert(index, (max_scale, outline));
+    }
+
     #[inline]
-    pub(crate) fn clip_path(&self) -> Option<ClipPathId> {
+    pub fn clear_lods(&mut self) {
+        self.lods.clear()
+    }
+
+    /// Returns the outline drawn when the scene is scaled by `scale`, or the full outline if
+    /// `scale` is `None`.
+    pub fn outline_for_scale(&self, scale: Option<f32>) -> &Outline {
+        let scale = match scale {
+            Some(scale) => scale,
+            None => return &self.outline,
+        };
+        match self.lods.iter().find(|&&(max_scale, _)| scale <= max_scale) {
+            Some((_, outline)) => outline,
+            None => &self.outline,
+        }
+    }
+
+    #[inline]
+    pub fn clip_path(&self) -> Option<ClipPathId> {
         self.clip_path
     }
 
@@ -296,12 +674,12 @@ impl DrawPath {
     }
 
     #[inline]
-    pub(crate) fn paint(&self) -> PaintId {
+    pub fn paint(&self) -> PaintId {
         self.paint
     }
 
     #[inline]
-    pub(crate) fn fill_rule(&self) -> FillRule {
+    pub fn fill_rule(&self) -> FillRule {
         self.fill_rule
     }
 
@@ -311,7 +689,7 @@ impl DrawPath {
     }
 
     #[inline]
-    pub(crate) fn blend_mode(&self) -> BlendMode {
+    pub fn blend_mode(&self) -> BlendMode {
         self.blend_mode
     }
 
@@ -321,7 +699,7 @@ impl DrawPath {
     }
 
     #[inline]
-    pub(crate) fn opacity(&self) -> u8 {
+    pub fn opacity(&self) -> u8 {
         self.opacity
     }
 
@@ -330,10 +708,35 @@ impl DrawPath {
         self.opacity = new_opacity
     }
 
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    #[inline]
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     #[inline]
     pub fn set_name(&mut self, new_name: String) {
         self.name = new_name
     }
+
+    #[inline]
+    pub fn source(&self) -> Option<&PathSource> {
+        self.source.as_ref()
+    }
+
+    #[inline]
+    pub fn set_source(&mut self, new_source: Option<PathSource>) {
+        self.source = new_source
+    }
 }
 
 impl ClipPath {
@@ -348,7 +751,7 @@ impl ClipPath {
     }
 
     #[inline]
-    pub(crate) fn fill_rule(&self) -> FillRule {
+    pub fn fill_rule(&self) -> FillRule {
         self.fill_rule
     }
 
@@ -374,3 +777,11 @@ impl RenderTarget {
         self.size
     }
 }
+
+// NaN and infinite coordinates make clipping and tiling panic or loop forever, so they're dropped
+// before the outline gets there.
+fn remove_non_finite_points(outline: &mut Outline) {
+    if outline.remove_non_finite_points() {
+        debug!("removed non-finite points from an outline");
+    }
+}
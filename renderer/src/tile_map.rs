@@ -10,6 +10,16 @@
 
 use pathfinder_geometry::vector::Vector2I;
 use pathfinder_geometry::rect::RectI;
+use std::collections::btree_map::{self, BTreeMap};
+use std::iter::Enumerate;
+use std::mem;
+use std::slice;
+
+// Maps covering at least this many tiles start out sparse.
+const SPARSE_MIN_TILE_COUNT: usize = 4096;
+// A sparse map becomes dense once more than 1/this of its tiles are stored, at which point the
+// dense map is smaller.
+const SPARSE_MAX_DENSITY_RECIPROCAL: usize = 8;
 
 #[derive(Debug)]
 pub struct DenseTileMap<T> {
@@ -18,16 +28,17 @@ pub struct DenseTileMap<T> {
 }
 
 impl<T> DenseTileMap<T> {
+    /// Creates a map filled with default values, reusing the allocation of `data`. The old
+    /// contents of `data` are discarded.
     #[inline]
-    pub fn new(rect: RectI) -> DenseTileMap<T>
+    pub fn with_buffer(rect: RectI, mut data: Vec<T>) -> DenseTileMap<T>
     where
         T: Copy + Clone + Default,
     {
         let length = rect.size().x() as usize * rect.size().y() as usize;
-        DenseTileMap {
-            data: vec![T::default(); length],
-            rect,
-        }
+        data.clear();
+        data.resize(length, T::default());
+        DenseTileMap { data, rect }
     }
 
     #[inline]
@@ -68,3 +79,202 @@ impl<T> DenseTileMap<T> {
         self.rect.origin() + Vector2I::new(index % width, index / width)
     }
 }
+
+/// A map from tiles to values that stores only the tiles that have been written to.
+///
+/// Every other tile in the rect has the default value. This suits paths that touch few of the
+/// tiles in their bounds, such as long diagonal lines across a large canvas.
+#[derive(Debug)]
+pub struct SparseTileMap<T> {
+    // Keyed by the index the tile would have in a `DenseTileMap`, so that iteration goes in the
+    // same order.
+    data: BTreeMap<usize, T>,
+    rect: RectI,
+    default: T,
+    // A buffer to reuse if the map becomes dense.
+    buffer: Vec<T>,
+}
+
+/// A tile map that is sparse while few of its tiles are stored and dense otherwise.
+#[derive(Debug)]
+pub enum TileMap<T> {
+    Dense(DenseTileMap<T>),
+    Sparse(SparseTileMap<T>),
+}
+
+pub enum TileMapIter<'a, T> {
+    Dense { iter: Enumerate<slice::Iter<'a, T>>, rect: RectI },
+    Sparse { iter: btree_map::Iter<'a, usize, T>, rect: RectI },
+}
+
+impl<T> TileMap<T>
+where
+    T: Copy + Default,
+{
+    /// Creates a map filled with default values. Large maps start out sparse; otherwise, the
+    /// allocation of `buffer` is reused.
+    pub fn new(rect: RectI, buffer: Vec<T>) -> TileMap<T> {
+        if tile_count(rect) < SPARSE_MIN_TILE_COUNT {
+            return TileMap::Dense(DenseTileMap::with_buffer(rect, buffer));
+        }
+        TileMap::Sparse(SparseTileMap {
+            data: BTreeMap::new(),
+            rect,
+            default: T::default(),
+            buffer,
+        })
+    }
+
+    #[inline]
+    pub fn rect(&self) -> RectI {
+        match *self {
+            TileMap::Dense(ref dense) => dense.rect,
+            TileMap::Sparse(ref sparse) => sparse.rect,
+        }
+    }
+
+    pub fn get(&self, coords: Vector2I) -> Option<&T> {
+        match *self {
+            TileMap::Dense(ref dense) => dense.get(coords),
+            TileMap::Sparse(ref sparse) => {
+                let index = sparse.coords_to_index(coords)?;
+                Some(sparse.data.get(&index).unwrap_or(&sparse.default))
+            }
+        }
+    }
+
+    /// Returns the value of a tile for writing, or `None` if the tile is outside the map.
+    pub fn get_mut(&mut self, coords: Vector2I) -> Option<&mut T> {
+        if let TileMap::Sparse(ref sparse) = *self {
+            if sparse.data.len() * SPARSE_MAX_DENSITY_RECIPROCAL >= tile_count(sparse.rect) {
+                self.make_dense();
+            }
+        }
+
+        match *self {
+            TileMap::Dense(ref mut dense) => {
+                let index = dense.coords_to_index(coords)?;
+                Some(&mut dense.data[index])
+            }
+            TileMap::Sparse(ref mut sparse) => {
+                let index = sparse.coords_to_index(coords)?;
+                Some(sparse.data.entry(index).or_insert(sparse.default))
+            }
+        }
+    }
+
+    /// Iterates over tiles and their values in row-major order.
+    ///
+    /// Sparse maps skip the tiles that were never written to.
+    pub fn iter(&self) -> TileMapIter<'_, T> {
+        match *self {
+            TileMap::Dense(ref dense) => {
+                TileMapIter::Dense { iter: dense.data.iter().enumerate(), rect: dense.rect }
+            }
+            TileMap::Sparse(ref sparse) => {
+                TileMapIter::Sparse { iter: sparse.data.iter(), rect: sparse.rect }
+            }
+        }
+    }
+
+    /// Returns the map's storage, for reuse in another map.
+    pub fn into_buffer(self) -> Vec<T> {
+        match self {
+            TileMap::Dense(dense) => dense.data,
+            TileMap::Sparse(sparse) => sparse.buffer,
+        }
+    }
+
+    fn make_dense(&mut self) {
+        let sparse = match *self {
+            TileMap::Sparse(ref mut sparse) => sparse,
+            TileMap::Dense(_) => return,
+        };
+        let buffer = mem::take(&mut sparse.buffer);
+        let mut dense = DenseTileMap::with_buffer(sparse.rect, buffer);
+        for (&index, &value) in &sparse.data {
+            dense.data[index] = value;
+        }
+        *self = TileMap::Dense(dense);
+    }
+}
+
+impl<T> SparseTileMap<T> {
+    #[inline]
+    fn coords_to_index(&self, coords: Vector2I) -> Option<usize> {
+        if !self.rect.contains_point(coords) {
+            return None;
+        }
+        Some((coords.y() - self.rect.min_y()) as usize * self.rect.size().x() as usize
+            + (coords.x() - self.rect.min_x()) as usize)
+    }
+}
+
+impl<'a, T> Iterator for TileMapIter<'a, T> {
+    type Item = (Vector2I, &'a T);
+
+    #[inline]
+    fn next(&mut self) -> Option<(Vector2I, &'a T)> {
+        let (index, value, rect) = match *self {
+            TileMapIter::Dense { ref mut iter, rect } => {
+                let (index, value) = iter.next()?;
+                (index, value, rect)
+            }
+            TileMapIter::Sparse { ref mut iter, rect } => {
+                let (&index, value) = iter.next()?;
+                (index, value, rect)
+            }
+        };
+        let (width, index) = (rect.size().x(), index as i32);
+        Some((rect.origin() + Vector2I::new(index % width, index / width), value))
+    }
+}
+
+#[inline]
+fn tile_count(rect: RectI) -> usize {
+    rect.size().x() as usize * rect.size().y() as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::TileMap;
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::vector::Vector2I;
+
+    #[test]
+    fn test_sparse_tile_map() {
+        let rect = RectI::new(Vector2I::new(-8, 4), Vector2I::new(256, 256));
+        let mut map: TileMap<u32> = TileMap::new(rect, vec![]);
+        assert!(matches!(map, TileMap::Sparse(_)));
+
+        // A diagonal line of tiles stays sparse.
+        for index in 0..256 {
+            *map.get_mut(rect.origin() + Vector2I::splat(index)).unwrap() = index as u32 + 1;
+        }
+        assert!(matches!(map, TileMap::Sparse(_)));
+        assert_eq!(map.get(rect.origin() + Vector2I::new(3, 3)), Some(&4));
+        assert_eq!(map.get(rect.origin() + Vector2I::new(3, 4)), Some(&0));
+        assert_eq!(map.get(rect.origin() - Vector2I::splat(1)), None);
+        assert!(map.get_mut(rect.lower_right()).is_none());
+
+        let values: Vec<(Vector2I, u32)> = map.iter().map(|(coords, &value)| {
+            (coords, value)
+        }).collect();
+        assert_eq!(values.len(), 256);
+        assert_eq!(values[10], (rect.origin() + Vector2I::splat(10), 11));
+
+        // Filling the map makes it dense, keeping what's been written.
+        for y in 0..64 {
+            for x in 0..256 {
+                *map.get_mut(rect.origin() + Vector2I::new(x, y)).unwrap() += 1;
+            }
+        }
+        assert!(matches!(map, TileMap::Dense(_)));
+        assert_eq!(map.get(rect.origin() + Vector2I::new(3, 3)), Some(&5));
+        assert_eq!(map.get(rect.origin() + Vector2I::new(200, 200)), Some(&201));
+        assert_eq!(map.iter().count(), 256 * 256);
+
+        let small_rect = RectI::new(Vector2I::default(), Vector2I::splat(8));
+        assert!(matches!(TileMap::<u32>::new(small_rect, vec![]), TileMap::Dense(_)));
+    }
+}
@@ -28,6 +28,13 @@ const FLATTENING_TOLERANCE: f32 = 0.1;
 pub const TILE_WIDTH: u32 = 16;
 pub const TILE_HEIGHT: u32 = 16;
 
+/// The width and height of a chunk, in tiles.
+///
+/// Render commands give tile positions relative to the corner of the chunk that the view box
+/// starts in, so that they fit in `i16`s however far from the origin the view box lies, as it may
+/// on an infinite canvas. The renderer adds the chunk's origin back.
+pub const CHUNK_SIZE: i32 = 1024;
+
 pub(crate) struct Tiler<'a> {
     scene_builder: &'a SceneBuilder<'a>,
     pub(crate) object_builder: ObjectBuilder,
@@ -52,6 +59,11 @@ pub(crate) struct DrawTilingPathInfo<'a> {
     pub(crate) blend_mode: BlendMode,
     pub(crate) opacity: u8,
     pub(crate) built_clip_path: Option<&'a BuiltPath>,
+    // A rectangular clip, in pixels, that's applied with a scissor rect instead of a mask.
+    pub(crate) clip_rect: Option<RectI>,
+    // Whether `built_clip_path` is applied with the stencil buffer instead of a mask. It's still
+    // used to cull tiles.
+    pub(crate) stencil_clip: bool,
 }
 
 impl<'a> Tiler<'a> {
@@ -68,7 +80,7 @@ impl<'a> Tiler<'a> {
             .bounds()
             .intersection(view_box)
             .unwrap_or(RectF::default());
-        let object_builder = ObjectBuilder::new(bounds, fill_rule);
+        let object_builder = ObjectBuilder::new(bounds, fill_rule, scene_builder);
 
         Tiler {
             scene_builder,
@@ -123,7 +135,7 @@ impl<'a> Tiler<'a> {
         }
     }
 
-    fn pack_and_cull(&mut self) {
+    pub(crate) fn pack_and_cull(&mut self) {
         match self.path_info {
             TilingPathInfo::Clip => self.pack_and_cull_clip_path(),
             TilingPathInfo::Draw { .. } => self.pack_and_cull_draw_path(),
@@ -136,17 +148,11 @@ impl<'a> Tiler<'a> {
             TilingPathInfo::Draw(draw_tiling_path_info) => draw_tiling_path_info,
         };
 
-        for (draw_tile_index, draw_tile) in self.object_builder
-                                                .built_path
-                                                .tiles
-                                                .data
-                                                .iter()
-                                                .enumerate() {
-            let tile_coords = self.object_builder
-                                  .local_tile_index_to_coords(draw_tile_index as u32);
-
+        // Tiles missing from a sparse map have no fills and a zero backdrop, so they would be
+        // skipped anyway.
+        for (tile_coords, draw_tile) in self.object_builder.built_path.tiles.iter() {
             // Figure out what clip tile we need, if any.
-            let clip_tile = match draw_tiling_path_info.built_clip_path {
+            let mut clip_tile = match draw_tiling_path_info.built_clip_path {
                 None => None,
                 Some(built_clip_path) => {
                     match built_clip_path.tiles.get(tile_coords) {
@@ -170,6 +176,23 @@ impl<'a> Tiler<'a> {
                 }
             };
 
+            // Tiles outside a scissor clip can be culled. Those that straddle its edge are only
+            // partly drawn, so they mustn't be treated as occluding anything. Neither must tiles
+            // partly outside a stencil clip.
+            let mut partly_clipped = false;
+            if let Some(clip_rect) = draw_tiling_path_info.clip_rect {
+                let tile_size = Vector2I::new(TILE_WIDTH as i32, TILE_HEIGHT as i32);
+                let tile_rect = RectI::new(tile_coords.scale_xy(tile_size), tile_size);
+                if !clip_rect.intersects(tile_rect) {
+                    continue;
+                }
+                partly_clipped = !clip_rect.contains_rect(tile_rect);
+            }
+            if draw_tiling_path_info.stencil_clip && clip_tile.is_some() {
+                clip_tile = None;
+                partly_clipped = true;
+            }
+
             if clip_tile.is_none() && draw_tile.is_solid() {
                 // This is the simple case of a solid tile with no clip, so there are optimization
                 // opportunities. First, tiles that must be blank per the fill rule are always
@@ -185,7 +208,8 @@ impl<'a> Tiler<'a> {
                 // that fact and stop here.
                 if draw_tiling_path_info.paint_metadata.is_opaque &&
                         draw_tiling_path_info.blend_mode.occludes_backdrop() &&
-                        draw_tiling_path_info.opacity == !0 {
+                        draw_tiling_path_info.opacity == !0 &&
+                        !partly_clipped {
                     self.object_builder
                         .built_path
                         .solid_tiles
@@ -195,19 +219,25 @@ impl<'a> Tiler<'a> {
             }
 
             // Allocate a mask tile.
-            let mask_tile_index = self.scene_builder.allocate_mask_tile_index();
+            let local_tile_indices = &mut self.object_builder.built_path.local_tile_indices;
+            let mask_tile_index = self.scene_builder.allocate_mask_tile_index(local_tile_indices);
 
             // Add the clip primitive to the mask framebuffer, if necessary.
             if let Some(clip_tile) = clip_tile {
                 ObjectBuilder::push_mask_tile(&mut self.object_builder.built_path.mask_tiles,
+                                              &mut self.object_builder.built_path
+                                                       .local_tile_indices,
                                               clip_tile,
+                                              false,
                                               mask_tile_index,
                                               self.object_index);
             }
 
             // Add the primitive to the mask framebuffer.
             ObjectBuilder::push_mask_tile(&mut self.object_builder.built_path.mask_tiles,
+                                          &mut self.object_builder.built_path.local_tile_indices,
                                           draw_tile,
+                                          true,
                                           mask_tile_index,
                                           self.object_index);
 
@@ -215,6 +245,7 @@ impl<'a> Tiler<'a> {
             ObjectBuilder::push_alpha_tile(&mut self.object_builder.built_path.alpha_tiles,
                                            mask_tile_index,
                                            tile_coords,
+                                           self.scene_builder.chunk_origin,
                                            self.object_index,
                                            &draw_tiling_path_info);
 
@@ -296,11 +327,16 @@ impl<'a> Tiler<'a> {
                     current_winding, current_tile_x
                 );
                 let current_tile_coords = Vector2I::new(current_tile_x, tile_y);
-                if let Some(tile_index) = self.object_builder
-                                              .tile_coords_to_local_index(current_tile_coords) {
-                    // FIXME(pcwalton): Handle winding overflow.
-                    self.object_builder.built_path.tiles.data[tile_index as usize].backdrop =
-                        current_winding as i8;
+                let tiles = &mut self.object_builder.built_path.tiles;
+                // Don't store zero backdrops in untouched tiles, to keep sparse maps sparse.
+                let needs_backdrop = current_winding != 0 || match tiles.get(current_tile_coords) {
+                    Some(tile) => tile.backdrop != 0,
+                    None => false,
+                };
+                if needs_backdrop {
+                    if let Some(tile) = tiles.get_mut(current_tile_coords) {
+                        tile.backdrop = current_winding;
+                    }
                 }
 
                 current_tile_x += 1;
@@ -435,6 +471,13 @@ impl<'a> Tiler<'a> {
     }
 }
 
+// Returns the corner of the chunk that contains the upper left corner of the view box, in tiles.
+pub(crate) fn chunk_origin(view_box: RectF) -> Vector2I {
+    let tile_origin = round_rect_out_to_tile_bounds(view_box).origin();
+    Vector2I::new(tile_origin.x().div_euclid(CHUNK_SIZE),
+                  tile_origin.y().div_euclid(CHUNK_SIZE)).scale(CHUNK_SIZE)
+}
+
 pub fn round_rect_out_to_tile_bounds(rect: RectF) -> RectI {
     rect.scale_xy(Vector2F::new(
         1.0 / TILE_WIDTH as f32,
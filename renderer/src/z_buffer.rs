@@ -59,7 +59,10 @@ impl ZBuffer {
         }
     }
 
-    pub(crate) fn build_solid_tiles(&self, paint_metadata: &[PaintMetadata]) -> SolidTiles {
+    pub(crate) fn build_solid_tiles(&self,
+                                    paint_metadata: &[PaintMetadata],
+                                    chunk_origin: Vector2I)
+                                    -> SolidTiles {
         let mut solid_tiles = SolidTiles { batches: vec![] };
 
         for tile_index in 0..self.buffer.data.len() {
@@ -75,26 +78,31 @@ impl ZBuffer {
 
             let tile_position = tile_coords + self.buffer.rect.origin();
 
-            // Create a batch if necessary.
-            match solid_tiles.batches.last() {
-                Some(ref batch) if batch.color_texture_page == paint_metadata.location.page &&
-                    batch.sampling_flags == paint_metadata.sampling_flags => {}
-                _ => {
-                    // Batch break.
-                    //
-                    // TODO(pcwalton): We could be more aggressive with batching here, since we
-                    // know there are no overlaps.
+            // Solid tiles never overlap, so every tile with the same paint page can go in one
+            // batch, regardless of order.
+            let batch_index = solid_tiles.batches.iter().position(|batch| {
+                batch.color_texture_page == paint_metadata.location.page &&
+                    batch.sampling_flags == paint_metadata.sampling_flags &&
+                    batch.color_texture_alpha_type == paint_metadata.alpha_type
+            });
+            let batch_index = match batch_index {
+                Some(batch_index) => batch_index,
+                None => {
                     solid_tiles.batches.push(SolidTileBatch {
                         color_texture_page: paint_metadata.location.page,
                         sampling_flags: paint_metadata.sampling_flags,
+                        color_texture_alpha_type: paint_metadata.alpha_type,
                         tiles: vec![],
                         effects: Effects::new(Filter::Composite(CompositeOp::SrcOver)),
                     });
+                    solid_tiles.batches.len() - 1
                 }
-            }
+            };
 
-            let batch = solid_tiles.batches.last_mut().unwrap();
-            batch.tiles.push(SolidTile::from_paint_metadata(tile_position, paint_metadata));
+            let batch = &mut solid_tiles.batches[batch_index];
+            batch.tiles.push(SolidTile::from_paint_metadata(tile_position,
+                                                            chunk_origin,
+                                                            paint_metadata));
         }
 
         solid_tiles
@@ -102,20 +110,25 @@ impl ZBuffer {
 }
 
 impl SolidTile {
-    pub(crate) fn from_paint_metadata(tile_position: Vector2I, paint_metadata: &PaintMetadata)
+    // The tile position is absolute, but the vertices' positions are relative to the chunk.
+    pub(crate) fn from_paint_metadata(tile_position: Vector2I,
+                                      chunk_origin: Vector2I,
+                                      paint_metadata: &PaintMetadata)
                                       -> SolidTile {
+        let vertex = |tile_offset| {
+            SolidTileVertex::from_paint_metadata(tile_position + tile_offset,
+                                                 chunk_origin,
+                                                 paint_metadata)
+        };
         SolidTile {
-            upper_left: SolidTileVertex::from_paint_metadata(tile_position, paint_metadata),
-            upper_right: SolidTileVertex::from_paint_metadata(tile_position + Vector2I::new(1, 0),
-                                                              paint_metadata),
-            lower_left: SolidTileVertex::from_paint_metadata(tile_position + Vector2I::new(0, 1),
-                                                             paint_metadata),
-            lower_right: SolidTileVertex::from_paint_metadata(tile_position + Vector2I::new(1, 1),
-                                                              paint_metadata),
+            upper_left: vertex(Vector2I::default()),
+            upper_right: vertex(Vector2I::new(1, 0)),
+            lower_left: vertex(Vector2I::new(0, 1)),
+            lower_right: vertex(Vector2I::splat(1)),
         }
     }
 
-    // The texture rect is in normalized coordinates.
+    // The tile position is relative to the chunk. The texture rect is in normalized coordinates.
     pub(crate) fn from_texture_rect(tile_position: Vector2I, texture_rect: RectF) -> SolidTile {
         SolidTile {
             upper_left: SolidTileVertex::new(tile_position, texture_rect.origin()),
@@ -127,6 +140,13 @@ impl SolidTile {
                                               texture_rect.lower_right()),
         }
     }
+
+    pub(crate) fn translate(&mut self, tile_offset: Vector2I) {
+        self.upper_left.translate(tile_offset);
+        self.upper_right.translate(tile_offset);
+        self.lower_left.translate(tile_offset);
+        self.lower_right.translate(tile_offset);
+    }
 }
 
 impl SolidTileVertex {
@@ -139,9 +159,12 @@ impl SolidTileVertex {
         }
     }
 
-    fn from_paint_metadata(tile_position: Vector2I, paint_metadata: &PaintMetadata)
+    fn from_paint_metadata(tile_position: Vector2I,
+                           chunk_origin: Vector2I,
+                           paint_metadata: &PaintMetadata)
                            -> SolidTileVertex {
         let color_uv = paint_metadata.calculate_tex_coords(tile_position);
+        let tile_position = tile_position - chunk_origin;
         SolidTileVertex {
             tile_x: tile_position.x() as i16,
             tile_y: tile_position.y() as i16,
@@ -149,4 +172,9 @@ impl SolidTileVertex {
             color_v: color_uv.y(),
         }
     }
+
+    fn translate(&mut self, tile_offset: Vector2I) {
+        self.tile_x += tile_offset.x() as i16;
+        self.tile_y += tile_offset.y() as i16;
+    }
 }
@@ -31,6 +31,20 @@ fn main() {
             continue;
         }
 
+        // A resource that only one subsystem uses is prefixed with the feature that turns that
+        // subsystem on, like `pf-debug-ui: textures/debug-font.png`, and is left out without it.
+        let line = match line.find(':') {
+            None => line,
+            Some(colon_index) => {
+                let feature = &line[..colon_index];
+                let env_var = format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"));
+                if env::var_os(env_var).is_none() {
+                    continue;
+                }
+                line[(colon_index + 1)..].trim_start()
+            }
+        };
+
         let escaped_path = line.escape_default().to_string();
         let mut full_path = cwd.clone();
         full_path.push(line);
@@ -21,7 +21,7 @@ in vec2 vTexCoord;
 out vec4 oFragColor;
 
 void main(){
-    vec4 color = texture(uSrc, vTexCoord);
-    oFragColor = vec4(color . rgb * color . a, color . a);
+
+    oFragColor = texture(uSrc, vTexCoord);
 }
 
@@ -14,12 +14,13 @@
 
 precision highp float;
 
-in vec2 aPosition;
+in ivec2 aPosition;
 
 out vec2 vTexCoord;
 
 void main(){
-    vTexCoord = aPosition;
-    gl_Position = vec4(mix(aPosition, vec2(- 1.0), vec2(1.0)), 0.0, 1.0);
+    vec2 position = vec2(aPosition);
+    vTexCoord = position;
+    gl_Position = vec4(mix(vec2(- 1.0), vec2(1.0), position), 0.0, 1.0);
 }
 
@@ -0,0 +1,50 @@
+#version {{version}}
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+precision highp float;
+
+
+
+
+
+uniform sampler2D uSrc;
+
+uniform sampler2D uSpotLUT;
+
+in vec2 vTexCoord;
+
+out vec4 oFragColor;
+
+vec3 sampleSpotLUT(vec3 color){
+    float blue = clamp(color . b, 0.0, 1.0)*(16.0 - 1.0);
+    float blueSlice0 = floor(blue);
+    float blueSlice1 = min(blueSlice0 + 1.0, 16.0 - 1.0);
+
+    vec2 sliceTexCoord =(clamp(color . rg, 0.0, 1.0)*(16.0 - 1.0)+ 0.5)/
+        vec2(16.0 * 16.0, 16.0);
+    vec3 color0 = texture(uSpotLUT, sliceTexCoord + vec2(blueSlice0 / 16.0, 0.0)). rgb;
+    vec3 color1 = texture(uSpotLUT, sliceTexCoord + vec2(blueSlice1 / 16.0, 0.0)). rgb;
+    return mix(color0, color1, blue - blueSlice0);
+}
+
+void main(){
+
+    vec4 color = texture(uSrc, vTexCoord);
+    if(color . a > 0.0)
+        color . rgb /= color . a;
+    oFragColor = vec4(sampleSpotLUT(color . rgb)* color . a, color . a);
+}
+
@@ -0,0 +1,53 @@
+#version {{version}}
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+precision highp float;
+
+uniform sampler2D uSrc;
+
+uniform vec4 uSrcRect;
+
+uniform int uSrcIsStats;
+
+out vec4 oFragColor;
+
+void main(){
+    ivec4 srcRect = ivec4(uSrcRect);
+    ivec2 blockOrigin = srcRect . xy + ivec2(gl_FragCoord . xy)* 4;
+
+    vec4 sum = vec4(0.0);
+    for(int y = 0;y < 4;y ++){
+        for(int x = 0;x < 4;x ++){
+            ivec2 texelCoord = blockOrigin + ivec2(x, y);
+            if(texelCoord . x >= srcRect . z || texelCoord . y >= srcRect . w)
+                continue;
+
+            vec4 texel = texelFetch(uSrc, texelCoord, 0);
+            if(uSrcIsStats != 0){
+                sum += texel;
+            } else {
+                float alpha = texel . a;
+                sum += vec4(alpha, alpha > 0.0 ? 1.0 : 0.0, alpha >= 1.0 ? 1.0 : 0.0, 1.0);
+            }
+        }
+    }
+
+
+    oFragColor = sum / 16.0;
+}
+
@@ -14,6 +14,10 @@
 
 precision highp float;
 
+
+
+precision highp sampler2D;
+
 uniform sampler2D uFillTexture;
 
 in vec2 vFillTexCoord;
@@ -12,7 +12,7 @@
 
 
 
-#extension GL_GOOGLE_include_directive : enable
+
 
 precision highp float;
 
@@ -31,6 +31,8 @@ out vec4 oFragColor;
 
 uniform sampler2D uStencilTexture;
 uniform sampler2D uPaintTexture;
+
+uniform int uPaintTexturePremultiplied;
 uniform sampler2D uDest;
 uniform vec2 uFramebufferSize;
 
@@ -42,6 +44,8 @@ in float vOpacity;
 vec4 sampleSrcColor(){
     float coverage = texture(uStencilTexture, vMaskTexCoord). r;
     vec4 srcRGBA = texture(uPaintTexture, vColorTexCoord);
+    if(uPaintTexturePremultiplied != 0 && srcRGBA . a > 0.0)
+        srcRGBA . rgb /= srcRGBA . a;
     return vec4(srcRGBA . rgb, srcRGBA . a * coverage * vOpacity);
 }
 
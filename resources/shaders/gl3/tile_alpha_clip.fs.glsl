@@ -0,0 +1,35 @@
+#version {{version}}
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+precision highp float;
+
+uniform sampler2D uStencilTexture;
+
+
+
+in vec2 vColorTexCoord;
+in vec2 vMaskTexCoord;
+in float vOpacity;
+
+out vec4 oFragColor;
+
+void main(){
+    if(texture(uStencilTexture, vMaskTexCoord). r < 0.5)
+        discard;
+    oFragColor = vec4(1.0);
+}
+
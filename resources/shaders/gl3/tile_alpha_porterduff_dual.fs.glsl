@@ -0,0 +1,51 @@
+#version {{version}}
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+precision highp float;
+
+uniform sampler2D uStencilTexture;
+uniform sampler2D uPaintTexture;
+
+uniform int uPaintTexturePremultiplied;
+uniform int uPorterDuffOp;
+
+in vec2 vColorTexCoord;
+in vec2 vMaskTexCoord;
+in float vOpacity;
+
+layout(location = 0, index = 0)out vec4 oFragColor;
+layout(location = 0, index = 1)out vec4 oFragBlendFactor;
+
+void main(){
+    float coverage = texture(uStencilTexture, vMaskTexCoord). r;
+    vec4 srcRGBA = texture(uPaintTexture, vColorTexCoord);
+    if(uPaintTexturePremultiplied == 0)
+        srcRGBA . rgb *= srcRGBA . a;
+    srcRGBA *= vOpacity;
+
+    bool destFactorIsSrcAlpha = uPorterDuffOp == 2 ||
+        uPorterDuffOp == 4;
+    float destFactor = destFactorIsSrcAlpha ? srcRGBA . a : 0.0;
+
+    oFragColor = srcRGBA * coverage;
+    oFragBlendFactor = vec4(1.0 - coverage + coverage * destFactor);
+}
@@ -16,12 +16,16 @@ precision highp float;
 
 uniform sampler2D uColorTexture;
 
+uniform int uColorTexturePremultiplied;
+
 in vec2 vColorTexCoord;
 
 out vec4 oFragColor;
 
 void main(){
     vec4 color = texture(uColorTexture, vColorTexCoord);
-    oFragColor = vec4(color . rgb * color . a, color . a);
+    if(uColorTexturePremultiplied == 0)
+        color . rgb *= color . a;
+    oFragColor = color;
 }
 
@@ -20,11 +20,13 @@
 
 
 
-#extension GL_GOOGLE_include_directive : enable
+
 
 precision highp float;
 
 uniform sampler2D uColorTexture;
+
+uniform int uColorTexturePremultiplied;
 uniform vec2 uSrcOffsetScale;
 uniform vec3 uInitialGaussCoeff;
 uniform int uSupport;
@@ -63,7 +65,8 @@ void main(){
 
 
     color /= gaussSum;
-    color . rgb *= color . a;
+    if(uColorTexturePremultiplied == 0)
+        color . rgb *= color . a;
     oFragColor = color;
 }
 
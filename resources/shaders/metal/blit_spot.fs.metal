@@ -0,0 +1,49 @@
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+#include <metal_stdlib>
+#include <simd/simd.h>
+
+using namespace metal;
+
+struct spvDescriptorSetBuffer0
+{
+    texture2d<float> uSpotLUT [[id(0)]];
+    sampler uSpotLUTSmplr [[id(1)]];
+    texture2d<float> uSrc [[id(2)]];
+    sampler uSrcSmplr [[id(3)]];
+};
+
+struct main0_out
+{
+    float4 oFragColor [[color(0)]];
+};
+
+struct main0_in
+{
+    float2 vTexCoord [[user(locn0)]];
+};
+
+float3 sampleSpotLUT(thread const float3& color, thread texture2d<float> uSpotLUT, thread const sampler uSpotLUTSmplr)
+{
+    float blue = fast::clamp(color.z, 0.0, 1.0) * 15.0;
+    float blueSlice0 = floor(blue);
+    float blueSlice1 = fast::min(blueSlice0 + 1.0, 15.0);
+    float2 sliceTexCoord = ((fast::clamp(color.xy, float2(0.0), float2(1.0)) * 15.0) + float2(0.5)) / float2(256.0, 16.0);
+    float3 color0 = uSpotLUT.sample(uSpotLUTSmplr, (sliceTexCoord + float2(blueSlice0 / 16.0, 0.0))).xyz;
+    float3 color1 = uSpotLUT.sample(uSpotLUTSmplr, (sliceTexCoord + float2(blueSlice1 / 16.0, 0.0))).xyz;
+    return mix(color0, color1, float3(blue - blueSlice0));
+}
+
+fragment main0_out main0(main0_in in [[stage_in]], constant spvDescriptorSetBuffer0& spvDescriptorSet0 [[buffer(0)]])
+{
+    main0_out out = {};
+    float4 color = spvDescriptorSet0.uSrc.sample(spvDescriptorSet0.uSrcSmplr, in.vTexCoord);
+    if (color.w > 0.0)
+    {
+        float3 _110 = color.xyz / float3(color.w);
+        color = float4(_110.x, _110.y, _110.z, color.w);
+    }
+    float3 param = color.xyz;
+    out.oFragColor = float4(sampleSpotLUT(param, spvDescriptorSet0.uSpotLUT, spvDescriptorSet0.uSpotLUTSmplr) * color.w, color.w);
+    return out;
+}
+
@@ -0,0 +1,50 @@
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+#include <metal_stdlib>
+#include <simd/simd.h>
+
+using namespace metal;
+
+struct spvDescriptorSetBuffer0
+{
+    constant float4* uSrcRect [[id(0)]];
+    texture2d<float> uSrc [[id(1)]];
+    sampler uSrcSmplr [[id(2)]];
+    constant int* uSrcIsStats [[id(3)]];
+};
+
+struct main0_out
+{
+    float4 oFragColor [[color(0)]];
+};
+
+fragment main0_out main0(constant spvDescriptorSetBuffer0& spvDescriptorSet0 [[buffer(0)]], float4 gl_FragCoord [[position]])
+{
+    main0_out out = {};
+    int4 srcRect = int4((*spvDescriptorSet0.uSrcRect));
+    int2 blockOrigin = srcRect.xy + (int2(gl_FragCoord.xy) * int2(4));
+    float4 sum = float4(0.0);
+    for (int y = 0; y < 4; y++)
+    {
+        for (int x = 0; x < 4; x++)
+        {
+            int2 texelCoord = blockOrigin + int2(x, y);
+            if ((texelCoord.x >= srcRect.z) || (texelCoord.y >= srcRect.w))
+            {
+                continue;
+            }
+            float4 texel = spvDescriptorSet0.uSrc.read(uint2(texelCoord), 0);
+            if ((*spvDescriptorSet0.uSrcIsStats) != 0)
+            {
+                sum += texel;
+            }
+            else
+            {
+                float alpha = texel.w;
+                sum += float4(alpha, (alpha > 0.0) ? 1.0 : 0.0, (alpha >= 1.0) ? 1.0 : 0.0, 1.0);
+            }
+        }
+    }
+    out.oFragColor = sum / float4(16.0);
+    return out;
+}
+
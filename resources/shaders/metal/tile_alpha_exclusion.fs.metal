@@ -15,6 +15,7 @@ struct spvDescriptorSetBuffer0
     constant float2* uFramebufferSize [[id(4)]];
     texture2d<float> uDest [[id(5)]];
     sampler uDestSmplr [[id(6)]];
+    constant int* uPaintTexturePremultiplied [[id(7)]];
 };
 
 struct main0_out
@@ -29,10 +30,25 @@ struct main0_in
     float vOpacity [[user(locn2)]];
 };
 
-float4 sampleSrcColor(thread texture2d<float> uStencilTexture, thread const sampler uStencilTextureSmplr, thread float2& vMaskTexCoord, thread texture2d<float> uPaintTexture, thread const sampler uPaintTextureSmplr, thread float2& vColorTexCoord, thread float& vOpacity)
+float4 sampleSrcColor(thread texture2d<float> uStencilTexture, thread const sampler uStencilTextureSmplr, thread float2& vMaskTexCoord, thread texture2d<float> uPaintTexture, thread const sampler uPaintTextureSmplr, thread float2& vColorTexCoord, thread float& vOpacity, constant int& uPaintTexturePremultiplied)
 {
     float coverage = uStencilTexture.sample(uStencilTextureSmplr, vMaskTexCoord).x;
     float4 srcRGBA = uPaintTexture.sample(uPaintTextureSmplr, vColorTexCoord);
+    bool _39 = uPaintTexturePremultiplied != 0;
+    bool _45;
+    if (_39)
+    {
+        _45 = srcRGBA.w > 0.0;
+    }
+    else
+    {
+        _45 = _39;
+    }
+    if (_45)
+    {
+        float3 _55 = srcRGBA.xyz / float3(srcRGBA.w);
+        srcRGBA = float4(_55.x, _55.y, _55.z, srcRGBA.w);
+    }
     return float4(srcRGBA.xyz, (srcRGBA.w * coverage) * vOpacity);
 }
 
@@ -50,7 +66,7 @@ float4 blendColors(thread const float4& destRGBA, thread const float4& srcRGBA,
 fragment main0_out main0(main0_in in [[stage_in]], constant spvDescriptorSetBuffer0& spvDescriptorSet0 [[buffer(0)]], float4 gl_FragCoord [[position]])
 {
     main0_out out = {};
-    float4 srcRGBA = sampleSrcColor(spvDescriptorSet0.uStencilTexture, spvDescriptorSet0.uStencilTextureSmplr, in.vMaskTexCoord, spvDescriptorSet0.uPaintTexture, spvDescriptorSet0.uPaintTextureSmplr, in.vColorTexCoord, in.vOpacity);
+    float4 srcRGBA = sampleSrcColor(spvDescriptorSet0.uStencilTexture, spvDescriptorSet0.uStencilTextureSmplr, in.vMaskTexCoord, spvDescriptorSet0.uPaintTexture, spvDescriptorSet0.uPaintTextureSmplr, in.vColorTexCoord, in.vOpacity, (*spvDescriptorSet0.uPaintTexturePremultiplied));
     float4 destRGBA = sampleDestColor(gl_FragCoord, (*spvDescriptorSet0.uFramebufferSize), spvDescriptorSet0.uDest, spvDescriptorSet0.uDestSmplr);
     float3 dest = destRGBA.xyz;
     float3 src = srcRGBA.xyz;
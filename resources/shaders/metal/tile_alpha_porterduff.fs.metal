@@ -0,0 +1,86 @@
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+#pragma clang diagnostic ignored "-Wmissing-prototypes"
+
+#include <metal_stdlib>
+#include <simd/simd.h>
+
+using namespace metal;
+
+struct spvDescriptorSetBuffer0
+{
+    texture2d<float> uStencilTexture [[id(0)]];
+    sampler uStencilTextureSmplr [[id(1)]];
+    texture2d<float> uPaintTexture [[id(2)]];
+    sampler uPaintTextureSmplr [[id(3)]];
+    constant float2* uFramebufferSize [[id(4)]];
+    texture2d<float> uDest [[id(5)]];
+    sampler uDestSmplr [[id(6)]];
+    constant int* uPorterDuffOp [[id(7)]];
+    constant int* uPaintTexturePremultiplied [[id(8)]];
+};
+
+struct main0_out
+{
+    float4 oFragColor [[color(0)]];
+};
+
+struct main0_in
+{
+    float2 vColorTexCoord [[user(locn0)]];
+    float2 vMaskTexCoord [[user(locn1)]];
+    float vOpacity [[user(locn2)]];
+};
+
+float4 sampleDestColor(thread float4& gl_FragCoord, thread float2 uFramebufferSize, thread texture2d<float> uDest, thread const sampler uDestSmplr)
+{
+    float2 destTexCoord = gl_FragCoord.xy / uFramebufferSize;
+    return uDest.sample(uDestSmplr, destTexCoord);
+}
+
+fragment main0_out main0(main0_in in [[stage_in]], constant spvDescriptorSetBuffer0& spvDescriptorSet0 [[buffer(0)]], float4 gl_FragCoord [[position]])
+{
+    main0_out out = {};
+    float coverage = spvDescriptorSet0.uStencilTexture.sample(spvDescriptorSet0.uStencilTextureSmplr, in.vMaskTexCoord).x;
+    float4 srcRGBA = spvDescriptorSet0.uPaintTexture.sample(spvDescriptorSet0.uPaintTextureSmplr, in.vColorTexCoord);
+    if ((*spvDescriptorSet0.uPaintTexturePremultiplied) == 0)
+    {
+        float3 _42 = srcRGBA.xyz * srcRGBA.w;
+        srcRGBA = float4(_42.x, _42.y, _42.z, srcRGBA.w);
+    }
+    srcRGBA *= in.vOpacity;
+    float4 destRGBA = sampleDestColor(gl_FragCoord, (*spvDescriptorSet0.uFramebufferSize), spvDescriptorSet0.uDest, spvDescriptorSet0.uDestSmplr);
+    float2 factors;
+    if ((*spvDescriptorSet0.uPorterDuffOp) == 0)
+    {
+        factors = float2(1.0, 0.0);
+    }
+    else
+    {
+        if ((*spvDescriptorSet0.uPorterDuffOp) == 1)
+        {
+            factors = float2(destRGBA.w, 0.0);
+        }
+        else
+        {
+            if ((*spvDescriptorSet0.uPorterDuffOp) == 2)
+            {
+                factors = float2(0.0, srcRGBA.w);
+            }
+            else
+            {
+                if ((*spvDescriptorSet0.uPorterDuffOp) == 3)
+                {
+                    factors = float2(1.0 - destRGBA.w, 0.0);
+                }
+                else
+                {
+                    factors = float2(1.0 - destRGBA.w, srcRGBA.w);
+                }
+            }
+        }
+    }
+    float4 composited = (srcRGBA * factors.x) + (destRGBA * factors.y);
+    out.oFragColor = mix(destRGBA, composited, float4(coverage));
+    return out;
+}
+
@@ -0,0 +1,66 @@
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+#include <metal_stdlib>
+#include <simd/simd.h>
+
+using namespace metal;
+
+struct spvDescriptorSetBuffer0
+{
+    texture2d<float> uStencilTexture [[id(0)]];
+    sampler uStencilTextureSmplr [[id(1)]];
+    texture2d<float> uPaintTexture [[id(2)]];
+    sampler uPaintTextureSmplr [[id(3)]];
+    constant int* uPorterDuffOp [[id(4)]];
+    constant int* uPaintTexturePremultiplied [[id(5)]];
+};
+
+struct main0_out
+{
+    float4 oFragColor [[color(0), index(0)]];
+    float4 oFragBlendFactor [[color(0), index(1)]];
+};
+
+struct main0_in
+{
+    float2 vColorTexCoord [[user(locn0)]];
+    float2 vMaskTexCoord [[user(locn1)]];
+    float vOpacity [[user(locn2)]];
+};
+
+fragment main0_out main0(main0_in in [[stage_in]], constant spvDescriptorSetBuffer0& spvDescriptorSet0 [[buffer(0)]])
+{
+    main0_out out = {};
+    float coverage = spvDescriptorSet0.uStencilTexture.sample(spvDescriptorSet0.uStencilTextureSmplr, in.vMaskTexCoord).x;
+    float4 srcRGBA = spvDescriptorSet0.uPaintTexture.sample(spvDescriptorSet0.uPaintTextureSmplr, in.vColorTexCoord);
+    if ((*spvDescriptorSet0.uPaintTexturePremultiplied) == 0)
+    {
+        float3 _42 = srcRGBA.xyz * srcRGBA.w;
+        srcRGBA = float4(_42.x, _42.y, _42.z, srcRGBA.w);
+    }
+    srcRGBA *= in.vOpacity;
+    bool _50 = (*spvDescriptorSet0.uPorterDuffOp) == 2;
+    bool _57;
+    if (!_50)
+    {
+        _57 = (*spvDescriptorSet0.uPorterDuffOp) == 4;
+    }
+    else
+    {
+        _57 = _50;
+    }
+    bool destFactorIsSrcAlpha = _57;
+    float _64;
+    if (destFactorIsSrcAlpha)
+    {
+        _64 = srcRGBA.w;
+    }
+    else
+    {
+        _64 = 0.0;
+    }
+    float destFactor = _64;
+    out.oFragColor = srcRGBA * coverage;
+    out.oFragBlendFactor = float4((1.0 - coverage) + (coverage * destFactor));
+    return out;
+}
+
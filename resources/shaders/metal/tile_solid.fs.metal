@@ -8,6 +8,7 @@ struct spvDescriptorSetBuffer0
 {
     texture2d<float> uColorTexture [[id(0)]];
     sampler uColorTextureSmplr [[id(1)]];
+    constant int* uColorTexturePremultiplied [[id(2)]];
 };
 
 struct main0_out
@@ -24,7 +25,12 @@ fragment main0_out main0(main0_in in [[stage_in]], constant spvDescriptorSetBuff
 {
     main0_out out = {};
     float4 color = spvDescriptorSet0.uColorTexture.sample(spvDescriptorSet0.uColorTextureSmplr, in.vColorTexCoord);
-    out.oFragColor = float4(color.xyz * color.w, color.w);
+    if ((*spvDescriptorSet0.uColorTexturePremultiplied) == 0)
+    {
+        float3 _30 = color.xyz * color.w;
+        color = float4(_30.x, _30.y, _30.z, color.w);
+    }
+    out.oFragColor = color;
     return out;
 }
 
@@ -11,6 +11,7 @@ struct spvDescriptorSetBuffer0
     sampler uColorTextureSmplr [[id(2)]];
     constant int* uSupport [[id(3)]];
     constant float2* uSrcOffsetScale [[id(4)]];
+    constant int* uColorTexturePremultiplied [[id(5)]];
 };
 
 struct main0_out
@@ -44,8 +45,11 @@ fragment main0_out main0(main0_in in [[stage_in]], constant spvDescriptorSetBuff
         gaussCoeff = float3(_108.x, _108.y, gaussCoeff.z);
     }
     color /= float4(gaussSum);
-    float3 _123 = color.xyz * color.w;
-    color = float4(_123.x, _123.y, _123.z, color.w);
+    if ((*spvDescriptorSet0.uColorTexturePremultiplied) == 0)
+    {
+        float3 _128 = color.xyz * color.w;
+        color = float4(_128.x, _128.y, _128.z, color.w);
+    }
     out.oFragColor = color;
     return out;
 }
@@ -32,3 +32,20 @@ impl ResourceLoader for EmbeddedResourceLoader {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::EmbeddedResourceLoader;
+    use crate::ResourceLoader;
+
+    #[test]
+    fn test_resources_of_disabled_features_are_left_out() {
+        let loader = EmbeddedResourceLoader::new();
+        let embedded = |path| loader.slurp(path).is_ok();
+        assert!(embedded("shaders/gl3/blit.vs.glsl"));
+        assert_eq!(embedded("textures/debug-font.png"), cfg!(feature = "pf-debug-ui"));
+        assert_eq!(embedded("shaders/gl3/tile_solid_filter_blur.fs.glsl"),
+                   cfg!(feature = "pf-filter-blur"));
+        assert_eq!(embedded("textures/gamma-lut.png"), cfg!(feature = "pf-filter-text"));
+    }
+}
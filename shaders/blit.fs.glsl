@@ -19,6 +19,6 @@ in vec2 vTexCoord;
 out vec4 oFragColor;
 
 void main() {
-    vec4 color = texture(uSrc, vTexCoord);
-    oFragColor = vec4(color.rgb * color.a, color.a);
+    // The source is a framebuffer, so it's already premultiplied.
+    oFragColor = texture(uSrc, vTexCoord);
 }
@@ -0,0 +1,47 @@
+#version 330
+
+// pathfinder/shaders/blit_spot.fs.glsl
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Blits the composited scene, remapping every color through a spot color lookup table.
+
+precision highp float;
+
+// The number of samples along each edge of the color cube. Must match `SPOT_LUT_SIZE` in
+// `pathfinder_renderer`.
+#define LUT_SIZE    16.0
+
+uniform sampler2D uSrc;
+// A 3D lookup table stored as a row of `LUT_SIZE` slices along the blue axis.
+uniform sampler2D uSpotLUT;
+
+in vec2 vTexCoord;
+
+out vec4 oFragColor;
+
+vec3 sampleSpotLUT(vec3 color) {
+    float blue = clamp(color.b, 0.0, 1.0) * (LUT_SIZE - 1.0);
+    float blueSlice0 = floor(blue);
+    float blueSlice1 = min(blueSlice0 + 1.0, LUT_SIZE - 1.0);
+
+    vec2 sliceTexCoord = (clamp(color.rg, 0.0, 1.0) * (LUT_SIZE - 1.0) + 0.5) /
+        vec2(LUT_SIZE * LUT_SIZE, LUT_SIZE);
+    vec3 color0 = texture(uSpotLUT, sliceTexCoord + vec2(blueSlice0 / LUT_SIZE, 0.0)).rgb;
+    vec3 color1 = texture(uSpotLUT, sliceTexCoord + vec2(blueSlice1 / LUT_SIZE, 0.0)).rgb;
+    return mix(color0, color1, blue - blueSlice0);
+}
+
+void main() {
+    // The source is a framebuffer, so it's premultiplied, but the LUT maps straight colors.
+    vec4 color = texture(uSrc, vTexCoord);
+    if (color.a > 0.0)
+        color.rgb /= color.a;
+    oFragColor = vec4(sampleSpotLUT(color.rgb) * color.a, color.a);
+}
@@ -0,0 +1,50 @@
+#version 330
+
+// pathfinder/shaders/coverage_reduce.fs.glsl
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// One step of reducing a region of a framebuffer to the statistics of a coverage query: each
+// fragment averages a 4x4 block of the source. The statistics are the coverage, whether the pixel
+// is covered at all, whether it's fully covered, and 1, so that the sums count pixels.
+
+precision highp float;
+
+uniform sampler2D uSrc;
+// The region of the source to reduce, as the upper left and lower right texel coordinates.
+uniform vec4 uSrcRect;
+// Nonzero if the source holds statistics from a previous step, instead of colors.
+uniform int uSrcIsStats;
+
+out vec4 oFragColor;
+
+void main() {
+    ivec4 srcRect = ivec4(uSrcRect);
+    ivec2 blockOrigin = srcRect.xy + ivec2(gl_FragCoord.xy) * 4;
+
+    vec4 sum = vec4(0.0);
+    for (int y = 0; y < 4; y++) {
+        for (int x = 0; x < 4; x++) {
+            ivec2 texelCoord = blockOrigin + ivec2(x, y);
+            if (texelCoord.x >= srcRect.z || texelCoord.y >= srcRect.w)
+                continue;
+
+            vec4 texel = texelFetch(uSrc, texelCoord, 0);
+            if (uSrcIsStats != 0) {
+                sum += texel;
+            } else {
+                float alpha = texel.a;
+                sum += vec4(alpha, alpha > 0.0 ? 1.0 : 0.0, alpha >= 1.0 ? 1.0 : 0.0, 1.0);
+            }
+        }
+    }
+
+    // Average rather than sum, so that half-float framebuffers can't overflow.
+    oFragColor = sum / 16.0;
+}
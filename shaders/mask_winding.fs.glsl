@@ -12,6 +12,10 @@
 
 precision highp float;
 
+// The fill texture holds unnormalized coverage, which OpenGL ES's default `lowp` samplers can't
+// represent.
+precision highp sampler2D;
+
 uniform sampler2D uFillTexture;
 
 in vec2 vFillTexCoord;
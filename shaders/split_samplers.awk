@@ -0,0 +1,11 @@
+# Splits each combined `sampler2D` uniform into a separate texture and sampler, as wgpu requires,
+# and redirects uses of the original name to a combined sampler constructed from the two.
+$1 == "uniform" && $2 == "sampler2D" {
+    name = $3
+    sub(/;.*/, "", name)
+    print "uniform texture2D " name "Image;"
+    print "uniform sampler " name "Smplr;"
+    print "#define " name " sampler2D(" name "Image, " name "Smplr)"
+    next
+}
+{ print }
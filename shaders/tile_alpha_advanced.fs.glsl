@@ -0,0 +1,31 @@
+#version 330
+
+// pathfinder/shaders/tile_alpha_advanced.fs.glsl
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Draws alpha tiles for an advanced blend equation, such as `GL_MULTIPLY_KHR`, which does the
+// blending itself. This is the same as `tile_alpha.fs.glsl`, except that the output is declared
+// to be usable with those equations.
+
+#extension GL_GOOGLE_include_directive : enable
+#extension GL_KHR_blend_equation_advanced : require
+
+precision highp float;
+
+layout(blend_support_all_equations) out;
+
+out vec4 oFragColor;
+
+#include "tile_alpha_sample.inc.glsl"
+
+void main() {
+    vec4 srcRGBA = sampleSrcColor();
+    oFragColor = vec4(srcRGBA.rgb * srcRGBA.a, srcRGBA.a);
+}
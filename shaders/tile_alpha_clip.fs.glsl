@@ -0,0 +1,32 @@
+#version 330
+
+// pathfinder/shaders/tile_alpha_clip.fs.glsl
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Draws the parts of a clip path's tiles that are at least half covered. The color is masked
+// out; this only writes to the stencil buffer.
+
+precision highp float;
+
+uniform sampler2D uStencilTexture;
+
+// These must be declared in the same order as in the vertex shader, so that they get the same
+// locations, even though only the mask coordinates are used.
+in vec2 vColorTexCoord;
+in vec2 vMaskTexCoord;
+in float vOpacity;
+
+out vec4 oFragColor;
+
+void main() {
+    if (texture(uStencilTexture, vMaskTexCoord).r < 0.5)
+        discard;
+    oFragColor = vec4(1.0);
+}
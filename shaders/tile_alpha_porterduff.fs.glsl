@@ -0,0 +1,54 @@
+#version 330
+
+// pathfinder/shaders/tile_alpha_porterduff.fs.glsl
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The copy, source-in, destination-in, source-out, and destination-atop blend modes, for devices
+// without dual-source blending. The operator's result is mixed with the destination by coverage.
+
+#extension GL_GOOGLE_include_directive : enable
+
+#define PORTER_DUFF_OP_COPY         0
+#define PORTER_DUFF_OP_SRC_IN       1
+#define PORTER_DUFF_OP_DEST_IN      2
+#define PORTER_DUFF_OP_SRC_OUT      3
+#define PORTER_DUFF_OP_DEST_ATOP    4
+
+precision highp float;
+
+uniform int uPorterDuffOp;
+
+out vec4 oFragColor;
+
+#include "tile_alpha_sample.inc.glsl"
+
+void main() {
+    float coverage = texture(uStencilTexture, vMaskTexCoord).r;
+    vec4 srcRGBA = texture(uPaintTexture, vColorTexCoord);
+    if (uPaintTexturePremultiplied == 0)
+        srcRGBA.rgb *= srcRGBA.a;
+    srcRGBA *= vOpacity;
+    vec4 destRGBA = sampleDestColor();
+
+    vec2 factors;
+    if (uPorterDuffOp == PORTER_DUFF_OP_COPY)
+        factors = vec2(1.0, 0.0);
+    else if (uPorterDuffOp == PORTER_DUFF_OP_SRC_IN)
+        factors = vec2(destRGBA.a, 0.0);
+    else if (uPorterDuffOp == PORTER_DUFF_OP_DEST_IN)
+        factors = vec2(0.0, srcRGBA.a);
+    else if (uPorterDuffOp == PORTER_DUFF_OP_SRC_OUT)
+        factors = vec2(1.0 - destRGBA.a, 0.0);
+    else
+        factors = vec2(1.0 - destRGBA.a, srcRGBA.a);
+
+    vec4 composited = srcRGBA * factors.x + destRGBA * factors.y;
+    oFragColor = mix(destRGBA, composited, coverage);
+}
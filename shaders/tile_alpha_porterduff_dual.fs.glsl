@@ -0,0 +1,49 @@
+#version 330
+
+// pathfinder/shaders/tile_alpha_porterduff_dual.fs.glsl
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The same blend modes as `tile_alpha_porterduff.fs.glsl`, with dual-source blending. The blend
+// state applies the operator's source factor to the first output and multiplies the destination
+// by the second output's alpha, which is the operator's destination factor mixed with one by
+// coverage.
+
+#define PORTER_DUFF_OP_DEST_IN      2
+#define PORTER_DUFF_OP_DEST_ATOP    4
+
+precision highp float;
+
+uniform sampler2D uStencilTexture;
+uniform sampler2D uPaintTexture;
+// Nonzero if the paint texture holds premultiplied colors, as render targets do.
+uniform int uPaintTexturePremultiplied;
+uniform int uPorterDuffOp;
+
+in vec2 vColorTexCoord;
+in vec2 vMaskTexCoord;
+in float vOpacity;
+
+layout(location = 0, index = 0) out vec4 oFragColor;
+layout(location = 0, index = 1) out vec4 oFragBlendFactor;
+
+void main() {
+    float coverage = texture(uStencilTexture, vMaskTexCoord).r;
+    vec4 srcRGBA = texture(uPaintTexture, vColorTexCoord);
+    if (uPaintTexturePremultiplied == 0)
+        srcRGBA.rgb *= srcRGBA.a;
+    srcRGBA *= vOpacity;
+
+    bool destFactorIsSrcAlpha = uPorterDuffOp == PORTER_DUFF_OP_DEST_IN ||
+        uPorterDuffOp == PORTER_DUFF_OP_DEST_ATOP;
+    float destFactor = destFactorIsSrcAlpha ? srcRGBA.a : 0.0;
+
+    oFragColor = srcRGBA * coverage;
+    oFragBlendFactor = vec4(1.0 - coverage + coverage * destFactor);
+}
@@ -10,6 +10,8 @@
 
 uniform sampler2D uStencilTexture;
 uniform sampler2D uPaintTexture;
+// Nonzero if the paint texture holds premultiplied colors, as render targets do.
+uniform int uPaintTexturePremultiplied;
 uniform sampler2D uDest;
 uniform vec2 uFramebufferSize;
 
@@ -17,10 +19,12 @@ in vec2 vColorTexCoord;
 in vec2 vMaskTexCoord;
 in float vOpacity;
 
-// NB: This does not premultiply.
+// NB: This does not premultiply. Premultiplied paints are unpremultiplied.
 vec4 sampleSrcColor() {
     float coverage = texture(uStencilTexture, vMaskTexCoord).r;
     vec4 srcRGBA = texture(uPaintTexture, vColorTexCoord);
+    if (uPaintTexturePremultiplied != 0 && srcRGBA.a > 0.0)
+        srcRGBA.rgb /= srcRGBA.a;
     return vec4(srcRGBA.rgb, srcRGBA.a * coverage * vOpacity);
 }
 
@@ -13,6 +13,8 @@
 precision highp float;
 
 uniform sampler2D uColorTexture;
+// Nonzero if the color texture holds premultiplied colors, as render targets do.
+uniform int uColorTexturePremultiplied;
 
 in vec2 vColorTexCoord;
 
@@ -20,5 +22,7 @@ out vec4 oFragColor;
 
 void main() {
     vec4 color = texture(uColorTexture, vColorTexCoord);
-    oFragColor = vec4(color.rgb * color.a, color.a);
+    if (uColorTexturePremultiplied == 0)
+        color.rgb *= color.a;
+    oFragColor = color;
 }
@@ -23,6 +23,8 @@
 precision highp float;
 
 uniform sampler2D uColorTexture;
+// Nonzero if the color texture holds premultiplied colors, as render targets do.
+uniform int uColorTexturePremultiplied;
 uniform vec2 uSrcOffsetScale;
 uniform vec3 uInitialGaussCoeff;
 uniform int uSupport;
@@ -61,6 +63,7 @@ void main() {
 
     // Finish.
     color /= gaussSum;
-    color.rgb *= color.a;
+    if (uColorTexturePremultiplied == 0)
+        color.rgb *= color.a;
     oFragColor = color;
 }
@@ -8,13 +8,21 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::arch::aarch64::{self, float32x2_t, float32x4_t, int32x2_t, int32x4_t};
-use std::arch::aarch64::{uint32x2_t, uint32x4_t};
-use std::f32;
-use std::fmt::{self, Debug, Formatter};
-use std::mem;
-use std::ops::{Add, BitAnd, BitOr, Index, IndexMut, Mul, Shr, Sub};
+//! An implementation of the SIMD types in terms of the AArch64 NEON intrinsics.
+//!
+//! NEON is a mandatory part of AArch64, so no runtime detection is necessary.
 
+use core::arch::aarch64::{self, float32x2_t, float32x4_t, int32x2_t, int32x4_t};
+use core::arch::aarch64::{uint32x2_t, uint32x4_t, uint8x16_t};
+use core::cmp::PartialEq;
+use core::fmt::{self, Debug, Formatter};
+use core::mem;
+use core::ops::{Add, BitAnd, BitOr, BitXor, Div, Index, IndexMut, Mul, Not, Shr, Sub};
+
+pub use self::pair::{wide_vectors_accelerated, F32x8, I32x8, WideVectors};
+
+#[path = "../pair.rs"]
+mod pair;
 mod swizzle_f32x4;
 mod swizzle_i32x4;
 
@@ -28,29 +36,32 @@ impl F32x2 {
 
     #[inline]
     pub fn new(a: f32, b: f32) -> F32x2 {
-        unsafe { F32x2(mem::transmute([a, b])) }
+        unsafe {
+            let vector = [a, b];
+            F32x2(aarch64::vld1_f32(vector.as_ptr()))
+        }
     }
 
     #[inline]
     pub fn splat(x: f32) -> F32x2 {
-        F32x2::new(x, x)
+        unsafe { F32x2(aarch64::vdup_n_f32(x)) }
     }
 
     // Basic operations
 
     #[inline]
     pub fn approx_recip(self) -> F32x2 {
-        unsafe { F32x2(vrecpe_v2f32(self.0)) }
+        unsafe { F32x2(aarch64::vrecpe_f32(self.0)) }
     }
 
     #[inline]
     pub fn min(self, other: F32x2) -> F32x2 {
-        unsafe { F32x2(simd_fmin(self.0, other.0)) }
+        unsafe { F32x2(aarch64::vmin_f32(self.0, other.0)) }
     }
 
     #[inline]
     pub fn max(self, other: F32x2) -> F32x2 {
-        unsafe { F32x2(simd_fmax(self.0, other.0)) }
+        unsafe { F32x2(aarch64::vmax_f32(self.0, other.0)) }
     }
 
     #[inline]
@@ -60,90 +71,83 @@ impl F32x2 {
 
     #[inline]
     pub fn abs(self) -> F32x2 {
-        unsafe { F32x2(fabs_v2f32(self.0)) }
+        unsafe { F32x2(aarch64::vabs_f32(self.0)) }
     }
 
     #[inline]
     pub fn floor(self) -> F32x2 {
-        unsafe { F32x2(floor_v2f32(self.0)) }
+        unsafe { F32x2(aarch64::vrndm_f32(self.0)) }
     }
 
     #[inline]
     pub fn ceil(self) -> F32x2 {
-        unsafe { F32x2(ceil_v2f32(self.0)) }
+        unsafe { F32x2(aarch64::vrndp_f32(self.0)) }
     }
 
     #[inline]
     pub fn sqrt(self) -> F32x2 {
-        unsafe { F32x2(sqrt_v2f32(self.0)) }
+        unsafe { F32x2(aarch64::vsqrt_f32(self.0)) }
     }
 
     // Packed comparisons
 
     #[inline]
     pub fn packed_eq(self, other: F32x2) -> U32x2 {
-        unsafe { U32x2(simd_eq(self.0, other.0)) }
+        unsafe { U32x2(aarch64::vceq_f32(self.0, other.0)) }
     }
 
     #[inline]
     pub fn packed_gt(self, other: F32x2) -> U32x2 {
-        unsafe { U32x2(simd_gt(self.0, other.0)) }
+        unsafe { U32x2(aarch64::vcgt_f32(self.0, other.0)) }
     }
 
     #[inline]
     pub fn packed_lt(self, other: F32x2) -> U32x2 {
-        unsafe { U32x2(simd_lt(self.0, other.0)) }
+        unsafe { U32x2(aarch64::vclt_f32(self.0, other.0)) }
     }
 
     #[inline]
     pub fn packed_le(self, other: F32x2) -> U32x2 {
-        unsafe { U32x2(simd_le(self.0, other.0)) }
+        unsafe { U32x2(aarch64::vcle_f32(self.0, other.0)) }
     }
 
     // Conversions
 
     #[inline]
     pub fn to_f32x4(self) -> F32x4 {
-        self.concat_xy_xy(F32x2::default())
+        unsafe { F32x4(aarch64::vcombine_f32(self.0, aarch64::vdup_n_f32(0.0))) }
     }
 
     /// Converts these packed floats to integers via rounding.
     #[inline]
     pub fn to_i32x2(self) -> I32x2 {
-        unsafe { I32x2(simd_cast(round_v2f32(self.0))) }
+        unsafe { I32x2(aarch64::vcvtn_s32_f32(self.0)) }
     }
 
     #[inline]
     pub fn to_i32x4(self) -> I32x4 {
-        self.to_i32x2().concat_xy_xy(I32x2::default())
+        self.to_f32x4().to_i32x4()
     }
 
     // Swizzle
 
     #[inline]
     pub fn yx(self) -> F32x2 {
-        unsafe { F32x2(simd_shuffle2(self.0, self.0, [1, 0])) }
+        unsafe { F32x2(aarch64::vrev64_f32(self.0)) }
     }
 
     // Concatenations
 
     #[inline]
     pub fn concat_xy_xy(self, other: F32x2) -> F32x4 {
-        unsafe { F32x4(simd_shuffle4(self.0, other.0, [0, 1, 0, 1])) }
+        unsafe { F32x4(aarch64::vcombine_f32(self.0, other.0)) }
     }
 }
 
 impl Default for F32x2 {
     #[inline]
     fn default() -> F32x2 {
-        F32x2::new(0.0, 0.0)
-    }
-}
-
-impl Debug for F32x2 {
-    #[inline]
-    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        write!(f, "<{}, {}>", self[0], self[1])
+        F32x2::splat(0.0)
     }
 }
 
@@ -151,31 +155,36 @@ impl Index<usize> for F32x2 {
     type Output = f32;
     #[inline]
     fn index(&self, index: usize) -> &f32 {
-        unsafe {
-            assert!(index < 2);
-            let ptr = &self.0 as *const float32x2_t as *const f32;
-            mem::transmute::<*const f32, &f32>(ptr.offset(index as isize))
-        }
+        unsafe { &mem::transmute::<&float32x2_t, &[f32; 2]>(&self.0)[index] }
     }
 }
 
 impl IndexMut<usize> for F32x2 {
     #[inline]
     fn index_mut(&mut self, index: usize) -> &mut f32 {
-        unsafe {
-            assert!(index < 2);
-            let ptr = &mut self.0 as *mut float32x2_t as *mut f32;
-            mem::transmute::<*mut f32, &mut f32>(ptr.offset(index as isize))
-        }
+        unsafe { &mut mem::transmute::<&mut float32x2_t, &mut [f32; 2]>(&mut self.0)[index] }
+    }
+}
+
+impl Debug for F32x2 {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "<{}, {}>", self[0], self[1])
     }
 }
 
+impl PartialEq for F32x2 {
+    #[inline]
+    fn eq(&self, other: &F32x2) -> bool {
+        self.packed_eq(*other).all_true()
+    }
+}
 
 impl Add<F32x2> for F32x2 {
     type Output = F32x2;
     #[inline]
     fn add(self, other: F32x2) -> F32x2 {
-        unsafe { F32x2(simd_add(self.0, other.0)) }
+        unsafe { F32x2(aarch64::vadd_f32(self.0, other.0)) }
     }
 }
 
@@ -183,7 +192,7 @@ impl Div<F32x2> for F32x2 {
     type Output = F32x2;
     #[inline]
     fn div(self, other: F32x2) -> F32x2 {
-        unsafe { F32x2(simd_div(self.0, other.0)) }
+        unsafe { F32x2(aarch64::vdiv_f32(self.0, other.0)) }
     }
 }
 
@@ -191,7 +200,7 @@ impl Mul<F32x2> for F32x2 {
     type Output = F32x2;
     #[inline]
     fn mul(self, other: F32x2) -> F32x2 {
-        unsafe { F32x2(simd_mul(self.0, other.0)) }
+        unsafe { F32x2(aarch64::vmul_f32(self.0, other.0)) }
     }
 }
 
@@ -199,7 +208,7 @@ impl Sub<F32x2> for F32x2 {
     type Output = F32x2;
     #[inline]
     fn sub(self, other: F32x2) -> F32x2 {
-        unsafe { F32x2(simd_sub(self.0, other.0)) }
+        unsafe { F32x2(aarch64::vsub_f32(self.0, other.0)) }
     }
 }
 
@@ -209,31 +218,36 @@ impl Sub<F32x2> for F32x2 {
 pub struct F32x4(pub float32x4_t);
 
 impl F32x4 {
+    // Constructors
+
     #[inline]
     pub fn new(a: f32, b: f32, c: f32, d: f32) -> F32x4 {
-        unsafe { F32x4(mem::transmute([a, b, c, d])) }
+        unsafe {
+            let vector = [a, b, c, d];
+            F32x4(aarch64::vld1q_f32(vector.as_ptr()))
+        }
     }
 
     #[inline]
     pub fn splat(x: f32) -> F32x4 {
-        F32x4::new(x, x, x, x)
+        unsafe { F32x4(aarch64::vdupq_n_f32(x)) }
     }
 
     // Basic operations
 
     #[inline]
     pub fn approx_recip(self) -> F32x4 {
-        unsafe { F32x4(vrecpe_v4f32(self.0)) }
+        unsafe { F32x4(aarch64::vrecpeq_f32(self.0)) }
     }
 
     #[inline]
     pub fn min(self, other: F32x4) -> F32x4 {
-        unsafe { F32x4(simd_fmin(self.0, other.0)) }
+        unsafe { F32x4(aarch64::vminq_f32(self.0, other.0)) }
     }
 
     #[inline]
     pub fn max(self, other: F32x4) -> F32x4 {
-        unsafe { F32x4(simd_fmax(self.0, other.0)) }
+        unsafe { F32x4(aarch64::vmaxq_f32(self.0, other.0)) }
     }
 
     #[inline]
@@ -243,103 +257,128 @@ impl F32x4 {
 
     #[inline]
     pub fn abs(self) -> F32x4 {
-        unsafe { F32x4(fabs_v4f32(self.0)) }
+        unsafe { F32x4(aarch64::vabsq_f32(self.0)) }
     }
 
     #[inline]
     pub fn floor(self) -> F32x4 {
-        unsafe { F32x4(floor_v4f32(self.0)) }
+        unsafe { F32x4(aarch64::vrndmq_f32(self.0)) }
     }
 
     #[inline]
     pub fn ceil(self) -> F32x4 {
-        unsafe { F32x4(ceil_v4f32(self.0)) }
+        unsafe { F32x4(aarch64::vrndpq_f32(self.0)) }
     }
 
     #[inline]
     pub fn sqrt(self) -> F32x4 {
-        unsafe { F32x4(sqrt_v4f32(self.0)) }
+        unsafe { F32x4(aarch64::vsqrtq_f32(self.0)) }
     }
 
     // Packed comparisons
 
     #[inline]
     pub fn packed_eq(self, other: F32x4) -> U32x4 {
-        unsafe { U32x4(simd_eq(self.0, other.0)) }
+        unsafe { U32x4(aarch64::vceqq_f32(self.0, other.0)) }
     }
 
     #[inline]
     pub fn packed_gt(self, other: F32x4) -> U32x4 {
-        unsafe { U32x4(simd_gt(self.0, other.0)) }
+        unsafe { U32x4(aarch64::vcgtq_f32(self.0, other.0)) }
+    }
+
+    #[inline]
+    pub fn packed_lt(self, other: F32x4) -> U32x4 {
+        unsafe { U32x4(aarch64::vcltq_f32(self.0, other.0)) }
     }
 
     #[inline]
     pub fn packed_le(self, other: F32x4) -> U32x4 {
-        unsafe { U32x4(simd_le(self.0, other.0)) }
+        unsafe { U32x4(aarch64::vcleq_f32(self.0, other.0)) }
     }
 
+    // Conversions
+
+    /// Converts these packed floats to integers via rounding.
     #[inline]
-    pub fn packed_lt(self, other: F32x4) -> U32x4 {
-        unsafe { U32x4(simd_lt(self.0, other.0)) }
+    pub fn to_i32x4(self) -> I32x4 {
+        unsafe { I32x4(aarch64::vcvtnq_s32_f32(self.0)) }
     }
 
-    // Swizzle conversions
+    // Extraction
 
     #[inline]
     pub fn xy(self) -> F32x2 {
-        unsafe { F32x2(simd_shuffle2(self.0, self.0, [0, 1])) }
+        unsafe { F32x2(aarch64::vget_low_f32(self.0)) }
     }
 
     #[inline]
-    pub fn yx(self) -> F32x2 {
-        unsafe { F32x2(simd_shuffle2(self.0, self.0, [1, 0])) }
+    pub fn xw(self) -> F32x2 {
+        self.xwyz().xy()
     }
 
     #[inline]
-    pub fn xw(self) -> F32x2 {
-        unsafe { F32x2(simd_shuffle2(self.0, self.0, [0, 3])) }
+    pub fn yx(self) -> F32x2 {
+        unsafe { F32x2(aarch64::vrev64_f32(aarch64::vget_low_f32(self.0))) }
     }
 
     #[inline]
     pub fn zy(self) -> F32x2 {
-        unsafe { F32x2(simd_shuffle2(self.0, self.0, [2, 1])) }
+        self.zyxw().xy()
     }
 
     #[inline]
     pub fn zw(self) -> F32x2 {
-        unsafe { F32x2(simd_shuffle2(self.0, self.0, [2, 3])) }
+        unsafe { F32x2(aarch64::vget_high_f32(self.0)) }
     }
 
     // Concatenations
 
     #[inline]
     pub fn concat_xy_xy(self, other: F32x4) -> F32x4 {
-        unsafe { F32x4(simd_shuffle4(self.0, other.0, [0, 1, 0, 1])) }
+        unsafe {
+            F32x4(aarch64::vcombine_f32(
+                aarch64::vget_low_f32(self.0),
+                aarch64::vget_low_f32(other.0),
+            ))
+        }
     }
 
     #[inline]
     pub fn concat_xy_zw(self, other: F32x4) -> F32x4 {
-        unsafe { F32x4(simd_shuffle4(self.0, other.0, [0, 1, 2, 3])) }
+        unsafe {
+            F32x4(aarch64::vcombine_f32(
+                aarch64::vget_low_f32(self.0),
+                aarch64::vget_high_f32(other.0),
+            ))
+        }
     }
 
     #[inline]
     pub fn concat_zw_zw(self, other: F32x4) -> F32x4 {
-        unsafe { F32x4(simd_shuffle4(self.0, other.0, [2, 3, 2, 3])) }
+        unsafe {
+            F32x4(aarch64::vcombine_f32(
+                aarch64::vget_high_f32(self.0),
+                aarch64::vget_high_f32(other.0),
+            ))
+        }
     }
 
-    // Conversions
-
-    /// Converts these packed floats to integers via rounding.
     #[inline]
-    pub fn to_i32x4(self) -> I32x4 {
-        unsafe { I32x4(round_v4f32(simd_cast(self.0))) }
+    pub fn concat_wz_yx(self, other: F32x4) -> F32x4 {
+        unsafe {
+            F32x4(aarch64::vcombine_f32(
+                aarch64::vrev64_f32(aarch64::vget_high_f32(self.0)),
+                aarch64::vrev64_f32(aarch64::vget_low_f32(other.0)),
+            ))
+        }
     }
 }
 
 impl Default for F32x4 {
     #[inline]
     fn default() -> F32x4 {
-        F32x4::new(0.0, 0.0, 0.0, 0.0)
+        F32x4::splat(0.0)
     }
 }
 
@@ -347,22 +386,14 @@ impl Index<usize> for F32x4 {
     type Output = f32;
     #[inline]
     fn index(&self, index: usize) -> &f32 {
-        unsafe {
-            assert!(index < 4);
-            let ptr = &self.0 as *const float32x4_t as *const f32;
-            mem::transmute::<*const f32, &f32>(ptr.offset(index as isize))
-        }
+        unsafe { &mem::transmute::<&float32x4_t, &[f32; 4]>(&self.0)[index] }
     }
 }
 
 impl IndexMut<usize> for F32x4 {
     #[inline]
     fn index_mut(&mut self, index: usize) -> &mut f32 {
-        unsafe {
-            assert!(index < 4);
-            let ptr = &mut self.0 as *mut float32x4_t as *mut f32;
-            mem::transmute::<*mut f32, &mut f32>(ptr.offset(index as isize))
-        }
+        unsafe { &mut mem::transmute::<&mut float32x4_t, &mut [f32; 4]>(&mut self.0)[index] }
     }
 }
 
@@ -384,7 +415,7 @@ impl Add<F32x4> for F32x4 {
     type Output = F32x4;
     #[inline]
     fn add(self, other: F32x4) -> F32x4 {
-        unsafe { F32x4(simd_add(self.0, other.0)) }
+        unsafe { F32x4(aarch64::vaddq_f32(self.0, other.0)) }
     }
 }
 
@@ -392,7 +423,7 @@ impl Div<F32x4> for F32x4 {
     type Output = F32x4;
     #[inline]
     fn div(self, other: F32x4) -> F32x4 {
-        unsafe { F32x4(simd_div(self.0, other.0)) }
+        unsafe { F32x4(aarch64::vdivq_f32(self.0, other.0)) }
     }
 }
 
@@ -400,7 +431,7 @@ impl Mul<F32x4> for F32x4 {
     type Output = F32x4;
     #[inline]
     fn mul(self, other: F32x4) -> F32x4 {
-        unsafe { F32x4(simd_mul(self.0, other.0)) }
+        unsafe { F32x4(aarch64::vmulq_f32(self.0, other.0)) }
     }
 }
 
@@ -408,24 +439,29 @@ impl Sub<F32x4> for F32x4 {
     type Output = F32x4;
     #[inline]
     fn sub(self, other: F32x4) -> F32x4 {
-        unsafe { F32x4(simd_sub(self.0, other.0)) }
+        unsafe { F32x4(aarch64::vsubq_f32(self.0, other.0)) }
     }
 }
 
 // Two 32-bit signed integers
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy)]
 pub struct I32x2(pub int32x2_t);
 
 impl I32x2 {
+    // Constructors
+
     #[inline]
-    pub fn new(x: i32, y: i32) -> I32x2 {
-        unsafe { I32x2(mem::transmute([x, y])) }
+    pub fn new(a: i32, b: i32) -> I32x2 {
+        unsafe {
+            let vector = [a, b];
+            I32x2(aarch64::vld1_s32(vector.as_ptr()))
+        }
     }
 
     #[inline]
     pub fn splat(x: i32) -> I32x2 {
-        I32x2::new(x, x)
+        unsafe { I32x2(aarch64::vdup_n_s32(x)) }
     }
 
     // Accessors
@@ -440,38 +476,66 @@ impl I32x2 {
         self[1]
     }
 
-    #[inline]
-    pub fn packed_eq(self, other: I32x2) -> U32x2 {
-        unsafe { U32x2(simd_eq(self.0, other.0)) }
-    }
-
     // Concatenations
 
     #[inline]
     pub fn concat_xy_xy(self, other: I32x2) -> I32x4 {
-        unsafe { I32x4(simd_shuffle4(self.0, other.0, [0, 1, 0, 1])) }
+        unsafe { I32x4(aarch64::vcombine_s32(self.0, other.0)) }
     }
 
     // Conversions
 
+    #[inline]
+    pub fn to_i32x4(self) -> I32x4 {
+        unsafe { I32x4(aarch64::vcombine_s32(self.0, aarch64::vdup_n_s32(0))) }
+    }
+
+    #[inline]
+    pub fn to_f32x4(self) -> F32x4 {
+        self.to_i32x4().to_f32x4()
+    }
+
     /// Converts these packed integers to floats.
     #[inline]
     pub fn to_f32x2(self) -> F32x2 {
-        unsafe { F32x2(simd_cast(self.0)) }
+        unsafe { F32x2(aarch64::vcvt_f32_s32(self.0)) }
     }
-}
 
-impl Default for I32x2 {
+    // Basic operations
+
     #[inline]
-    fn default() -> I32x2 {
-        I32x2::splat(0)
+    pub fn max(self, other: I32x2) -> I32x2 {
+        unsafe { I32x2(aarch64::vmax_s32(self.0, other.0)) }
+    }
+
+    #[inline]
+    pub fn min(self, other: I32x2) -> I32x2 {
+        unsafe { I32x2(aarch64::vmin_s32(self.0, other.0)) }
+    }
+
+    // Comparisons
+
+    // TODO(pcwalton): Use the `U32x2` type!
+    #[inline]
+    pub fn packed_eq(self, other: I32x2) -> U32x4 {
+        self.to_i32x4().packed_eq(other.to_i32x4())
+    }
+
+    #[inline]
+    pub fn packed_gt(self, other: I32x2) -> U32x4 {
+        self.to_i32x4().packed_gt(other.to_i32x4())
+    }
+
+    #[inline]
+    pub fn packed_le(self, other: I32x2) -> U32x4 {
+        self.to_i32x4().packed_le(other.to_i32x4())
     }
 }
 
-impl PartialEq for I32x2 {
+impl Default for I32x2 {
     #[inline]
-    fn eq(&self, other: &I32x2) -> bool {
-        self.packed_eq(*other).all_true()
+    fn default() -> I32x2 {
+        I32x2::splat(0)
     }
 }
 
@@ -479,22 +543,14 @@ impl Index<usize> for I32x2 {
     type Output = i32;
     #[inline]
     fn index(&self, index: usize) -> &i32 {
-        unsafe {
-            assert!(index < 2);
-            let ptr = &self.0 as *const int32x2_t as *const i32;
-            mem::transmute::<*const i32, &i32>(ptr.offset(index as isize))
-        }
+        unsafe { &mem::transmute::<&int32x2_t, &[i32; 2]>(&self.0)[index] }
     }
 }
 
 impl IndexMut<usize> for I32x2 {
     #[inline]
     fn index_mut(&mut self, index: usize) -> &mut i32 {
-        unsafe {
-            assert!(index < 2);
-            let ptr = &mut self.0 as *mut int32x2_t as *mut i32;
-            mem::transmute::<*mut i32, &mut i32>(ptr.offset(index as isize))
-        }
+        unsafe { &mut mem::transmute::<&mut int32x2_t, &mut [i32; 2]>(&mut self.0)[index] }
     }
 }
 
@@ -502,7 +558,7 @@ impl Add<I32x2> for I32x2 {
     type Output = I32x2;
     #[inline]
     fn add(self, other: I32x2) -> I32x2 {
-        unsafe { I32x2(simd_add(self.0, other.0)) }
+        unsafe { I32x2(aarch64::vadd_s32(self.0, other.0)) }
     }
 }
 
@@ -510,7 +566,7 @@ impl Sub<I32x2> for I32x2 {
     type Output = I32x2;
     #[inline]
     fn sub(self, other: I32x2) -> I32x2 {
-        unsafe { I32x2(simd_sub(self.0, other.0)) }
+        unsafe { I32x2(aarch64::vsub_s32(self.0, other.0)) }
     }
 }
 
@@ -518,107 +574,151 @@ impl Mul<I32x2> for I32x2 {
     type Output = I32x2;
     #[inline]
     fn mul(self, other: I32x2) -> I32x2 {
-        unsafe { I32x2(simd_mul(self.0, other.0)) }
+        unsafe { I32x2(aarch64::vmul_s32(self.0, other.0)) }
+    }
+}
+
+impl Debug for I32x2 {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "<{}, {}>", self[0], self[1])
+    }
+}
+
+impl PartialEq for I32x2 {
+    #[inline]
+    fn eq(&self, other: &I32x2) -> bool {
+        self.packed_eq(*other).all_true()
     }
 }
 
 // Four 32-bit signed integers
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy)]
 pub struct I32x4(pub int32x4_t);
 
 impl I32x4 {
+    // Constructors
+
     #[inline]
     pub fn new(a: i32, b: i32, c: i32, d: i32) -> I32x4 {
-        unsafe { I32x4(mem::transmute([a, b, c, d])) }
+        unsafe {
+            let vector = [a, b, c, d];
+            I32x4(aarch64::vld1q_s32(vector.as_ptr()))
+        }
     }
 
     #[inline]
     pub fn splat(x: i32) -> I32x4 {
-        I32x4::new(x, x, x, x)
+        unsafe { I32x4(aarch64::vdupq_n_s32(x)) }
     }
 
-    // Basic operations
+    // Extraction
 
     #[inline]
-    pub fn max(self, other: I32x4) -> I32x4 {
-        unsafe { I32x4(simd_fmax(self.0, other.0)) }
+    pub fn xy(self) -> I32x2 {
+        unsafe { I32x2(aarch64::vget_low_s32(self.0)) }
     }
 
     #[inline]
-    pub fn min(self, other: I32x4) -> I32x4 {
-        unsafe { I32x4(simd_fmin(self.0, other.0)) }
+    pub fn xw(self) -> I32x2 {
+        self.xwyz().xy()
     }
 
-    // Packed comparisons
-
     #[inline]
-    pub fn packed_eq(self, other: I32x4) -> U32x4 {
-        unsafe { U32x4(simd_eq(self.0, other.0)) }
+    pub fn yx(self) -> I32x2 {
+        unsafe { I32x2(aarch64::vrev64_s32(aarch64::vget_low_s32(self.0))) }
     }
 
     #[inline]
-    pub fn packed_le(self, other: I32x4) -> U32x4 {
-        unsafe { U32x4(simd_le(self.0, other.0)) }
+    pub fn zy(self) -> I32x2 {
+        self.zyxw().xy()
     }
 
     #[inline]
-    pub fn packed_lt(self, other: I32x4) -> U32x4 {
-        unsafe { U32x4(simd_lt(self.0, other.0)) }
+    pub fn zw(self) -> I32x2 {
+        unsafe { I32x2(aarch64::vget_high_s32(self.0)) }
     }
 
     // Concatenations
 
     #[inline]
     pub fn concat_xy_xy(self, other: I32x4) -> I32x4 {
-        unsafe { I32x4(simd_shuffle4(self.0, other.0, [0, 1, 4, 5])) }
+        unsafe {
+            I32x4(aarch64::vcombine_s32(
+                aarch64::vget_low_s32(self.0),
+                aarch64::vget_low_s32(other.0),
+            ))
+        }
     }
 
     #[inline]
     pub fn concat_zw_zw(self, other: I32x4) -> I32x4 {
-        unsafe { I32x4(simd_shuffle4(self.0, other.0, [2, 3, 6, 7])) }
+        unsafe {
+            I32x4(aarch64::vcombine_s32(
+                aarch64::vget_high_s32(self.0),
+                aarch64::vget_high_s32(other.0),
+            ))
+        }
     }
 
-    // Swizzle conversions
+    // Conversions
 
+    /// Converts these packed integers to floats.
     #[inline]
-    pub fn xy(self) -> I32x2 {
-        unsafe { I32x2(simd_shuffle2(self.0, self.0, [0, 1])) }
+    pub fn to_f32x4(self) -> F32x4 {
+        unsafe { F32x4(aarch64::vcvtq_f32_s32(self.0)) }
     }
 
+    /// Converts these packed signed integers to unsigned integers.
+    ///
+    /// Overflowing values will wrap around.
     #[inline]
-    pub fn yx(self) -> I32x2 {
-        unsafe { I32x2(simd_shuffle2(self.0, self.0, [1, 0])) }
+    pub fn to_u32x4(self) -> U32x4 {
+        unsafe { U32x4(aarch64::vreinterpretq_u32_s32(self.0)) }
     }
 
+    // Basic operations
+
     #[inline]
-    pub fn xw(self) -> I32x2 {
-        unsafe { I32x2(simd_shuffle2(self.0, self.0, [0, 3])) }
+    pub fn max(self, other: I32x4) -> I32x4 {
+        unsafe { I32x4(aarch64::vmaxq_s32(self.0, other.0)) }
     }
 
     #[inline]
-    pub fn zy(self) -> I32x2 {
-        unsafe { I32x2(simd_shuffle2(self.0, self.0, [2, 1])) }
+    pub fn min(self, other: I32x4) -> I32x4 {
+        unsafe { I32x4(aarch64::vminq_s32(self.0, other.0)) }
     }
 
+    // Packed comparisons
+
     #[inline]
-    pub fn zw(self) -> I32x2 {
-        unsafe { I32x2(simd_shuffle2(self.0, self.0, [2, 3])) }
+    pub fn packed_eq(self, other: I32x4) -> U32x4 {
+        unsafe { U32x4(aarch64::vceqq_s32(self.0, other.0)) }
     }
 
-    // Conversions
+    // Comparisons
 
-    /// Converts these packed integers to floats.
     #[inline]
-    pub fn to_f32x4(self) -> F32x4 {
-        unsafe { F32x4(simd_cast(self.0)) }
+    pub fn packed_gt(self, other: I32x4) -> U32x4 {
+        unsafe { U32x4(aarch64::vcgtq_s32(self.0, other.0)) }
+    }
+
+    #[inline]
+    pub fn packed_lt(self, other: I32x4) -> U32x4 {
+        unsafe { U32x4(aarch64::vcltq_s32(self.0, other.0)) }
+    }
+
+    #[inline]
+    pub fn packed_le(self, other: I32x4) -> U32x4 {
+        unsafe { U32x4(aarch64::vcleq_s32(self.0, other.0)) }
     }
 }
 
 impl Default for I32x4 {
     #[inline]
     fn default() -> I32x4 {
-        I32x4::new(0, 0, 0, 0)
+        I32x4::splat(0)
     }
 }
 
@@ -626,22 +726,14 @@ impl Index<usize> for I32x4 {
     type Output = i32;
     #[inline]
     fn index(&self, index: usize) -> &i32 {
-        unsafe {
-            assert!(index < 4);
-            let ptr = &self.0 as *const int32x4_t as *const i32;
-            mem::transmute::<*const i32, &i32>(ptr.offset(index as isize))
-        }
+        unsafe { &mem::transmute::<&int32x4_t, &[i32; 4]>(&self.0)[index] }
     }
 }
 
 impl IndexMut<usize> for I32x4 {
     #[inline]
     fn index_mut(&mut self, index: usize) -> &mut i32 {
-        unsafe {
-            assert!(index < 4);
-            let ptr = &mut self.0 as *mut int32x4_t as *mut i32;
-            mem::transmute::<*mut i32, &mut i32>(ptr.offset(index as isize))
-        }
+        unsafe { &mut mem::transmute::<&mut int32x4_t, &mut [i32; 4]>(&mut self.0)[index] }
     }
 }
 
@@ -649,7 +741,7 @@ impl Add<I32x4> for I32x4 {
     type Output = I32x4;
     #[inline]
     fn add(self, other: I32x4) -> I32x4 {
-        unsafe { I32x4(simd_add(self.0, other.0)) }
+        unsafe { I32x4(aarch64::vaddq_s32(self.0, other.0)) }
     }
 }
 
@@ -657,7 +749,7 @@ impl Sub<I32x4> for I32x4 {
     type Output = I32x4;
     #[inline]
     fn sub(self, other: I32x4) -> I32x4 {
-        unsafe { I32x4(simd_sub(self.0, other.0)) }
+        unsafe { I32x4(aarch64::vsubq_s32(self.0, other.0)) }
     }
 }
 
@@ -665,14 +757,7 @@ impl Mul<I32x4> for I32x4 {
     type Output = I32x4;
     #[inline]
     fn mul(self, other: I32x4) -> I32x4 {
-        unsafe { I32x4(simd_mul(self.0, other.0)) }
-    }
-}
-
-impl PartialEq for I32x4 {
-    #[inline]
-    fn eq(&self, other: &I32x4) -> bool {
-        self.packed_eq(*other).all_true()
+        unsafe { I32x4(aarch64::vmulq_s32(self.0, other.0)) }
     }
 }
 
@@ -680,7 +765,7 @@ impl BitAnd<I32x4> for I32x4 {
     type Output = I32x4;
     #[inline]
     fn bitand(self, other: I32x4) -> I32x4 {
-        unsafe { I32x4(simd_and(self.0, other.0)) }
+        unsafe { I32x4(aarch64::vandq_s32(self.0, other.0)) }
     }
 }
 
@@ -688,15 +773,21 @@ impl BitOr<I32x4> for I32x4 {
     type Output = I32x4;
     #[inline]
     fn bitor(self, other: I32x4) -> I32x4 {
-        unsafe { I32x4(simd_or(self.0, other.0)) }
+        unsafe { I32x4(aarch64::vorrq_s32(self.0, other.0)) }
     }
 }
 
-impl Shr<I32x4> for I32x4 {
-    type Output = I32x4;
+impl Debug for I32x4 {
     #[inline]
-    fn shr(self, other: I32x4) -> I32x4 {
-        unsafe { I32x4(simd_shr(self.0, other.0)) }
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "<{}, {}, {}, {}>", self[0], self[1], self[2], self[3])
+    }
+}
+
+impl PartialEq for I32x4 {
+    #[inline]
+    fn eq(&self, other: &I32x4) -> bool {
+        self.packed_eq(*other).all_true()
     }
 }
 
@@ -711,7 +802,7 @@ impl U32x2 {
     /// The result is *undefined* if both values in this vector are not booleans. A boolean is a
     /// value with all bits set or all bits clear (i.e. !0 or 0).
     #[inline]
-    pub fn all_true(&self) -> bool {
+    pub fn all_true(self) -> bool {
         unsafe { aarch64::vminv_u32(self.0) == !0 }
     }
 
@@ -720,35 +811,50 @@ impl U32x2 {
     /// The result is *undefined* if both values in this vector are not booleans. A boolean is a
     /// value with all bits set or all bits clear (i.e. !0 or 0).
     #[inline]
-    pub fn all_false(&self) -> bool {
+    pub fn all_false(self) -> bool {
         unsafe { aarch64::vmaxv_u32(self.0) == 0 }
     }
 }
 
-impl Index<usize> for U32x2 {
-    type Output = u32;
+// Four 32-bit unsigned integers
+
+#[derive(Clone, Copy)]
+pub struct U32x4(pub uint32x4_t);
+
+impl U32x4 {
+    // Constructors
+
     #[inline]
-    fn index(&self, index: usize) -> &u32 {
+    pub fn new(a: u32, b: u32, c: u32, d: u32) -> U32x4 {
         unsafe {
-            assert!(index < 2);
-            let ptr = &self.0 as *const uint32x2_t as *const u32;
-            mem::transmute::<*const u32, &u32>(ptr.offset(index as isize))
+            let vector = [a, b, c, d];
+            U32x4(aarch64::vld1q_u32(vector.as_ptr()))
         }
     }
-}
 
-// Four 32-bit unsigned integers
+    #[inline]
+    pub fn splat(x: u32) -> U32x4 {
+        unsafe { U32x4(aarch64::vdupq_n_u32(x)) }
+    }
 
-#[derive(Clone, Copy)]
-pub struct U32x4(pub uint32x4_t);
+    // Conversions
+
+    /// Converts these packed unsigned integers to signed integers.
+    ///
+    /// Overflowing values will wrap around.
+    #[inline]
+    pub fn to_i32x4(self) -> I32x4 {
+        unsafe { I32x4(aarch64::vreinterpretq_s32_u32(self.0)) }
+    }
+
+    // Basic operations
 
-impl U32x4 {
     /// Returns true if all four booleans in this vector are true.
     ///
     /// The result is *undefined* if all four values in this vector are not booleans. A boolean is
     /// a value with all bits set or all bits clear (i.e. !0 or 0).
     #[inline]
-    pub fn all_true(&self) -> bool {
+    pub fn all_true(self) -> bool {
         unsafe { aarch64::vminvq_u32(self.0) == !0 }
     }
 
@@ -757,76 +863,96 @@ impl U32x4 {
     /// The result is *undefined* if all four values in this vector are not booleans. A boolean is
     /// a value with all bits set or all bits clear (i.e. !0 or 0).
     #[inline]
-    pub fn all_false(&self) -> bool {
+    pub fn all_false(self) -> bool {
         unsafe { aarch64::vmaxvq_u32(self.0) == 0 }
     }
+
+    // Extraction
+
+    #[inline]
+    pub fn xy(self) -> U32x2 {
+        unsafe { U32x2(aarch64::vget_low_u32(self.0)) }
+    }
+
+    // Packed comparisons
+
+    #[inline]
+    pub fn packed_eq(self, other: U32x4) -> U32x4 {
+        unsafe { U32x4(aarch64::vceqq_u32(self.0, other.0)) }
+    }
+}
+
+impl Debug for U32x4 {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "<{}, {}, {}, {}>", self[0], self[1], self[2], self[3])
+    }
 }
 
 impl Index<usize> for U32x4 {
     type Output = u32;
     #[inline]
     fn index(&self, index: usize) -> &u32 {
-        unsafe {
-            assert!(index < 4);
-            let ptr = &self.0 as *const uint32x4_t as *const u32;
-            mem::transmute::<*const u32, &u32>(ptr.offset(index as isize))
-        }
+        unsafe { &mem::transmute::<&uint32x4_t, &[u32; 4]>(&self.0)[index] }
     }
 }
 
-// Intrinsics
-
-extern "platform-intrinsic" {
-    fn simd_add<T>(x: T, y: T) -> T;
-    fn simd_div<T>(x: T, y: T) -> T;
-    fn simd_mul<T>(x: T, y: T) -> T;
-    fn simd_sub<T>(x: T, y: T) -> T;
-
-    fn simd_shr<T>(x: T, y: T) -> T;
-
-    fn simd_and<T>(x: T, y: T) -> T;
-    fn simd_or<T>(x: T, y: T) -> T;
-
-    fn simd_fmin<T>(x: T, y: T) -> T;
-    fn simd_fmax<T>(x: T, y: T) -> T;
+impl PartialEq for U32x4 {
+    #[inline]
+    fn eq(&self, other: &U32x4) -> bool {
+        self.packed_eq(*other).all_true()
+    }
+}
 
-    fn simd_eq<T, U>(x: T, y: T) -> U;
-    fn simd_gt<T, U>(x: T, y: T) -> U;
-    fn simd_le<T, U>(x: T, y: T) -> U;
-    fn simd_lt<T, U>(x: T, y: T) -> U;
+impl Not for U32x4 {
+    type Output = U32x4;
+    #[inline]
+    fn not(self) -> U32x4 {
+        unsafe { U32x4(aarch64::vmvnq_u32(self.0)) }
+    }
+}
 
-    fn simd_shuffle2<T, U>(x: T, y: T, idx: [u32; 2]) -> U;
-    fn simd_shuffle4<T, U>(x: T, y: T, idx: [u32; 4]) -> U;
+impl BitXor<U32x4> for U32x4 {
+    type Output = U32x4;
+    #[inline]
+    fn bitxor(self, other: U32x4) -> U32x4 {
+        unsafe { U32x4(aarch64::veorq_u32(self.0, other.0)) }
+    }
+}
 
-    fn simd_cast<T, U>(x: T) -> U;
+impl Shr<u32> for U32x4 {
+    type Output = U32x4;
+    #[inline]
+    fn shr(self, amount: u32) -> U32x4 {
+        // NEON only has a left shift by a variable amount; negative amounts shift right.
+        unsafe { U32x4(aarch64::vshlq_u32(self.0, aarch64::vdupq_n_s32(-(amount as i32)))) }
+    }
 }
 
-extern "C" {
-    #[link_name = "llvm.fabs.v2f32"]
-    fn fabs_v2f32(a: float32x2_t) -> float32x2_t;
-    #[link_name = "llvm.floor.v2f32"]
-    fn floor_v2f32(a: float32x2_t) -> float32x2_t;
-    #[link_name = "llvm.ceil.v2f32"]
-    fn ceil_v2f32(a: float32x2_t) -> float32x2_t;
-    #[link_name = "llvm.round.v2f32"]
-    fn round_v2f32(a: float32x2_t) -> float32x2_t;
-    #[link_name = "llvm.sqrt.v2f32"]
-    fn sqrt_v2f32(a: float32x2_t) -> float32x2_t;
+// Swizzle helpers
 
-    #[link_name = "llvm.fabs.v4f32"]
-    fn fabs_v4f32(a: float32x4_t) -> float32x4_t;
-    #[link_name = "llvm.floor.v4f32"]
-    fn floor_v4f32(a: float32x4_t) -> float32x4_t;
-    #[link_name = "llvm.ceil.v4f32"]
-    fn ceil_v4f32(a: float32x4_t) -> float32x4_t;
-    #[link_name = "llvm.round.v4f32"]
-    fn round_v4f32(a: float32x4_t) -> float32x4_t;
-    #[link_name = "llvm.sqrt.v4f32"]
-    fn sqrt_v4f32(a: float32x4_t) -> float32x4_t;
+/// Builds the `tbl` byte indices that select the given 32-bit lanes.
+///
+/// The lane indices are always constants at the call sites, so this folds away.
+#[inline]
+unsafe fn lane_table(lanes: [u8; 4]) -> uint8x16_t {
+    let mut table = [0; 16];
+    for (lane, &index) in lanes.iter().enumerate() {
+        for byte in 0..4 {
+            table[lane * 4 + byte] = index * 4 + byte as u8;
+        }
+    }
+    aarch64::vld1q_u8(table.as_ptr())
+}
 
-    #[link_name = "llvm.aarch64.neon.frecpe.v2f32"]
-    fn vrecpe_v2f32(a: float32x2_t) -> float32x2_t;
+#[inline]
+pub(crate) unsafe fn shuffle_f32x4(vector: float32x4_t, lanes: [u8; 4]) -> float32x4_t {
+    let bytes = aarch64::vreinterpretq_u8_f32(vector);
+    aarch64::vreinterpretq_f32_u8(aarch64::vqtbl1q_u8(bytes, lane_table(lanes)))
+}
 
-    #[link_name = "llvm.aarch64.neon.frecpe.v4f32"]
-    fn vrecpe_v4f32(a: float32x4_t) -> float32x4_t;
+#[inline]
+pub(crate) unsafe fn shuffle_i32x4(vector: int32x4_t, lanes: [u8; 4]) -> int32x4_t {
+    let bytes = aarch64::vreinterpretq_u8_s32(vector);
+    aarch64::vreinterpretq_s32_u8(aarch64::vqtbl1q_u8(bytes, lane_table(lanes)))
 }
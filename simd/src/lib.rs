@@ -8,19 +8,21 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-#![cfg_attr(pf_rustc_nightly, feature(link_llvm_intrinsics, platform_intrinsics))]
-#![cfg_attr(pf_rustc_nightly, feature(simd_ffi, stdsimd))]
-
 //! A minimal SIMD abstraction, usable outside of Pathfinder.
+//!
+//! This crate is `no_std` unless the default `pf-std` feature is on. Without it, turn on
+//! `pf-libm` for the scalar math functions.
+
+#![cfg_attr(not(feature = "pf-std"), no_std)]
 
-#[cfg(all(not(feature = "pf-no-simd"), pf_rustc_nightly, target_arch = "aarch64"))]
+#[cfg(all(not(feature = "pf-no-simd"), target_arch = "aarch64"))]
 pub use crate::arm as default;
 #[cfg(any(
     feature = "pf-no-simd",
     not(any(
         target_arch = "x86",
         target_arch = "x86_64",
-        all(pf_rustc_nightly, target_arch = "aarch64")
+        target_arch = "aarch64"
     ))
 ))]
 pub use crate::scalar as default;
@@ -30,9 +32,10 @@ pub use crate::scalar as default;
 ))]
 pub use crate::x86 as default;
 
-#[cfg(all(pf_rustc_nightly, target_arch = "aarch64"))]
+#[cfg(target_arch = "aarch64")]
 pub mod arm;
 mod extras;
+pub mod math;
 pub mod scalar;
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 pub mod x86;
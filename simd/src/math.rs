@@ -0,0 +1,91 @@
+// pathfinder/simd/src/math.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Scalar floating-point functions that work without `std`.
+//!
+//! `core` doesn't provide the transcendental and rounding functions on `f32`, so without the
+//! `pf-std` feature these call into `libm` instead, which the `pf-libm` feature pulls in.
+
+#[cfg(all(not(feature = "pf-std"), not(feature = "pf-libm")))]
+compile_error!("`pathfinder_simd` needs either the `pf-std` or the `pf-libm` feature");
+
+macro_rules! define_functions {
+    ($float:ty; $($(#[$attr:meta])* fn $name:ident($($arg:ident),*) -> $ret:ty {
+        std: $std:expr,
+        libm: $libm:expr
+    })*) => {
+        $(
+            $(#[$attr])*
+            #[cfg(feature = "pf-std")]
+            #[inline]
+            pub fn $name($($arg: $float),*) -> $ret {
+                $std
+            }
+
+            $(#[$attr])*
+            #[cfg(not(feature = "pf-std"))]
+            #[inline]
+            pub fn $name($($arg: $float),*) -> $ret {
+                $libm
+            }
+        )*
+    }
+}
+
+define_functions! {
+    f32;
+
+    fn abs(x) -> f32 { std: x.abs(), libm: libm::fabsf(x) }
+    fn atan2(y, x) -> f32 { std: y.atan2(x), libm: libm::atan2f(y, x) }
+    fn cbrt(x) -> f32 { std: x.cbrt(), libm: libm::cbrtf(x) }
+    fn ceil(x) -> f32 { std: x.ceil(), libm: libm::ceilf(x) }
+    fn cos(x) -> f32 { std: x.cos(), libm: libm::cosf(x) }
+    fn exp(x) -> f32 { std: x.exp(), libm: libm::expf(x) }
+    fn floor(x) -> f32 { std: x.floor(), libm: libm::floorf(x) }
+    fn hypot(x, y) -> f32 { std: x.hypot(y), libm: libm::hypotf(x, y) }
+    fn ln(x) -> f32 { std: x.ln(), libm: libm::logf(x) }
+    fn powf(x, y) -> f32 { std: x.powf(y), libm: libm::powf(x, y) }
+    fn round(x) -> f32 { std: x.round(), libm: libm::roundf(x) }
+    fn sin(x) -> f32 { std: x.sin(), libm: libm::sinf(x) }
+    fn sqrt(x) -> f32 { std: x.sqrt(), libm: libm::sqrtf(x) }
+    fn tan(x) -> f32 { std: x.tan(), libm: libm::tanf(x) }
+    fn trunc(x) -> f32 { std: x.trunc(), libm: libm::truncf(x) }
+
+    /// Returns the sine and cosine of `x`, in that order.
+    fn sin_cos(x) -> (f32, f32) { std: x.sin_cos(), libm: libm::sincosf(x) }
+
+    /// The least nonnegative remainder of `x / y`, like `f32::rem_euclid`.
+    fn rem_euclid(x, y) -> f32 {
+        std: x.rem_euclid(y),
+        libm: {
+            let r = libm::fmodf(x, y);
+            if r < 0.0 { r + libm::fabsf(y) } else { r }
+        }
+    }
+}
+
+/// The same functions, for `f64`.
+pub mod f64 {
+    define_functions! {
+        f64;
+
+        fn abs(x) -> f64 { std: x.abs(), libm: libm::fabs(x) }
+        fn atan2(y, x) -> f64 { std: y.atan2(x), libm: libm::atan2(y, x) }
+        fn ceil(x) -> f64 { std: x.ceil(), libm: libm::ceil(x) }
+        fn cos(x) -> f64 { std: x.cos(), libm: libm::cos(x) }
+        fn floor(x) -> f64 { std: x.floor(), libm: libm::floor(x) }
+        fn round(x) -> f64 { std: x.round(), libm: libm::round(x) }
+        fn sin(x) -> f64 { std: x.sin(), libm: libm::sin(x) }
+        fn sqrt(x) -> f64 { std: x.sqrt(), libm: libm::sqrt(x) }
+
+        /// Returns the sine and cosine of `x`, in that order.
+        fn sin_cos(x) -> (f64, f64) { std: x.sin_cos(), libm: libm::sincos(x) }
+    }
+}
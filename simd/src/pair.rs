@@ -0,0 +1,320 @@
+// pathfinder/simd/src/pair.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Eight-wide vectors built out of pairs of four-wide vectors.
+//!
+//! This is shared by the backends that have no native 256-bit registers, so that code written
+//! against `F32x8` and `I32x8` still compiles everywhere.
+
+use super::{F32x4, I32x4};
+use core::fmt::{self, Debug, Formatter};
+use core::ops::{Add, BitAnd, BitOr, Div, Index, IndexMut, Mul, Shr, Sub};
+
+/// Proof that `F32x8` and `I32x8` are backed by native 256-bit instructions.
+///
+/// This backend never hands one out; it exists so that code using the wide vectors compiles on
+/// every target.
+#[derive(Clone, Copy, Debug)]
+pub struct WideVectors(());
+
+impl WideVectors {
+    #[cfg(test)]
+    pub(crate) fn emulated() -> WideVectors {
+        WideVectors(())
+    }
+}
+
+/// Returns a token for building `F32x8` and `I32x8` if they are backed by native 256-bit
+/// instructions.
+///
+/// On this backend they are emulated with pairs of four-wide vectors, so they are no faster than
+/// using `F32x4` and `I32x4` directly, and this always returns `None`.
+#[inline]
+pub fn wide_vectors_accelerated() -> Option<WideVectors> {
+    None
+}
+
+// Eight 32-bit floats
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct F32x8(F32x4, F32x4);
+
+impl F32x8 {
+    // Constructors
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(_: WideVectors, a: f32, b: f32, c: f32, d: f32, e: f32, f: f32, g: f32, h: f32)
+               -> F32x8 {
+        F32x8(F32x4::new(a, b, c, d), F32x4::new(e, f, g, h))
+    }
+
+    #[inline]
+    pub fn splat(_: WideVectors, x: f32) -> F32x8 {
+        F32x8(F32x4::splat(x), F32x4::splat(x))
+    }
+
+    #[inline]
+    pub fn from_halves(_: WideVectors, low: F32x4, high: F32x4) -> F32x8 {
+        F32x8(low, high)
+    }
+
+    // Basic operations
+
+    #[inline]
+    pub fn min(self, other: F32x8) -> F32x8 {
+        F32x8(self.0.min(other.0), self.1.min(other.1))
+    }
+
+    #[inline]
+    pub fn max(self, other: F32x8) -> F32x8 {
+        F32x8(self.0.max(other.0), self.1.max(other.1))
+    }
+
+    #[inline]
+    pub fn clamp(self, min: F32x8, max: F32x8) -> F32x8 {
+        self.max(min).min(max)
+    }
+
+    #[inline]
+    pub fn abs(self) -> F32x8 {
+        F32x8(self.0.abs(), self.1.abs())
+    }
+
+    #[inline]
+    pub fn floor(self) -> F32x8 {
+        F32x8(self.0.floor(), self.1.floor())
+    }
+
+    #[inline]
+    pub fn ceil(self) -> F32x8 {
+        F32x8(self.0.ceil(), self.1.ceil())
+    }
+
+    #[inline]
+    pub fn sqrt(self) -> F32x8 {
+        F32x8(self.0.sqrt(), self.1.sqrt())
+    }
+
+    // Conversions
+
+    /// Converts these packed floats to integers via rounding.
+    #[inline]
+    pub fn to_i32x8(self) -> I32x8 {
+        I32x8(self.0.to_i32x4(), self.1.to_i32x4())
+    }
+
+    // Extraction
+
+    #[inline]
+    pub fn low(self) -> F32x4 {
+        self.0
+    }
+
+    #[inline]
+    pub fn high(self) -> F32x4 {
+        self.1
+    }
+}
+
+impl Index<usize> for F32x8 {
+    type Output = f32;
+    #[inline]
+    fn index(&self, index: usize) -> &f32 {
+        if index < 4 { &self.0[index] } else { &self.1[index - 4] }
+    }
+}
+
+impl IndexMut<usize> for F32x8 {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut f32 {
+        if index < 4 { &mut self.0[index] } else { &mut self.1[index - 4] }
+    }
+}
+
+impl Debug for F32x8 {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f,
+               "<{}, {}, {}, {}, {}, {}, {}, {}>",
+               self[0], self[1], self[2], self[3], self[4], self[5], self[6], self[7])
+    }
+}
+
+impl Add<F32x8> for F32x8 {
+    type Output = F32x8;
+    #[inline]
+    fn add(self, other: F32x8) -> F32x8 {
+        F32x8(self.0 + other.0, self.1 + other.1)
+    }
+}
+
+impl Div<F32x8> for F32x8 {
+    type Output = F32x8;
+    #[inline]
+    fn div(self, other: F32x8) -> F32x8 {
+        F32x8(self.0 / other.0, self.1 / other.1)
+    }
+}
+
+impl Mul<F32x8> for F32x8 {
+    type Output = F32x8;
+    #[inline]
+    fn mul(self, other: F32x8) -> F32x8 {
+        F32x8(self.0 * other.0, self.1 * other.1)
+    }
+}
+
+impl Sub<F32x8> for F32x8 {
+    type Output = F32x8;
+    #[inline]
+    fn sub(self, other: F32x8) -> F32x8 {
+        F32x8(self.0 - other.0, self.1 - other.1)
+    }
+}
+
+// Eight 32-bit signed integers
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct I32x8(I32x4, I32x4);
+
+impl I32x8 {
+    // Constructors
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(_: WideVectors, a: i32, b: i32, c: i32, d: i32, e: i32, f: i32, g: i32, h: i32)
+               -> I32x8 {
+        I32x8(I32x4::new(a, b, c, d), I32x4::new(e, f, g, h))
+    }
+
+    #[inline]
+    pub fn splat(_: WideVectors, x: i32) -> I32x8 {
+        I32x8(I32x4::splat(x), I32x4::splat(x))
+    }
+
+    #[inline]
+    pub fn from_halves(_: WideVectors, low: I32x4, high: I32x4) -> I32x8 {
+        I32x8(low, high)
+    }
+
+    // Basic operations
+
+    #[inline]
+    pub fn min(self, other: I32x8) -> I32x8 {
+        I32x8(self.0.min(other.0), self.1.min(other.1))
+    }
+
+    #[inline]
+    pub fn max(self, other: I32x8) -> I32x8 {
+        I32x8(self.0.max(other.0), self.1.max(other.1))
+    }
+
+    // Swizzle
+
+    /// Swaps the first two and the last two lanes of each half of this vector.
+    #[inline]
+    pub fn yxwz(self) -> I32x8 {
+        I32x8(self.0.yxwz(), self.1.yxwz())
+    }
+
+    // Conversions
+
+    /// Converts these packed integers to floats.
+    #[inline]
+    pub fn to_f32x8(self) -> F32x8 {
+        F32x8(self.0.to_f32x4(), self.1.to_f32x4())
+    }
+
+    // Extraction
+
+    #[inline]
+    pub fn low(self) -> I32x4 {
+        self.0
+    }
+
+    #[inline]
+    pub fn high(self) -> I32x4 {
+        self.1
+    }
+}
+
+impl Index<usize> for I32x8 {
+    type Output = i32;
+    #[inline]
+    fn index(&self, index: usize) -> &i32 {
+        if index < 4 { &self.0[index] } else { &self.1[index - 4] }
+    }
+}
+
+impl IndexMut<usize> for I32x8 {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut i32 {
+        if index < 4 { &mut self.0[index] } else { &mut self.1[index - 4] }
+    }
+}
+
+impl Debug for I32x8 {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f,
+               "<{}, {}, {}, {}, {}, {}, {}, {}>",
+               self[0], self[1], self[2], self[3], self[4], self[5], self[6], self[7])
+    }
+}
+
+impl Add<I32x8> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn add(self, other: I32x8) -> I32x8 {
+        I32x8(self.0 + other.0, self.1 + other.1)
+    }
+}
+
+impl Sub<I32x8> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn sub(self, other: I32x8) -> I32x8 {
+        I32x8(self.0 - other.0, self.1 - other.1)
+    }
+}
+
+impl Mul<I32x8> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn mul(self, other: I32x8) -> I32x8 {
+        I32x8(self.0 * other.0, self.1 * other.1)
+    }
+}
+
+impl BitAnd<I32x8> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn bitand(self, other: I32x8) -> I32x8 {
+        I32x8(self.0 & other.0, self.1 & other.1)
+    }
+}
+
+impl BitOr<I32x8> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn bitor(self, other: I32x8) -> I32x8 {
+        I32x8(self.0 | other.0, self.1 | other.1)
+    }
+}
+
+/// Shifts each lane right, shifting in zeroes.
+impl Shr<u32> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn shr(self, amount: u32) -> I32x8 {
+        I32x8((self.0.to_u32x4() >> amount).to_i32x4(), (self.1.to_u32x4() >> amount).to_i32x4())
+    }
+}
@@ -8,10 +8,15 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::f32;
-use std::fmt::{self, Debug, Formatter};
-use std::ops::{Add, BitAnd, BitOr, Div, Index, IndexMut, Mul, Shr, Sub};
+use crate::math;
+use core::f32;
+use core::fmt::{self, Debug, Formatter};
+use core::ops::{Add, BitAnd, BitOr, Div, Index, IndexMut, Mul, Shr, Sub};
 
+pub use self::pair::{wide_vectors_accelerated, F32x8, I32x8, WideVectors};
+
+#[path = "../pair.rs"]
+mod pair;
 mod swizzle_f32x4;
 mod swizzle_i32x4;
 
@@ -57,22 +62,22 @@ impl F32x2 {
 
     #[inline]
     pub fn abs(self) -> F32x2 {
-        F32x2([self[0].abs(), self[1].abs()])
+        F32x2([math::abs(self[0]), math::abs(self[1])])
     }
 
     #[inline]
     pub fn floor(self) -> F32x2 {
-        F32x2([self[0].floor(), self[1].floor()])
+        F32x2([math::floor(self[0]), math::floor(self[1])])
     }
 
     #[inline]
     pub fn ceil(self) -> F32x2 {
-        F32x2([self[0].ceil(), self[1].ceil()])
+        F32x2([math::ceil(self[0]), math::ceil(self[1])])
     }
 
     #[inline]
     pub fn sqrt(self) -> F32x2 {
-        F32x2([self[0].sqrt(), self[1].sqrt()])
+        F32x2([math::sqrt(self[0]), math::sqrt(self[1])])
     }
 
     // Packed comparisons
@@ -119,13 +124,13 @@ impl F32x2 {
     /// Converts these packed floats to integers via rounding.
     #[inline]
     pub fn to_i32x2(self) -> I32x2 {
-        I32x2([self[0].round() as i32, self[1].round() as i32])
+        I32x2([math::round(self[0]) as i32, math::round(self[1]) as i32])
     }
 
     /// Converts these packed floats to integers via rounding.
     #[inline]
     pub fn to_i32x4(self) -> I32x4 {
-        I32x4([self[0].round() as i32, self[1].round() as i32, 0, 0])
+        I32x4([math::round(self[0]) as i32, math::round(self[1]) as i32, 0, 0])
     }
 
     // Swizzle
@@ -240,36 +245,36 @@ impl F32x4 {
 
     #[inline]
     pub fn abs(self) -> F32x4 {
-        F32x4([self[0].abs(), self[1].abs(), self[2].abs(), self[3].abs()])
+        F32x4([math::abs(self[0]), math::abs(self[1]), math::abs(self[2]), math::abs(self[3])])
     }
 
     #[inline]
     pub fn floor(self) -> F32x4 {
         F32x4([
-            self[0].floor(),
-            self[1].floor(),
-            self[2].floor(),
-            self[3].floor(),
+            math::floor(self[0]),
+            math::floor(self[1]),
+            math::floor(self[2]),
+            math::floor(self[3]),
         ])
     }
 
     #[inline]
     pub fn ceil(self) -> F32x4 {
         F32x4([
-            self[0].ceil(),
-            self[1].ceil(),
-            self[2].ceil(),
-            self[3].ceil(),
+            math::ceil(self[0]),
+            math::ceil(self[1]),
+            math::ceil(self[2]),
+            math::ceil(self[3]),
         ])
     }
 
     #[inline]
     pub fn sqrt(self) -> F32x4 {
         F32x4([
-            self[0].sqrt(),
-            self[1].sqrt(),
-            self[2].sqrt(),
-            self[3].sqrt(),
+            math::sqrt(self[0]),
+            math::sqrt(self[1]),
+            math::sqrt(self[2]),
+            math::sqrt(self[3]),
         ])
     }
 
@@ -319,10 +324,10 @@ impl F32x4 {
     #[inline]
     pub fn to_i32x4(self) -> I32x4 {
         I32x4([
-            self[0].round() as i32,
-            self[1].round() as i32,
-            self[2].round() as i32,
-            self[3].round() as i32,
+            math::round(self[0]) as i32,
+            math::round(self[1]) as i32,
+            math::round(self[2]) as i32,
+            math::round(self[3]) as i32,
         ])
     }
 
@@ -8,8 +8,10 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::default::{F32x4, I32x4, U32x4};
-use crate::scalar::F32x4 as F32x4S;
+use crate::default::{self, F32x4, F32x8, I32x4, I32x8, U32x4};
+use crate::scalar::{F32x4 as F32x4S, F32x8 as F32x8S, I32x4 as I32x4S, I32x8 as I32x8S};
+use crate::scalar::U32x4 as U32x4S;
+use crate::scalar::{self, WideVectors as WideVectorsS};
 
 // F32x4
 
@@ -37,7 +39,15 @@ fn test_f32x4_accessors_and_mutators() {
 fn test_f32x4_basic_ops() {
     let a = F32x4::new(1.0, 3.0, 5.0, 7.0);
     let b = F32x4::new(2.0, 2.0, 6.0, 6.0);
-    assert_eq!(a.approx_recip(), F32x4::new(0.99975586, 0.33325195, 0.19995117, 0.14282227));
+    // The reciprocal estimate's precision depends on the instruction set: SSE gives 12 bits and
+    // NEON 8, so only check it exactly where the result is known.
+    let recip = a.approx_recip();
+    for lane in 0..4 {
+        let exact = 1.0 / a[lane];
+        assert!((recip[lane] - exact).abs() <= exact / 256.0);
+    }
+    #[cfg(all(not(feature = "pf-no-simd"), any(target_arch = "x86", target_arch = "x86_64")))]
+    assert_eq!(recip, F32x4::new(0.99975586, 0.33325195, 0.19995117, 0.14282227));
     assert_eq!(a.min(b), F32x4::new(1.0, 2.0, 5.0, 6.0));
     assert_eq!(a.max(b), F32x4::new(2.0, 3.0, 6.0, 7.0));
     let c = F32x4::new(-1.0, 1.3, -20.0, 3.6);
@@ -667,6 +677,70 @@ fn test_i32x4_swizzles() {
     assert_eq!(a.zwww(), I32x4::new(3, 4, 4, 4));
 }
 
+// F32x8
+
+#[test]
+fn test_f32x8_basic_ops() {
+    let wide = match default::wide_vectors_accelerated() {
+        Some(wide) => wide,
+        None => return,
+    };
+    let a = F32x8::new(wide, 1.0, 3.0, 5.0, 7.0, -1.0, 1.3, -20.0, 3.6);
+    let b = F32x8::splat(wide, 2.0);
+    assert_eq!(a.min(b), F32x8::new(wide, 1.0, 2.0, 2.0, 2.0, -1.0, 1.3, -20.0, 2.0));
+    assert_eq!(a.max(b), F32x8::new(wide, 2.0, 3.0, 5.0, 7.0, 2.0, 2.0, 2.0, 3.6));
+    assert_eq!(a.abs().high(), F32x4::new(1.0, 1.3, 20.0, 3.6));
+    assert_eq!(a.floor().high(), F32x4::new(-1.0, 1.0, -20.0, 3.0));
+    assert_eq!(a.ceil().high(), F32x4::new(-1.0, 2.0, -20.0, 4.0));
+    assert_eq!((a * b - b).low(), F32x4::new(0.0, 4.0, 8.0, 12.0));
+    assert_eq!(F32x8::from_halves(wide, a.low(), a.high()), a);
+}
+
+// I32x8
+
+#[test]
+fn test_i32x8_basic_ops() {
+    let wide = match default::wide_vectors_accelerated() {
+        Some(wide) => wide,
+        None => return,
+    };
+    let a = I32x8::new(wide, 0x123, 0x456, 0x789, 0xabc, -1, 2, -3, 4);
+    assert_eq!((a & I32x8::splat(wide, 0xf00)) >> 8,
+               I32x8::new(wide, 1, 4, 7, 0xa, 0xf, 0, 0xf, 0));
+    assert_eq!(a.yxwz().low(), I32x4::new(0x456, 0x123, 0xabc, 0x789));
+    assert_eq!(a.high().to_f32x4(), a.to_f32x8().high());
+    assert_eq!(I32x8::from_halves(wide, a.low(), a.high()), a);
+}
+
+// Emulated F32x8 and I32x8
+
+#[test]
+fn test_wide_vectors_are_not_accelerated_when_emulated() {
+    assert!(scalar::wide_vectors_accelerated().is_none());
+}
+
+#[test]
+fn test_f32x8s_basic_ops() {
+    let wide = WideVectorsS::emulated();
+    let a = F32x8S::new(wide, 1.0, 3.0, 5.0, 7.0, -1.0, 1.3, -20.0, 3.6);
+    let b = F32x8S::splat(wide, 2.0);
+    assert_eq!(a.min(b), F32x8S::new(wide, 1.0, 2.0, 2.0, 2.0, -1.0, 1.3, -20.0, 2.0));
+    assert_eq!(a.max(b), F32x8S::new(wide, 2.0, 3.0, 5.0, 7.0, 2.0, 2.0, 2.0, 3.6));
+    assert_eq!(a.abs().high(), F32x4S::new(1.0, 1.3, 20.0, 3.6));
+    assert_eq!((a * b - b).low(), F32x4S::new(0.0, 4.0, 8.0, 12.0));
+    assert_eq!(F32x8S::from_halves(wide, a.low(), a.high()), a);
+}
+
+#[test]
+fn test_i32x8s_basic_ops() {
+    let wide = WideVectorsS::emulated();
+    let a = I32x8S::new(wide, 0x123, 0x456, 0x789, 0xabc, -1, 2, -3, 4);
+    assert_eq!((a & I32x8S::splat(wide, 0xf00)) >> 8,
+               I32x8S::new(wide, 1, 4, 7, 0xa, 0xf, 0, 0xf, 0));
+    assert_eq!(a.yxwz().low(), I32x4S::new(0x456, 0x123, 0xabc, 0x789));
+    assert_eq!(I32x8S::from_halves(wide, a.low(), a.high()), a);
+}
+
 // Scalar F32x4
 
 #[test]
@@ -689,3 +763,173 @@ fn test_f32x4s_basic_ops() {
     assert_eq!(c.ceil(), F32x4S::new(-1.0, 2.0, -20.0, 4.0));
     assert_eq!(c.to_i32x4().to_f32x4(), F32x4S::new(-1.0, 1.0, -20.0, 4.0));
 }
+
+// Scalar and SIMD backend agreement
+//
+// `default` is SSE or NEON where available, so these check each operation lane by lane against
+// the scalar fallback. Float lanes are compared bitwise so that the sign of zero counts.
+// Overflowing integer arithmetic, float-to-int conversions of out-of-range values, and rounding
+// of exact halves are left out: the scalar backend panics, saturates, or rounds away from zero
+// there, while the hardware wraps and rounds to even.
+
+const F32_EDGES: [[f32; 4]; 4] = [
+    [-0.0, 0.0, 0.5, -0.5],
+    [1.25, -1.75, 2.75, -2.25],
+    [16777216.0, -16777216.0, 1.0e-30, -1.0e30],
+    [f32::MIN_POSITIVE, f32::MAX, -f32::MAX, 3.0],
+];
+
+const I32_EDGES: [[i32; 4]; 3] = [
+    [0, -1, 1, 2],
+    [i32::MIN, i32::MAX, -0x10000, 0x7fff],
+    [0x12345678, -0x12345678, 0x55555555, -0x55555556],
+];
+
+fn f32x4_pair(lanes: [f32; 4]) -> (F32x4, F32x4S) {
+    (F32x4::new(lanes[0], lanes[1], lanes[2], lanes[3]),
+     F32x4S::new(lanes[0], lanes[1], lanes[2], lanes[3]))
+}
+
+fn i32x4_pair(lanes: [i32; 4]) -> (I32x4, I32x4S) {
+    (I32x4::new(lanes[0], lanes[1], lanes[2], lanes[3]),
+     I32x4S::new(lanes[0], lanes[1], lanes[2], lanes[3]))
+}
+
+fn assert_f32x4_matches(simd: F32x4, scalar: F32x4S) {
+    for lane in 0..4 {
+        assert_eq!(simd[lane].to_bits(), scalar[lane].to_bits(),
+                   "lane {}: {:?} != {:?}", lane, simd, scalar);
+    }
+}
+
+fn assert_i32x4_matches(simd: I32x4, scalar: I32x4S) {
+    for lane in 0..4 {
+        assert_eq!(simd[lane], scalar[lane], "lane {}: {:?} != {:?}", lane, simd, scalar);
+    }
+}
+
+fn assert_u32x4_matches(simd: U32x4, scalar: U32x4S) {
+    for lane in 0..4 {
+        assert_eq!(simd[lane], scalar[lane], "lane {}: {:?} != {:?}", lane, simd, scalar);
+    }
+}
+
+#[test]
+fn test_backends_agree_on_f32x4_lane_order() {
+    let (a, a_s) = f32x4_pair([1.0, 2.0, 3.0, 4.0]);
+    let (b, b_s) = f32x4_pair([5.0, 6.0, 7.0, 8.0]);
+    assert_f32x4_matches(a, a_s);
+    assert_eq!((a.x(), a.y(), a.z(), a.w()), (a_s[0], a_s[1], a_s[2], a_s[3]));
+    assert_f32x4_matches(a.concat_xy_xy(b), a_s.concat_xy_xy(b_s));
+    assert_f32x4_matches(a.concat_xy_zw(b), a_s.concat_xy_zw(b_s));
+    assert_f32x4_matches(a.concat_zw_zw(b), a_s.concat_zw_zw(b_s));
+    assert_f32x4_matches(a.concat_wz_yx(b), a_s.concat_wz_yx(b_s));
+    assert_f32x4_matches(a.wzyx(), a_s.wzyx());
+    assert_f32x4_matches(a.yxwz(), a_s.yxwz());
+    assert_f32x4_matches(a.xxyy(), a_s.xxyy());
+    assert_f32x4_matches(a.zwxy(), a_s.zwxy());
+    for &(simd, scalar) in &[(a.xy(), a_s.xy()), (a.xw(), a_s.xw()), (a.yx(), a_s.yx()),
+                             (a.zy(), a_s.zy()), (a.zw(), a_s.zw())] {
+        assert_eq!((simd[0], simd[1]), (scalar[0], scalar[1]));
+    }
+}
+
+#[test]
+fn test_backends_agree_on_f32x4_edge_values() {
+    for &lanes in &F32_EDGES {
+        let (a, a_s) = f32x4_pair(lanes);
+        assert_f32x4_matches(a.abs(), a_s.abs());
+        assert_f32x4_matches(a.floor(), a_s.floor());
+        assert_f32x4_matches(a.ceil(), a_s.ceil());
+        assert_f32x4_matches(a.abs().sqrt(), a_s.abs().sqrt());
+        assert_f32x4_matches(a + a, a_s + a_s);
+        assert_f32x4_matches(a * a, a_s * a_s);
+        assert_f32x4_matches(a - a.floor(), a_s - a_s.floor());
+        for &other in &F32_EDGES {
+            let (b, b_s) = f32x4_pair(other);
+            assert_u32x4_matches(a.packed_eq(b), a_s.packed_eq(b_s));
+            assert_u32x4_matches(a.packed_gt(b), a_s.packed_gt(b_s));
+            assert_u32x4_matches(a.packed_le(b), a_s.packed_le(b_s));
+            assert_u32x4_matches(a.packed_lt(b), a_s.packed_lt(b_s));
+        }
+    }
+}
+
+#[test]
+fn test_backends_agree_on_f32x4_min_max_and_clamp() {
+    // Signed zeros are skipped: which zero `min` and `max` return differs between backends.
+    let (a, a_s) = f32x4_pair([1.25, -1.75, f32::MAX, -f32::MAX]);
+    let (b, b_s) = f32x4_pair([-3.0, 1.0e-30, 16777216.0, -1.0e30]);
+    assert_f32x4_matches(a.min(b), a_s.min(b_s));
+    assert_f32x4_matches(a.max(b), a_s.max(b_s));
+    let (lo, lo_s) = f32x4_pair([-1.0, -1.0, -1.0, -1.0]);
+    let (hi, hi_s) = f32x4_pair([1.0, 1.0, 1.0, 1.0]);
+    assert_f32x4_matches(a.clamp(lo, hi), a_s.clamp(lo_s, hi_s));
+    assert_f32x4_matches(b.clamp(lo, hi), b_s.clamp(lo_s, hi_s));
+}
+
+#[test]
+fn test_backends_agree_on_f32x4_to_i32x4() {
+    let (a, a_s) = f32x4_pair([1.25, -1.75, 2.75, -2.25]);
+    assert_i32x4_matches(a.to_i32x4(), a_s.to_i32x4());
+    let (b, b_s) = f32x4_pair([-0.0, 0.4999999, -0.4999999, 16777216.0]);
+    assert_i32x4_matches(b.to_i32x4(), b_s.to_i32x4());
+    let (c, c_s) = f32x4_pair([2147483520.0, -2147483648.0, 1.0e-30, -1.0e-30]);
+    assert_i32x4_matches(c.to_i32x4(), c_s.to_i32x4());
+}
+
+#[test]
+fn test_backends_agree_on_i32x4_lane_order() {
+    let (a, a_s) = i32x4_pair([1, 2, 3, 4]);
+    let (b, b_s) = i32x4_pair([5, 6, 7, 8]);
+    assert_i32x4_matches(a, a_s);
+    assert_i32x4_matches(a.concat_xy_xy(b), a_s.concat_xy_xy(b_s));
+    assert_i32x4_matches(a.concat_zw_zw(b), a_s.concat_zw_zw(b_s));
+    assert_i32x4_matches(a.wzyx(), a_s.wzyx());
+    assert_i32x4_matches(a.yxwz(), a_s.yxwz());
+    for &(simd, scalar) in &[(a.xy(), a_s.xy()), (a.xw(), a_s.xw()), (a.zy(), a_s.zy()),
+                             (a.zw(), a_s.zw())] {
+        assert_eq!((simd[0], simd[1]), (scalar[0], scalar[1]));
+    }
+}
+
+#[test]
+fn test_backends_agree_on_i32x4_edge_values() {
+    for &lanes in &I32_EDGES {
+        let (a, a_s) = i32x4_pair(lanes);
+        assert_f32x4_matches(a.to_f32x4(), a_s.to_f32x4());
+        assert_u32x4_matches(a.to_u32x4(), a_s.to_u32x4());
+        let (mask, mask_s) = i32x4_pair([0xff, -0x100, 0x0f0f0f0f, i32::MIN]);
+        assert_i32x4_matches(a & mask, a_s & mask_s);
+        assert_i32x4_matches(a | mask, a_s | mask_s);
+        for &other in &I32_EDGES {
+            let (b, b_s) = i32x4_pair(other);
+            assert_i32x4_matches(a.min(b), a_s.min(b_s));
+            assert_i32x4_matches(a.max(b), a_s.max(b_s));
+            assert_u32x4_matches(a.packed_eq(b), a_s.packed_eq(b_s));
+            assert_u32x4_matches(a.packed_gt(b), a_s.packed_gt(b_s));
+            assert_u32x4_matches(a.packed_le(b), a_s.packed_le(b_s));
+            assert_u32x4_matches(a.packed_lt(b), a_s.packed_lt(b_s));
+        }
+    }
+    // Stay clear of overflow, which panics in the scalar backend under debug assertions.
+    let (a, a_s) = i32x4_pair([i32::MIN + 1, i32::MAX - 1, -0x8000, 0x7fff]);
+    let (one, one_s) = i32x4_pair([-1, 1, 0x10000, 0x10000]);
+    assert_i32x4_matches(a + one, a_s + one_s);
+    assert_i32x4_matches(a - one.min(I32x4::splat(0)), a_s - one_s.min(I32x4S::splat(0)));
+    assert_i32x4_matches(a.concat_zw_zw(a) * one, a_s.concat_zw_zw(a_s) * one_s);
+}
+
+#[test]
+fn test_backends_agree_on_u32x4_edge_values() {
+    let (a, a_s) = (U32x4::new(!0, 0x80000000, 1, 0), U32x4S::new(!0, 0x80000000, 1, 0));
+    assert_i32x4_matches(a.to_i32x4(), a_s.to_i32x4());
+    for shift in &[0, 1, 31] {
+        assert_u32x4_matches(a >> *shift, a_s >> *shift);
+    }
+    assert_eq!((a.all_true(), a.all_false()), (a_s.all_true(), a_s.all_false()));
+    let all = (U32x4::new(!0, !0, !0, !0), U32x4S::new(!0, !0, !0, !0));
+    assert_eq!((all.0.all_true(), all.0.all_false()), (all.1.all_true(), all.1.all_false()));
+    let none = (U32x4::new(0, 0, 0, 0), U32x4S::new(0, 0, 0, 0));
+    assert_eq!((none.0.all_true(), none.0.all_false()), (none.1.all_true(), none.1.all_false()));
+}
@@ -0,0 +1,365 @@
+// pathfinder/simd/src/x86/avx.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Eight-wide vectors backed by AVX2.
+//!
+//! AVX2 isn't part of the x86-64 baseline, so these vectors can only be built with the
+//! `WideVectors` token that `wide_vectors_accelerated()` returns once the CPU has been checked.
+//! Use them from functions compiled with `#[target_feature(enable = "avx2")]` so that the
+//! operations below get inlined.
+
+use crate::x86::{F32x4, I32x4};
+use core::fmt::{self, Debug, Formatter};
+use core::mem;
+use core::ops::{Add, BitAnd, BitOr, Div, Index, IndexMut, Mul, Shr, Sub};
+
+#[cfg(target_pointer_width = "32")]
+use core::arch::x86::{__m256, __m256i};
+#[cfg(target_pointer_width = "32")]
+use core::arch::x86;
+#[cfg(target_pointer_width = "64")]
+use core::arch::x86_64::{__m256, __m256i};
+#[cfg(target_pointer_width = "64")]
+use core::arch::x86_64 as x86;
+
+/// Proof that the CPU supports the instructions behind `F32x8` and `I32x8`.
+///
+/// The only way to get one is from `wide_vectors_accelerated()`, and every wide vector is built
+/// from one, so holding a wide vector means its operations are safe to run.
+#[derive(Clone, Copy, Debug)]
+pub struct WideVectors(());
+
+/// Returns a token for building `F32x8` and `I32x8` if they are backed by native 256-bit
+/// instructions.
+///
+/// The result of the CPU feature check is cached by the standard library, so this is cheap
+/// enough to call at the top of a hot loop. Without `std`, the CPU can't be checked at runtime, so
+/// this only succeeds if AVX2 was enabled at compile time.
+#[cfg(feature = "pf-std")]
+#[inline]
+pub fn wide_vectors_accelerated() -> Option<WideVectors> {
+    if is_x86_feature_detected!("avx2") { Some(WideVectors(())) } else { None }
+}
+
+#[cfg(not(feature = "pf-std"))]
+#[inline]
+pub fn wide_vectors_accelerated() -> Option<WideVectors> {
+    if cfg!(target_feature = "avx2") { Some(WideVectors(())) } else { None }
+}
+
+// Eight 32-bit floats
+
+#[derive(Clone, Copy)]
+pub struct F32x8(__m256);
+
+impl F32x8 {
+    // Constructors
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(_: WideVectors, a: f32, b: f32, c: f32, d: f32, e: f32, f: f32, g: f32, h: f32)
+               -> F32x8 {
+        unsafe {
+            let vector = [a, b, c, d, e, f, g, h];
+            F32x8(x86::_mm256_loadu_ps(vector.as_ptr()))
+        }
+    }
+
+    #[inline]
+    pub fn splat(_: WideVectors, x: f32) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_set1_ps(x)) }
+    }
+
+    #[inline]
+    pub fn from_halves(_: WideVectors, low: F32x4, high: F32x4) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_set_m128(high.0, low.0)) }
+    }
+
+    // Basic operations
+
+    #[inline]
+    pub fn min(self, other: F32x8) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_min_ps(self.0, other.0)) }
+    }
+
+    #[inline]
+    pub fn max(self, other: F32x8) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_max_ps(self.0, other.0)) }
+    }
+
+    #[inline]
+    pub fn clamp(self, min: F32x8, max: F32x8) -> F32x8 {
+        self.max(min).min(max)
+    }
+
+    #[inline]
+    pub fn abs(self) -> F32x8 {
+        unsafe {
+            let tmp = x86::_mm256_srli_epi32(x86::_mm256_set1_epi32(-1), 1);
+            F32x8(x86::_mm256_and_ps(x86::_mm256_castsi256_ps(tmp), self.0))
+        }
+    }
+
+    #[inline]
+    pub fn floor(self) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_floor_ps(self.0)) }
+    }
+
+    #[inline]
+    pub fn ceil(self) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_ceil_ps(self.0)) }
+    }
+
+    #[inline]
+    pub fn sqrt(self) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_sqrt_ps(self.0)) }
+    }
+
+    // Conversions
+
+    /// Converts these packed floats to integers via rounding.
+    #[inline]
+    pub fn to_i32x8(self) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_cvtps_epi32(self.0)) }
+    }
+
+    // Extraction
+
+    #[inline]
+    pub fn low(self) -> F32x4 {
+        unsafe { F32x4(x86::_mm256_castps256_ps128(self.0)) }
+    }
+
+    #[inline]
+    pub fn high(self) -> F32x4 {
+        unsafe { F32x4(x86::_mm256_extractf128_ps(self.0, 1)) }
+    }
+}
+
+impl Index<usize> for F32x8 {
+    type Output = f32;
+    #[inline]
+    fn index(&self, index: usize) -> &f32 {
+        unsafe { &mem::transmute::<&__m256, &[f32; 8]>(&self.0)[index] }
+    }
+}
+
+impl IndexMut<usize> for F32x8 {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut f32 {
+        unsafe { &mut mem::transmute::<&mut __m256, &mut [f32; 8]>(&mut self.0)[index] }
+    }
+}
+
+impl Debug for F32x8 {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f,
+               "<{}, {}, {}, {}, {}, {}, {}, {}>",
+               self[0], self[1], self[2], self[3], self[4], self[5], self[6], self[7])
+    }
+}
+
+impl PartialEq for F32x8 {
+    #[inline]
+    fn eq(&self, other: &F32x8) -> bool {
+        unsafe {
+            let mask = x86::_mm256_cmp_ps(self.0, other.0, x86::_CMP_EQ_OQ);
+            x86::_mm256_movemask_ps(mask) == 0xff
+        }
+    }
+}
+
+impl Add<F32x8> for F32x8 {
+    type Output = F32x8;
+    #[inline]
+    fn add(self, other: F32x8) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_add_ps(self.0, other.0)) }
+    }
+}
+
+impl Div<F32x8> for F32x8 {
+    type Output = F32x8;
+    #[inline]
+    fn div(self, other: F32x8) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_div_ps(self.0, other.0)) }
+    }
+}
+
+impl Mul<F32x8> for F32x8 {
+    type Output = F32x8;
+    #[inline]
+    fn mul(self, other: F32x8) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_mul_ps(self.0, other.0)) }
+    }
+}
+
+impl Sub<F32x8> for F32x8 {
+    type Output = F32x8;
+    #[inline]
+    fn sub(self, other: F32x8) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_sub_ps(self.0, other.0)) }
+    }
+}
+
+// Eight 32-bit signed integers
+
+#[derive(Clone, Copy)]
+pub struct I32x8(__m256i);
+
+impl I32x8 {
+    // Constructors
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(_: WideVectors, a: i32, b: i32, c: i32, d: i32, e: i32, f: i32, g: i32, h: i32)
+               -> I32x8 {
+        unsafe {
+            let vector = [a, b, c, d, e, f, g, h];
+            I32x8(x86::_mm256_loadu_si256(vector.as_ptr() as *const __m256i))
+        }
+    }
+
+    #[inline]
+    pub fn splat(_: WideVectors, x: i32) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_set1_epi32(x)) }
+    }
+
+    #[inline]
+    pub fn from_halves(_: WideVectors, low: I32x4, high: I32x4) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_set_m128i(high.0, low.0)) }
+    }
+
+    // Basic operations
+
+    #[inline]
+    pub fn min(self, other: I32x8) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_min_epi32(self.0, other.0)) }
+    }
+
+    #[inline]
+    pub fn max(self, other: I32x8) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_max_epi32(self.0, other.0)) }
+    }
+
+    // Swizzle
+
+    /// Swaps the first two and the last two lanes of each half of this vector.
+    #[inline]
+    pub fn yxwz(self) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_shuffle_epi32(self.0, 0b1011_0001)) }
+    }
+
+    // Conversions
+
+    /// Converts these packed integers to floats.
+    #[inline]
+    pub fn to_f32x8(self) -> F32x8 {
+        unsafe { F32x8(x86::_mm256_cvtepi32_ps(self.0)) }
+    }
+
+    // Extraction
+
+    #[inline]
+    pub fn low(self) -> I32x4 {
+        unsafe { I32x4(x86::_mm256_castsi256_si128(self.0)) }
+    }
+
+    #[inline]
+    pub fn high(self) -> I32x4 {
+        unsafe { I32x4(x86::_mm256_extracti128_si256(self.0, 1)) }
+    }
+}
+
+impl Index<usize> for I32x8 {
+    type Output = i32;
+    #[inline]
+    fn index(&self, index: usize) -> &i32 {
+        unsafe { &mem::transmute::<&__m256i, &[i32; 8]>(&self.0)[index] }
+    }
+}
+
+impl IndexMut<usize> for I32x8 {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut i32 {
+        unsafe { &mut mem::transmute::<&mut __m256i, &mut [i32; 8]>(&mut self.0)[index] }
+    }
+}
+
+impl Debug for I32x8 {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f,
+               "<{}, {}, {}, {}, {}, {}, {}, {}>",
+               self[0], self[1], self[2], self[3], self[4], self[5], self[6], self[7])
+    }
+}
+
+impl PartialEq for I32x8 {
+    #[inline]
+    fn eq(&self, other: &I32x8) -> bool {
+        unsafe {
+            let mask = x86::_mm256_cmpeq_epi32(self.0, other.0);
+            x86::_mm256_movemask_epi8(mask) == -1
+        }
+    }
+}
+
+impl Add<I32x8> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn add(self, other: I32x8) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_add_epi32(self.0, other.0)) }
+    }
+}
+
+impl Sub<I32x8> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn sub(self, other: I32x8) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_sub_epi32(self.0, other.0)) }
+    }
+}
+
+impl Mul<I32x8> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn mul(self, other: I32x8) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_mullo_epi32(self.0, other.0)) }
+    }
+}
+
+impl BitAnd<I32x8> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn bitand(self, other: I32x8) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_and_si256(self.0, other.0)) }
+    }
+}
+
+impl BitOr<I32x8> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn bitor(self, other: I32x8) -> I32x8 {
+        unsafe { I32x8(x86::_mm256_or_si256(self.0, other.0)) }
+    }
+}
+
+/// Shifts each lane right, shifting in zeroes.
+impl Shr<u32> for I32x8 {
+    type Output = I32x8;
+    #[inline]
+    fn shr(self, amount: u32) -> I32x8 {
+        unsafe {
+            let amount = x86::_mm_cvtsi32_si128(amount as i32);
+            I32x8(x86::_mm256_srl_epi32(self.0, amount))
+        }
+    }
+}
@@ -0,0 +1,878 @@
+// pathfinder/svg/src/animation.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! SMIL animation of SVG documents.
+//!
+//! usvg discards `<animate>` and `<animateTransform>` elements, so this module reads them from the
+//! document source separately and applies them to the usvg tree at the requested time before the
+//! tree is converted to a scene.
+//!
+//! Only attributes that survive usvg's conversion to paths can be animated: `opacity`,
+//! `fill-opacity`, `stroke-opacity`, `stroke-width`, `fill`, `stroke`, and `transform`. Animated
+//! geometry (for example, `d` or `width`) is not supported. Timing supports offset `begin` values,
+//! `dur`, `repeatCount`, `fill="freeze"`, `values`, `keyTimes`, `calcMode="discrete"`, and
+//! `from`/`to`/`by`; event- and syncbase-relative timing is not.
+//!
+//! Only SMIL animation elements are read. CSS animations (`@keyframes` rules and the `animation`
+//! properties that use them) are ignored, so documents animated that way are drawn statically.
+
+use crate::BuiltSVG;
+use pathfinder_renderer::scene::Scene;
+use roxmltree::{Document, Node as XmlNode};
+use std::str;
+use usvg::{Error as UsvgError, Fill, Node, NodeKind, Opacity, Options as UsvgOptions};
+use usvg::{Color as SvgColor, Paint as UsvgPaint, Stroke, StrokeWidth, Transform as UsvgTransform};
+use usvg::Tree;
+
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// An SVG document with SMIL animations that can be sampled at any time.
+pub struct AnimatedSVGScene {
+    tree: Tree,
+    animations: Vec<Animation>,
+    // The unanimated contents of each animated node, restored before every sample.
+    base_nodes: Vec<(Node, NodeKind)>,
+}
+
+impl AnimatedSVGScene {
+    /// Parses an SVG document along with its animations.
+    pub fn from_data(data: &[u8], options: &UsvgOptions)
+                     -> Result<AnimatedSVGScene, UsvgError> {
+        // Keep groups with IDs around so that animations targeting them have somewhere to go.
+        let mut options = options.clone();
+        options.keep_named_groups = true;
+        let tree = Tree::from_data(data, &options)?;
+
+        // Compressed documents are drawn without animation.
+        let mut animations = match str::from_utf8(data) {
+            Ok(text) => parse_animations(text),
+            Err(_) => vec![],
+        };
+
+        let mut base_nodes: Vec<(Node, NodeKind)> = vec![];
+        animations.retain(|animation| {
+            let node = match tree.node_by_id(&animation.target_id) {
+                None => return false,
+                Some(node) => node,
+            };
+            if !base_nodes.iter().any(|(base_node, _)| *base_node == node) {
+                let kind = (*node.borrow()).clone();
+                base_nodes.push((node, kind));
+            }
+            true
+        });
+
+        // `to` animations start from the unanimated value.
+        for animation in &mut animations {
+            if animation.from_base_value {
+                let base_kind = base_nodes.iter().find(|(node, _)| {
+                    node.id() == animation.target_id
+                }).map(|(_, kind)| kind);
+                if let Some(value) = base_kind.and_then(|kind| animation.attribute.get(kind)) {
+                    animation.values.insert(0, value);
+                }
+            }
+        }
+        animations.retain(|animation| !animation.values.is_empty());
+
+        Ok(AnimatedSVGScene { tree, animations, base_nodes })
+    }
+
+    /// Returns the time, in seconds, at which all animations have finished, or `None` if any
+    /// animation repeats indefinitely.
+    pub fn duration(&self) -> Option<f32> {
+        let mut duration = 0.0;
+        for animation in &self.animations {
+            let end = animation.begin + animation.active_duration();
+            if end.is_infinite() {
+                return None;
+            }
+            duration = f32::max(duration, end);
+        }
+        Some(duration)
+    }
+
+    /// Returns the tree as it appears `time` seconds into the animation.
+    pub fn tree_at(&mut self, time: f32) -> &Tree {
+        for (node, kind) in &self.base_nodes {
+            *node.borrow_mut() = kind.clone();
+        }
+
+        // Later animations override earlier ones, as in SMIL's sandwich model.
+        for animation in &self.animations {
+            let value = match animation.sample(time) {
+                None => continue,
+                Some(value) => value,
+            };
+            if let Some(node) = self.tree.node_by_id(&animation.target_id) {
+                animation.attribute.set(&mut *node.borrow_mut(), &value, animation.additive);
+            }
+        }
+
+        &self.tree
+    }
+
+    /// Builds the scene as it appears `time` seconds into the animation.
+    pub fn built_svg_at(&mut self, time: f32) -> BuiltSVG {
+        BuiltSVG::from_tree(self.tree_at(time))
+    }
+
+    /// Returns the scene as it appears `time` seconds into the animation.
+    #[inline]
+    pub fn scene_at(&mut self, time: f32) -> Scene {
+        self.built_svg_at(time).scene
+    }
+}
+
+struct Animation {
+    target_id: String,
+    attribute: AnimatedAttribute,
+    // Each value is a list of numbers: a single number for scalar attributes, RGB for colors, and
+    // the parameters for transforms.
+    values: Vec<Vec<f32>>,
+    // If set, the unanimated value must be prepended to `values`.
+    from_base_value: bool,
+    key_times: Option<Vec<f32>>,
+    discrete: bool,
+    begin: f32,
+    simple_duration: f32,
+    repeat_count: f32,
+    freeze: bool,
+    additive: bool,
+}
+
+impl Animation {
+    fn active_duration(&self) -> f32 {
+        self.simple_duration * self.repeat_count
+    }
+
+    fn sample(&self, time: f32) -> Option<Vec<f32>> {
+        let local_time = time - self.begin;
+        if local_time < 0.0 {
+            return None;
+        }
+
+        let progress = if local_time >= self.active_duration() {
+            if !self.freeze {
+                return None;
+            }
+            // Frozen animations hold the value they had at the end of the active duration.
+            match self.repeat_count.fract() {
+                fraction if fraction == 0.0 => 1.0,
+                fraction => fraction,
+            }
+        } else {
+            (local_time / self.simple_duration).fract()
+        };
+
+        Some(self.value_at(progress))
+    }
+
+    fn value_at(&self, progress: f32) -> Vec<f32> {
+        let value_count = self.values.len();
+        if value_count == 1 {
+            return self.values[0].clone();
+        }
+
+        // Discrete animations divide the duration into one interval per value; interpolated ones
+        // into one interval between each pair of values.
+        let interval_count = if self.discrete { value_count } else { value_count - 1 };
+        let key_time = |index: usize| {
+            match self.key_times {
+                Some(ref key_times) => key_times[index],
+                None => index as f32 / interval_count as f32,
+            }
+        };
+
+        let mut index = 0;
+        while index + 1 < value_count && key_time(index + 1) <= progress {
+            index += 1;
+        }
+        if self.discrete || index + 1 == value_count {
+            return self.values[index].clone();
+        }
+
+        let (start_time, end_time) = (key_time(index), key_time(index + 1));
+        let t = if end_time > start_time {
+            (progress - start_time) / (end_time - start_time)
+        } else {
+            0.0
+        };
+        let (from, to) = (&self.values[index], &self.values[index + 1]);
+        if from.len() != to.len() {
+            return if t < 0.5 { from.clone() } else { to.clone() };
+        }
+        from.iter().zip(to.iter()).map(|(from, to)| from + (to - from) * t).collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum AnimatedAttribute {
+    Opacity,
+    FillOpacity,
+    StrokeOpacity,
+    StrokeWidth,
+    Fill,
+    Stroke,
+    Transform(TransformType),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TransformType {
+    Translate,
+    Scale,
+    Rotate,
+    SkewX,
+    SkewY,
+}
+
+impl AnimatedAttribute {
+    fn from_name(name: &str) -> Option<AnimatedAttribute> {
+        match name {
+            "opacity" => Some(AnimatedAttribute::Opacity),
+            "fill-opacity" => Some(AnimatedAttribute::FillOpacity),
+            "stroke-opacity" => Some(AnimatedAttribute::StrokeOpacity),
+            "stroke-width" => Some(AnimatedAttribute::StrokeWidth),
+            "fill" => Some(AnimatedAttribute::Fill),
+            "stroke" => Some(AnimatedAttribute::Stroke),
+            _ => None,
+        }
+    }
+
+    fn parse_value(self, string: &str) -> Option<Vec<f32>> {
+        match self {
+            AnimatedAttribute::Fill | AnimatedAttribute::Stroke => {
+                let color = string.trim().parse::<svgtypes::Color>().ok()?;
+                Some(vec![color.red as f32, color.green as f32, color.blue as f32])
+            }
+            _ => {
+                let numbers: Option<Vec<f32>> = string.split(|c: char| {
+                    c == ',' || c.is_whitespace()
+                }).filter(|number| !number.is_empty()).map(|number| {
+                    number.parse().ok()
+                }).collect();
+                numbers.filter(|numbers| !numbers.is_empty())
+            }
+        }
+    }
+
+    // Returns the current value of this attribute on a node, if it has one.
+    fn get(self, kind: &NodeKind) -> Option<Vec<f32>> {
+        match (self, kind) {
+            (AnimatedAttribute::Opacity, &NodeKind::Group(ref group)) => {
+                Some(vec![group.opacity.value() as f32])
+            }
+            (AnimatedAttribute::Opacity, &NodeKind::Path(_)) => Some(vec![1.0]),
+            (AnimatedAttribute::FillOpacity, &NodeKind::Path(ref path)) => {
+                path.fill.as_ref().map(|fill| vec![fill.opacity.value() as f32])
+            }
+            (AnimatedAttribute::StrokeOpacity, &NodeKind::Path(ref path)) => {
+                path.stroke.as_ref().map(|stroke| vec![stroke.opacity.value() as f32])
+            }
+            (AnimatedAttribute::StrokeWidth, &NodeKind::Path(ref path)) => {
+                path.stroke.as_ref().map(|stroke| vec![stroke.width.value() as f32])
+            }
+            (AnimatedAttribute::Fill, &NodeKind::Path(ref path)) => {
+                match path.fill {
+                    Some(Fill { paint: UsvgPaint::Color(color), .. }) => {
+                        Some(color_to_value(color))
+                    }
+                    _ => None,
+                }
+            }
+            (AnimatedAttribute::Stroke, &NodeKind::Path(ref path)) => {
+                match path.stroke {
+                    Some(Stroke { paint: UsvgPaint::Color(color), .. }) => {
+                        Some(color_to_value(color))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn set(self, kind: &mut NodeKind, value: &[f32], additive: bool) {
+        match (self, kind) {
+            (AnimatedAttribute::Opacity, &mut NodeKind::Group(ref mut group)) => {
+                group.opacity = Opacity::new(value[0] as f64);
+            }
+            (AnimatedAttribute::Opacity, &mut NodeKind::Path(ref mut path)) => {
+                // usvg only wraps paths in groups if they start out translucent, so approximate
+                // element opacity with the fill and stroke opacities.
+                if let Some(ref mut fill) = path.fill {
+                    fill.opacity = Opacity::new(fill.opacity.value() * value[0] as f64);
+                }
+                if let Some(ref mut stroke) = path.stroke {
+                    stroke.opacity = Opacity::new(stroke.opacity.value() * value[0] as f64);
+                }
+            }
+            (AnimatedAttribute::FillOpacity, &mut NodeKind::Path(ref mut path)) => {
+                if let Some(ref mut fill) = path.fill {
+                    fill.opacity = Opacity::new(value[0] as f64);
+                }
+            }
+            (AnimatedAttribute::StrokeOpacity, &mut NodeKind::Path(ref mut path)) => {
+                if let Some(ref mut stroke) = path.stroke {
+                    stroke.opacity = Opacity::new(value[0] as f64);
+                }
+            }
+            (AnimatedAttribute::StrokeWidth, &mut NodeKind::Path(ref mut path)) => {
+                if value[0] <= 0.0 {
+                    path.stroke = None;
+                } else if let Some(ref mut stroke) = path.stroke {
+                    stroke.width = StrokeWidth::new(value[0] as f64);
+                }
+            }
+            (AnimatedAttribute::Fill, &mut NodeKind::Path(ref mut path)) => {
+                let fill = path.fill.get_or_insert_with(Fill::default);
+                fill.paint = UsvgPaint::Color(value_to_color(value));
+            }
+            (AnimatedAttribute::Stroke, &mut NodeKind::Path(ref mut path)) => {
+                let stroke = path.stroke.get_or_insert_with(Stroke::default);
+                stroke.paint = UsvgPaint::Color(value_to_color(value));
+            }
+            (AnimatedAttribute::Transform(transform_type), kind) => {
+                let transform = match *kind {
+                    NodeKind::Group(ref mut group) => &mut group.transform,
+                    NodeKind::Path(ref mut path) => &mut path.transform,
+                    NodeKind::Image(ref mut image) => &mut image.transform,
+                    _ => return,
+                };
+                let animated_transform = transform_type.to_transform(value);
+                if additive {
+                    transform.append(&animated_transform);
+                } else {
+                    *transform = animated_transform;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl TransformType {
+    fn from_name(name: &str) -> Option<TransformType> {
+        match name {
+            "translate" => Some(TransformType::Translate),
+            "scale" => Some(TransformType::Scale),
+            "rotate" => Some(TransformType::Rotate),
+            "skewX" => Some(TransformType::SkewX),
+            "skewY" => Some(TransformType::SkewY),
+            _ => None,
+        }
+    }
+
+    fn to_transform(self, value: &[f32]) -> UsvgTransform {
+        let param = |index: usize, default: f32| {
+            value.get(index).cloned().unwrap_or(default) as f64
+        };
+        match self {
+            TransformType::Translate => UsvgTransform::new_translate(param(0, 0.0), param(1, 0.0)),
+            TransformType::Scale => {
+                let scale_x = param(0, 1.0);
+                UsvgTransform::new_scale(scale_x, param(1, scale_x as f32))
+            }
+            TransformType::Rotate => {
+                let (center_x, center_y) = (param(1, 0.0), param(2, 0.0));
+                let mut transform = UsvgTransform::new_translate(center_x, center_y);
+                transform.append(&UsvgTransform::new_rotate(param(0, 0.0)));
+                transform.append(&UsvgTransform::new_translate(-center_x, -center_y));
+                transform
+            }
+            TransformType::SkewX => UsvgTransform::new_skew_x(param(0, 0.0)),
+            TransformType::SkewY => UsvgTransform::new_skew_y(param(0, 0.0)),
+        }
+    }
+}
+
+fn parse_animations(text: &str) -> Vec<Animation> {
+    let document = match Document::parse(text) {
+        Ok(document) => document,
+        Err(_) => return vec![],
+    };
+    document.descendants().filter_map(parse_animation).collect()
+}
+
+fn parse_animation(element: XmlNode) -> Option<Animation> {
+    let attribute_name = element.attribute("attributeName")?;
+    let attribute = match element.tag_name().name() {
+        "animate" | "animateColor" => AnimatedAttribute::from_name(attribute_name)?,
+        "animateTransform" if attribute_name == "transform" => {
+            let transform_type = element.attribute("type").unwrap_or("translate");
+            AnimatedAttribute::Transform(TransformType::from_name(transform_type)?)
+        }
+        _ => return None,
+    };
+
+    let target_id = match element.attribute((XLINK_NAMESPACE, "href"))
+                                 .or_else(|| element.attribute("href")) {
+        Some(href) if href.starts_with('#') => href[1..].to_owned(),
+        Some(_) => return None,
+        None => element.parent_element()?.attribute("id")?.to_owned(),
+    };
+
+    let simple_duration = parse_clock_value(element.attribute("dur")?)?;
+    if simple_duration <= 0.0 {
+        return None;
+    }
+
+    // Animations that only begin in response to events never start.
+    let begin = match element.attribute("begin") {
+        None => 0.0,
+        Some(begin) => begin.split(';').filter_map(parse_clock_value).next()?,
+    };
+
+    let repeat_count = match element.attribute("repeatCount") {
+        Some("indefinite") => f32::INFINITY,
+        Some(repeat_count) => repeat_count.trim().parse::<f32>().ok().filter(|&count| count > 0.0)?,
+        None => 1.0,
+    };
+
+    let mut additive = element.attribute("additive") == Some("sum");
+    let mut from_base_value = false;
+    let values = match element.attribute("values") {
+        Some(values) => {
+            values.split(';')
+                  .map(str::trim)
+                  .filter(|value| !value.is_empty())
+                  .map(|value| attribute.parse_value(value))
+                  .collect::<Option<Vec<_>>>()?
+        }
+        None => {
+            let from = match element.attribute("from") {
+                Some(from) => Some(attribute.parse_value(from)?),
+                None => None,
+            };
+            match (from, element.attribute("to"), element.attribute("by")) {
+                (Some(from), Some(to), _) => vec![from, attribute.parse_value(to)?],
+                (Some(from), None, Some(by)) => {
+                    let by = attribute.parse_value(by)?;
+                    let to = from.iter().zip(by.iter()).map(|(from, by)| from + by).collect();
+                    vec![from, to]
+                }
+                (None, Some(to), _) => {
+                    from_base_value = true;
+                    vec![attribute.parse_value(to)?]
+                }
+                (None, None, Some(by)) => {
+                    additive = true;
+                    let by = attribute.parse_value(by)?;
+                    vec![vec![0.0; by.len()], by]
+                }
+                (_, None, None) => return None,
+            }
+        }
+    };
+    if values.is_empty() {
+        return None;
+    }
+
+    let discrete = element.attribute("calcMode") == Some("discrete");
+    let key_times = match element.attribute("keyTimes") {
+        None => None,
+        Some(key_times) => {
+            let key_times = key_times.split(';')
+                                     .map(|key_time| key_time.trim().parse().ok())
+                                     .collect::<Option<Vec<f32>>>()?;
+            if key_times.len() != values.len() + from_base_value as usize {
+                return None;
+            }
+            Some(key_times)
+        }
+    };
+
+    Some(Animation {
+        target_id,
+        attribute,
+        values,
+        from_base_value,
+        key_times,
+        discrete,
+        begin,
+        simple_duration,
+        repeat_count,
+        freeze: element.attribute("fill") == Some("freeze"),
+        additive,
+    })
+}
+
+// Parses a SMIL clock value (e.g. `2s`, `150ms`, or `00:01:30`) into seconds.
+fn parse_clock_value(string: &str) -> Option<f32> {
+    let string = string.trim();
+    if string.contains(':') {
+        let mut seconds = 0.0;
+        for component in string.split(':') {
+            seconds = seconds * 60.0 + component.parse::<f32>().ok()?;
+        }
+        return Some(seconds);
+    }
+
+    let (number, scale) = if string.ends_with("ms") {
+        (&string[..string.len() - 2], 0.001)
+    } else if string.ends_with("min") {
+        (&string[..string.len() - 3], 60.0)
+    } else if string.ends_with('h') {
+        (&string[..string.len() - 1], 3600.0)
+    } else if string.ends_with('s') {
+        (&string[..string.len() - 1], 1.0)
+    } else {
+        (string, 1.0)
+    };
+    number.parse::<f32>().ok().map(|number| number * scale)
+}
+
+fn color_to_value(color: SvgColor) -> Vec<f32> {
+    vec![color.red as f32, color.green as f32, color.blue as f32]
+}
+
+fn value_to_color(value: &[f32]) -> SvgColor {
+    let component = |index: usize| {
+        value.get(index).cloned().unwrap_or(0.0).round().max(0.0).min(255.0) as u8
+    };
+    SvgColor { red: component(0), green: component(1), blue: component(2) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AnimatedAttribute, AnimatedSVGScene, Animation, TransformType};
+    use super::{parse_animations, parse_clock_value};
+    use usvg::{NodeKind, Options, Transform as UsvgTransform, Tree};
+
+    fn assert_approx_eq(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.001, "{} != {}", actual, expected);
+    }
+
+    fn assert_values_approx_eq(actual: Option<Vec<f32>>, expected: &[f32]) {
+        let actual = actual.expect("expected a value");
+        assert_eq!(actual.len(), expected.len(), "{:?} != {:?}", actual, expected);
+        for (&actual, &expected) in actual.iter().zip(expected.iter()) {
+            assert_approx_eq(actual, expected);
+        }
+    }
+
+    // Applies `transform` to a point.
+    fn apply(transform: &UsvgTransform, x: f64, y: f64) -> (f32, f32) {
+        ((transform.a * x + transform.c * y + transform.e) as f32,
+         (transform.b * x + transform.d * y + transform.f) as f32)
+    }
+
+    // A two-second opacity animation between `values`, starting immediately and playing once.
+    fn opacity_animation(values: &[f32]) -> Animation {
+        Animation {
+            target_id: "r".to_owned(),
+            attribute: AnimatedAttribute::Opacity,
+            values: values.iter().map(|&value| vec![value]).collect(),
+            from_base_value: false,
+            key_times: None,
+            discrete: false,
+            begin: 0.0,
+            simple_duration: 2.0,
+            repeat_count: 1.0,
+            freeze: false,
+            additive: false,
+        }
+    }
+
+    fn parse_one(element: &str) -> Option<Animation> {
+        let svg = format!(r##"<svg xmlns="http://www.w3.org/2000/svg"
+                                   xmlns:xlink="http://www.w3.org/1999/xlink">
+                                  <rect id="r" width="10" height="10">{}</rect>
+                              </svg>"##,
+                          element);
+        let mut animations = parse_animations(&svg);
+        assert!(animations.len() <= 1);
+        animations.pop()
+    }
+
+    fn animated_scene(svg: &str) -> AnimatedSVGScene {
+        AnimatedSVGScene::from_data(svg.as_bytes(), &Options::default()).unwrap()
+    }
+
+    fn path_transform(tree: &Tree, id: &str) -> UsvgTransform {
+        match *tree.node_by_id(id).expect("no such node").borrow() {
+            NodeKind::Path(ref path) => path.transform,
+            _ => panic!("`{}` isn't a path", id),
+        }
+    }
+
+    fn fill_opacity(tree: &Tree, id: &str) -> f32 {
+        match *tree.node_by_id(id).expect("no such node").borrow() {
+            NodeKind::Path(ref path) => path.fill.as_ref().unwrap().opacity.value() as f32,
+            _ => panic!("`{}` isn't a path", id),
+        }
+    }
+
+    #[test]
+    fn test_clock_values() {
+        assert_approx_eq(parse_clock_value("2s").unwrap(), 2.0);
+        assert_approx_eq(parse_clock_value("150ms").unwrap(), 0.15);
+        assert_approx_eq(parse_clock_value("1.5min").unwrap(), 90.0);
+        assert_approx_eq(parse_clock_value("2h").unwrap(), 7200.0);
+        assert_approx_eq(parse_clock_value(" 3 ").unwrap(), 3.0);
+        assert_approx_eq(parse_clock_value("01:30").unwrap(), 90.0);
+        assert_approx_eq(parse_clock_value("01:00:02.5").unwrap(), 3602.5);
+        assert!(parse_clock_value("").is_none());
+        assert!(parse_clock_value("indefinite").is_none());
+        assert!(parse_clock_value("click").is_none());
+        assert!(parse_clock_value("1:xx").is_none());
+    }
+
+    #[test]
+    fn test_sampling_follows_begin_and_duration() {
+        let mut animation = opacity_animation(&[0.0, 1.0]);
+        animation.begin = 1.0;
+
+        assert!(animation.sample(0.5).is_none());
+        assert_values_approx_eq(animation.sample(1.0), &[0.0]);
+        assert_values_approx_eq(animation.sample(2.0), &[0.5]);
+        // Without `fill="freeze"`, the animation stops applying once it ends.
+        assert!(animation.sample(3.0).is_none());
+
+        animation.freeze = true;
+        assert_values_approx_eq(animation.sample(3.0), &[1.0]);
+        assert_values_approx_eq(animation.sample(100.0), &[1.0]);
+    }
+
+    #[test]
+    fn test_sampling_repeats() {
+        let mut animation = opacity_animation(&[0.0, 1.0]);
+        animation.repeat_count = 2.5;
+        animation.freeze = true;
+
+        assert_approx_eq(animation.active_duration(), 5.0);
+        assert_values_approx_eq(animation.sample(0.5), &[0.25]);
+        assert_values_approx_eq(animation.sample(2.5), &[0.25]);
+        assert_values_approx_eq(animation.sample(4.0), &[0.0]);
+        // A fractional repeat count freezes partway through the last iteration.
+        assert_values_approx_eq(animation.sample(6.0), &[0.5]);
+
+        animation.repeat_count = f32::INFINITY;
+        assert!(animation.active_duration().is_infinite());
+        assert_values_approx_eq(animation.sample(1001.0), &[0.5]);
+    }
+
+    #[test]
+    fn test_values_are_interpolated_between_key_times() {
+        let mut animation = opacity_animation(&[0.0, 10.0, 20.0]);
+        assert_values_approx_eq(Some(animation.value_at(0.0)), &[0.0]);
+        assert_values_approx_eq(Some(animation.value_at(0.25)), &[5.0]);
+        assert_values_approx_eq(Some(animation.value_at(0.75)), &[15.0]);
+        assert_values_approx_eq(Some(animation.value_at(1.0)), &[20.0]);
+
+        animation.key_times = Some(vec![0.0, 0.8, 1.0]);
+        assert_values_approx_eq(Some(animation.value_at(0.4)), &[5.0]);
+        assert_values_approx_eq(Some(animation.value_at(0.9)), &[15.0]);
+
+        // Discrete animations give each value an equal share of the duration.
+        animation.key_times = None;
+        animation.discrete = true;
+        assert_values_approx_eq(Some(animation.value_at(0.0)), &[0.0]);
+        assert_values_approx_eq(Some(animation.value_at(0.5)), &[10.0]);
+        assert_values_approx_eq(Some(animation.value_at(0.9)), &[20.0]);
+
+        assert_values_approx_eq(Some(opacity_animation(&[7.0]).value_at(0.5)), &[7.0]);
+    }
+
+    #[test]
+    fn test_values_of_different_lengths_switch_halfway() {
+        let mut animation = opacity_animation(&[]);
+        animation.values = vec![vec![1.0], vec![2.0, 3.0]];
+        assert_values_approx_eq(Some(animation.value_at(0.25)), &[1.0]);
+        assert_values_approx_eq(Some(animation.value_at(0.75)), &[2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_attribute_values_are_parsed() {
+        let translate = AnimatedAttribute::Transform(TransformType::Translate);
+        assert_eq!(AnimatedAttribute::Opacity.parse_value("0.5"), Some(vec![0.5]));
+        assert_eq!(translate.parse_value("10, 20"), Some(vec![10.0, 20.0]));
+        assert_eq!(translate.parse_value(" 10 20 "), Some(vec![10.0, 20.0]));
+        assert_eq!(AnimatedAttribute::Fill.parse_value("red"), Some(vec![255.0, 0.0, 0.0]));
+        assert_eq!(AnimatedAttribute::Stroke.parse_value("#00ff80"),
+                   Some(vec![0.0, 255.0, 128.0]));
+        assert!(AnimatedAttribute::Opacity.parse_value("").is_none());
+        assert!(AnimatedAttribute::Opacity.parse_value("half").is_none());
+        assert!(AnimatedAttribute::Fill.parse_value("not-a-color").is_none());
+    }
+
+    #[test]
+    fn test_transform_types() {
+        let translate = TransformType::Translate.to_transform(&[5.0]);
+        assert_eq!(apply(&translate, 1.0, 1.0), (6.0, 1.0));
+
+        let uniform_scale = TransformType::Scale.to_transform(&[2.0]);
+        assert_eq!(apply(&uniform_scale, 1.0, 1.0), (2.0, 2.0));
+        let scale = TransformType::Scale.to_transform(&[2.0, 3.0]);
+        assert_eq!(apply(&scale, 1.0, 1.0), (2.0, 3.0));
+
+        // Rotation is about the optional center point.
+        let rotate = TransformType::Rotate.to_transform(&[90.0, 10.0, 0.0]);
+        let (x, y) = apply(&rotate, 20.0, 0.0);
+        assert_approx_eq(x, 10.0);
+        assert_approx_eq(y, 10.0);
+
+        let skew_x = TransformType::SkewX.to_transform(&[45.0]);
+        let (x, y) = apply(&skew_x, 0.0, 1.0);
+        assert_approx_eq(x, 1.0);
+        assert_approx_eq(y, 1.0);
+        let skew_y = TransformType::SkewY.to_transform(&[45.0]);
+        let (x, y) = apply(&skew_y, 1.0, 0.0);
+        assert_approx_eq(x, 1.0);
+        assert_approx_eq(y, 1.0);
+    }
+
+    #[test]
+    fn test_setting_attributes() {
+        let tree = Tree::from_str(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+                <rect id="r" width="10" height="10" fill="blue" fill-opacity="0.5"
+                      stroke="black" transform="translate(10 0)"/>
+            </svg>
+        "#, &Options::default()).unwrap();
+        let node = tree.node_by_id("r").unwrap();
+        let translate = AnimatedAttribute::Transform(TransformType::Translate);
+        let scale = AnimatedAttribute::Transform(TransformType::Scale);
+
+        AnimatedAttribute::Fill.set(&mut *node.borrow_mut(), &[255.0, 128.4, -3.0], false);
+        assert_eq!(AnimatedAttribute::Fill.get(&*node.borrow()), Some(vec![255.0, 128.0, 0.0]));
+
+        // Element opacity scales the fill opacity.
+        AnimatedAttribute::Opacity.set(&mut *node.borrow_mut(), &[0.5], false);
+        assert_approx_eq(fill_opacity(&tree, "r"), 0.25);
+
+        AnimatedAttribute::StrokeWidth.set(&mut *node.borrow_mut(), &[0.0], false);
+        assert!(AnimatedAttribute::StrokeWidth.get(&*node.borrow()).is_none());
+
+        // Additive transforms apply after the element's own transform.
+        scale.set(&mut *node.borrow_mut(), &[2.0], true);
+        assert_eq!(apply(&path_transform(&tree, "r"), 1.0, 1.0), (12.0, 2.0));
+        translate.set(&mut *node.borrow_mut(), &[0.0, 5.0], false);
+        assert_eq!(apply(&path_transform(&tree, "r"), 1.0, 1.0), (1.0, 6.0));
+    }
+
+    #[test]
+    fn test_timing_attributes_are_parsed() {
+        let animation = parse_one(r#"<animate attributeName="opacity" from="0" to="1"
+                                              begin="click; 1500ms" dur="00:02"
+                                              repeatCount="2.5" fill="freeze"/>"#).unwrap();
+        assert_eq!(animation.target_id, "r");
+        assert_eq!(animation.attribute, AnimatedAttribute::Opacity);
+        assert_eq!(animation.values, vec![vec![0.0], vec![1.0]]);
+        assert_approx_eq(animation.begin, 1.5);
+        assert_approx_eq(animation.simple_duration, 2.0);
+        assert_approx_eq(animation.repeat_count, 2.5);
+        assert!(animation.freeze);
+
+        let indefinite = parse_one(r#"<animate attributeName="opacity" values="0;1;0" dur="1s"
+                                               repeatCount="indefinite"/>"#).unwrap();
+        assert!(indefinite.repeat_count.is_infinite());
+        assert_eq!(indefinite.values.len(), 3);
+
+        // Animations that can never run are dropped.
+        assert!(parse_one(r#"<animate attributeName="opacity" to="1" begin="click"
+                                      dur="1s"/>"#).is_none());
+        assert!(parse_one(r#"<animate attributeName="opacity" to="1"/>"#).is_none());
+        assert!(parse_one(r#"<animate attributeName="opacity" to="1" dur="0s"/>"#).is_none());
+        assert!(parse_one(r#"<animate attributeName="opacity" to="1" dur="indefinite"/>"#)
+            .is_none());
+        assert!(parse_one(r#"<animate attributeName="opacity" to="1" dur="1s"
+                                      repeatCount="0"/>"#).is_none());
+        assert!(parse_one(r#"<animate attributeName="opacity" values="0;1" keyTimes="0;0.5;1"
+                                      dur="1s"/>"#).is_none());
+        assert!(parse_one(r#"<animate attributeName="width" to="1" dur="1s"/>"#).is_none());
+    }
+
+    #[test]
+    fn test_from_to_and_by_values() {
+        let to = parse_one(r#"<animate attributeName="opacity" to="0.5" dur="1s"/>"#).unwrap();
+        assert!(to.from_base_value);
+        assert_eq!(to.values, vec![vec![0.5]]);
+
+        let from_by = parse_one(r#"<animate attributeName="opacity" from="0.25" by="0.5"
+                                            dur="1s"/>"#).unwrap();
+        assert_eq!(from_by.values, vec![vec![0.25], vec![0.75]]);
+        assert!(!from_by.additive);
+
+        // A lone `by` animates relative to the underlying value.
+        let by = parse_one(r#"<animateTransform attributeName="transform" type="translate"
+                                               by="10 20" dur="1s"/>"#).unwrap();
+        assert_eq!(by.attribute, AnimatedAttribute::Transform(TransformType::Translate));
+        assert_eq!(by.values, vec![vec![0.0, 0.0], vec![10.0, 20.0]]);
+        assert!(by.additive);
+
+        let sum = parse_one(r#"<animateTransform attributeName="transform" type="rotate"
+                                                from="0" to="90" additive="sum"
+                                                dur="1s"/>"#).unwrap();
+        assert!(sum.additive);
+    }
+
+    #[test]
+    fn test_animations_can_target_other_elements() {
+        let animations = parse_animations(r##"
+            <svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+                <rect id="a" width="10" height="10"/>
+                <rect id="b" width="10" height="10"/>
+                <animate xlink:href="#a" attributeName="opacity" to="0" dur="1s"/>
+                <animate href="#b" attributeName="opacity" to="0" dur="1s"/>
+                <animate href="other.svg#a" attributeName="opacity" to="0" dur="1s"/>
+            </svg>
+        "##);
+        let target_ids: Vec<_> = animations.iter().map(|animation| &*animation.target_id).collect();
+        assert_eq!(target_ids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_tree_at_samples_from_the_unanimated_tree() {
+        let mut scene = animated_scene(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <rect id="r" width="10" height="10" fill="blue" transform="translate(10 0)">
+                    <animate attributeName="fill-opacity" from="0" to="1" dur="2s"
+                             fill="freeze"/>
+                    <animateTransform attributeName="transform" type="scale" from="1" to="3"
+                                      dur="2s" additive="sum"/>
+                </rect>
+            </svg>
+        "#);
+        assert_eq!(scene.duration(), Some(2.0));
+
+        // Sampling the same time twice gives the same result, so the additive scale doesn't
+        // accumulate.
+        for _ in 0..2 {
+            let tree = scene.tree_at(1.0);
+            assert_approx_eq(fill_opacity(tree, "r"), 0.5);
+            assert_eq!(apply(&path_transform(tree, "r"), 1.0, 1.0), (12.0, 2.0));
+        }
+
+        // Sampling earlier works too, and after the end only the frozen animation applies.
+        assert_approx_eq(fill_opacity(scene.tree_at(0.5), "r"), 0.25);
+        let tree = scene.tree_at(5.0);
+        assert_approx_eq(fill_opacity(tree, "r"), 1.0);
+        assert_eq!(apply(&path_transform(tree, "r"), 1.0, 1.0), (11.0, 1.0));
+
+        assert_eq!(scene.scene_at(1.0).path_count(), 1);
+    }
+
+    #[test]
+    fn test_to_animations_start_from_the_base_value() {
+        let mut scene = animated_scene(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <rect id="r" width="10" height="10" fill="blue" fill-opacity="0.2">
+                    <animate attributeName="fill-opacity" to="1" dur="2s"
+                             repeatCount="indefinite"/>
+                </rect>
+            </svg>
+        "#);
+        assert_eq!(scene.duration(), None);
+        assert_approx_eq(fill_opacity(scene.tree_at(0.0), "r"), 0.2);
+        assert_approx_eq(fill_opacity(scene.tree_at(1.0), "r"), 0.6);
+        assert_approx_eq(fill_opacity(scene.tree_at(3.0), "r"), 0.6);
+    }
+}
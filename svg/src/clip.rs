@@ -0,0 +1,273 @@
+// pathfinder/svg/src/clip.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! SVG `<clipPath>` and `<mask>` elements.
+//!
+//! Clip paths and masks are defined once but are interpreted in the user space (and, for
+//! `objectBoundingBox` units, the bounding box) of each element that references them, so they are
+//! instantiated anew at every reference.
+//!
+//! Clip paths map directly onto the renderer's clip paths. Masks are rendered as layers: the
+//! masked content and the mask content are drawn to separate render targets, the mask content
+//! with its luminance converted to alpha, and the mask is then composited onto the content with
+//! destination-in.
+
+use crate::filter::ColorMatrix;
+use crate::{BuildResultFlags, SVGBuilder, PathDestination, State};
+use crate::usvg_transform_to_transform_2d;
+use pathfinder_content::effects::{CompositeOp, Effects, Filter};
+use pathfinder_content::outline::Outline;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
+use pathfinder_renderer::scene::{ClipPath, ClipPathId, RenderTarget};
+use usvg::{Node, NodeExt, NodeKind, Units};
+
+impl<'a> SVGBuilder<'a> {
+    /// Creates a clip path from the `<clipPath>` element `clip_path_node`, as seen from the
+    /// element `node`.
+    pub(crate) fn instantiate_clip_path(&mut self,
+                                        clip_path_node: &Node,
+                                        node: &Node,
+                                        state: &State)
+                                        -> Option<ClipPathId> {
+        let (units, clip_path_transform) = match *clip_path_node.borrow() {
+            NodeKind::ClipPath(ref clip_path) => {
+                // TODO(pcwalton): Support `clip-path` on `<clipPath>` elements themselves.
+                if clip_path.clip_path.is_some() {
+                    self.result_flags.insert(BuildResultFlags::UNSUPPORTED_CLIP_PATH_ATTR);
+                }
+                (clip_path.units, usvg_transform_to_transform_2d(&clip_path.transform))
+            }
+            _ => return None,
+        };
+
+        let mut clip_state = (*state).clone();
+        clip_state.path_destination = PathDestination::Clip;
+        clip_state.transform = state.transform * clip_path_transform;
+        if units == Units::ObjectBoundingBox {
+            clip_state.transform = clip_state.transform * bounding_box_transform(node)?;
+        }
+
+        let mut clip_outline = None;
+        for kid in clip_path_node.children() {
+            self.process_node(&kid, &clip_state, &mut clip_outline);
+        }
+
+        // An empty clip path clips everything away.
+        let clip_outline = clip_outline.unwrap_or_else(Outline::new);
+
+        // FIXME(pcwalton): Is the winding fill rule correct to use?
+        let mut clip_path = ClipPath::new(clip_outline);
+        clip_path.set_name(format!("ClipPath({})", clip_path_node.id()));
+        let clip_path_id = self.scene.push_clip_path(clip_path);
+        self.clip_paths.insert(clip_path_node.id().to_owned(), clip_path_id);
+        Some(clip_path_id)
+    }
+
+    /// Draws the contents of the group `node` masked by the `<mask>` element `mask_node`.
+    pub(crate) fn process_masked_group(&mut self,
+                                       node: &Node,
+                                       mask_node: &Node,
+                                       state: &State,
+                                       clip_outline: &mut Option<Outline>) {
+        let content_units = match *mask_node.borrow() {
+            NodeKind::Mask(ref mask) => mask.content_units,
+            _ => return,
+        };
+
+        let render_target_size = self.scene.view_box().size().ceil().to_i32();
+        let content_name = format!("MaskContent({})", node.id());
+        let content_render_target_id =
+            self.scene.push_render_target(RenderTarget::new(render_target_size, content_name));
+        self.process_group_contents(node, state, clip_outline);
+
+        let mut mask_state = (*state).clone();
+        let luminance_to_alpha = ColorMatrix::luminance_to_alpha();
+        mask_state.color_matrix = Some(match state.color_matrix {
+            None => luminance_to_alpha,
+            Some(ref color_matrix) => luminance_to_alpha.concat(color_matrix),
+        });
+        if content_units == Units::ObjectBoundingBox {
+            match bounding_box_transform(node) {
+                None => mask_state.path_destination = PathDestination::Defs,
+                Some(transform) => mask_state.transform = mask_state.transform * transform,
+            }
+        }
+
+        // TODO(pcwalton): Clip the mask to the mask region.
+        let mask_name = format!("Mask({})", mask_node.id());
+        let mask_render_target_id =
+            self.scene.push_render_target(RenderTarget::new(render_target_size, mask_name));
+        for kid in mask_node.children() {
+            self.process_node(&kid, &mask_state, clip_outline);
+        }
+
+        self.scene.pop_render_target();
+        self.scene.draw_render_target(mask_render_target_id,
+                                      Effects::new(Filter::Composite(CompositeOp::DestIn)));
+        self.scene.pop_render_target();
+        self.scene.draw_render_target(content_render_target_id,
+                                      Effects::new(Filter::Composite(CompositeOp::SrcOver)));
+    }
+}
+
+// Returns the transform that maps the unit square onto the bounding box of `node`.
+fn bounding_box_transform(node: &Node) -> Option<Transform2F> {
+    let bbox = node.calculate_bbox()?;
+    if bbox.width() <= 0.0 || bbox.height() <= 0.0 {
+        return None;
+    }
+    let origin = Vector2F::new(bbox.x() as f32, bbox.y() as f32);
+    let size = Vector2F::new(bbox.width() as f32, bbox.height() as f32);
+    Some(Transform2F::from_translation(origin) * Transform2F::from_scale(size))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BuildResultFlags, BuiltSVG};
+    use pathfinder_content::fill::FillRule;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::Vector2F;
+    use usvg::{Options, Tree};
+
+    fn build(svg: &str) -> BuiltSVG {
+        BuiltSVG::from_tree(&Tree::from_str(svg, &Options::default()).unwrap())
+    }
+
+    // Returns the bounds of the clip path applied to each path in the scene.
+    fn clip_bounds(built_svg: &BuiltSVG) -> Vec<Option<RectF>> {
+        let scene = &built_svg.scene;
+        (0..scene.path_count()).map(|path_index| {
+            scene.path(path_index).clip_path().map(|clip_path_id| {
+                scene.clip_path(clip_path_id).outline().bounds()
+            })
+        }).collect()
+    }
+
+    fn assert_bounds_approx_eq(bounds: Option<RectF>, origin: (f32, f32), size: (f32, f32)) {
+        let bounds = bounds.expect("the path isn't clipped");
+        let expected = RectF::new(Vector2F::new(origin.0, origin.1),
+                                  Vector2F::new(size.0, size.1));
+        assert!((bounds.origin() - expected.origin()).length() < 0.01 &&
+                (bounds.size() - expected.size()).length() < 0.01,
+                "{:?} != {:?}",
+                bounds,
+                expected);
+    }
+
+    #[test]
+    fn test_clip_path_in_user_space() {
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <clipPath id="c">
+                    <rect x="10" y="20" width="30" height="40"/>
+                </clipPath>
+                <rect width="100" height="100" fill="blue" clip-path="url(#c)"/>
+            </svg>
+        "#);
+
+        assert!(built_svg.result_flags.is_empty());
+        let bounds = clip_bounds(&built_svg);
+        assert_eq!(bounds.len(), 1);
+        assert_bounds_approx_eq(bounds[0], (10.0, 20.0), (30.0, 40.0));
+
+        let clip_path_id = built_svg.scene.path(0).clip_path().unwrap();
+        assert_eq!(built_svg.clip_paths["c"].0, clip_path_id.0);
+        assert_eq!(built_svg.scene.clip_path(clip_path_id).fill_rule(), FillRule::Winding);
+    }
+
+    #[test]
+    fn test_clip_path_children_are_united() {
+        // The clip region covers both rectangles, moved by the clip path's own transform.
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <clipPath id="c" transform="translate(5 0)">
+                    <rect x="0" y="0" width="40" height="40"/>
+                    <rect x="20" y="20" width="40" height="40"/>
+                </clipPath>
+                <rect width="100" height="100" fill="blue" clip-path="url(#c)"/>
+            </svg>
+        "#);
+
+        assert!(built_svg.result_flags.is_empty());
+        let clip_path_id = built_svg.scene.path(0).clip_path().unwrap();
+        assert_eq!(built_svg.scene.clip_path(clip_path_id).outline().contours().len(), 2);
+        assert_bounds_approx_eq(clip_bounds(&built_svg)[0], (5.0, 0.0), (60.0, 60.0));
+    }
+
+    #[test]
+    fn test_object_bounding_box_clip_paths_fit_each_element() {
+        // The same clip path, the left half of the bounding box, is instantiated once per
+        // reference.
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <clipPath id="c" clipPathUnits="objectBoundingBox">
+                    <rect width="0.5" height="1"/>
+                </clipPath>
+                <rect x="20" y="10" width="40" height="20" fill="blue" clip-path="url(#c)"/>
+                <rect x="60" y="50" width="20" height="40" fill="blue" clip-path="url(#c)"/>
+            </svg>
+        "#);
+
+        assert!(built_svg.result_flags.is_empty());
+        let bounds = clip_bounds(&built_svg);
+        assert_eq!(bounds.len(), 2);
+        assert_bounds_approx_eq(bounds[0], (20.0, 10.0), (20.0, 20.0));
+        assert_bounds_approx_eq(bounds[1], (60.0, 50.0), (10.0, 40.0));
+        let scene = &built_svg.scene;
+        assert_ne!(scene.path(0).clip_path().unwrap().0, scene.path(1).clip_path().unwrap().0);
+    }
+
+    #[test]
+    fn test_nested_clip_paths_are_reported() {
+        // Clip paths can't be intersected yet, so the inner group is clipped only by the inner
+        // clip path.
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <clipPath id="outer">
+                    <rect width="50" height="100"/>
+                </clipPath>
+                <clipPath id="inner">
+                    <rect width="100" height="50"/>
+                </clipPath>
+                <g clip-path="url(#outer)">
+                    <rect width="100" height="100" fill="red"/>
+                    <g clip-path="url(#inner)">
+                        <rect width="100" height="100" fill="blue"/>
+                    </g>
+                </g>
+            </svg>
+        "#);
+
+        assert_eq!(built_svg.result_flags, BuildResultFlags::UNSUPPORTED_CLIP_PATH_ATTR);
+        let bounds = clip_bounds(&built_svg);
+        assert_eq!(bounds.len(), 2);
+        assert_bounds_approx_eq(bounds[0], (0.0, 0.0), (50.0, 100.0));
+        assert_bounds_approx_eq(bounds[1], (0.0, 0.0), (100.0, 50.0));
+    }
+
+    #[test]
+    fn test_clip_paths_on_clip_paths_are_reported() {
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <clipPath id="a">
+                    <rect width="50" height="100"/>
+                </clipPath>
+                <clipPath id="b" clip-path="url(#a)">
+                    <rect width="100" height="50"/>
+                </clipPath>
+                <rect width="100" height="100" fill="blue" clip-path="url(#b)"/>
+            </svg>
+        "#);
+
+        assert_eq!(built_svg.result_flags, BuildResultFlags::UNSUPPORTED_CLIP_PATH_ATTR);
+        assert_bounds_approx_eq(clip_bounds(&built_svg)[0], (0.0, 0.0), (100.0, 50.0));
+    }
+}
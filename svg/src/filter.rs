@@ -0,0 +1,685 @@
+// pathfinder/svg/src/filter.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Lowers SVG `<filter>` chains onto Pathfinder layers and effects.
+//!
+//! Pathfinder has no general filter graph, so each primitive is mapped onto scene content
+//! instead: blurs and compositing operations become render targets drawn with effects, offsets
+//! and color matrices are folded into the transforms and paints of the source paths, and floods
+//! become rectangles covering the filter region. A result that is referenced more than once is
+//! rendered into a render target the first time and that render target is drawn at every
+//! reference, as long as the references see the same transform, clip, and color matrix; since
+//! offsets and color matrices are folded into the source, references that differ in those
+//! necessarily get their own copies.
+//!
+//! Primitive subregions are ignored; every primitive covers the whole filter region.
+
+use crate::{BuildResultFlags, SVGBuilder, ColorUExt, State};
+use pathfinder_color::ColorU;
+use pathfinder_content::effects::{BlurDirection, CompositeOp, Effects, Filter};
+use pathfinder_content::gradient::{ColorStop, Gradient};
+use pathfinder_content::outline::{Contour, Outline};
+use pathfinder_content::pattern::{Image, Pattern, PatternSource};
+use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{ClipPathId, DrawPath, RenderTarget};
+use std::f32::consts::PI;
+use usvg::{FeColorMatrixKind, FeCompositeOperator, Filter as UsvgFilter, FilterInput, FilterKind};
+use usvg::{Node, NodeExt, Units};
+
+/// A 4×5 matrix applied to unpremultiplied RGBA colors, as in `<feColorMatrix>`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct ColorMatrix(pub [f32; 20]);
+
+impl<'a> SVGBuilder<'a> {
+    pub(crate) fn process_filtered_group(&mut self,
+                                         node: &Node,
+                                         filter: &UsvgFilter,
+                                         state: &State,
+                                         clip_outline: &mut Option<Outline>) {
+        if filter.children.is_empty() {
+            // An empty filter chain produces a transparent result.
+            return;
+        }
+
+        let filter_region = self.filter_region(node, filter);
+        let mut filter_context = FilterContext {
+            node,
+            filter,
+            filter_region,
+            reference_counts: count_result_references(filter),
+            shared_results: vec![],
+        };
+        self.emit_filter_primitive(&mut filter_context,
+                                   filter.children.len() - 1,
+                                   state,
+                                   clip_outline);
+    }
+
+    fn filter_region(&self, node: &Node, filter: &UsvgFilter) -> RectF {
+        let rect = RectF::new(Vector2F::new(filter.rect.x() as f32, filter.rect.y() as f32),
+                              Vector2F::new(filter.rect.width() as f32,
+                                            filter.rect.height() as f32));
+        match filter.units {
+            Units::UserSpaceOnUse => rect,
+            Units::ObjectBoundingBox => {
+                let bbox = match node.calculate_bbox() {
+                    None => return RectF::default(),
+                    Some(bbox) => bbox,
+                };
+                let bbox_origin = Vector2F::new(bbox.x() as f32, bbox.y() as f32);
+                let bbox_size = Vector2F::new(bbox.width() as f32, bbox.height() as f32);
+                RectF::new(bbox_origin + rect.origin().scale_xy(bbox_size),
+                           rect.size().scale_xy(bbox_size))
+            }
+        }
+    }
+
+    /// Filters the colors of `paint` by the color matrix in effect, if any.
+    pub(crate) fn apply_color_matrix(&mut self, paint: &mut Paint, state: &State) {
+        if let Some(ref color_matrix) = state.color_matrix {
+            if !color_matrix.apply_to_paint(paint) {
+                self.result_flags.insert(BuildResultFlags::UNSUPPORTED_FILTER_ATTR);
+            }
+        }
+    }
+
+    fn emit_filter_primitive(&mut self,
+                             context: &mut FilterContext,
+                             primitive_index: usize,
+                             state: &State,
+                             clip_outline: &mut Option<Outline>) {
+        let filter = context.filter;
+        let primitive = &filter.children[primitive_index];
+        match primitive.kind {
+            FilterKind::FeGaussianBlur(ref blur) => {
+                // Blur radii are given in user space, but the blur is performed in device space.
+                let scale = state.transform.scale_factor();
+                let sigma_x = blur.std_dev_x.value() as f32 * scale;
+                let sigma_y = blur.std_dev_y.value() as f32 * scale;
+
+                let render_target_size = self.scene.view_box().size().ceil().to_i32();
+                let mut render_targets = vec![];
+                for &(direction, sigma) in &[(BlurDirection::Y, sigma_y),
+                                             (BlurDirection::X, sigma_x)] {
+                    if sigma > 0.0 {
+                        let name = format!("FilterBlur{:?}({})", direction, context.node.id());
+                        let render_target = RenderTarget::new(render_target_size, name);
+                        let render_target_id = self.scene.push_render_target(render_target);
+                        render_targets.push((render_target_id, direction, sigma));
+                    }
+                }
+
+                self.emit_filter_input(context,
+                                       primitive_index,
+                                       &blur.input,
+                                       state,
+                                       clip_outline);
+
+                for (render_target_id, direction, sigma) in render_targets.into_iter().rev() {
+                    self.scene.pop_render_target();
+                    self.scene.draw_render_target(render_target_id,
+                                                  Effects::new(Filter::Blur { direction, sigma }));
+                }
+            }
+            FilterKind::FeOffset(ref offset) => {
+                let mut state = (*state).clone();
+                let translation = Vector2F::new(offset.dx as f32, offset.dy as f32);
+                state.transform = state.transform * Transform2F::from_translation(translation);
+                self.emit_filter_input(context,
+                                       primitive_index,
+                                       &offset.input,
+                                       &state,
+                                       clip_outline);
+            }
+            FilterKind::FeFlood(ref flood) => {
+                let mut color = ColorU::from_svg_color(flood.color);
+                color.a = (flood.opacity.value() * 255.0).round() as u8;
+                let mut paint = Paint::Color(color);
+                self.apply_color_matrix(&mut paint, state);
+
+                let mut outline = Outline::new();
+                outline.push_contour(rect_contour(context.filter_region));
+                outline.transform(&state.transform);
+
+                let paint_id = self.scene.push_paint(&paint);
+                let mut path = DrawPath::new(outline, paint_id);
+                path.set_clip_path(state.clip_path);
+                path.set_name(format!("FilterFlood({})", context.node.id()));
+                self.scene.push_path(path);
+            }
+            FilterKind::FeMerge(ref merge) => {
+                for input in &merge.inputs {
+                    self.emit_filter_input(context, primitive_index, input, state, clip_outline);
+                }
+            }
+            FilterKind::FeColorMatrix(ref color_matrix) => {
+                let mut state = (*state).clone();
+                let new_color_matrix = ColorMatrix::from_usvg_color_matrix(&color_matrix.kind);
+                state.color_matrix = Some(match state.color_matrix {
+                    None => new_color_matrix,
+                    Some(ref outer_color_matrix) => outer_color_matrix.concat(&new_color_matrix),
+                });
+                self.emit_filter_input(context,
+                                       primitive_index,
+                                       &color_matrix.input,
+                                       &state,
+                                       clip_outline);
+            }
+            FilterKind::FeComposite(ref composite) => {
+                // `in` is the source and `in2` is the destination. Pathfinder lacks a source-atop
+                // operation, so we swap the inputs and use destination-atop instead.
+                let (source, destination, composite_op) = match composite.operator {
+                    FeCompositeOperator::Over => {
+                        (&composite.input1, &composite.input2, CompositeOp::SrcOver)
+                    }
+                    FeCompositeOperator::In => {
+                        (&composite.input1, &composite.input2, CompositeOp::SrcIn)
+                    }
+                    FeCompositeOperator::Out => {
+                        (&composite.input1, &composite.input2, CompositeOp::SrcOut)
+                    }
+                    FeCompositeOperator::Atop => {
+                        (&composite.input2, &composite.input1, CompositeOp::DestAtop)
+                    }
+                    FeCompositeOperator::Xor | FeCompositeOperator::Arithmetic { .. } => {
+                        // Render targets can't be drawn with these operators. Drawing the inputs
+                        // with some other operator would silently produce the wrong image, so
+                        // the result is left transparent instead.
+                        self.result_flags.insert(BuildResultFlags::UNSUPPORTED_FILTER_ATTR);
+                        return;
+                    }
+                };
+
+                let render_target_size = self.scene.view_box().size().ceil().to_i32();
+                let destination_name = format!("FilterCompositeDest({})", context.node.id());
+                let destination_render_target_id = self.scene.push_render_target(
+                    RenderTarget::new(render_target_size, destination_name));
+                self.emit_filter_input(context, primitive_index, destination, state, clip_outline);
+
+                let source_name = format!("FilterCompositeSrc({})", context.node.id());
+                let source_render_target_id = self.scene.push_render_target(
+                    RenderTarget::new(render_target_size, source_name));
+                self.emit_filter_input(context, primitive_index, source, state, clip_outline);
+
+                self.scene.pop_render_target();
+                self.scene.draw_render_target(source_render_target_id,
+                                              Effects::new(Filter::Composite(composite_op)));
+                self.scene.pop_render_target();
+                self.scene.draw_render_target(
+                    destination_render_target_id,
+                    Effects::new(Filter::Composite(CompositeOp::SrcOver)));
+            }
+            _ => {
+                // Fall back to drawing the input unfiltered rather than dropping it entirely.
+                self.result_flags.insert(BuildResultFlags::UNSUPPORTED_FILTER_ATTR);
+                self.emit_filter_input(context,
+                                       primitive_index,
+                                       &FilterInput::SourceGraphic,
+                                       state,
+                                       clip_outline);
+            }
+        }
+    }
+
+    fn emit_filter_input(&mut self,
+                         context: &mut FilterContext,
+                         primitive_index: usize,
+                         input: &FilterInput,
+                         state: &State,
+                         clip_outline: &mut Option<Outline>) {
+        match *input {
+            FilterInput::SourceGraphic => {
+                for kid in context.node.children() {
+                    self.process_node(&kid, state, clip_outline);
+                }
+            }
+            FilterInput::SourceAlpha => {
+                let mut state = (*state).clone();
+                state.color_matrix = Some(match state.color_matrix {
+                    None => ColorMatrix::source_alpha(),
+                    Some(ref color_matrix) => color_matrix.concat(&ColorMatrix::source_alpha()),
+                });
+                for kid in context.node.children() {
+                    self.process_node(&kid, &state, clip_outline);
+                }
+            }
+            FilterInput::Reference(ref name) => {
+                // Results can only refer to primitives earlier in the chain, which guarantees
+                // termination.
+                match referenced_primitive(context.filter, primitive_index, name) {
+                    Some(referenced_index) => {
+                        self.emit_filter_result(context, referenced_index, state, clip_outline)
+                    }
+                    None => {
+                        self.emit_filter_input(context,
+                                               primitive_index,
+                                               &FilterInput::SourceGraphic,
+                                               state,
+                                               clip_outline)
+                    }
+                }
+            }
+            _ => {
+                // TODO(pcwalton): Support `BackgroundImage`, `FillPaint`, etc.
+                self.result_flags.insert(BuildResultFlags::UNSUPPORTED_FILTER_ATTR);
+                self.emit_filter_input(context,
+                                       primitive_index,
+                                       &FilterInput::SourceGraphic,
+                                       state,
+                                       clip_outline);
+            }
+        }
+    }
+
+    // Emits the result of the primitive at `primitive_index` on behalf of a reference to it.
+    fn emit_filter_result(&mut self,
+                          context: &mut FilterContext,
+                          primitive_index: usize,
+                          state: &State,
+                          clip_outline: &mut Option<Outline>) {
+        if context.reference_counts[primitive_index] < 2 {
+            self.emit_filter_primitive(context, primitive_index, state, clip_outline);
+            return;
+        }
+
+        let cached_render_target_id = context.shared_results.iter().find(|shared_result| {
+            shared_result.primitive_index == primitive_index && shared_result.matches(state)
+        }).map(|shared_result| shared_result.render_target_id);
+
+        let render_target_id = match cached_render_target_id {
+            Some(render_target_id) => render_target_id,
+            None => {
+                let render_target_size = self.scene.view_box().size().ceil().to_i32();
+                let name = format!("FilterResult{}({})", primitive_index, context.node.id());
+                let render_target_id =
+                    self.scene.push_render_target(RenderTarget::new(render_target_size, name));
+                self.emit_filter_primitive(context, primitive_index, state, clip_outline);
+                self.scene.pop_render_target();
+
+                context.shared_results.push(SharedResult {
+                    primitive_index,
+                    transform: state.transform,
+                    clip_path: state.clip_path,
+                    color_matrix: state.color_matrix,
+                    render_target_id,
+                });
+                render_target_id
+            }
+        };
+
+        self.scene.draw_render_target(render_target_id,
+                                      Effects::new(Filter::Composite(CompositeOp::SrcOver)));
+    }
+}
+
+struct FilterContext<'a> {
+    node: &'a Node,
+    filter: &'a UsvgFilter,
+    filter_region: RectF,
+    // How many times each primitive's result is referenced by later primitives.
+    reference_counts: Vec<u32>,
+    // Results referenced more than once, already rendered into render targets.
+    shared_results: Vec<SharedResult>,
+}
+
+struct SharedResult {
+    primitive_index: usize,
+    transform: Transform2F,
+    clip_path: Option<ClipPathId>,
+    color_matrix: Option<ColorMatrix>,
+    render_target_id: RenderTargetId,
+}
+
+impl SharedResult {
+    fn matches(&self, state: &State) -> bool {
+        self.transform == state.transform &&
+            self.clip_path.map(|clip_path| clip_path.0) ==
+                state.clip_path.map(|clip_path| clip_path.0) &&
+            self.color_matrix == state.color_matrix
+    }
+}
+
+// Returns the index of the primitive before `primitive_index` whose result is named `name`.
+fn referenced_primitive(filter: &UsvgFilter, primitive_index: usize, name: &str)
+                        -> Option<usize> {
+    filter.children[0..primitive_index].iter().rposition(|primitive| primitive.result == name)
+}
+
+fn count_result_references(filter: &UsvgFilter) -> Vec<u32> {
+    let mut reference_counts = vec![0; filter.children.len()];
+    for (primitive_index, primitive) in filter.children.iter().enumerate() {
+        let inputs: Vec<&FilterInput> = match primitive.kind {
+            FilterKind::FeGaussianBlur(ref blur) => vec![&blur.input],
+            FilterKind::FeOffset(ref offset) => vec![&offset.input],
+            FilterKind::FeMerge(ref merge) => merge.inputs.iter().collect(),
+            FilterKind::FeColorMatrix(ref color_matrix) => vec![&color_matrix.input],
+            FilterKind::FeComposite(ref composite) => {
+                match composite.operator {
+                    FeCompositeOperator::Xor | FeCompositeOperator::Arithmetic { .. } => vec![],
+                    _ => vec![&composite.input1, &composite.input2],
+                }
+            }
+            _ => vec![],
+        };
+        for input in inputs {
+            if let FilterInput::Reference(ref name) = *input {
+                if let Some(referenced_index) = referenced_primitive(filter,
+                                                                     primitive_index,
+                                                                     name) {
+                    reference_counts[referenced_index] += 1;
+                }
+            }
+        }
+    }
+    reference_counts
+}
+
+impl ColorMatrix {
+    #[inline]
+    fn identity() -> ColorMatrix {
+        ColorMatrix([
+            1.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0, 0.0,
+        ])
+    }
+
+    #[inline]
+    fn source_alpha() -> ColorMatrix {
+        ColorMatrix([
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0, 0.0,
+        ])
+    }
+
+    // See the "Filter Effects Module Level 1" specification for the formulas.
+    fn from_usvg_color_matrix(kind: &FeColorMatrixKind) -> ColorMatrix {
+        match *kind {
+            FeColorMatrixKind::Matrix(ref values) if values.len() == 20 => {
+                let mut matrix = [0.0; 20];
+                for (dest, src) in matrix.iter_mut().zip(values.iter()) {
+                    *dest = *src as f32;
+                }
+                ColorMatrix(matrix)
+            }
+            FeColorMatrixKind::Matrix(_) => ColorMatrix::identity(),
+            FeColorMatrixKind::Saturate(amount) => {
+                let s = amount.value() as f32;
+                ColorMatrix([
+                    0.213 + 0.787 * s, 0.715 - 0.715 * s, 0.072 - 0.072 * s, 0.0, 0.0,
+                    0.213 - 0.213 * s, 0.715 + 0.285 * s, 0.072 - 0.072 * s, 0.0, 0.0,
+                    0.213 - 0.213 * s, 0.715 - 0.715 * s, 0.072 + 0.928 * s, 0.0, 0.0,
+                    0.0,               0.0,               0.0,               1.0, 0.0,
+                ])
+            }
+            FeColorMatrixKind::HueRotate(degrees) => {
+                let theta = degrees as f32 * PI / 180.0;
+                let (sin, cos) = (theta.sin(), theta.cos());
+                ColorMatrix([
+                    0.213 + cos * 0.787 - sin * 0.213,
+                    0.715 - cos * 0.715 - sin * 0.715,
+                    0.072 - cos * 0.072 + sin * 0.928,
+                    0.0, 0.0,
+                    0.213 - cos * 0.213 + sin * 0.143,
+                    0.715 + cos * 0.285 + sin * 0.140,
+                    0.072 - cos * 0.072 - sin * 0.283,
+                    0.0, 0.0,
+                    0.213 - cos * 0.213 - sin * 0.787,
+                    0.715 - cos * 0.715 + sin * 0.715,
+                    0.072 + cos * 0.928 + sin * 0.072,
+                    0.0, 0.0,
+                    0.0, 0.0, 0.0, 1.0, 0.0,
+                ])
+            }
+            FeColorMatrixKind::LuminanceToAlpha => ColorMatrix::luminance_to_alpha(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn luminance_to_alpha() -> ColorMatrix {
+        ColorMatrix([
+            0.0,    0.0,    0.0,    0.0, 0.0,
+            0.0,    0.0,    0.0,    0.0, 0.0,
+            0.0,    0.0,    0.0,    0.0, 0.0,
+            0.2125, 0.7154, 0.0721, 0.0, 0.0,
+        ])
+    }
+
+    /// Returns a matrix that applies `other` first and then `self`.
+    pub(crate) fn concat(&self, other: &ColorMatrix) -> ColorMatrix {
+        let (a, b) = (&self.0, &other.0);
+        let mut result = [0.0; 20];
+        for row in 0..4 {
+            for column in 0..5 {
+                let mut value = if column == 4 { a[row * 5 + 4] } else { 0.0 };
+                for k in 0..4 {
+                    value += a[row * 5 + k] * b[k * 5 + column];
+                }
+                result[row * 5 + column] = value;
+            }
+        }
+        ColorMatrix(result)
+    }
+
+    pub(crate) fn apply(&self, color: ColorU) -> ColorU {
+        let color = color.to_f32();
+        let input = [color.r(), color.g(), color.b(), color.a()];
+        let mut output = [0; 4];
+        for (output, coefficients) in output.iter_mut().zip(self.0.chunks(5)) {
+            let mut value = coefficients[4];
+            for (coefficient, input) in coefficients.iter().zip(input.iter()) {
+                value += coefficient * input;
+            }
+            *output = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        ColorU::new(output[0], output[1], output[2], output[3])
+    }
+
+    /// Applies this matrix to every color in `paint`: a solid color, each gradient stop, or each
+    /// pixel of an image pattern.
+    ///
+    /// Returns false, leaving the paint untouched, if its colors aren't available on the CPU, as
+    /// with render targets, compressed images, and custom paints.
+    pub(crate) fn apply_to_paint(&self, paint: &mut Paint) -> bool {
+        match *paint {
+            Paint::Color(ref mut color) => *color = self.apply(*color),
+            Paint::Gradient(ref mut gradient) => {
+                let mut filtered_gradient = Gradient::new(gradient.geometry);
+                for stop in gradient.stops() {
+                    filtered_gradient.add_color_stop(ColorStop::new(self.apply(stop.color),
+                                                                    stop.offset));
+                }
+                *gradient = filtered_gradient;
+            }
+            Paint::Pattern(Pattern { source: PatternSource::Image(ref mut image), .. }) => {
+                let pixels = image.pixels().iter().map(|&pixel| self.apply(pixel)).collect();
+                *image = Image::new(image.size(), pixels);
+            }
+            Paint::Pattern(_) | Paint::Custom(_) => return false,
+        }
+        true
+    }
+}
+
+fn rect_contour(rect: RectF) -> Contour {
+    let mut contour = Contour::new();
+    contour.push_endpoint(rect.origin());
+    contour.push_endpoint(rect.upper_right());
+    contour.push_endpoint(rect.lower_right());
+    contour.push_endpoint(rect.lower_left());
+    contour.close();
+    contour
+}
+
+#[cfg(test)]
+mod test {
+    use super::ColorMatrix;
+    use crate::{BuildResultFlags, BuiltSVG};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::gradient::{ColorStop, Gradient};
+    use pathfinder_content::pattern::{Image, Pattern, PatternFlags, PatternSource};
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+    use pathfinder_renderer::paint::Paint;
+    use pathfinder_renderer::scene::DisplayItem;
+    use usvg::{Options, Tree};
+
+    // Swaps the red and blue channels.
+    fn swap_red_blue() -> ColorMatrix {
+        ColorMatrix([
+            0.0, 0.0, 1.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0, 0.0,
+            1.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0, 0.0,
+        ])
+    }
+
+    fn build(svg: &str) -> BuiltSVG {
+        BuiltSVG::from_tree(&Tree::from_str(svg, &Options::default()).unwrap())
+    }
+
+    #[test]
+    fn test_color_matrix_filters_gradient_stops() {
+        let mut gradient = Gradient::linear(LineSegment2F::new(Vector2F::default(),
+                                                               Vector2F::new(10.0, 0.0)));
+        gradient.add_color_stop(ColorStop::new(ColorU::new(255, 0, 0, 255), 0.0));
+        gradient.add_color_stop(ColorStop::new(ColorU::new(0, 128, 0, 64), 1.0));
+        let mut paint = Paint::Gradient(gradient.clone());
+
+        assert!(swap_red_blue().apply_to_paint(&mut paint));
+        match paint {
+            Paint::Gradient(ref filtered_gradient) => {
+                assert_eq!(filtered_gradient.geometry, gradient.geometry);
+                assert_eq!(filtered_gradient.stops(), &[
+                    ColorStop::new(ColorU::new(0, 0, 255, 255), 0.0),
+                    ColorStop::new(ColorU::new(0, 128, 0, 64), 1.0),
+                ]);
+            }
+            _ => panic!("Expected a gradient paint"),
+        }
+    }
+
+    #[test]
+    fn test_color_matrix_filters_image_pixels() {
+        let pixels = vec![ColorU::new(255, 0, 0, 255), ColorU::new(10, 20, 30, 40)];
+        let image = Image::new(Vector2I::new(2, 1), pixels);
+        let pattern = Pattern::new(PatternSource::Image(image),
+                                   Transform2F::default(),
+                                   PatternFlags::empty());
+        let mut paint = Paint::Pattern(pattern);
+
+        assert!(swap_red_blue().apply_to_paint(&mut paint));
+        match paint {
+            Paint::Pattern(Pattern { source: PatternSource::Image(ref image), .. }) => {
+                assert_eq!(image.pixels(),
+                           &[ColorU::new(0, 0, 255, 255), ColorU::new(30, 20, 10, 40)]);
+            }
+            _ => panic!("Expected an image pattern"),
+        }
+    }
+
+    #[test]
+    fn test_shared_result_is_built_once() {
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <filter id="f" x="0" y="0" width="100" height="100"
+                        filterUnits="userSpaceOnUse">
+                    <feGaussianBlur in="SourceGraphic" stdDeviation="2" result="blur"/>
+                    <feMerge>
+                        <feMergeNode in="blur"/>
+                        <feMergeNode in="blur"/>
+                    </feMerge>
+                </filter>
+                <g filter="url(#f)">
+                    <rect x="10" y="10" width="50" height="50" fill="red"/>
+                </g>
+            </svg>
+        "#);
+        let scene = &built_svg.scene;
+
+        // The blurred rectangle is drawn once...
+        assert_eq!(scene.path_count(), 1);
+
+        // ...into a render target that both merge nodes then draw.
+        let mut render_target_draws = vec![];
+        for display_item in scene.display_list() {
+            if let DisplayItem::DrawRenderTarget { render_target, .. } = *display_item {
+                render_target_draws.push(render_target);
+            }
+        }
+        let shared_render_target = *render_target_draws.last().unwrap();
+        assert_eq!(render_target_draws.iter()
+                                      .filter(|&&render_target| {
+                                          render_target == shared_render_target
+                                      })
+                                      .count(),
+                   2);
+    }
+
+    #[test]
+    fn test_unsupported_composite_operators_are_not_drawn() {
+        for operator in &[r#"operator="xor""#,
+                          r#"operator="arithmetic" k1="0" k2="1" k3="1" k4="0""#] {
+            let built_svg = build(&format!(r#"
+                <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                    <filter id="f">
+                        <feFlood flood-color="blue" result="flood"/>
+                        <feComposite in="SourceGraphic" in2="flood" {}/>
+                    </filter>
+                    <g filter="url(#f)">
+                        <rect x="10" y="10" width="50" height="50" fill="red"/>
+                    </g>
+                </svg>
+            "#, operator));
+
+            assert!(built_svg.result_flags.contains(BuildResultFlags::UNSUPPORTED_FILTER_ATTR));
+            assert_eq!(built_svg.scene.path_count(), 0);
+            assert!(built_svg.scene.display_list().iter().all(|display_item| {
+                match *display_item {
+                    DisplayItem::DrawRenderTarget { .. } => false,
+                    _ => true,
+                }
+            }));
+        }
+    }
+
+    #[test]
+    fn test_color_matrix_filters_flood() {
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <filter id="f">
+                    <feFlood flood-color="red" result="flood"/>
+                    <feColorMatrix in="flood" values="0 0 1 0 0
+                                                      0 1 0 0 0
+                                                      1 0 0 0 0
+                                                      0 0 0 1 0"/>
+                </filter>
+                <g filter="url(#f)">
+                    <rect x="10" y="10" width="50" height="50" fill="red"/>
+                </g>
+            </svg>
+        "#);
+        let scene = &built_svg.scene;
+
+        assert_eq!(scene.path_count(), 1);
+        match *scene.paint(scene.path(0).paint()) {
+            Paint::Color(color) => assert_eq!(color, ColorU::new(0, 0, 255, 255)),
+            _ => panic!("Expected a color paint"),
+        }
+    }
+}
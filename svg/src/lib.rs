@@ -9,12 +9,19 @@
 // except according to those terms.
 
 //! Converts a subset of SVG to a Pathfinder scene.
+//!
+//! Parsing and styling are handled by usvg, so by the time a tree reaches this crate, CSS
+//! `<style>` blocks (type, class, ID, and attribute selectors), `style` attributes, and
+//! inheritance have all been resolved into plain presentation attributes. Styling problems in
+//! imported documents are therefore usvg limitations rather than something to fix here.
 
 #[macro_use]
 extern crate bitflags;
 
+use crate::filter::ColorMatrix;
 use hashbrown::HashMap;
 use pathfinder_color::ColorU;
+use pathfinder_content::dash::OutlineDash;
 use pathfinder_content::fill::FillRule;
 use pathfinder_content::outline::Outline;
 use pathfinder_content::segment::{Segment, SegmentFlags};
@@ -25,21 +32,59 @@ use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::Vector2F;
 use pathfinder_renderer::paint::Paint;
-use pathfinder_renderer::scene::{ClipPath, ClipPathId, DrawPath, Scene};
+use pathfinder_renderer::scene::{ClipPathId, DrawPath, Scene};
 use std::fmt::{Display, Formatter, Result as FormatResult};
 use usvg::{Color as SvgColor, FillRule as UsvgFillRule, LineCap as UsvgLineCap};
 use usvg::{LineJoin as UsvgLineJoin, Node, NodeExt, NodeKind, Opacity, Paint as UsvgPaint};
 use usvg::{PathSegment as UsvgPathSegment, Rect as UsvgRect, Transform as UsvgTransform};
-use usvg::{Tree, Visibility};
+use usvg::{Filter as UsvgFilter, Tree, Visibility};
+
+pub mod animation;
+
+#[cfg(feature = "pf-text")]
+pub mod text;
+
+mod clip;
+mod filter;
+mod raster;
+mod view_box;
 
 const HAIRLINE_STROKE_WIDTH: f32 = 0.0333;
 
+// How many nodes to process between progress reports.
+const PROGRESS_REPORT_INTERVAL: usize = 1024;
+
 pub struct BuiltSVG {
     pub scene: Scene,
     pub result_flags: BuildResultFlags,
     pub clip_paths: HashMap<String, ClipPathId>,
 }
 
+/// How far along the conversion of an SVG tree to a scene is.
+#[derive(Clone, Copy, Debug)]
+pub struct BuildProgress {
+    pub processed_node_count: usize,
+    pub total_node_count: usize,
+}
+
+// Holds the transient state needed while converting a tree.
+struct SVGBuilder<'a> {
+    scene: Scene,
+    result_flags: BuildResultFlags,
+    clip_paths: HashMap<String, ClipPathId>,
+    clip_path_nodes: HashMap<String, Node>,
+    mask_nodes: HashMap<String, Node>,
+    filters: HashMap<String, UsvgFilter>,
+    progress: Option<ProgressTracker<'a>>,
+}
+
+struct ProgressTracker<'a> {
+    callback: &'a mut dyn FnMut(BuildProgress) -> bool,
+    processed_node_count: usize,
+    total_node_count: usize,
+    cancelled: bool,
+}
+
 bitflags! {
     // NB: If you change this, make sure to update the `Display`
     // implementation as well.
@@ -63,6 +108,12 @@ bitflags! {
 }
 
 impl BuiltSVG {
+    /// Builds a scene from a usvg tree.
+    ///
+    /// The tree can come from usvg's own parser or from anything else that produces one, such as
+    /// resvg's frontend. Since usvg has already resolved styles, flattened `<use>` elements, and
+    /// converted text to paths, this handles everything in such a tree that Pathfinder can draw;
+    /// what it skips is reported in `result_flags`.
     // TODO(pcwalton): Allow a global transform to be set.
     #[inline]
     pub fn from_tree(tree: &Tree) -> BuiltSVG {
@@ -71,63 +122,152 @@ impl BuiltSVG {
 
     // TODO(pcwalton): Allow a global transform to be set.
     pub fn from_tree_and_scene(tree: &Tree, scene: Scene) -> BuiltSVG {
-        // TODO(pcwalton): Maybe have a `SVGBuilder` type to hold the clip path IDs and other
-        // transient data separate from `BuiltSVG`?
-        let mut built_svg = BuiltSVG {
+        let mut builder = SVGBuilder::new(scene, None);
+        builder.build(tree);
+        builder.into_built_svg()
+    }
+
+    /// Like `from_tree_and_scene()`, but periodically reports progress to `callback`.
+    ///
+    /// Progress is counted in tree nodes. It is reported every thousand or so nodes, and once more
+    /// with the final count on completion. If the callback returns false, the build stops as soon
+    /// as possible and `None` is returned. This is useful for very large documents, such as map
+    /// exports, where building the scene can take a while.
+    ///
+    /// This is not a streaming importer. usvg parses the entire document into a `Tree` before
+    /// this is called, so peak memory usage is the same as for `from_tree_and_scene()`, and
+    /// tiling can't start until the whole scene has been built.
+    pub fn from_tree_and_scene_with_progress<F>(tree: &Tree, scene: Scene, mut callback: F)
+                                                -> Option<BuiltSVG>
+                                                where F: FnMut(BuildProgress) -> bool {
+        let total_node_count = tree.root().descendants().count();
+        let progress = ProgressTracker {
+            callback: &mut callback,
+            processed_node_count: 0,
+            total_node_count,
+            cancelled: false,
+        };
+
+        let mut builder = SVGBuilder::new(scene, Some(progress));
+        builder.build(tree);
+        let finished = builder.progress.as_mut().map_or(true, |progress| progress.finish());
+        if finished {
+            Some(builder.into_built_svg())
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> SVGBuilder<'a> {
+    fn new(scene: Scene, progress: Option<ProgressTracker<'a>>) -> SVGBuilder<'a> {
+        SVGBuilder {
             scene,
             result_flags: BuildResultFlags::empty(),
             clip_paths: HashMap::new(),
-        };
+            clip_path_nodes: HashMap::new(),
+            mask_nodes: HashMap::new(),
+            filters: HashMap::new(),
+            progress,
+        }
+    }
 
+    fn into_built_svg(self) -> BuiltSVG {
+        BuiltSVG {
+            scene: self.scene,
+            result_flags: self.result_flags,
+            clip_paths: self.clip_paths,
+        }
+    }
+
+    fn build(&mut self, tree: &Tree) {
         let root = &tree.root();
         match *root.borrow() {
             NodeKind::Svg(ref svg) => {
-                built_svg.scene.set_view_box(usvg_rect_to_euclid_rect(&svg.view_box.rect));
+                // The scene covers the SVG viewport, and the `viewBox` is fitted into it according
+                // to `preserveAspectRatio`. (usvg has already turned nested `<svg>` elements into
+                // groups with the appropriate transforms and clips.)
+                let viewport = RectF::new(Vector2F::default(),
+                                          Vector2F::new(svg.size.width() as f32,
+                                                        svg.size.height() as f32));
+                let mut state = State::new();
+                state.transform =
+                    view_box::aspect_ratio_transform(&svg.view_box.aspect,
+                                                     usvg_rect_to_euclid_rect(&svg.view_box.rect),
+                                                     viewport);
+                self.scene.set_view_box(viewport);
                 for kid in root.children() {
-                    built_svg.process_node(&kid, &State::new(), &mut None);
+                    self.process_node(&kid, &state, &mut None);
                 }
             }
             _ => unreachable!(),
         }
-
-        built_svg
     }
 
     fn process_node(&mut self,
                     node: &Node,
                     state: &State,
                     clip_outline: &mut Option<Outline>) {
+        if let Some(ref mut progress) = self.progress {
+            if !progress.advance() {
+                return;
+            }
+        }
+
         let mut state = (*state).clone();
+        // The node transform maps into the parent's user space, so it must be applied first.
+        // Getting this backwards is mostly invisible for simple documents, but it scatters the
+        // marker instances (arrowheads, etc.) that usvg resolves into translated, rotated, and
+        // scaled groups nested inside transformed parents.
         let node_transform = usvg_transform_to_transform_2d(&node.transform());
-        state.transform = node_transform * state.transform;
+        state.transform = state.transform * node_transform;
 
         match *node.borrow() {
             NodeKind::Group(ref group) => {
-                if group.filter.is_some() {
-                    self.result_flags
-                        .insert(BuildResultFlags::UNSUPPORTED_FILTER_ATTR);
-                }
-                if group.mask.is_some() {
-                    self.result_flags
-                        .insert(BuildResultFlags::UNSUPPORTED_MASK_ATTR);
-                }
-
                 if let Some(ref clip_path_name) = group.clip_path {
-                    if let Some(clip_path_id) = self.clip_paths.get(clip_path_name) {
+                    let clip_path_node = self.clip_path_nodes.get(clip_path_name).cloned();
+                    if let Some(clip_path_node) = clip_path_node {
+                        // The renderer can't intersect clip paths, so the innermost one wins.
                         // TODO(pcwalton): Combine multiple clip paths if there's already one.
-                        state.clip_path = Some(*clip_path_id);
+                        if state.clip_path.is_some() {
+                            self.result_flags
+                                .insert(BuildResultFlags::UNSUPPORTED_CLIP_PATH_ATTR);
+                        }
+                        if let Some(clip_path_id) =
+                                self.instantiate_clip_path(&clip_path_node, node, &state) {
+                            state.clip_path = Some(clip_path_id);
+                        }
+                    } else {
+                        self.result_flags.insert(BuildResultFlags::UNSUPPORTED_CLIP_PATH_ATTR);
                     }
                 }
 
-                for kid in node.children() {
-                    self.process_node(&kid, &state, clip_outline)
+                if let Some(ref mask_name) = group.mask {
+                    if let Some(mask_node) = self.mask_nodes.get(mask_name).cloned() {
+                        if state.path_destination == PathDestination::Draw {
+                            self.process_masked_group(node, &mask_node, &state, clip_outline);
+                            return;
+                        }
+                    } else {
+                        self.result_flags.insert(BuildResultFlags::UNSUPPORTED_MASK_ATTR);
+                    }
                 }
+
+                self.process_group_contents(node, &state, clip_outline);
             }
             NodeKind::Path(ref path) if state.path_destination == PathDestination::Clip => {
-                // TODO(pcwalton): Multiple clip paths.
+                // The clip region is the union of all the paths in the clip path.
                 let path = UsvgPathToSegments::new(path.data.iter().cloned());
                 let path = Transform2FPathIter::new(path, &state.transform);
-                *clip_outline = Some(Outline::from_segments(path));
+                let outline = Outline::from_segments(path);
+                match *clip_outline {
+                    None => *clip_outline = Some(outline),
+                    Some(ref mut clip_outline) => {
+                        for contour in outline.contours() {
+                            clip_outline.push_contour((*contour).clone());
+                        }
+                    }
+                }
             }
             NodeKind::Path(ref path) if state.path_destination == PathDestination::Draw &&
                     path.visibility == Visibility::Visible => {
@@ -153,7 +293,14 @@ impl BuiltSVG {
                     };
 
                     let path = UsvgPathToSegments::new(path.data.iter().cloned());
-                    let outline = Outline::from_segments(path);
+                    let mut outline = Outline::from_segments(path);
+
+                    if let Some(ref dash_array) = stroke.dasharray {
+                        let dashes: Vec<f32> = dash_array.iter().map(|dash| *dash as f32).collect();
+                        let mut dash = OutlineDash::new(&outline, &dashes, stroke.dashoffset);
+                        dash.dash();
+                        outline = dash.into_outline();
+                    }
 
                     let mut stroke_to_fill = OutlineStrokeToFill::new(&outline, stroke_style);
                     stroke_to_fill.offset();
@@ -170,19 +317,8 @@ impl BuiltSVG {
             }
             NodeKind::Path(..) => {}
             NodeKind::ClipPath(_) => {
-                let mut clip_outline = None;
-                state.path_destination = PathDestination::Clip;
-                for kid in node.children() {
-                    self.process_node(&kid, &state, &mut clip_outline);
-                }
-
-                if let Some(clip_outline) = clip_outline {
-                    // FIXME(pcwalton): Is the winding fill rule correct to use?
-                    let mut clip_path = ClipPath::new(clip_outline);
-                    clip_path.set_name(format!("ClipPath({})", node.id()));
-                    let clip_path_id = self.scene.push_clip_path(clip_path);
-                    self.clip_paths.insert(node.id().to_owned(), clip_path_id);
-                }
+                // Clip paths are instantiated when referenced; see `instantiate_clip_path()`.
+                self.clip_path_nodes.insert(node.id().to_owned(), (*node).clone());
             }
             NodeKind::Defs => {
                 // FIXME(pcwalton): This is wrong.
@@ -191,21 +327,20 @@ impl BuiltSVG {
                     self.process_node(&kid, &state, clip_outline);
                 }
             }
-            NodeKind::Filter(..) => {
-                self.result_flags
-                    .insert(BuildResultFlags::UNSUPPORTED_FILTER_NODE);
+            NodeKind::Filter(ref filter) => {
+                self.filters.insert(node.id().to_owned(), (*filter).clone());
             }
-            NodeKind::Image(..) => {
-                self.result_flags
-                    .insert(BuildResultFlags::UNSUPPORTED_IMAGE_NODE);
+            NodeKind::Image(ref image) if state.path_destination == PathDestination::Draw &&
+                    image.visibility == Visibility::Visible => {
+                self.process_image(node, image, &state);
             }
+            NodeKind::Image(..) => {}
             NodeKind::LinearGradient(..) => {
                 self.result_flags
                     .insert(BuildResultFlags::UNSUPPORTED_LINEAR_GRADIENT_NODE);
             }
             NodeKind::Mask(..) => {
-                self.result_flags
-                    .insert(BuildResultFlags::UNSUPPORTED_MASK_NODE);
+                self.mask_nodes.insert(node.id().to_owned(), (*node).clone());
             }
             NodeKind::Pattern(..) => {
                 self.result_flags
@@ -222,6 +357,28 @@ impl BuiltSVG {
         }
     }
 
+    fn process_group_contents(&mut self,
+                              node: &Node,
+                              state: &State,
+                              clip_outline: &mut Option<Outline>) {
+        if let NodeKind::Group(ref group) = *node.borrow() {
+            if let Some(ref filter_name) = group.filter {
+                if let Some(filter) = self.filters.get(filter_name).cloned() {
+                    if state.path_destination == PathDestination::Draw {
+                        self.process_filtered_group(node, &filter, state, clip_outline);
+                        return;
+                    }
+                } else {
+                    self.result_flags.insert(BuildResultFlags::UNSUPPORTED_FILTER_ATTR);
+                }
+            }
+        }
+
+        for kid in node.children() {
+            self.process_node(&kid, state, clip_outline)
+        }
+    }
+
     fn push_draw_path(&mut self,
                       mut outline: Outline,
                       name: String,
@@ -230,7 +387,8 @@ impl BuiltSVG {
                       opacity: Opacity,
                       fill_rule: UsvgFillRule) {
         outline.transform(&state.transform);
-        let paint = Paint::from_svg_paint(paint, &state.transform, &mut self.result_flags);
+        let mut paint = Paint::from_svg_paint(paint, &state.transform, &mut self.result_flags);
+        self.apply_color_matrix(&mut paint, state);
         let style = self.scene.push_paint(&paint);
         let fill_rule = FillRule::from_usvg_fill_rule(fill_rule);
         let mut path = DrawPath::new(outline, style);
@@ -242,6 +400,40 @@ impl BuiltSVG {
     }
 }
 
+impl<'a> ProgressTracker<'a> {
+    // Returns false if the build has been cancelled.
+    fn advance(&mut self) -> bool {
+        if self.cancelled {
+            return false;
+        }
+        // Clip paths and masks are revisited at every reference, so don't overshoot the total.
+        self.processed_node_count = (self.processed_node_count + 1).min(self.total_node_count);
+        if self.processed_node_count % PROGRESS_REPORT_INTERVAL == 0 {
+            self.report();
+        }
+        !self.cancelled
+    }
+
+    // Reports completion. Returns false if the build was cancelled.
+    fn finish(&mut self) -> bool {
+        if !self.cancelled {
+            self.processed_node_count = self.total_node_count;
+            self.report();
+        }
+        !self.cancelled
+    }
+
+    fn report(&mut self) {
+        let progress = BuildProgress {
+            processed_node_count: self.processed_node_count,
+            total_node_count: self.total_node_count,
+        };
+        if !(self.callback)(progress) {
+            self.cancelled = true;
+        }
+    }
+}
+
 impl Display for BuildResultFlags {
     fn fmt(&self, formatter: &mut Formatter) -> FormatResult {
         if self.is_empty() {
@@ -477,6 +669,8 @@ struct State {
     transform: Transform2F,
     // The current clip path in effect.
     clip_path: Option<ClipPathId>,
+    // The color matrix that filters in effect apply to paints.
+    color_matrix: Option<ColorMatrix>,
 }
 
 impl State {
@@ -485,6 +679,7 @@ impl State {
             path_destination: PathDestination::Draw,
             transform: Transform2F::default(),
             clip_path: None,
+            color_matrix: None,
         }
     }
 }
@@ -495,3 +690,316 @@ enum PathDestination {
     Defs,
     Clip,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{BuildProgress, BuiltSVG, PROGRESS_REPORT_INTERVAL};
+    use pathfinder_color::ColorU;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::Vector2F;
+    use pathfinder_renderer::paint::Paint;
+    use pathfinder_renderer::scene::Scene;
+    use usvg::{Options, Tree};
+
+    fn build(svg: &str) -> BuiltSVG {
+        BuiltSVG::from_tree(&Tree::from_str(svg, &Options::default()).unwrap())
+    }
+
+    // Builds `svg`, recording every progress report. The callback returns `keep_going`.
+    fn build_with_progress(svg: &str, keep_going: bool)
+                           -> (Option<BuiltSVG>, Vec<BuildProgress>) {
+        let tree = Tree::from_str(svg, &Options::default()).unwrap();
+        let mut reports = vec![];
+        let callback = |progress: BuildProgress| {
+            reports.push(progress);
+            keep_going
+        };
+        let built_svg = BuiltSVG::from_tree_and_scene_with_progress(&tree, Scene::new(), callback);
+        (built_svg, reports)
+    }
+
+    // An SVG with enough nodes for several intermediate progress reports.
+    fn large_svg() -> String {
+        let mut svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">"#
+            .to_owned();
+        for index in 0..(PROGRESS_REPORT_INTERVAL * 3) {
+            svg.push_str(&format!(r#"<rect x="{}" width="1" height="1" fill="blue"/>"#,
+                                  index % 100));
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+
+    // Returns the bounds of every path filled with `color`.
+    fn bounds_of_paths_with_color(built_svg: &BuiltSVG, color: ColorU) -> Vec<RectF> {
+        let scene = &built_svg.scene;
+        (0..scene.path_count()).map(|path_index| scene.path(path_index)).filter(|path| {
+            match *scene.paint(path.paint()) {
+                Paint::Color(paint_color) => paint_color == color,
+                _ => false,
+            }
+        }).map(|path| path.outline().bounds()).collect()
+    }
+
+    fn path_colors(built_svg: &BuiltSVG) -> Vec<ColorU> {
+        let scene = &built_svg.scene;
+        (0..scene.path_count()).map(|path_index| {
+            match *scene.paint(scene.path(path_index).paint()) {
+                Paint::Color(color) => color,
+                ref paint => panic!("Expected a color paint, found {:?}", paint),
+            }
+        }).collect()
+    }
+
+    // Strokes a horizontal line 100 units long, returning the horizontal extent of each dash.
+    fn dashes(dash_array: &str, dash_offset: f32) -> Vec<(f32, f32)> {
+        let built_svg = build(&format!(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="20">
+                <path d="M 0 10 L 100 10" fill="none" stroke="black" stroke-width="2"
+                      stroke-dasharray="{}" stroke-dashoffset="{}"/>
+            </svg>
+        "#, dash_array, dash_offset));
+        assert_eq!(built_svg.scene.path_count(), 1);
+        built_svg.scene.path(0).outline().contours().iter().map(|contour| {
+            (contour.bounds().min_x().round(), contour.bounds().max_x().round())
+        }).collect()
+    }
+
+    // Builds a 200x100 document whose square view box is filled by a blue square, returning the
+    // scene's view box and where the square ends up.
+    fn fit_square(preserve_aspect_ratio: &str) -> (RectF, RectF) {
+        let built_svg = build(&format!(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="200" height="100"
+                 viewBox="25 25 50 50" preserveAspectRatio="{}">
+                <rect x="25" y="25" width="50" height="50" fill="blue"/>
+            </svg>
+        "#, preserve_aspect_ratio));
+        let square_bounds = bounds_of_paths_with_color(&built_svg, ColorU::new(0, 0, 255, 255));
+        assert_eq!(square_bounds.len(), 1);
+        (built_svg.scene.view_box(), square_bounds[0])
+    }
+
+    fn assert_rect_approx_eq(rect: RectF, origin: Vector2F, lower_right: Vector2F) {
+        let epsilon = 0.01;
+        assert!((rect.origin() - origin).length() < epsilon,
+                "{:?} != {:?}",
+                rect.origin(),
+                origin);
+        assert!((rect.lower_right() - lower_right).length() < epsilon,
+                "{:?} != {:?}",
+                rect.lower_right(),
+                lower_right);
+    }
+
+    #[test]
+    fn test_marker_in_nested_transformed_groups() {
+        // The marker's rectangle sits at the end of the line, (40, 10) in the line's own space.
+        // The inner scale applies before the outer translation, which puts it at (180, 20).
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+                <marker id="m" markerWidth="4" markerHeight="4" markerUnits="userSpaceOnUse"
+                        orient="0">
+                    <rect width="4" height="4" fill="red"/>
+                </marker>
+                <g transform="translate(100 0)">
+                    <g transform="scale(2)">
+                        <path d="M 10 10 L 40 10" stroke="black" marker-end="url(#m)"/>
+                    </g>
+                </g>
+            </svg>
+        "#);
+
+        let marker_bounds = bounds_of_paths_with_color(&built_svg, ColorU::new(255, 0, 0, 255));
+        assert_eq!(marker_bounds.len(), 1);
+        assert_rect_approx_eq(marker_bounds[0],
+                              Vector2F::new(180.0, 20.0),
+                              Vector2F::new(188.0, 28.0));
+    }
+
+    #[test]
+    fn test_css_is_resolved_before_import() {
+        // usvg applies `<style>` rules, `style` attributes, and inheritance, so the importer only
+        // ever sees plain fills.
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+                <style>
+                    rect { fill: red }
+                    .blue { fill: blue }
+                    #green { fill: lime }
+                    [data-kind="yellow"] { fill: yellow }
+                </style>
+                <rect x="0" width="10" height="10"/>
+                <rect class="blue" x="10" width="10" height="10"/>
+                <rect id="green" x="20" width="10" height="10"/>
+                <rect data-kind="yellow" x="30" width="10" height="10"/>
+                <g style="fill: #800080">
+                    <circle cx="50" cy="5" r="5"/>
+                </g>
+            </svg>
+        "#);
+
+        assert!(built_svg.result_flags.is_empty());
+        assert_eq!(path_colors(&built_svg), vec![
+            ColorU::new(255, 0, 0, 255),
+            ColorU::new(0, 0, 255, 255),
+            ColorU::new(0, 255, 0, 255),
+            ColorU::new(255, 255, 0, 255),
+            ColorU::new(128, 0, 128, 255),
+        ]);
+    }
+
+    #[test]
+    fn test_tree_import_flattens_use_elements() {
+        // usvg has already replaced each `<use>` with a transformed copy of what it references.
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"
+                 width="100" height="100">
+                <defs>
+                    <rect id="r" width="10" height="10" fill="blue"/>
+                </defs>
+                <use xlink:href="#r" x="50" y="20"/>
+                <use xlink:href="#r" x="70" y="40"/>
+            </svg>
+        "#);
+
+        assert!(built_svg.result_flags.is_empty());
+        let bounds = bounds_of_paths_with_color(&built_svg, ColorU::new(0, 0, 255, 255));
+        assert_eq!(bounds.len(), 2);
+        assert_rect_approx_eq(bounds[0], Vector2F::new(50.0, 20.0), Vector2F::new(60.0, 30.0));
+        assert_rect_approx_eq(bounds[1], Vector2F::new(70.0, 40.0), Vector2F::new(80.0, 50.0));
+    }
+
+    #[test]
+    fn test_progress_is_monotonic_and_reaches_the_total() {
+        let (built_svg, reports) = build_with_progress(&large_svg(), true);
+
+        let built_svg = built_svg.expect("the build was not cancelled");
+        assert_eq!(built_svg.scene.path_count(), PROGRESS_REPORT_INTERVAL * 3);
+        assert!(reports.len() > 1, "expected intermediate reports, got {:?}", reports);
+        let total_node_count = reports[0].total_node_count;
+        assert!(total_node_count > PROGRESS_REPORT_INTERVAL * 3);
+        for pair in reports.windows(2) {
+            assert!(pair[0].processed_node_count <= pair[1].processed_node_count,
+                    "progress went backwards: {:?}",
+                    pair);
+        }
+        for report in &reports {
+            assert_eq!(report.total_node_count, total_node_count);
+            assert!(report.processed_node_count <= total_node_count);
+        }
+        assert_eq!(reports.last().unwrap().processed_node_count, total_node_count);
+    }
+
+    #[test]
+    fn test_small_documents_report_completion() {
+        let (built_svg, reports) = build_with_progress(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+                <rect width="10" height="10" fill="blue"/>
+            </svg>
+        "#, true);
+
+        assert_eq!(built_svg.expect("the build was not cancelled").scene.path_count(), 1);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].processed_node_count, reports[0].total_node_count);
+    }
+
+    #[test]
+    fn test_cancelling_stops_the_build() {
+        let (built_svg, reports) = build_with_progress(&large_svg(), false);
+
+        assert!(built_svg.is_none());
+        // Nothing is reported after the callback asks to stop, not even completion.
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].processed_node_count, PROGRESS_REPORT_INTERVAL);
+        assert!(reports[0].processed_node_count < reports[0].total_node_count);
+    }
+
+    #[test]
+    fn test_stroke_dasharray() {
+        assert_eq!(dashes("10 10", 0.0),
+                   vec![(0.0, 10.0), (20.0, 30.0), (40.0, 50.0), (60.0, 70.0), (80.0, 90.0)]);
+        // An odd number of values is repeated to make the pattern `10 5 5 10 5 5`.
+        assert_eq!(dashes("10, 5, 5", 0.0), vec![
+            (0.0, 10.0), (15.0, 20.0), (30.0, 35.0),
+            (40.0, 50.0), (55.0, 60.0), (70.0, 75.0),
+            (80.0, 90.0), (95.0, 100.0),
+        ]);
+    }
+
+    #[test]
+    fn test_stroke_dashoffset() {
+        assert_eq!(dashes("10 10", 5.0), vec![
+            (0.0, 5.0), (15.0, 25.0), (35.0, 45.0), (55.0, 65.0), (75.0, 85.0), (95.0, 100.0),
+        ]);
+        // A negative offset moves the pattern along the path.
+        assert_eq!(dashes("10 10", -5.0),
+                   vec![(5.0, 15.0), (25.0, 35.0), (45.0, 55.0), (65.0, 75.0), (85.0, 95.0)]);
+    }
+
+    #[test]
+    fn test_degenerate_stroke_dasharrays_are_solid() {
+        assert_eq!(dashes("0 0", 0.0), vec![(0.0, 100.0)]);
+        assert_eq!(dashes("0", 3.0), vec![(0.0, 100.0)]);
+        assert_eq!(dashes("10 -5", 0.0), vec![(0.0, 100.0)]);
+    }
+
+    #[test]
+    fn test_view_box_meet() {
+        // The 50x50 view box is scaled by 2 to fit the 200x100 viewport's height.
+        let viewport = RectF::new(Vector2F::default(), Vector2F::new(200.0, 100.0));
+        let cases = [("xMinYMid meet", 0.0), ("xMidYMid meet", 50.0), ("xMaxYMid meet", 100.0)];
+        for &(preserve_aspect_ratio, x) in &cases {
+            let (view_box, square) = fit_square(preserve_aspect_ratio);
+            assert_eq!(view_box, viewport);
+            assert_rect_approx_eq(square, Vector2F::new(x, 0.0), Vector2F::new(x + 100.0, 100.0));
+        }
+        // `meet` is the default.
+        assert_eq!(fit_square("xMaxYMid").1, fit_square("xMaxYMid meet").1);
+    }
+
+    #[test]
+    fn test_view_box_slice() {
+        // The view box is scaled by 4 to cover the viewport's width, and overflows vertically.
+        let viewport = RectF::new(Vector2F::default(), Vector2F::new(200.0, 100.0));
+        let cases = [
+            ("xMidYMin slice", 0.0),
+            ("xMidYMid slice", -50.0),
+            ("xMidYMax slice", -100.0),
+        ];
+        for &(preserve_aspect_ratio, y) in &cases {
+            let (view_box, square) = fit_square(preserve_aspect_ratio);
+            assert_eq!(view_box, viewport);
+            assert_rect_approx_eq(square, Vector2F::new(0.0, y), Vector2F::new(200.0, y + 200.0));
+        }
+    }
+
+    #[test]
+    fn test_view_box_none_stretches() {
+        let (view_box, square) = fit_square("none");
+        assert_eq!(view_box, RectF::new(Vector2F::default(), Vector2F::new(200.0, 100.0)));
+        assert_rect_approx_eq(square, Vector2F::default(), Vector2F::new(200.0, 100.0));
+    }
+
+    #[test]
+    fn test_nested_svg_viewports() {
+        // The nested viewport maps its 10x10 view box onto the right half of the document, and
+        // clips to it.
+        let built_svg = build(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+                <svg x="100" y="0" width="100" height="100" viewBox="0 0 10 10">
+                    <rect x="5" y="5" width="10" height="10" fill="blue"/>
+                </svg>
+            </svg>
+        "#);
+
+        assert!(built_svg.result_flags.is_empty());
+        let bounds = bounds_of_paths_with_color(&built_svg, ColorU::new(0, 0, 255, 255));
+        assert_eq!(bounds.len(), 1);
+        assert_rect_approx_eq(bounds[0], Vector2F::new(150.0, 50.0), Vector2F::new(250.0, 150.0));
+        let scene = &built_svg.scene;
+        let clip_path_id = scene.path(0).clip_path().expect("the nested viewport doesn't clip");
+        assert_rect_approx_eq(scene.clip_path(clip_path_id).outline().bounds(),
+                              Vector2F::new(100.0, 0.0),
+                              Vector2F::new(200.0, 100.0));
+    }
+}
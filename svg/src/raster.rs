@@ -0,0 +1,269 @@
+// pathfinder/svg/src/raster.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Raster SVG `<image>` elements.
+//!
+//! Images are decoded up front and drawn as a rectangle filled with an image pattern, positioned
+//! according to the element's `preserveAspectRatio`.
+
+use crate::{BuildResultFlags, SVGBuilder, State, usvg_rect_to_euclid_rect};
+use crate::view_box;
+use pathfinder_content::outline::{Contour, Outline};
+use pathfinder_content::pattern::{Image, Pattern, PatternFlags, PatternSource};
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::vector::Vector2F;
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::DrawPath;
+use std::fs;
+use usvg::{Image as UsvgImage, ImageData, ImageFormat, ImageRendering, Node, NodeExt};
+
+impl<'a> SVGBuilder<'a> {
+    pub(crate) fn process_image(&mut self, node: &Node, usvg_image: &UsvgImage, state: &State) {
+        let image_data = match usvg_image.format {
+            ImageFormat::PNG | ImageFormat::JPEG => load_image_data(&usvg_image.data),
+            ImageFormat::SVG => {
+                // TODO(pcwalton): Import nested SVG images as scene content.
+                None
+            }
+        };
+        let decoded_image = image_data.and_then(|data| image::load_from_memory(&data).ok());
+        let decoded_image = match decoded_image {
+            Some(decoded_image) => decoded_image,
+            None => {
+                self.result_flags.insert(BuildResultFlags::UNSUPPORTED_IMAGE_NODE);
+                return;
+            }
+        };
+
+        let image = Image::from_image_buffer(decoded_image.to_rgba());
+        let mut pattern_flags = PatternFlags::empty();
+        if usvg_image.rendering_mode == ImageRendering::OptimizeSpeed {
+            pattern_flags.insert(PatternFlags::NO_SMOOTHING);
+        }
+
+        let image_size = image.size().to_f32();
+        if image_size.x() <= 0.0 || image_size.y() <= 0.0 {
+            return;
+        }
+
+        // Place the image in its viewport. With `slice`, the image overflows the viewport and is
+        // clipped to it; with `meet`, the image is letterboxed and only its own rect is drawn.
+        let viewport = usvg_rect_to_euclid_rect(&usvg_image.view_box.rect);
+        let image_transform = view_box::aspect_ratio_transform(
+            &usvg_image.view_box.aspect,
+            RectF::new(Vector2F::default(), image_size),
+            viewport);
+        let image_rect = RectF::from_points(image_transform * Vector2F::default(),
+                                            image_transform * image_size);
+        let draw_rect = match image_rect.intersection(viewport) {
+            None => return,
+            Some(draw_rect) => draw_rect,
+        };
+
+        let mut contour = Contour::new();
+        contour.push_endpoint(draw_rect.origin());
+        contour.push_endpoint(draw_rect.upper_right());
+        contour.push_endpoint(draw_rect.lower_right());
+        contour.push_endpoint(draw_rect.lower_left());
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        outline.transform(&state.transform);
+
+        let pattern = Pattern::new(PatternSource::Image(image), image_transform, pattern_flags);
+        let mut paint = Paint::Pattern(pattern);
+        paint.apply_transform(&state.transform);
+        self.apply_color_matrix(&mut paint, state);
+
+        let paint_id = self.scene.push_paint(&paint);
+        let mut path = DrawPath::new(outline, paint_id);
+        path.set_clip_path(state.clip_path);
+        path.set_name(format!("Image({})", node.id()));
+        self.scene.push_path(path);
+    }
+}
+
+fn load_image_data(image_data: &ImageData) -> Option<Vec<u8>> {
+    match *image_data {
+        ImageData::Raw(ref data) => Some((*data).clone()),
+        ImageData::Path(ref path) => fs::read(path).ok(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BuildResultFlags, BuiltSVG};
+    use image::png::PngEncoder;
+    use image::{ColorType, Rgba, RgbaImage};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::pattern::{Pattern, PatternFlags, PatternSource};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+    use pathfinder_renderer::paint::Paint;
+    use std::env;
+    use std::fs;
+    use usvg::{Options, Tree};
+
+    const IMAGE_WIDTH: u32 = 4;
+    const IMAGE_HEIGHT: u32 = 2;
+
+    // Encodes a red 4x2 PNG.
+    fn png() -> Vec<u8> {
+        let pixels = RgbaImage::from_pixel(IMAGE_WIDTH, IMAGE_HEIGHT, Rgba([255, 0, 0, 255]));
+        let mut data = vec![];
+        PngEncoder::new(&mut data).encode(&pixels, IMAGE_WIDTH, IMAGE_HEIGHT, ColorType::Rgba8)
+                                  .unwrap();
+        data
+    }
+
+    fn base64(data: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut string = String::new();
+        for chunk in data.chunks(3) {
+            let bits = chunk.iter().enumerate().fold(0, |bits, (index, &byte)| {
+                bits | (byte as u32) << (16 - index * 8)
+            });
+            for index in 0..4 {
+                string.push(if index <= chunk.len() {
+                    ALPHABET[(bits >> (18 - index * 6)) as usize & 63] as char
+                } else {
+                    '='
+                });
+            }
+        }
+        string
+    }
+
+    fn data_uri(mime_type: &str, data: &[u8]) -> String {
+        format!("data:{};base64,{}", mime_type, base64(data))
+    }
+
+    // Builds a 100x100 document containing an `<image>` with the given attributes.
+    fn build_image(href: &str, attributes: &str) -> BuiltSVG {
+        let svg = format!(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"
+                 width="100" height="100">
+                <image xlink:href="{}" {}/>
+            </svg>
+        "#, href, attributes);
+        BuiltSVG::from_tree(&Tree::from_str(&svg, &Options::default()).unwrap())
+    }
+
+    // Returns the bounds and the pattern of each path in the scene.
+    fn image_paths(built_svg: &BuiltSVG) -> Vec<(RectF, Pattern)> {
+        let scene = &built_svg.scene;
+        (0..scene.path_count()).map(|path_index| {
+            let path = scene.path(path_index);
+            match *scene.paint(path.paint()) {
+                Paint::Pattern(ref pattern) => (path.outline().bounds(), (*pattern).clone()),
+                ref paint => panic!("Expected a pattern paint, found {:?}", paint),
+            }
+        }).collect()
+    }
+
+    // Returns where the pattern puts the image's upper left and lower right corners.
+    fn image_corners(pattern: &Pattern) -> (Vector2F, Vector2F) {
+        let size = Vector2F::new(IMAGE_WIDTH as f32, IMAGE_HEIGHT as f32);
+        (pattern.transform * Vector2F::default(), pattern.transform * size)
+    }
+
+    fn rect(left: f32, top: f32, right: f32, bottom: f32) -> RectF {
+        RectF::from_points(Vector2F::new(left, top), Vector2F::new(right, bottom))
+    }
+
+    #[test]
+    fn test_data_uri_images_become_image_patterns() {
+        let built_svg = build_image(&data_uri("image/png", &png()),
+                                    r#"x="10" y="20" width="40" height="20""#);
+
+        assert!(built_svg.result_flags.is_empty());
+        let image_paths = image_paths(&built_svg);
+        assert_eq!(image_paths.len(), 1);
+        let (bounds, ref pattern) = image_paths[0];
+        assert_eq!(bounds, rect(10.0, 20.0, 50.0, 40.0));
+        assert_eq!(image_corners(pattern), (Vector2F::new(10.0, 20.0), Vector2F::new(50.0, 40.0)));
+        assert!(pattern.smoothing_enabled());
+        match pattern.source {
+            PatternSource::Image(ref image) => {
+                assert_eq!(image.size(), Vector2I::new(IMAGE_WIDTH as i32, IMAGE_HEIGHT as i32));
+                assert!(image.pixels().iter().all(|&pixel| pixel == ColorU::new(255, 0, 0, 255)));
+            }
+            ref source => panic!("Expected an image, found {:?}", source),
+        }
+    }
+
+    #[test]
+    fn test_meet_letterboxes_the_image() {
+        let href = data_uri("image/png", &png());
+        let centered = build_image(&href, r#"x="10" y="20" width="40" height="40""#);
+        let centered_paths = image_paths(&centered);
+        let (bounds, ref pattern) = centered_paths[0];
+        assert_eq!(bounds, rect(10.0, 30.0, 50.0, 50.0));
+        assert_eq!(image_corners(pattern), (Vector2F::new(10.0, 30.0), Vector2F::new(50.0, 50.0)));
+
+        let bottom = build_image(&href, r#"x="10" y="20" width="40" height="40"
+                                            preserveAspectRatio="xMinYMax meet""#);
+        assert_eq!(image_paths(&bottom)[0].0, rect(10.0, 40.0, 50.0, 60.0));
+    }
+
+    #[test]
+    fn test_slice_clips_the_image_to_its_viewport() {
+        let href = data_uri("image/png", &png());
+        let sliced = build_image(&href, r#"x="10" y="20" width="40" height="40"
+                                            preserveAspectRatio="xMidYMid slice""#);
+        let sliced_paths = image_paths(&sliced);
+        let (bounds, ref pattern) = sliced_paths[0];
+        assert_eq!(bounds, rect(10.0, 20.0, 50.0, 60.0));
+        assert_eq!(image_corners(pattern), (Vector2F::new(-10.0, 20.0), Vector2F::new(70.0, 60.0)));
+
+        let stretched = build_image(&href, r#"x="10" y="20" width="40" height="40"
+                                               preserveAspectRatio="none""#);
+        let stretched_paths = image_paths(&stretched);
+        let (bounds, ref pattern) = stretched_paths[0];
+        assert_eq!(bounds, rect(10.0, 20.0, 50.0, 60.0));
+        assert_eq!(image_corners(pattern), (Vector2F::new(10.0, 20.0), Vector2F::new(50.0, 60.0)));
+    }
+
+    #[test]
+    fn test_image_rendering_controls_smoothing() {
+        let built_svg = build_image(&data_uri("image/png", &png()),
+                                    r#"width="40" height="20" image-rendering="optimizeSpeed""#);
+        let image_paths = image_paths(&built_svg);
+        assert!(image_paths[0].1.flags.contains(PatternFlags::NO_SMOOTHING));
+    }
+
+    #[test]
+    fn test_external_images_are_loaded() {
+        let path = env::temp_dir().join("pathfinder_svg_test_external_image.png");
+        fs::write(&path, png()).unwrap();
+        let built_svg = build_image(&path.to_string_lossy(), r#"width="40" height="20""#);
+        fs::remove_file(&path).unwrap();
+
+        assert!(built_svg.result_flags.is_empty());
+        let image_paths = image_paths(&built_svg);
+        assert_eq!(image_paths.len(), 1);
+        assert_eq!(image_paths[0].0, rect(0.0, 0.0, 40.0, 20.0));
+    }
+
+    #[test]
+    fn test_undecodable_images_are_reported() {
+        // The PNG signature is intact, but the image data is cut off.
+        let truncated = build_image(&data_uri("image/png", &png()[..20]),
+                                    r#"width="40" height="20""#);
+        assert_eq!(truncated.result_flags, BuildResultFlags::UNSUPPORTED_IMAGE_NODE);
+        assert_eq!(truncated.scene.path_count(), 0);
+
+        let nested_svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2"/>"#;
+        let nested = build_image(&data_uri("image/svg+xml", nested_svg),
+                                 r#"width="40" height="20""#);
+        assert_eq!(nested.result_flags, BuildResultFlags::UNSUPPORTED_IMAGE_NODE);
+        assert_eq!(nested.scene.path_count(), 0);
+    }
+}
@@ -0,0 +1,96 @@
+// pathfinder/svg/src/text.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Font resolution for SVG `<text>` elements.
+//!
+//! usvg lays out text itself (including `x`/`y`/`dx`/`dy` positioning, `text-anchor`, and
+//! `<tspan>`s) and hands us the glyphs as ordinary paths. However, any text whose font family
+//! can't be found is silently dropped, and usvg's default family, Times New Roman, is missing on
+//! many systems. This module uses font-kit to pick fallback families that are actually installed.
+//!
+//! Text is not drawn with `pathfinder_text`. By the time a tree reaches this crate there are no
+//! text nodes left, so glyphs are rasterized like any other path, without `pathfinder_text`'s
+//! glyph quantization or shaping.
+
+use font_kit::family_name::FamilyName;
+use font_kit::properties::Properties;
+use font_kit::source::SystemSource;
+use usvg::Options as UsvgOptions;
+
+/// Returns usvg options whose default font family is installed on this system.
+///
+/// Pass these to `usvg::Tree::from_data()` or `usvg::Tree::from_file()` so that SVG text renders
+/// instead of coming out blank.
+pub fn usvg_options_with_system_fonts() -> UsvgOptions {
+    let mut options = UsvgOptions::default();
+    if let Some(family_name) = resolve_family_name(&[FamilyName::Serif, FamilyName::SansSerif]) {
+        options.font_family = family_name;
+    }
+    options
+}
+
+/// Returns the name of the first installed family that matches one of `family_names`.
+pub fn resolve_family_name(family_names: &[FamilyName]) -> Option<String> {
+    let handle = SystemSource::new().select_best_match(family_names, &Properties::new()).ok()?;
+    let font = handle.load().ok()?;
+    Some(font.family_name())
+}
+
+#[cfg(test)]
+mod test {
+    use super::usvg_options_with_system_fonts;
+    use crate::BuiltSVG;
+    use pathfinder_geometry::rect::RectF;
+    use usvg::Tree;
+
+    // Returns the union of the bounds of every path in the scene.
+    fn build_text(svg: &str) -> Option<RectF> {
+        let tree = Tree::from_str(svg, &usvg_options_with_system_fonts()).unwrap();
+        let built_svg = BuiltSVG::from_tree(&tree);
+        assert!(built_svg.result_flags.is_empty());
+        let scene = &built_svg.scene;
+        let mut bounds: Option<RectF> = None;
+        for path_index in 0..scene.path_count() {
+            let path_bounds = scene.path(path_index).outline().bounds();
+            bounds = Some(bounds.map_or(path_bounds, |bounds| bounds.union_rect(path_bounds)));
+        }
+        bounds
+    }
+
+    // These need at least one font installed.
+    #[test]
+    fn test_text_elements_produce_paths() {
+        let bounds = build_text(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+                <text x="10" y="50" font-size="20" fill="blue">Hello</text>
+            </svg>
+        "#).expect("the text produced no paths");
+
+        // The glyphs sit on the baseline, starting at the text position.
+        assert!(bounds.min_x() >= 9.0 && bounds.min_x() < 20.0, "{:?}", bounds);
+        assert!(bounds.min_y() >= 30.0 && bounds.max_y() <= 55.0, "{:?}", bounds);
+        assert!(bounds.width() > 20.0, "{:?}", bounds);
+    }
+
+    #[test]
+    fn test_text_anchor_and_tspans_are_laid_out() {
+        let bounds = build_text(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+                <text x="150" y="50" font-size="20" text-anchor="end">
+                    Hello <tspan dy="20">world</tspan>
+                </text>
+            </svg>
+        "#).expect("the text produced no paths");
+
+        // The text ends at its position, and the `<tspan>` drops below the baseline.
+        assert!(bounds.max_x() <= 151.0 && bounds.min_x() < 100.0, "{:?}", bounds);
+        assert!(bounds.max_y() > 55.0, "{:?}", bounds);
+    }
+}
@@ -0,0 +1,132 @@
+// pathfinder/svg/src/view_box.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `viewBox` and `preserveAspectRatio` handling.
+
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
+use usvg::{Align, AspectRatio};
+
+/// Returns the transform that fits `content` into `viewport` as `preserveAspectRatio` dictates.
+pub(crate) fn aspect_ratio_transform(aspect: &AspectRatio, content: RectF, viewport: RectF)
+                                     -> Transform2F {
+    if content.width() <= 0.0 || content.height() <= 0.0 {
+        return Transform2F::default();
+    }
+
+    let mut scale = viewport.size() / content.size();
+    if aspect.align != Align::None {
+        let uniform_scale = if aspect.slice {
+            f32::max(scale.x(), scale.y())
+        } else {
+            f32::min(scale.x(), scale.y())
+        };
+        scale = Vector2F::splat(uniform_scale);
+    }
+
+    // Distribute the leftover space according to the alignment.
+    let (align_x, align_y) = alignment_factors(aspect.align);
+    let leftover = viewport.size() - content.size().scale_xy(scale);
+    let origin = viewport.origin() + leftover.scale_xy(Vector2F::new(align_x, align_y));
+
+    Transform2F::from_translation(origin) *
+        Transform2F::from_scale(scale) *
+        Transform2F::from_translation(-content.origin())
+}
+
+fn alignment_factors(align: Align) -> (f32, f32) {
+    match align {
+        Align::None | Align::XMinYMin => (0.0, 0.0),
+        Align::XMidYMin => (0.5, 0.0),
+        Align::XMaxYMin => (1.0, 0.0),
+        Align::XMinYMid => (0.0, 0.5),
+        Align::XMidYMid => (0.5, 0.5),
+        Align::XMaxYMid => (1.0, 0.5),
+        Align::XMinYMax => (0.0, 1.0),
+        Align::XMidYMax => (0.5, 1.0),
+        Align::XMaxYMax => (1.0, 1.0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::aspect_ratio_transform;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::Vector2F;
+    use usvg::{Align, AspectRatio};
+
+    // Every alignment, with how far along each axis it puts the content.
+    const ALIGNMENTS: [(Align, f32, f32); 9] = [
+        (Align::XMinYMin, 0.0, 0.0),
+        (Align::XMidYMin, 0.5, 0.0),
+        (Align::XMaxYMin, 1.0, 0.0),
+        (Align::XMinYMid, 0.0, 0.5),
+        (Align::XMidYMid, 0.5, 0.5),
+        (Align::XMaxYMid, 1.0, 0.5),
+        (Align::XMinYMax, 0.0, 1.0),
+        (Align::XMidYMax, 0.5, 1.0),
+        (Align::XMaxYMax, 1.0, 1.0),
+    ];
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> RectF {
+        RectF::new(Vector2F::new(x, y), Vector2F::new(width, height))
+    }
+
+    // Returns where `content` ends up when fitted into a 100x100 viewport at (10, 10).
+    fn fit(align: Align, slice: bool, content: RectF) -> RectF {
+        let aspect = AspectRatio { defer: false, align, slice };
+        let transform = aspect_ratio_transform(&aspect, content, rect(10.0, 10.0, 100.0, 100.0));
+        RectF::from_points(transform * content.origin(), transform * content.lower_right())
+    }
+
+    #[test]
+    fn test_meet_fits_the_content_inside_the_viewport() {
+        let (tall, wide) = (rect(5.0, 5.0, 10.0, 20.0), rect(5.0, 5.0, 20.0, 10.0));
+        for &(align, align_x, align_y) in &ALIGNMENTS {
+            assert_eq!(fit(align, false, tall), rect(10.0 + 50.0 * align_x, 10.0, 50.0, 100.0),
+                       "{:?}",
+                       align);
+            assert_eq!(fit(align, false, wide), rect(10.0, 10.0 + 50.0 * align_y, 100.0, 50.0),
+                       "{:?}",
+                       align);
+        }
+    }
+
+    #[test]
+    fn test_slice_covers_the_viewport() {
+        let (tall, wide) = (rect(5.0, 5.0, 10.0, 20.0), rect(5.0, 5.0, 20.0, 10.0));
+        for &(align, align_x, align_y) in &ALIGNMENTS {
+            assert_eq!(fit(align, true, tall), rect(10.0, 10.0 - 100.0 * align_y, 100.0, 200.0),
+                       "{:?}",
+                       align);
+            assert_eq!(fit(align, true, wide), rect(10.0 - 100.0 * align_x, 10.0, 200.0, 100.0),
+                       "{:?}",
+                       align);
+        }
+    }
+
+    #[test]
+    fn test_none_stretches_the_content() {
+        let viewport = rect(10.0, 10.0, 100.0, 100.0);
+        assert_eq!(fit(Align::None, false, rect(5.0, 5.0, 10.0, 20.0)), viewport);
+        // `slice` has no effect without an alignment.
+        assert_eq!(fit(Align::None, true, rect(5.0, 5.0, 20.0, 10.0)), viewport);
+    }
+
+    #[test]
+    fn test_empty_content_is_left_alone() {
+        let aspect = AspectRatio { defer: false, align: Align::XMidYMid, slice: false };
+        let viewport = rect(10.0, 10.0, 100.0, 100.0);
+        assert_eq!(aspect_ratio_transform(&aspect, rect(0.0, 0.0, 0.0, 10.0), viewport),
+                   Transform2F::default());
+    }
+}
@@ -0,0 +1,217 @@
+// pathfinder/swf/src/bitmaps.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Decodes bitmap characters so that they can be used for bitmap fills.
+
+use pathfinder_color::ColorU;
+use pathfinder_content::pattern::Image;
+use pathfinder_geometry::vector::Vector2I;
+use swf_types::tags::DefineBitmap;
+use swf_types::ImageType;
+
+// Pixel formats of `DefineBitsLossless` tags.
+const LOSSLESS_FORMAT_COLOR_MAPPED: u8 = 3;
+const LOSSLESS_FORMAT_RGB15: u8 = 4;
+const LOSSLESS_FORMAT_RGB32: u8 = 5;
+
+/// Returns `None` if the bitmap is in a format we can't decode yet.
+pub(crate) fn decode_bitmap(bitmap: &DefineBitmap) -> Option<Image> {
+    match bitmap.media_type {
+        ImageType::Jpeg | ImageType::Png | ImageType::Gif => {
+            let image = image::load_from_memory(&bitmap.data).ok()?;
+            Some(Image::from_image_buffer(image.to_rgba()))
+        }
+        ImageType::SwfLossless1 => decode_lossless(&bitmap.data, false),
+        ImageType::SwfLossless2 => decode_lossless(&bitmap.data, true),
+        // TODO(jon): JPEGs with separate alpha, and JPEGs that depend on a `JPEGTables` tag.
+        _ => None,
+    }
+}
+
+// `data` is the tag body following the character ID: the format, the size, the color table size
+// for color-mapped bitmaps, and then the zlib-compressed color table and pixels.
+fn decode_lossless(data: &[u8], has_alpha: bool) -> Option<Image> {
+    if data.len() < 5 {
+        return None;
+    }
+    let format = data[0];
+    let width = u16::from_le_bytes([data[1], data[2]]) as usize;
+    let height = u16::from_le_bytes([data[3], data[4]]) as usize;
+
+    let (color_table_size, compressed) = if format == LOSSLESS_FORMAT_COLOR_MAPPED {
+        (*data.get(5)? as usize + 1, &data[6..])
+    } else {
+        (0, &data[5..])
+    };
+    let data = inflate::inflate_bytes_zlib(compressed).ok()?;
+
+    let mut pixels = Vec::with_capacity(width * height);
+    match format {
+        LOSSLESS_FORMAT_COLOR_MAPPED => {
+            let bytes_per_color = if has_alpha { 4 } else { 3 };
+            let (color_table, indices) = split_at_checked(&data,
+                                                          color_table_size * bytes_per_color)?;
+            let color_table: Vec<ColorU> = color_table.chunks(bytes_per_color).map(|color| {
+                if has_alpha {
+                    unpremultiply(color[0], color[1], color[2], color[3])
+                } else {
+                    ColorU { r: color[0], g: color[1], b: color[2], a: 255 }
+                }
+            }).collect();
+
+            // Rows are padded to a multiple of 4 bytes.
+            let stride = (width + 3) & !3;
+            for y in 0..height {
+                let row = indices.get((y * stride)..(y * stride + width))?;
+                for &index in row {
+                    pixels.push(color_table.get(index as usize).copied().unwrap_or_default());
+                }
+            }
+        }
+        LOSSLESS_FORMAT_RGB15 if !has_alpha => {
+            let stride = (width * 2 + 3) & !3;
+            for y in 0..height {
+                let row = data.get((y * stride)..(y * stride + width * 2))?;
+                for pixel in row.chunks(2) {
+                    let pixel = u16::from_be_bytes([pixel[0], pixel[1]]);
+                    let expand = |channel: u16| ((channel & 0x1f) * 255 / 31) as u8;
+                    pixels.push(ColorU {
+                        r: expand(pixel >> 10),
+                        g: expand(pixel >> 5),
+                        b: expand(pixel),
+                        a: 255,
+                    });
+                }
+            }
+        }
+        LOSSLESS_FORMAT_RGB32 => {
+            for pixel in data.get(0..(width * height * 4))?.chunks(4) {
+                if has_alpha {
+                    pixels.push(unpremultiply(pixel[1], pixel[2], pixel[3], pixel[0]));
+                } else {
+                    pixels.push(ColorU { r: pixel[1], g: pixel[2], b: pixel[3], a: 255 });
+                }
+            }
+        }
+        _ => return None,
+    }
+
+    Some(Image::new(Vector2I::new(width as i32, height as i32), pixels))
+}
+
+fn split_at_checked(data: &[u8], mid: usize) -> Option<(&[u8], &[u8])> {
+    if mid <= data.len() {
+        Some(data.split_at(mid))
+    } else {
+        None
+    }
+}
+
+// Lossless bitmaps with alpha store premultiplied colors, but `Image` wants straight alpha.
+fn unpremultiply(r: u8, g: u8, b: u8, a: u8) -> ColorU {
+    if a == 0 {
+        return ColorU::transparent_black();
+    }
+    let unpremultiply = |channel: u8| (channel as u32 * 255 / a as u32).min(255) as u8;
+    ColorU { r: unpremultiply(r), g: unpremultiply(g), b: unpremultiply(b), a }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LOSSLESS_FORMAT_COLOR_MAPPED, LOSSLESS_FORMAT_RGB15, LOSSLESS_FORMAT_RGB32};
+    use super::decode_lossless;
+    use pathfinder_color::ColorU;
+    use pathfinder_content::pattern::Image;
+    use pathfinder_geometry::vector::Vector2I;
+
+    // Wraps `data` in a zlib stream made of a single uncompressed block.
+    fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let length = data.len() as u16;
+        let mut stream = vec![0x78, 0x01, 0x01];
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(data);
+
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in data {
+            a = (a + byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        stream.extend_from_slice(&((b << 16) | a).to_be_bytes());
+        stream
+    }
+
+    fn lossless_tag(format: u8, width: u16, height: u16, color_table_size: Option<u8>, data: &[u8])
+                    -> Vec<u8> {
+        let mut tag = vec![format];
+        tag.extend_from_slice(&width.to_le_bytes());
+        tag.extend_from_slice(&height.to_le_bytes());
+        tag.extend(color_table_size.map(|size| size - 1));
+        tag.extend_from_slice(&zlib_stored(data));
+        tag
+    }
+
+    fn rgba(r: u8, g: u8, b: u8, a: u8) -> ColorU {
+        ColorU { r, g, b, a }
+    }
+
+    fn pixels(image: &Image) -> Vec<ColorU> {
+        image.pixels().to_vec()
+    }
+
+    #[test]
+    fn test_rgb32_bitmaps() {
+        let data = [0xff, 10, 20, 30, 0xff, 40, 50, 60];
+        let image = decode_lossless(&lossless_tag(LOSSLESS_FORMAT_RGB32, 2, 1, None, &data),
+                                    false).unwrap();
+        assert_eq!(image.size(), Vector2I::new(2, 1));
+        assert_eq!(pixels(&image), vec![rgba(10, 20, 30, 255), rgba(40, 50, 60, 255)]);
+    }
+
+    #[test]
+    fn test_lossless2_bitmaps_are_unpremultiplied() {
+        let data = [128, 64, 0, 128, 0, 10, 20, 30];
+        let image = decode_lossless(&lossless_tag(LOSSLESS_FORMAT_RGB32, 2, 1, None, &data),
+                                    true).unwrap();
+        assert_eq!(pixels(&image), vec![rgba(127, 0, 255, 128), ColorU::transparent_black()]);
+    }
+
+    #[test]
+    fn test_color_mapped_rows_are_padded() {
+        // Three pixels per row, padded to four bytes.
+        let mut data = vec![255, 0, 0, 0, 0, 255];
+        data.extend_from_slice(&[0, 1, 0, 99, 1, 1, 7, 99]);
+        let tag = lossless_tag(LOSSLESS_FORMAT_COLOR_MAPPED, 3, 2, Some(2), &data);
+        let image = decode_lossless(&tag, false).unwrap();
+
+        let (red, blue) = (rgba(255, 0, 0, 255), rgba(0, 0, 255, 255));
+        // Indices past the end of the color table come out transparent.
+        assert_eq!(pixels(&image),
+                   vec![red, blue, red, blue, blue, ColorU::transparent_black()]);
+    }
+
+    #[test]
+    fn test_rgb15_bitmaps() {
+        let data = [0x7c, 0x00, 0x03, 0xe0, 0x00, 0x1f, 0, 0];
+        let image = decode_lossless(&lossless_tag(LOSSLESS_FORMAT_RGB15, 3, 1, None, &data),
+                                    false).unwrap();
+        assert_eq!(pixels(&image),
+                   vec![rgba(255, 0, 0, 255), rgba(0, 255, 0, 255), rgba(0, 0, 255, 255)]);
+    }
+
+    #[test]
+    fn test_truncated_bitmaps_are_rejected() {
+        let data = [0xff, 10, 20, 30];
+        let tag = lossless_tag(LOSSLESS_FORMAT_RGB32, 2, 1, None, &data);
+        assert!(decode_lossless(&tag, false).is_none());
+        assert!(decode_lossless(&tag[0..4], false).is_none());
+        assert!(decode_lossless(&tag[0..10], false).is_none());
+    }
+}
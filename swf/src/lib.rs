@@ -8,19 +8,23 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::collections::HashMap;
 use std::ops::Add;
 use pathfinder_color::{ColorF, ColorU};
 use pathfinder_content::fill::FillRule;
 use pathfinder_content::outline::{Outline, Contour};
+use pathfinder_content::pattern::Image;
 use pathfinder_content::stroke::{OutlineStrokeToFill, StrokeStyle};
+use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::Vector2F;
 use pathfinder_renderer::scene::{DrawPath, Scene};
 
-use swf_types::tags::SetBackgroundColor;
+use swf_types::tags::{DefineMorphShape, SetBackgroundColor};
 use swf_types::{Tag, SRgb8, Movie};
 
 use crate::shapes::{GraphicLayers, PaintOrLine};
 
+mod bitmaps;
 mod shapes;
 
 type SymbolId = u16;
@@ -72,6 +76,8 @@ impl Add for Point2<Twips> {
 
 enum Symbol {
     Graphic(GraphicLayers),
+    // Morph shapes are kept undecoded, since the outline depends on the ratio they're drawn at.
+    MorphGraphic(DefineMorphShape),
     // Timeline, // TODO(jon)
 }
 
@@ -103,20 +109,24 @@ impl Stage {
 }
 
 
-pub struct SymbolLibrary(Vec<Symbol>);
+pub struct SymbolLibrary {
+    symbols: Vec<Symbol>,
+    // Bitmaps share the character ID space with symbols, but are only drawn through bitmap fills.
+    bitmaps: HashMap<SymbolId, Image>,
+}
 
 impl SymbolLibrary {
     fn add_symbol(&mut self, symbol: Symbol) {
-        self.0.push(symbol);
+        self.symbols.push(symbol);
     }
 
     fn symbols(&self) -> &Vec<Symbol> {
-        &self.0
+        &self.symbols
     }
 }
 
 pub fn process_swf_tags(movie: &Movie) -> (SymbolLibrary, Stage) {
-    let mut symbol_library = SymbolLibrary(Vec::new());
+    let mut symbol_library = SymbolLibrary { symbols: Vec::new(), bitmaps: HashMap::new() };
     let stage_width = Twips(movie.header.frame_size.x_max);
     let stage_height = Twips(movie.header.frame_size.y_max);
     // let num_frames = movie.header.frame_count;
@@ -137,11 +147,20 @@ pub fn process_swf_tags(movie: &Movie) -> (SymbolLibrary, Stage) {
             Tag::SetBackgroundColor(SetBackgroundColor { color }) => {
                 stage.background_color = *color;
             },
+            Tag::DefineBitmap(bitmap) => {
+                // Bitmaps we can't decode are left out, and fills using them draw nothing.
+                if let Some(image) = bitmaps::decode_bitmap(bitmap) {
+                    symbol_library.bitmaps.insert(bitmap.id, image);
+                }
+            }
             Tag::DefineShape(shape) => {
-                symbol_library.add_symbol(Symbol::Graphic(shapes::decode_shape(shape)));
-                // We will assume that symbol ids just go up, and are 1 based.
-                let symbol_id: SymbolId = shape.id;
-                debug_assert!(symbol_id as usize == symbol_library.0.len());
+                let graphic = shapes::decode_shape(shape, &symbol_library.bitmaps);
+                // NOTE: Symbols are kept in the order they're defined in. Their IDs are shared
+                // with bitmaps, so they don't line up with indices in the library.
+                symbol_library.add_symbol(Symbol::Graphic(graphic));
+            }
+            Tag::DefineMorphShape(shape) => {
+                symbol_library.add_symbol(Symbol::MorphGraphic(shape.clone()));
             }
             _ => ()
         }
@@ -149,56 +168,120 @@ pub fn process_swf_tags(movie: &Movie) -> (SymbolLibrary, Stage) {
     (symbol_library, stage)
 }
 
-#[allow(irrefutable_let_patterns)]
+/// How to draw a symbol library into a scene.
+#[derive(Clone, Copy, Debug)]
+pub struct DrawOptions {
+    /// The ratio to draw morph shapes (shape tweens) at, from 0.0 for the start shape to 1.0 for
+    /// the end shape. This is the `ratio` field of a `PlaceObject` tag divided by 65535.
+    pub ratio: f32,
+    /// A transform applied to every shape. Unlike a transform applied when the scene is built,
+    /// this leaves the width of non-scaling strokes alone.
+    pub transform: Transform2F,
+}
+
+impl Default for DrawOptions {
+    #[inline]
+    fn default() -> DrawOptions {
+        DrawOptions { ratio: 0.0, transform: Transform2F::default() }
+    }
+}
+
 pub fn draw_paths_into_scene(library: &SymbolLibrary, scene: &mut Scene) {
+    draw_paths_into_scene_with_options(library, &DrawOptions::default(), scene)
+}
+
+pub fn draw_paths_into_scene_with_options(library: &SymbolLibrary,
+                                          options: &DrawOptions,
+                                          scene: &mut Scene) {
     for symbol in library.symbols() {
-        // NOTE: Right now symbols only contain graphics.
-        if let Symbol::Graphic(graphic) = symbol {
-            for style_layer in graphic.layers() {
-                let mut path = Outline::new();
-                let paint_id = scene.push_paint(&style_layer.fill());
-
-                for shape in style_layer.shapes() {
-                    let mut contour = Contour::new();
-                    let Point2 { x, y } = shape.outline.first().unwrap().from.as_f32();
-                    contour.push_endpoint(Vector2F::new(x, y));
-                    for segment in &shape.outline {
-                        let Point2 { x, y } = segment.to.as_f32();
-                        match segment.ctrl {
-                            Some(ctrl) => {
-                                let Point2 { x: ctrl_x, y: ctrl_y } = ctrl.as_f32();
-                                contour.push_quadratic(
-                                    Vector2F::new(ctrl_x, ctrl_y),
-                                    Vector2F::new(x, y)
-                                );
-                            }
-                            None => {
-                                contour.push_endpoint(Vector2F::new(x, y));
-                            },
-                        }
+        match symbol {
+            Symbol::Graphic(graphic) => {
+                draw_graphic_into_scene(graphic, &options.transform, scene)
+            }
+            Symbol::MorphGraphic(shape) => {
+                let ratio = options.ratio.max(0.0).min(1.0);
+                let graphic = shapes::decode_morph_shape(shape, ratio, &library.bitmaps);
+                draw_graphic_into_scene(&graphic, &options.transform, scene)
+            }
+        }
+    }
+}
+
+fn draw_graphic_into_scene(graphic: &GraphicLayers, transform: &Transform2F, scene: &mut Scene) {
+    for style_layer in graphic.layers() {
+        let mut path = Outline::new();
+        let mut paint = style_layer.fill().clone();
+        paint.apply_transform(transform);
+        let paint_id = scene.push_paint(&paint);
+
+        for shape in style_layer.shapes() {
+            let mut contour = Contour::new();
+            let Point2 { x, y } = shape.outline.first().unwrap().from.as_f32();
+            contour.push_endpoint(Vector2F::new(x, y));
+            for segment in &shape.outline {
+                let Point2 { x, y } = segment.to.as_f32();
+                match segment.ctrl {
+                    Some(ctrl) => {
+                        let Point2 { x: ctrl_x, y: ctrl_y } = ctrl.as_f32();
+                        contour.push_quadratic(
+                            Vector2F::new(ctrl_x, ctrl_y),
+                            Vector2F::new(x, y)
+                        );
                     }
-                    if shape.is_closed() {
-                        // NOTE: I'm not sure if this really does anything in this context,
-                        // since all our closed shapes already have coincident start and end points.
-                        contour.close();
+                    None => {
+                        contour.push_endpoint(Vector2F::new(x, y));
+                    },
+                }
+            }
+            if shape.is_closed() {
+                // NOTE: I'm not sure if this really does anything in this context,
+                // since all our closed shapes already have coincident start and end points.
+                contour.close();
+            }
+            path.push_contour(contour);
+        }
+
+        let fill_rule = match style_layer.kind() {
+            PaintOrLine::Paint(_) => {
+                path.transform(transform);
+                FillRule::EvenOdd
+            }
+            PaintOrLine::Line(line) => {
+                // Non-scaling strokes are transformed before they're stroked, so that their width
+                // stays the same. Otherwise, the stroke itself is transformed along with the path.
+                let mut line_width = line.width.as_f32();
+                let scaling = !line.no_h_scale && !line.no_v_scale;
+                if !scaling {
+                    path.transform(transform);
+                    let scale_x = transform.matrix * Vector2F::new(1.0, 0.0);
+                    let scale_y = transform.matrix * Vector2F::new(0.0, 1.0);
+                    if !line.no_h_scale {
+                        line_width *= scale_x.length();
+                    } else if !line.no_v_scale {
+                        line_width *= scale_y.length();
                     }
-                    path.push_contour(contour);
                 }
 
-                if let PaintOrLine::Line(line) = style_layer.kind() {
-                    let mut stroke_to_fill = OutlineStrokeToFill::new(&path, StrokeStyle {
-                        line_width: line.width.as_f32(),
-                        line_cap: line.cap,
-                        line_join: line.join,
-                    });
-                    stroke_to_fill.offset();
-                    path = stroke_to_fill.into_outline();
+                let mut stroke_to_fill = OutlineStrokeToFill::new(&path, StrokeStyle {
+                    line_width,
+                    line_cap: line.start_cap,
+                    line_join: line.join,
+                });
+                stroke_to_fill.set_end_cap(line.end_cap);
+                stroke_to_fill.offset();
+                path = stroke_to_fill.into_outline();
+                if scaling {
+                    path.transform(transform);
                 }
 
-                let mut path = DrawPath::new(path, paint_id);
-                path.set_fill_rule(FillRule::EvenOdd);
-                scene.push_path(path);
+                // Stroked outlines overlap themselves wherever the line crosses itself or turns
+                // sharply, so they have to be filled with the nonzero rule.
+                FillRule::Winding
             }
-        }
+        };
+
+        let mut path = DrawPath::new(path, paint_id);
+        path.set_fill_rule(fill_rule);
+        scene.push_path(path);
     }
 }
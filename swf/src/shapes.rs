@@ -11,13 +11,26 @@
 use crate::{Twips, Point2};
 
 use pathfinder_color::ColorU;
+use pathfinder_content::gradient::{ColorStop, Gradient, GradientGeometry};
+use pathfinder_content::pattern::{Image, Pattern, PatternFlags, PatternSource};
 use pathfinder_content::stroke::{LineJoin, LineCap};
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
 use pathfinder_renderer::paint::Paint;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::mem;
-use swf_types::tags::DefineShape;
-use swf_types::{CapStyle, FillStyle, JoinStyle, LineStyle, ShapeRecord, StraightSRgba8, Vector2D};
-use swf_types::{fill_styles, join_styles, shape_records};
+use swf_types::tags::{DefineMorphShape, DefineShape};
+use swf_types::{CapStyle, FillStyle, JoinStyle, LineStyle, Matrix, ShapeRecord, StraightSRgba8};
+use swf_types::{ColorStop as SwfColorStop, Gradient as SwfGradient, MorphGradient, Vector2D};
+use swf_types::{MorphFillStyle, MorphLineStyle, MorphShape, MorphShapeRecord, MorphShapeStyles};
+use swf_types::{fill_styles, join_styles, morph_fill_styles, morph_shape_records, shape_records};
+use swf_types::{Shape as SwfShape, ShapeStyles};
+
+// Gradients are defined in a square from -16384 to 16384 twips on each axis, which their fill
+// style's matrix maps into the shape.
+const GRADIENT_SQUARE_HALF_SIZE: f32 = 16384.0;
 
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct LineSegment {
@@ -122,7 +135,11 @@ pub(crate) struct SwfLineStyle {
     color: Paint,
     pub(crate) width: Twips,
     pub(crate) join: LineJoin,
-    pub(crate) cap: LineCap,
+    pub(crate) start_cap: LineCap,
+    pub(crate) end_cap: LineCap,
+    // Non-scaling strokes keep their width along the given axis when the shape is transformed.
+    pub(crate) no_h_scale: bool,
+    pub(crate) no_v_scale: bool,
 }
 
 pub(crate) enum PaintOrLine {
@@ -238,76 +255,157 @@ impl StyleLayer {
 
 fn get_new_styles<'a>(
     fills: &'a Vec<FillStyle>,
-    lines: &'a Vec<LineStyle>
+    lines: &'a Vec<LineStyle>,
+    bitmaps: &'a HashMap<u16, Image>,
 ) -> impl Iterator<Item=PaintOrLine> + 'a {
     // This enforces the order that fills and line groupings are added in.
     // Fills always come first.
-    fills.iter().filter_map(|fill_style| {
-        match fill_style {
-            FillStyle::Solid(
-                fill_styles::Solid {
-                    color: StraightSRgba8 {
-                        r,
-                        g,
-                        b,
-                        a
-                    }
-                }
-            ) => Some(PaintOrLine::Paint(Paint::Color(ColorU { r: *r, g: *g, b: *b, a: *a }))),
-            _ => unimplemented!("Unimplemented fill style")
-        }
+    fills.iter().map(move |fill_style| {
+        PaintOrLine::Paint(decode_fill_style(fill_style, bitmaps))
     }).chain(
-        lines.iter().filter_map(|LineStyle {
+        lines.iter().map(move |LineStyle {
             width,
             fill,
             join,
             start_cap,
-            end_cap: _,
+            end_cap,
+            no_h_scale,
+            no_v_scale,
             /*
             TODO(jon): Handle these cases?
-            pub no_h_scale: bool,
-            pub no_v_scale: bool,
             pub no_close: bool,
             pub pixel_hinting: bool,
             */
             ..
         }| {
-            if let FillStyle::Solid(fill_styles::Solid {
-                color: StraightSRgba8 {
-                    r,
-                    g,
-                    b,
-                    a
-                }
-            }) = fill {
-                // NOTE: PathFinder doesn't support different cap styles for start and end of
-                // strokes, so lets assume that they're always the same for the inputs we care about.
-                // Alternately, we split a line in two with a diff cap style for each.
-                // assert_eq!(start_cap, end_cap);
-                Some(PaintOrLine::Line(SwfLineStyle {
-                    width: Twips(*width as i32),
-                    color: Paint::Color(ColorU { r: *r, g: *g, b: *b, a: *a }),
-                    join: match join {
-                        JoinStyle::Bevel => LineJoin::Bevel,
-                        JoinStyle::Round => LineJoin::Round,
-                        JoinStyle::Miter(join_styles::Miter { limit }) => {
-                            LineJoin::Miter(*limit as f32)
-                        },
-                    },
-                    cap: match start_cap {
-                        CapStyle::None => LineCap::Butt,
-                        CapStyle::Square => LineCap::Square,
-                        CapStyle::Round => LineCap::Round,
+            PaintOrLine::Line(SwfLineStyle {
+                // NOTE: Flash draws lines at least one pixel wide, so that zero-width lines
+                // show up as hairlines.
+                width: Twips((*width as i32).max(20)),
+                color: decode_fill_style(fill, bitmaps),
+                join: match join {
+                    JoinStyle::Bevel => LineJoin::Bevel,
+                    JoinStyle::Round => LineJoin::Round,
+                    // The miter limit factor is an 8.8 fixed point multiple of half the line
+                    // width, which is what Pathfinder expects too.
+                    JoinStyle::Miter(join_styles::Miter { limit }) => {
+                        LineJoin::Miter(*limit as f32 / 256.0)
                     },
-                }))
-            } else {
-                unimplemented!("unimplemented line fill style");
-            }
+                },
+                start_cap: decode_cap_style(start_cap),
+                end_cap: decode_cap_style(end_cap),
+                no_h_scale: *no_h_scale,
+                no_v_scale: *no_v_scale,
+            })
         })
     )
 }
 
-pub(crate) fn decode_shape(shape: &DefineShape) -> GraphicLayers {
+fn decode_cap_style(cap_style: &CapStyle) -> LineCap {
+    match cap_style {
+        CapStyle::None => LineCap::Butt,
+        CapStyle::Square => LineCap::Square,
+        CapStyle::Round => LineCap::Round,
+    }
+}
+
+fn decode_fill_style(fill_style: &FillStyle, bitmaps: &HashMap<u16, Image>) -> Paint {
+    match fill_style {
+        FillStyle::Solid(fill_styles::Solid { color }) => Paint::Color(decode_color(color)),
+        FillStyle::LinearGradient(fill_styles::LinearGradient { matrix, gradient }) => {
+            decode_gradient(linear_gradient_square(), gradient, matrix)
+        }
+        FillStyle::RadialGradient(fill_styles::RadialGradient { matrix, gradient }) => {
+            decode_gradient(radial_gradient_square(0.0), gradient, matrix)
+        }
+        FillStyle::FocalGradient(fill_styles::FocalGradient { matrix, gradient, focal_point }) => {
+            let focal_point = focal_point.epsilons as f32 / 256.0;
+            decode_gradient(radial_gradient_square(focal_point), gradient, matrix)
+        }
+        FillStyle::Bitmap(fill_styles::Bitmap { bitmap_id, matrix, repeating, smoothed }) => {
+            let image = match bitmaps.get(bitmap_id) {
+                Some(image) => image.clone(),
+                // NOTE: Flash draws fills that refer to missing bitmaps as nothing at all; this
+                // includes the placeholder ID 0xffff that some exporters emit.
+                None => return Paint::Color(ColorU::transparent_black()),
+            };
+            let mut flags = PatternFlags::empty();
+            if *repeating {
+                flags.insert(PatternFlags::REPEAT_X | PatternFlags::REPEAT_Y);
+            }
+            if !*smoothed {
+                flags.insert(PatternFlags::NO_SMOOTHING);
+            }
+            // The matrix maps bitmap pixels into the shape's twips.
+            let transform = twips_to_pixels() * decode_matrix(matrix);
+            Paint::Pattern(Pattern::new(PatternSource::Image(image), transform, flags))
+        }
+    }
+}
+
+// Builds a gradient in the coordinate space of the gradient square, then maps it into the shape.
+fn decode_gradient(mut gradient: Gradient, swf_gradient: &SwfGradient, matrix: &Matrix) -> Paint {
+    // TODO(jon): Pathfinder gradients always pad, so the reflect and repeat spread modes are
+    // drawn as pad, and linear RGB interpolation is drawn as sRGB.
+    for stop in &swf_gradient.colors {
+        let offset = stop.ratio as f32 / 255.0;
+        gradient.add_color_stop(ColorStop::new(decode_color(&stop.color), offset));
+    }
+
+    transform_gradient(&mut gradient, &(twips_to_pixels() * decode_matrix(matrix)));
+    Paint::Gradient(gradient)
+}
+
+// Linear gradients run from left to right across the gradient square.
+fn linear_gradient_square() -> Gradient {
+    Gradient::linear(LineSegment2F::new(Vector2F::new(-GRADIENT_SQUARE_HALF_SIZE, 0.0),
+                                        Vector2F::new(GRADIENT_SQUARE_HALF_SIZE, 0.0)))
+}
+
+// Radial gradients fill the circle inscribed in the gradient square. The focal point moves the
+// center of the innermost circle along the x axis, between -1.0 and 1.0 times the radius.
+fn radial_gradient_square(focal_point: f32) -> Gradient {
+    let focal_point = focal_point.max(-1.0).min(1.0) * GRADIENT_SQUARE_HALF_SIZE;
+    let line = LineSegment2F::new(Vector2F::new(focal_point, 0.0), Vector2F::default());
+    Gradient::radial(line, 0.0, GRADIENT_SQUARE_HALF_SIZE)
+}
+
+// NOTE: We don't use `Paint::apply_transform()` here, since gradient matrices are frequently
+// rotated, and it only scales radii by the diagonal of the matrix. Scaling by the square root of
+// the determinant is exact for rotations and uniform scales, which covers most inputs; skewed or
+// non-uniformly scaled radial gradients would need elliptical gradients.
+fn transform_gradient(gradient: &mut Gradient, transform: &Transform2F) {
+    match *gradient.geometry_mut() {
+        GradientGeometry::Linear(ref mut line) => *line = *transform * *line,
+        GradientGeometry::Radial { ref mut line, ref mut start_radius, ref mut end_radius } => {
+            let radius_scale = transform.matrix.det().abs().sqrt();
+            *line = *transform * *line;
+            *start_radius *= radius_scale;
+            *end_radius *= radius_scale;
+        }
+    }
+}
+
+fn decode_color(color: &StraightSRgba8) -> ColorU {
+    ColorU { r: color.r, g: color.g, b: color.b, a: color.a }
+}
+
+fn decode_matrix(matrix: &Matrix) -> Transform2F {
+    Transform2F::row_major(matrix.scale_x.epsilons as f32 / 65536.0,
+                           matrix.rotate_skew1.epsilons as f32 / 65536.0,
+                           matrix.rotate_skew0.epsilons as f32 / 65536.0,
+                           matrix.scale_y.epsilons as f32 / 65536.0,
+                           matrix.translate_x as f32,
+                           matrix.translate_y as f32)
+}
+
+// Shape coordinates are converted from twips to pixels only once we output them, so paints that
+// are positioned in twips need the same conversion.
+fn twips_to_pixels() -> Transform2F {
+    Transform2F::from_uniform_scale(1.0 / 20.0)
+}
+
+pub(crate) fn decode_shape(shape: &DefineShape, bitmaps: &HashMap<u16, Image>) -> GraphicLayers {
     let DefineShape {
         shape,
         // id,
@@ -316,6 +414,19 @@ pub(crate) fn decode_shape(shape: &DefineShape) -> GraphicLayers {
         // has_scaling_strokes,
         ..
     } = shape;
+    decode_shape_records(shape, bitmaps)
+}
+
+/// Decodes a morph shape (from either `DefineMorphShape` or `DefineMorphShape2`) as it appears
+/// at `ratio`, where 0.0 is the start shape and 1.0 is the end shape.
+pub(crate) fn decode_morph_shape(shape: &DefineMorphShape,
+                                 ratio: f32,
+                                 bitmaps: &HashMap<u16, Image>)
+                                 -> GraphicLayers {
+    decode_shape_records(&interpolate_morph_shape(&shape.shape, ratio), bitmaps)
+}
+
+fn decode_shape_records(shape: &SwfShape, bitmaps: &HashMap<u16, Image>) -> GraphicLayers {
     let mut graphic = GraphicLayers::new();
     let mut current_line_style = None;
     let mut current_left_fill = None;
@@ -328,7 +439,9 @@ pub(crate) fn decode_shape(shape: &DefineShape) -> GraphicLayers {
     let mut both_fills_set_and_same = false;
 
     // Create style groups for initially specified fills and lines.
-    for fills_or_line in get_new_styles(&shape.initial_styles.fill, &shape.initial_styles.line) {
+    for fills_or_line in get_new_styles(&shape.initial_styles.fill,
+                                               &shape.initial_styles.line,
+                                               bitmaps) {
         match fills_or_line {
             PaintOrLine::Paint(fill) => graphic.begin_fill_style(fill),
             PaintOrLine::Line(line) => graphic.begin_line_style(line),
@@ -351,7 +464,7 @@ pub(crate) fn decode_shape(shape: &DefineShape) -> GraphicLayers {
                     // Consolidate current style grouping and begin a new one.
                     graphic.end_style_group();
                     graphic.begin_style_group();
-                    for fills_or_line in get_new_styles(&new_style.fill, &new_style.line) {
+                    for fills_or_line in get_new_styles(&new_style.fill, &new_style.line, bitmaps) {
                         match fills_or_line {
                             PaintOrLine::Paint(fill) => graphic.begin_fill_style(fill),
                             PaintOrLine::Line(line) => graphic.begin_line_style(line),
@@ -503,6 +616,216 @@ pub(crate) fn decode_shape(shape: &DefineShape) -> GraphicLayers {
     graphic
 }
 
+// Flattens a morph shape into a regular shape at the given ratio, so that it can go through the
+// same decoding as any other shape.
+//
+// Morph edges are specified as deltas, but we interpolate absolute positions and then take deltas
+// between the rounded results; otherwise rounding errors would accumulate along each contour, and
+// shapes that are closed in both the start and end states could fail to close when interpolated.
+fn interpolate_morph_shape(shape: &MorphShape, ratio: f32) -> SwfShape {
+    let mut records = Vec::with_capacity(shape.records.len());
+    let mut start_pos = Point2 { x: 0, y: 0 };
+    let mut end_pos = Point2 { x: 0, y: 0 };
+    let mut pos = Point2 { x: 0, y: 0 };
+
+    for record in &shape.records {
+        match record {
+            MorphShapeRecord::StyleChange(morph_shape_records::MorphStyleChange {
+                move_to,
+                left_fill,
+                right_fill,
+                line_style,
+                new_styles,
+            }) => {
+                let move_to = move_to.as_ref().map(|move_to| {
+                    start_pos = Point2 { x: move_to.move_to.x, y: move_to.move_to.y };
+                    end_pos = Point2 { x: move_to.morph_move_to.x, y: move_to.morph_move_to.y };
+                    pos = lerp_point(start_pos, end_pos, ratio);
+                    Vector2D { x: pos.x, y: pos.y }
+                });
+                records.push(ShapeRecord::StyleChange(shape_records::StyleChange {
+                    move_to,
+                    left_fill: *left_fill,
+                    right_fill: *right_fill,
+                    line_style: *line_style,
+                    new_styles: new_styles.as_ref().map(|styles| interpolate_styles(styles, ratio)),
+                }));
+            }
+            MorphShapeRecord::Edge(morph_shape_records::MorphEdge {
+                delta,
+                morph_delta,
+                control_delta,
+                morph_control_delta,
+            }) => {
+                let start_to = Point2 { x: start_pos.x + delta.x, y: start_pos.y + delta.y };
+                let end_to = Point2 { x: end_pos.x + morph_delta.x, y: end_pos.y + morph_delta.y };
+                let to = lerp_point(start_to, end_to, ratio);
+
+                // If only one of the two edges is a curve, the straight one is treated as a curve
+                // with its control point halfway along it.
+                let control_delta = match (control_delta, morph_control_delta) {
+                    (None, None) => None,
+                    (control_delta, morph_control_delta) => {
+                        let start_ctrl = control_point(start_pos, start_to, control_delta);
+                        let end_ctrl = control_point(end_pos, end_to, morph_control_delta);
+                        let ctrl = lerp_point(start_ctrl, end_ctrl, ratio);
+                        Some(Vector2D { x: ctrl.x - pos.x, y: ctrl.y - pos.y })
+                    }
+                };
+
+                records.push(ShapeRecord::Edge(shape_records::Edge {
+                    delta: Vector2D { x: to.x - pos.x, y: to.y - pos.y },
+                    control_delta,
+                }));
+                start_pos = start_to;
+                end_pos = end_to;
+                pos = to;
+            }
+        }
+    }
+
+    SwfShape { initial_styles: interpolate_styles(&shape.initial_styles, ratio), records }
+}
+
+fn control_point(from: Point2<i32>, to: Point2<i32>, control_delta: &Option<Vector2D>)
+                 -> Point2<i32> {
+    match *control_delta {
+        Some(Vector2D { x, y }) => Point2 { x: from.x + x, y: from.y + y },
+        None => Point2 { x: (from.x + to.x) / 2, y: (from.y + to.y) / 2 },
+    }
+}
+
+fn interpolate_styles(styles: &MorphShapeStyles, ratio: f32) -> ShapeStyles {
+    ShapeStyles {
+        fill: styles.fill.iter().map(|fill| interpolate_fill_style(fill, ratio)).collect(),
+        line: styles.line.iter().map(|line| interpolate_line_style(line, ratio)).collect(),
+    }
+}
+
+fn interpolate_fill_style(fill: &MorphFillStyle, ratio: f32) -> FillStyle {
+    match fill {
+        MorphFillStyle::Solid(morph_fill_styles::Solid { color, morph_color }) => {
+            FillStyle::Solid(fill_styles::Solid { color: lerp_color(color, morph_color, ratio) })
+        }
+        MorphFillStyle::LinearGradient(morph_fill_styles::LinearGradient {
+            matrix,
+            morph_matrix,
+            gradient,
+        }) => {
+            FillStyle::LinearGradient(fill_styles::LinearGradient {
+                matrix: lerp_matrix(matrix, morph_matrix, ratio),
+                gradient: interpolate_gradient(gradient, ratio),
+            })
+        }
+        MorphFillStyle::RadialGradient(morph_fill_styles::RadialGradient {
+            matrix,
+            morph_matrix,
+            gradient,
+        }) => {
+            FillStyle::RadialGradient(fill_styles::RadialGradient {
+                matrix: lerp_matrix(matrix, morph_matrix, ratio),
+                gradient: interpolate_gradient(gradient, ratio),
+            })
+        }
+        MorphFillStyle::FocalGradient(morph_fill_styles::FocalGradient {
+            matrix,
+            morph_matrix,
+            gradient,
+            focal_point,
+            morph_focal_point,
+        }) => {
+            let mut interpolated_focal_point = focal_point.clone();
+            interpolated_focal_point.epsilons =
+                lerp(focal_point.epsilons as i32, morph_focal_point.epsilons as i32, ratio) as i16;
+            FillStyle::FocalGradient(fill_styles::FocalGradient {
+                matrix: lerp_matrix(matrix, morph_matrix, ratio),
+                gradient: interpolate_gradient(gradient, ratio),
+                focal_point: interpolated_focal_point,
+            })
+        }
+        MorphFillStyle::Bitmap(morph_fill_styles::Bitmap {
+            bitmap_id,
+            matrix,
+            morph_matrix,
+            repeating,
+            smoothed,
+        }) => {
+            FillStyle::Bitmap(fill_styles::Bitmap {
+                bitmap_id: *bitmap_id,
+                matrix: lerp_matrix(matrix, morph_matrix, ratio),
+                repeating: *repeating,
+                smoothed: *smoothed,
+            })
+        }
+    }
+}
+
+// Morph gradients pair each start stop with an end stop, so they always have the same number.
+fn interpolate_gradient(gradient: &MorphGradient, ratio: f32) -> SwfGradient {
+    SwfGradient {
+        spread: gradient.spread.clone(),
+        color_space: gradient.color_space.clone(),
+        colors: gradient.colors.iter().map(|stop| {
+            SwfColorStop {
+                ratio: lerp_u8(stop.ratio, stop.morph_ratio, ratio),
+                color: lerp_color(&stop.color, &stop.morph_color, ratio),
+            }
+        }).collect(),
+    }
+}
+
+fn interpolate_line_style(line: &MorphLineStyle, ratio: f32) -> LineStyle {
+    LineStyle {
+        width: lerp(line.width as i32, line.morph_width as i32, ratio) as u16,
+        start_cap: line.start_cap.clone(),
+        end_cap: line.end_cap.clone(),
+        join: line.join.clone(),
+        no_h_scale: line.no_h_scale,
+        no_v_scale: line.no_v_scale,
+        no_close: line.no_close,
+        pixel_hinting: line.pixel_hinting,
+        fill: interpolate_fill_style(&line.fill, ratio),
+    }
+}
+
+#[inline]
+fn lerp(start: i32, end: i32, ratio: f32) -> i32 {
+    (start as f32 + (end - start) as f32 * ratio).round() as i32
+}
+
+#[inline]
+fn lerp_u8(start: u8, end: u8, ratio: f32) -> u8 {
+    lerp(start as i32, end as i32, ratio) as u8
+}
+
+#[inline]
+fn lerp_color(start: &StraightSRgba8, end: &StraightSRgba8, ratio: f32) -> StraightSRgba8 {
+    StraightSRgba8 {
+        r: lerp_u8(start.r, end.r, ratio),
+        g: lerp_u8(start.g, end.g, ratio),
+        b: lerp_u8(start.b, end.b, ratio),
+        a: lerp_u8(start.a, end.a, ratio),
+    }
+}
+
+fn lerp_matrix(start: &Matrix, end: &Matrix, ratio: f32) -> Matrix {
+    let mut matrix = start.clone();
+    matrix.scale_x.epsilons = lerp(start.scale_x.epsilons, end.scale_x.epsilons, ratio);
+    matrix.scale_y.epsilons = lerp(start.scale_y.epsilons, end.scale_y.epsilons, ratio);
+    matrix.rotate_skew0.epsilons =
+        lerp(start.rotate_skew0.epsilons, end.rotate_skew0.epsilons, ratio);
+    matrix.rotate_skew1.epsilons =
+        lerp(start.rotate_skew1.epsilons, end.rotate_skew1.epsilons, ratio);
+    matrix.translate_x = lerp(start.translate_x, end.translate_x, ratio);
+    matrix.translate_y = lerp(start.translate_y, end.translate_y, ratio);
+    matrix
+}
+
+#[inline]
+fn lerp_point(start: Point2<i32>, end: Point2<i32>, ratio: f32) -> Point2<i32> {
+    Point2 { x: lerp(start.x, end.x, ratio), y: lerp(start.y, end.y, ratio) }
+}
+
 fn find_matches(
     mut first_open_index: usize,
     shapes: &mut Vec<Shape>,
@@ -605,3 +928,113 @@ impl GraphicLayers {
     }
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::{interpolate_fill_style, interpolate_gradient, linear_gradient_square};
+    use super::{radial_gradient_square, transform_gradient, twips_to_pixels};
+    use pathfinder_content::gradient::GradientGeometry;
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::Vector2F;
+    use std::f32::consts::FRAC_PI_2;
+    use swf_types::{ColorSpace, FillStyle, Gradient, GradientSpread, MorphColorStop};
+    use swf_types::{MorphFillStyle, MorphGradient, StraightSRgba8};
+    use swf_types::{fill_styles, morph_fill_styles};
+
+    fn color(r: u8, g: u8, b: u8, a: u8) -> StraightSRgba8 {
+        StraightSRgba8 { r, g, b, a }
+    }
+
+    fn components(color: &StraightSRgba8) -> [u8; 4] {
+        [color.r, color.g, color.b, color.a]
+    }
+
+    #[test]
+    fn test_solid_morph_fills_are_interpolated() {
+        let fill = MorphFillStyle::Solid(morph_fill_styles::Solid {
+            color: color(0, 100, 200, 255),
+            morph_color: color(100, 100, 0, 55),
+        });
+        match interpolate_fill_style(&fill, 0.25) {
+            FillStyle::Solid(fill_styles::Solid { color }) => {
+                assert_eq!(components(&color), [25, 100, 150, 205]);
+            }
+            _ => panic!("a solid morph fill should stay solid"),
+        }
+    }
+
+    #[test]
+    fn test_morph_gradient_stops_are_interpolated() {
+        let gradient = MorphGradient {
+            spread: GradientSpread::Pad,
+            color_space: ColorSpace::SRgb,
+            colors: vec![
+                MorphColorStop {
+                    ratio: 0,
+                    color: color(255, 0, 0, 255),
+                    morph_ratio: 100,
+                    morph_color: color(0, 0, 255, 255),
+                },
+                MorphColorStop {
+                    ratio: 255,
+                    color: color(0, 0, 0, 0),
+                    morph_ratio: 155,
+                    morph_color: color(0, 0, 0, 255),
+                },
+            ],
+        };
+        let stops = |gradient: &Gradient| -> Vec<(u8, [u8; 4])> {
+            gradient.colors.iter().map(|stop| (stop.ratio, components(&stop.color))).collect()
+        };
+        assert_eq!(stops(&interpolate_gradient(&gradient, 0.0)),
+                   vec![(0, [255, 0, 0, 255]), (255, [0, 0, 0, 0])]);
+        assert_eq!(stops(&interpolate_gradient(&gradient, 1.0)),
+                   vec![(100, [0, 0, 255, 255]), (155, [0, 0, 0, 255])]);
+        assert_eq!(stops(&interpolate_gradient(&gradient, 0.5)),
+                   vec![(50, [128, 0, 128, 255]), (205, [0, 0, 0, 128])]);
+    }
+
+    #[test]
+    fn test_linear_gradients_span_the_gradient_square() {
+        // Squeeze the gradient square down to 200 twips wide, centered 2000 twips to the right.
+        let matrix = Transform2F::from_translation(Vector2F::new(2000.0, 0.0)) *
+            Transform2F::from_uniform_scale(100.0 / 16384.0);
+        let mut gradient = linear_gradient_square();
+        transform_gradient(&mut gradient, &(twips_to_pixels() * matrix));
+        match *gradient.geometry() {
+            GradientGeometry::Linear(line) => {
+                assert_eq!(line, LineSegment2F::new(Vector2F::new(95.0, 0.0),
+                                                    Vector2F::new(105.0, 0.0)));
+            }
+            _ => panic!("a linear gradient should stay linear"),
+        }
+    }
+
+    #[test]
+    fn test_radial_gradient_radii_follow_rotated_matrices() {
+        let matrix = Transform2F::from_rotation(FRAC_PI_2) * Transform2F::from_uniform_scale(2.0);
+        let mut gradient = radial_gradient_square(0.5);
+        transform_gradient(&mut gradient, &(twips_to_pixels() * matrix));
+        match *gradient.geometry() {
+            GradientGeometry::Radial { line, start_radius, end_radius } => {
+                // The focal point sits halfway to the edge, rotated onto the y axis.
+                assert!((line.from() - Vector2F::new(0.0, 819.2)).length() < 0.01);
+                assert_eq!(line.to(), Vector2F::default());
+                assert_eq!(start_radius, 0.0);
+                assert!((end_radius - 1638.4).abs() < 0.01);
+            }
+            _ => panic!("a radial gradient should stay radial"),
+        }
+    }
+
+    #[test]
+    fn test_focal_points_are_clamped_to_the_circle() {
+        for &(focal_point, expected_x) in &[(2.0, 16384.0), (-1.5, -16384.0), (0.0, 0.0)] {
+            match *radial_gradient_square(focal_point).geometry() {
+                GradientGeometry::Radial { line, .. } => assert_eq!(line.from_x(), expected_x),
+                _ => panic!("focal gradients should be radial"),
+            }
+        }
+    }
+}
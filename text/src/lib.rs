@@ -23,6 +23,8 @@ use pathfinder_renderer::scene::{DrawPath, Scene};
 use skribo::{FontCollection, Layout, TextStyle};
 use std::mem;
 
+pub mod woff;
+
 pub trait SceneExt {
     // TODO(pcwalton): Support stroked glyphs.
     fn push_glyph<F>(&mut self,
@@ -41,6 +43,7 @@ pub trait SceneExt {
                    transform: &Transform2F,
                    render_mode: TextRenderMode,
                    hinting_options: HintingOptions,
+                   quantization: GlyphQuantization,
                    paint_id: PaintId)
                    -> Result<(), GlyphLoadingError>;
 
@@ -51,6 +54,7 @@ pub trait SceneExt {
                  transform: &Transform2F,
                  render_mode: TextRenderMode,
                  hinting_options: HintingOptions,
+                 quantization: GlyphQuantization,
                  paint_id: PaintId)
                  -> Result<(), GlyphLoadingError>;
 }
@@ -86,6 +90,7 @@ impl SceneExt for Scene {
                    transform: &Transform2F,
                    render_mode: TextRenderMode,
                    hinting_options: HintingOptions,
+                   quantization: GlyphQuantization,
                    paint_id: PaintId)
                    -> Result<(), GlyphLoadingError> {
         for glyph in &layout.glyphs {
@@ -94,7 +99,8 @@ impl SceneExt for Scene {
             // FIXME(pcwalton): Cache this!
             let scale = style.size / (font.metrics().units_per_em as f32);
             let scale = Vector2F::new(scale, -scale);
-            let transform = *transform * Transform2F::from_scale(scale).translate(offset);
+            let mut transform = *transform * Transform2F::from_scale(scale).translate(offset);
+            transform.vector = quantization.quantize(transform.vector);
             self.push_glyph(font,
                             glyph.glyph_id,
                             &transform,
@@ -113,10 +119,17 @@ impl SceneExt for Scene {
                  transform: &Transform2F,
                  render_mode: TextRenderMode,
                  hinting_options: HintingOptions,
+                 quantization: GlyphQuantization,
                  paint_id: PaintId)
                  -> Result<(), GlyphLoadingError> {
         let layout = skribo::layout(style, collection, text);
-        self.push_layout(&layout, style, &transform, render_mode, hinting_options, paint_id)
+        self.push_layout(&layout,
+                         style,
+                         &transform,
+                         render_mode,
+                         hinting_options,
+                         quantization,
+                         paint_id)
     }
 }
 
@@ -126,6 +139,43 @@ pub enum TextRenderMode {
     Stroke(StrokeStyle),
 }
 
+/// How the origin of each glyph in a layout is rounded, in scene coordinates, before its outline
+/// is placed there.
+///
+/// Coarser positioning makes more glyphs identical to ones drawn before, so that a glyph cache
+/// hits more often, and keeps text from shimmering as it scrolls by fractions of a pixel, at the
+/// cost of less even spacing.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GlyphQuantization {
+    /// Glyphs are placed exactly where the layout puts them.
+    None,
+    /// Glyph origins are rounded to the nearest quarter pixel horizontally and to the nearest
+    /// pixel vertically, so each glyph has four horizontal variants.
+    QuarterPixelX,
+    /// Glyph origins are rounded to the nearest pixel.
+    Integer,
+}
+
+impl GlyphQuantization {
+    /// Rounds a glyph origin according to this mode.
+    pub fn quantize(self, origin: Vector2F) -> Vector2F {
+        match self {
+            GlyphQuantization::None => origin,
+            GlyphQuantization::QuarterPixelX => {
+                Vector2F::new((origin.x() * 4.0).round() * 0.25, origin.y().round())
+            }
+            GlyphQuantization::Integer => origin.round(),
+        }
+    }
+}
+
+impl Default for GlyphQuantization {
+    #[inline]
+    fn default() -> GlyphQuantization {
+        GlyphQuantization::None
+    }
+}
+
 struct OutlinePathBuilder {
     outline: Outline,
     current_contour: Contour,
@@ -219,3 +269,59 @@ impl FlatPathBuilder for OutlinePathBuilder {
         Point2D::new(point.x(), point.y())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{GlyphQuantization, SceneExt, TextRenderMode};
+    use font_kit::hinting::HintingOptions;
+    use font_kit::loaders::default::Font;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::Vector2F;
+    use pathfinder_renderer::paint::Paint;
+    use pathfinder_renderer::scene::Scene;
+    use skribo::{FontCollection, FontFamily, TextStyle};
+    use std::fs;
+    use std::sync::Arc;
+
+    // Lays out a single glyph at `origin` and returns the bounds of its outline.
+    fn glyph_bounds(origin: Vector2F, quantization: GlyphQuantization) -> RectF {
+        let font_data = fs::read("../resources/fonts/overpass-regular.otf").unwrap();
+        let font = Font::from_bytes(Arc::new(font_data), 0).unwrap();
+        let mut collection = FontCollection::new();
+        collection.add_family(FontFamily::new_from_font(font));
+
+        let mut scene = Scene::new();
+        let paint = scene.push_paint(&Paint::black());
+        scene.push_text("l",
+                        &TextStyle { size: 16.0 },
+                        &collection,
+                        &Transform2F::from_translation(origin),
+                        TextRenderMode::Fill,
+                        HintingOptions::None,
+                        quantization,
+                        paint).unwrap();
+        scene.path(0).outline().bounds()
+    }
+
+    #[test]
+    fn test_quantize() {
+        let origin = Vector2F::new(1.3, -2.6);
+        assert_eq!(GlyphQuantization::None.quantize(origin), origin);
+        assert_eq!(GlyphQuantization::QuarterPixelX.quantize(origin), Vector2F::new(1.25, -3.0));
+        assert_eq!(GlyphQuantization::QuarterPixelX.quantize(Vector2F::new(1.38, 0.4)),
+                   Vector2F::new(1.5, 0.0));
+        assert_eq!(GlyphQuantization::Integer.quantize(origin), Vector2F::new(1.0, -3.0));
+    }
+
+    #[test]
+    fn test_glyphs_are_placed_at_quantized_origins() {
+        let origin = Vector2F::new(10.3, 20.6);
+        assert_ne!(glyph_bounds(origin, GlyphQuantization::None),
+                   glyph_bounds(Vector2F::new(10.0, 21.0), GlyphQuantization::None));
+        assert_eq!(glyph_bounds(origin, GlyphQuantization::Integer),
+                   glyph_bounds(Vector2F::new(10.0, 21.0), GlyphQuantization::None));
+        assert_eq!(glyph_bounds(origin, GlyphQuantization::QuarterPixelX),
+                   glyph_bounds(Vector2F::new(10.25, 21.0), GlyphQuantization::None));
+    }
+}
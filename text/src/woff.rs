@@ -0,0 +1,724 @@
+// pathfinder/text/src/woff.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Converts WOFF and WOFF2 fonts to plain SFNT (TrueType or OpenType) data, which is what
+//! font-kit loads.
+//!
+//! See https://www.w3.org/TR/WOFF/ and https://www.w3.org/TR/WOFF2/.
+
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+
+const WOFF_SIGNATURE: u32 = 0x774f_4646;        // 'wOFF'
+const WOFF2_SIGNATURE: u32 = 0x774f_4632;       // 'wOF2'
+const TTC_TAG: u32 = 0x7474_6366;               // 'ttcf'
+
+const TAG_GLYF: u32 = 0x676c_7966;
+const TAG_LOCA: u32 = 0x6c6f_6361;
+const TAG_HMTX: u32 = 0x686d_7478;
+const TAG_HHEA: u32 = 0x6868_6561;
+const TAG_MAXP: u32 = 0x6d61_7870;
+
+const WOFF_HEADER_SIZE: usize = 44;
+const SFNT_HEADER_SIZE: usize = 12;
+const SFNT_TABLE_RECORD_SIZE: usize = 16;
+
+// The tags that WOFF2 table directory entries can refer to by index, in order.
+static WOFF2_KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+// Simple glyph flags, as stored in `glyf`.
+const GLYF_ON_CURVE: u8 = 0x01;
+const GLYF_X_SHORT: u8 = 0x02;
+const GLYF_Y_SHORT: u8 = 0x04;
+const GLYF_X_SAME_OR_POSITIVE: u8 = 0x10;
+const GLYF_Y_SAME_OR_POSITIVE: u8 = 0x20;
+const GLYF_OVERLAP_SIMPLE: u8 = 0x40;
+
+// Composite glyph flags.
+const COMPOSITE_ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const COMPOSITE_WE_HAVE_A_SCALE: u16 = 0x0008;
+const COMPOSITE_MORE_COMPONENTS: u16 = 0x0020;
+const COMPOSITE_WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const COMPOSITE_WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+const COMPOSITE_WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WoffError {
+    /// The data ended before a structure that it claims to contain.
+    Truncated,
+    /// The data is inconsistent with itself.
+    Malformed(&'static str),
+    /// A compressed table or block couldn't be decompressed.
+    Decompression(String),
+    /// The font uses a feature that isn't supported, such as WOFF2 font collections.
+    Unsupported(&'static str),
+}
+
+/// Converts WOFF or WOFF2 data to SFNT data. Anything else is assumed to be SFNT data already and
+/// is returned unchanged.
+pub fn decode_font_data(data: Vec<u8>) -> Result<Vec<u8>, WoffError> {
+    match Reader::new(&data).u32() {
+        Ok(WOFF_SIGNATURE) => decode_woff(&data),
+        Ok(WOFF2_SIGNATURE) => decode_woff2(&data),
+        _ => Ok(data),
+    }
+}
+
+pub fn decode_woff(data: &[u8]) -> Result<Vec<u8>, WoffError> {
+    let mut reader = Reader::new(data);
+    if reader.u32()? != WOFF_SIGNATURE {
+        return Err(WoffError::Malformed("not a WOFF font"));
+    }
+    let flavor = reader.u32()?;
+    reader.skip(4)?;                                            // length
+    let table_count = reader.u16()?;
+    reader.skip(WOFF_HEADER_SIZE - 14)?;
+
+    let mut tables = Vec::with_capacity(table_count as usize);
+    for _ in 0..table_count {
+        let tag = reader.u32()?;
+        let offset = reader.u32()? as usize;
+        let compressed_length = reader.u32()? as usize;
+        let original_length = reader.u32()? as usize;
+        reader.skip(4)?;                                        // checksum
+
+        let table_data = data.get(offset..(offset + compressed_length))
+                             .ok_or(WoffError::Truncated)?;
+        let table_data = if compressed_length < original_length {
+            inflate::inflate_bytes_zlib(table_data).map_err(WoffError::Decompression)?
+        } else if compressed_length == original_length {
+            table_data.to_vec()
+        } else {
+            return Err(WoffError::Malformed("table is larger compressed than uncompressed"));
+        };
+        if table_data.len() != original_length {
+            return Err(WoffError::Malformed("table has the wrong length when decompressed"));
+        }
+        tables.push(Table { tag, data: table_data });
+    }
+
+    Ok(build_sfnt(flavor, tables))
+}
+
+pub fn decode_woff2(data: &[u8]) -> Result<Vec<u8>, WoffError> {
+    let mut reader = Reader::new(data);
+    if reader.u32()? != WOFF2_SIGNATURE {
+        return Err(WoffError::Malformed("not a WOFF2 font"));
+    }
+    let flavor = reader.u32()?;
+    if flavor == TTC_TAG {
+        return Err(WoffError::Unsupported("WOFF2 font collections"));
+    }
+    reader.skip(4)?;                                            // length
+    let table_count = reader.u16()?;
+    reader.skip(2 + 4)?;                                        // reserved, totalSfntSize
+    let compressed_size = reader.u32()? as usize;
+    reader.skip(2 + 2 + 4 * 5)?;                                // version, metadata, private data
+
+    let mut entries = Vec::with_capacity(table_count as usize);
+    for _ in 0..table_count {
+        let flags = reader.u8()?;
+        let tag = match flags & 0x3f {
+            0x3f => reader.u32()?,
+            index => u32::from_be_bytes(*WOFF2_KNOWN_TAGS[index as usize]),
+        };
+        let original_length = reader.base128()? as usize;
+
+        // For `glyf` and `loca`, transform version 0 is the glyph transform and 3 is no
+        // transform; for every other table, version 0 is no transform.
+        let transform_version = flags >> 6;
+        let transformed = if tag == TAG_GLYF || tag == TAG_LOCA {
+            transform_version == 0
+        } else {
+            transform_version != 0
+        };
+        let length = if transformed { reader.base128()? as usize } else { original_length };
+        entries.push(Woff2TableEntry { tag, transformed, length });
+    }
+
+    let compressed = reader.bytes(compressed_size)?;
+    let mut stream = vec![];
+    brotli_decompressor::Decompressor::new(compressed, 4096)
+        .read_to_end(&mut stream)
+        .map_err(|error| WoffError::Decompression(error.to_string()))?;
+
+    // The tables are concatenated in the decompressed stream, in directory order.
+    let mut table_data = Vec::with_capacity(entries.len());
+    let mut stream_reader = Reader::new(&stream);
+    for entry in &entries {
+        table_data.push(stream_reader.bytes(entry.length)?);
+    }
+
+    let find_table = |tag: u32| entries.iter().position(|entry| entry.tag == tag);
+
+    // Reconstruct `glyf` and `loca` first, since `hmtx` may need the glyph bounding boxes.
+    let mut glyf = None;
+    if let Some(glyf_index) = find_table(TAG_GLYF) {
+        if entries[glyf_index].transformed {
+            if find_table(TAG_LOCA).is_none() {
+                return Err(WoffError::Malformed("transformed glyf table without a loca table"));
+            }
+            glyf = Some(reconstruct_glyf(table_data[glyf_index])?);
+        }
+    }
+
+    let mut tables = Vec::with_capacity(entries.len());
+    for (entry, &data) in entries.iter().zip(table_data.iter()) {
+        let data = match (entry.tag, &glyf) {
+            (TAG_GLYF, Some(glyf)) => glyf.glyf.clone(),
+            (TAG_LOCA, Some(glyf)) => glyf.loca.clone(),
+            (TAG_LOCA, None) if entry.transformed => {
+                return Err(WoffError::Malformed("transformed loca table without a glyf table"));
+            }
+            (TAG_HMTX, _) if entry.transformed => {
+                let glyf = glyf.as_ref();
+                let glyf = glyf.ok_or(WoffError::Malformed("hmtx transform without glyf"))?;
+                let hhea = find_table(TAG_HHEA).map(|index| table_data[index]);
+                let hhea = hhea.ok_or(WoffError::Malformed("hmtx transform without hhea"))?;
+                // `numberOfHMetrics` is the last field of `hhea`.
+                let metric_count = Reader::new(hhea.get(34..).ok_or(WoffError::Truncated)?).u16()?;
+                reconstruct_hmtx(data, metric_count, &glyf.x_mins)?
+            }
+            (_, _) if entry.transformed => {
+                return Err(WoffError::Unsupported("unknown table transform"));
+            }
+            (_, _) => data.to_vec(),
+        };
+        tables.push(Table { tag: entry.tag, data });
+    }
+
+    // The number of glyphs in `maxp` has to agree with `loca`, or font loaders will reject it.
+    if let (Some(glyf), Some(maxp)) = (&glyf, tables.iter().find(|table| table.tag == TAG_MAXP)) {
+        if Reader::new(maxp.data.get(4..).ok_or(WoffError::Truncated)?).u16()? as usize !=
+                glyf.x_mins.len() {
+            return Err(WoffError::Malformed("glyph count doesn't match maxp"));
+        }
+    }
+
+    Ok(build_sfnt(flavor, tables))
+}
+
+struct Table {
+    tag: u32,
+    data: Vec<u8>,
+}
+
+struct Woff2TableEntry {
+    tag: u32,
+    transformed: bool,
+    // The length of the table in the decompressed stream.
+    length: usize,
+}
+
+struct ReconstructedGlyf {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    // The minimum X coordinate of each glyph, for reconstructing left side bearings.
+    x_mins: Vec<i16>,
+}
+
+// Reverses the WOFF2 glyph transform, which splits `glyf` into separate streams for each kind of
+// data and encodes points compactly.
+fn reconstruct_glyf(data: &[u8]) -> Result<ReconstructedGlyf, WoffError> {
+    let mut header = Reader::new(data);
+    header.skip(2)?;                                            // reserved
+    let option_flags = header.u16()?;
+    let glyph_count = header.u16()? as usize;
+    let index_format = header.u16()?;
+
+    let mut offset = 36;
+    let mut next_stream = |header: &mut Reader| -> Result<Reader, WoffError> {
+        let size = header.u32()? as usize;
+        let stream = data.get(offset..(offset + size)).ok_or(WoffError::Truncated)?;
+        offset += size;
+        Ok(Reader::new(stream))
+    };
+    let mut contour_count_stream = next_stream(&mut header)?;
+    let mut point_count_stream = next_stream(&mut header)?;
+    let mut flag_stream = next_stream(&mut header)?;
+    let mut glyph_stream = next_stream(&mut header)?;
+    let mut composite_stream = next_stream(&mut header)?;
+    let mut bbox_stream = next_stream(&mut header)?;
+    let mut instruction_stream = next_stream(&mut header)?;
+
+    let bitmap_size = ((glyph_count + 31) >> 5) << 2;
+    let bbox_bitmap = bbox_stream.bytes(bitmap_size)?;
+    let overlap_bitmap = if option_flags & 1 != 0 {
+        Some(data.get(offset..(offset + ((glyph_count + 7) >> 3))).ok_or(WoffError::Truncated)?)
+    } else {
+        None
+    };
+    let bit_is_set = |bitmap: &[u8], index: usize| bitmap[index >> 3] & (0x80 >> (index & 7)) != 0;
+
+    let mut glyf = vec![];
+    let mut loca_offsets = Vec::with_capacity(glyph_count + 1);
+    let mut x_mins = Vec::with_capacity(glyph_count);
+    let mut points = vec![];
+    for glyph_index in 0..glyph_count {
+        loca_offsets.push(glyf.len());
+        let contour_count = contour_count_stream.i16()?;
+        let has_bbox = bit_is_set(bbox_bitmap, glyph_index);
+
+        if contour_count == 0 {
+            if has_bbox {
+                return Err(WoffError::Malformed("empty glyph with a bounding box"));
+            }
+            x_mins.push(0);
+            continue;
+        }
+
+        if contour_count < 0 {
+            // A composite glyph. These are stored as is, apart from the instructions.
+            if !has_bbox {
+                return Err(WoffError::Malformed("composite glyph without a bounding box"));
+            }
+            let (composite_data, has_instructions) = read_composite(&mut composite_stream)?;
+            let bbox = bbox_stream.bytes(8)?;
+            x_mins.push(Reader::new(bbox).i16()?);
+            push_i16(&mut glyf, contour_count);
+            glyf.extend_from_slice(bbox);
+            glyf.extend_from_slice(composite_data);
+            if has_instructions {
+                let instruction_length = glyph_stream.u255()?;
+                push_u16(&mut glyf, instruction_length);
+                glyf.extend_from_slice(instruction_stream.bytes(instruction_length as usize)?);
+            }
+        } else {
+            // A simple glyph.
+            let mut end_points = Vec::with_capacity(contour_count as usize);
+            let mut point_count = 0usize;
+            for _ in 0..contour_count {
+                point_count += point_count_stream.u255()? as usize;
+                if point_count == 0 || point_count > 0x10000 {
+                    return Err(WoffError::Malformed("bad contour point count"));
+                }
+                end_points.push((point_count - 1) as u16);
+            }
+
+            points.clear();
+            let (mut x, mut y) = (0i32, 0i32);
+            for _ in 0..point_count {
+                let (dx, dy, on_curve) = read_triplet(flag_stream.u8()?, &mut glyph_stream)?;
+                x += dx;
+                y += dy;
+                points.push((x, y, on_curve));
+            }
+            let instruction_length = glyph_stream.u255()?;
+
+            let bbox = if has_bbox {
+                let mut bbox = Reader::new(bbox_stream.bytes(8)?);
+                [bbox.i16()?, bbox.i16()?, bbox.i16()?, bbox.i16()?]
+            } else {
+                let x_min = points.iter().map(|point| point.0).min().unwrap_or(0);
+                let y_min = points.iter().map(|point| point.1).min().unwrap_or(0);
+                let x_max = points.iter().map(|point| point.0).max().unwrap_or(0);
+                let y_max = points.iter().map(|point| point.1).max().unwrap_or(0);
+                [x_min as i16, y_min as i16, x_max as i16, y_max as i16]
+            };
+            x_mins.push(bbox[0]);
+
+            push_i16(&mut glyf, contour_count);
+            for &value in &bbox {
+                push_i16(&mut glyf, value);
+            }
+            for &end_point in &end_points {
+                push_u16(&mut glyf, end_point);
+            }
+            push_u16(&mut glyf, instruction_length);
+            glyf.extend_from_slice(instruction_stream.bytes(instruction_length as usize)?);
+            let overlaps = match overlap_bitmap {
+                Some(bitmap) => bit_is_set(bitmap, glyph_index),
+                None => false,
+            };
+            push_points(&mut glyf, &points, overlaps);
+        }
+
+        // Keep glyphs 4-byte aligned, which also keeps them 2-byte aligned for short `loca`.
+        while glyf.len() % 4 != 0 {
+            glyf.push(0);
+        }
+    }
+    loca_offsets.push(glyf.len());
+
+    let mut loca = vec![];
+    for &loca_offset in &loca_offsets {
+        if index_format == 0 {
+            if loca_offset / 2 > 0xffff {
+                return Err(WoffError::Malformed("glyf table too large for short loca offsets"));
+            }
+            push_u16(&mut loca, (loca_offset / 2) as u16);
+        } else {
+            push_u32(&mut loca, loca_offset as u32);
+        }
+    }
+
+    Ok(ReconstructedGlyf { glyf, loca, x_mins })
+}
+
+// Returns the data for one composite glyph, minus its header, and whether it has instructions.
+fn read_composite<'a>(stream: &mut Reader<'a>) -> Result<(&'a [u8], bool), WoffError> {
+    let start = stream.offset;
+    let mut has_instructions = false;
+    loop {
+        let flags = stream.u16()?;
+        has_instructions |= flags & COMPOSITE_WE_HAVE_INSTRUCTIONS != 0;
+        let mut argument_size = 2;                              // glyph index
+        argument_size += if flags & COMPOSITE_ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & COMPOSITE_WE_HAVE_A_SCALE != 0 {
+            argument_size += 2;
+        } else if flags & COMPOSITE_WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            argument_size += 4;
+        } else if flags & COMPOSITE_WE_HAVE_A_TWO_BY_TWO != 0 {
+            argument_size += 8;
+        }
+        stream.skip(argument_size)?;
+        if flags & COMPOSITE_MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    Ok((&stream.data[start..stream.offset], has_instructions))
+}
+
+// Decodes one point delta from the glyph stream, as described by its flag byte.
+fn read_triplet(flag: u8, stream: &mut Reader) -> Result<(i32, i32, bool), WoffError> {
+    let on_curve = flag & 0x80 == 0;
+    let flag = (flag & 0x7f) as i32;
+    let with_sign = |flag: i32, value: i32| if flag & 1 != 0 { value } else { -value };
+
+    let (dx, dy) = if flag < 10 {
+        let b0 = stream.u8()? as i32;
+        (0, with_sign(flag, ((flag & 14) << 7) + b0))
+    } else if flag < 20 {
+        let b0 = stream.u8()? as i32;
+        (with_sign(flag, (((flag - 10) & 14) << 7) + b0), 0)
+    } else if flag < 84 {
+        let b0 = flag - 20;
+        let b1 = stream.u8()? as i32;
+        (with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+         with_sign(flag >> 1, 1 + ((b0 & 0x0c) << 2) + (b1 & 0x0f)))
+    } else if flag < 120 {
+        let b0 = flag - 84;
+        let (b1, b2) = (stream.u8()? as i32, stream.u8()? as i32);
+        (with_sign(flag, 1 + ((b0 / 12) << 8) + b1),
+         with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2))
+    } else if flag < 124 {
+        let (b1, b2, b3) = (stream.u8()? as i32, stream.u8()? as i32, stream.u8()? as i32);
+        (with_sign(flag, (b1 << 4) + (b2 >> 4)), with_sign(flag >> 1, ((b2 & 0x0f) << 8) + b3))
+    } else {
+        let (x, y) = (stream.u16()? as i32, stream.u16()? as i32);
+        (with_sign(flag, x), with_sign(flag >> 1, y))
+    };
+    Ok((dx, dy, on_curve))
+}
+
+// Writes the flags and coordinates of a simple glyph in the usual `glyf` encoding.
+fn push_points(glyf: &mut Vec<u8>, points: &[(i32, i32, bool)], overlaps: bool) {
+    let (mut flags, mut xs, mut ys) = (vec![], vec![], vec![]);
+    let (mut last_x, mut last_y) = (0, 0);
+    for (point_index, &(x, y, on_curve)) in points.iter().enumerate() {
+        let mut flag = if on_curve { GLYF_ON_CURVE } else { 0 };
+        if overlaps && point_index == 0 {
+            flag |= GLYF_OVERLAP_SIMPLE;
+        }
+        flag |= push_coordinate(&mut xs, x - last_x, GLYF_X_SHORT, GLYF_X_SAME_OR_POSITIVE);
+        flag |= push_coordinate(&mut ys, y - last_y, GLYF_Y_SHORT, GLYF_Y_SAME_OR_POSITIVE);
+        flags.push(flag);
+        last_x = x;
+        last_y = y;
+    }
+    glyf.extend_from_slice(&flags);
+    glyf.extend_from_slice(&xs);
+    glyf.extend_from_slice(&ys);
+}
+
+// Returns the flags that describe how the coordinate was written.
+fn push_coordinate(output: &mut Vec<u8>, delta: i32, short_flag: u8, same_or_positive_flag: u8)
+                   -> u8 {
+    if delta == 0 {
+        same_or_positive_flag
+    } else if delta > -256 && delta < 256 {
+        if delta > 0 {
+            output.push(delta as u8);
+            short_flag | same_or_positive_flag
+        } else {
+            output.push(-delta as u8);
+            short_flag
+        }
+    } else {
+        push_i16(output, delta as i16);
+        0
+    }
+}
+
+// Reverses the WOFF2 `hmtx` transform, which drops left side bearings that match the glyphs'
+// minimum X coordinates.
+fn reconstruct_hmtx(data: &[u8], metric_count: u16, x_mins: &[i16])
+                    -> Result<Vec<u8>, WoffError> {
+    let (metric_count, glyph_count) = (metric_count as usize, x_mins.len());
+    if metric_count == 0 || metric_count > glyph_count {
+        return Err(WoffError::Malformed("bad number of horizontal metrics"));
+    }
+
+    let mut reader = Reader::new(data);
+    let flags = reader.u8()?;
+    let mut advance_widths = Vec::with_capacity(metric_count);
+    for _ in 0..metric_count {
+        advance_widths.push(reader.u16()?);
+    }
+    // Flag bit 0 means the bearings of glyphs with their own advance widths were dropped, and bit
+    // 1 means the bearings of the remaining glyphs were.
+    let mut left_side_bearings = Vec::with_capacity(glyph_count);
+    for (glyph_index, &x_min) in x_mins.iter().enumerate() {
+        let dropped_flag = if glyph_index < metric_count { 1 } else { 2 };
+        left_side_bearings.push(if flags & dropped_flag != 0 { x_min } else { reader.i16()? });
+    }
+
+    let mut hmtx = Vec::with_capacity(metric_count * 2 + glyph_count * 2);
+    for (glyph_index, &left_side_bearing) in left_side_bearings.iter().enumerate() {
+        if let Some(&advance_width) = advance_widths.get(glyph_index) {
+            push_u16(&mut hmtx, advance_width);
+        }
+        push_i16(&mut hmtx, left_side_bearing);
+    }
+    Ok(hmtx)
+}
+
+// Writes the tables out with an SFNT header and table directory, sorted by tag as the spec asks.
+fn build_sfnt(flavor: u32, mut tables: Vec<Table>) -> Vec<u8> {
+    tables.sort_by_key(|table| table.tag);
+
+    let table_count = tables.len() as u16;
+    let mut entry_selector = 0;
+    while (2u16 << entry_selector) <= table_count {
+        entry_selector += 1;
+    }
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut sfnt = vec![];
+    push_u32(&mut sfnt, flavor);
+    push_u16(&mut sfnt, table_count);
+    push_u16(&mut sfnt, search_range);
+    push_u16(&mut sfnt, entry_selector);
+    push_u16(&mut sfnt, table_count * 16 - search_range);
+
+    let mut offset = SFNT_HEADER_SIZE + SFNT_TABLE_RECORD_SIZE * tables.len();
+    for table in &tables {
+        push_u32(&mut sfnt, table.tag);
+        push_u32(&mut sfnt, checksum(&table.data));
+        push_u32(&mut sfnt, offset as u32);
+        push_u32(&mut sfnt, table.data.len() as u32);
+        offset += (table.data.len() + 3) & !3;
+    }
+    for table in &tables {
+        sfnt.extend_from_slice(&table.data);
+        while sfnt.len() % 4 != 0 {
+            sfnt.push(0);
+        }
+    }
+    sfnt
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn push_u16(output: &mut Vec<u8>, value: u16) {
+    output.extend_from_slice(&value.to_be_bytes());
+}
+
+fn push_i16(output: &mut Vec<u8>, value: i16) {
+    output.extend_from_slice(&value.to_be_bytes());
+}
+
+fn push_u32(output: &mut Vec<u8>, value: u32) {
+    output.extend_from_slice(&value.to_be_bytes());
+}
+
+// A cursor over big-endian data.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, offset: 0 }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], WoffError> {
+        let end = self.offset.checked_add(length).ok_or(WoffError::Truncated)?;
+        let bytes = self.data.get(self.offset..end).ok_or(WoffError::Truncated)?;
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> Result<(), WoffError> {
+        self.bytes(length).map(drop)
+    }
+
+    fn u8(&mut self) -> Result<u8, WoffError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, WoffError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, WoffError> {
+        self.u16().map(|value| value as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, WoffError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // WOFF2's `UIntBase128`: up to five bytes, seven bits each, most significant first.
+    fn base128(&mut self) -> Result<u32, WoffError> {
+        let mut value = 0u32;
+        for byte_index in 0..5 {
+            let byte = self.u8()?;
+            if byte_index == 0 && byte == 0x80 {
+                return Err(WoffError::Malformed("UIntBase128 with leading zeros"));
+            }
+            if value & 0xfe00_0000 != 0 {
+                return Err(WoffError::Malformed("UIntBase128 overflow"));
+            }
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(WoffError::Malformed("UIntBase128 longer than five bytes"))
+    }
+
+    // WOFF2's `255UInt16`.
+    fn u255(&mut self) -> Result<u16, WoffError> {
+        const LOWEST_U_CODE: u16 = 253;
+        match self.u8()? {
+            253 => self.u16(),
+            254 => Ok(self.u8()? as u16 + LOWEST_U_CODE * 2),
+            255 => Ok(self.u8()? as u16 + LOWEST_U_CODE),
+            code => Ok(code as u16),
+        }
+    }
+}
+
+impl fmt::Display for WoffError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            WoffError::Truncated => write!(formatter, "the font data is truncated"),
+            WoffError::Malformed(message) => write!(formatter, "malformed font: {}", message),
+            WoffError::Decompression(ref message) => {
+                write!(formatter, "failed to decompress the font: {}", message)
+            }
+            WoffError::Unsupported(feature) => write!(formatter, "unsupported: {}", feature),
+        }
+    }
+}
+
+impl Error for WoffError {}
+
+#[cfg(test)]
+mod test {
+    use super::{build_sfnt, decode_font_data, reconstruct_glyf, Table};
+
+    fn push_u16(output: &mut Vec<u8>, value: u16) {
+        output.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn push_u32(output: &mut Vec<u8>, value: u32) {
+        output.extend_from_slice(&value.to_be_bytes());
+    }
+
+    #[test]
+    fn test_uncompressed_woff_round_trips() {
+        let tables = vec![
+            Table { tag: u32::from_be_bytes(*b"cmap"), data: vec![1, 2, 3, 4, 5] },
+            Table { tag: u32::from_be_bytes(*b"head"), data: vec![6, 7, 8, 9] },
+        ];
+        let sfnt = build_sfnt(0x0001_0000, tables);
+
+        // Wrap the same tables in a WOFF container, stored without compression.
+        let mut woff = vec![];
+        push_u32(&mut woff, 0x774f_4646);
+        push_u32(&mut woff, 0x0001_0000);
+        push_u32(&mut woff, 0);
+        push_u16(&mut woff, 2);
+        woff.resize(44, 0);
+        let data_offset = 44 + 20 * 2;
+        for (tag, offset, length) in &[(*b"cmap", data_offset, 5), (*b"head", data_offset + 8, 4)] {
+            push_u32(&mut woff, u32::from_be_bytes(*tag));
+            push_u32(&mut woff, *offset);
+            push_u32(&mut woff, *length);
+            push_u32(&mut woff, *length);
+            push_u32(&mut woff, 0);
+        }
+        woff.extend_from_slice(&[1, 2, 3, 4, 5, 0, 0, 0, 6, 7, 8, 9]);
+
+        assert_eq!(decode_font_data(woff).unwrap(), sfnt);
+        assert_eq!(decode_font_data(sfnt.clone()).unwrap(), sfnt);
+    }
+
+    #[test]
+    fn test_reconstruct_transformed_glyf() {
+        // One empty glyph and one triangle with points (0, 0), (100, 0), and (50, -300).
+        let contour_counts = [0, 0, 0, 1];
+        let point_counts = [3];
+        // A Y-only delta of +0, an X-only delta of +100, and then a two-byte triplet for
+        // (-50, -300). All of the points are on the curve.
+        let flags = [1, 11, 88];
+        // The triplet data, followed by the instruction length.
+        let glyphs = [0, 100, 49, 43, 0];
+        // The bounding box bitmap, with no explicit bounding boxes.
+        let bboxes = [0, 0, 0, 0];
+
+        let mut glyf = vec![];
+        push_u16(&mut glyf, 0);
+        push_u16(&mut glyf, 0);
+        push_u16(&mut glyf, 2);
+        push_u16(&mut glyf, 0);
+        for size in &[contour_counts.len(), point_counts.len(), flags.len(), glyphs.len(), 0,
+                      bboxes.len(), 0] {
+            push_u32(&mut glyf, *size as u32);
+        }
+        for stream in &[&contour_counts[..], &point_counts, &flags, &glyphs, &bboxes] {
+            glyf.extend_from_slice(stream);
+        }
+
+        let reconstructed = reconstruct_glyf(&glyf).unwrap();
+        assert_eq!(reconstructed.x_mins, vec![0, 0]);
+        assert_eq!(reconstructed.loca, vec![0, 0, 0, 0, 0, 12]);
+        assert_eq!(reconstructed.glyf, vec![
+            0, 1,                                   // contour count
+            0, 0, 0xfe, 0xd4, 0, 100, 0, 0,         // bounding box
+            0, 2,                                   // contour end points
+            0, 0,                                   // instruction length
+            0x31, 0x33, 0x03,                       // flags
+            100, 50,                                // X coordinates
+            0xfe, 0xd4,                             // Y coordinates
+            0, 0, 0,                                // padding
+        ]);
+    }
+}
@@ -51,6 +51,7 @@ pub static WINDOW_COLOR: ColorU = ColorU { r: 0,   g: 0,   b: 0,   a: 255 - 90 }
 
 static BUTTON_ICON_COLOR: ColorU = ColorU { r: 255, g: 255, b: 255, a: 255 };
 static OUTLINE_COLOR:     ColorU = ColorU { r: 255, g: 255, b: 255, a: 192 };
+static GRAPH_COLOR:       ColorU = ColorU { r: 255, g: 255, b: 255, a: 32  };
 
 static INVERTED_TEXT_COLOR: ColorU = ColorU { r: 0,   g: 0,   b: 0,   a: 255      };
 
@@ -124,38 +125,77 @@ impl<D> UIPresenter<D> where D: Device {
 
 
     pub fn draw_solid_rect(&self, device: &D, rect: RectI, color: ColorU) {
-        self.draw_rect(device, rect, color, true);
+        self.draw_rects(device, &[rect], color, true);
     }
 
     pub fn draw_rect_outline(&self, device: &D, rect: RectI, color: ColorU) {
-        self.draw_rect(device, rect, color, false);
+        self.draw_rects(device, &[rect], color, false);
     }
 
-    fn draw_rect(&self,
-                 device: &D,
-                 rect: RectI,
-                 color: ColorU,
-                 filled: bool) {
-        let vertex_data = [
-            DebugSolidVertex::new(rect.origin()),
-            DebugSolidVertex::new(rect.upper_right()),
-            DebugSolidVertex::new(rect.lower_right()),
-            DebugSolidVertex::new(rect.lower_left()),
-        ];
+    /// Draws any number of same-colored rects in one draw call.
+    pub fn draw_solid_rects(&self, device: &D, rects: &[RectI], color: ColorU) {
+        self.draw_rects(device, rects, color, true);
+    }
+
+    /// Outlines any number of same-colored rects in one draw call.
+    pub fn draw_rect_outlines(&self, device: &D, rects: &[RectI], color: ColorU) {
+        self.draw_rects(device, rects, color, false);
+    }
+
+    /// Draws a bar graph of `values` filling `rect`, with one bar per value from left to right.
+    ///
+    /// The bars are scaled so that `max_value` reaches the top of the graph; higher values are
+    /// cut off.
+    pub fn draw_bar_graph(&self,
+                          device: &D,
+                          rect: RectI,
+                          values: &[f32],
+                          max_value: f32,
+                          color: ColorU) {
+        self.draw_solid_rect(device, rect, GRAPH_COLOR);
+        if values.is_empty() || max_value <= 0.0 {
+            return;
+        }
+
+        let bar_width = i32::max(rect.width() / values.len() as i32, 1);
+        let mut bars = Vec::with_capacity(values.len());
+        for (index, &value) in values.iter().enumerate() {
+            let height = f32::min(value / max_value, 1.0) * rect.height() as f32;
+            let height = height.round() as i32;
+            if height <= 0 {
+                continue;
+            }
+            let origin = Vector2I::new(rect.min_x() + index as i32 * bar_width,
+                                       rect.max_y() - height);
+            bars.push(RectI::new(origin, Vector2I::new(bar_width, height)));
+        }
+        self.draw_solid_rects(device, &bars, color);
+    }
 
-        if filled {
-            self.draw_solid_rects_with_vertex_data(device,
-                                                   &vertex_data,
-                                                   &QUAD_INDICES,
-                                                   color,
-                                                   true);
-        } else {
-            self.draw_solid_rects_with_vertex_data(device,
-                                                   &vertex_data,
-                                                   &RECT_LINE_INDICES,
-                                                   color,
-                                                   false);
+    fn draw_rects(&self,
+                  device: &D,
+                  rects: &[RectI],
+                  color: ColorU,
+                  filled: bool) {
+        if rects.is_empty() {
+            return;
         }
+
+        let indices: &[u32] = if filled { &QUAD_INDICES } else { &RECT_LINE_INDICES };
+        let mut vertex_data = Vec::with_capacity(rects.len() * 4);
+        let mut index_data = Vec::with_capacity(rects.len() * indices.len());
+        for rect in rects {
+            let first_vertex_index = vertex_data.len() as u32;
+            vertex_data.extend_from_slice(&[
+                DebugSolidVertex::new(rect.origin()),
+                DebugSolidVertex::new(rect.upper_right()),
+                DebugSolidVertex::new(rect.lower_right()),
+                DebugSolidVertex::new(rect.lower_left()),
+            ]);
+            index_data.extend(indices.iter().map(|&index| index + first_vertex_index));
+        }
+
+        self.draw_solid_rects_with_vertex_data(device, &vertex_data, &index_data, color, filled);
     }
 
     fn draw_solid_rects_with_vertex_data(&self,
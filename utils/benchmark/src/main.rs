@@ -0,0 +1,548 @@
+// pathfinder/utils/benchmark/src/main.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Benchmarks Pathfinder on a corpus of SVG files without opening a window.
+//!
+//! Each scene is rendered a number of times to warm up caches and drivers, and then a number of
+//! times more while measuring. The build time, CPU frame time, GPU time, and render statistics of
+//! every scene are written out as CSV or JSON, so that results can be compared across revisions.
+
+#[macro_use]
+extern crate serde_derive;
+
+use clap::{App, Arg, ArgMatches};
+use pathfinder_color::ColorF;
+use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use pathfinder_gpu::{Device, RenderTarget, TextureFormat};
+use pathfinder_renderer::concurrent::rayon::RayonExecutor;
+use pathfinder_renderer::concurrent::scene_proxy::SceneProxy;
+use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererOptions};
+use pathfinder_renderer::gpu::renderer::{RenderStats, RenderTime, Renderer};
+use pathfinder_renderer::gpu_data::RenderCommand;
+use pathfinder_renderer::options::{BuildOptions, RenderTransform};
+use pathfinder_renderer::scene::Scene;
+use pathfinder_resources::fs::FilesystemResourceLoader;
+use pathfinder_svg::BuiltSVG;
+use pathfinder_svg::text;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+use usvg::Tree;
+
+#[cfg(target_os = "macos")]
+static BACKENDS: [&str; 2] = ["gl", "metal"];
+#[cfg(not(target_os = "macos"))]
+static BACKENDS: [&str; 1] = ["gl"];
+
+// How long to wait for a frame's GPU timer queries before concluding that the device doesn't
+// support them.
+const GPU_TIME_TIMEOUT: Duration = Duration::from_secs(1);
+
+fn main() {
+    let matches = App::new("pathfinder_benchmark")
+        .about("Renders a corpus of SVG files repeatedly and reports timings for each")
+        .arg(
+            Arg::with_name("warmup")
+                .short("w")
+                .long("warmup")
+                .value_name("FRAMES")
+                .takes_value(true)
+                .default_value("5")
+                .help("The number of frames to render before measuring"),
+        )
+        .arg(
+            Arg::with_name("frames")
+                .short("n")
+                .long("frames")
+                .value_name("FRAMES")
+                .takes_value(true)
+                .default_value("20")
+                .help("The number of frames to measure"),
+        )
+        .arg(
+            Arg::with_name("size")
+                .short("s")
+                .long("size")
+                .value_name("WIDTHxHEIGHT")
+                .takes_value(true)
+                .help("The size to render every scene at [default: the size of each SVG]"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["csv", "json"])
+                .default_value("csv")
+                .help("The format to write results in"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("The file to write results to [default: standard output]"),
+        )
+        .arg(
+            Arg::with_name("backend")
+                .long("backend")
+                .takes_value(true)
+                .possible_values(&BACKENDS)
+                .default_value("gl")
+                .help("The GPU API to render with"),
+        )
+        .arg(
+            Arg::with_name("INPUT")
+                .help("SVG files, or directories of SVG files, to benchmark")
+                .required(true)
+                .multiple(true),
+        )
+        .get_matches();
+
+    if let Err(message) = run(&matches) {
+        eprintln!("pathfinder_benchmark: {}", message);
+        process::exit(1);
+    }
+}
+
+struct BenchmarkOptions {
+    warmup_frames: u32,
+    measured_frames: u32,
+    size: Option<Vector2I>,
+}
+
+/// The results for one scene. Times are in milliseconds.
+#[derive(Serialize)]
+struct SceneResult {
+    scene: String,
+    width: i32,
+    height: i32,
+    frames: u32,
+    build_time_mean: f64,
+    build_time_min: f64,
+    frame_time_mean: f64,
+    frame_time_min: f64,
+    /// `None` if the device doesn't support timer queries.
+    gpu_time_mean: Option<f64>,
+    gpu_time_min: Option<f64>,
+    path_count: usize,
+    fill_count: usize,
+    alpha_tile_count: usize,
+    solid_tile_count: usize,
+    culled_path_count: usize,
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let options = BenchmarkOptions {
+        warmup_frames: parse_frame_count(matches.value_of("warmup").unwrap(), 0)?,
+        measured_frames: parse_frame_count(matches.value_of("frames").unwrap(), 1)?,
+        size: match matches.value_of("size") {
+            None => None,
+            Some(size) => Some(parse_size(size)?),
+        },
+    };
+
+    let mut scene_paths = vec![];
+    for input in matches.values_of("INPUT").unwrap() {
+        collect_scene_paths(Path::new(input), &mut scene_paths)?;
+    }
+    if scene_paths.is_empty() {
+        return Err("no SVG files to benchmark".to_owned());
+    }
+
+    let results = match matches.value_of("backend").unwrap() {
+        #[cfg(target_os = "macos")]
+        "metal" => benchmark_with_metal(&scene_paths, &options)?,
+        _ => benchmark_with_gl(&scene_paths, &options)?,
+    };
+
+    let mut writer: Box<dyn Write> = match matches.value_of("output") {
+        None => Box::new(io::stdout()),
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(|error| format!("failed to create `{}`: {}", path, error))?;
+            Box::new(BufWriter::new(file))
+        }
+    };
+    let result = match matches.value_of("format").unwrap() {
+        "json" => write_json(&mut writer, &results),
+        _ => write_csv(&mut writer, &results),
+    };
+    result.and_then(|()| writer.flush())
+          .map_err(|error| format!("failed to write results: {}", error))
+}
+
+fn benchmark_with_gl(scene_paths: &[PathBuf], options: &BenchmarkOptions)
+                     -> Result<Vec<SceneResult>, String> {
+    use pathfinder_gl::headless::HeadlessContext;
+
+    let context = HeadlessContext::new().map_err(|error| error.to_string())?;
+    benchmark(context.create_device(), scene_paths, options)
+}
+
+#[cfg(target_os = "macos")]
+fn benchmark_with_metal(scene_paths: &[PathBuf], options: &BenchmarkOptions)
+                        -> Result<Vec<SceneResult>, String> {
+    use metal::{CoreAnimationLayer, MTLPixelFormat};
+    use pathfinder_metal::MetalDevice;
+
+    // Create a layer that is never attached to a window, just so that we have a device to use.
+    let metal_device = metal::Device::system_default().expect("No Metal device found!");
+    let layer = CoreAnimationLayer::new();
+    layer.set_device(&metal_device);
+    layer.set_pixel_format(MTLPixelFormat::BGRA8Unorm);
+
+    benchmark(MetalDevice::new(&layer), scene_paths, options)
+}
+
+fn benchmark<D>(device: D, scene_paths: &[PathBuf], options: &BenchmarkOptions)
+                -> Result<Vec<SceneResult>, String>
+                where D: Device {
+    // Create the renderer once, so that compiling shaders doesn't count against the first scene.
+    let framebuffer = create_framebuffer(&device, Vector2I::splat(1));
+    let resource_loader = FilesystemResourceLoader::locate();
+    let mut renderer = Renderer::new(device,
+                                     &resource_loader,
+                                     DestFramebuffer::Other(framebuffer),
+                                     RendererOptions {
+                                         background_color: Some(ColorF::white()),
+                                         ..RendererOptions::default()
+                                     });
+
+    let mut results = vec![];
+    for scene_path in scene_paths {
+        let (scene, transform, size) = load_scene(scene_path, options.size)?;
+        let framebuffer = create_framebuffer(&renderer.device, size);
+        renderer.replace_dest_framebuffer(DestFramebuffer::Other(framebuffer));
+        let scene_proxy = SceneProxy::from_scene(scene, RayonExecutor);
+
+        for _ in 0..options.warmup_frames {
+            render_frame(&mut renderer, &scene_proxy, transform)?;
+        }
+
+        let mut frames = vec![];
+        for _ in 0..options.measured_frames {
+            frames.push(render_frame(&mut renderer, &scene_proxy, transform)?);
+        }
+
+        results.push(SceneResult::new(scene_path, size, &frames));
+    }
+    Ok(results)
+}
+
+struct FrameResult {
+    build_time: Duration,
+    frame_time: Duration,
+    gpu_time: Option<Duration>,
+    stats: RenderStats,
+}
+
+// Builds and renders the scene, and then waits for the GPU to finish.
+fn render_frame<D>(renderer: &mut Renderer<D>, scene_proxy: &SceneProxy, transform: Transform2F)
+                   -> Result<FrameResult, String>
+                   where D: Device {
+    let start_time = Instant::now();
+    let mut build_time = Duration::default();
+
+    renderer.begin_scene();
+    let build_options = BuildOptions {
+        transform: RenderTransform::Transform2D(transform),
+        ..BuildOptions::default()
+    };
+    for command in scene_proxy.build_with_stream(build_options) {
+        // The benchmark stops at the first error, so the scene needn't be ended.
+        let command = command.map_err(|error| format!("failed to build the scene: {}", error))?;
+        renderer.render_command(&command).map_err(|error| format!("failed to render: {}", error))?;
+        if let RenderCommand::Finish { build_time: command_build_time, .. } = command {
+            build_time = command_build_time;
+        }
+    }
+    renderer.end_scene();
+
+    // Reading a pixel back blocks until all the rendering commands have been executed.
+    let texture_data_receiver = match *renderer.dest_framebuffer() {
+        DestFramebuffer::Other(ref framebuffer) => {
+            let rect = RectI::new(Vector2I::default(), Vector2I::splat(1));
+            renderer.device.read_pixels(&RenderTarget::Framebuffer(framebuffer), rect)
+        }
+        DestFramebuffer::Default { .. } |
+        DestFramebuffer::ForeignTarget { .. } |
+        DestFramebuffer::Layers(_) => unreachable!(),
+    };
+    renderer.device.recv_texture_data(&texture_data_receiver);
+    let frame_time = Instant::now() - start_time;
+
+    let gpu_time = wait_for_rendering_time(renderer).map(|time| time.stage_0 + time.stage_1);
+    Ok(FrameResult { build_time, frame_time, gpu_time, stats: renderer.stats })
+}
+
+fn wait_for_rendering_time<D>(renderer: &mut Renderer<D>) -> Option<RenderTime>
+                              where D: Device {
+    let start_time = Instant::now();
+    loop {
+        if let Some(rendering_time) = renderer.shift_rendering_time() {
+            return Some(rendering_time);
+        }
+        if Instant::now() - start_time > GPU_TIME_TIMEOUT {
+            return None;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+impl SceneResult {
+    fn new(scene_path: &Path, size: Vector2I, frames: &[FrameResult]) -> SceneResult {
+        let build_times: Vec<_> = frames.iter().map(|frame| frame.build_time).collect();
+        let frame_times: Vec<_> = frames.iter().map(|frame| frame.frame_time).collect();
+        let gpu_times: Option<Vec<_>> = frames.iter().map(|frame| frame.gpu_time).collect();
+        let stats = frames.last().unwrap().stats;
+        SceneResult {
+            scene: scene_path.display().to_string(),
+            width: size.x(),
+            height: size.y(),
+            frames: frames.len() as u32,
+            build_time_mean: mean_ms(&build_times),
+            build_time_min: min_ms(&build_times),
+            frame_time_mean: mean_ms(&frame_times),
+            frame_time_min: min_ms(&frame_times),
+            gpu_time_mean: gpu_times.as_ref().map(|gpu_times| mean_ms(gpu_times)),
+            gpu_time_min: gpu_times.as_ref().map(|gpu_times| min_ms(gpu_times)),
+            path_count: stats.path_count,
+            fill_count: stats.fill_count,
+            alpha_tile_count: stats.alpha_tile_count,
+            solid_tile_count: stats.solid_tile_count,
+            culled_path_count: stats.culled_path_count,
+        }
+    }
+}
+
+fn mean_ms(times: &[Duration]) -> f64 {
+    let total: Duration = times.iter().sum();
+    total.as_secs_f64() * 1000.0 / times.len() as f64
+}
+
+fn min_ms(times: &[Duration]) -> f64 {
+    times.iter().min().unwrap().as_secs_f64() * 1000.0
+}
+
+fn write_json(writer: &mut dyn Write, results: &[SceneResult]) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *writer, results)?;
+    writeln!(writer)
+}
+
+fn write_csv(writer: &mut dyn Write, results: &[SceneResult]) -> io::Result<()> {
+    writeln!(writer,
+             "scene,width,height,frames,build_time_mean,build_time_min,frame_time_mean,\
+              frame_time_min,gpu_time_mean,gpu_time_min,path_count,fill_count,alpha_tile_count,\
+              solid_tile_count,culled_path_count")?;
+    for result in results {
+        writeln!(writer,
+                 "{},{},{},{},{:.3},{:.3},{:.3},{:.3},{},{},{},{},{},{},{}",
+                 csv_field(&result.scene),
+                 result.width,
+                 result.height,
+                 result.frames,
+                 result.build_time_mean,
+                 result.build_time_min,
+                 result.frame_time_mean,
+                 result.frame_time_min,
+                 optional_csv_time(result.gpu_time_mean),
+                 optional_csv_time(result.gpu_time_min),
+                 result.path_count,
+                 result.fill_count,
+                 result.alpha_tile_count,
+                 result.solid_tile_count,
+                 result.culled_path_count)?;
+    }
+    Ok(())
+}
+
+fn csv_field(field: &str) -> String {
+    if !field.contains(&[',', '"', '\n'][..]) {
+        return field.to_owned();
+    }
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+fn optional_csv_time(time: Option<f64>) -> String {
+    match time {
+        None => String::new(),
+        Some(time) => format!("{:.3}", time),
+    }
+}
+
+// Adds `path` if it's a file, or the SVG files in it, in name order, if it's a directory.
+fn collect_scene_paths(path: &Path, scene_paths: &mut Vec<PathBuf>) -> Result<(), String> {
+    if !path.is_dir() {
+        scene_paths.push(path.to_owned());
+        return Ok(());
+    }
+
+    let entries = fs::read_dir(path)
+        .map_err(|error| format!("failed to read `{}`: {}", path.display(), error))?;
+    let mut directory_scene_paths = vec![];
+    for entry in entries {
+        let entry_path = entry.map_err(|error| error.to_string())?.path();
+        if entry_path.extension().and_then(|extension| extension.to_str()) == Some("svg") {
+            directory_scene_paths.push(entry_path);
+        }
+    }
+    directory_scene_paths.sort();
+    scene_paths.extend(directory_scene_paths);
+    Ok(())
+}
+
+// Loads an SVG, and returns it along with the transform that fits it to the render size.
+fn load_scene(path: &Path, size: Option<Vector2I>)
+              -> Result<(Scene, Transform2F, Vector2I), String> {
+    let tree = Tree::from_file(path, &text::usvg_options_with_system_fonts())
+        .map_err(|error| format!("failed to parse `{}`: {}", path.display(), error))?;
+    let mut scene = BuiltSVG::from_tree(&tree).scene;
+
+    let view_box = scene.view_box();
+    let size = size.unwrap_or_else(|| view_box.size().ceil().to_i32());
+    if size.x() <= 0 || size.y() <= 0 {
+        return Err(format!("`{}` is empty", path.display()));
+    }
+
+    let transform = Transform2F::from_scale(size.to_f32() / view_box.size()) *
+        Transform2F::from_translation(-view_box.origin());
+    scene.set_view_box(RectF::new(Vector2F::default(), size.to_f32()));
+    Ok((scene, transform, size))
+}
+
+fn create_framebuffer<D>(device: &D, size: Vector2I) -> D::Framebuffer where D: Device {
+    let texture = device.create_texture(TextureFormat::RGBA8, size);
+    device.create_framebuffer(texture)
+}
+
+fn parse_frame_count(count: &str, min: u32) -> Result<u32, String> {
+    count.parse::<u32>()
+         .ok()
+         .filter(|count| *count >= min)
+         .ok_or_else(|| format!("invalid frame count `{}`", count))
+}
+
+fn parse_size(size: &str) -> Result<Vector2I, String> {
+    let mut dimensions = size.splitn(2, &['x', 'X'][..]).map(|dimension| {
+        dimension.trim().parse::<i32>().ok()
+    });
+    match (dimensions.next(), dimensions.next()) {
+        (Some(Some(width)), Some(Some(height))) => Ok(Vector2I::new(width, height)),
+        _ => Err(format!("invalid size `{}`; expected WIDTHxHEIGHT", size)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FrameResult, SceneResult, collect_scene_paths, parse_frame_count, parse_size};
+    use super::{write_csv, write_json};
+    use pathfinder_geometry::vector::Vector2I;
+    use pathfinder_renderer::gpu::renderer::RenderStats;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use std::str;
+    use std::time::Duration;
+
+    fn frame(build_ms: u64, frame_ms: u64, gpu_ms: Option<u64>) -> FrameResult {
+        FrameResult {
+            build_time: Duration::from_millis(build_ms),
+            frame_time: Duration::from_millis(frame_ms),
+            gpu_time: gpu_ms.map(Duration::from_millis),
+            stats: RenderStats { path_count: 3, fill_count: 40, ..RenderStats::default() },
+        }
+    }
+
+    #[test]
+    fn test_scene_results_summarize_frames() {
+        let frames = [frame(2, 10, Some(4)), frame(4, 6, Some(2))];
+        let result = SceneResult::new(Path::new("a.svg"), Vector2I::new(640, 480), &frames);
+        assert_eq!((result.width, result.height, result.frames), (640, 480, 2));
+        assert_eq!((result.build_time_mean, result.build_time_min), (3.0, 2.0));
+        assert_eq!((result.frame_time_mean, result.frame_time_min), (8.0, 6.0));
+        assert_eq!((result.gpu_time_mean, result.gpu_time_min), (Some(3.0), Some(2.0)));
+        assert_eq!((result.path_count, result.fill_count), (3, 40));
+
+        // A single frame without a GPU time means the device can't measure it.
+        let frames = [frame(2, 10, Some(4)), frame(4, 6, None)];
+        let result = SceneResult::new(Path::new("a.svg"), Vector2I::new(640, 480), &frames);
+        assert_eq!((result.gpu_time_mean, result.gpu_time_min), (None, None));
+    }
+
+    #[test]
+    fn test_csv_output() {
+        let size = Vector2I::splat(8);
+        let results = [
+            SceneResult::new(Path::new("plain.svg"), size, &[frame(1, 2, Some(3))]),
+            SceneResult::new(Path::new("a \"b\",c.svg"), size, &[frame(1, 2, None)]),
+        ];
+        let mut output = vec![];
+        write_csv(&mut output, &results).unwrap();
+
+        let lines: Vec<_> = str::from_utf8(&output).unwrap().lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].split(',').count(), 15);
+        assert_eq!(lines[1], "plain.svg,8,8,1,1.000,1.000,2.000,2.000,3.000,3.000,3,40,0,0,0");
+        assert_eq!(lines[2], "\"a \"\"b\"\",c.svg\",8,8,1,1.000,1.000,2.000,2.000,,,3,40,0,0,0");
+    }
+
+    #[test]
+    fn test_json_output() {
+        let size = Vector2I::splat(8);
+        let results = [SceneResult::new(Path::new("a.svg"), size, &[frame(1, 2, None)])];
+        let mut output = vec![];
+        write_json(&mut output, &results).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let result = &json.as_array().unwrap()[0];
+        assert_eq!(result["scene"], "a.svg");
+        assert_eq!(result["frame_time_mean"], 2.0);
+        assert!(result["gpu_time_mean"].is_null());
+        assert_eq!(result["fill_count"], 40);
+    }
+
+    #[test]
+    fn test_directories_contribute_their_svgs_in_name_order() {
+        let directory_name = format!("pathfinder-benchmark-{}", std::process::id());
+        let directory = env::temp_dir().join(directory_name);
+        fs::create_dir_all(&directory).unwrap();
+        for name in &["b.svg", "a.svg", "notes.txt"] {
+            fs::write(directory.join(name), "").unwrap();
+        }
+
+        let mut scene_paths = vec![];
+        collect_scene_paths(Path::new("first.svg"), &mut scene_paths).unwrap();
+        collect_scene_paths(&directory, &mut scene_paths).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(scene_paths,
+                   vec![Path::new("first.svg").to_owned(),
+                        directory.join("a.svg"),
+                        directory.join("b.svg")]);
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        assert_eq!(parse_size("640x480"), Ok(Vector2I::new(640, 480)));
+        assert_eq!(parse_size("64 X 32"), Ok(Vector2I::new(64, 32)));
+        assert!(parse_size("640").is_err());
+        assert!(parse_size("640x").is_err());
+
+        assert_eq!(parse_frame_count("0", 0), Ok(0));
+        assert!(parse_frame_count("0", 1).is_err());
+        assert!(parse_frame_count("-1", 0).is_err());
+    }
+}
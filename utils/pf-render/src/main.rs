@@ -0,0 +1,383 @@
+// pathfinder/utils/pf-render/src/main.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Renders an SVG file or a serialized scene to a PNG image without opening a window.
+//!
+//! This exercises the whole stack (SVG import, scene building, and GPU rendering), so it doubles
+//! as an end-to-end smoke test and as a convenient way to produce images for bug reports. See the
+//! `scene_file` module for the serialized scene format.
+
+#[macro_use]
+extern crate serde_derive;
+
+use clap::{App, Arg, ArgMatches};
+use image::ColorType;
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use pathfinder_gpu::{Device, RenderTarget, TextureData, TextureFormat};
+use pathfinder_renderer::concurrent::rayon::RayonExecutor;
+use pathfinder_renderer::concurrent::scene_proxy::SceneProxy;
+use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererOptions};
+use pathfinder_renderer::gpu::renderer::Renderer;
+use pathfinder_renderer::options::{BuildOptions, RenderTransform};
+use pathfinder_renderer::scene::Scene;
+use pathfinder_resources::fs::FilesystemResourceLoader;
+use pathfinder_svg::BuiltSVG;
+use pathfinder_svg::text;
+use std::path::Path;
+use std::process;
+use usvg::Tree;
+
+mod scene_file;
+
+#[cfg(target_os = "macos")]
+static BACKENDS: [&str; 2] = ["gl", "metal"];
+#[cfg(not(target_os = "macos"))]
+static BACKENDS: [&str; 1] = ["gl"];
+
+static FORMATS: [&str; 2] = ["svg", "scene"];
+
+fn main() {
+    let matches = app().get_matches();
+    if let Err(message) = Options::from_matches(&matches).and_then(|options| run(&options)) {
+        eprintln!("pf-render: {}", message);
+        process::exit(1);
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    App::new("pf-render")
+        .about("Renders an SVG file or a serialized scene to a PNG image without opening a window")
+        .arg(
+            Arg::with_name("size")
+                .short("s")
+                .long("size")
+                .value_name("WIDTHxHEIGHT")
+                .takes_value(true)
+                .help("The output image size in pixels [default: the view box size × scale]"),
+        )
+        .arg(
+            Arg::with_name("scale")
+                .short("x")
+                .long("scale")
+                .value_name("FACTOR")
+                .takes_value(true)
+                .help("The factor by which to scale the view box size [default: 1]")
+                .conflicts_with("size"),
+        )
+        .arg(
+            Arg::with_name("background")
+                .short("b")
+                .long("background")
+                .value_name("COLOR")
+                .takes_value(true)
+                .help("The background color, as `#rrggbb`, `#rrggbbaa`, or `transparent` \
+                       [default: #ffffff]"),
+        )
+        .arg(
+            Arg::with_name("backend")
+                .long("backend")
+                .takes_value(true)
+                .possible_values(&BACKENDS)
+                .default_value("gl")
+                .help("The GPU API to render with"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&FORMATS)
+                .help("The format of the input [default: `scene` for `.json` files, else `svg`]"),
+        )
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Path to the SVG file or serialized scene to render")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path to the PNG file to write")
+                .required(true)
+                .index(2),
+        )
+}
+
+// The validated command line.
+#[derive(Debug, PartialEq)]
+struct Options {
+    input_path: String,
+    output_path: String,
+    // The output size, if given explicitly rather than by scaling the view box size.
+    size: Option<Vector2I>,
+    scale: f32,
+    background_color: ColorU,
+    backend: String,
+    format: InputFormat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum InputFormat {
+    Svg,
+    // A JSON scene, as read by `scene_file`.
+    Scene,
+}
+
+impl Options {
+    fn from_matches(matches: &ArgMatches) -> Result<Options, String> {
+        let scale = match matches.value_of("scale") {
+            None => 1.0,
+            Some(scale) => {
+                scale.parse::<f32>()
+                     .ok()
+                     .filter(|scale| *scale > 0.0)
+                     .ok_or_else(|| format!("invalid scale `{}`", scale))?
+            }
+        };
+        let size = match matches.value_of("size") {
+            None => None,
+            Some(size) => Some(parse_size(size)?),
+        };
+        let background_color = match matches.value_of("background") {
+            None => ColorU::white(),
+            Some(color) => parse_color(color)?,
+        };
+        let input_path = matches.value_of("INPUT").unwrap();
+        let format = match matches.value_of("format") {
+            Some("scene") => InputFormat::Scene,
+            Some(_) => InputFormat::Svg,
+            None => match Path::new(input_path).extension() {
+                Some(extension) if extension.eq_ignore_ascii_case("json") => InputFormat::Scene,
+                _ => InputFormat::Svg,
+            },
+        };
+
+        Ok(Options {
+            input_path: input_path.to_owned(),
+            output_path: matches.value_of("OUTPUT").unwrap().to_owned(),
+            size,
+            scale,
+            background_color,
+            backend: matches.value_of("backend").unwrap().to_owned(),
+            format,
+        })
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let input_path = &options.input_path;
+    let output_path = &options.output_path;
+
+    let mut scene = match options.format {
+        InputFormat::Svg => {
+            let tree = Tree::from_file(input_path, &text::usvg_options_with_system_fonts())
+                .map_err(|error| format!("failed to parse `{}`: {}", input_path, error))?;
+            BuiltSVG::from_tree(&tree).scene
+        }
+        InputFormat::Scene => scene_file::load(input_path)?,
+    };
+
+    // Fit the view box to the output image.
+    let view_box = scene.view_box();
+    let size = match options.size {
+        None => view_box.size().scale(options.scale).ceil().to_i32(),
+        Some(size) => size,
+    };
+    if size.x() <= 0 || size.y() <= 0 {
+        return Err(format!("output size {}x{} is empty", size.x(), size.y()));
+    }
+
+    let transform = Transform2F::from_scale(size.to_f32() / view_box.size()) *
+        Transform2F::from_translation(-view_box.origin());
+    scene.set_view_box(RectF::new(Vector2F::default(), size.to_f32()));
+
+    let background_color = options.background_color.to_f32();
+    let pixels = match &*options.backend {
+        #[cfg(target_os = "macos")]
+        "metal" => render_with_metal(scene, transform, size, background_color)?,
+        _ => render_with_gl(scene, transform, size, background_color)?,
+    };
+
+    image::save_buffer(Path::new(output_path),
+                       &pixels,
+                       size.x() as u32,
+                       size.y() as u32,
+                       ColorType::RGBA(8))
+        .map_err(|error| format!("failed to write `{}`: {}", output_path, error))
+}
+
+fn render_with_gl(scene: Scene, transform: Transform2F, size: Vector2I, background_color: ColorF)
+                  -> Result<Vec<u8>, String> {
+    use pathfinder_gl::headless::HeadlessContext;
+
+    // Create a GL context with no window or display attached.
+    let context = HeadlessContext::new().map_err(|error| error.to_string())?;
+    render(context.create_device(), scene, transform, size, background_color)
+}
+
+#[cfg(target_os = "macos")]
+fn render_with_metal(scene: Scene,
+                     transform: Transform2F,
+                     size: Vector2I,
+                     background_color: ColorF)
+                     -> Result<Vec<u8>, String> {
+    use metal::{CGSize, CoreAnimationLayer, MTLPixelFormat};
+    use pathfinder_metal::MetalDevice;
+
+    // Create a layer that is never attached to a window, just so that we have a device to use.
+    let metal_device = metal::Device::system_default()
+        .ok_or_else(|| "no Metal device found".to_owned())?;
+    let layer = CoreAnimationLayer::new();
+    layer.set_device(&metal_device);
+    layer.set_pixel_format(MTLPixelFormat::BGRA8Unorm);
+    layer.set_drawable_size(CGSize::new(size.x() as f64, size.y() as f64));
+
+    render(MetalDevice::new(&layer), scene, transform, size, background_color)
+}
+
+// Renders the scene into an offscreen framebuffer and reads back the RGBA pixels.
+fn render<D>(device: D,
+             scene: Scene,
+             transform: Transform2F,
+             size: Vector2I,
+             background_color: ColorF)
+             -> Result<Vec<u8>, String>
+             where D: Device {
+    let texture = device.create_texture(TextureFormat::RGBA8, size);
+    let framebuffer = device.create_framebuffer(texture);
+
+    let resource_loader = FilesystemResourceLoader::locate();
+    let mut renderer = Renderer::new(device,
+                                     &resource_loader,
+                                     DestFramebuffer::Other(framebuffer),
+                                     RendererOptions {
+                                         background_color: Some(background_color),
+                                         ..RendererOptions::default()
+                                     });
+
+    let scene_proxy = SceneProxy::from_scene(scene, RayonExecutor);
+    scene_proxy.build_and_render(&mut renderer, BuildOptions {
+        transform: RenderTransform::Transform2D(transform),
+        ..BuildOptions::default()
+    }).map_err(|error| format!("failed to render: {}", error))?;
+
+    let viewport = RectI::new(Vector2I::default(), size);
+    let texture_data_receiver = match *renderer.dest_framebuffer() {
+        DestFramebuffer::Other(ref framebuffer) => {
+            renderer.device.read_pixels(&RenderTarget::Framebuffer(framebuffer), viewport)
+        }
+        DestFramebuffer::Default { .. } |
+        DestFramebuffer::ForeignTarget { .. } |
+        DestFramebuffer::Layers(_) => unreachable!(),
+    };
+    match renderer.device.recv_texture_data(&texture_data_receiver) {
+        TextureData::U8(pixels) => Ok(pixels),
+        _ => panic!("Unexpected pixel format for an RGBA8 framebuffer!"),
+    }
+}
+
+fn parse_size(size: &str) -> Result<Vector2I, String> {
+    let mut dimensions = size.splitn(2, ['x', 'X']).map(|dimension| {
+        dimension.trim().parse::<i32>().ok()
+    });
+    match (dimensions.next(), dimensions.next()) {
+        (Some(Some(width)), Some(Some(height))) if width > 0 && height > 0 => {
+            Ok(Vector2I::new(width, height))
+        }
+        _ => Err(format!("invalid size `{}`; expected WIDTHxHEIGHT", size)),
+    }
+}
+
+fn parse_color(color: &str) -> Result<ColorU, String> {
+    if color == "transparent" {
+        return Ok(ColorU::transparent_black());
+    }
+
+    let hex = color.trim_start_matches('#');
+    let value = u32::from_str_radix(hex, 16).ok();
+    match (hex.len(), value) {
+        (6, Some(rgb)) => Ok(ColorU::from_u32((rgb << 8) | 0xff)),
+        (8, Some(rgba)) => Ok(ColorU::from_u32(rgba)),
+        _ => Err(format!("invalid color `{}`; expected #rrggbb or #rrggbbaa", color)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{InputFormat, Options, app};
+    use pathfinder_color::ColorU;
+    use pathfinder_geometry::vector::Vector2I;
+    use std::iter;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        let args = iter::once("pf-render").chain(args.iter().cloned());
+        let matches = app().get_matches_from_safe(args).map_err(|error| error.message)?;
+        Options::from_matches(&matches)
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(parse(&["in.svg", "out.png"]), Ok(Options {
+            input_path: "in.svg".to_owned(),
+            output_path: "out.png".to_owned(),
+            size: None,
+            scale: 1.0,
+            background_color: ColorU::white(),
+            backend: "gl".to_owned(),
+            format: InputFormat::Svg,
+        }));
+    }
+
+    #[test]
+    fn test_format() {
+        let format = |args: &[&str]| parse(args).unwrap().format;
+        assert_eq!(format(&["scene.json", "out.png"]), InputFormat::Scene);
+        assert_eq!(format(&["SCENE.JSON", "out.png"]), InputFormat::Scene);
+        assert_eq!(format(&["in.svgz", "out.png"]), InputFormat::Svg);
+        assert_eq!(format(&["--format", "scene", "in.txt", "out.png"]), InputFormat::Scene);
+        assert_eq!(format(&["-f", "svg", "in.json", "out.png"]), InputFormat::Svg);
+        assert!(parse(&["--format", "pdf", "in.pdf", "out.png"]).is_err());
+    }
+
+    #[test]
+    fn test_size_and_scale() {
+        let options = parse(&["--size", "640x480", "in.svg", "out.png"]).unwrap();
+        assert_eq!(options.size, Some(Vector2I::new(640, 480)));
+        assert_eq!(parse(&["-x", "2.5", "in.svg", "out.png"]).unwrap().scale, 2.5);
+
+        assert!(parse(&["--size", "640", "in.svg", "out.png"]).is_err());
+        assert!(parse(&["--size", "0x480", "in.svg", "out.png"]).is_err());
+        assert!(parse(&["--scale", "0", "in.svg", "out.png"]).is_err());
+        assert!(parse(&["--scale", "big", "in.svg", "out.png"]).is_err());
+        assert!(parse(&["-s", "64x64", "-x", "2", "in.svg", "out.png"]).is_err());
+    }
+
+    #[test]
+    fn test_background() {
+        let background = |color| parse(&["-b", color, "in.svg", "out.png"]);
+        assert_eq!(background("#ff0000").unwrap().background_color,
+                   ColorU::new(255, 0, 0, 255));
+        assert_eq!(background("#00ff0080").unwrap().background_color,
+                   ColorU::new(0, 255, 0, 128));
+        assert_eq!(background("transparent").unwrap().background_color,
+                   ColorU::transparent_black());
+        assert!(background("#fff").is_err());
+        assert!(background("red").is_err());
+    }
+
+    #[test]
+    fn test_required_arguments_and_backend() {
+        assert!(parse(&["in.svg"]).is_err());
+        assert!(parse(&["--backend", "vulkan", "in.svg", "out.png"]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+// pathfinder/utils/pf-render/src/scene_file.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Serialized scenes, for rendering content that doesn't come from an SVG file.
+//!
+//! A scene file is JSON: a view box and a list of paths, drawn back to front. Contours are
+//! polylines, and a path with a `stroke` style is stroked rather than filled:
+//!
+//! ```json
+//! {
+//!     "view_box": { "origin": [0, 0], "size": [100, 100] },
+//!     "paths": [
+//!         {
+//!             "contours": [{ "points": [[10, 10], [90, 10], [50, 90]], "closed": true }],
+//!             "color": { "r": 255, "g": 0, "b": 0, "a": 255 },
+//!             "fill_rule": "EvenOdd",
+//!             "stroke": { "line_width": 2, "line_cap": "Round", "line_join": "Round" }
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! `fill_rule` defaults to `Winding`, `closed` to `false`, and `stroke` to filling the path.
+
+use pathfinder_color::ColorU;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::outline::{Contour, Outline};
+use pathfinder_content::stroke::{OutlineStrokeToFill, StrokeStyle};
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::vector::Vector2F;
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{DrawPath, Scene};
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    view_box: RectF,
+    #[serde(default)]
+    paths: Vec<PathDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PathDesc {
+    contours: Vec<ContourDesc>,
+    color: ColorU,
+    #[serde(default)]
+    fill_rule: FillRuleDesc,
+    #[serde(default)]
+    stroke: Option<StrokeStyle>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContourDesc {
+    points: Vec<Vector2F>,
+    #[serde(default)]
+    closed: bool,
+}
+
+// `FillRule` has no `serde` support of its own.
+#[derive(Clone, Copy, Default, Deserialize)]
+enum FillRuleDesc {
+    #[default]
+    Winding,
+    EvenOdd,
+}
+
+/// Reads the scene file at `path`.
+pub fn load(path: &str) -> Result<Scene, String> {
+    let file = File::open(path)
+        .map_err(|error| format!("failed to open `{}`: {}", path, error))?;
+    from_reader(BufReader::new(file))
+        .map_err(|error| format!("failed to parse `{}`: {}", path, error))
+}
+
+fn from_reader<R>(reader: R) -> Result<Scene, serde_json::Error> where R: Read {
+    let scene_file: SceneFile = serde_json::from_reader(reader)?;
+
+    let mut scene = Scene::new();
+    scene.set_view_box(scene_file.view_box);
+    for path_desc in scene_file.paths {
+        let mut outline = Outline::new();
+        for contour_desc in &path_desc.contours {
+            let mut contour = Contour::new();
+            for &point in &contour_desc.points {
+                contour.push_endpoint(point);
+            }
+            if contour_desc.closed {
+                contour.close();
+            }
+            outline.push_contour(contour);
+        }
+
+        if let Some(stroke_style) = path_desc.stroke {
+            let mut stroke_to_fill = OutlineStrokeToFill::new(&outline, stroke_style);
+            stroke_to_fill.offset();
+            outline = stroke_to_fill.into_outline();
+        }
+
+        let paint = scene.push_paint(&Paint::Color(path_desc.color));
+        let mut draw_path = DrawPath::new(outline, paint);
+        draw_path.set_fill_rule(match path_desc.fill_rule {
+            FillRuleDesc::Winding => FillRule::Winding,
+            FillRuleDesc::EvenOdd => FillRule::EvenOdd,
+        });
+        scene.push_path(draw_path);
+    }
+    Ok(scene)
+}
+
+#[cfg(test)]
+mod test {
+    use super::from_reader;
+    use pathfinder_content::fill::FillRule;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::Vector2F;
+
+    #[test]
+    fn test_paths_are_read_in_order() {
+        let scene = from_reader(r#"{
+            "view_box": { "origin": [0, 0], "size": [100, 50] },
+            "paths": [
+                {
+                    "contours": [{ "points": [[10, 10], [90, 10], [50, 40]], "closed": true }],
+                    "color": { "r": 255, "g": 0, "b": 0, "a": 255 },
+                    "fill_rule": "EvenOdd"
+                },
+                {
+                    "contours": [{ "points": [[0, 0], [20, 0]] }],
+                    "color": { "r": 0, "g": 0, "b": 255, "a": 128 },
+                    "stroke": { "line_width": 4, "line_cap": "Butt", "line_join": "Bevel" }
+                }
+            ]
+        }"#.as_bytes()).unwrap();
+
+        assert_eq!(scene.view_box(),
+                   RectF::new(Vector2F::default(), Vector2F::new(100.0, 50.0)));
+        assert_eq!(scene.path_count(), 2);
+
+        let filled = scene.path(0);
+        assert_eq!(filled.fill_rule(), FillRule::EvenOdd);
+        assert_eq!(filled.outline().bounds(),
+                   RectF::from_points(Vector2F::new(10.0, 10.0), Vector2F::new(90.0, 40.0)));
+        assert!(filled.outline().contours()[0].is_closed());
+
+        // A stroked line becomes a filled rectangle as wide as the stroke.
+        let stroked = scene.path(1);
+        assert_eq!(stroked.fill_rule(), FillRule::Winding);
+        assert_eq!(stroked.outline().bounds(),
+                   RectF::from_points(Vector2F::new(0.0, -2.0), Vector2F::new(20.0, 2.0)));
+    }
+
+    #[test]
+    fn test_malformed_scenes_are_rejected() {
+        // No view box.
+        assert!(from_reader(r#"{ "paths": [] }"#.as_bytes()).is_err());
+        // A misspelled field.
+        assert!(from_reader(r#"{
+            "view_box": { "origin": [0, 0], "size": [1, 1] },
+            "path": []
+        }"#.as_bytes()).is_err());
+        // An unknown fill rule.
+        assert!(from_reader(r#"{
+            "view_box": { "origin": [0, 0], "size": [1, 1] },
+            "paths": [{ "contours": [], "color": { "r": 0, "g": 0, "b": 0, "a": 0 },
+                        "fill_rule": "NonZero" }]
+        }"#.as_bytes()).is_err());
+    }
+}
@@ -8,7 +8,11 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-//! A WebGL implementation of the device abstraction.
+//! A WebGL 2 implementation of the device abstraction.
+//!
+//! This lets Pathfinder, and therefore `pathfinder_canvas`, run in browsers through
+//! `wasm-bindgen`. Pair it with `EmbeddedResourceLoader`, since there is no filesystem to load
+//! shaders from.
 
 #[macro_use]
 extern crate log;
@@ -16,24 +20,106 @@ extern crate log;
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::vector::Vector2I;
 use pathfinder_gpu::{BlendFactor, BlendOp, BufferData, BufferTarget, RenderTarget};
-use pathfinder_gpu::{BufferUploadMode, ClearOps, DepthFunc, Device, Primitive, RenderOptions};
+use pathfinder_gpu::{BufferUploadMode, ClearOps, ComputeDimensions, ComputeState, DepthFunc};
+use pathfinder_gpu::{Device, DeviceLostError, Primitive, RenderOptions};
 use pathfinder_gpu::{RenderState, ShaderKind, StencilFunc, TextureData, TextureDataRef};
 use pathfinder_gpu::{TextureFormat, TextureSamplingFlags, UniformData, VertexAttrClass};
 use pathfinder_gpu::{VertexAttrDescriptor, VertexAttrType};
+use pathfinder_gpu::memory::{Allocation, GPUMemoryUsage, MemoryCounter};
+use half::f16;
 use pathfinder_resources::ResourceLoader;
+use std::cell::RefCell;
 use std::mem;
 use std::str;
 use std::time::Duration;
-use web_sys::WebGl2RenderingContext as WebGl;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{HtmlCanvasElement, WebGl2RenderingContext as WebGl};
+
+// Compressed texture formats, from the WebGL extensions that define them.
+const COMPRESSED_RGBA_S3TC_DXT1_EXT: u32 = 0x83f1;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: u32 = 0x83f3;
+const COMPRESSED_RGBA_BPTC_UNORM_EXT: u32 = 0x8e8c;
+const COMPRESSED_RGBA8_ETC2_EAC: u32 = 0x9278;
+const COMPRESSED_RGBA_ASTC_4X4_KHR: u32 = 0x93b0;
+
+// The number of texture units whose bindings `save_state()` preserves.
+const SAVED_TEXTURE_UNIT_COUNT: u32 = 8;
 
 pub struct WebGlDevice {
     context: web_sys::WebGl2RenderingContext,
+    texture_memory: MemoryCounter,
+    buffer_memory: MemoryCounter,
+    supported_compressed_formats: Vec<TextureFormat>,
+    saved_states: RefCell<Vec<WebGlSavedState>>,
 }
 
 impl WebGlDevice {
     pub fn new(context: web_sys::WebGl2RenderingContext) -> Self {
-        context.get_extension("EXT_color_buffer_float").unwrap();
-        WebGlDevice { context }
+        // Pathfinder renders fills into a half-float framebuffer, which WebGL 2 only allows with
+        // this extension.
+        match context.get_extension("EXT_color_buffer_float") {
+            Ok(Some(_)) => {}
+            _ => warn!("`EXT_color_buffer_float` is unavailable; rendering will likely fail."),
+        }
+
+        // Compressed formats can only be used once their extension has been enabled.
+        let mut supported_compressed_formats = vec![];
+        for &(extension, formats) in &[
+            ("WEBGL_compressed_texture_s3tc", &[TextureFormat::BC1, TextureFormat::BC3][..]),
+            ("EXT_texture_compression_bptc", &[TextureFormat::BC7][..]),
+            ("WEBGL_compressed_texture_etc", &[TextureFormat::ETC2RGBA8][..]),
+            ("WEBGL_compressed_texture_astc", &[TextureFormat::ASTC4x4][..]),
+        ] {
+            if let Ok(Some(_)) = context.get_extension(extension) {
+                supported_compressed_formats.extend_from_slice(formats);
+            }
+        }
+
+        WebGlDevice {
+            context,
+            texture_memory: MemoryCounter::new(),
+            buffer_memory: MemoryCounter::new(),
+            supported_compressed_formats,
+            saved_states: RefCell::new(vec![]),
+        }
+    }
+
+    /// Creates a device that renders to the given `<canvas>` element.
+    ///
+    /// The context is created without antialiasing or a depth buffer, since Pathfinder does its
+    /// own antialiasing and uses only the stencil buffer.
+    pub fn from_canvas(canvas: &HtmlCanvasElement) -> Result<WebGlDevice, JsValue> {
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(&options, &"antialias".into(), &false.into())?;
+        js_sys::Reflect::set(&options, &"depth".into(), &false.into())?;
+        js_sys::Reflect::set(&options, &"stencil".into(), &true.into())?;
+        let context = canvas.get_context_with_context_options("webgl2", &options)?
+                            .ok_or_else(|| JsValue::from_str("WebGL 2 is unavailable"))?
+                            .dyn_into::<WebGl>()?;
+        Ok(WebGlDevice::new(context))
+    }
+
+    /// Wraps a texture created outside Pathfinder, so that it can be supplied to the renderer as
+    /// an external texture.
+    ///
+    /// For video, upload each frame into the texture with `texImage2D()` from the `<video>`
+    /// element, which stays on the GPU in most browsers. Pathfinder doesn't take ownership of the
+    /// texture, nor count it in `memory_usage()`.
+    pub fn wrap_texture(&self,
+                        texture: web_sys::WebGlTexture,
+                        format: TextureFormat,
+                        size: Vector2I)
+                        -> WebGlTexture {
+        let texture = WebGlTexture {
+            texture,
+            format,
+            size,
+            context: self.context.clone(),
+            allocation: self.texture_memory.allocate(0),
+            is_external: true,
+        };
+        self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
+        texture
     }
 
     // Error checking
@@ -43,7 +129,7 @@ impl WebGlDevice {
         let mut num_errors = 0;
         loop {
             let err = self.context.get_error();
-            println!(
+            error!(
                 "GL error: 0x{:x} ({})",
                 err,
                 match err {
@@ -192,6 +278,21 @@ impl WebGlDevice {
             self.set_uniform(uniform, data);
         }
         self.set_render_options(&render_state.options);
+        if let Some(scissor) = render_state.options.scissor {
+            self.set_scissor(render_state.viewport, scissor);
+        }
+    }
+
+    fn set_scissor(&self, viewport: RectI, scissor: RectI) {
+        // GL window coordinates have Y pointing up, so flip the rect within the viewport.
+        let scissor = scissor.intersection(RectI::new(Vector2I::default(), viewport.size()))
+                             .unwrap_or_default();
+        self.context.scissor(viewport.min_x() + scissor.min_x(),
+                             viewport.max_y() - scissor.max_y(),
+                             scissor.width(),
+                             scissor.height());
+        self.context.enable(WebGl::SCISSOR_TEST);
+        self.ck();
     }
 
     fn set_render_options(&self, render_options: &RenderOptions) {
@@ -207,6 +308,7 @@ impl WebGlDevice {
                     BlendOp::ReverseSubtract => WebGl::FUNC_REVERSE_SUBTRACT,
                     BlendOp::Max => WebGl::MAX,
                     BlendOp::Min => WebGl::MIN,
+                    _ => panic!("WebGL doesn't support advanced blend equations!"),
                 };
                 self.context.blend_equation(func);
                 self.ck();
@@ -219,6 +321,9 @@ impl WebGlDevice {
                     BlendFactor::DestAlpha => WebGl::DST_ALPHA,
                     BlendFactor::OneMinusDestAlpha => WebGl::ONE_MINUS_DST_ALPHA,
                     BlendFactor::DestColor => WebGl::DST_COLOR,
+                    BlendFactor::Src1Alpha | BlendFactor::OneMinusSrc1Alpha => {
+                        panic!("WebGL doesn't support dual-source blending!")
+                    }
                 };
 
                 self.context.blend_func_separate(
@@ -305,6 +410,10 @@ impl WebGlDevice {
             self.context.disable(WebGl::STENCIL_TEST);
         }
 
+        if render_options.scissor.is_some() {
+            self.context.disable(WebGl::SCISSOR_TEST);
+        }
+
         self.context.color_mask(true, true, true, true);
         self.ck();
     }
@@ -333,6 +442,166 @@ impl WebGlDevice {
         }
     }
 
+    // State saving
+
+    fn parameter<T>(&self, name: u32) -> Option<T> where T: JsCast {
+        self.context.get_parameter(name).ok().and_then(|value| value.dyn_into().ok())
+    }
+
+    fn parameter_u32(&self, name: u32) -> u32 {
+        self.context.get_parameter(name).ok().and_then(|value| value.as_f64()).unwrap_or(0.0) as u32
+    }
+
+    fn parameter_bool(&self, name: u32) -> bool {
+        self.context.get_parameter(name).ok().and_then(|value| value.as_bool()).unwrap_or(false)
+    }
+
+    fn capture_state(&self) -> WebGlSavedState {
+        let active_texture = self.parameter_u32(WebGl::ACTIVE_TEXTURE);
+        let mut textures = vec![];
+        let mut samplers = vec![];
+        for unit in 0..SAVED_TEXTURE_UNIT_COUNT {
+            self.context.active_texture(WebGl::TEXTURE0 + unit);
+            textures.push(self.parameter(WebGl::TEXTURE_BINDING_2D));
+            samplers.push(self.parameter(WebGl::SAMPLER_BINDING));
+        }
+
+        let viewport: Option<js_sys::Int32Array> = self.parameter(WebGl::VIEWPORT);
+        let mut viewport_values = [0; 4];
+        if let Some(viewport) = viewport {
+            viewport.copy_to(&mut viewport_values);
+        }
+        let scissor_box: Option<js_sys::Int32Array> = self.parameter(WebGl::SCISSOR_BOX);
+        let mut scissor_box_values = [0; 4];
+        if let Some(scissor_box) = scissor_box {
+            scissor_box.copy_to(&mut scissor_box_values);
+        }
+        let clear_color: Option<js_sys::Float32Array> = self.parameter(WebGl::COLOR_CLEAR_VALUE);
+        let mut clear_color_values = [0.0; 4];
+        if let Some(clear_color) = clear_color {
+            clear_color.copy_to(&mut clear_color_values);
+        }
+        let mut color_mask = [true; 4];
+        if let Some(mask) = self.parameter::<js_sys::Array>(WebGl::COLOR_WRITEMASK) {
+            for (index, value) in color_mask.iter_mut().enumerate() {
+                *value = mask.get(index as u32).as_bool().unwrap_or(true);
+            }
+        }
+
+        WebGlSavedState {
+            program: self.parameter(WebGl::CURRENT_PROGRAM),
+            vertex_array: self.parameter(WebGl::VERTEX_ARRAY_BINDING),
+            array_buffer: self.parameter(WebGl::ARRAY_BUFFER_BINDING),
+            draw_framebuffer: self.parameter(WebGl::DRAW_FRAMEBUFFER_BINDING),
+            read_framebuffer: self.parameter(WebGl::READ_FRAMEBUFFER_BINDING),
+            viewport: viewport_values,
+            active_texture,
+            textures,
+            samplers,
+            blend: self.context.is_enabled(WebGl::BLEND),
+            blend_src_rgb: self.parameter_u32(WebGl::BLEND_SRC_RGB),
+            blend_dest_rgb: self.parameter_u32(WebGl::BLEND_DST_RGB),
+            blend_src_alpha: self.parameter_u32(WebGl::BLEND_SRC_ALPHA),
+            blend_dest_alpha: self.parameter_u32(WebGl::BLEND_DST_ALPHA),
+            blend_equation_rgb: self.parameter_u32(WebGl::BLEND_EQUATION_RGB),
+            blend_equation_alpha: self.parameter_u32(WebGl::BLEND_EQUATION_ALPHA),
+            depth_test: self.context.is_enabled(WebGl::DEPTH_TEST),
+            depth_func: self.parameter_u32(WebGl::DEPTH_FUNC),
+            depth_mask: self.parameter_bool(WebGl::DEPTH_WRITEMASK),
+            stencil_test: self.context.is_enabled(WebGl::STENCIL_TEST),
+            stencil_func: self.parameter_u32(WebGl::STENCIL_FUNC),
+            stencil_ref: self.parameter_u32(WebGl::STENCIL_REF) as i32,
+            stencil_value_mask: self.parameter_u32(WebGl::STENCIL_VALUE_MASK),
+            stencil_write_mask: self.parameter_u32(WebGl::STENCIL_WRITEMASK),
+            stencil_fail: self.parameter_u32(WebGl::STENCIL_FAIL),
+            stencil_depth_fail: self.parameter_u32(WebGl::STENCIL_PASS_DEPTH_FAIL),
+            stencil_pass: self.parameter_u32(WebGl::STENCIL_PASS_DEPTH_PASS),
+            color_mask,
+            clear_color: clear_color_values,
+            scissor_test: self.context.is_enabled(WebGl::SCISSOR_TEST),
+            scissor_box: scissor_box_values,
+            cull_face: self.context.is_enabled(WebGl::CULL_FACE),
+            unpack_alignment: self.parameter_u32(WebGl::UNPACK_ALIGNMENT) as i32,
+        }
+    }
+
+    // Puts the context into the state that the rest of the device assumes.
+    fn reset_state(&self) {
+        self.context.use_program(None);
+        self.unbind_vertex_array();
+        self.context.bind_buffer(WebGl::ARRAY_BUFFER, None);
+        for unit in 0..SAVED_TEXTURE_UNIT_COUNT {
+            self.context.bind_sampler(unit, None);
+            self.unbind_texture(unit);
+        }
+        self.context.active_texture(WebGl::TEXTURE0);
+
+        for &capability in &[
+            WebGl::BLEND,
+            WebGl::DEPTH_TEST,
+            WebGl::STENCIL_TEST,
+            WebGl::SCISSOR_TEST,
+            WebGl::CULL_FACE,
+        ] {
+            self.context.disable(capability);
+        }
+        self.context.stencil_mask(!0);
+        self.context.color_mask(true, true, true, true);
+        self.context.pixel_storei(WebGl::UNPACK_ALIGNMENT, 4);
+        self.ck();
+    }
+
+    fn apply_state(&self, state: &WebGlSavedState) {
+        self.context.use_program(state.program.as_ref());
+        self.context.bind_vertex_array(state.vertex_array.as_ref());
+        self.context.bind_buffer(WebGl::ARRAY_BUFFER, state.array_buffer.as_ref());
+        self.context.bind_framebuffer(WebGl::DRAW_FRAMEBUFFER, state.draw_framebuffer.as_ref());
+        self.context.bind_framebuffer(WebGl::READ_FRAMEBUFFER, state.read_framebuffer.as_ref());
+        let viewport = state.viewport;
+        self.context.viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        let scissor_box = state.scissor_box;
+        self.context.scissor(scissor_box[0], scissor_box[1], scissor_box[2], scissor_box[3]);
+
+        for unit in 0..SAVED_TEXTURE_UNIT_COUNT {
+            self.context.active_texture(WebGl::TEXTURE0 + unit);
+            self.context.bind_texture(WebGl::TEXTURE_2D, state.textures[unit as usize].as_ref());
+            self.context.bind_sampler(unit, state.samplers[unit as usize].as_ref());
+        }
+        self.context.active_texture(state.active_texture);
+
+        for &(capability, enabled) in &[
+            (WebGl::BLEND, state.blend),
+            (WebGl::DEPTH_TEST, state.depth_test),
+            (WebGl::STENCIL_TEST, state.stencil_test),
+            (WebGl::SCISSOR_TEST, state.scissor_test),
+            (WebGl::CULL_FACE, state.cull_face),
+        ] {
+            if enabled {
+                self.context.enable(capability);
+            } else {
+                self.context.disable(capability);
+            }
+        }
+
+        self.context.blend_func_separate(state.blend_src_rgb,
+                                         state.blend_dest_rgb,
+                                         state.blend_src_alpha,
+                                         state.blend_dest_alpha);
+        self.context.blend_equation_separate(state.blend_equation_rgb, state.blend_equation_alpha);
+        self.context.depth_func(state.depth_func);
+        self.context.depth_mask(state.depth_mask);
+        self.context.stencil_func(state.stencil_func, state.stencil_ref, state.stencil_value_mask);
+        self.context.stencil_mask(state.stencil_write_mask);
+        self.context.stencil_op(state.stencil_fail, state.stencil_depth_fail, state.stencil_pass);
+
+        let color_mask = state.color_mask;
+        self.context.color_mask(color_mask[0], color_mask[1], color_mask[2], color_mask[3]);
+        let clear_color = state.clear_color;
+        self.context.clear_color(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+        self.context.pixel_storei(WebGl::UNPACK_ALIGNMENT, state.unpack_alignment);
+        self.ck();
+    }
+
     fn preprocess(&self, source: &[u8], version: &str) -> String {
         let source = std::str::from_utf8(source).unwrap();
         let mut output = String::new();
@@ -361,6 +630,37 @@ impl WebGlDevice {
         */
         output
     }
+
+    fn compressed_tex_image_2d(&self, format: TextureFormat, size: Vector2I, data: &[u8]) {
+        let data = &data[0..format.byte_size(size)];
+        self.context.compressed_tex_image_2d_with_u8_array(WebGl::TEXTURE_2D,
+                                                           0,
+                                                           format.gl_internal_format(),
+                                                           size.x(),
+                                                           size.y(),
+                                                           0,
+                                                           data);
+    }
+
+    fn render_target_format(&self, render_target: &RenderTarget<WebGlDevice>) -> TextureFormat {
+        match *render_target {
+            RenderTarget::Default => TextureFormat::RGBA8,
+            RenderTarget::Framebuffer(ref framebuffer) => framebuffer.texture.format,
+        }
+    }
+
+    fn get_texture_data(&self, receiver: &WebGlTextureDataReceiver) -> TextureData {
+        let (format, size) = (receiver.format, receiver.size);
+        let pixel_count = size.x() as usize * size.y() as usize;
+        let mut bytes = vec![0; pixel_count * 4 * format.read_type_size()];
+        self.context.bind_buffer(WebGl::PIXEL_PACK_BUFFER, Some(&receiver.gl_pixel_buffer));
+        self.context
+            .get_buffer_sub_data_with_i32_and_u8_array(WebGl::PIXEL_PACK_BUFFER, 0, &mut bytes);
+        self.context.bind_buffer(WebGl::PIXEL_PACK_BUFFER, None);
+        self.ck();
+
+        unpack_pixels(format, size, &bytes)
+    }
 }
 
 fn slice_to_u8<T>(slice: &[T]) -> &[u8] {
@@ -376,9 +676,20 @@ fn check_and_extract_data(
     minimum_size: Vector2I,
     format: TextureFormat,
 ) -> &[u8] {
+    if format.is_compressed() {
+        match data_ref {
+            TextureDataRef::U8(data) => {
+                assert!(data.len() >= format.byte_size(minimum_size));
+                return data;
+            }
+            _ => panic!("Compressed texture data must be supplied as bytes!"),
+        }
+    }
+
     let channels = match (format, data_ref) {
         (TextureFormat::R8, TextureDataRef::U8(_)) => 1,
         (TextureFormat::RGBA8, TextureDataRef::U8(_)) => 4,
+        (TextureFormat::R16F, TextureDataRef::F16(_)) => 1,
         (TextureFormat::RGBA16F, TextureDataRef::F16(_)) => 4,
         (TextureFormat::RGBA32F, TextureDataRef::F32(_)) => 4,
         _ => panic!("Unimplemented texture format!"),
@@ -404,11 +715,13 @@ fn check_and_extract_data(
 
 impl Device for WebGlDevice {
     type Buffer = WebGlBuffer;
+    type Fence = WebGlFence;
     type Framebuffer = WebGlFramebuffer;
     type Program = WebGlProgram;
     type Shader = WebGlShader;
+    type StorageBuffer = WebGlStorageBuffer;
     type Texture = WebGlTexture;
-    type TextureDataReceiver = ();
+    type TextureDataReceiver = WebGlTextureDataReceiver;
     type TimerQuery = WebGlTimerQuery;
     type Uniform = WebGlUniform;
     type VertexArray = WebGlVertexArray;
@@ -421,21 +734,29 @@ impl Device for WebGlDevice {
             format,
             size,
             context: self.context.clone(),
+            allocation: self.texture_memory.allocate(format.byte_size(size) as u64),
+            is_external: false,
         };
         self.bind_texture(&texture, 0);
-        self.context
-            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
-                WebGl::TEXTURE_2D,
-                0,
-                format.gl_internal_format() as i32,
-                size.x(),
-                size.y(),
-                0,
-                format.gl_format(),
-                format.gl_type(),
-                None,
-            )
-            .unwrap();
+        if format.is_compressed() {
+            // WebGL insists on data for compressed textures.
+            let data = vec![0; format.byte_size(size)];
+            self.compressed_tex_image_2d(format, size, &data);
+        } else {
+            self.context
+                .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                    WebGl::TEXTURE_2D,
+                    0,
+                    format.gl_internal_format() as i32,
+                    size.x(),
+                    size.y(),
+                    0,
+                    format.gl_format(),
+                    format.gl_type(),
+                    None,
+                )
+                .unwrap();
+        }
 
         self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
         texture
@@ -455,22 +776,28 @@ impl Device for WebGlDevice {
             format,
             size,
             context: self.context.clone(),
+            allocation: self.texture_memory.allocate(format.byte_size(size) as u64),
+            is_external: false,
         };
 
         self.bind_texture(&texture, 0);
-        self.context
-            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
-                WebGl::TEXTURE_2D,
-                0,
-                format.gl_internal_format() as i32,
-                size.x(),
-                size.y(),
-                0,
-                format.gl_format(),
-                format.gl_type(),
-                Some(data),
-            )
-            .unwrap();
+        if format.is_compressed() {
+            self.compressed_tex_image_2d(format, size, data);
+        } else {
+            self.context
+                .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                    WebGl::TEXTURE_2D,
+                    0,
+                    format.gl_internal_format() as i32,
+                    size.x(),
+                    size.y(),
+                    0,
+                    format.gl_format(),
+                    format.gl_type(),
+                    Some(data),
+                )
+                .unwrap();
+        }
 
         self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
         texture
@@ -494,6 +821,7 @@ impl Device for WebGlDevice {
         let gl_shader_kind = match kind {
             ShaderKind::Vertex => WebGl::VERTEX_SHADER,
             ShaderKind::Fragment => WebGl::FRAGMENT_SHADER,
+            ShaderKind::Compute => panic!("WebGL doesn't support compute shaders!"),
         };
 
         let gl_shader = self
@@ -666,6 +994,7 @@ impl Device for WebGlDevice {
         WebGlBuffer {
             buffer,
             context: self.context.clone(),
+            allocation: self.buffer_memory.allocate(0),
         }
     }
 
@@ -679,20 +1008,24 @@ impl Device for WebGlDevice {
         let target = match target {
             BufferTarget::Vertex => WebGl::ARRAY_BUFFER,
             BufferTarget::Index => WebGl::ELEMENT_ARRAY_BUFFER,
+            BufferTarget::Storage => panic!("WebGL doesn't support storage buffers!"),
         };
         self.context.bind_buffer(target, Some(&buffer.buffer));
         self.ck();
         let usage = mode.to_gl_usage();
-        match data {
+        let byte_len = match data {
             BufferData::Uninitialized(len) => {
-                self.context
-                    .buffer_data_with_i32(target, (len * mem::size_of::<T>()) as i32, usage)
+                let byte_len = len * mem::size_of::<T>();
+                self.context.buffer_data_with_i32(target, byte_len as i32, usage);
+                byte_len
             }
-            BufferData::Memory(buffer) => {
-                self.context
-                    .buffer_data_with_u8_array(target, slice_to_u8(buffer), usage)
+            BufferData::Memory(data) => {
+                let data = slice_to_u8(data);
+                self.context.buffer_data_with_u8_array(target, data, usage);
+                data.len()
             }
-        }
+        };
+        buffer.allocation.resize(byte_len as u64);
     }
 
     #[inline]
@@ -749,7 +1082,27 @@ impl Device for WebGlDevice {
         assert!(rect.max_y() <= texture.size.y());
 
         self.bind_texture(texture, 0);
-        if rect.origin() == Vector2I::default() && rect.size() == texture.size {
+        if texture.format.is_compressed() {
+            if rect.origin() == Vector2I::default() && rect.size() == texture.size {
+                self.compressed_tex_image_2d(texture.format, texture.size, data);
+            } else {
+                // Partial updates must start on a block boundary.
+                let block_size = texture.format.block_size();
+                assert_eq!(rect.origin().x() % block_size.x(), 0);
+                assert_eq!(rect.origin().y() % block_size.y(), 0);
+                let data = &data[0..texture.format.byte_size(rect.size())];
+                self.context.compressed_tex_sub_image_2d_with_u8_array(
+                    WebGl::TEXTURE_2D,
+                    0,
+                    rect.origin().x(),
+                    rect.origin().y(),
+                    rect.size().x(),
+                    rect.size().y(),
+                    texture.format.gl_internal_format(),
+                    data,
+                );
+            }
+        } else if rect.origin() == Vector2I::default() && rect.size() == texture.size {
             self.context
                 .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
                     WebGl::TEXTURE_2D,
@@ -770,8 +1123,8 @@ impl Device for WebGlDevice {
                     0,
                     rect.origin().x(),
                     rect.origin().y(),
-                    texture.size.x() as i32,
-                    texture.size.y() as i32,
+                    rect.size().x(),
+                    rect.size().y(),
                     texture.format.gl_format(),
                     texture.format.gl_type(),
                     Some(data),
@@ -782,8 +1135,40 @@ impl Device for WebGlDevice {
         self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
     }
 
-    fn read_pixels(&self, _render_target: &RenderTarget<WebGlDevice>, _viewport: RectI) -> () {
-        panic!("read_pixels is not supported");
+    fn read_pixels(&self, render_target: &RenderTarget<WebGlDevice>, viewport: RectI)
+                   -> WebGlTextureDataReceiver {
+        let (origin, size) = (viewport.origin(), viewport.size());
+        let format = self.render_target_format(render_target);
+        self.bind_render_target(render_target);
+
+        // WebGL 2 only guarantees that RGBA reads work, so always read four channels and drop
+        // the extra ones later.
+        let byte_size = size.x() as usize * size.y() as usize * 4 * format.read_type_size();
+        let gl_pixel_buffer = self.context.create_buffer().unwrap();
+        self.context.bind_buffer(WebGl::PIXEL_PACK_BUFFER, Some(&gl_pixel_buffer));
+        self.context.buffer_data_with_i32(WebGl::PIXEL_PACK_BUFFER,
+                                          byte_size as i32,
+                                          WebGl::STREAM_READ);
+        self.context
+            .read_pixels_with_i32(origin.x(),
+                                  origin.y(),
+                                  size.x(),
+                                  size.y(),
+                                  WebGl::RGBA,
+                                  format.read_type(),
+                                  0)
+            .unwrap();
+        self.context.bind_buffer(WebGl::PIXEL_PACK_BUFFER, None);
+        self.ck();
+
+        let gl_sync = self.context.fence_sync(WebGl::SYNC_GPU_COMMANDS_COMPLETE, 0).unwrap();
+        WebGlTextureDataReceiver {
+            context: self.context.clone(),
+            gl_pixel_buffer,
+            gl_sync,
+            size,
+            format,
+        }
     }
 
     fn begin_commands(&self) {
@@ -833,36 +1218,143 @@ impl Device for WebGlDevice {
         self.reset_render_state(render_state);
     }
 
+    // Browsers expose GPU timer queries only behind `EXT_disjoint_timer_query_webgl2`, which is
+    // disabled almost everywhere, so timings always read as zero. They must still resolve, or the
+    // renderer would queue pending timers forever.
+
     #[inline]
     fn create_timer_query(&self) -> WebGlTimerQuery {
-        // FIXME use performance timers
         WebGlTimerQuery {}
     }
 
     #[inline]
-    fn begin_timer_query(&self, _query: &Self::TimerQuery) {
-        // FIXME use performance timers
-    }
+    fn begin_timer_query(&self, _query: &Self::TimerQuery) {}
 
     #[inline]
-    fn end_timer_query(&self, _: &Self::TimerQuery) {
-        // FIXME use performance timers
-    }
+    fn end_timer_query(&self, _: &Self::TimerQuery) {}
 
     #[inline]
     fn try_recv_timer_query(&self, _query: &WebGlTimerQuery) -> Option<Duration> {
-        None
+        Some(Duration::from_millis(0))
     }
 
     #[inline]
     fn recv_timer_query(&self, _query: &WebGlTimerQuery) -> Duration {
         Duration::from_millis(0)
     }
-    fn try_recv_texture_data(&self, _receiver: &Self::TextureDataReceiver) -> Option<TextureData> {
-        None
+
+    fn memory_usage(&self) -> GPUMemoryUsage {
+        GPUMemoryUsage {
+            textures: self.texture_memory.get(),
+            buffers: self.buffer_memory.get(),
+        }
+    }
+
+    fn supports_texture_format(&self, format: TextureFormat) -> bool {
+        !format.is_compressed() || self.supported_compressed_formats.contains(&format)
+    }
+
+    fn insert_fence(&self) -> WebGlFence {
+        let gl_sync = self.context.fence_sync(WebGl::SYNC_GPU_COMMANDS_COMPLETE, 0).unwrap();
+        self.context.flush();
+        WebGlFence { context: self.context.clone(), gl_sync }
+    }
+
+    fn is_fence_signaled(&self, fence: &WebGlFence) -> bool {
+        match self.context.client_wait_sync_with_u32(&fence.gl_sync, 0, 0) {
+            WebGl::ALREADY_SIGNALED | WebGl::CONDITION_SATISFIED => true,
+            _ => false,
+        }
+    }
+
+    // WebGL forbids blocking in `clientWaitSync`, but `finish` waits for everything, which
+    // includes the fence.
+    fn wait_for_fence(&self, fence: &WebGlFence) {
+        if !self.is_fence_signaled(fence) {
+            self.context.finish();
+        }
+    }
+
+    fn gpu_wait_for_fence(&self, fence: &WebGlFence) {
+        self.context.wait_sync_with_i32(&fence.gl_sync, 0, WebGl::TIMEOUT_IGNORED as i32);
+    }
+
+    // Browsers don't say why a context was lost.
+    fn check_device(&self) -> Result<(), DeviceLostError> {
+        if self.context.is_context_lost() {
+            Err(DeviceLostError::UnknownContextReset)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn save_state(&self) {
+        let state = self.capture_state();
+        self.saved_states.borrow_mut().push(state);
+        self.reset_state();
+    }
+
+    fn restore_state(&self) {
+        let state = self.saved_states.borrow_mut().pop().expect("No WebGL state was saved!");
+        self.apply_state(&state);
+    }
+
+    #[inline]
+    fn supports_dual_source_blending(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn supports_advanced_blending(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn supports_compute(&self) -> bool {
+        false
+    }
+
+    fn create_compute_program_from_shader(
+        &self,
+        _: &dyn ResourceLoader,
+        _: &str,
+        _: WebGlShader,
+    ) -> WebGlProgram {
+        panic!("WebGL doesn't support compute shaders!")
+    }
+
+    fn set_compute_program_local_size(&self, _: &mut WebGlProgram, _: ComputeDimensions) {
+        panic!("WebGL doesn't support compute shaders!")
+    }
+
+    fn get_storage_buffer(&self, _: &WebGlProgram, _: &str, _: u32) -> WebGlStorageBuffer {
+        panic!("WebGL doesn't support storage buffers!")
+    }
+
+    fn dispatch_compute(&self, _: ComputeDimensions, _: &ComputeState<WebGlDevice>) {
+        panic!("WebGL doesn't support compute shaders!")
+    }
+
+    fn dispatch_compute_indirect(
+        &self,
+        _: &WebGlBuffer,
+        _: usize,
+        _: &ComputeState<WebGlDevice>,
+    ) {
+        panic!("WebGL doesn't support compute shaders!")
+    }
+
+    fn try_recv_texture_data(&self, receiver: &Self::TextureDataReceiver) -> Option<TextureData> {
+        // WebGL forbids blocking in `clientWaitSync`, so only poll.
+        match self.context.client_wait_sync_with_u32(&receiver.gl_sync, 0, 0) {
+            WebGl::TIMEOUT_EXPIRED | WebGl::WAIT_FAILED => None,
+            _ => Some(self.get_texture_data(receiver)),
+        }
     }
-    fn recv_texture_data(&self, _receiver: &Self::TextureDataReceiver) -> TextureData {
-        unimplemented!()
+
+    fn recv_texture_data(&self, receiver: &Self::TextureDataReceiver) -> TextureData {
+        // `getBufferSubData` stalls until the read has finished if it hasn't already.
+        self.get_texture_data(receiver)
     }
 
     #[inline]
@@ -888,6 +1380,7 @@ impl Device for WebGlDevice {
         let suffix = match kind {
             ShaderKind::Vertex => 'v',
             ShaderKind::Fragment => 'f',
+            ShaderKind::Compute => 'c',
         };
         let path = format!("shaders/gl3/{}.{}s.glsl", name, suffix);
         self.create_shader_from_source(name, &resources.slurp(&path).unwrap(), kind)
@@ -911,6 +1404,54 @@ pub struct WebGlVertexAttr {
     attr: u32,
 }
 
+// The context state that `save_state()` preserves, as queried from WebGL.
+struct WebGlSavedState {
+    program: Option<web_sys::WebGlProgram>,
+    vertex_array: Option<web_sys::WebGlVertexArrayObject>,
+    array_buffer: Option<web_sys::WebGlBuffer>,
+    draw_framebuffer: Option<web_sys::WebGlFramebuffer>,
+    read_framebuffer: Option<web_sys::WebGlFramebuffer>,
+    viewport: [i32; 4],
+    active_texture: u32,
+    textures: Vec<Option<web_sys::WebGlTexture>>,
+    samplers: Vec<Option<web_sys::WebGlSampler>>,
+    blend: bool,
+    blend_src_rgb: u32,
+    blend_dest_rgb: u32,
+    blend_src_alpha: u32,
+    blend_dest_alpha: u32,
+    blend_equation_rgb: u32,
+    blend_equation_alpha: u32,
+    depth_test: bool,
+    depth_func: u32,
+    depth_mask: bool,
+    stencil_test: bool,
+    stencil_func: u32,
+    stencil_ref: i32,
+    stencil_value_mask: u32,
+    stencil_write_mask: u32,
+    stencil_fail: u32,
+    stencil_depth_fail: u32,
+    stencil_pass: u32,
+    color_mask: [bool; 4],
+    clear_color: [f32; 4],
+    scissor_test: bool,
+    scissor_box: [i32; 4],
+    cull_face: bool,
+    unpack_alignment: i32,
+}
+
+pub struct WebGlFence {
+    context: web_sys::WebGl2RenderingContext,
+    gl_sync: web_sys::WebGlSync,
+}
+
+impl Drop for WebGlFence {
+    fn drop(&mut self) {
+        self.context.delete_sync(Some(&self.gl_sync));
+    }
+}
+
 pub struct WebGlFramebuffer {
     pub framebuffer: web_sys::WebGlFramebuffer,
     pub texture: WebGlTexture,
@@ -919,6 +1460,7 @@ pub struct WebGlFramebuffer {
 pub struct WebGlBuffer {
     context: web_sys::WebGl2RenderingContext,
     pub buffer: web_sys::WebGlBuffer,
+    allocation: Allocation,
 }
 
 impl Drop for WebGlBuffer {
@@ -932,6 +1474,10 @@ pub struct WebGlUniform {
     location: Option<web_sys::WebGlUniformLocation>,
 }
 
+/// WebGL 2 has no storage buffers, so this type has no values.
+#[derive(Debug)]
+pub enum WebGlStorageBuffer {}
+
 pub struct WebGlProgram {
     context: web_sys::WebGl2RenderingContext,
     pub gl_program: web_sys::WebGlProgram,
@@ -952,15 +1498,36 @@ pub struct WebGlTexture {
     texture: web_sys::WebGlTexture,
     pub size: Vector2I,
     pub format: TextureFormat,
+    #[allow(dead_code)]
+    allocation: Allocation,
+    // Whether the texture came from `WebGlDevice::wrap_texture()` and so belongs to someone else.
+    is_external: bool,
 }
 impl Drop for WebGlTexture {
     fn drop(&mut self) {
-        self.context.delete_texture(Some(&self.texture));
+        if !self.is_external {
+            self.context.delete_texture(Some(&self.texture));
+        }
     }
 }
 
 pub struct WebGlTimerQuery {}
 
+pub struct WebGlTextureDataReceiver {
+    context: web_sys::WebGl2RenderingContext,
+    gl_pixel_buffer: web_sys::WebGlBuffer,
+    gl_sync: web_sys::WebGlSync,
+    size: Vector2I,
+    format: TextureFormat,
+}
+
+impl Drop for WebGlTextureDataReceiver {
+    fn drop(&mut self) {
+        self.context.delete_buffer(Some(&self.gl_pixel_buffer));
+        self.context.delete_sync(Some(&self.gl_sync));
+    }
+}
+
 trait BufferTargetExt {
     fn to_gl_target(self) -> u32;
 }
@@ -970,6 +1537,7 @@ impl BufferTargetExt for BufferTarget {
         match self {
             BufferTarget::Vertex => WebGl::ARRAY_BUFFER,
             BufferTarget::Index => WebGl::ELEMENT_ARRAY_BUFFER,
+            BufferTarget::Storage => panic!("WebGL doesn't support storage buffers!"),
         }
     }
 }
@@ -1030,6 +1598,8 @@ trait TextureFormatExt {
     fn gl_internal_format(self) -> u32;
     fn gl_format(self) -> u32;
     fn gl_type(self) -> u32;
+    fn read_type(self) -> u32;
+    fn read_type_size(self) -> usize;
 }
 
 impl TextureFormatExt for TextureFormat {
@@ -1037,24 +1607,45 @@ impl TextureFormatExt for TextureFormat {
         match self {
             TextureFormat::R8 => WebGl::R8,
             TextureFormat::R16F => WebGl::R16F,
-            TextureFormat::RGBA8 => WebGl::RGBA,
+            TextureFormat::RGBA8 => WebGl::RGBA8,
             TextureFormat::RGBA16F => WebGl::RGBA16F,
             TextureFormat::RGBA32F => WebGl::RGBA32F,
+            TextureFormat::BC1 => COMPRESSED_RGBA_S3TC_DXT1_EXT,
+            TextureFormat::BC3 => COMPRESSED_RGBA_S3TC_DXT5_EXT,
+            TextureFormat::BC7 => COMPRESSED_RGBA_BPTC_UNORM_EXT,
+            TextureFormat::ETC2RGBA8 => COMPRESSED_RGBA8_ETC2_EAC,
+            TextureFormat::ASTC4x4 => COMPRESSED_RGBA_ASTC_4X4_KHR,
         }
     }
 
+    // Compressed formats have no pixel format or type; they're uploaded whole.
     fn gl_format(self) -> u32 {
         match self {
             TextureFormat::R8 | TextureFormat::R16F => WebGl::RED,
-            TextureFormat::RGBA8 | TextureFormat::RGBA16F | TextureFormat::RGBA32F => WebGl::RGBA,
+            _ => WebGl::RGBA,
         }
     }
 
     fn gl_type(self) -> u32 {
         match self {
-            TextureFormat::R8 | TextureFormat::RGBA8 => WebGl::UNSIGNED_BYTE,
             TextureFormat::R16F | TextureFormat::RGBA16F => WebGl::HALF_FLOAT,
             TextureFormat::RGBA32F => WebGl::FLOAT,
+            _ => WebGl::UNSIGNED_BYTE,
+        }
+    }
+
+    // The type `readPixels` accepts for framebuffers of this format.
+    fn read_type(self) -> u32 {
+        match self {
+            TextureFormat::R16F | TextureFormat::RGBA16F | TextureFormat::RGBA32F => WebGl::FLOAT,
+            _ => WebGl::UNSIGNED_BYTE,
+        }
+    }
+
+    fn read_type_size(self) -> usize {
+        match self.read_type() {
+            WebGl::UNSIGNED_BYTE => 1,
+            _ => 4,
         }
     }
 }
@@ -1084,3 +1675,93 @@ pub enum GLVersion {
     /// OpenGL ES 3.0+.
     GLES3 = 1,
 }
+
+// Converts the RGBA pixels `readPixels` returned to the requested format, dropping the channels
+// we didn't ask for and flipping the rows right side up.
+fn unpack_pixels(format: TextureFormat, size: Vector2I, bytes: &[u8]) -> TextureData {
+    let channels = format.channels();
+    let mut texture_data = match format {
+        TextureFormat::R8 | TextureFormat::RGBA8 => {
+            TextureData::U8(bytes.chunks(4).flat_map(|pixel| pixel[0..channels].to_vec())
+                                           .collect())
+        }
+        TextureFormat::R16F | TextureFormat::RGBA16F => {
+            TextureData::F16(bytes.chunks(16).flat_map(|pixel| {
+                pixel.chunks(4).take(channels).map(|value| {
+                    f16::from_f32(f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+                }).collect::<Vec<_>>()
+            }).collect())
+        }
+        TextureFormat::RGBA32F => {
+            TextureData::F32(bytes.chunks(4).map(|value| {
+                f32::from_le_bytes([value[0], value[1], value[2], value[3]])
+            }).collect())
+        }
+        _ => panic!("Can't read back compressed textures!"),
+    };
+    match texture_data {
+        TextureData::U8(ref mut pixels) => flip_y(pixels, size, channels),
+        TextureData::U16(ref mut pixels) => flip_y(pixels, size, channels),
+        TextureData::F16(ref mut pixels) => flip_y(pixels, size, channels),
+        TextureData::F32(ref mut pixels) => flip_y(pixels, size, channels),
+    }
+    texture_data
+}
+
+fn flip_y<T>(pixels: &mut [T], size: Vector2I, channels: usize) {
+    let stride = size.x() as usize * channels;
+    for y in 0..(size.y() as usize / 2) {
+        let (index_a, index_b) = (y * stride, (size.y() as usize - y - 1) * stride);
+        for offset in 0..stride {
+            pixels.swap(index_a + offset, index_b + offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TextureFormatExt, flip_y, unpack_pixels};
+    use half::f16;
+    use pathfinder_geometry::vector::Vector2I;
+    use pathfinder_gpu::{TextureData, TextureFormat};
+
+    #[test]
+    fn test_flip_y() {
+        let mut pixels = vec![1, 2, 3, 4, 5, 6];
+        flip_y(&mut pixels, Vector2I::new(1, 3), 2);
+        assert_eq!(pixels, [5, 6, 3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn test_read_type_size() {
+        assert_eq!(TextureFormat::R8.read_type_size(), 1);
+        assert_eq!(TextureFormat::RGBA8.read_type_size(), 1);
+        assert_eq!(TextureFormat::R16F.read_type_size(), 4);
+        assert_eq!(TextureFormat::RGBA32F.read_type_size(), 4);
+    }
+
+    #[test]
+    fn test_unpack_pixels_drops_unrequested_channels() {
+        // Two rows of one RGBA pixel each, bottom row first as `readPixels` returns them.
+        let bytes = [10, 11, 12, 13, 20, 21, 22, 23];
+        match unpack_pixels(TextureFormat::R8, Vector2I::new(1, 2), &bytes) {
+            TextureData::U8(pixels) => assert_eq!(pixels, [20, 10]),
+            texture_data => panic!("unexpected texture data: {:?}", texture_data),
+        }
+        match unpack_pixels(TextureFormat::RGBA8, Vector2I::new(1, 2), &bytes) {
+            TextureData::U8(pixels) => assert_eq!(pixels, [20, 21, 22, 23, 10, 11, 12, 13]),
+            texture_data => panic!("unexpected texture data: {:?}", texture_data),
+        }
+    }
+
+    #[test]
+    fn test_unpack_pixels_converts_floats_to_half_floats() {
+        let bytes: Vec<u8> = [0.5f32, 1.0, 1.0, 1.0].iter()
+                                                    .flat_map(|value| value.to_le_bytes().to_vec())
+                                                    .collect();
+        match unpack_pixels(TextureFormat::R16F, Vector2I::new(1, 1), &bytes) {
+            TextureData::F16(pixels) => assert_eq!(pixels, [f16::from_f32(0.5)]),
+            texture_data => panic!("unexpected texture data: {:?}", texture_data),
+        }
+    }
+}
@@ -0,0 +1,1772 @@
+// pathfinder/wgpu/src/lib.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A wgpu implementation of the device abstraction, for Vulkan, Metal, D3D12, and WebGPU.
+//!
+//! Shaders are loaded as SPIR-V from `shaders/spirv/`. Run `make` in the top-level `shaders/`
+//! directory to generate them.
+
+use crate::spirv::{ResourceBinding, ShaderReflection, UniformBlock};
+use futures::FutureExt;
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_gpu::{BlendFactor, BlendOp, BlendState, BufferData, BufferTarget, BufferUploadMode};
+use pathfinder_gpu::{ComputeDimensions, ComputeState, DepthFunc, Device, Primitive, RenderState};
+use pathfinder_gpu::{DeviceLostError, RenderTarget, ShaderKind};
+use pathfinder_gpu::{StencilFunc, TextureData, TextureDataRef, TextureFormat};
+use pathfinder_gpu::{TextureSamplingFlags, UniformData, VertexAttrClass, VertexAttrDescriptor};
+use pathfinder_gpu::VertexAttrType;
+use pathfinder_gpu::memory::{Allocation, GPUMemoryUsage, MemoryCounter};
+use pathfinder_resources::ResourceLoader;
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::io::Cursor;
+use std::mem;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::slice;
+use std::time::Duration;
+use wgpu::{BindGroupLayout, BufferAsyncErr, BufferReadMapping, BufferUsage, CommandEncoder};
+use wgpu::ComputePipeline;
+use wgpu::{PipelineLayout, RenderPipeline, Sampler, ShaderModule, SwapChain, SwapChainOutput};
+use wgpu::{TextureUsage, TextureView, VertexFormat};
+
+mod spirv;
+
+// wgpu requires rows in buffer-to-texture copies to be aligned to this many bytes.
+const ROW_ALIGNMENT: usize = 256;
+
+const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+const SWAP_CHAIN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8Unorm;
+
+// The bind group indices that the shader build assigns to each stage.
+const VERTEX_BIND_GROUP_INDEX: u32 = 0;
+const FRAGMENT_BIND_GROUP_INDEX: u32 = 1;
+const COMPUTE_BIND_GROUP_INDEX: u32 = 0;
+
+pub struct WgpuDevice {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    default_target: RefCell<DefaultTarget>,
+    default_depth_stencil_texture: RefCell<wgpu::Texture>,
+    default_depth_stencil_view: RefCell<TextureView>,
+    encoder: RefCell<Option<CommandEncoder>>,
+    samplers: Vec<Sampler>,
+    placeholder_texture: WgpuTexture,
+    pipelines: RefCell<Vec<(PipelineKey, Rc<RenderPipeline>)>>,
+    next_program_id: Cell<usize>,
+    texture_memory: MemoryCounter,
+    buffer_memory: MemoryCounter,
+}
+
+enum DefaultTarget {
+    SwapChain {
+        surface: wgpu::Surface,
+        swap_chain: SwapChain,
+        frame: Option<SwapChainOutput>,
+    },
+    Texture(WgpuTexture),
+}
+
+pub enum WgpuProgram {
+    Raster(WgpuRasterProgram),
+    Compute(WgpuComputeProgram),
+}
+
+pub struct WgpuRasterProgram {
+    id: usize,
+    vertex: WgpuShader,
+    fragment: WgpuShader,
+    bind_group_layouts: [BindGroupLayout; 2],
+    pipeline_layout: PipelineLayout,
+}
+
+// Unlike render pipelines, a compute pipeline depends on nothing but the shader, so it's created
+// along with the program.
+pub struct WgpuComputeProgram {
+    shader: WgpuShader,
+    bind_group_layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+}
+
+impl WgpuProgram {
+    fn raster(&self) -> &WgpuRasterProgram {
+        match *self {
+            WgpuProgram::Raster(ref program) => program,
+            WgpuProgram::Compute(_) => panic!("Expected a raster program!"),
+        }
+    }
+
+    fn compute(&self) -> &WgpuComputeProgram {
+        match *self {
+            WgpuProgram::Compute(ref program) => program,
+            WgpuProgram::Raster(_) => panic!("Expected a compute program!"),
+        }
+    }
+}
+
+pub struct WgpuShader {
+    module: ShaderModule,
+    reflection: ShaderReflection,
+}
+
+#[derive(Clone)]
+pub struct WgpuBuffer {
+    buffer: Rc<RefCell<Option<wgpu::Buffer>>>,
+    allocation: Rc<Allocation>,
+}
+
+pub struct WgpuFramebuffer(WgpuTexture);
+
+pub struct WgpuTexture {
+    texture: wgpu::Texture,
+    view: TextureView,
+    format: TextureFormat,
+    size: Vector2I,
+    sampling_flags: Cell<TextureSamplingFlags>,
+    #[allow(dead_code)]
+    allocation: Allocation,
+}
+
+pub struct WgpuTextureDataReceiver {
+    state: RefCell<WgpuTextureDataReceiverState>,
+    format: TextureFormat,
+    size: Vector2I,
+    padded_bytes_per_row: usize,
+}
+
+enum WgpuTextureDataReceiverState {
+    Pending {
+        // Keeps the buffer alive until the mapping completes.
+        #[allow(dead_code)]
+        buffer: wgpu::Buffer,
+        mapping: Pin<Box<dyn Future<Output = Result<BufferReadMapping, BufferAsyncErr>>>>,
+    },
+    Downloaded(TextureData),
+    Finished,
+}
+
+pub struct WgpuFence {
+    state: RefCell<WgpuFenceState>,
+}
+
+enum WgpuFenceState {
+    Pending {
+        // Keeps the buffer alive until the mapping completes.
+        #[allow(dead_code)]
+        buffer: wgpu::Buffer,
+        mapping: Pin<Box<dyn Future<Output = Result<BufferReadMapping, BufferAsyncErr>>>>,
+    },
+    Signaled,
+}
+
+enum WgpuDispatch<'a> {
+    Direct(ComputeDimensions),
+    Indirect(&'a WgpuBuffer, usize),
+}
+
+// wgpu doesn't expose timestamp queries, so timer queries always report zero.
+pub struct WgpuTimerQuery;
+
+pub struct WgpuUniform {
+    name: String,
+}
+
+pub struct WgpuStorageBuffer {
+    binding: u32,
+}
+
+#[derive(Clone, Copy)]
+pub struct WgpuVertexAttr {
+    location: u32,
+}
+
+pub struct WgpuVertexArray {
+    vertex_buffers: RefCell<Vec<WgpuBuffer>>,
+    index_buffer: RefCell<Option<WgpuBuffer>>,
+    attributes: RefCell<Vec<(u32, VertexAttrDescriptor)>>,
+}
+
+#[derive(Clone, PartialEq)]
+struct PipelineKey {
+    program_id: usize,
+    vertex_buffers: Vec<VertexBufferKey>,
+    primitive: wgpu::PrimitiveTopology,
+    color_format: wgpu::TextureFormat,
+    blend: Option<BlendState>,
+    color_mask: bool,
+    depth_stencil: Option<DepthStencilKey>,
+}
+
+#[derive(Clone, PartialEq)]
+struct VertexBufferKey {
+    stride: u64,
+    step_mode: wgpu::InputStepMode,
+    attributes: Vec<wgpu::VertexAttributeDescriptor>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct DepthStencilKey {
+    depth_compare: wgpu::CompareFunction,
+    depth_write: bool,
+    stencil_compare: wgpu::CompareFunction,
+    stencil_mask: u32,
+    stencil_write: bool,
+}
+
+impl WgpuDevice {
+    /// Creates a device that presents to `surface`, which is `size` device pixels in size.
+    pub fn new(device: wgpu::Device, queue: wgpu::Queue, surface: wgpu::Surface, size: Vector2I)
+               -> WgpuDevice {
+        let swap_chain = create_swap_chain(&device, &surface, size);
+        let default_target = DefaultTarget::SwapChain { surface, swap_chain, frame: None };
+        WgpuDevice::with_default_target(device, queue, default_target, MemoryCounter::new(), size)
+    }
+
+    /// Creates a device whose default render target is an offscreen RGBA8 texture, for headless
+    /// rendering.
+    pub fn new_headless(device: wgpu::Device, queue: wgpu::Queue, size: Vector2I) -> WgpuDevice {
+        let texture_memory = MemoryCounter::new();
+        let texture = create_texture(&device, &texture_memory, TextureFormat::RGBA8, size);
+        WgpuDevice::with_default_target(device,
+                                        queue,
+                                        DefaultTarget::Texture(texture),
+                                        texture_memory,
+                                        size)
+    }
+
+    fn with_default_target(device: wgpu::Device,
+                           queue: wgpu::Queue,
+                           default_target: DefaultTarget,
+                           texture_memory: MemoryCounter,
+                           size: Vector2I)
+                           -> WgpuDevice {
+        let samplers = (0..16).map(|sampling_flags_value| {
+            let sampling_flags = TextureSamplingFlags::from_bits(sampling_flags_value).unwrap();
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: if sampling_flags.contains(TextureSamplingFlags::REPEAT_U) {
+                    wgpu::AddressMode::Repeat
+                } else {
+                    wgpu::AddressMode::ClampToEdge
+                },
+                address_mode_v: if sampling_flags.contains(TextureSamplingFlags::REPEAT_V) {
+                    wgpu::AddressMode::Repeat
+                } else {
+                    wgpu::AddressMode::ClampToEdge
+                },
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: if sampling_flags.contains(TextureSamplingFlags::NEAREST_MAG) {
+                    wgpu::FilterMode::Nearest
+                } else {
+                    wgpu::FilterMode::Linear
+                },
+                min_filter: if sampling_flags.contains(TextureSamplingFlags::NEAREST_MIN) {
+                    wgpu::FilterMode::Nearest
+                } else {
+                    wgpu::FilterMode::Linear
+                },
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                lod_min_clamp: 0.0,
+                lod_max_clamp: 0.0,
+                compare: wgpu::CompareFunction::Always,
+            })
+        }).collect();
+
+        let default_depth_stencil_texture = create_depth_stencil_texture(&device, size);
+        let default_depth_stencil_view = default_depth_stencil_texture.create_default_view();
+        let placeholder_texture =
+            create_texture(&device, &texture_memory, TextureFormat::RGBA8, Vector2I::splat(1));
+
+        WgpuDevice {
+            device,
+            queue,
+            default_target: RefCell::new(default_target),
+            default_depth_stencil_texture: RefCell::new(default_depth_stencil_texture),
+            default_depth_stencil_view: RefCell::new(default_depth_stencil_view),
+            encoder: RefCell::new(None),
+            samplers,
+            placeholder_texture,
+            pipelines: RefCell::new(vec![]),
+            next_program_id: Cell::new(0),
+            texture_memory,
+            buffer_memory: MemoryCounter::new(),
+        }
+    }
+
+    /// Resizes the default render target.
+    pub fn resize(&self, new_size: Vector2I) {
+        let mut default_target = self.default_target.borrow_mut();
+        match *default_target {
+            DefaultTarget::SwapChain { ref surface, ref mut swap_chain, ref mut frame } => {
+                *frame = None;
+                *swap_chain = create_swap_chain(&self.device, surface, new_size);
+            }
+            DefaultTarget::Texture(ref mut texture) => {
+                *texture = create_texture(&self.device,
+                                          &self.texture_memory,
+                                          TextureFormat::RGBA8,
+                                          new_size);
+            }
+        }
+
+        let depth_stencil_texture = create_depth_stencil_texture(&self.device, new_size);
+        *self.default_depth_stencil_view.borrow_mut() = depth_stencil_texture.create_default_view();
+        *self.default_depth_stencil_texture.borrow_mut() = depth_stencil_texture;
+    }
+
+    /// Presents the current frame, if rendering to a surface.
+    pub fn present(&self) {
+        if let DefaultTarget::SwapChain { ref mut frame, .. } = *self.default_target.borrow_mut() {
+            // Dropping the swap chain output presents it.
+            *frame = None;
+        }
+    }
+
+    fn acquire_frame_if_necessary(&self) {
+        if let DefaultTarget::SwapChain {
+            ref mut swap_chain,
+            ref mut frame,
+            ..
+        } = *self.default_target.borrow_mut() {
+            if frame.is_none() {
+                *frame = Some(swap_chain.get_next_texture().expect("Timed out acquiring a frame!"));
+            }
+        }
+    }
+
+    // Runs `callback` with the current command encoder, or with a temporary one that is submitted
+    // immediately if we're outside `begin_commands()`/`end_commands()`.
+    fn with_encoder<F, R>(&self, callback: F) -> R where F: FnOnce(&mut CommandEncoder) -> R {
+        let mut encoder = self.encoder.borrow_mut();
+        match *encoder {
+            Some(ref mut encoder) => callback(encoder),
+            None => {
+                let mut encoder = self.create_command_encoder();
+                let result = callback(&mut encoder);
+                self.queue.submit(&[encoder.finish()]);
+                result
+            }
+        }
+    }
+
+    fn create_command_encoder(&self) -> CommandEncoder {
+        self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None })
+    }
+
+    fn create_bind_group_layout(&self, reflection: &ShaderReflection, visibility: wgpu::ShaderStage)
+                                -> BindGroupLayout {
+        let mut bindings = vec![];
+        if let Some(ref uniform_block) = reflection.uniform_block {
+            bindings.push(wgpu::BindGroupLayoutEntry {
+                binding: uniform_block.binding,
+                visibility,
+                ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+            });
+        }
+        for image in &reflection.images {
+            bindings.push(wgpu::BindGroupLayoutEntry {
+                binding: image.binding,
+                visibility,
+                ty: wgpu::BindingType::SampledTexture {
+                    multisampled: false,
+                    dimension: wgpu::TextureViewDimension::D2,
+                    component_type: wgpu::TextureComponentType::Float,
+                },
+            });
+        }
+        for sampler in &reflection.samplers {
+            bindings.push(wgpu::BindGroupLayoutEntry {
+                binding: sampler.binding,
+                visibility,
+                ty: wgpu::BindingType::Sampler { comparison: false },
+            });
+        }
+        for storage_buffer in &reflection.storage_buffers {
+            bindings.push(wgpu::BindGroupLayoutEntry {
+                binding: storage_buffer.binding,
+                visibility,
+                ty: wgpu::BindingType::StorageBuffer { dynamic: false, readonly: false },
+            });
+        }
+        self.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            bindings: &bindings,
+        })
+    }
+
+    fn create_bind_group(&self,
+                         shader: &WgpuShader,
+                         layout: &BindGroupLayout,
+                         uniforms: &[(&WgpuUniform, UniformData)],
+                         textures: &[&WgpuTexture],
+                         storage_buffers: &[(&WgpuStorageBuffer, &WgpuBuffer)])
+                         -> wgpu::BindGroup {
+        let reflection = &shader.reflection;
+        let uniform_buffer = reflection.uniform_block.as_ref().map(|uniform_block| {
+            let data = pack_uniforms(uniform_block, uniforms);
+            (self.device.create_buffer_with_data(&data, BufferUsage::UNIFORM), data.len())
+        });
+
+        let storage_buffers: Vec<_> = reflection.storage_buffers.iter().map(|binding| {
+            let &(_, buffer) = storage_buffers.iter().find(|&&(storage_buffer, _)| {
+                storage_buffer.binding == binding.binding
+            }).unwrap_or_else(|| panic!("No buffer supplied for storage buffer `{}`!",
+                                        binding.name));
+            (binding.binding, buffer.buffer.borrow(), buffer.allocation.size())
+        }).collect();
+
+        let mut bindings = vec![];
+        if let (Some(uniform_block), Some((buffer, size))) = (reflection.uniform_block.as_ref(),
+                                                              uniform_buffer.as_ref()) {
+            bindings.push(wgpu::Binding {
+                binding: uniform_block.binding,
+                resource: wgpu::BindingResource::Buffer { buffer, range: 0..(*size as u64) },
+            });
+        }
+        for image in &reflection.images {
+            let texture = self.texture_for_binding(image, "Image", uniforms, textures);
+            bindings.push(wgpu::Binding {
+                binding: image.binding,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+        }
+        for sampler in &reflection.samplers {
+            let texture = self.texture_for_binding(sampler, "Smplr", uniforms, textures);
+            // wgpu 0.5 can't filter anisotropically, so there are only samplers for the other
+            // flags.
+            let mut sampling_flags = texture.sampling_flags.get();
+            sampling_flags.set_max_anisotropy(1);
+            let sampler_index = sampling_flags.bits() as usize;
+            bindings.push(wgpu::Binding {
+                binding: sampler.binding,
+                resource: wgpu::BindingResource::Sampler(&self.samplers[sampler_index]),
+            });
+        }
+        for &(binding, ref buffer, size) in &storage_buffers {
+            let buffer = buffer.as_ref().expect("Storage buffer not allocated!");
+            bindings.push(wgpu::Binding {
+                binding,
+                resource: wgpu::BindingResource::Buffer { buffer, range: 0..size },
+            });
+        }
+
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            bindings: &bindings,
+        })
+    }
+
+    // Finds the texture bound to the `sampler2D` that the shader build split into `binding`.
+    fn texture_for_binding<'a>(&'a self,
+                               binding: &ResourceBinding,
+                               suffix: &str,
+                               uniforms: &[(&WgpuUniform, UniformData)],
+                               textures: &[&'a WgpuTexture])
+                               -> &'a WgpuTexture {
+        let name = if binding.name.ends_with(suffix) {
+            &binding.name[0..(binding.name.len() - suffix.len())]
+        } else {
+            &binding.name
+        };
+        for &(uniform, ref data) in uniforms {
+            if let UniformData::TextureUnit(unit) = *data {
+                if uniform.name == name {
+                    return textures[unit as usize];
+                }
+            }
+        }
+        &self.placeholder_texture
+    }
+
+    fn pipeline_for_render_state(&self, render_state: &RenderState<WgpuDevice>)
+                                 -> Rc<RenderPipeline> {
+        let key = self.pipeline_key(render_state);
+        if let Some(&(_, ref pipeline)) = self.pipelines
+                                              .borrow()
+                                              .iter()
+                                              .find(|&&(ref other_key, _)| *other_key == key) {
+            return (*pipeline).clone();
+        }
+
+        let vertex_buffers: Vec<_> = key.vertex_buffers.iter().map(|vertex_buffer| {
+            wgpu::VertexBufferDescriptor {
+                stride: vertex_buffer.stride,
+                step_mode: vertex_buffer.step_mode,
+                attributes: &vertex_buffer.attributes,
+            }
+        }).collect();
+
+        let blend = match key.blend {
+            None => wgpu::BlendDescriptor::REPLACE,
+            Some(blend) => {
+                // Pathfinder only ever uses one blend operation for both color and alpha.
+                wgpu::BlendDescriptor {
+                    src_factor: blend.src_rgb_factor.to_wgpu_blend_factor(),
+                    dst_factor: blend.dest_rgb_factor.to_wgpu_blend_factor(),
+                    operation: blend.op.to_wgpu_blend_operation(),
+                }
+            }
+        };
+        let alpha_blend = match key.blend {
+            None => wgpu::BlendDescriptor::REPLACE,
+            Some(blend) => {
+                wgpu::BlendDescriptor {
+                    src_factor: blend.src_alpha_factor.to_wgpu_blend_factor(),
+                    dst_factor: blend.dest_alpha_factor.to_wgpu_blend_factor(),
+                    operation: blend.op.to_wgpu_blend_operation(),
+                }
+            }
+        };
+
+        let depth_stencil_state = key.depth_stencil.map(|depth_stencil| {
+            let pass_op = if depth_stencil.stencil_write {
+                wgpu::StencilOperation::Replace
+            } else {
+                wgpu::StencilOperation::Keep
+            };
+            let stencil_face = wgpu::StencilStateFaceDescriptor {
+                compare: depth_stencil.stencil_compare,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op,
+            };
+            wgpu::DepthStencilStateDescriptor {
+                format: DEPTH_STENCIL_FORMAT,
+                depth_write_enabled: depth_stencil.depth_write,
+                depth_compare: depth_stencil.depth_compare,
+                stencil_front: stencil_face.clone(),
+                stencil_back: stencil_face,
+                stencil_read_mask: depth_stencil.stencil_mask,
+                stencil_write_mask: if depth_stencil.stencil_write {
+                    depth_stencil.stencil_mask
+                } else {
+                    0
+                },
+            }
+        });
+
+        let program = render_state.program.raster();
+        let pipeline = self.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: &program.pipeline_layout,
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &program.vertex.module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &program.fragment.module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                depth_bias: 0,
+                depth_bias_slope_scale: 0.0,
+                depth_bias_clamp: 0.0,
+            }),
+            primitive_topology: key.primitive,
+            color_states: &[wgpu::ColorStateDescriptor {
+                format: key.color_format,
+                color_blend: blend,
+                alpha_blend,
+                write_mask: if key.color_mask {
+                    wgpu::ColorWrite::ALL
+                } else {
+                    wgpu::ColorWrite::empty()
+                },
+            }],
+            depth_stencil_state,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint32,
+                vertex_buffers: &vertex_buffers,
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        });
+
+        let pipeline = Rc::new(pipeline);
+        self.pipelines.borrow_mut().push((key, pipeline.clone()));
+        pipeline
+    }
+
+    fn pipeline_key(&self, render_state: &RenderState<WgpuDevice>) -> PipelineKey {
+        let mut vertex_buffers: Vec<VertexBufferKey> = vec![];
+        for &(location, ref descriptor) in render_state.vertex_array.attributes.borrow().iter() {
+            let buffer_index = descriptor.buffer_index as usize;
+            while vertex_buffers.len() <= buffer_index {
+                vertex_buffers.push(VertexBufferKey {
+                    stride: 0,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: vec![],
+                });
+            }
+            let vertex_buffer = &mut vertex_buffers[buffer_index];
+            vertex_buffer.stride = descriptor.stride as u64;
+            if descriptor.divisor != 0 {
+                vertex_buffer.step_mode = wgpu::InputStepMode::Instance;
+            }
+            vertex_buffer.attributes.push(wgpu::VertexAttributeDescriptor {
+                offset: descriptor.offset as u64,
+                format: descriptor.to_wgpu_vertex_format(),
+                shader_location: location,
+            });
+        }
+
+        let depth_stencil = if self.render_target_has_depth(render_state.target) {
+            let options = &render_state.options;
+            let (depth_compare, depth_write) = match options.depth {
+                None => (wgpu::CompareFunction::Always, false),
+                Some(depth) => (depth.func.to_wgpu_compare_function(), depth.write),
+            };
+            let (stencil_compare, stencil_mask, stencil_write) = match options.stencil {
+                None => (wgpu::CompareFunction::Always, 0, false),
+                Some(stencil) => {
+                    (stencil.func.to_wgpu_compare_function(), stencil.mask, stencil.write)
+                }
+            };
+            Some(DepthStencilKey {
+                depth_compare,
+                depth_write,
+                stencil_compare,
+                stencil_mask,
+                stencil_write,
+            })
+        } else {
+            None
+        };
+
+        PipelineKey {
+            program_id: render_state.program.raster().id,
+            vertex_buffers,
+            primitive: render_state.primitive.to_wgpu_primitive_topology(),
+            color_format: self.render_target_format(render_state.target),
+            blend: render_state.options.blend,
+            color_mask: render_state.options.color_mask,
+            depth_stencil,
+        }
+    }
+
+    fn render_target_format(&self, render_target: &RenderTarget<WgpuDevice>)
+                            -> wgpu::TextureFormat {
+        match *render_target {
+            RenderTarget::Default => {
+                match *self.default_target.borrow() {
+                    DefaultTarget::SwapChain { .. } => SWAP_CHAIN_FORMAT,
+                    DefaultTarget::Texture(ref texture) => texture.format.to_wgpu_format(),
+                }
+            }
+            RenderTarget::Framebuffer(framebuffer) => framebuffer.0.format.to_wgpu_format(),
+        }
+    }
+
+    fn render_target_has_depth(&self, render_target: &RenderTarget<WgpuDevice>) -> bool {
+        match *render_target {
+            RenderTarget::Default => true,
+            RenderTarget::Framebuffer(_) => false,
+        }
+    }
+
+    fn draw(&self,
+            render_state: &RenderState<WgpuDevice>,
+            indexed: bool,
+            count: u32,
+            instance_count: u32) {
+        let program = render_state.program.raster();
+        let pipeline = self.pipeline_for_render_state(render_state);
+        let vertex_bind_group = self.create_bind_group(&program.vertex,
+                                                       &program.bind_group_layouts[0],
+                                                       render_state.uniforms,
+                                                       render_state.textures,
+                                                       &[]);
+        let fragment_bind_group = self.create_bind_group(&program.fragment,
+                                                         &program.bind_group_layouts[1],
+                                                         render_state.uniforms,
+                                                         render_state.textures,
+                                                         &[]);
+
+        let vertex_buffers = render_state.vertex_array.vertex_buffers.borrow();
+        let vertex_buffers: Vec<_> = vertex_buffers.iter().map(|vertex_buffer| {
+            vertex_buffer.buffer.borrow()
+        }).collect();
+        let index_buffer = render_state.vertex_array.index_buffer.borrow();
+        let index_buffer = index_buffer.as_ref().map(|index_buffer| index_buffer.buffer.borrow());
+
+        if let RenderTarget::Default = *render_state.target {
+            self.acquire_frame_if_necessary();
+        }
+        let default_target = self.default_target.borrow();
+        let color_view = match *render_state.target {
+            RenderTarget::Default => {
+                match *default_target {
+                    DefaultTarget::SwapChain { ref frame, .. } => &frame.as_ref().unwrap().view,
+                    DefaultTarget::Texture(ref texture) => &texture.view,
+                }
+            }
+            RenderTarget::Framebuffer(framebuffer) => &framebuffer.0.view,
+        };
+        let depth_stencil_view = self.default_depth_stencil_view.borrow();
+
+        let clear_ops = &render_state.options.clear_ops;
+        let color_attachment = wgpu::RenderPassColorAttachmentDescriptor {
+            attachment: color_view,
+            resolve_target: None,
+            load_op: if clear_ops.color.is_some() {
+                wgpu::LoadOp::Clear
+            } else {
+                wgpu::LoadOp::Load
+            },
+            store_op: wgpu::StoreOp::Store,
+            clear_color: match clear_ops.color {
+                None => wgpu::Color::TRANSPARENT,
+                Some(color) => {
+                    wgpu::Color {
+                        r: color.r() as f64,
+                        g: color.g() as f64,
+                        b: color.b() as f64,
+                        a: color.a() as f64,
+                    }
+                }
+            },
+        };
+        let depth_stencil_attachment = if self.render_target_has_depth(render_state.target) {
+            Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &*depth_stencil_view,
+                depth_load_op: if clear_ops.depth.is_some() {
+                    wgpu::LoadOp::Clear
+                } else {
+                    wgpu::LoadOp::Load
+                },
+                depth_store_op: wgpu::StoreOp::Store,
+                clear_depth: clear_ops.depth.unwrap_or(1.0),
+                stencil_load_op: if clear_ops.stencil.is_some() {
+                    wgpu::LoadOp::Clear
+                } else {
+                    wgpu::LoadOp::Load
+                },
+                stencil_store_op: wgpu::StoreOp::Store,
+                clear_stencil: clear_ops.stencil.unwrap_or(0) as u32,
+            })
+        } else {
+            None
+        };
+
+        self.with_encoder(|encoder| {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[color_attachment],
+                depth_stencil_attachment,
+            });
+
+            let viewport = render_state.viewport;
+            pass.set_viewport(viewport.origin().x() as f32,
+                              viewport.origin().y() as f32,
+                              viewport.size().x() as f32,
+                              viewport.size().y() as f32,
+                              0.0,
+                              1.0);
+            if let Some(scissor) = render_state.options.scissor {
+                // The scissor rect has to lie within the render target.
+                let scissor = scissor.intersection(RectI::new(Vector2I::default(),
+                                                              viewport.size()))
+                                     .unwrap_or_default();
+                pass.set_scissor_rect((viewport.min_x() + scissor.min_x()) as u32,
+                                      (viewport.min_y() + scissor.min_y()) as u32,
+                                      scissor.width() as u32,
+                                      scissor.height() as u32);
+            }
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(VERTEX_BIND_GROUP_INDEX, &vertex_bind_group, &[]);
+            pass.set_bind_group(FRAGMENT_BIND_GROUP_INDEX, &fragment_bind_group, &[]);
+            if let Some(stencil) = render_state.options.stencil {
+                pass.set_stencil_reference(stencil.reference);
+            }
+
+            for (slot, vertex_buffer) in vertex_buffers.iter().enumerate() {
+                let vertex_buffer = vertex_buffer.as_ref().expect("Vertex buffer not allocated!");
+                pass.set_vertex_buffer(slot as u32, vertex_buffer, 0, 0);
+            }
+
+            if indexed {
+                let index_buffer = index_buffer.as_ref().expect("No index buffer bound to VAO!");
+                let index_buffer = index_buffer.as_ref().expect("Index buffer not allocated!");
+                pass.set_index_buffer(index_buffer, 0, 0);
+                pass.draw_indexed(0..count, 0, 0..instance_count);
+            } else {
+                pass.draw(0..count, 0..instance_count);
+            }
+        });
+    }
+
+    fn dispatch(&self, compute_state: &ComputeState<WgpuDevice>, dispatch: WgpuDispatch) {
+        let program = compute_state.program.compute();
+        let bind_group = self.create_bind_group(&program.shader,
+                                                &program.bind_group_layout,
+                                                compute_state.uniforms,
+                                                compute_state.textures,
+                                                compute_state.storage_buffers);
+
+        self.with_encoder(|encoder| {
+            let mut pass = encoder.begin_compute_pass();
+            pass.set_pipeline(&program.pipeline);
+            pass.set_bind_group(COMPUTE_BIND_GROUP_INDEX, &bind_group, &[]);
+            match dispatch {
+                WgpuDispatch::Direct(dimensions) => {
+                    pass.dispatch(dimensions.x, dimensions.y, dimensions.z)
+                }
+                WgpuDispatch::Indirect(buffer, offset) => {
+                    let buffer = buffer.buffer.borrow();
+                    let buffer = buffer.as_ref().expect("Indirect dispatch buffer not allocated!");
+                    pass.dispatch_indirect(buffer, offset as u64);
+                }
+            }
+        });
+    }
+}
+
+impl Device for WgpuDevice {
+    type Buffer = WgpuBuffer;
+    type Fence = WgpuFence;
+    type Framebuffer = WgpuFramebuffer;
+    type Program = WgpuProgram;
+    type Shader = WgpuShader;
+    type StorageBuffer = WgpuStorageBuffer;
+    type Texture = WgpuTexture;
+    type TextureDataReceiver = WgpuTextureDataReceiver;
+    type TimerQuery = WgpuTimerQuery;
+    type Uniform = WgpuUniform;
+    type VertexArray = WgpuVertexArray;
+    type VertexAttr = WgpuVertexAttr;
+
+    fn create_texture(&self, format: TextureFormat, size: Vector2I) -> WgpuTexture {
+        create_texture(&self.device, &self.texture_memory, format, size)
+    }
+
+    fn create_texture_from_data(&self, format: TextureFormat, size: Vector2I, data: TextureDataRef)
+                                -> WgpuTexture {
+        let texture = self.create_texture(format, size);
+        self.upload_to_texture(&texture, RectI::new(Vector2I::default(), size), data);
+        texture
+    }
+
+    fn create_shader_from_source(&self, name: &str, source: &[u8], _: ShaderKind) -> WgpuShader {
+        let words = wgpu::read_spirv(Cursor::new(source))
+            .unwrap_or_else(|_| panic!("Shader `{}` isn't valid SPIR-V!", name));
+        let reflection = ShaderReflection::from_words(&words);
+        let module = self.device.create_shader_module(&words);
+        WgpuShader { module, reflection }
+    }
+
+    fn create_vertex_array(&self) -> WgpuVertexArray {
+        WgpuVertexArray {
+            vertex_buffers: RefCell::new(vec![]),
+            index_buffer: RefCell::new(None),
+            attributes: RefCell::new(vec![]),
+        }
+    }
+
+    fn bind_buffer(&self,
+                   vertex_array: &WgpuVertexArray,
+                   buffer: &WgpuBuffer,
+                   target: BufferTarget) {
+        match target {
+            BufferTarget::Vertex => {
+                vertex_array.vertex_buffers.borrow_mut().push((*buffer).clone())
+            }
+            BufferTarget::Index => {
+                *vertex_array.index_buffer.borrow_mut() = Some((*buffer).clone())
+            }
+            BufferTarget::Storage => {
+                // Storage buffers are bound per dispatch, through `ComputeState`.
+            }
+        }
+    }
+
+    fn create_program_from_shaders(&self,
+                                   _: &dyn ResourceLoader,
+                                   _: &str,
+                                   vertex_shader: WgpuShader,
+                                   fragment_shader: WgpuShader)
+                                   -> WgpuProgram {
+        let bind_group_layouts = [
+            self.create_bind_group_layout(&vertex_shader.reflection, wgpu::ShaderStage::VERTEX),
+            self.create_bind_group_layout(&fragment_shader.reflection,
+                                          wgpu::ShaderStage::FRAGMENT),
+        ];
+        let pipeline_layout =
+            self.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                bind_group_layouts: &[&bind_group_layouts[0], &bind_group_layouts[1]],
+            });
+
+        let id = self.next_program_id.get();
+        self.next_program_id.set(id + 1);
+
+        WgpuProgram::Raster(WgpuRasterProgram {
+            id,
+            vertex: vertex_shader,
+            fragment: fragment_shader,
+            bind_group_layouts,
+            pipeline_layout,
+        })
+    }
+
+    fn create_compute_program_from_shader(&self,
+                                          _: &dyn ResourceLoader,
+                                          _: &str,
+                                          shader: WgpuShader)
+                                          -> WgpuProgram {
+        let bind_group_layout = self.create_bind_group_layout(&shader.reflection,
+                                                              wgpu::ShaderStage::COMPUTE);
+        let pipeline_layout =
+            self.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                bind_group_layouts: &[&bind_group_layout],
+            });
+        let pipeline = self.device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            layout: &pipeline_layout,
+            compute_stage: wgpu::ProgrammableStageDescriptor {
+                module: &shader.module,
+                entry_point: "main",
+            },
+        });
+        WgpuProgram::Compute(WgpuComputeProgram { shader, bind_group_layout, pipeline })
+    }
+
+    // The workgroup size is baked into the SPIR-V, so there's nothing to do here.
+    #[inline]
+    fn set_compute_program_local_size(&self, _: &mut WgpuProgram, _: ComputeDimensions) {}
+
+    #[inline]
+    fn get_storage_buffer(&self, _: &WgpuProgram, _: &str, binding: u32) -> WgpuStorageBuffer {
+        WgpuStorageBuffer { binding }
+    }
+
+    fn get_vertex_attr(&self, program: &WgpuProgram, name: &str) -> Option<WgpuVertexAttr> {
+        let name = format!("a{}", name);
+        let reflection = &program.raster().vertex.reflection;
+        reflection.input_location(&name).map(|location| WgpuVertexAttr { location })
+    }
+
+    fn get_uniform(&self, _: &WgpuProgram, name: &str) -> WgpuUniform {
+        WgpuUniform { name: format!("u{}", name) }
+    }
+
+    fn configure_vertex_attr(&self,
+                             vertex_array: &WgpuVertexArray,
+                             attr: &WgpuVertexAttr,
+                             descriptor: &VertexAttrDescriptor) {
+        debug_assert_ne!(descriptor.stride, 0);
+        let mut attributes = vertex_array.attributes.borrow_mut();
+        attributes.retain(|&(location, _)| location != attr.location);
+        attributes.push((attr.location, *descriptor));
+    }
+
+    fn create_framebuffer(&self, texture: WgpuTexture) -> WgpuFramebuffer {
+        WgpuFramebuffer(texture)
+    }
+
+    fn create_buffer(&self) -> WgpuBuffer {
+        WgpuBuffer {
+            buffer: Rc::new(RefCell::new(None)),
+            allocation: Rc::new(self.buffer_memory.allocate(0)),
+        }
+    }
+
+    fn allocate_buffer<T>(&self,
+                          buffer: &WgpuBuffer,
+                          data: BufferData<T>,
+                          target: BufferTarget,
+                          _: BufferUploadMode) {
+        let usage = match target {
+            BufferTarget::Vertex => BufferUsage::VERTEX,
+            BufferTarget::Index => BufferUsage::INDEX,
+            // Storage buffers are commonly written by one dispatch and drive the next.
+            BufferTarget::Storage => BufferUsage::STORAGE | BufferUsage::INDIRECT,
+        } | BufferUsage::COPY_DST;
+
+        // wgpu wants buffer sizes to be multiples of 4.
+        let (new_buffer, size) = match data {
+            BufferData::Uninitialized(size) => {
+                let size = (size * mem::size_of::<T>() + 3) & !3;
+                let new_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: None,
+                    size: size as u64,
+                    usage,
+                });
+                (new_buffer, size)
+            }
+            BufferData::Memory(slice) => {
+                let size = slice.len() * mem::size_of::<T>();
+                let bytes = unsafe { slice::from_raw_parts(slice.as_ptr() as *const u8, size) };
+                let mut bytes = bytes.to_vec();
+                bytes.resize((size + 3) & !3, 0);
+                (self.device.create_buffer_with_data(&bytes, usage), bytes.len())
+            }
+        };
+        *buffer.buffer.borrow_mut() = Some(new_buffer);
+        buffer.allocation.resize(size as u64);
+    }
+
+    #[inline]
+    fn framebuffer_texture<'f>(&self, framebuffer: &'f WgpuFramebuffer) -> &'f WgpuTexture {
+        &framebuffer.0
+    }
+
+    #[inline]
+    fn destroy_framebuffer(&self, framebuffer: WgpuFramebuffer) -> WgpuTexture {
+        framebuffer.0
+    }
+
+    #[inline]
+    fn texture_format(&self, texture: &WgpuTexture) -> TextureFormat {
+        texture.format
+    }
+
+    #[inline]
+    fn texture_size(&self, texture: &WgpuTexture) -> Vector2I {
+        texture.size
+    }
+
+    fn set_texture_sampling_mode(&self, texture: &WgpuTexture, flags: TextureSamplingFlags) {
+        texture.sampling_flags.set(flags)
+    }
+
+    fn upload_to_texture(&self, texture: &WgpuTexture, rect: RectI, data: TextureDataRef) {
+        assert!(rect.size().x() >= 0);
+        assert!(rect.size().y() >= 0);
+        assert!(rect.max_x() <= texture.size.x());
+        assert!(rect.max_y() <= texture.size.y());
+        if rect.size().x() == 0 || rect.size().y() == 0 {
+            return;
+        }
+
+        let data_ptr = data.check_and_extract_data_ptr(rect.size(), texture.format);
+        let bytes_per_row = rect.size().x() as usize * texture.format.bytes_per_pixel();
+        let padded_bytes_per_row = align_row(bytes_per_row);
+        let row_count = rect.size().y() as usize;
+
+        let mut staging_data = vec![0; padded_bytes_per_row * row_count];
+        unsafe {
+            let data = slice::from_raw_parts(data_ptr as *const u8, bytes_per_row * row_count);
+            for (src_row, dest_row) in data.chunks(bytes_per_row)
+                                           .zip(staging_data.chunks_mut(padded_bytes_per_row)) {
+                dest_row[0..bytes_per_row].copy_from_slice(src_row);
+            }
+        }
+        let staging_buffer = self.device.create_buffer_with_data(&staging_data,
+                                                                 BufferUsage::COPY_SRC);
+
+        self.with_encoder(|encoder| {
+            encoder.copy_buffer_to_texture(wgpu::BufferCopyView {
+                buffer: &staging_buffer,
+                offset: 0,
+                bytes_per_row: padded_bytes_per_row as u32,
+                rows_per_image: row_count as u32,
+            }, wgpu::TextureCopyView {
+                texture: &texture.texture,
+                mip_level: 0,
+                array_layer: 0,
+                origin: wgpu::Origin3d {
+                    x: rect.origin().x() as u32,
+                    y: rect.origin().y() as u32,
+                    z: 0,
+                },
+            }, rect_extent(rect));
+        });
+    }
+
+    fn read_pixels(&self, target: &RenderTarget<WgpuDevice>, viewport: RectI)
+                   -> WgpuTextureDataReceiver {
+        let default_target = self.default_target.borrow();
+        let texture = match *target {
+            RenderTarget::Default => {
+                match *default_target {
+                    DefaultTarget::Texture(ref texture) => texture,
+                    DefaultTarget::SwapChain { .. } => {
+                        panic!("Reading back from a swap chain is unsupported!")
+                    }
+                }
+            }
+            RenderTarget::Framebuffer(framebuffer) => &framebuffer.0,
+        };
+
+        let bytes_per_row = viewport.size().x() as usize * texture.format.bytes_per_pixel();
+        let padded_bytes_per_row = align_row(bytes_per_row);
+        let buffer_size = (padded_bytes_per_row * viewport.size().y() as usize) as u64;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: buffer_size,
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+        });
+
+        // The copy has to be submitted before the buffer can be mapped.
+        let was_recording = self.encoder.borrow().is_some();
+        let mut encoder = self.encoder.borrow_mut().take().unwrap_or_else(|| {
+            self.create_command_encoder()
+        });
+        encoder.copy_texture_to_buffer(wgpu::TextureCopyView {
+            texture: &texture.texture,
+            mip_level: 0,
+            array_layer: 0,
+            origin: wgpu::Origin3d {
+                x: viewport.origin().x() as u32,
+                y: viewport.origin().y() as u32,
+                z: 0,
+            },
+        }, wgpu::BufferCopyView {
+            buffer: &buffer,
+            offset: 0,
+            bytes_per_row: padded_bytes_per_row as u32,
+            rows_per_image: viewport.size().y() as u32,
+        }, rect_extent(viewport));
+        self.queue.submit(&[encoder.finish()]);
+        if was_recording {
+            *self.encoder.borrow_mut() = Some(self.create_command_encoder());
+        }
+
+        let mapping = Box::pin(buffer.map_read(0, buffer_size));
+        WgpuTextureDataReceiver {
+            state: RefCell::new(WgpuTextureDataReceiverState::Pending { buffer, mapping }),
+            format: texture.format,
+            size: viewport.size(),
+            padded_bytes_per_row,
+        }
+    }
+
+    fn begin_commands(&self) {
+        *self.encoder.borrow_mut() = Some(self.create_command_encoder());
+    }
+
+    fn end_commands(&self) {
+        let encoder = self.encoder.borrow_mut().take().expect("Not recording commands!");
+        self.queue.submit(&[encoder.finish()]);
+    }
+
+    fn draw_arrays(&self, index_count: u32, render_state: &RenderState<WgpuDevice>) {
+        self.draw(render_state, false, index_count, 1);
+    }
+
+    fn draw_elements(&self, index_count: u32, render_state: &RenderState<WgpuDevice>) {
+        self.draw(render_state, true, index_count, 1);
+    }
+
+    fn draw_elements_instanced(&self,
+                               index_count: u32,
+                               instance_count: u32,
+                               render_state: &RenderState<WgpuDevice>) {
+        self.draw(render_state, true, index_count, instance_count);
+    }
+
+    #[inline]
+    fn create_timer_query(&self) -> WgpuTimerQuery {
+        WgpuTimerQuery
+    }
+
+    #[inline]
+    fn begin_timer_query(&self, _: &WgpuTimerQuery) {}
+
+    #[inline]
+    fn end_timer_query(&self, _: &WgpuTimerQuery) {}
+
+    #[inline]
+    fn try_recv_timer_query(&self, _: &WgpuTimerQuery) -> Option<Duration> {
+        Some(Duration::default())
+    }
+
+    #[inline]
+    fn recv_timer_query(&self, _: &WgpuTimerQuery) -> Duration {
+        Duration::default()
+    }
+
+    fn memory_usage(&self) -> GPUMemoryUsage {
+        GPUMemoryUsage {
+            textures: self.texture_memory.get(),
+            buffers: self.buffer_memory.get(),
+        }
+    }
+
+    // wgpu 0.5 has no compressed texture formats.
+    #[inline]
+    fn supports_texture_format(&self, format: TextureFormat) -> bool {
+        !format.is_compressed()
+    }
+
+    // wgpu has no fences, but a buffer mapping can't complete before the submission that copies
+    // into the buffer, which in turn can't complete before earlier submissions.
+    fn insert_fence(&self) -> WgpuFence {
+        let source = self.device.create_buffer_with_data(&[0; 4], BufferUsage::COPY_SRC);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 4,
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+        });
+
+        let was_recording = self.encoder.borrow().is_some();
+        let mut encoder = self.encoder.borrow_mut().take().unwrap_or_else(|| {
+            self.create_command_encoder()
+        });
+        encoder.copy_buffer_to_buffer(&source, 0, &buffer, 0, 4);
+        self.queue.submit(&[encoder.finish()]);
+        if was_recording {
+            *self.encoder.borrow_mut() = Some(self.create_command_encoder());
+        }
+
+        let mapping = Box::pin(buffer.map_read(0, 4));
+        WgpuFence { state: RefCell::new(WgpuFenceState::Pending { buffer, mapping }) }
+    }
+
+    fn is_fence_signaled(&self, fence: &WgpuFence) -> bool {
+        self.device.poll(wgpu::Maintain::Poll);
+        fence.poll()
+    }
+
+    fn wait_for_fence(&self, fence: &WgpuFence) {
+        while !fence.poll() {
+            self.device.poll(wgpu::Maintain::Wait);
+        }
+    }
+
+    // All of our work goes to the one queue, which wgpu already orders.
+    #[inline]
+    fn gpu_wait_for_fence(&self, _: &WgpuFence) {}
+
+    // wgpu doesn't report device loss yet.
+    #[inline]
+    fn check_device(&self) -> Result<(), DeviceLostError> {
+        Ok(())
+    }
+
+    // wgpu has no global pipeline state; each pass we encode sets everything it uses.
+    #[inline]
+    fn save_state(&self) {}
+
+    #[inline]
+    fn restore_state(&self) {}
+
+    #[inline]
+    fn supports_dual_source_blending(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn supports_advanced_blending(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn supports_compute(&self) -> bool {
+        true
+    }
+
+    fn dispatch_compute(&self,
+                        dimensions: ComputeDimensions,
+                        compute_state: &ComputeState<WgpuDevice>) {
+        self.dispatch(compute_state, WgpuDispatch::Direct(dimensions));
+    }
+
+    fn dispatch_compute_indirect(&self,
+                                 buffer: &WgpuBuffer,
+                                 offset: usize,
+                                 compute_state: &ComputeState<WgpuDevice>) {
+        self.dispatch(compute_state, WgpuDispatch::Indirect(buffer, offset));
+    }
+
+    fn try_recv_texture_data(&self, receiver: &WgpuTextureDataReceiver) -> Option<TextureData> {
+        self.device.poll(wgpu::Maintain::Poll);
+        receiver.try_recv()
+    }
+
+    fn recv_texture_data(&self, receiver: &WgpuTextureDataReceiver) -> TextureData {
+        loop {
+            self.device.poll(wgpu::Maintain::Wait);
+            if let Some(texture_data) = receiver.try_recv() {
+                return texture_data;
+            }
+        }
+    }
+
+    #[inline]
+    fn create_shader(&self, resources: &dyn ResourceLoader, name: &str, kind: ShaderKind)
+                     -> WgpuShader {
+        let suffix = match kind {
+            ShaderKind::Vertex => 'v',
+            ShaderKind::Fragment => 'f',
+            ShaderKind::Compute => 'c',
+        };
+        let path = format!("shaders/spirv/{}.{}s.spv", name, suffix);
+        self.create_shader_from_source(name, &resources.slurp(&path).unwrap(), kind)
+    }
+}
+
+impl WgpuFence {
+    fn poll(&self) -> bool {
+        let mut state = self.state.borrow_mut();
+        match *state {
+            WgpuFenceState::Pending { ref mut mapping, .. } => {
+                if mapping.as_mut().now_or_never().is_none() {
+                    return false;
+                }
+            }
+            WgpuFenceState::Signaled => return true,
+        }
+        *state = WgpuFenceState::Signaled;
+        true
+    }
+}
+
+impl WgpuTextureDataReceiver {
+    fn try_recv(&self) -> Option<TextureData> {
+        let mut state = self.state.borrow_mut();
+        let mapping = match *state {
+            WgpuTextureDataReceiverState::Pending { ref mut mapping, .. } => {
+                match mapping.as_mut().now_or_never() {
+                    None => return None,
+                    Some(mapping) => mapping.expect("Failed to map the readback buffer!"),
+                }
+            }
+            WgpuTextureDataReceiverState::Downloaded(_) => {
+                match mem::replace(&mut *state, WgpuTextureDataReceiverState::Finished) {
+                    WgpuTextureDataReceiverState::Downloaded(texture_data) => {
+                        return Some(texture_data)
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            WgpuTextureDataReceiverState::Finished => {
+                panic!("Tried to receive texture data twice!")
+            }
+        };
+
+        let bytes_per_row = self.size.x() as usize * self.format.bytes_per_pixel();
+        let mut pixels = Vec::with_capacity(bytes_per_row * self.size.y() as usize);
+        for row in mapping.as_slice().chunks(self.padded_bytes_per_row) {
+            pixels.extend_from_slice(&row[0..bytes_per_row]);
+        }
+
+        let texture_data = match self.format {
+            TextureFormat::R8 | TextureFormat::RGBA8 => TextureData::U8(pixels),
+            TextureFormat::R16F | TextureFormat::RGBA16F => {
+                TextureData::F16(pixels.chunks(2).map(|bytes| {
+                    half::f16::from_bits(u16::from_ne_bytes([bytes[0], bytes[1]]))
+                }).collect())
+            }
+            TextureFormat::RGBA32F => {
+                TextureData::F32(pixels.chunks(4).map(|bytes| {
+                    f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                }).collect())
+            }
+            _ => panic!("Can't read back compressed textures!"),
+        };
+        *state = WgpuTextureDataReceiverState::Finished;
+        Some(texture_data)
+    }
+}
+
+// Lays out the uniforms in `render_state` according to the reflected uniform block.
+fn pack_uniforms(uniform_block: &UniformBlock, uniforms: &[(&WgpuUniform, UniformData)])
+                 -> Vec<u8> {
+    let mut data = vec![0; uniform_block.size as usize];
+    for &(uniform, ref uniform_data) in uniforms {
+        let member = match uniform_block.member(&uniform.name) {
+            None => continue,
+            Some(member) => member,
+        };
+        let (offset, matrix_stride) = (member.offset as usize, member.matrix_stride as usize);
+        match *uniform_data {
+            UniformData::Float(value) => write_f32s(&mut data, offset, &[value]),
+            UniformData::IVec3(values) => write_i32s(&mut data, offset, &values),
+            UniformData::Int(value) => write_i32s(&mut data, offset, &[value]),
+            UniformData::Mat2(matrix) => {
+                write_f32s(&mut data, offset, &[matrix.x(), matrix.y()]);
+                write_f32s(&mut data, offset + matrix_stride, &[matrix.z(), matrix.w()]);
+            }
+            UniformData::Mat4(matrix) => {
+                for (column_index, column) in matrix.iter().enumerate() {
+                    write_f32s(&mut data,
+                               offset + column_index * matrix_stride,
+                               &[column.x(), column.y(), column.z(), column.w()]);
+                }
+            }
+            UniformData::Vec2(vector) => write_f32s(&mut data, offset, &[vector.x(), vector.y()]),
+            UniformData::Vec3(values) => write_f32s(&mut data, offset, &values),
+            UniformData::Vec4(vector) => {
+                write_f32s(&mut data, offset, &[vector.x(), vector.y(), vector.z(), vector.w()])
+            }
+            UniformData::TextureUnit(_) => {}
+        }
+    }
+    data
+}
+
+fn write_f32s(data: &mut [u8], offset: usize, values: &[f32]) {
+    for (index, value) in values.iter().enumerate() {
+        let start = offset + index * 4;
+        data[start..(start + 4)].copy_from_slice(&value.to_ne_bytes());
+    }
+}
+
+fn write_i32s(data: &mut [u8], offset: usize, values: &[i32]) {
+    for (index, value) in values.iter().enumerate() {
+        let start = offset + index * 4;
+        data[start..(start + 4)].copy_from_slice(&value.to_ne_bytes());
+    }
+}
+
+fn align_row(bytes_per_row: usize) -> usize {
+    (bytes_per_row + ROW_ALIGNMENT - 1) / ROW_ALIGNMENT * ROW_ALIGNMENT
+}
+
+fn rect_extent(rect: RectI) -> wgpu::Extent3d {
+    wgpu::Extent3d { width: rect.size().x() as u32, height: rect.size().y() as u32, depth: 1 }
+}
+
+fn create_texture(device: &wgpu::Device,
+                  texture_memory: &MemoryCounter,
+                  format: TextureFormat,
+                  size: Vector2I)
+                  -> WgpuTexture {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d { width: size.x() as u32, height: size.y() as u32, depth: 1 },
+        array_layer_count: 1,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: format.to_wgpu_format(),
+        usage: TextureUsage::SAMPLED | TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::COPY_SRC |
+            TextureUsage::COPY_DST,
+    });
+    let view = texture.create_default_view();
+    WgpuTexture {
+        texture,
+        view,
+        format,
+        size,
+        sampling_flags: Cell::new(TextureSamplingFlags::empty()),
+        allocation: texture_memory.allocate(size.x() as u64 * size.y() as u64 *
+                                            format.bytes_per_pixel() as u64),
+    }
+}
+
+fn create_depth_stencil_texture(device: &wgpu::Device, size: Vector2I) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d { width: size.x() as u32, height: size.y() as u32, depth: 1 },
+        array_layer_count: 1,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_STENCIL_FORMAT,
+        usage: TextureUsage::OUTPUT_ATTACHMENT,
+    })
+}
+
+fn create_swap_chain(device: &wgpu::Device, surface: &wgpu::Surface, size: Vector2I)
+                     -> SwapChain {
+    device.create_swap_chain(surface, &wgpu::SwapChainDescriptor {
+        usage: TextureUsage::OUTPUT_ATTACHMENT,
+        format: SWAP_CHAIN_FORMAT,
+        width: size.x() as u32,
+        height: size.y() as u32,
+        present_mode: wgpu::PresentMode::Fifo,
+    })
+}
+
+trait BlendFactorExt {
+    fn to_wgpu_blend_factor(self) -> wgpu::BlendFactor;
+}
+
+impl BlendFactorExt for BlendFactor {
+    #[inline]
+    fn to_wgpu_blend_factor(self) -> wgpu::BlendFactor {
+        match self {
+            BlendFactor::Zero => wgpu::BlendFactor::Zero,
+            BlendFactor::One => wgpu::BlendFactor::One,
+            BlendFactor::SrcAlpha => wgpu::BlendFactor::SrcAlpha,
+            BlendFactor::OneMinusSrcAlpha => wgpu::BlendFactor::OneMinusSrcAlpha,
+            BlendFactor::DestAlpha => wgpu::BlendFactor::DstAlpha,
+            BlendFactor::OneMinusDestAlpha => wgpu::BlendFactor::OneMinusDstAlpha,
+            BlendFactor::DestColor => wgpu::BlendFactor::DstColor,
+            BlendFactor::Src1Alpha | BlendFactor::OneMinusSrc1Alpha => {
+                panic!("wgpu doesn't support dual-source blending!")
+            }
+        }
+    }
+}
+
+trait BlendOpExt {
+    fn to_wgpu_blend_operation(self) -> wgpu::BlendOperation;
+}
+
+impl BlendOpExt for BlendOp {
+    #[inline]
+    fn to_wgpu_blend_operation(self) -> wgpu::BlendOperation {
+        match self {
+            BlendOp::Add => wgpu::BlendOperation::Add,
+            BlendOp::Subtract => wgpu::BlendOperation::Subtract,
+            BlendOp::ReverseSubtract => wgpu::BlendOperation::ReverseSubtract,
+            BlendOp::Min => wgpu::BlendOperation::Min,
+            BlendOp::Max => wgpu::BlendOperation::Max,
+            _ => panic!("wgpu doesn't support advanced blend equations!"),
+        }
+    }
+}
+
+trait DepthFuncExt {
+    fn to_wgpu_compare_function(self) -> wgpu::CompareFunction;
+}
+
+impl DepthFuncExt for DepthFunc {
+    #[inline]
+    fn to_wgpu_compare_function(self) -> wgpu::CompareFunction {
+        match self {
+            DepthFunc::Less => wgpu::CompareFunction::Less,
+            DepthFunc::Always => wgpu::CompareFunction::Always,
+        }
+    }
+}
+
+trait StencilFuncExt {
+    fn to_wgpu_compare_function(self) -> wgpu::CompareFunction;
+}
+
+impl StencilFuncExt for StencilFunc {
+    #[inline]
+    fn to_wgpu_compare_function(self) -> wgpu::CompareFunction {
+        match self {
+            StencilFunc::Always => wgpu::CompareFunction::Always,
+            StencilFunc::Equal => wgpu::CompareFunction::Equal,
+        }
+    }
+}
+
+trait PrimitiveExt {
+    fn to_wgpu_primitive_topology(self) -> wgpu::PrimitiveTopology;
+}
+
+impl PrimitiveExt for Primitive {
+    #[inline]
+    fn to_wgpu_primitive_topology(self) -> wgpu::PrimitiveTopology {
+        match self {
+            Primitive::Triangles => wgpu::PrimitiveTopology::TriangleList,
+            Primitive::Lines => wgpu::PrimitiveTopology::LineList,
+        }
+    }
+}
+
+trait TextureFormatExt {
+    fn to_wgpu_format(self) -> wgpu::TextureFormat;
+}
+
+impl TextureFormatExt for TextureFormat {
+    #[inline]
+    fn to_wgpu_format(self) -> wgpu::TextureFormat {
+        match self {
+            TextureFormat::R8 => wgpu::TextureFormat::R8Unorm,
+            TextureFormat::R16F => wgpu::TextureFormat::R16Float,
+            TextureFormat::RGBA8 => wgpu::TextureFormat::Rgba8Unorm,
+            TextureFormat::RGBA16F => wgpu::TextureFormat::Rgba16Float,
+            TextureFormat::RGBA32F => wgpu::TextureFormat::Rgba32Float,
+            _ => panic!("wgpu doesn't support compressed texture formats yet!"),
+        }
+    }
+}
+
+trait VertexAttrDescriptorExt {
+    fn to_wgpu_vertex_format(&self) -> VertexFormat;
+}
+
+impl VertexAttrDescriptorExt for VertexAttrDescriptor {
+    fn to_wgpu_vertex_format(&self) -> VertexFormat {
+        // wgpu has no single-component 8- or 16-bit formats, so those are fetched as two
+        // components, of which the shader only reads the first.
+        match (self.class, self.attr_type, self.size) {
+            (VertexAttrClass::Int, VertexAttrType::I8, 1) |
+            (VertexAttrClass::Int, VertexAttrType::I8, 2) => VertexFormat::Char2,
+            (VertexAttrClass::Int, VertexAttrType::I8, 4) => VertexFormat::Char4,
+            (VertexAttrClass::Int, VertexAttrType::U8, 1) |
+            (VertexAttrClass::Int, VertexAttrType::U8, 2) => VertexFormat::Uchar2,
+            (VertexAttrClass::Int, VertexAttrType::U8, 4) => VertexFormat::Uchar4,
+            (VertexAttrClass::FloatNorm, VertexAttrType::I8, 1) |
+            (VertexAttrClass::FloatNorm, VertexAttrType::I8, 2) => VertexFormat::Char2Norm,
+            (VertexAttrClass::FloatNorm, VertexAttrType::I8, 4) => VertexFormat::Char4Norm,
+            (VertexAttrClass::FloatNorm, VertexAttrType::U8, 1) |
+            (VertexAttrClass::FloatNorm, VertexAttrType::U8, 2) => VertexFormat::Uchar2Norm,
+            (VertexAttrClass::FloatNorm, VertexAttrType::U8, 4) => VertexFormat::Uchar4Norm,
+            (VertexAttrClass::Int, VertexAttrType::I16, 1) |
+            (VertexAttrClass::Int, VertexAttrType::I16, 2) => VertexFormat::Short2,
+            (VertexAttrClass::Int, VertexAttrType::I16, 4) => VertexFormat::Short4,
+            (VertexAttrClass::Int, VertexAttrType::U16, 1) |
+            (VertexAttrClass::Int, VertexAttrType::U16, 2) => VertexFormat::Ushort2,
+            (VertexAttrClass::Int, VertexAttrType::U16, 4) => VertexFormat::Ushort4,
+            (VertexAttrClass::FloatNorm, VertexAttrType::I16, 1) |
+            (VertexAttrClass::FloatNorm, VertexAttrType::I16, 2) => VertexFormat::Short2Norm,
+            (VertexAttrClass::FloatNorm, VertexAttrType::I16, 4) => VertexFormat::Short4Norm,
+            (VertexAttrClass::FloatNorm, VertexAttrType::U16, 1) |
+            (VertexAttrClass::FloatNorm, VertexAttrType::U16, 2) => VertexFormat::Ushort2Norm,
+            (VertexAttrClass::FloatNorm, VertexAttrType::U16, 4) => VertexFormat::Ushort4Norm,
+            (VertexAttrClass::Float, VertexAttrType::F32, 1) => VertexFormat::Float,
+            (VertexAttrClass::Float, VertexAttrType::F32, 2) => VertexFormat::Float2,
+            (VertexAttrClass::Float, VertexAttrType::F32, 3) => VertexFormat::Float3,
+            (VertexAttrClass::Float, VertexAttrType::F32, 4) => VertexFormat::Float4,
+            (attr_class, attr_type, attr_size) => {
+                panic!("Unsupported vertex class/type/size combination: {:?}/{:?}/{}!",
+                       attr_class,
+                       attr_type,
+                       attr_size)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TextureFormatExt, VertexAttrDescriptorExt, WgpuUniform, align_row};
+    use super::{pack_uniforms, write_f32s};
+    use crate::spirv::{UniformBlock, UniformBlockMember};
+    use pathfinder_gpu::{TextureFormat, UniformData, VertexAttrClass, VertexAttrDescriptor};
+    use pathfinder_gpu::VertexAttrType;
+    use pathfinder_simd::default::{F32x2, F32x4};
+    use wgpu::VertexFormat;
+
+    fn member(name: &str, offset: u32, matrix_stride: u32) -> UniformBlockMember {
+        UniformBlockMember { name: name.to_owned(), offset, matrix_stride }
+    }
+
+    fn uniform(name: &str) -> WgpuUniform {
+        WgpuUniform { name: name.to_owned() }
+    }
+
+    fn f32s(data: &[u8], offset: usize, count: usize) -> Vec<f32> {
+        data[offset..(offset + count * 4)].chunks(4).map(|bytes| {
+            f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        }).collect()
+    }
+
+    fn i32s(data: &[u8], offset: usize, count: usize) -> Vec<i32> {
+        data[offset..(offset + count * 4)].chunks(4).map(|bytes| {
+            i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        }).collect()
+    }
+
+    fn attr(class: VertexAttrClass, attr_type: VertexAttrType, size: usize)
+            -> VertexAttrDescriptor {
+        VertexAttrDescriptor {
+            size,
+            class,
+            attr_type,
+            stride: 0,
+            offset: 0,
+            divisor: 0,
+            buffer_index: 0,
+        }
+    }
+
+    #[test]
+    fn test_uniforms_are_packed_at_their_reflected_offsets() {
+        let uniform_block = UniformBlock {
+            binding: 0,
+            size: 208,
+            members: vec![
+                member("uTransform", 0, 16),
+                member("uColorMatrix", 64, 32),
+                member("uTileSize", 128, 16),
+                member("uPathIndex", 136, 16),
+                member("uMaskSize", 144, 16),
+                member("uFillColor", 160, 16),
+                member("uOpacity", 176, 16),
+                member("uTextureSize", 192, 16),
+            ],
+        };
+        let (transform, color_matrix) = (uniform("uTransform"), uniform("uColorMatrix"));
+        let (tile_size, path_index) = (uniform("uTileSize"), uniform("uPathIndex"));
+        let (mask_size, fill_color) = (uniform("uMaskSize"), uniform("uFillColor"));
+        let (opacity, texture) = (uniform("uOpacity"), uniform("uTexture"));
+        let data = pack_uniforms(&uniform_block, &[
+            (&transform, UniformData::Mat4([F32x4::new(1.0, 2.0, 3.0, 4.0),
+                                            F32x4::new(5.0, 6.0, 7.0, 8.0),
+                                            F32x4::new(9.0, 10.0, 11.0, 12.0),
+                                            F32x4::new(13.0, 14.0, 15.0, 16.0)])),
+            (&color_matrix, UniformData::Mat2(F32x4::new(0.5, 1.5, 2.5, 3.5))),
+            (&tile_size, UniformData::Vec2(F32x2::new(16.0, 4.0))),
+            (&path_index, UniformData::Int(-7)),
+            (&mask_size, UniformData::IVec3([1, 2, 3])),
+            (&fill_color, UniformData::Vec4(F32x4::new(0.25, 0.5, 0.75, 1.0))),
+            (&opacity, UniformData::Float(0.125)),
+            // Not in the block: skipped, like texture units.
+            (&texture, UniformData::Vec3([9.0, 9.0, 9.0])),
+        ]);
+
+        assert_eq!(data.len(), 208);
+        assert_eq!(f32s(&data, 0, 16), (1..=16).map(|value| value as f32).collect::<Vec<_>>());
+        // Each `mat2` column starts a new matrix stride.
+        assert_eq!(f32s(&data, 64, 2), vec![0.5, 1.5]);
+        assert_eq!(f32s(&data, 72, 6), vec![0.0; 6]);
+        assert_eq!(f32s(&data, 96, 2), vec![2.5, 3.5]);
+        assert_eq!(f32s(&data, 128, 2), vec![16.0, 4.0]);
+        assert_eq!(i32s(&data, 136, 1), vec![-7]);
+        assert_eq!(i32s(&data, 144, 3), vec![1, 2, 3]);
+        assert_eq!(f32s(&data, 160, 4), vec![0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(f32s(&data, 176, 1), vec![0.125]);
+        assert_eq!(f32s(&data, 192, 4), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn test_uniform_writes_are_native_endian() {
+        let mut data = vec![0; 12];
+        write_f32s(&mut data, 4, &[1.0, -2.0]);
+        assert_eq!(&data[..4], &[0; 4]);
+        assert_eq!(&data[4..8], &1.0f32.to_ne_bytes());
+        assert_eq!(&data[8..], &(-2.0f32).to_ne_bytes());
+    }
+
+    #[test]
+    fn test_rows_are_aligned_for_buffer_copies() {
+        assert_eq!(align_row(0), 0);
+        assert_eq!(align_row(1), 256);
+        assert_eq!(align_row(256), 256);
+        assert_eq!(align_row(257), 512);
+        assert_eq!(align_row(100 * 4), 512);
+    }
+
+    #[test]
+    fn test_texture_formats() {
+        assert_eq!(TextureFormat::R8.to_wgpu_format(), wgpu::TextureFormat::R8Unorm);
+        assert_eq!(TextureFormat::R16F.to_wgpu_format(), wgpu::TextureFormat::R16Float);
+        assert_eq!(TextureFormat::RGBA8.to_wgpu_format(), wgpu::TextureFormat::Rgba8Unorm);
+        assert_eq!(TextureFormat::RGBA16F.to_wgpu_format(), wgpu::TextureFormat::Rgba16Float);
+        assert_eq!(TextureFormat::RGBA32F.to_wgpu_format(), wgpu::TextureFormat::Rgba32Float);
+    }
+
+    #[test]
+    fn test_vertex_formats() {
+        let cases = [
+            (VertexAttrClass::Float, VertexAttrType::F32, 1, VertexFormat::Float),
+            (VertexAttrClass::Float, VertexAttrType::F32, 2, VertexFormat::Float2),
+            (VertexAttrClass::Float, VertexAttrType::F32, 4, VertexFormat::Float4),
+            (VertexAttrClass::Int, VertexAttrType::I16, 2, VertexFormat::Short2),
+            (VertexAttrClass::Int, VertexAttrType::U16, 4, VertexFormat::Ushort4),
+            (VertexAttrClass::Int, VertexAttrType::U8, 4, VertexFormat::Uchar4),
+            (VertexAttrClass::FloatNorm, VertexAttrType::U8, 4, VertexFormat::Uchar4Norm),
+            (VertexAttrClass::FloatNorm, VertexAttrType::I16, 2, VertexFormat::Short2Norm),
+            // Single 8- and 16-bit components are fetched as pairs.
+            (VertexAttrClass::Int, VertexAttrType::I8, 1, VertexFormat::Char2),
+            (VertexAttrClass::Int, VertexAttrType::U16, 1, VertexFormat::Ushort2),
+            (VertexAttrClass::FloatNorm, VertexAttrType::U8, 1, VertexFormat::Uchar2Norm),
+        ];
+        for &(class, attr_type, size, format) in &cases {
+            assert_eq!(attr(class, attr_type, size).to_wgpu_vertex_format(), format);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported vertex class/type/size combination")]
+    fn test_unsupported_vertex_formats_panic() {
+        attr(VertexAttrClass::Float, VertexAttrType::U8, 3).to_wgpu_vertex_format();
+    }
+}
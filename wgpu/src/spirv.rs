@@ -0,0 +1,393 @@
+// pathfinder/wgpu/src/spirv.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Just enough SPIR-V reflection to bind Pathfinder's shaders.
+//!
+//! The shaders are written against GL-style loose uniforms, and the build packs those into a
+//! single uniform block per stage and splits each `sampler2D` into a texture and a sampler (see
+//! `shaders/Makefile`). This module recovers the layout of that block, the bindings of the
+//! textures and samplers, the bindings of any storage buffers, and the locations of the vertex
+//! inputs, so that the device can still look everything up by name.
+
+use std::collections::{HashMap, HashSet};
+
+const SPIRV_MAGIC: u32 = 0x07230203;
+const HEADER_LENGTH: usize = 5;
+
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_CLASS_INPUT: u32 = 1;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+// The largest uniform Pathfinder uses is a `mat4`.
+const MAX_UNIFORM_SIZE: u32 = 64;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ShaderReflection {
+    pub(crate) uniform_block: Option<UniformBlock>,
+    pub(crate) images: Vec<ResourceBinding>,
+    pub(crate) samplers: Vec<ResourceBinding>,
+    pub(crate) storage_buffers: Vec<ResourceBinding>,
+    pub(crate) inputs: Vec<(String, u32)>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct UniformBlock {
+    pub(crate) binding: u32,
+    pub(crate) size: u32,
+    pub(crate) members: Vec<UniformBlockMember>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct UniformBlockMember {
+    pub(crate) name: String,
+    pub(crate) offset: u32,
+    pub(crate) matrix_stride: u32,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ResourceBinding {
+    pub(crate) name: String,
+    pub(crate) set: u32,
+    pub(crate) binding: u32,
+}
+
+impl ShaderReflection {
+    pub(crate) fn from_words(words: &[u32]) -> ShaderReflection {
+        assert!(words.len() >= HEADER_LENGTH && words[0] == SPIRV_MAGIC, "Not a SPIR-V module!");
+
+        let mut names = HashMap::new();
+        let mut member_names = HashMap::new();
+        let mut decorations: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut member_decorations: HashMap<(u32, u32, u32), u32> = HashMap::new();
+        let mut built_ins = HashSet::new();
+        let mut buffer_blocks = HashSet::new();
+        let mut image_types = HashSet::new();
+        let mut sampler_types = HashSet::new();
+        let mut struct_types = HashMap::new();
+        let mut pointer_types = HashMap::new();
+        let mut variables = vec![];
+
+        let mut index = HEADER_LENGTH;
+        while index < words.len() {
+            let (word_count, opcode) = ((words[index] >> 16) as usize, words[index] & 0xffff);
+            assert!(word_count > 0 && index + word_count <= words.len(), "Malformed SPIR-V!");
+            let operands = &words[(index + 1)..(index + word_count)];
+            match opcode {
+                OP_NAME => {
+                    names.insert(operands[0], decode_string(&operands[1..]));
+                }
+                OP_MEMBER_NAME => {
+                    member_names.insert((operands[0], operands[1]), decode_string(&operands[2..]));
+                }
+                OP_TYPE_IMAGE => {
+                    image_types.insert(operands[0]);
+                }
+                OP_TYPE_SAMPLER => {
+                    sampler_types.insert(operands[0]);
+                }
+                OP_TYPE_STRUCT => {
+                    struct_types.insert(operands[0], operands.len() as u32 - 1);
+                }
+                OP_TYPE_POINTER => {
+                    pointer_types.insert(operands[0], operands[2]);
+                }
+                OP_VARIABLE => variables.push((operands[0], operands[1], operands[2])),
+                OP_DECORATE if operands[1] == DECORATION_BUILT_IN => {
+                    built_ins.insert(operands[0]);
+                }
+                OP_DECORATE if operands[1] == DECORATION_BUFFER_BLOCK => {
+                    buffer_blocks.insert(operands[0]);
+                }
+                OP_DECORATE if operands.len() >= 3 => {
+                    decorations.insert((operands[0], operands[1]), operands[2]);
+                }
+                OP_MEMBER_DECORATE if operands.len() >= 4 => {
+                    member_decorations.insert((operands[0], operands[1], operands[2]),
+                                              operands[3]);
+                }
+                _ => {}
+            }
+            index += word_count;
+        }
+
+        let mut reflection = ShaderReflection::default();
+        for (pointer_type, id, storage_class) in variables {
+            let pointee_type = match pointer_types.get(&pointer_type) {
+                None => continue,
+                Some(&pointee_type) => pointee_type,
+            };
+            let name = names.get(&id).cloned().unwrap_or_default();
+            let binding = decorations.get(&(id, DECORATION_BINDING)).cloned().unwrap_or(0);
+            let set = decorations.get(&(id, DECORATION_DESCRIPTOR_SET)).cloned().unwrap_or(0);
+
+            match storage_class {
+                // Before SPIR-V 1.3, storage buffers are uniform blocks decorated `BufferBlock`.
+                STORAGE_CLASS_UNIFORM if buffer_blocks.contains(&pointee_type) => {
+                    reflection.storage_buffers.push(ResourceBinding { name, set, binding });
+                }
+                STORAGE_CLASS_STORAGE_BUFFER => {
+                    reflection.storage_buffers.push(ResourceBinding { name, set, binding });
+                }
+                STORAGE_CLASS_UNIFORM => {
+                    let member_count = match struct_types.get(&pointee_type) {
+                        None => continue,
+                        Some(&member_count) => member_count,
+                    };
+                    let mut members = vec![];
+                    for member_index in 0..member_count {
+                        let name = match member_names.get(&(pointee_type, member_index)) {
+                            None => continue,
+                            Some(name) => name.clone(),
+                        };
+                        let key = (pointee_type, member_index, DECORATION_OFFSET);
+                        let offset = member_decorations.get(&key).cloned().unwrap_or(0);
+                        let key = (pointee_type, member_index, DECORATION_MATRIX_STRIDE);
+                        let matrix_stride = member_decorations.get(&key).cloned().unwrap_or(16);
+                        members.push(UniformBlockMember { name, offset, matrix_stride });
+                    }
+
+                    // We don't track member types, so overestimate the size of the last member.
+                    let size = members.iter()
+                                      .map(|member| member.offset + MAX_UNIFORM_SIZE)
+                                      .max()
+                                      .unwrap_or(0);
+                    reflection.uniform_block = Some(UniformBlock { binding, size, members });
+                }
+                STORAGE_CLASS_UNIFORM_CONSTANT if image_types.contains(&pointee_type) => {
+                    reflection.images.push(ResourceBinding { name, set, binding });
+                }
+                STORAGE_CLASS_UNIFORM_CONSTANT if sampler_types.contains(&pointee_type) => {
+                    reflection.samplers.push(ResourceBinding { name, set, binding });
+                }
+                STORAGE_CLASS_INPUT if !built_ins.contains(&id) => {
+                    if let Some(&location) = decorations.get(&(id, DECORATION_LOCATION)) {
+                        reflection.inputs.push((name, location));
+                    }
+                }
+                _ => {}
+            }
+        }
+        reflection
+    }
+
+    pub(crate) fn input_location(&self, name: &str) -> Option<u32> {
+        self.inputs.iter().find(|(input_name, _)| input_name == name).map(|&(_, location)| {
+            location
+        })
+    }
+}
+
+impl UniformBlock {
+    pub(crate) fn member(&self, name: &str) -> Option<&UniformBlockMember> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+fn decode_string(words: &[u32]) -> String {
+    let mut bytes = vec![];
+    'outer: for word in words {
+        for &byte in &word.to_le_bytes() {
+            if byte == 0 {
+                break 'outer;
+            }
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OP_TYPE_FLOAT: u32 = 22;
+
+    // Assembles a module from instructions of the form `(opcode, operands)`.
+    fn module(instructions: &[(u32, Vec<u32>)]) -> Vec<u32> {
+        let mut words = vec![SPIRV_MAGIC, 0x00010000, 0, 100, 0];
+        for &(opcode, ref operands) in instructions {
+            words.push(((operands.len() as u32 + 1) << 16) | opcode);
+            words.extend_from_slice(operands);
+        }
+        words
+    }
+
+    // Encodes a literal string, nul-terminated and padded to a whole word.
+    fn string(string: &str) -> Vec<u32> {
+        let mut bytes = string.as_bytes().to_vec();
+        bytes.resize((bytes.len() + 4) & !3, 0);
+        bytes.chunks(4).map(|chunk| {
+            u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])
+        }).collect()
+    }
+
+    fn name(id: u32, name: &str) -> (u32, Vec<u32>) {
+        let mut operands = vec![id];
+        operands.extend(string(name));
+        (OP_NAME, operands)
+    }
+
+    fn member_name(id: u32, member: u32, name: &str) -> (u32, Vec<u32>) {
+        let mut operands = vec![id, member];
+        operands.extend(string(name));
+        (OP_MEMBER_NAME, operands)
+    }
+
+    fn binding(id: u32, set: u32, binding: u32) -> Vec<(u32, Vec<u32>)> {
+        vec![(OP_DECORATE, vec![id, DECORATION_DESCRIPTOR_SET, set]),
+             (OP_DECORATE, vec![id, DECORATION_BINDING, binding])]
+    }
+
+    fn reflect(instructions: Vec<(u32, Vec<u32>)>) -> ShaderReflection {
+        ShaderReflection::from_words(&module(&instructions))
+    }
+
+    fn bindings(resources: &[ResourceBinding]) -> Vec<(&str, u32, u32)> {
+        resources.iter().map(|resource| {
+            (&*resource.name, resource.set, resource.binding)
+        }).collect()
+    }
+
+    #[test]
+    fn test_uniform_block_layout() {
+        let mut instructions = vec![
+            (OP_TYPE_FLOAT, vec![1, 32]),
+            member_name(10, 0, "uTransform"),
+            member_name(10, 1, "uTileSize"),
+            member_name(10, 2, "uFramebufferSize"),
+            (OP_MEMBER_DECORATE, vec![10, 0, DECORATION_OFFSET, 0]),
+            (OP_MEMBER_DECORATE, vec![10, 0, DECORATION_MATRIX_STRIDE, 32]),
+            (OP_MEMBER_DECORATE, vec![10, 1, DECORATION_OFFSET, 128]),
+            (OP_MEMBER_DECORATE, vec![10, 2, DECORATION_OFFSET, 136]),
+            (OP_TYPE_STRUCT, vec![10, 1, 1, 1]),
+            (OP_TYPE_POINTER, vec![11, STORAGE_CLASS_UNIFORM, 10]),
+            (OP_VARIABLE, vec![11, 12, STORAGE_CLASS_UNIFORM]),
+        ];
+        instructions.extend(binding(12, 0, 5));
+        let reflection = reflect(instructions);
+
+        let block = reflection.uniform_block.expect("no uniform block");
+        assert_eq!(block.binding, 5);
+        // The last member is assumed to be as large as a `mat4`.
+        assert_eq!(block.size, 136 + MAX_UNIFORM_SIZE);
+        let members: Vec<_> = block.members.iter().map(|member| {
+            (&*member.name, member.offset, member.matrix_stride)
+        }).collect();
+        assert_eq!(members, vec![("uTransform", 0, 32),
+                                 ("uTileSize", 128, 16),
+                                 ("uFramebufferSize", 136, 16)]);
+        assert_eq!(block.member("uTileSize").map(|member| member.offset), Some(128));
+        assert!(block.member("uMissing").is_none());
+    }
+
+    #[test]
+    fn test_textures_samplers_and_storage_buffers() {
+        let mut instructions = vec![
+            name(22, "uColorTexture"),
+            name(32, "uColorTextureSampler"),
+            name(42, "bFills"),
+            name(46, "bTiles"),
+            (OP_TYPE_IMAGE, vec![20, 1, 1, 0, 0, 0, 1, 0]),
+            (OP_TYPE_POINTER, vec![21, STORAGE_CLASS_UNIFORM_CONSTANT, 20]),
+            (OP_VARIABLE, vec![21, 22, STORAGE_CLASS_UNIFORM_CONSTANT]),
+            (OP_TYPE_SAMPLER, vec![30]),
+            (OP_TYPE_POINTER, vec![31, STORAGE_CLASS_UNIFORM_CONSTANT, 30]),
+            (OP_VARIABLE, vec![31, 32, STORAGE_CLASS_UNIFORM_CONSTANT]),
+            // An old-style storage buffer: a uniform block decorated `BufferBlock`.
+            (OP_DECORATE, vec![40, DECORATION_BUFFER_BLOCK]),
+            (OP_TYPE_STRUCT, vec![40, 1]),
+            (OP_TYPE_POINTER, vec![41, STORAGE_CLASS_UNIFORM, 40]),
+            (OP_VARIABLE, vec![41, 42, STORAGE_CLASS_UNIFORM]),
+            // A SPIR-V 1.3 storage buffer.
+            (OP_TYPE_STRUCT, vec![44, 1]),
+            (OP_TYPE_POINTER, vec![45, STORAGE_CLASS_STORAGE_BUFFER, 44]),
+            (OP_VARIABLE, vec![45, 46, STORAGE_CLASS_STORAGE_BUFFER]),
+        ];
+        instructions.extend(binding(22, 1, 0));
+        instructions.extend(binding(32, 1, 1));
+        instructions.extend(binding(42, 0, 2));
+        instructions.extend(binding(46, 0, 3));
+        let reflection = reflect(instructions);
+
+        assert_eq!(bindings(&reflection.images), vec![("uColorTexture", 1, 0)]);
+        assert_eq!(bindings(&reflection.samplers), vec![("uColorTextureSampler", 1, 1)]);
+        assert_eq!(bindings(&reflection.storage_buffers), vec![("bFills", 0, 2), ("bTiles", 0, 3)]);
+        // Neither storage buffer is mistaken for the uniform block.
+        assert!(reflection.uniform_block.is_none());
+    }
+
+    #[test]
+    fn test_vertex_inputs_skip_built_ins() {
+        let reflection = reflect(vec![
+            name(52, "aPosition"),
+            name(53, "aTileIndex"),
+            name(54, "gl_VertexIndex"),
+            name(55, "aUnlocated"),
+            (OP_DECORATE, vec![52, DECORATION_LOCATION, 0]),
+            (OP_DECORATE, vec![53, DECORATION_LOCATION, 3]),
+            (OP_DECORATE, vec![54, DECORATION_BUILT_IN, 42]),
+            (OP_DECORATE, vec![54, DECORATION_LOCATION, 7]),
+            (OP_TYPE_FLOAT, vec![51, 32]),
+            (OP_TYPE_POINTER, vec![50, STORAGE_CLASS_INPUT, 51]),
+            (OP_VARIABLE, vec![50, 52, STORAGE_CLASS_INPUT]),
+            (OP_VARIABLE, vec![50, 53, STORAGE_CLASS_INPUT]),
+            (OP_VARIABLE, vec![50, 54, STORAGE_CLASS_INPUT]),
+            (OP_VARIABLE, vec![50, 55, STORAGE_CLASS_INPUT]),
+        ]);
+
+        assert_eq!(reflection.inputs, vec![("aPosition".to_owned(), 0),
+                                           ("aTileIndex".to_owned(), 3)]);
+        assert_eq!(reflection.input_location("aTileIndex"), Some(3));
+        assert_eq!(reflection.input_location("gl_VertexIndex"), None);
+        assert_eq!(reflection.input_location("aUnlocated"), None);
+    }
+
+    #[test]
+    fn test_strings_end_at_the_first_nul() {
+        assert_eq!(decode_string(&string("abc")), "abc");
+        assert_eq!(decode_string(&string("abcd")), "abcd");
+        assert_eq!(decode_string(&string("")), "");
+        // An unterminated string runs to the end of the operands.
+        assert_eq!(decode_string(&[u32::from_le_bytes(*b"wxyz")]), "wxyz");
+    }
+
+    #[test]
+    #[should_panic(expected = "Not a SPIR-V module!")]
+    fn test_rejects_other_data() {
+        ShaderReflection::from_words(&[0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Malformed SPIR-V!")]
+    fn test_rejects_truncated_instructions() {
+        let mut words = module(&[name(1, "aPosition")]);
+        words.pop();
+        ShaderReflection::from_words(&words);
+    }
+}